@@ -0,0 +1,116 @@
+//! Generation of the base configuration of a switch tagging many vlans on its trunks.
+//!
+//! Save a baseline before a change with `cargo bench -p backend -- --save-baseline before` and
+//! compare against it afterward with `cargo bench -p backend -- --baseline before`.
+use backend::{
+    config::GenerationProfile,
+    device::ros::{generate_for_model, render_script},
+    topology::{DeviceId, Topology, TopologyHolder, access::device::DeviceAccess},
+};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use serde_json::{Map, Value, json};
+use std::hint::black_box;
+use tokio::runtime::Runtime;
+
+const MODEL: &[u8] = b"CRS326-24G-2S+";
+const TRUNK_PORTS: u32 = 2;
+const ACCESS_PORTS: u32 = 24;
+/// the generated vlans are numbered from here, below the internal vlans of the bridge planes
+const FIRST_VLAN_ID: u32 = 100;
+
+/// switch with a bridge over all ports, the trunks tag every vlan and the access ports are
+/// spread over the vlans
+fn switch_topology(vlan_count: u32) -> Topology {
+    let all_vlans = (1..=vlan_count).collect::<Vec<_>>();
+    let mut interfaces = Map::new();
+    interfaces.insert("1".into(), json!({"name": "bridge", "device": 1}));
+    for trunk in 1..=TRUNK_PORTS {
+        interfaces.insert(
+            (1 + trunk).to_string(),
+            json!({
+                "name": format!("sfp-sfpplus{trunk}"),
+                "device": 1,
+                "external": {"SfpSfpPlus": trunk},
+                "bridge": 1,
+                "tagged_vlans": all_vlans,
+            }),
+        );
+    }
+    for port in 1..=ACCESS_PORTS {
+        interfaces.insert(
+            (1 + TRUNK_PORTS + port).to_string(),
+            json!({
+                "name": format!("ether{port}"),
+                "device": 1,
+                "external": {"Ethernet": port},
+                "bridge": 1,
+                "vlan": port % vlan_count + 1,
+            }),
+        );
+    }
+    let ports = (1..=1 + TRUNK_PORTS + ACCESS_PORTS)
+        .map(|id| json!({"Interface": id}))
+        .collect::<Vec<_>>();
+    let vlans = all_vlans
+        .iter()
+        .map(|id| {
+            let vlan_id = FIRST_VLAN_ID + id;
+            (
+                id.to_string(),
+                json!({
+                    "name": format!("vlan-{vlan_id}"),
+                    "vlan_id": vlan_id,
+                    "group": 1,
+                    "terminations": [],
+                    "wlans": [],
+                }),
+            )
+        })
+        .collect::<Map<_, _>>();
+    serde_json::from_value(json!({
+        "devices": {"1": {"name": "core-switch", "has_routeros": true, "ports": ports}},
+        "interfaces": Value::Object(interfaces),
+        "vlan_groups": {"1": {"vlans": all_vlans}},
+        "vlans": Value::Object(vlans),
+    }))
+    .expect("invalid topology")
+}
+
+fn switch(runtime: &Runtime, vlan_count: u32) -> DeviceAccess {
+    runtime
+        .block_on(TopologyHolder::offline(switch_topology(vlan_count)).devices_by_id(DeviceId(1)))
+        .expect("switch not found in topology")
+}
+
+fn generate_config(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .expect("cannot start runtime");
+    let profile = GenerationProfile::default();
+    let mut group = c.benchmark_group("generate_config");
+    for vlan_count in [50, 200, 500] {
+        let device = switch(&runtime, vlan_count);
+        group.bench_with_input(
+            BenchmarkId::new("mutations", vlan_count),
+            &device,
+            |b, device| {
+                b.iter(|| {
+                    generate_for_model(black_box(device), MODEL, &profile)
+                        .expect("generation failed")
+                })
+            },
+        );
+        let mutations = generate_for_model(&device, MODEL, &profile).expect("generation failed");
+        group.bench_with_input(
+            BenchmarkId::new("script", vlan_count),
+            &mutations,
+            |b, mutations| {
+                b.iter(|| render_script(black_box(mutations)).expect("rendering failed"))
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, generate_config);
+criterion_main!(benches);
@@ -1,4 +1,4 @@
-use backend::topology::fetch::build_topology;
+use backend::{config::CONFIG, topology::fetch::build_topology};
 use env_logger::{Env, TimestampPrecision};
 use std::sync::Arc;
 
@@ -8,7 +8,9 @@ async fn main() -> anyhow::Result<()> {
         .parse_env(Env::default().filter_or("LOG_LEVEL", "info"))
         .format_timestamp(Some(TimestampPrecision::Millis))
         .init();
-    let topology = Arc::new(build_topology().await?);
+    // the default instance is listed first
+    let (_, instance) = CONFIG.load().netbox_instances().remove(0);
+    let topology = Arc::new(build_topology(&instance).await?);
     for device in topology.list_devices() {
         if !device.has_routeros() {
             continue;
@@ -1,7 +1,22 @@
-use config::{Config, ConfigError, Environment, File};
+use crate::{context::AccessLevel, device::certificate::parse_routeros_time};
+use arc_swap::ArcSwap;
+pub use config::ConfigError;
+use config::{Config, Environment, File};
 use lazy_static::lazy_static;
 use serde::Deserialize;
-use std::{collections::HashMap, net::IpAddr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Debug, Formatter},
+    net::{IpAddr, Ipv4Addr},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use time::{OffsetDateTime, Time, UtcOffset};
+
+#[cfg(test)]
+mod test;
 
 #[derive(Deserialize)]
 pub struct Settings {
@@ -9,16 +24,666 @@ pub struct Settings {
     auth_issuer: String,
     auth_token_url: Option<String>,
     auth_url: Option<String>,
+    /// subjects allowed to use the provisioner
+    #[serde(default)]
     pub auth_users: Box<[Box<str>]>,
+    #[serde(default)]
+    pub auth_admins: Box<[Box<str>]>,
+    /// access granted by the claims of the token, e.g. the groups managed in the issuer
+    #[serde(default)]
+    pub auth_rules: Box<[AccessRule]>,
+    /// claim holding the slugs of the netbox tenants visible to the user
+    pub auth_tenant_claim: Option<Box<str>>,
+    /// slugs of the netbox tenants visible to a user, by subject
+    #[serde(default)]
+    pub auth_user_tenants: HashMap<Box<str>, Box<[Box<str>]>>,
 
     server_port: Option<u16>,
     server_mgmt_port: Option<u16>,
     server_bind_address: Option<IpAddr>,
+    /// bearer token required by `POST /reload` on the management port, the endpoint is
+    /// disabled without it
+    server_reload_token: Option<Box<str>>,
 
     pub netbox_url: String,
     pub netbox_token: String,
+    /// additional netbox instances, selectable by name in the api
+    #[serde(default)]
+    pub netbox_instances: HashMap<Box<str>, NetboxInstance>,
+    topology_snapshot_dir: Option<PathBuf>,
+    /// directory of the secrets sent to the devices, one file per secret
+    secrets_dir: Option<PathBuf>,
+    /// file the enabled port mirrors are persisted to, reverted after a restart
+    port_mirror_file: Option<PathBuf>,
+    /// write a journal entry on the netbox device after an apply, needs a token with write access
+    #[serde(default)]
+    pub netbox_journal: bool,
 
     pub mikrotik_credentials: HashMap<Box<str>, MikrotikCredentials>,
+    /// netwatch profiles, enabled on devices tagged with `netwatch-<profile>`
+    #[serde(default)]
+    pub netwatch_profiles: HashMap<Box<str>, NetwatchProfile>,
+    /// scripts deployed to every device, keyed by script name
+    #[serde(default)]
+    pub scripts: HashMap<Box<str>, ScriptTemplate>,
+    /// daily schedules of the poe ports and wlans tagged `schedule-<name>`
+    #[serde(default)]
+    pub schedules: HashMap<Box<str>, Schedule>,
+    /// options of the dhcp servers, selected by the netbox custom field `dhcp_options` of a prefix
+    #[serde(default)]
+    pub dhcp_option_sets: HashMap<Box<str>, DhcpOptionSet>,
+    /// access point names of the lte interfaces tagged `apn-<name>`
+    #[serde(default)]
+    pub apn_profiles: HashMap<Box<str>, ApnProfile>,
+    /// pppoe accounts by the netbox custom field `pppoe_credentials` of a device or by its name
+    #[serde(default)]
+    pub pppoe_credentials: HashMap<Box<str>, PppoeCredentials>,
+    /// named generation profiles, referenced by `role_profiles`
+    #[serde(default)]
+    pub generation_profiles: HashMap<Box<str>, GenerationProfile>,
+    /// generation profile of the devices by netbox role slug
+    #[serde(default)]
+    pub role_profiles: HashMap<Box<str>, Box<str>>,
+    /// resources never modified or deleted on any device
+    #[serde(default)]
+    pub protected_resources: Box<[ProtectedResource]>,
+    /// additionally protected resources by device name
+    #[serde(default)]
+    pub device_protected_resources: HashMap<Box<str>, Box<[ProtectedResource]>>,
+    /// webhooks posted to on provisioning events
+    #[serde(default)]
+    pub notifiers: Box<[NotifierConfig]>,
+    /// announced maintenance, the ongoing windows are shown on the public status page
+    #[serde(default)]
+    pub maintenance_windows: Box<[MaintenanceWindow]>,
+    notify_unreachable_after: Option<u32>,
+    backoff_after_failures: Option<u32>,
+    backoff_initial_delay_secs: Option<u64>,
+    backoff_max_delay_secs: Option<u64>,
+    device_facts_ttl_secs: Option<u64>,
+    /// periodic email summary of the provisioning status, not sent if not set
+    pub digest: Option<DigestConfig>,
+    /// certificates issued to the devices for api-ssl and CAPsMAN, not managed if not set
+    pub certificates: Option<CertificateConfig>,
+    /// RADIUS server of the wlan controllers, required by wlans with `wpa-enterprise`
+    pub radius: Option<RadiusConfig>,
+    /// captive portal on the interfaces and vlans tagged `captive-portal`, no hotspot is
+    /// generated if not set
+    pub hotspot: Option<HotspotConfig>,
+    drift_check_interval_secs: Option<u64>,
+
+    monitor_interval_secs: Option<u64>,
+    availability_interval_secs: Option<u64>,
+    ping_mode: Option<PingMode>,
+    graphql_persisted_queries_only: Option<bool>,
+    graphql_max_depth: Option<usize>,
+    graphql_max_complexity: Option<usize>,
+    /// introspection of the authenticated schema, enabled in debug builds by default
+    graphql_introspection: Option<bool>,
+    /// device connections a single request keeps open at once
+    graphql_device_connections: Option<usize>,
+    /// time budget of a query or mutation, slower fields are cancelled and reported as error
+    graphql_request_timeout_secs: Option<u64>,
+    rate_limit_burst: Option<u32>,
+    rate_limit_user_per_minute: Option<u32>,
+    rate_limit_device_per_minute: Option<u32>,
+}
+
+/// Limits applied to queries on the authenticated schema
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct QueryLimits {
+    pub max_depth: usize,
+    pub max_complexity: usize,
+    pub introspection: bool,
+    /// device connections of a request, further devices of e.g. a list wait for a free one
+    pub max_device_connections: usize,
+    pub request_timeout: Duration,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 16,
+            max_complexity: 5000,
+            introspection: true,
+            max_device_connections: 16,
+            request_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Grants access to users with one of the values in a claim of their token
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AccessRule {
+    /// name of the claim, nested claims are separated by dots, e.g. `realm_access.roles`
+    pub claim: Box<str>,
+    pub values: Box<[Box<str>]>,
+    #[serde(default)]
+    pub level: AccessLevel,
+}
+
+/// How devices are pinged from the provisioner
+#[derive(Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PingMode {
+    /// icmp ping with fallback to a tcp probe
+    #[default]
+    Auto,
+    /// icmp ping only, needs raw socket permissions
+    Icmp,
+    /// tcp connect to the api or ssh port
+    Tcp,
+}
+
+#[derive(Deserialize)]
+pub struct NetwatchProfile {
+    target: NetwatchTarget,
+    interval_secs: Option<u64>,
+    up_script: Option<Box<str>>,
+    down_script: Option<Box<str>>,
+}
+
+/// Host watched by a netwatch profile
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NetwatchTarget {
+    /// the router on the prefix of the primary address
+    Gateway,
+    /// the wlan controller of an access point
+    Controller,
+}
+
+impl NetwatchProfile {
+    pub fn target(&self) -> NetwatchTarget {
+        self.target
+    }
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.unwrap_or(10))
+    }
+    pub fn up_script(&self) -> Option<&str> {
+        self.up_script.as_deref()
+    }
+    pub fn down_script(&self) -> Option<&str> {
+        self.down_script.as_deref()
+    }
+}
+
+/// Managed script, `{{device}}` and `{{address}}` in the source are replaced
+/// by the name and the management address of the device
+#[derive(Deserialize)]
+pub struct ScriptTemplate {
+    source: Box<str>,
+    /// run the script by a scheduler with this interval
+    interval_secs: Option<u64>,
+    start_time: Option<Box<str>>,
+}
+
+impl ScriptTemplate {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval_secs.map(Duration::from_secs)
+    }
+    pub fn start_time(&self) -> Option<&str> {
+        self.start_time.as_deref()
+    }
+}
+
+/// Daily time window a poe port or wlan is switched on, the times like `07:00:00` are in the
+/// time zone of the device. The window ends the next day if `off` is before `on`.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(try_from = "ScheduleTimes")]
+pub struct Schedule {
+    on: Time,
+    off: Time,
+    utc_offset: UtcOffset,
+}
+
+/// schedule as written in the configuration
+#[derive(Deserialize)]
+struct ScheduleTimes {
+    on: Box<str>,
+    off: Box<str>,
+    /// offset of the time zone of the devices like `+01:00`, UTC if not set. The ports are set
+    /// to the state of the schedule when the configuration is applied.
+    utc_offset: Option<Box<str>>,
+}
+
+impl TryFrom<ScheduleTimes> for Schedule {
+    type Error = String;
+
+    fn try_from(times: ScheduleTimes) -> Result<Self, Self::Error> {
+        let on = parse_time(&times.on)?;
+        let off = parse_time(&times.off)?;
+        if on == off {
+            return Err(format!("Schedule switches on and off at {}", times.on));
+        }
+        let utc_offset = times
+            .utc_offset
+            .as_deref()
+            .map(parse_utc_offset)
+            .transpose()?
+            .unwrap_or(UtcOffset::UTC);
+        Ok(Self {
+            on,
+            off,
+            utc_offset,
+        })
+    }
+}
+
+impl Schedule {
+    pub fn on(&self) -> Time {
+        self.on
+    }
+    pub fn off(&self) -> Time {
+        self.off
+    }
+    /// the ports and wlans are switched on at the time of day
+    pub fn is_on_at(&self, time: Time) -> bool {
+        if self.on < self.off {
+            self.on <= time && time < self.off
+        } else {
+            time >= self.on || time < self.off
+        }
+    }
+    /// the ports and wlans are switched on now, in the time zone of the devices
+    pub fn is_on_now(&self) -> bool {
+        self.is_on_at(OffsetDateTime::now_utc().to_offset(self.utc_offset).time())
+    }
+}
+
+/// time of day like `07:00:00` or `07:00`
+fn parse_time(value: &str) -> Result<Time, String> {
+    let invalid = || format!("Invalid time {value}, expected like 07:00:00");
+    if !value.bytes().all(|b| b.is_ascii_digit() || b == b':') {
+        return Err(invalid());
+    }
+    let parts = value
+        .split(':')
+        .map(|part| part.parse::<u8>().map_err(|_| invalid()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (hour, minute, second) = match parts.as_slice() {
+        [hour, minute] => (*hour, *minute, 0),
+        [hour, minute, second] => (*hour, *minute, *second),
+        _ => return Err(invalid()),
+    };
+    Time::from_hms(hour, minute, second).map_err(|_| invalid())
+}
+
+/// offset like `+01:00` or `-05:30`
+fn parse_utc_offset(value: &str) -> Result<UtcOffset, String> {
+    let invalid = || format!("Invalid utc offset {value}, expected like +01:00");
+    let (sign, offset) = match value.split_at_checked(1) {
+        Some(("+", offset)) => (1, offset),
+        Some(("-", offset)) => (-1, offset),
+        _ => return Err(invalid()),
+    };
+    if !offset.bytes().all(|b| b.is_ascii_digit() || b == b':') {
+        return Err(invalid());
+    }
+    let (hours, minutes) = offset.split_once(':').unwrap_or((offset, "0"));
+    let hours = hours.parse::<i8>().map_err(|_| invalid())?;
+    let minutes = minutes.parse::<i8>().map_err(|_| invalid())?;
+    UtcOffset::from_hms(sign * hours, sign * minutes, 0).map_err(|_| invalid())
+}
+
+/// Options handed to the clients of a dhcp network
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct DhcpOptionSet {
+    /// dns domain of the clients
+    pub domain: Option<Box<str>>,
+    /// tftp server the clients boot or load their provisioning from
+    pub tftp_server: Option<Ipv4Addr>,
+    pub boot_file: Option<Box<str>>,
+    pub ntp_servers: Box<[Ipv4Addr]>,
+    /// additional options by name, e.g. the vlan of voip phones
+    pub options: BTreeMap<Box<str>, DhcpOption>,
+}
+
+/// Access point name of the mobile network an lte interface dials into
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ApnProfile {
+    pub apn: Box<str>,
+    pub user: Option<Box<str>>,
+    /// name of the password in the secrets directory
+    password_name: Option<Box<str>>,
+}
+
+impl ApnProfile {
+    pub fn password_name(&self) -> Option<&str> {
+        self.password_name.as_deref()
+    }
+}
+
+/// Account of a cpe at the internet provider
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PppoeCredentials {
+    pub user: Box<str>,
+    /// name of the password in the secrets directory
+    password_name: Box<str>,
+}
+
+impl PppoeCredentials {
+    pub fn password_name(&self) -> &str {
+        &self.password_name
+    }
+}
+
+/// Raw dhcp option
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DhcpOption {
+    pub code: u8,
+    /// value in RouterOS syntax like `'sip.example.com'` or `0x0a`
+    pub value: Box<str>,
+}
+
+/// Announced maintenance, the times like `2026-10-20 22:00:00` are in UTC
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    pub title: Box<str>,
+    pub start: Box<str>,
+    pub end: Box<str>,
+}
+
+impl MaintenanceWindow {
+    /// start in seconds since the unix epoch, none if the time is invalid
+    pub fn start(&self) -> Option<u64> {
+        parse_routeros_time(&self.start)
+    }
+    /// end in seconds since the unix epoch, none if the time is invalid
+    pub fn end(&self) -> Option<u64> {
+        parse_routeros_time(&self.end)
+    }
+    /// the window contains the given time in seconds since the unix epoch, windows with an
+    /// invalid time are never ongoing
+    pub fn is_ongoing(&self, now: u64) -> bool {
+        matches!((self.start(), self.end()), (Some(start), Some(end)) if start <= now && now < end)
+    }
+}
+
+/// Name of the instance given by `netbox_url` and `netbox_token`
+pub const DEFAULT_NETBOX_INSTANCE: &str = "default";
+
+/// Endpoint of a netbox instance
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NetboxInstance {
+    pub url: String,
+    pub token: String,
+}
+
+/// Parts of the configuration generated for a device, everything except the pppoe client is
+/// generated by default
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct GenerationProfile {
+    pub dhcp_server: bool,
+    pub dhcp_client: bool,
+    /// central dhcp servers of the interfaces tagged `dhcp-relay`, used if the prefix names none
+    /// by the netbox custom field `dhcp_relay`
+    pub dhcp_relay_servers: Box<[Ipv4Addr]>,
+    /// dial in by pppoe on the interfaces tagged `pppoe`, meant for the profile of the cpe role
+    pub pppoe_client: bool,
+    /// devices of the role must not let clients open ports or join multicast upstream, upnp and
+    /// igmp proxy enabled in netbox are left out with a warning
+    pub security_sensitive: bool,
+    pub ospf: bool,
+    pub vrrp: bool,
+    pub netwatch: bool,
+    /// dhcp lease time on guest networks, shorter than the default for the changing clients
+    pub guest_lease_time_secs: u64,
+    /// generated interface names are shortened to this length, colliding names are numbered
+    pub interface_name_max_length: usize,
+    /// first vlan id assigned to bridge planes without a netbox vlan
+    pub internal_vlan_start: u16,
+    /// last vlan id assigned to bridge planes without a netbox vlan
+    pub internal_vlan_end: u16,
+}
+
+impl GenerationProfile {
+    pub fn guest_lease_time(&self) -> Duration {
+        Duration::from_secs(self.guest_lease_time_secs)
+    }
+    /// vlan ids for the internal planes, limited to the ids valid in 802.1Q
+    pub fn internal_vlans(&self) -> RangeInclusive<u16> {
+        self.internal_vlan_start.max(2)..=self.internal_vlan_end.min(4094)
+    }
+}
+
+impl Default for GenerationProfile {
+    fn default() -> Self {
+        Self {
+            dhcp_server: true,
+            dhcp_client: true,
+            dhcp_relay_servers: Box::default(),
+            pppoe_client: false,
+            security_sensitive: false,
+            ospf: true,
+            vrrp: true,
+            netwatch: true,
+            guest_lease_time_secs: 60 * 60,
+            interface_name_max_length: 64,
+            internal_vlan_start: 3900,
+            internal_vlan_end: 3999,
+        }
+    }
+}
+
+/// Login page and limits of the captive portals
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct HotspotConfig {
+    /// dns name of the login page, the address of the router is used if not set
+    pub dns_name: Option<Box<str>>,
+    /// directory of the login pages on the device
+    pub html_directory: Option<Box<str>>,
+    /// authenticate the users by the RADIUS server of the device
+    pub use_radius: bool,
+    /// allow a time limited login without account
+    pub trial: bool,
+    session_timeout_secs: Option<u64>,
+    idle_timeout_secs: Option<u64>,
+    /// limit of every user like `2M/10M`, upload and download
+    pub rate_limit: Option<Box<str>>,
+    /// hosts reachable without login like `*.example.com`
+    pub walled_garden: Box<[Box<str>]>,
+}
+
+impl HotspotConfig {
+    pub fn session_timeout(&self) -> Option<Duration> {
+        self.session_timeout_secs.map(Duration::from_secs)
+    }
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout_secs.map(Duration::from_secs)
+    }
+}
+
+/// Resource on the device the provisioner must never modify or delete
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ProtectedResource {
+    /// resource path like `ip/address`, the paths below are protected as well
+    pub path: Box<str>,
+    /// value identifying the entry like the address or the user name, the whole path is
+    /// protected if not set
+    pub key: Option<Box<str>>,
+}
+
+impl ProtectedResource {
+    pub fn describe(&self) -> String {
+        match &self.key {
+            Some(key) => format!("{} {key}", self.path),
+            None => self.path.to_string(),
+        }
+    }
+}
+
+/// Event a notification is posted for
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotificationEvent {
+    ApplySucceeded,
+    ApplyFailed,
+    /// all steps were applied, but the device did not take them as generated or could not be
+    /// verified afterward
+    ApplyNotConverged,
+    /// the configuration of a monitored device differs from the generated one
+    DriftDetected,
+    /// a monitored device could not be accessed several times in a row
+    DeviceUnreachable,
+}
+
+/// Format of the posted message
+#[derive(Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifierKind {
+    Slack,
+    Mattermost,
+    /// json object with the event, instance, device, message and the rendered text
+    #[default]
+    Webhook,
+}
+
+/// Webhook receiving notifications, `{{event}}`, `{{instance}}`, `{{device}}` and `{{message}}`
+/// in the templates are replaced by the values of the event
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct NotifierConfig {
+    /// shown in the logs, the url carries the secret of the webhook and is never logged
+    pub name: Option<Box<str>>,
+    #[serde(default)]
+    pub kind: NotifierKind,
+    pub url: String,
+    /// events posted to this webhook, all events if not set
+    pub events: Option<Box<[NotificationEvent]>>,
+    /// message templates by event, replacing the default message
+    #[serde(default)]
+    pub templates: HashMap<NotificationEvent, Box<str>>,
+}
+
+impl NotificationEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            NotificationEvent::ApplySucceeded => "apply-succeeded",
+            NotificationEvent::ApplyFailed => "apply-failed",
+            NotificationEvent::ApplyNotConverged => "apply-not-converged",
+            NotificationEvent::DriftDetected => "drift-detected",
+            NotificationEvent::DeviceUnreachable => "device-unreachable",
+        }
+    }
+}
+
+impl NotifierConfig {
+    /// the configured name, the kind of the webhook if not set
+    pub fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.to_string(),
+            None => format!("{:?} webhook", self.kind),
+        }
+    }
+    pub fn is_enabled(&self, event: NotificationEvent) -> bool {
+        self.events
+            .as_ref()
+            .map(|events| events.contains(&event))
+            .unwrap_or(true)
+    }
+}
+
+/// Encryption of the smtp connection
+#[derive(Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// tls from the start, usually on port 465
+    Tls,
+    /// upgraded plain connection, usually on port 587
+    #[default]
+    Starttls,
+    /// unencrypted, only for a relay on the same host or network
+    None,
+}
+
+/// Email summary of drifted devices, failed applies and new devices
+#[derive(Deserialize, Clone, PartialEq, Eq)]
+pub struct DigestConfig {
+    pub smtp_host: Box<str>,
+    pub smtp_port: Option<u16>,
+    #[serde(default)]
+    pub smtp_security: SmtpSecurity,
+    pub smtp_user: Option<Box<str>>,
+    pub smtp_password: Option<Box<str>>,
+    pub from: Box<str>,
+    pub to: Box<[Box<str>]>,
+    interval_secs: Option<u64>,
+}
+
+/// the smtp password is not logged
+impl Debug for DigestConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DigestConfig")
+            .field("smtp_host", &self.smtp_host)
+            .field("smtp_port", &self.smtp_port)
+            .field("smtp_security", &self.smtp_security)
+            .field("smtp_user", &self.smtp_user)
+            .field("smtp_password", &self.smtp_password.as_ref().map(|_| "***"))
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .field("interval_secs", &self.interval_secs)
+            .finish()
+    }
+}
+
+impl DigestConfig {
+    /// time between two digests, daily by default
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.unwrap_or(24 * 60 * 60))
+    }
+}
+
+/// Local CA and validity of the certificates issued to the devices
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CertificateConfig {
+    /// PEM file of the CA imported to the devices, every device creates its own CA if not set
+    ca_certificate_file: Option<PathBuf>,
+    /// unencrypted PEM file of the private key of the CA, it stays on the server and signs the
+    /// certificate requests of the devices
+    ca_key_file: Option<PathBuf>,
+    days_valid: Option<u32>,
+    renew_before_days: Option<u32>,
+}
+
+impl CertificateConfig {
+    /// certificate and key of the CA signing on the server, both have to be configured
+    pub fn ca_files(&self) -> Option<(&Path, &Path)> {
+        Some((
+            self.ca_certificate_file.as_deref()?,
+            self.ca_key_file.as_deref()?,
+        ))
+    }
+    /// validity of the issued certificates, a year by default
+    pub fn days_valid(&self) -> u32 {
+        self.days_valid.unwrap_or(365)
+    }
+    /// days before the expiry a certificate is due for renewal
+    pub fn renew_before_days(&self) -> u32 {
+        self.renew_before_days.unwrap_or(30)
+    }
+}
+
+/// RADIUS server authenticating the wlan clients
+#[derive(Deserialize, Clone)]
+pub struct RadiusConfig {
+    pub address: IpAddr,
+    /// name of the shared secret in the secrets directory
+    secret_name: Box<str>,
+    /// send accounting records for the wlan clients
+    #[serde(default)]
+    pub accounting: bool,
+}
+
+impl RadiusConfig {
+    pub fn secret_name(&self) -> &str {
+        &self.secret_name
+    }
 }
 
 #[derive(Deserialize)]
@@ -63,10 +728,152 @@ impl Settings {
         self.server_mgmt_port
             .unwrap_or_else(|| self.server_port() + 1000)
     }
+    /// whether the `Authorization` header of a reload request carries the reload token,
+    /// compared in constant time
+    pub fn reload_authorized(&self, authorization: Option<&str>) -> bool {
+        let (Some(expected), Some(given)) = (
+            self.server_reload_token.as_deref(),
+            authorization.and_then(|header| header.strip_prefix("Bearer ")),
+        ) else {
+            return false;
+        };
+        expected.len() == given.len()
+            && expected
+                .bytes()
+                .zip(given.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
     pub fn server_bind_address(&self) -> IpAddr {
         self.server_bind_address
             .unwrap_or_else(|| IpAddr::from([0u8; 16]))
     }
+    /// polling interval of the interface counters, the collector is disabled if not set
+    pub fn monitor_interval(&self) -> Option<Duration> {
+        self.monitor_interval_secs.map(Duration::from_secs)
+    }
+    /// interval pinging all devices for the availability history and gauges, disabled if not set
+    pub fn availability_interval(&self) -> Option<Duration> {
+        self.availability_interval_secs.map(Duration::from_secs)
+    }
+    /// consecutive failed accesses of a monitored device until it is reported unreachable
+    pub fn notify_unreachable_after(&self) -> u32 {
+        self.notify_unreachable_after.unwrap_or(3).max(1)
+    }
+    /// consecutive failed connections until fleet operations back off from a device
+    pub fn backoff_after_failures(&self) -> u32 {
+        self.backoff_after_failures.unwrap_or(3).max(1)
+    }
+    /// first delay of the backoff, doubled with every further failure
+    pub fn backoff_initial_delay(&self) -> Duration {
+        Duration::from_secs(self.backoff_initial_delay_secs.unwrap_or(60))
+    }
+    pub fn backoff_max_delay(&self) -> Duration {
+        Duration::from_secs(self.backoff_max_delay_secs.unwrap_or(3600))
+    }
+    /// time the routerboard facts read from a device are cached
+    pub fn device_facts_ttl(&self) -> Duration {
+        Duration::from_secs(self.device_facts_ttl_secs.unwrap_or(600))
+    }
+    /// interval comparing the monitored devices with the generated configuration, the check
+    /// is disabled if not set
+    pub fn drift_check_interval(&self) -> Option<Duration> {
+        self.drift_check_interval_secs.map(Duration::from_secs)
+    }
+    pub fn ping_mode(&self) -> PingMode {
+        self.ping_mode.unwrap_or_default()
+    }
+    /// reject queries not sent as persisted query id on the authenticated endpoint
+    pub fn graphql_persisted_queries_only(&self) -> bool {
+        self.graphql_persisted_queries_only.unwrap_or(false)
+    }
+    /// number of device accesses allowed at once
+    pub fn rate_limit_burst(&self) -> u32 {
+        self.rate_limit_burst.unwrap_or(20)
+    }
+    /// refill interval of the token bucket of a user
+    pub fn rate_limit_user_interval(&self) -> Duration {
+        per_minute(self.rate_limit_user_per_minute.unwrap_or(60))
+    }
+    /// refill interval of the token bucket of a device
+    pub fn rate_limit_device_interval(&self) -> Duration {
+        per_minute(self.rate_limit_device_per_minute.unwrap_or(30))
+    }
+    /// generation profile mapped to the role, the default profile if there is none
+    pub fn generation_profile(&self, role: Option<&str>) -> GenerationProfile {
+        role.and_then(|role| self.role_profiles.get(role))
+            .and_then(|profile| self.generation_profiles.get(profile))
+            .cloned()
+            .unwrap_or_default()
+    }
+    /// global and device specific protected resources
+    pub fn protected_resources(&self, device: &str) -> Vec<ProtectedResource> {
+        self.protected_resources
+            .iter()
+            .chain(
+                self.device_protected_resources
+                    .get(device)
+                    .into_iter()
+                    .flatten(),
+            )
+            .cloned()
+            .collect()
+    }
+    /// all configured netbox instances, the default instance first
+    pub fn netbox_instances(&self) -> Vec<(&str, NetboxInstance)> {
+        let mut instances = vec![(
+            DEFAULT_NETBOX_INSTANCE,
+            NetboxInstance {
+                url: self.netbox_url.clone(),
+                token: self.netbox_token.clone(),
+            },
+        )];
+        let mut additional = self
+            .netbox_instances
+            .iter()
+            .filter(|(name, _)| name.as_ref() != DEFAULT_NETBOX_INSTANCE)
+            .map(|(name, instance)| (name.as_ref(), instance.clone()))
+            .collect::<Vec<_>>();
+        additional.sort_by_key(|(name, _)| *name);
+        instances.extend(additional);
+        instances
+    }
+    /// directory the last fetched topologies are persisted to, no snapshots if not set
+    pub fn topology_snapshot_dir(&self) -> Option<&Path> {
+        self.topology_snapshot_dir.as_deref()
+    }
+    /// directory of the secrets sent to the devices, `/run/secrets` by default
+    pub fn secrets_dir(&self) -> PathBuf {
+        self.secrets_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("/run/secrets"))
+    }
+    /// file of the enabled port mirrors, they are lost on a restart if not set
+    pub fn port_mirror_file(&self) -> Option<&Path> {
+        self.port_mirror_file.as_deref()
+    }
+    pub fn graphql_limits(&self) -> QueryLimits {
+        let default = QueryLimits::default();
+        QueryLimits {
+            max_depth: self.graphql_max_depth.unwrap_or(default.max_depth),
+            max_complexity: self
+                .graphql_max_complexity
+                .unwrap_or(default.max_complexity),
+            introspection: self.graphql_introspection.unwrap_or(cfg!(debug_assertions)),
+            max_device_connections: self
+                .graphql_device_connections
+                .unwrap_or(default.max_device_connections)
+                .max(1),
+            request_timeout: self
+                .graphql_request_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.request_timeout),
+        }
+    }
+}
+
+fn per_minute(count: u32) -> Duration {
+    Duration::from_secs(60) / count.max(1)
 }
 
 fn create_settings() -> Result<Settings, ConfigError> {
@@ -78,6 +885,45 @@ fn create_settings() -> Result<Settings, ConfigError> {
     Ok(settings)
 }
 
+/// Current settings, replaced by [`ConfigHolder::reload`].
+///
+/// Values read on every use like credentials, users, tenants and the oidc client settings
+/// take effect right away, the connection backoff is updated by the reload. The listen ports,
+/// the intervals of the background tasks and the rate limits need a restart, as does a new
+/// issuer: the tokens are validated against the issuer the server was started with.
+pub struct ConfigHolder {
+    current: ArcSwap<Settings>,
+}
+
+impl ConfigHolder {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(settings),
+        }
+    }
+    /// the current settings, they stay valid while in use if they are replaced meanwhile
+    pub fn load(&self) -> Arc<Settings> {
+        self.current.load_full()
+    }
+    /// reads `config.yaml` again, the current settings are kept if it is invalid
+    pub fn reload(&self) -> Result<(), ConfigError> {
+        self.replace(create_settings()?)
+    }
+    /// replaces the settings unless they need a restart
+    pub fn replace(&self, settings: Settings) -> Result<(), ConfigError> {
+        let current = self.load();
+        if settings.auth_issuer != current.auth_issuer {
+            return Err(ConfigError::Message(format!(
+                "A new issuer {} needs a restart",
+                settings.auth_issuer
+            )));
+        }
+        self.current.store(Arc::new(settings));
+        Ok(())
+    }
+}
+
 lazy_static! {
-    pub static ref CONFIG: Settings = create_settings().expect("Cannot load config.yaml");
+    pub static ref CONFIG: ConfigHolder =
+        ConfigHolder::new(create_settings().expect("Cannot load config.yaml"));
 }
@@ -0,0 +1,77 @@
+use crate::config::{ConfigHolder, Schedule, Settings};
+use time::Time;
+
+fn settings(yaml: &str) -> Settings {
+    serde_yaml::from_str(&format!(
+        "auth_client_id: provisioner\nnetbox_token: token\nmikrotik_credentials: {{}}\n{yaml}"
+    ))
+    .expect("invalid settings")
+}
+
+#[test]
+fn test_replace_settings() {
+    let holder = ConfigHolder::new(settings(
+        "auth_issuer: https://sso.example.com\nnetbox_url: https://netbox-a.example.com",
+    ));
+    let before = holder.load();
+    holder
+        .replace(settings(
+            "auth_issuer: https://sso.example.com\nnetbox_url: https://netbox-b.example.com",
+        ))
+        .expect("reload rejected");
+    // settings in use stay valid, new readers get the replaced ones
+    assert_eq!("https://netbox-a.example.com", before.netbox_url);
+    assert_eq!("https://netbox-b.example.com", holder.load().netbox_url);
+
+    // the tokens are validated against the issuer of the start
+    assert!(
+        holder
+            .replace(settings(
+                "auth_issuer: https://other.example.com\nnetbox_url: https://netbox-c.example.com",
+            ))
+            .is_err()
+    );
+    assert_eq!("https://netbox-b.example.com", holder.load().netbox_url);
+}
+
+#[test]
+fn test_reload_authorized() {
+    let base = "auth_issuer: https://sso.example.com\nnetbox_url: https://netbox.example.com";
+    let without_token = settings(base);
+    assert!(!without_token.reload_authorized(None));
+    assert!(!without_token.reload_authorized(Some("Bearer ")));
+
+    let with_token = settings(&format!("{base}\nserver_reload_token: s3cret"));
+    assert!(with_token.reload_authorized(Some("Bearer s3cret")));
+    assert!(!with_token.reload_authorized(None));
+    assert!(!with_token.reload_authorized(Some("s3cret")));
+    assert!(!with_token.reload_authorized(Some("Bearer s3cre")));
+    assert!(!with_token.reload_authorized(Some("Bearer s3creT")));
+}
+
+#[test]
+fn test_schedule_window() {
+    let schedule = |yaml: &str| serde_yaml::from_str::<Schedule>(yaml);
+    let at = |hour, minute| Time::from_hms(hour, minute, 0).expect("invalid time");
+    let office = schedule("on: \"07:00:00\"\noff: \"19:30\"").expect("invalid schedule");
+    assert_eq!(at(19, 30), office.off());
+    assert!(!office.is_on_at(at(6, 59)));
+    assert!(office.is_on_at(at(7, 0)));
+    assert!(!office.is_on_at(at(19, 30)));
+
+    // the window ends the next day
+    let night = schedule("on: \"22:00\"\noff: \"06:00\"\nutc_offset: \"+01:00\"")
+        .expect("invalid schedule");
+    assert!(night.is_on_at(at(23, 0)));
+    assert!(night.is_on_at(at(5, 0)));
+    assert!(!night.is_on_at(at(12, 0)));
+
+    for invalid in [
+        "on: \"25:00\"\noff: \"06:00\"",
+        "on: \"7 am\"\noff: \"06:00\"",
+        "on: \"07:00\"\noff: \"07:00:00\"",
+        "on: \"07:00\"\noff: \"19:00\"\nutc_offset: \"01:00\"",
+    ] {
+        assert!(schedule(invalid).is_err(), "{invalid} accepted");
+    }
+}
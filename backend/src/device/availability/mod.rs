@@ -0,0 +1,109 @@
+use async_graphql::SimpleObject;
+use lazy_static::lazy_static;
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    sync::{Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(test)]
+mod test;
+
+/// Ping results kept per address, older results are dropped
+const HISTORY_SIZE: usize = 1440;
+
+/// Reachability of a device over the recorded pings of its management address
+#[derive(Debug, Clone, PartialEq, SimpleObject)]
+pub struct DeviceAvailability {
+    /// seconds since the unix epoch of the last successful ping
+    last_seen: Option<u64>,
+    /// share of successful pings in percent
+    uptime_percent: f64,
+    /// failed pings since the last successful one
+    consecutive_failures: u32,
+    /// number of recorded pings the uptime is calculated from
+    samples: u32,
+}
+
+impl DeviceAvailability {
+    pub fn is_up(&self) -> bool {
+        self.samples > 0 && self.consecutive_failures == 0
+    }
+    pub fn uptime_percent(&self) -> f64 {
+        self.uptime_percent
+    }
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+}
+
+#[derive(Debug, Default)]
+struct AddressHistory {
+    samples: VecDeque<bool>,
+    last_seen: Option<SystemTime>,
+    consecutive_failures: u32,
+}
+
+impl AddressHistory {
+    fn record(&mut self, reachable: bool, at: SystemTime) {
+        if self.samples.len() == HISTORY_SIZE {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(reachable);
+        if reachable {
+            self.last_seen = Some(at);
+            self.consecutive_failures = 0;
+        } else {
+            self.consecutive_failures += 1;
+        }
+    }
+    fn availability(&self) -> DeviceAvailability {
+        let reachable = self.samples.iter().filter(|reachable| **reachable).count();
+        DeviceAvailability {
+            last_seen: self.last_seen.map(|time| {
+                time.duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default()
+            }),
+            uptime_percent: if self.samples.is_empty() {
+                0.0
+            } else {
+                reachable as f64 * 100.0 / self.samples.len() as f64
+            },
+            consecutive_failures: self.consecutive_failures,
+            samples: self.samples.len() as u32,
+        }
+    }
+}
+
+/// Ping results of the management addresses, kept in memory only
+#[derive(Debug, Default)]
+pub struct AvailabilityHistory {
+    addresses: Mutex<HashMap<IpAddr, AddressHistory>>,
+}
+
+impl AvailabilityHistory {
+    pub fn record(&self, address: IpAddr, reachable: bool) {
+        self.record_at(address, reachable, SystemTime::now());
+    }
+    pub fn record_at(&self, address: IpAddr, reachable: bool, at: SystemTime) {
+        self.lock()
+            .entry(address)
+            .or_default()
+            .record(reachable, at);
+    }
+    /// availability of an address, none if it was never pinged
+    pub fn availability(&self, address: IpAddr) -> Option<DeviceAvailability> {
+        self.lock().get(&address).map(AddressHistory::availability)
+    }
+    fn lock(&self) -> MutexGuard<'_, HashMap<IpAddr, AddressHistory>> {
+        self.addresses
+            .lock()
+            .expect("availability history poisoned")
+    }
+}
+
+lazy_static! {
+    pub static ref AVAILABILITY: AvailabilityHistory = AvailabilityHistory::default();
+}
@@ -0,0 +1,33 @@
+use crate::device::availability::{AvailabilityHistory, HISTORY_SIZE};
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    time::{Duration, UNIX_EPOCH},
+};
+
+#[test]
+fn test_availability() {
+    let history = AvailabilityHistory::default();
+    let address = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    assert_eq!(None, history.availability(address));
+
+    let seen = UNIX_EPOCH + Duration::from_secs(1000);
+    history.record_at(address, true, seen);
+    history.record_at(address, true, seen);
+    history.record_at(address, false, seen + Duration::from_secs(10));
+    history.record_at(address, false, seen + Duration::from_secs(20));
+    let availability = history.availability(address).expect("address not recorded");
+    assert_eq!(Some(1000), availability.last_seen);
+    assert_eq!(50.0, availability.uptime_percent());
+    assert_eq!(2, availability.consecutive_failures());
+    assert_eq!(4, availability.samples);
+    assert!(!availability.is_up());
+
+    // only the newest results are kept
+    for _ in 0..HISTORY_SIZE {
+        history.record_at(address, true, seen);
+    }
+    let availability = history.availability(address).expect("address not recorded");
+    assert_eq!(100.0, availability.uptime_percent());
+    assert_eq!(HISTORY_SIZE as u32, availability.samples);
+    assert!(availability.is_up());
+}
@@ -0,0 +1,167 @@
+use crate::config::{CONFIG, Settings};
+use async_graphql::{Enum, SimpleObject};
+use lazy_static::lazy_static;
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Mutex, MutexGuard, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(test)]
+mod test;
+
+/// Whether fleet operations connect to a device
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BackoffState {
+    /// no connection failed since the last successful one
+    Healthy,
+    /// connections failed, not often enough to back off or the next retry is due
+    Failing,
+    /// fleet operations skip the device until the retry
+    BackingOff,
+}
+
+/// Connection failures of the management address of a device
+#[derive(Debug, Clone, PartialEq, SimpleObject)]
+pub struct ConnectionFailures {
+    state: BackoffState,
+    /// failed connections since the last successful one
+    consecutive_failures: u32,
+    last_error: Box<str>,
+    /// seconds since the unix epoch fleet operations try the device again, none if they do
+    /// not back off
+    retry_at: Option<u64>,
+}
+
+impl ConnectionFailures {
+    pub fn state(&self) -> BackoffState {
+        self.state
+    }
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+}
+
+#[derive(Debug)]
+struct AddressFailures {
+    failures: u32,
+    last_error: Box<str>,
+    retry_at: Option<SystemTime>,
+}
+
+/// Consecutive connection failures per management address, kept in memory only. After `after`
+/// failures fleet operations back off from the address, the delay doubles with every further
+/// failure up to `max`. A successful connection resets the address.
+#[derive(Debug)]
+pub struct ConnectionBackoff {
+    limits: RwLock<BackoffLimits>,
+    addresses: Mutex<HashMap<IpAddr, AddressFailures>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BackoffLimits {
+    after: u32,
+    initial: Duration,
+    max: Duration,
+}
+
+impl ConnectionBackoff {
+    pub fn new(after: u32, initial: Duration, max: Duration) -> Self {
+        Self {
+            limits: RwLock::new(BackoffLimits {
+                after: after.max(1),
+                initial,
+                max,
+            }),
+            addresses: Mutex::default(),
+        }
+    }
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self::new(
+            settings.backoff_after_failures(),
+            settings.backoff_initial_delay(),
+            settings.backoff_max_delay(),
+        )
+    }
+    /// takes the limits of reloaded settings, the next failure of an address is delayed by them
+    pub fn reconfigure(&self, settings: &Settings) {
+        *self.limits.write().expect("connection backoff poisoned") = BackoffLimits {
+            after: settings.backoff_after_failures().max(1),
+            initial: settings.backoff_initial_delay(),
+            max: settings.backoff_max_delay(),
+        };
+    }
+    pub fn record_success(&self, address: IpAddr) {
+        self.lock().remove(&address);
+    }
+    pub fn record_failure(&self, address: IpAddr, error: &str) {
+        self.record_failure_at(address, error, SystemTime::now());
+    }
+    pub fn record_failure_at(&self, address: IpAddr, error: &str, at: SystemTime) {
+        let mut addresses = self.lock();
+        let entry = addresses.entry(address).or_insert(AddressFailures {
+            failures: 0,
+            last_error: Box::default(),
+            retry_at: None,
+        });
+        entry.failures += 1;
+        entry.last_error = error.into();
+        entry.retry_at = self.delay(entry.failures).map(|delay| at + delay);
+    }
+    /// forgets the failures of the address, fleet operations connect to it again right away
+    pub fn reset(&self, address: IpAddr) -> bool {
+        self.lock().remove(&address).is_some()
+    }
+    /// failures of an address, none if the last connection succeeded
+    pub fn failures(&self, address: IpAddr) -> Option<ConnectionFailures> {
+        self.failures_at(address, SystemTime::now())
+    }
+    pub fn failures_at(&self, address: IpAddr, at: SystemTime) -> Option<ConnectionFailures> {
+        self.lock().get(&address).map(|entry| {
+            let backing_off = entry.retry_at.filter(|retry_at| *retry_at > at);
+            ConnectionFailures {
+                state: if backing_off.is_some() {
+                    BackoffState::BackingOff
+                } else {
+                    BackoffState::Failing
+                },
+                consecutive_failures: entry.failures,
+                last_error: entry.last_error.clone(),
+                retry_at: backing_off.map(|time| {
+                    time.duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or_default()
+                }),
+            }
+        })
+    }
+    /// time until fleet operations try the address again, none if they do not back off
+    pub fn remaining(&self, address: IpAddr) -> Option<Duration> {
+        self.remaining_at(address, SystemTime::now())
+    }
+    pub fn remaining_at(&self, address: IpAddr, at: SystemTime) -> Option<Duration> {
+        self.lock()
+            .get(&address)
+            .and_then(|entry| entry.retry_at)
+            .and_then(|retry_at| retry_at.duration_since(at).ok())
+            .filter(|remaining| !remaining.is_zero())
+    }
+    fn delay(&self, failures: u32) -> Option<Duration> {
+        let limits = *self.limits.read().expect("connection backoff poisoned");
+        let doublings = failures.checked_sub(limits.after)?;
+        Some(
+            limits
+                .initial
+                .saturating_mul(2u32.saturating_pow(doublings))
+                .min(limits.max),
+        )
+    }
+    fn lock(&self) -> MutexGuard<'_, HashMap<IpAddr, AddressFailures>> {
+        self.addresses.lock().expect("connection backoff poisoned")
+    }
+}
+
+lazy_static! {
+    pub static ref BACKOFF: ConnectionBackoff = ConnectionBackoff::from_settings(&CONFIG.load());
+}
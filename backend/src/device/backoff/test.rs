@@ -0,0 +1,93 @@
+use crate::{
+    config::Settings,
+    device::backoff::{BackoffState, ConnectionBackoff},
+};
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    time::{Duration, UNIX_EPOCH},
+};
+
+#[test]
+fn test_connection_backoff() {
+    let backoff = ConnectionBackoff::new(2, Duration::from_secs(60), Duration::from_secs(200));
+    let address = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let start = UNIX_EPOCH + Duration::from_secs(1000);
+    assert_eq!(None, backoff.failures_at(address, start));
+
+    backoff.record_failure_at(address, "timeout", start);
+    let failures = backoff
+        .failures_at(address, start)
+        .expect("failure not recorded");
+    assert_eq!(BackoffState::Failing, failures.state());
+    assert_eq!(None, backoff.remaining_at(address, start));
+
+    backoff.record_failure_at(address, "timeout", start);
+    let failures = backoff
+        .failures_at(address, start)
+        .expect("failure not recorded");
+    assert_eq!(BackoffState::BackingOff, failures.state());
+    assert_eq!(2, failures.consecutive_failures());
+    assert_eq!(Some(1060), failures.retry_at);
+    assert_eq!(
+        Some(Duration::from_secs(50)),
+        backoff.remaining_at(address, start + Duration::from_secs(10))
+    );
+    // the retry is due
+    let retry = start + Duration::from_secs(60);
+    assert_eq!(None, backoff.remaining_at(address, retry));
+    assert_eq!(
+        Some(BackoffState::Failing),
+        backoff.failures_at(address, retry).map(|f| f.state())
+    );
+
+    // the delay doubles up to the maximum
+    backoff.record_failure_at(address, "timeout", retry);
+    assert_eq!(
+        Some(Duration::from_secs(120)),
+        backoff.remaining_at(address, retry)
+    );
+    backoff.record_failure_at(address, "timeout", retry);
+    assert_eq!(
+        Some(Duration::from_secs(200)),
+        backoff.remaining_at(address, retry)
+    );
+
+    backoff.record_success(address);
+    assert_eq!(None, backoff.failures_at(address, retry));
+    backoff.record_failure_at(address, "timeout", retry);
+    assert!(backoff.reset(address));
+    assert!(!backoff.reset(address));
+}
+
+#[test]
+fn test_reconfigure_backoff() {
+    let backoff = ConnectionBackoff::new(3, Duration::from_secs(60), Duration::from_secs(3600));
+    let address = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let start = UNIX_EPOCH + Duration::from_secs(1000);
+    backoff.record_failure_at(address, "timeout", start);
+    assert_eq!(None, backoff.remaining_at(address, start));
+
+    let settings: Settings = serde_yaml::from_str(
+        "auth_client_id: provisioner
+auth_issuer: https://sso.example.com
+netbox_url: https://netbox.example.com
+netbox_token: token
+mikrotik_credentials: {}
+backoff_after_failures: 2
+backoff_initial_delay_secs: 10
+backoff_max_delay_secs: 15",
+    )
+    .expect("invalid settings");
+    backoff.reconfigure(&settings);
+    // the recorded failures count towards the reloaded limits
+    backoff.record_failure_at(address, "timeout", start);
+    assert_eq!(
+        Some(Duration::from_secs(10)),
+        backoff.remaining_at(address, start)
+    );
+    backoff.record_failure_at(address, "timeout", start);
+    assert_eq!(
+        Some(Duration::from_secs(15)),
+        backoff.remaining_at(address, start)
+    );
+}
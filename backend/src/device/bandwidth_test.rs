@@ -0,0 +1,158 @@
+use crate::{
+    Error,
+    device::{AccessibleDevice, command::run_command},
+};
+use async_graphql::{Enum, SimpleObject};
+use log::warn;
+use mikrotik_model::MikrotikDevice;
+use std::{
+    hash::{BuildHasher, RandomState},
+    time::Duration,
+};
+
+/// user and group on the server device, only allowed to run bandwidth tests
+const TEST_ACCOUNT: &str = "provisioner-btest";
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum BandwidthTestProtocol {
+    #[default]
+    Tcp,
+    Udp,
+}
+
+impl BandwidthTestProtocol {
+    fn as_ros(&self) -> &'static str {
+        match self {
+            BandwidthTestProtocol::Tcp => "tcp",
+            BandwidthTestProtocol::Udp => "udp",
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct BandwidthTestResult {
+    status: Option<Box<str>>,
+    /// average transmit rate in bits per second
+    tx_total_average: Option<u64>,
+    /// average receive rate in bits per second
+    rx_total_average: Option<u64>,
+    lost_packets: Option<u64>,
+    duration: Option<Box<str>>,
+}
+
+/// Runs a bandwidth test from `client` towards `server`
+///
+/// Enables the bandwidth server on the server device and runs the client side with a
+/// temporary account restricted to the `test` policy. The account is removed and the
+/// server is restored to its previous state afterward, also when the test fails.
+pub async fn run_bandwidth_test(
+    server: &AccessibleDevice,
+    client: &AccessibleDevice,
+    duration: Duration,
+    protocol: BandwidthTestProtocol,
+) -> Result<BandwidthTestResult, Error> {
+    let was_enabled = run_command(&server.client, "/tool/bandwidth-server/print", &[])
+        .await?
+        .first()
+        .and_then(|s| s.get("enabled"))
+        .is_some_and(|enabled| enabled.as_ref() == "true" || enabled.as_ref() == "yes");
+    let result = run_with_test_account(server, client, duration, protocol).await;
+    let cleanup = cleanup_server(&server.client, was_enabled).await;
+    let result = result?;
+    cleanup?;
+    Ok(result)
+}
+
+async fn run_with_test_account(
+    server: &AccessibleDevice,
+    client: &AccessibleDevice,
+    duration: Duration,
+    protocol: BandwidthTestProtocol,
+) -> Result<BandwidthTestResult, Error> {
+    // leftovers of an interrupted test
+    remove_test_account(&server.client).await?;
+    let password = one_time_password();
+    run_command(
+        &server.client,
+        "/user/group/add",
+        &[("name", TEST_ACCOUNT), ("policy", "test")],
+    )
+    .await?;
+    run_command(
+        &server.client,
+        "/user/add",
+        &[
+            ("name", TEST_ACCOUNT),
+            ("group", TEST_ACCOUNT),
+            ("password", password.as_str()),
+        ],
+    )
+    .await?;
+    run_command(
+        &server.client,
+        "/tool/bandwidth-server/set",
+        &[("enabled", "yes"), ("authenticate", "yes")],
+    )
+    .await?;
+    let address = server.address.to_string();
+    let duration = format!("{}s", duration.as_secs());
+    let attributes = [
+        ("address", address.as_str()),
+        ("duration", duration.as_str()),
+        ("protocol", protocol.as_ros()),
+        ("direction", "both"),
+        ("user", TEST_ACCOUNT),
+        ("password", password.as_str()),
+    ];
+    let replies = run_command(&client.client, "/tool/bandwidth-test", &attributes).await?;
+    let mut last = replies.into_vec().pop().unwrap_or_default();
+    Ok(BandwidthTestResult {
+        status: last.remove("status"),
+        tx_total_average: last.get("tx-total-average").and_then(|v| v.parse().ok()),
+        rx_total_average: last.get("rx-total-average").and_then(|v| v.parse().ok()),
+        lost_packets: last.get("lost-packets").and_then(|v| v.parse().ok()),
+        duration: last.remove("duration"),
+    })
+}
+
+async fn cleanup_server(client: &MikrotikDevice, was_enabled: bool) -> Result<(), Error> {
+    let enabled = if was_enabled { "yes" } else { "no" };
+    let restored = run_command(
+        client,
+        "/tool/bandwidth-server/set",
+        &[("enabled", enabled)],
+    )
+    .await;
+    let removed = remove_test_account(client).await;
+    if let Err(error) = &restored {
+        warn!("Cannot restore bandwidth server: {error}");
+    }
+    if let Err(error) = &removed {
+        warn!("Cannot remove bandwidth test account: {error}");
+    }
+    restored.and(removed)
+}
+
+async fn remove_test_account(client: &MikrotikDevice) -> Result<(), Error> {
+    // the user has to go first, a group in use cannot be removed
+    for path in ["/user", "/user/group"] {
+        let entries = run_command(client, &format!("{path}/print"), &[]).await?;
+        for entry in entries
+            .iter()
+            .filter(|e| e.get("name").map(Box::as_ref) == Some(TEST_ACCOUNT))
+        {
+            if let Some(id) = entry.get(".id") {
+                run_command(client, &format!("{path}/remove"), &[(".id", id)]).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// password of the temporary test account, it only lives for the duration of the test
+fn one_time_password() -> String {
+    let state = RandomState::new();
+    (0..4u8)
+        .map(|i| format!("{:016x}", state.hash_one(i)))
+        .collect()
+}
@@ -0,0 +1,158 @@
+use crate::{
+    Error,
+    device::{AccessibleDevice, command::run_command, ros::naming::transliterate},
+    topology::access::interface::InterfaceAccess,
+};
+use async_graphql::{Enum, SimpleObject};
+use mikrotik_model::ascii::AsciiString;
+use std::collections::HashMap;
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum CablingStatus {
+    /// neighbor matches the cable in netbox
+    Ok,
+    /// another device or port is seen than the one expected by netbox
+    Mismatch,
+    /// netbox expects a neighbor but none is seen
+    Missing,
+    /// a neighbor is seen on a port without cable in netbox
+    Unexpected,
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct CablingCheck {
+    interface: Box<str>,
+    status: CablingStatus,
+    expected_device: Option<Box<str>>,
+    expected_interface: Option<Box<str>>,
+    seen_device: Option<Box<str>>,
+    seen_interface: Option<Box<str>>,
+}
+
+struct Neighbor {
+    identity: Option<Box<str>>,
+    interface: Option<Box<str>>,
+}
+
+impl AccessibleDevice {
+    /// Compares the lldp neighbors seen by the device against the cables documented in netbox
+    pub async fn check_cabling(&self) -> Result<Box<[CablingCheck]>, Error> {
+        let mut neighbors = HashMap::<Box<str>, Vec<Neighbor>>::new();
+        for mut reply in run_command(&self.client, "/ip/neighbor/print", &[]).await? {
+            if !reply
+                .get("discovered-by")
+                .map(|d| d.split(',').any(|p| p == "lldp"))
+                .unwrap_or(true)
+            {
+                continue;
+            }
+            let Some(ports) = reply.remove("interface") else {
+                continue;
+            };
+            // the first entry is the physical port, further entries are the bridges above it
+            let Some(port) = ports.split(',').next() else {
+                continue;
+            };
+            neighbors.entry(port.into()).or_default().push(Neighbor {
+                identity: reply.remove("identity"),
+                interface: reply.remove("interface-name"),
+            });
+        }
+
+        let mut result = Vec::new();
+        for interface in self.device_config.interfaces() {
+            let Some(if_name) = label_name(&interface).map(|n| to_str(&n)) else {
+                continue;
+            };
+            let expected = interface
+                .connected_interfaces()
+                .into_iter()
+                .filter_map(|far| {
+                    far.device().map(|d| {
+                        (
+                            Box::<str>::from(d.name()),
+                            label_name(&far).map(|n| to_str(&n)),
+                        )
+                    })
+                })
+                .next();
+            let seen = neighbors.remove(&if_name).unwrap_or_default();
+            match expected {
+                Some((expected_device, expected_interface)) => {
+                    let found = seen
+                        .iter()
+                        .find(|n| n.identity.as_deref() == Some(expected_device.as_ref()));
+                    let status = match found {
+                        Some(n)
+                            if expected_interface.is_none()
+                                || n.interface == expected_interface =>
+                        {
+                            CablingStatus::Ok
+                        }
+                        Some(_) => CablingStatus::Mismatch,
+                        None if seen.is_empty() => CablingStatus::Missing,
+                        None => CablingStatus::Mismatch,
+                    };
+                    let shown = found.or(seen.first());
+                    result.push(CablingCheck {
+                        interface: if_name,
+                        status,
+                        expected_device: Some(expected_device),
+                        expected_interface,
+                        seen_device: shown.and_then(|n| n.identity.clone()),
+                        seen_interface: shown.and_then(|n| n.interface.clone()),
+                    });
+                }
+                None => {
+                    for n in seen {
+                        result.push(CablingCheck {
+                            interface: if_name.clone(),
+                            status: CablingStatus::Unexpected,
+                            expected_device: None,
+                            expected_interface: None,
+                            seen_device: n.identity,
+                            seen_interface: n.interface,
+                        });
+                    }
+                }
+            }
+        }
+        for (if_name, seen) in neighbors {
+            for n in seen {
+                result.push(CablingCheck {
+                    interface: if_name.clone(),
+                    status: CablingStatus::Unexpected,
+                    expected_device: None,
+                    expected_interface: None,
+                    seen_device: n.identity,
+                    seen_interface: n.interface,
+                });
+            }
+        }
+        Ok(result.into_boxed_slice())
+    }
+}
+
+/// name of the port with its label from netbox, e.g. `ether1-uplink`
+fn label_name(interface: &InterfaceAccess) -> Option<AsciiString> {
+    interface.external_port().map(|port| {
+        if let Some(label) = interface.label() {
+            let mut name = port.short_name().0.to_vec();
+            name.push(b'-');
+            for char in transliterate(label).chars() {
+                if char.is_ascii_alphanumeric() {
+                    name.push(char as u8);
+                } else if matches!(char, '-' | '.') {
+                    name.push(b'-');
+                }
+            }
+            name.into_boxed_slice().into()
+        } else {
+            port.short_name()
+        }
+    })
+}
+
+fn to_str(name: &AsciiString) -> Box<str> {
+    String::from_utf8_lossy(&name.0).into()
+}
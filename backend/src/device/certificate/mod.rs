@@ -0,0 +1,534 @@
+use crate::{
+    Error,
+    config::CertificateConfig,
+    device::{
+        AccessibleDevice,
+        command::{CommandReply, run_command},
+    },
+};
+use async_graphql::SimpleObject;
+use log::{info, warn};
+use rcgen::{
+    Certificate, CertificateParams, CertificateSigningRequestParams, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair, KeyUsagePurpose,
+};
+use std::{
+    hash::{BuildHasher, Hasher, RandomState},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use time::{Duration, OffsetDateTime};
+
+#[cfg(test)]
+mod test;
+
+/// certificates named with this prefix are managed by the provisioner
+pub const MANAGED_PREFIX: &str = "provisioner-";
+/// CA of the device certificate, imported without its key or created on the device
+pub const CA_CERTIFICATE: &str = "provisioner-ca";
+/// certificate of the device used by api-ssl and CAPsMAN
+pub const DEVICE_CERTIFICATE: &str = "provisioner-device";
+/// appended to the name of a replaced certificate until its successor is in use
+const RETIRED_SUFFIX: &str = "-retired";
+/// files written by `/certificate/create-certificate-request`
+const REQUEST_FILE: &str = "certificate-request.pem";
+const REQUEST_KEY_FILE: &str = "certificate-request_key.pem";
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+const CA_KEY_USAGE: &str = "key-cert-sign,crl-sign";
+const DEVICE_KEY_USAGE: &str = "digital-signature,key-encipherment,tls-server,tls-client";
+
+/// Certificate installed on a device
+#[derive(SimpleObject, Debug, Clone)]
+pub struct DeviceCertificate {
+    name: Box<str>,
+    common_name: Option<Box<str>>,
+    /// seconds since the unix epoch the certificate expires at, none if it is not signed
+    expires_at: Option<u64>,
+    /// days until the certificate expires, negative if it is expired
+    days_left: Option<i64>,
+    /// the private key is stored on the device
+    private_key: bool,
+    authority: bool,
+    /// created by the provisioner, renewed by `provisionCertificates`
+    managed: bool,
+    /// a managed certificate expiring within the renewal period or not signed
+    needs_renewal: bool,
+}
+
+/// Outcome of provisioning the managed certificates of a device
+#[derive(SimpleObject, Debug)]
+pub struct CertificateProvisioning {
+    /// certificates created or replaced on the device
+    issued: Box<[Box<str>]>,
+    /// all certificates on the device afterward
+    certificates: Box<[DeviceCertificate]>,
+}
+
+impl DeviceCertificate {
+    fn from_reply(mut reply: CommandReply, now: u64, renew_before_days: u32) -> Self {
+        let name = reply.remove("name").unwrap_or_default();
+        let expires_at = reply
+            .get("invalid-after")
+            .and_then(|time| parse_routeros_time(time));
+        let days_left = expires_at
+            .map(|expires_at| (expires_at as i64 - now as i64).div_euclid(SECONDS_PER_DAY));
+        let managed = name.starts_with(MANAGED_PREFIX);
+        let flag =
+            |reply: &CommandReply, key: &str| reply.get(key).is_some_and(|v| v.as_ref() == "true");
+        DeviceCertificate {
+            common_name: reply.remove("common-name"),
+            expires_at,
+            days_left,
+            private_key: flag(&reply, "private-key"),
+            authority: flag(&reply, "ca"),
+            managed,
+            needs_renewal: managed
+                && days_left.is_none_or(|days| days < i64::from(renew_before_days)),
+            name,
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn needs_renewal(&self) -> bool {
+        self.needs_renewal
+    }
+    /// signed and not expired
+    pub fn is_valid(&self) -> bool {
+        self.days_left.is_some_and(|days| days >= 0)
+    }
+}
+
+/// CA configured on the server. It signs the certificate requests of the devices, so its key
+/// never leaves the server.
+pub struct LocalCa {
+    certificate_pem: String,
+    certificate: Certificate,
+    key: KeyPair,
+}
+
+impl LocalCa {
+    async fn load(certificate_file: &Path, key_file: &Path) -> Result<Self, Error> {
+        Self::from_pem(
+            read_certificate_file(certificate_file).await?,
+            &read_certificate_file(key_file).await?,
+        )
+    }
+    /// the key has to be an unencrypted PEM file
+    pub fn from_pem(certificate_pem: String, key_pem: &str) -> Result<Self, Error> {
+        let key = KeyPair::from_pem(key_pem).map_err(signing_error)?;
+        // the issuer is only taken for its name and key, signing it again changes nothing
+        let certificate = CertificateParams::from_ca_cert_pem(&certificate_pem)
+            .and_then(|params| params.self_signed(&key))
+            .map_err(signing_error)?;
+        Ok(Self {
+            certificate_pem,
+            certificate,
+            key,
+        })
+    }
+    /// signs a certificate request of a device, valid from `now` for `days_valid` days
+    pub fn sign(&self, request_pem: &str, days_valid: u32, now: u64) -> Result<String, Error> {
+        let mut request =
+            CertificateSigningRequestParams::from_pem(request_pem).map_err(signing_error)?;
+        let params = &mut request.params;
+        params.not_before = OffsetDateTime::from_unix_timestamp(now as i64)
+            .map_err(|error| Error::CertificateSigning(error.to_string().into_boxed_str()))?;
+        params.not_after = params.not_before + Duration::days(i64::from(days_valid));
+        params.is_ca = IsCa::ExplicitNoCa;
+        params.key_usages = vec![
+            KeyUsagePurpose::DigitalSignature,
+            KeyUsagePurpose::KeyEncipherment,
+        ];
+        params.extended_key_usages = vec![
+            ExtendedKeyUsagePurpose::ServerAuth,
+            ExtendedKeyUsagePurpose::ClientAuth,
+        ];
+        params.use_authority_key_identifier_extension = true;
+        Ok(request
+            .signed_by(&self.certificate, &self.key)
+            .map_err(signing_error)?
+            .pem())
+    }
+}
+
+fn signing_error(error: rcgen::Error) -> Error {
+    Error::CertificateSigning(error.to_string().into_boxed_str())
+}
+
+async fn read_certificate_file(path: &Path) -> Result<String, Error> {
+    tokio::fs::read_to_string(path)
+        .await
+        .map_err(|error| Error::CertificateFile {
+            path: path.display().to_string().into_boxed_str(),
+            error,
+        })
+}
+
+/// Parses a timestamp printed by RouterOS, `2025-11-10 12:00:00` since 7.10 and
+/// `nov/10/2025 12:00:00` before. The time zone of the device is taken as UTC.
+pub fn parse_routeros_time(value: &str) -> Option<u64> {
+    let (date, time) = value.trim().split_once(' ')?;
+    let (year, month, day) = if let Some((month, rest)) = date.split_once('/') {
+        let (day, year) = rest.split_once('/')?;
+        let month = [
+            "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+        ]
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(month))?
+            + 1;
+        (year.parse().ok()?, month as u32, day.parse().ok()?)
+    } else {
+        let mut parts = date.splitn(3, '-');
+        (
+            parts.next()?.parse().ok()?,
+            parts.next()?.parse().ok()?,
+            parts.next()?.parse().ok()?,
+        )
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut parts = time.splitn(3, ':');
+    let mut seconds = 0;
+    for _ in 0..3 {
+        let part = parts.next()?.parse::<i64>().ok()?;
+        seconds = seconds * 60 + part;
+    }
+    let timestamp = days_from_civil(year, month, day) * SECONDS_PER_DAY + seconds;
+    u64::try_from(timestamp).ok()
+}
+
+/// days since the unix epoch of a date of the gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl AccessibleDevice {
+    pub async fn fetch_certificates(
+        &self,
+        config: &CertificateConfig,
+    ) -> Result<Box<[DeviceCertificate]>, Error> {
+        let now = now();
+        Ok(run_command(&self.client, "/certificate/print", &[])
+            .await?
+            .into_iter()
+            .map(|reply| DeviceCertificate::from_reply(reply, now, config.renew_before_days()))
+            .collect())
+    }
+
+    /// Issues the managed certificates missing on the device or due for renewal, all of them
+    /// with `force`. A new CA replaces the device certificate as well. The replaced
+    /// certificates are kept under a retired name until api-ssl and CAPsMAN use their
+    /// successors, so the services are never left without a certificate.
+    pub async fn provision_certificates(
+        &self,
+        config: &CertificateConfig,
+        force: bool,
+    ) -> Result<CertificateProvisioning, Error> {
+        let existing = self.fetch_certificates(config).await?;
+        let due = |name: &str| {
+            force
+                || existing
+                    .iter()
+                    .find(|certificate| certificate.name() == name)
+                    .is_none_or(DeviceCertificate::needs_renewal)
+        };
+        let ca = match config.ca_files() {
+            Some((certificate_file, key_file)) => {
+                Some(LocalCa::load(certificate_file, key_file).await?)
+            }
+            None => None,
+        };
+        let mut issued = Vec::new();
+        let mut retired = Vec::new();
+        let renew_ca = due(CA_CERTIFICATE);
+        if renew_ca {
+            retired.extend(self.retire_certificate(&existing, CA_CERTIFICATE).await?);
+            self.install_ca(ca.as_ref(), config).await?;
+            issued.push(CA_CERTIFICATE.into());
+        }
+        if renew_ca || due(DEVICE_CERTIFICATE) {
+            retired.extend(
+                self.retire_certificate(&existing, DEVICE_CERTIFICATE)
+                    .await?,
+            );
+            self.issue_device_certificate(ca.as_ref(), config).await?;
+            issued.push(DEVICE_CERTIFICATE.into());
+        }
+        if !retired.is_empty() {
+            self.replace_retired_in_services(&retired).await?;
+            for name in &retired {
+                run_command(&self.client, "/certificate/remove", &[("numbers", name)]).await?;
+            }
+        }
+        if !issued.is_empty() {
+            info!(
+                "Issued certificates {issued:?} on {}",
+                self.device_config.name()
+            );
+        }
+        Ok(CertificateProvisioning {
+            issued: issued.into_boxed_slice(),
+            certificates: self.fetch_certificates(config).await?,
+        })
+    }
+
+    /// renames a certificate to be replaced, a certificate left retired by an interrupted
+    /// provisioning is removed first
+    async fn retire_certificate(
+        &self,
+        existing: &[DeviceCertificate],
+        name: &str,
+    ) -> Result<Option<String>, Error> {
+        let exists = |name: &str| {
+            existing
+                .iter()
+                .any(|certificate| certificate.name() == name)
+        };
+        let retired_name = format!("{name}{RETIRED_SUFFIX}");
+        if exists(&retired_name) {
+            run_command(
+                &self.client,
+                "/certificate/remove",
+                &[("numbers", &retired_name)],
+            )
+            .await?;
+        }
+        if !exists(name) {
+            return Ok(None);
+        }
+        run_command(
+            &self.client,
+            "/certificate/set",
+            &[("numbers", name), ("name", &retired_name)],
+        )
+        .await?;
+        Ok(Some(retired_name))
+    }
+
+    /// Points api-ssl and the CAPsMAN manager still using a retired certificate to its
+    /// successor. The services follow the renamed certificate, they would be left without one
+    /// once it is removed.
+    async fn replace_retired_in_services(&self, retired: &[String]) -> Result<(), Error> {
+        let successor = |current: Option<&Box<str>>| {
+            let current = current?;
+            retired
+                .iter()
+                .any(|name| **name == **current)
+                .then(|| current.trim_end_matches(RETIRED_SUFFIX).to_string())
+        };
+        let services = run_command(&self.client, "/ip/service/print", &[]).await?;
+        for service in &services {
+            if let (Some(name), Some(certificate)) =
+                (service.get("name"), successor(service.get("certificate")))
+            {
+                run_command(
+                    &self.client,
+                    "/ip/service/set",
+                    &[("numbers", name), ("certificate", &certificate)],
+                )
+                .await?;
+            }
+        }
+        if self.device_config.wlan_controller_of().is_none() {
+            return Ok(());
+        }
+        // the print fails on devices without the CAPsMAN package, nothing uses the certificates
+        // there
+        let Ok(managers) = run_command(&self.client, "/caps-man/manager/print", &[]).await else {
+            return Ok(());
+        };
+        for manager in &managers {
+            let mut attributes = Vec::new();
+            let certificate = successor(manager.get("certificate"));
+            if let Some(certificate) = &certificate {
+                attributes.push(("certificate", certificate.as_str()));
+            }
+            let ca_certificate = successor(manager.get("ca-certificate"));
+            if let Some(ca_certificate) = &ca_certificate {
+                attributes.push(("ca-certificate", ca_certificate.as_str()));
+            }
+            if !attributes.is_empty() {
+                run_command(&self.client, "/caps-man/manager/set", &attributes).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Imports the certificate of the configured CA without its key, the CA signs the device
+    /// certificates on the server. Without a configured CA a self-signed CA is created on the
+    /// device.
+    async fn install_ca(
+        &self,
+        ca: Option<&LocalCa>,
+        config: &CertificateConfig,
+    ) -> Result<(), Error> {
+        let Some(ca) = ca else {
+            let common_name = format!("{} CA", self.device_config.name());
+            self.create_certificate(CA_CERTIFICATE, &common_name, None, CA_KEY_USAGE, config)
+                .await?;
+            return self.sign_certificate(CA_CERTIFICATE, None).await;
+        };
+        self.import_file(
+            &format!("{CA_CERTIFICATE}.crt"),
+            &ca.certificate_pem,
+            &[("name", CA_CERTIFICATE)],
+        )
+        .await
+    }
+
+    /// uploads a PEM file, imports it and removes the file again
+    async fn import_file(
+        &self,
+        file_name: &str,
+        contents: &str,
+        attributes: &[(&str, &str)],
+    ) -> Result<(), Error> {
+        run_command(
+            &self.client,
+            "/file/add",
+            &[("name", file_name), ("contents", contents)],
+        )
+        .await?;
+        let mut import = vec![("file-name", file_name), ("passphrase", "")];
+        import.extend_from_slice(attributes);
+        let imported = run_command(&self.client, "/certificate/import", &import).await;
+        self.remove_file(file_name).await;
+        imported?;
+        Ok(())
+    }
+
+    async fn remove_file(&self, file_name: &str) {
+        if let Err(error) =
+            run_command(&self.client, "/file/remove", &[("numbers", file_name)]).await
+        {
+            warn!("Cannot remove {file_name}: {error}");
+        }
+    }
+
+    /// The key of the device certificate is created on the device. The configured CA signs a
+    /// request of the device on the server, the device CA signs on the device.
+    async fn issue_device_certificate(
+        &self,
+        ca: Option<&LocalCa>,
+        config: &CertificateConfig,
+    ) -> Result<(), Error> {
+        let alt_name = self
+            .device_config
+            .management_ip()
+            .map(|address| format!("IP:{address}"));
+        self.create_certificate(
+            DEVICE_CERTIFICATE,
+            self.device_config.name(),
+            alt_name.as_deref(),
+            DEVICE_KEY_USAGE,
+            config,
+        )
+        .await?;
+        let Some(ca) = ca else {
+            return self
+                .sign_certificate(DEVICE_CERTIFICATE, Some(CA_CERTIFICATE))
+                .await;
+        };
+        // the passphrase only protects the key file until it is imported again
+        let passphrase = format!("{:016x}", RandomState::new().build_hasher().finish());
+        run_command(
+            &self.client,
+            "/certificate/create-certificate-request",
+            &[
+                ("template", DEVICE_CERTIFICATE),
+                ("key-passphrase", &passphrase),
+            ],
+        )
+        .await?;
+        let result = async {
+            let request = self.read_file(REQUEST_FILE).await?;
+            let signed = ca.sign(&request, config.days_valid(), now())?;
+            // the template is replaced by the signed certificate, the key of the request is
+            // paired with it on import
+            run_command(
+                &self.client,
+                "/certificate/remove",
+                &[("numbers", DEVICE_CERTIFICATE)],
+            )
+            .await?;
+            self.import_file(
+                &format!("{DEVICE_CERTIFICATE}.crt"),
+                &signed,
+                &[("name", DEVICE_CERTIFICATE)],
+            )
+            .await?;
+            run_command(
+                &self.client,
+                "/certificate/import",
+                &[("file-name", REQUEST_KEY_FILE), ("passphrase", &passphrase)],
+            )
+            .await?;
+            Ok(())
+        }
+        .await;
+        self.remove_file(REQUEST_FILE).await;
+        self.remove_file(REQUEST_KEY_FILE).await;
+        result
+    }
+
+    async fn read_file(&self, file_name: &str) -> Result<Box<str>, Error> {
+        run_command(&self.client, "/file/print", &[])
+            .await?
+            .into_iter()
+            .find(|file| file.get("name").is_some_and(|name| **name == *file_name))
+            .and_then(|mut file| file.remove("contents"))
+            .ok_or_else(|| Error::CommandFailed {
+                command: "/file/print".into(),
+                message: format!("{file_name} not found").into_boxed_str(),
+            })
+    }
+
+    async fn create_certificate(
+        &self,
+        name: &str,
+        common_name: &str,
+        alt_name: Option<&str>,
+        key_usage: &str,
+        config: &CertificateConfig,
+    ) -> Result<(), Error> {
+        let days_valid = config.days_valid().to_string();
+        let mut attributes = vec![
+            ("name", name),
+            ("common-name", common_name),
+            ("days-valid", &days_valid),
+            ("key-usage", key_usage),
+        ];
+        if let Some(alt_name) = alt_name {
+            attributes.push(("subject-alt-name", alt_name));
+        }
+        run_command(&self.client, "/certificate/add", &attributes).await?;
+        Ok(())
+    }
+
+    /// signs with the given CA, self-signed without
+    async fn sign_certificate(&self, name: &str, ca: Option<&str>) -> Result<(), Error> {
+        let mut attributes = vec![("number", name)];
+        if let Some(ca) = ca {
+            attributes.push(("ca", ca));
+        }
+        run_command(&self.client, "/certificate/sign", &attributes).await?;
+        Ok(())
+    }
+}
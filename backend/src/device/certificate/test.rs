@@ -0,0 +1,71 @@
+use crate::device::certificate::{DeviceCertificate, LocalCa, parse_routeros_time};
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+
+#[test]
+fn test_parse_routeros_time() {
+    assert_eq!(Some(0), parse_routeros_time("1970-01-01 00:00:00"));
+    assert_eq!(Some(1762776000), parse_routeros_time("2025-11-10 12:00:00"));
+    assert_eq!(
+        Some(1762776000),
+        parse_routeros_time("nov/10/2025 12:00:00")
+    );
+    assert_eq!(Some(1709164800), parse_routeros_time("2024-02-29 00:00:00"));
+    assert_eq!(None, parse_routeros_time("2025-13-10 12:00:00"));
+    assert_eq!(None, parse_routeros_time("never"));
+}
+
+#[test]
+fn test_needs_renewal() {
+    let now = 1762776000;
+    let certificate = |name: &str, invalid_after: Option<&str>| {
+        let reply = [("name", Some(name)), ("invalid-after", invalid_after)]
+            .into_iter()
+            .filter_map(|(key, value)| Some((Box::from(key), Box::from(value?))))
+            .collect();
+        DeviceCertificate::from_reply(reply, now, 30)
+    };
+    let expiring = certificate("provisioner-device", Some("2025-11-20 12:00:00"));
+    assert!(expiring.needs_renewal());
+    assert!(expiring.is_valid());
+    let fresh = certificate("provisioner-ca", Some("2026-11-10 12:00:00"));
+    assert!(!fresh.needs_renewal());
+    let unsigned = certificate("provisioner-device", None);
+    assert!(unsigned.needs_renewal());
+    assert!(!unsigned.is_valid());
+    let foreign = certificate("letsencrypt", Some("2025-11-01 12:00:00"));
+    assert!(!foreign.needs_renewal());
+    assert!(!foreign.is_valid());
+}
+
+#[test]
+fn test_local_ca_signs_request() {
+    let ca_key = KeyPair::generate().expect("cannot create key");
+    let mut ca_params = CertificateParams::new(Vec::<String>::new()).expect("invalid params");
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    ca_params
+        .distinguished_name
+        .push(DnType::CommonName, "provisioner CA");
+    let ca_certificate = ca_params.self_signed(&ca_key).expect("cannot sign CA");
+    let ca = LocalCa::from_pem(ca_certificate.pem(), &ca_key.serialize_pem()).expect("invalid CA");
+
+    let device_key = KeyPair::generate().expect("cannot create key");
+    let mut request = CertificateParams::new(vec!["10.0.0.1".to_string()]).expect("invalid params");
+    request
+        .distinguished_name
+        .push(DnType::CommonName, "router");
+    let request = request
+        .serialize_request(&device_key)
+        .and_then(|request| request.pem())
+        .expect("cannot create request");
+
+    let now = 1762776000;
+    let signed = ca.sign(&request, 365, now).expect("cannot sign request");
+    let signed = CertificateParams::from_ca_cert_pem(&signed).expect("invalid certificate");
+    assert_eq!(now as i64, signed.not_before.unix_timestamp());
+    assert_eq!(
+        now as i64 + 365 * 24 * 60 * 60,
+        signed.not_after.unix_timestamp()
+    );
+    assert!(!matches!(signed.is_ca, IsCa::Ca(_)));
+    assert!(ca.sign("no request", 365, now).is_err());
+}
@@ -0,0 +1,93 @@
+use crate::Error;
+use mikrotik_api::prelude::{ApiReply, CommandBuilder};
+use mikrotik_model::MikrotikDevice;
+use std::collections::HashMap;
+
+pub type CommandReply = HashMap<Box<str>, Box<str>>;
+
+/// printed by a script run with [`run_script`] once all its commands succeeded
+const SCRIPT_DONE_MARKER: &str = "provisioner-script-done";
+/// printed by a script run with [`run_script`] when one of its commands failed
+const SCRIPT_FAILED_MARKER: &str = "provisioner-script-failed";
+
+/// Runs a command on the device and collects the attributes of all returned replies
+pub async fn run_command(
+    client: &MikrotikDevice,
+    command: &str,
+    attributes: &[(&str, &str)],
+) -> Result<Box<[CommandReply]>, Error> {
+    let mut builder = CommandBuilder::new(command.as_bytes());
+    for (key, value) in attributes {
+        builder = builder.attribute(key.as_bytes(), Some(value.as_bytes()));
+    }
+    let mut receiver = client.send_command(builder.build()).await?;
+    let mut replies = Vec::new();
+    while let Some(reply) = receiver.recv().await {
+        match reply? {
+            ApiReply::Reply(attributes) => replies.push(
+                attributes
+                    .into_iter()
+                    .map(|(key, value)| (decode(&key), decode(&value)))
+                    .collect(),
+            ),
+            ApiReply::Trap { message, .. } => {
+                return Err(Error::CommandFailed {
+                    command: command.into(),
+                    message: decode(&message),
+                });
+            }
+            ApiReply::Done => break,
+        }
+    }
+    Ok(replies.into_boxed_slice())
+}
+
+/// Runs a RouterOS script and waits for it to finish. `/execute` does not report a failing
+/// command of the script, so the script prints a marker once all commands succeeded.
+pub async fn run_script(client: &MikrotikDevice, script: &str) -> Result<(), Error> {
+    let replies = run_command(
+        client,
+        "/execute",
+        &[("script", &checked_script(script)), ("as-string", "")],
+    )
+    .await?;
+    let output = replies
+        .iter()
+        .filter_map(|reply| reply.get("ret"))
+        .map(AsRef::as_ref)
+        .collect::<Vec<&str>>()
+        .join("\n");
+    check_script_output(&output)
+}
+
+/// wraps the script to print the done marker after the last command and the failed marker if a
+/// command fails
+pub fn checked_script(script: &str) -> String {
+    format!(
+        ":do {{\n{script}\n:put \"{SCRIPT_DONE_MARKER}\"\n}} on-error={{ :put \"{SCRIPT_FAILED_MARKER}\" }}\n"
+    )
+}
+
+/// the script ran through if its output contains the done marker
+pub fn check_script_output(output: &str) -> Result<(), Error> {
+    if output.lines().any(|line| line.trim() == SCRIPT_DONE_MARKER) {
+        return Ok(());
+    }
+    let message = output
+        .lines()
+        .filter(|line| line.trim() != SCRIPT_FAILED_MARKER)
+        .collect::<Vec<_>>()
+        .join("\n");
+    Err(Error::CommandFailed {
+        command: "/execute".into(),
+        message: if message.trim().is_empty() {
+            "script failed".into()
+        } else {
+            message.into()
+        },
+    })
+}
+
+fn decode(value: &[u8]) -> Box<str> {
+    String::from_utf8_lossy(value).into()
+}
@@ -0,0 +1,58 @@
+use crate::{
+    Error,
+    device::{AccessibleDevice, command::run_command},
+    topology::access::{AccessTopology, ip_addresses::IpAddressAccess},
+};
+use async_graphql::{ComplexObject, SimpleObject};
+use std::net::IpAddr;
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct DhcpLease {
+    address: Option<Box<str>>,
+    mac_address: Option<Box<str>>,
+    host_name: Option<Box<str>>,
+    server: Option<Box<str>>,
+    status: Option<Box<str>>,
+    dynamic: bool,
+    /// the matching ip address in netbox
+    netbox_address: Option<IpAddressAccess>,
+}
+
+#[ComplexObject]
+impl DhcpLease {
+    /// the client is not documented in netbox
+    async fn unknown(&self) -> bool {
+        self.netbox_address.is_none()
+    }
+}
+
+impl AccessibleDevice {
+    pub async fn fetch_dhcp_leases(&self) -> Result<Box<[DhcpLease]>, Error> {
+        let topology = self.device_config.topology();
+        Ok(
+            run_command(&self.client, "/ip/dhcp-server/lease/print", &[])
+                .await?
+                .into_iter()
+                .map(|mut reply| {
+                    let netbox_address = reply
+                        .get("address")
+                        .and_then(|a| a.parse::<IpAddr>().ok())
+                        .and_then(|a| topology.find_ip_address(a));
+                    DhcpLease {
+                        address: reply.remove("address"),
+                        mac_address: reply.remove("mac-address"),
+                        host_name: reply.remove("host-name"),
+                        server: reply.remove("server"),
+                        status: reply.remove("status"),
+                        dynamic: reply
+                            .get("dynamic")
+                            .map(|d| d.as_ref() == "true")
+                            .unwrap_or(false),
+                        netbox_address,
+                    }
+                })
+                .collect(),
+        )
+    }
+}
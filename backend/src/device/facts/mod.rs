@@ -0,0 +1,104 @@
+use crate::{
+    Error,
+    device::{AccessibleDevice, ros::SetupError},
+};
+use async_graphql::Context;
+use mikrotik_model::{model::SystemRouterboardState, resource::SingleResource};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, SystemTime},
+};
+
+#[cfg(test)]
+mod test;
+
+/// Facts of a device with the time since they were read from it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cached<T> {
+    pub value: T,
+    pub age: Duration,
+}
+
+/// Facts read from a device per serial number, kept for `ttl` since facts like the model only
+/// change when the hardware is replaced, and replaced hardware has another serial number
+#[derive(Debug)]
+pub struct FactsCache<T> {
+    ttl: Duration,
+    entries: Mutex<HashMap<Box<str>, (T, SystemTime)>>,
+}
+
+impl<T: Clone> FactsCache<T> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::default(),
+        }
+    }
+    /// facts read less than `ttl` before `at`
+    pub fn get_at(&self, serial: &str, at: SystemTime) -> Option<Cached<T>> {
+        let mut entries = self.lock();
+        let (value, fetched_at) = entries.get(serial)?;
+        let age = at.duration_since(*fetched_at).unwrap_or_default();
+        if age >= self.ttl {
+            entries.remove(serial);
+            return None;
+        }
+        Some(Cached {
+            value: value.clone(),
+            age,
+        })
+    }
+    pub fn insert_at(&self, serial: &str, value: T, at: SystemTime) {
+        self.lock().insert(serial.into(), (value, at));
+    }
+    fn lock(&self) -> MutexGuard<'_, HashMap<Box<str>, (T, SystemTime)>> {
+        self.entries.lock().expect("facts cache poisoned")
+    }
+}
+
+/// Routerboard facts of the devices, kept in the application context and passed to every
+/// request. Connections outside of requests, like the ones of rollouts and the monitor, read
+/// the facts from the device.
+#[derive(Debug, Clone)]
+pub struct DeviceFacts(Arc<FactsCache<SystemRouterboardState>>);
+
+impl DeviceFacts {
+    pub fn new(ttl: Duration) -> Self {
+        Self(Arc::new(FactsCache::new(ttl)))
+    }
+    /// facts of the request, none if the application does not cache them
+    pub fn of(ctx: &Context<'_>) -> Option<Self> {
+        ctx.data_opt::<DeviceFacts>().cloned()
+    }
+}
+
+impl AccessibleDevice {
+    /// Routerboard facts of the device, read from the device only if the cached ones expired.
+    /// They are looked up by the serial number documented in netbox, devices without one are
+    /// read every time.
+    pub async fn routerboard(&self) -> Result<Cached<SystemRouterboardState>, Error> {
+        let now = SystemTime::now();
+        let cached = self
+            .facts
+            .as_ref()
+            .zip(self.device_config.serial())
+            .and_then(|(facts, serial)| facts.0.get_at(serial, now));
+        if let Some(cached) = cached {
+            return Ok(cached);
+        }
+        let routerboard = SystemRouterboardState::fetch(&self.client)
+            .await?
+            .ok_or(SetupError::RouterboardNotDefined)?;
+        if let Some(facts) = &self.facts {
+            // kept by the serial read from the device, hardware replaced at the same address
+            // is found once its serial number is documented
+            let serial = routerboard.serial_number.to_string();
+            facts.0.insert_at(&serial, routerboard.clone(), now);
+        }
+        Ok(Cached {
+            value: routerboard,
+            age: Duration::ZERO,
+        })
+    }
+}
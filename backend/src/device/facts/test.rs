@@ -0,0 +1,26 @@
+use crate::device::facts::{Cached, FactsCache};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn test_facts_cache() {
+    let cache = FactsCache::new(Duration::from_secs(60));
+    let fetched = UNIX_EPOCH + Duration::from_secs(1000);
+    assert_eq!(None, cache.get_at("HCT0000001", fetched));
+
+    cache.insert_at("HCT0000001", "RB750Gr3", fetched);
+    assert_eq!(
+        Some(Cached {
+            value: "RB750Gr3",
+            age: Duration::from_secs(30),
+        }),
+        cache.get_at("HCT0000001", fetched + Duration::from_secs(30))
+    );
+    // hardware replaced at the same address has another serial number
+    assert_eq!(None, cache.get_at("HCT0000002", fetched));
+    // expired facts are read again
+    assert_eq!(
+        None,
+        cache.get_at("HCT0000001", fetched + Duration::from_secs(60))
+    );
+    assert_eq!(None, cache.get_at("HCT0000001", fetched));
+}
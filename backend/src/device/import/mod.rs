@@ -0,0 +1,393 @@
+use crate::{
+    Error,
+    device::{
+        AccessibleDevice,
+        command::{CommandReply, run_command},
+    },
+    topology::access::{
+        AccessTopology, device::DeviceAccess, interface::InterfaceAccess, vlan::VlanAccess,
+    },
+};
+use async_graphql::SimpleObject;
+use ipnet::IpNet;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cfg(test)]
+mod test;
+
+/// Interface of the device missing in netbox
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+pub struct ProposedInterface {
+    name: Box<str>,
+    /// netbox interface type like `1000base-t`, `bridge` or `virtual`
+    interface_type: Box<str>,
+    /// bridge the interface is a port of
+    bridge: Option<Box<str>>,
+}
+
+/// Static address of the device missing on the interface in netbox
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+pub struct ProposedIpAddress {
+    interface: Box<str>,
+    address: Box<str>,
+}
+
+/// Vlan carried by an interface of the device but not assigned to it in netbox
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+pub struct ProposedVlanAssignment {
+    interface: Box<str>,
+    vlan_id: u16,
+    tagged: bool,
+    /// netbox id of the vlan with this vlan id, none if there is none or more than one
+    netbox_vlan: Option<u32>,
+}
+
+/// NetBox objects documenting the configuration found on a device, for devices set up by hand
+/// before they were managed. Nothing is written to netbox.
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+pub struct DeviceImport {
+    interfaces: Box<[ProposedInterface]>,
+    ip_addresses: Box<[ProposedIpAddress]>,
+    vlan_assignments: Box<[ProposedVlanAssignment]>,
+}
+
+impl DeviceImport {
+    pub fn interfaces(&self) -> &[ProposedInterface] {
+        &self.interfaces
+    }
+    pub fn ip_addresses(&self) -> &[ProposedIpAddress] {
+        &self.ip_addresses
+    }
+    pub fn vlan_assignments(&self) -> &[ProposedVlanAssignment] {
+        &self.vlan_assignments
+    }
+}
+
+/// Kind of interface read from the device
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LiveInterfaceKind {
+    Ethernet { default_name: Box<str> },
+    Bridge,
+    Vlan,
+}
+
+/// Interface as configured on the device, with the vlans it carries on its bridge
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LiveInterface {
+    pub name: Box<str>,
+    pub kind: LiveInterfaceKind,
+    pub bridge: Option<Box<str>>,
+    pub untagged_vlan: Option<u16>,
+    pub tagged_vlans: BTreeSet<u16>,
+}
+
+/// Bridges, vlans and addresses read from the device
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LiveConfig {
+    pub interfaces: Vec<LiveInterface>,
+    /// static addresses by interface name
+    pub addresses: Vec<(Box<str>, IpNet)>,
+}
+
+impl LiveConfig {
+    /// Collects the configuration from the replies of the print commands. Vlans of bridge ports
+    /// are only taken from bridges with vlan filtering, the default pvid 1 is not a vlan
+    /// assignment. Dynamic and link local addresses are left out.
+    pub fn parse(
+        ethernet: &[CommandReply],
+        bridges: &[CommandReply],
+        bridge_ports: &[CommandReply],
+        bridge_vlans: &[CommandReply],
+        vlans: &[CommandReply],
+        addresses: &[CommandReply],
+    ) -> Self {
+        let mut interfaces = BTreeMap::<Box<str>, LiveInterface>::new();
+        let mut add = |name: &str, kind: LiveInterfaceKind| {
+            interfaces.insert(
+                name.into(),
+                LiveInterface {
+                    name: name.into(),
+                    kind,
+                    bridge: None,
+                    untagged_vlan: None,
+                    tagged_vlans: BTreeSet::new(),
+                },
+            );
+        };
+        for reply in ethernet {
+            if let (Some(name), Some(default_name)) = (reply.get("name"), reply.get("default-name"))
+            {
+                add(
+                    name.as_ref(),
+                    LiveInterfaceKind::Ethernet {
+                        default_name: default_name.clone(),
+                    },
+                );
+            }
+        }
+        let mut filtering = BTreeSet::new();
+        for reply in bridges {
+            if let Some(name) = reply.get("name") {
+                add(name.as_ref(), LiveInterfaceKind::Bridge);
+                if is_true(reply, "vlan-filtering") {
+                    filtering.insert(name.clone());
+                }
+            }
+        }
+        for reply in vlans {
+            if let Some(name) = reply.get("name") {
+                add(name.as_ref(), LiveInterfaceKind::Vlan);
+            }
+        }
+        for reply in vlans {
+            let (Some(name), Some(parent), Some(vlan_id)) = (
+                reply.get("name"),
+                reply.get("interface"),
+                reply.get("vlan-id").and_then(|id| id.parse().ok()),
+            ) else {
+                continue;
+            };
+            let parent_kind = interfaces.get(parent).map(|parent| parent.kind.clone());
+            if let Some(interface) = interfaces.get_mut(name) {
+                // a vlan interface of a bridge terminates the vlan on the bridge
+                if parent_kind == Some(LiveInterfaceKind::Bridge) {
+                    interface.bridge = Some(parent.clone());
+                    interface.untagged_vlan = Some(vlan_id);
+                }
+            }
+            if parent_kind != Some(LiveInterfaceKind::Bridge) {
+                if let Some(parent) = interfaces.get_mut(parent) {
+                    parent.tagged_vlans.insert(vlan_id);
+                }
+            }
+        }
+        for reply in bridge_ports {
+            let (Some(bridge), Some(port)) = (reply.get("bridge"), reply.get("interface")) else {
+                continue;
+            };
+            if let Some(interface) = interfaces.get_mut(port) {
+                interface.bridge = Some(bridge.clone());
+                if filtering.contains(bridge) {
+                    interface.untagged_vlan = reply
+                        .get("pvid")
+                        .and_then(|pvid| pvid.parse().ok())
+                        .filter(|pvid| *pvid != 1);
+                }
+            }
+        }
+        for reply in bridge_vlans {
+            if !reply.get("bridge").is_some_and(|b| filtering.contains(b)) {
+                continue;
+            }
+            let vlan_ids = reply
+                .get("vlan-ids")
+                .map(|ids| parse_vlan_ids(ids))
+                .unwrap_or_default();
+            // the bridge itself is tagged to reach the vlan interfaces on it
+            for port in list(reply, "tagged") {
+                if let Some(interface) = interfaces
+                    .get_mut(port)
+                    .filter(|interface| interface.kind != LiveInterfaceKind::Bridge)
+                {
+                    interface.tagged_vlans.extend(vlan_ids.iter().copied());
+                }
+            }
+        }
+        let addresses = addresses
+            .iter()
+            .filter(|reply| !is_true(reply, "dynamic"))
+            .filter_map(|reply| {
+                let address = reply.get("address")?.parse::<IpNet>().ok()?;
+                if matches!(address, IpNet::V6(net) if net.addr().is_unicast_link_local()) {
+                    return None;
+                }
+                Some((reply.get("interface")?.clone(), address))
+            })
+            .collect();
+        Self {
+            interfaces: interfaces.into_values().collect(),
+            addresses,
+        }
+    }
+}
+
+fn is_true(reply: &CommandReply, key: &str) -> bool {
+    reply.get(key).is_some_and(|value| value.as_ref() == "true")
+}
+
+fn list<'a>(reply: &'a CommandReply, key: &str) -> impl Iterator<Item = &'a str> {
+    reply
+        .get(key)
+        .into_iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// vlan ids like `10,20-22`
+fn parse_vlan_ids(ids: &str) -> Vec<u16> {
+    let mut result = Vec::new();
+    for part in ids.split(',').map(str::trim) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                if let (Ok(start), Ok(end)) = (start.parse::<u16>(), end.parse::<u16>()) {
+                    result.extend(start..=end);
+                }
+            }
+            None => result.extend(part.parse::<u16>().ok()),
+        }
+    }
+    result
+}
+
+/// netbox type of an ethernet port by its default name
+fn ethernet_type(default_name: &str) -> &'static str {
+    if default_name.starts_with("sfp-sfpplus") {
+        "10gbase-x-sfpp"
+    } else if default_name.starts_with("sfp") {
+        "1000base-x-sfp"
+    } else {
+        "1000base-t"
+    }
+}
+
+/// the netbox interface of a port by its default name, of other interfaces by the name
+fn find_interface<'a>(
+    netbox_interfaces: &'a [InterfaceAccess],
+    interface: &LiveInterface,
+) -> Option<&'a InterfaceAccess> {
+    netbox_interfaces
+        .iter()
+        .find(|netbox| match &interface.kind {
+            LiveInterfaceKind::Ethernet { default_name } => netbox
+                .external_port()
+                .and_then(|port| port.default_name())
+                .map_or(netbox.name() == interface.name.as_ref(), |name| {
+                    name.to_string() == default_name.as_ref()
+                }),
+            _ => netbox.name() == interface.name.as_ref(),
+        })
+}
+
+/// Proposes the interfaces, addresses and vlan assignments found on the device but missing in
+/// netbox. Ethernet ports are matched by their default name, other interfaces by name.
+pub fn propose_import(device: &DeviceAccess, live: &LiveConfig) -> DeviceImport {
+    let netbox_interfaces = device.interfaces();
+    let topology_vlans = device.topology().list_vlans().collect::<Vec<_>>();
+    let netbox_vlan = |vlan_id: u16| match topology_vlans
+        .iter()
+        .filter(|vlan| vlan.vlan_id() == Some(vlan_id))
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [vlan] => Some(vlan.id.0),
+        _ => None,
+    };
+    let mut interfaces = Vec::new();
+    let mut vlan_assignments = Vec::new();
+    for interface in &live.interfaces {
+        let netbox = find_interface(&netbox_interfaces, interface);
+        if netbox.is_none() {
+            interfaces.push(ProposedInterface {
+                name: interface.name.clone(),
+                interface_type: match &interface.kind {
+                    LiveInterfaceKind::Ethernet { default_name } => ethernet_type(default_name),
+                    LiveInterfaceKind::Bridge => "bridge",
+                    LiveInterfaceKind::Vlan => "virtual",
+                }
+                .into(),
+                bridge: interface.bridge.clone(),
+            });
+        }
+        let has_vlan = |vlan: Option<VlanAccess>, vlan_id: u16| {
+            vlan.and_then(|vlan| vlan.vlan_id()) == Some(vlan_id)
+        };
+        let assignments = interface
+            .untagged_vlan
+            .map(|vlan_id| (vlan_id, false))
+            .into_iter()
+            .chain(
+                interface
+                    .tagged_vlans
+                    .iter()
+                    .map(|vlan_id| (*vlan_id, true)),
+            );
+        for (vlan_id, tagged) in assignments {
+            let assigned = netbox.is_some_and(|netbox| {
+                if tagged {
+                    netbox
+                        .tagged_vlans()
+                        .any(|vlan| has_vlan(Some(vlan), vlan_id))
+                } else {
+                    has_vlan(netbox.untagged_vlan(), vlan_id)
+                }
+            });
+            if !assigned {
+                vlan_assignments.push(ProposedVlanAssignment {
+                    interface: interface.name.clone(),
+                    vlan_id,
+                    tagged,
+                    netbox_vlan: netbox_vlan(vlan_id),
+                });
+            }
+        }
+    }
+    let ip_addresses = live
+        .addresses
+        .iter()
+        .filter(|(name, address)| {
+            // a renamed port is found by its default name like in the interfaces above
+            let netbox = live
+                .interfaces
+                .iter()
+                .find(|interface| interface.name == *name)
+                .map_or_else(
+                    || {
+                        netbox_interfaces
+                            .iter()
+                            .find(|netbox| netbox.name() == name.as_ref())
+                    },
+                    |interface| find_interface(&netbox_interfaces, interface),
+                );
+            !netbox.is_some_and(|netbox| {
+                netbox
+                    .ips()
+                    .into_iter()
+                    .any(|ip| ip.net() == Some(*address))
+            })
+        })
+        .map(|(name, address)| ProposedIpAddress {
+            interface: name.clone(),
+            address: address.to_string().into(),
+        })
+        .collect();
+    DeviceImport {
+        interfaces: interfaces.into_boxed_slice(),
+        ip_addresses,
+        vlan_assignments: vlan_assignments.into_boxed_slice(),
+    }
+}
+
+impl AccessibleDevice {
+    /// Reads the bridges, vlans and addresses of the device and proposes the netbox objects
+    /// documenting them
+    pub async fn import_from_device(&self) -> Result<DeviceImport, Error> {
+        let print = |command: &'static str| run_command(&self.client, command, &[]);
+        let ethernet = print("/interface/ethernet/print").await?;
+        let bridges = print("/interface/bridge/print").await?;
+        let ports = print("/interface/bridge/port/print").await?;
+        let bridge_vlans = print("/interface/bridge/vlan/print").await?;
+        let vlans = print("/interface/vlan/print").await?;
+        let mut addresses = print("/ip/address/print").await?.into_vec();
+        addresses.extend(print("/ipv6/address/print").await?);
+        let live = LiveConfig::parse(
+            &ethernet,
+            &bridges,
+            &ports,
+            &bridge_vlans,
+            &vlans,
+            &addresses,
+        );
+        Ok(propose_import(&self.device_config, &live))
+    }
+}
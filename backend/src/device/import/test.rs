@@ -0,0 +1,157 @@
+use crate::{
+    device::{
+        command::CommandReply,
+        import::{
+            LiveConfig, ProposedInterface, ProposedIpAddress, ProposedVlanAssignment,
+            propose_import,
+        },
+    },
+    topology::{
+        DeviceId, TopologyHolder,
+        snapshot::{FixtureFormat, parse_topology},
+    },
+};
+
+fn replies(entries: &[&[(&str, &str)]]) -> Box<[CommandReply]> {
+    entries
+        .iter()
+        .map(|entry| {
+            entry
+                .iter()
+                .map(|(key, value)| (Box::from(*key), Box::from(*value)))
+                .collect()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_propose_import() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: router
+    ports:
+      - !Interface 1
+      - !Interface 2
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    external: !Ethernet 1
+    tagged_vlans: [1]
+    ips: [2]
+  2:
+    name: bridge
+    device: 1
+    ips: [1]
+vlan_groups:
+  1:
+    vlans: [1]
+vlans:
+  1:
+    name: mgmt
+    vlan_id: 10
+    group: 1
+    terminations: []
+    wlans: []
+ip_addresses:
+  1:
+    ip: 192.168.1.1/24
+    interface: 2
+  2:
+    ip: 10.1.1.1/24
+    interface: 1
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let live = LiveConfig::parse(
+        &replies(&[
+            &[("name", "ether1-uplink"), ("default-name", "ether1")],
+            &[("name", "ether2-office"), ("default-name", "ether2")],
+        ]),
+        &replies(&[&[("name", "bridge"), ("vlan-filtering", "true")]]),
+        &replies(&[
+            &[
+                ("bridge", "bridge"),
+                ("interface", "ether1-uplink"),
+                ("pvid", "1"),
+            ],
+            &[
+                ("bridge", "bridge"),
+                ("interface", "ether2-office"),
+                ("pvid", "20"),
+            ],
+        ]),
+        &replies(&[&[
+            ("bridge", "bridge"),
+            ("vlan-ids", "10,20"),
+            ("tagged", "bridge,ether1-uplink"),
+        ]]),
+        &replies(&[&[("name", "mgmt"), ("interface", "bridge"), ("vlan-id", "10")]]),
+        &replies(&[
+            &[("address", "192.168.10.1/24"), ("interface", "mgmt")],
+            &[("address", "192.168.1.1/24"), ("interface", "bridge")],
+            // documented on the port, which netbox still calls ether1
+            &[("address", "10.1.1.1/24"), ("interface", "ether1-uplink")],
+            &[
+                ("address", "10.0.0.5/24"),
+                ("interface", "ether1-uplink"),
+                ("dynamic", "true"),
+            ],
+            &[("address", "fe80::1/64"), ("interface", "ether1-uplink")],
+        ]),
+    );
+    let import = propose_import(&device, &live);
+    assert_eq!(
+        [
+            ProposedInterface {
+                name: "ether2-office".into(),
+                interface_type: "1000base-t".into(),
+                bridge: Some("bridge".into()),
+            },
+            ProposedInterface {
+                name: "mgmt".into(),
+                interface_type: "virtual".into(),
+                bridge: Some("bridge".into()),
+            },
+        ],
+        import.interfaces()
+    );
+    assert_eq!(
+        [ProposedIpAddress {
+            interface: "mgmt".into(),
+            address: "192.168.10.1/24".into(),
+        }],
+        import.ip_addresses()
+    );
+    // vlan 10 of ether1 is documented already, only vlan 10 is known in netbox
+    assert_eq!(
+        [
+            ProposedVlanAssignment {
+                interface: "ether1-uplink".into(),
+                vlan_id: 20,
+                tagged: true,
+                netbox_vlan: None,
+            },
+            ProposedVlanAssignment {
+                interface: "ether2-office".into(),
+                vlan_id: 20,
+                tagged: false,
+                netbox_vlan: None,
+            },
+            ProposedVlanAssignment {
+                interface: "mgmt".into(),
+                vlan_id: 10,
+                tagged: false,
+                netbox_vlan: Some(1),
+            },
+        ],
+        import.vlan_assignments()
+    );
+}
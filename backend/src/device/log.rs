@@ -0,0 +1,41 @@
+use crate::{
+    Error,
+    device::{AccessibleDevice, command::run_command},
+};
+use async_graphql::SimpleObject;
+
+#[derive(SimpleObject, Debug)]
+pub struct LogEntry {
+    time: Option<Box<str>>,
+    topics: Box<[Box<str>]>,
+    message: Option<Box<str>>,
+}
+
+impl AccessibleDevice {
+    /// Fetches the newest log entries, optionally restricted to entries having one of the topics
+    pub async fn fetch_logs(
+        &self,
+        limit: usize,
+        topics: &[&str],
+    ) -> Result<Box<[LogEntry]>, Error> {
+        let entries = run_command(&self.client, "/log/print", &[]).await?;
+        let mut result = entries
+            .into_iter()
+            .rev()
+            .map(|mut reply| LogEntry {
+                time: reply.remove("time"),
+                topics: reply
+                    .remove("topics")
+                    .map(|t| t.split(',').map(Box::from).collect())
+                    .unwrap_or_default(),
+                message: reply.remove("message"),
+            })
+            .filter(|entry| {
+                topics.is_empty() || entry.topics.iter().any(|t| topics.contains(&t.as_ref()))
+            })
+            .take(limit)
+            .collect::<Vec<_>>();
+        result.reverse();
+        Ok(result.into_boxed_slice())
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{
+    Error,
+    device::{AccessibleDevice, command::run_command},
+    topology::access::device::DeviceAccess,
+};
+use async_graphql::{Enum, SimpleObject};
+use std::collections::HashMap;
+
+#[cfg(test)]
+mod test;
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum MacStatus {
+    /// the port has the mac address documented in netbox
+    Ok,
+    /// the documented mac address belongs to another port of the device, e.g. swapped labels
+    Swapped,
+    /// the documented mac address is not on the device, e.g. replaced hardware
+    Mismatch,
+    /// no mac address documented in netbox
+    Undocumented,
+    /// the port documented in netbox does not exist on the device
+    Missing,
+}
+
+/// Mac address of an ethernet port in netbox compared with the device
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+pub struct MacCheck {
+    interface: Box<str>,
+    status: MacStatus,
+    documented: Option<Box<str>>,
+    seen: Option<Box<str>>,
+}
+
+impl MacCheck {
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+    pub fn status(&self) -> MacStatus {
+        self.status
+    }
+    /// hardware differs from netbox, an undocumented mac address is not a mismatch
+    pub fn is_mismatch(&self) -> bool {
+        matches!(self.status, MacStatus::Swapped | MacStatus::Mismatch)
+    }
+}
+
+/// Compares the mac addresses of the ethernet ports in netbox with the factory mac addresses seen
+/// on the device, keyed by the default name of the port
+pub fn compare_mac_addresses(
+    device: &DeviceAccess,
+    seen: &HashMap<Box<str>, Box<str>>,
+) -> Box<[MacCheck]> {
+    let mut result = Vec::new();
+    for interface in device.interfaces() {
+        let Some(default_name) = interface
+            .external_port()
+            .filter(|port| port.is_ethernet())
+            .and_then(|port| port.default_name())
+        else {
+            continue;
+        };
+        let default_name = Box::<str>::from(String::from_utf8_lossy(&default_name.0));
+        let documented = interface.mac_address().map(normalize);
+        let seen_mac = seen.get(&default_name).map(|mac| normalize(mac));
+        let status = match (&documented, &seen_mac) {
+            (_, None) => MacStatus::Missing,
+            (None, Some(_)) => MacStatus::Undocumented,
+            (Some(documented), Some(seen_mac)) if documented == seen_mac => MacStatus::Ok,
+            (Some(documented), Some(_)) => {
+                if seen.values().any(|mac| normalize(mac) == *documented) {
+                    MacStatus::Swapped
+                } else {
+                    MacStatus::Mismatch
+                }
+            }
+        };
+        result.push(MacCheck {
+            interface: default_name,
+            status,
+            documented,
+            seen: seen_mac,
+        });
+    }
+    result.into_boxed_slice()
+}
+
+/// upper case with colons, like RouterOS prints them
+fn normalize(mac: &str) -> Box<str> {
+    mac.trim().to_uppercase().replace('-', ":").into_boxed_str()
+}
+
+impl AccessibleDevice {
+    /// Compares the factory mac addresses of the ethernet ports with the mac addresses in netbox
+    pub async fn check_mac_addresses(&self) -> Result<Box<[MacCheck]>, Error> {
+        let seen = run_command(&self.client, "/interface/ethernet/print", &[])
+            .await?
+            .into_iter()
+            .filter_map(|mut reply| {
+                // the current mac address may be overwritten, the original one identifies the port
+                let mac = reply
+                    .remove("orig-mac-address")
+                    .or_else(|| reply.remove("mac-address"))?;
+                Some((reply.remove("default-name")?, mac))
+            })
+            .collect();
+        Ok(compare_mac_addresses(&self.device_config, &seen))
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{
+    device::mac::{MacStatus, compare_mac_addresses},
+    topology::{
+        CablePort, Device, Interface, PhysicalPortId, TopologyHolder, test::TopologyBuilder,
+    },
+};
+use std::collections::HashMap;
+
+#[tokio::test]
+async fn test_compare_mac_addresses() {
+    let mut builder = TopologyBuilder::default();
+    let device = builder.devices.next_id();
+    let documented = [
+        Some("48:a9:8a:00:00:01"),
+        Some("48:A9:8A:00:00:03"),
+        Some("48:A9:8A:00:00:99"),
+        None,
+        Some("48:A9:8A:00:00:05"),
+    ];
+    let mut ports = Vec::new();
+    for (idx, mac) in documented.into_iter().enumerate() {
+        let id = builder.interfaces.next_id();
+        ports.push(CablePort::Interface(id));
+        builder.interfaces.insert(
+            id,
+            Interface {
+                name: format!("ether{}", idx + 1).into(),
+                device,
+                external: Some(PhysicalPortId::Ethernet(idx as u16 + 1)),
+                mac_address: mac.map(Box::from),
+                ..Default::default()
+            },
+        );
+    }
+    builder.devices.insert(
+        device,
+        Device {
+            name: "switch".into(),
+            ports: ports.into_iter().collect(),
+            ..Default::default()
+        },
+    );
+    let device = TopologyHolder::offline(builder.build())
+        .devices_by_id(device)
+        .await
+        .expect("device not found");
+    let seen = HashMap::from(
+        [
+            ("ether1", "48:A9:8A:00:00:01"),
+            ("ether2", "48:A9:8A:00:00:02"),
+            ("ether3", "48:A9:8A:00:00:03"),
+            ("ether4", "48:A9:8A:00:00:04"),
+        ]
+        .map(|(port, mac)| (Box::from(port), Box::from(mac))),
+    );
+    let status = compare_mac_addresses(&device, &seen)
+        .iter()
+        .map(|check| (Box::from(check.interface()), check.status()))
+        .collect::<HashMap<Box<str>, _>>();
+    assert_eq!(
+        HashMap::from(
+            [
+                ("ether1", MacStatus::Ok),
+                ("ether2", MacStatus::Swapped),
+                ("ether3", MacStatus::Mismatch),
+                ("ether4", MacStatus::Undocumented),
+                ("ether5", MacStatus::Missing),
+            ]
+            .map(|(port, status)| (Box::from(port), status))
+        ),
+        status
+    );
+}
@@ -0,0 +1,386 @@
+use crate::{
+    Error,
+    config::CONFIG,
+    device::command::{CommandReply, run_command},
+    provisioning::lock::lock_device,
+    topology::{DeviceId, PhysicalPortId, TopologyInstances, access::device::DeviceAccess},
+};
+use async_graphql::SimpleObject;
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use mikrotik_model::MikrotikDevice;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(test)]
+mod test;
+
+/// Mirrors are reverted after this time at the latest
+pub const MAX_MIRROR_DURATION: Duration = Duration::from_secs(4 * 60 * 60);
+/// Attempts to revert a mirror after its time, the device may be unreachable meanwhile
+const REVERT_ATTEMPTS: u32 = 5;
+const REVERT_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Key of a device, the ids are only unique within a netbox instance
+type MirrorKey = (Box<str>, DeviceId);
+
+/// Mirror settings of the switch chip before the mirror was enabled
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SwitchMirror {
+    id: Box<str>,
+    source: Box<str>,
+    target: Box<str>,
+}
+
+/// Traffic of a port copied to another port of the same switch chip, e.g. to capture it with a
+/// laptop on the target port
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct PortMirror {
+    device_id: u32,
+    /// port whose traffic is copied, named as in netbox
+    source_port: Box<str>,
+    /// port receiving the copy
+    target_port: Box<str>,
+    /// seconds since the unix epoch the mirror is reverted
+    reverts_at: u64,
+    #[graphql(skip)]
+    id: u64,
+    #[graphql(skip)]
+    previous: SwitchMirror,
+}
+
+/// Port mirrors currently enabled. The mirror is not part of the generated configuration, the
+/// drift check skips the devices while it is enabled.
+///
+/// The mirrors are written to the file of `port_mirror_file` on every change, the next start
+/// takes them over with [`revert_persisted_mirrors`]. Without the file they are kept in memory
+/// only and stay on the switches if the provisioner stops before their time.
+#[derive(Default)]
+pub struct PortMirrors {
+    state: Mutex<MirrorState>,
+    file: Option<PathBuf>,
+}
+
+/// Entry of the mirror file
+#[derive(Serialize, Deserialize)]
+struct PersistedMirror {
+    instance: Box<str>,
+    mirror: PortMirror,
+}
+
+#[derive(Default)]
+struct MirrorState {
+    next_id: u64,
+    mirrors: HashMap<MirrorKey, PortMirror>,
+}
+
+impl PortMirrors {
+    /// mirrors persisted to the file, if set
+    pub fn persisted(file: Option<PathBuf>) -> Self {
+        Self {
+            state: Mutex::default(),
+            file,
+        }
+    }
+    pub fn is_mirrored(&self, instance: &str, device: DeviceId) -> bool {
+        self.lock().mirrors.contains_key(&(instance.into(), device))
+    }
+    /// the switch settings before the first mirror of the device, a replaced mirror is not
+    /// restored. Only of the given mirror if an id is set.
+    fn previous(&self, instance: &str, device: DeviceId, id: Option<u64>) -> Option<SwitchMirror> {
+        self.lock()
+            .mirrors
+            .get(&(instance.into(), device))
+            .filter(|mirror| id.is_none_or(|id| id == mirror.id))
+            .map(|mirror| mirror.previous.clone())
+    }
+    fn insert(&self, instance: &str, mut mirror: PortMirror) -> PortMirror {
+        let mut state = self.lock();
+        state.next_id += 1;
+        mirror.id = state.next_id;
+        state.mirrors.insert(
+            (instance.into(), DeviceId(mirror.device_id)),
+            mirror.clone(),
+        );
+        self.save(&state);
+        mirror
+    }
+    /// removes the mirror of the device, only the given one if an id is set
+    fn remove(&self, instance: &str, device: DeviceId, id: Option<u64>) -> Option<PortMirror> {
+        let mut state = self.lock();
+        let key = (instance.into(), device);
+        let removed = match state.mirrors.get(&key) {
+            Some(mirror) if id.is_none_or(|id| id == mirror.id) => state.mirrors.remove(&key),
+            _ => None,
+        };
+        if removed.is_some() {
+            self.save(&state);
+        }
+        removed
+    }
+    fn lock(&self) -> MutexGuard<'_, MirrorState> {
+        self.state.lock().expect("port mirrors poisoned")
+    }
+    /// writes the mirrors to a temporary file first, a crash never leaves a partial file
+    fn save(&self, state: &MirrorState) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let mirrors = state
+            .mirrors
+            .iter()
+            .map(|((instance, _), mirror)| PersistedMirror {
+                instance: instance.clone(),
+                mirror: mirror.clone(),
+            })
+            .collect::<Vec<_>>();
+        let temp_file = file.with_extension("tmp");
+        let written = serde_json::to_vec(&mirrors)
+            .map_err(std::io::Error::from)
+            .and_then(|data| std::fs::write(&temp_file, data))
+            .and_then(|()| std::fs::rename(&temp_file, file));
+        if let Err(e) = written {
+            error!("Cannot persist port mirrors to {}: {e}", file.display());
+        }
+    }
+    /// the mirrors of the file, none if it does not exist yet
+    fn load(&self) -> Vec<(Box<str>, PortMirror)> {
+        let Some(file) = &self.file else {
+            return Vec::new();
+        };
+        let data = match std::fs::read(file) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Vec::new(),
+            Err(e) => {
+                error!("Cannot read port mirrors from {}: {e}", file.display());
+                return Vec::new();
+            }
+        };
+        match serde_json::from_slice::<Vec<PersistedMirror>>(&data) {
+            Ok(mirrors) => mirrors
+                .into_iter()
+                .map(|persisted| (persisted.instance, persisted.mirror))
+                .collect(),
+            Err(e) => {
+                error!("Invalid port mirrors in {}: {e}", file.display());
+                Vec::new()
+            }
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref PORT_MIRRORS: PortMirrors =
+        PortMirrors::persisted(CONFIG.load().port_mirror_file().map(Path::to_path_buf));
+}
+
+/// Takes over the mirrors persisted before a restart and reverts them at their time, a mirror
+/// whose time passed meanwhile is reverted right away. Call once at startup.
+pub async fn revert_persisted_mirrors(topology: &TopologyInstances) {
+    for (instance, mirror) in PORT_MIRRORS.load() {
+        let device = match topology.get(Some(instance.as_ref())) {
+            Some(holder) => holder.devices_by_id(DeviceId(mirror.device_id)).await,
+            None => None,
+        };
+        let Some(device) = device else {
+            error!(
+                "Device {} of the port mirror of {instance} not found, restore mirror-source={} \
+                 mirror-target={} by hand",
+                mirror.device_id, mirror.previous.source, mirror.previous.target
+            );
+            continue;
+        };
+        let remaining = Duration::from_secs(
+            mirror
+                .reverts_at
+                .saturating_sub(unix_seconds(SystemTime::now())),
+        );
+        let mirror = PORT_MIRRORS.insert(&instance, mirror);
+        info!(
+            "Reverting port mirror on {} in {remaining:?}",
+            device.name()
+        );
+        tokio::spawn(revert_after(instance, device, mirror.id, remaining));
+    }
+}
+
+/// Mirrors the traffic of `source` to `target` on the switch chip of the device, both given by
+/// their netbox name. Enabling a mirror again replaces the ports and the time. The switch
+/// settings are restored after `duration`, limited to [`MAX_MIRROR_DURATION`].
+pub async fn enable_port_mirror(
+    instance: &str,
+    device: &DeviceAccess,
+    source: &str,
+    target: &str,
+    duration: Duration,
+) -> Result<PortMirror, Error> {
+    if source == target {
+        return Err(Error::InvalidPortMirror(
+            format!("{source} onto itself").into(),
+        ));
+    }
+    let source_port = physical_port(device, source)?;
+    let target_port = physical_port(device, target)?;
+    let duration = duration.clamp(Duration::from_secs(1), MAX_MIRROR_DURATION);
+    let client = device.connect(None, None).await?;
+    let previous = match PORT_MIRRORS.previous(instance, device.id(), None) {
+        Some(previous) => previous,
+        None => switch_mirror(client.client(), device.name()).await?,
+    };
+    let source_name = interface_name(client.client(), source_port).await?;
+    let target_name = interface_name(client.client(), target_port).await?;
+    set_switch_mirror(client.client(), &previous.id, &source_name, &target_name).await?;
+    let mirror = PORT_MIRRORS.insert(
+        instance,
+        PortMirror {
+            device_id: device.id().0,
+            source_port: source.into(),
+            target_port: target.into(),
+            reverts_at: unix_seconds(SystemTime::now() + duration),
+            id: 0,
+            previous,
+        },
+    );
+    info!(
+        "Mirroring {source} to {target} on {} for {duration:?}",
+        device.name()
+    );
+    tokio::spawn(revert_after(
+        instance.into(),
+        device.clone(),
+        mirror.id,
+        duration,
+    ));
+    Ok(mirror)
+}
+
+/// Restores the switch settings of the device before its mirror, returns false if there was
+/// no mirror. The mirror is kept if the device cannot be reached.
+pub async fn disable_port_mirror(instance: &str, device: &DeviceAccess) -> Result<bool, Error> {
+    revert(instance, device, None).await
+}
+
+async fn revert_after(instance: Box<str>, device: DeviceAccess, id: u64, duration: Duration) {
+    tokio::time::sleep(duration).await;
+    for attempt in 1..=REVERT_ATTEMPTS {
+        // a plan applied meanwhile holds the device, the revert is tried again later
+        let result = match lock_device(&instance, device.id()) {
+            Ok(_lock) => revert(&instance, &device, Some(id)).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(_) => return,
+            Err(e) => warn!(
+                "Cannot revert port mirror on {} (attempt {attempt}): {e}",
+                device.name()
+            ),
+        }
+        tokio::time::sleep(REVERT_RETRY_DELAY).await;
+    }
+    // the device is checked for drift again, the mirror stays until it is reverted by hand
+    if PORT_MIRRORS
+        .remove(&instance, device.id(), Some(id))
+        .is_some()
+    {
+        error!("Port mirror on {} stays enabled", device.name());
+    }
+}
+
+async fn revert(instance: &str, device: &DeviceAccess, id: Option<u64>) -> Result<bool, Error> {
+    // a mirror replaced meanwhile is reverted by its own timer
+    let Some(previous) = PORT_MIRRORS.previous(instance, device.id(), id) else {
+        return Ok(false);
+    };
+    let client = device.connect(None, None).await?;
+    set_switch_mirror(
+        client.client(),
+        &previous.id,
+        &previous.source,
+        &previous.target,
+    )
+    .await?;
+    let removed = PORT_MIRRORS.remove(instance, device.id(), id).is_some();
+    if removed {
+        info!("Reverted port mirror on {}", device.name());
+    }
+    Ok(removed)
+}
+
+/// ethernet port of the netbox interface, only physical ports can be mirrored
+fn physical_port(device: &DeviceAccess, name: &str) -> Result<PhysicalPortId, Error> {
+    let interface = device
+        .interfaces()
+        .into_iter()
+        .find(|interface| interface.name() == name)
+        .ok_or_else(|| {
+            Error::InvalidPortMirror(format!("port {name} not found on {}", device.name()).into())
+        })?;
+    match interface.external_port() {
+        Some(port @ (PhysicalPortId::Ethernet(_) | PhysicalPortId::SfpSfpPlus(_))) => Ok(port),
+        _ => Err(Error::InvalidPortMirror(
+            format!("{name}, it is no ethernet port").into(),
+        )),
+    }
+}
+
+/// current name of the ethernet port, the ports are renamed by the provisioning
+async fn interface_name(client: &MikrotikDevice, port: PhysicalPortId) -> Result<Box<str>, Error> {
+    let default_name = port.to_string();
+    run_command(client, "/interface/ethernet/print", &[])
+        .await?
+        .into_iter()
+        .find(|reply| reply.get("default-name").map(Box::as_ref) == Some(default_name.as_str()))
+        .and_then(|mut reply| reply.remove("name"))
+        .ok_or_else(|| Error::InvalidPortMirror(format!("{default_name} not on the device").into()))
+}
+
+/// mirror settings of the first switch chip, devices with several chips only mirror on the first
+async fn switch_mirror(client: &MikrotikDevice, device: &str) -> Result<SwitchMirror, Error> {
+    let replies = run_command(client, "/interface/ethernet/switch/print", &[]).await?;
+    let switch = replies.into_iter().next().ok_or_else(|| {
+        Error::InvalidPortMirror(format!("on {device}, it has no switch chip").into())
+    })?;
+    Ok(SwitchMirror {
+        id: attribute(&switch, ".id"),
+        source: attribute(&switch, "mirror-source"),
+        target: attribute(&switch, "mirror-target"),
+    })
+}
+
+fn attribute(reply: &CommandReply, name: &str) -> Box<str> {
+    reply
+        .get(name)
+        .cloned()
+        .unwrap_or_else(|| Box::from("none"))
+}
+
+async fn set_switch_mirror(
+    client: &MikrotikDevice,
+    id: &str,
+    source: &str,
+    target: &str,
+) -> Result<(), Error> {
+    run_command(
+        client,
+        "/interface/ethernet/switch/set",
+        &[
+            (".id", id),
+            ("mirror-source", source),
+            ("mirror-target", target),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
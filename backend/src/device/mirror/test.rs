@@ -0,0 +1,124 @@
+use crate::{
+    device::{
+        mirror::{
+            PortMirror, PortMirrors, SwitchMirror, interface_name, set_switch_mirror, switch_mirror,
+        },
+        mock::MockDevice,
+    },
+    topology::{DeviceId, PhysicalPortId},
+};
+
+fn mirror(source: &str) -> PortMirror {
+    PortMirror {
+        device_id: 1,
+        source_port: source.into(),
+        target_port: "ether5".into(),
+        reverts_at: 0,
+        id: 0,
+        previous: SwitchMirror {
+            id: "*1".into(),
+            source: "none".into(),
+            target: "none".into(),
+        },
+    }
+}
+
+#[test]
+fn test_replaced_mirror() {
+    let mirrors = PortMirrors::default();
+    let first = mirrors.insert("default", mirror("ether2"));
+    assert!(mirrors.is_mirrored("default", DeviceId(1)));
+    assert!(!mirrors.is_mirrored("other", DeviceId(1)));
+    let second = mirrors.insert("default", mirror("ether3"));
+    // the timer of the replaced mirror must not revert the new one
+    assert!(
+        mirrors
+            .previous("default", DeviceId(1), Some(first.id))
+            .is_none()
+    );
+    assert!(
+        mirrors
+            .remove("default", DeviceId(1), Some(first.id))
+            .is_none()
+    );
+    assert!(
+        mirrors
+            .previous("default", DeviceId(1), Some(second.id))
+            .is_some()
+    );
+    assert!(mirrors.remove("default", DeviceId(1), None).is_some());
+    assert!(!mirrors.is_mirrored("default", DeviceId(1)));
+}
+
+#[test]
+fn test_persisted_mirrors() {
+    let file = std::env::temp_dir().join("provisioner-port-mirrors.json");
+    let _ = std::fs::remove_file(&file);
+    let mirrors = PortMirrors::persisted(Some(file.clone()));
+    assert!(mirrors.load().is_empty());
+    let enabled = mirrors.insert("default", mirror("ether2"));
+    // a restarted provisioner finds the mirror with the settings to restore
+    let restarted = PortMirrors::persisted(Some(file.clone()));
+    match restarted.load().as_slice() {
+        [(instance, loaded)] => {
+            assert_eq!("default", instance.as_ref());
+            assert_eq!(enabled.source_port, loaded.source_port);
+            assert_eq!(enabled.previous, loaded.previous);
+        }
+        loaded => panic!("unexpected mirrors {loaded:?}"),
+    }
+    mirrors.remove("default", DeviceId(1), None);
+    assert!(restarted.load().is_empty());
+}
+
+#[tokio::test]
+async fn test_switch_mirror() {
+    let mock = MockDevice::start().await.expect("cannot start mock");
+    mock.set_resource(
+        "interface/ethernet",
+        [
+            [("default-name", "ether2"), ("name", "office")].as_slice(),
+            [("default-name", "ether5"), ("name", "ether5")].as_slice(),
+        ],
+    )
+    .await;
+    mock.set_resource(
+        "interface/ethernet/switch",
+        [[(".id", "*1"), ("name", "switch1")].as_slice()],
+    )
+    .await;
+    let client = mock.connect().await.expect("cannot connect to mock");
+    assert_eq!(
+        interface_name(&client, PhysicalPortId::Ethernet(2))
+            .await
+            .expect("port not found")
+            .as_ref(),
+        "office"
+    );
+    assert!(
+        interface_name(&client, PhysicalPortId::Ethernet(3))
+            .await
+            .is_err()
+    );
+    let previous = switch_mirror(&client, "hex").await.expect("no switch chip");
+    assert_eq!(
+        previous,
+        SwitchMirror {
+            id: "*1".into(),
+            source: "none".into(),
+            target: "none".into(),
+        }
+    );
+    set_switch_mirror(&client, &previous.id, "office", "ether5")
+        .await
+        .expect("set failed");
+    let switch = mock.resource("interface/ethernet/switch").await;
+    assert_eq!(
+        switch[0].get("mirror-source").map(Box::as_ref),
+        Some("office")
+    );
+    assert_eq!(
+        switch[0].get("mirror-target").map(Box::as_ref),
+        Some("ether5")
+    );
+}
@@ -0,0 +1,320 @@
+use crate::{Error, device::AccessibleDevice, topology::access::device::DeviceAccess};
+use mikrotik_model::MikrotikDevice;
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+    task::JoinHandle,
+};
+
+/// Attributes of a resource entry or a command, without the leading `=`
+pub type MockEntry = BTreeMap<Box<str>, Box<str>>;
+
+/// Command received by the mock, prints are not recorded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockCommand {
+    pub command: Box<str>,
+    pub attributes: MockEntry,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    resources: BTreeMap<Box<str>, Vec<MockEntry>>,
+    commands: Vec<MockCommand>,
+    next_id: u32,
+}
+
+/// In-process RouterOS api endpoint serving canned resources per path.
+///
+/// `print` returns the entries of the path, `add`, `set` and `remove` modify them, so the
+/// state after applying mutations can be fetched again.
+pub struct MockDevice {
+    address: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    server: JoinHandle<()>,
+}
+
+impl Drop for MockDevice {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+impl MockDevice {
+    pub async fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let server = tokio::spawn({
+            let state = state.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(serve(stream, state.clone()));
+                }
+            }
+        });
+        Ok(Self {
+            address,
+            state,
+            server,
+        })
+    }
+
+    /// replaces the entries of a path, e.g. `interface/ethernet`
+    pub async fn set_resource<'a>(
+        &self,
+        path: &str,
+        entries: impl IntoIterator<Item = &'a [(&'a str, &'a str)]>,
+    ) {
+        let mut state = self.state.lock().await;
+        let mut values = Vec::new();
+        for entry in entries {
+            let mut entry = entry
+                .iter()
+                .map(|(key, value)| (Box::from(*key), Box::from(*value)))
+                .collect::<MockEntry>();
+            if !entry.contains_key(".id") {
+                entry.insert(".id".into(), state.create_id());
+            }
+            values.push(entry);
+        }
+        state.resources.insert(normalize_path(path), values);
+    }
+
+    pub async fn resource(&self, path: &str) -> Vec<MockEntry> {
+        self.state
+            .lock()
+            .await
+            .resources
+            .get(&normalize_path(path))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// all commands except prints in the order they were received
+    pub async fn commands(&self) -> Vec<MockCommand> {
+        self.state.lock().await.commands.clone()
+    }
+
+    /// port the mock listens on, at the address of [`MockDevice::accessible`]
+    pub fn port(&self) -> u16 {
+        self.address.port()
+    }
+
+    pub async fn connect(&self) -> Result<MikrotikDevice, Error> {
+        Ok(MikrotikDevice::connect(self.address, b"admin", None).await?)
+    }
+
+    /// an accessible device of the topology connected to the mock
+    pub async fn accessible(&self, device: DeviceAccess) -> Result<AccessibleDevice, Error> {
+        Ok(AccessibleDevice::from_client(
+            device,
+            IpAddr::from(Ipv4Addr::LOCALHOST),
+            self.connect().await?,
+        ))
+    }
+}
+
+impl MockState {
+    fn create_id(&mut self) -> Box<str> {
+        self.next_id += 1;
+        format!("*{:X}", self.next_id).into()
+    }
+
+    /// returns the replies of a command, the last reply is `!done` or `!trap`
+    fn execute(
+        &mut self,
+        command: &str,
+        attributes: MockEntry,
+        queries: &MockEntry,
+    ) -> Vec<Sentence> {
+        if command == "/login" || command == "/cancel" {
+            return vec![reply("!done", [])];
+        }
+        let Some((path, action)) = command.rsplit_once('/') else {
+            return vec![trap("no such command")];
+        };
+        let path = normalize_path(path);
+        if action != "print" && action != "getall" {
+            self.commands.push(MockCommand {
+                command: command.into(),
+                attributes: attributes.clone(),
+            });
+        }
+        match action {
+            "print" | "getall" => {
+                let mut replies = self
+                    .resources
+                    .get(&path)
+                    .into_iter()
+                    .flatten()
+                    .filter(|entry| {
+                        queries
+                            .iter()
+                            .all(|(key, value)| entry.get(key) == Some(value))
+                    })
+                    .map(|entry| {
+                        reply(
+                            "!re",
+                            entry.iter().map(|(key, value)| format!("={key}={value}")),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                replies.push(reply("!done", []));
+                replies
+            }
+            "add" => {
+                let id = self.create_id();
+                let mut entry = attributes;
+                entry.insert(".id".into(), id.clone());
+                self.resources.entry(path).or_default().push(entry);
+                vec![reply("!done", [format!("=ret={id}")])]
+            }
+            "set" => {
+                let entries = self.resources.entry(path).or_default();
+                let entry = match attributes.get(".id") {
+                    Some(id) => entries.iter_mut().find(|e| e.get(".id") == Some(id)),
+                    // single resources like system/identity have no id
+                    None => entries.first_mut(),
+                };
+                match entry {
+                    Some(entry) => {
+                        entry.extend(attributes);
+                        vec![reply("!done", [])]
+                    }
+                    None => vec![trap("no such item")],
+                }
+            }
+            "remove" => {
+                let entries = self.resources.entry(path).or_default();
+                let count = entries.len();
+                entries.retain(|e| e.get(".id") != attributes.get(".id"));
+                if entries.len() < count {
+                    vec![reply("!done", [])]
+                } else {
+                    vec![trap("no such item")]
+                }
+            }
+            _ => vec![trap("no such command")],
+        }
+    }
+}
+
+fn normalize_path(path: &str) -> Box<str> {
+    path.trim_matches('/').into()
+}
+
+type Sentence = Vec<String>;
+
+fn reply(kind: &str, words: impl IntoIterator<Item = String>) -> Sentence {
+    let mut sentence = vec![kind.to_string()];
+    sentence.extend(words);
+    sentence
+}
+
+fn trap(message: &str) -> Sentence {
+    reply("!trap", [format!("=message={message}")])
+}
+
+async fn serve(mut stream: TcpStream, state: Arc<Mutex<MockState>>) -> io::Result<()> {
+    loop {
+        let sentence = read_sentence(&mut stream).await?;
+        let Some(command) = sentence.first() else {
+            continue;
+        };
+        let mut tag = None;
+        let mut attributes = MockEntry::new();
+        let mut queries = MockEntry::new();
+        for word in &sentence[1..] {
+            if let Some(value) = word.strip_prefix(".tag=") {
+                tag = Some(value.to_string());
+            } else if let Some((key, value)) =
+                word.strip_prefix('=').and_then(|w| w.split_once('='))
+            {
+                attributes.insert(key.into(), value.into());
+            } else if let Some((key, value)) =
+                word.strip_prefix('?').and_then(|w| w.split_once('='))
+            {
+                queries.insert(key.into(), value.into());
+            }
+        }
+        // only the properties of the entries are relevant
+        attributes.remove(".proplist");
+        let replies = state.lock().await.execute(command, attributes, &queries);
+        let is_trap = replies.last().map(|r| r[0] == "!trap").unwrap_or(false);
+        for mut sentence in replies {
+            if let Some(tag) = &tag {
+                sentence.push(format!(".tag={tag}"));
+            }
+            write_sentence(&mut stream, &sentence).await?;
+        }
+        if is_trap {
+            let mut done = reply("!done", []);
+            if let Some(tag) = &tag {
+                done.push(format!(".tag={tag}"));
+            }
+            write_sentence(&mut stream, &done).await?;
+        }
+    }
+}
+
+async fn read_sentence(stream: &mut TcpStream) -> io::Result<Sentence> {
+    let mut sentence = Vec::new();
+    loop {
+        let length = read_length(stream).await?;
+        if length == 0 {
+            return Ok(sentence);
+        }
+        let mut word = vec![0; length];
+        stream.read_exact(&mut word).await?;
+        sentence.push(String::from_utf8_lossy(&word).into_owned());
+    }
+}
+
+async fn write_sentence(stream: &mut TcpStream, sentence: &[String]) -> io::Result<()> {
+    let mut data = Vec::new();
+    for word in sentence {
+        encode_length(&mut data, word.len());
+        data.extend_from_slice(word.as_bytes());
+    }
+    data.push(0);
+    stream.write_all(&data).await
+}
+
+/// word length encoding of the RouterOS api
+async fn read_length(stream: &mut TcpStream) -> io::Result<usize> {
+    let first = stream.read_u8().await? as usize;
+    let (mut length, following) = match first {
+        0x00..=0x7F => (first, 0),
+        0x80..=0xBF => (first & 0x3F, 1),
+        0xC0..=0xDF => (first & 0x1F, 2),
+        0xE0..=0xEF => (first & 0x0F, 3),
+        _ => (0, 4),
+    };
+    for _ in 0..following {
+        length = (length << 8) | stream.read_u8().await? as usize;
+    }
+    Ok(length)
+}
+
+fn encode_length(data: &mut Vec<u8>, length: usize) {
+    let bytes = (length as u32).to_be_bytes();
+    match length {
+        0..0x80 => data.push(bytes[3]),
+        0x80..0x4000 => data.extend_from_slice(&[bytes[2] | 0x80, bytes[3]]),
+        0x4000..0x20_0000 => data.extend_from_slice(&[bytes[1] | 0xC0, bytes[2], bytes[3]]),
+        0x20_0000..0x1000_0000 => {
+            data.extend_from_slice(&[bytes[0] | 0xE0, bytes[1], bytes[2], bytes[3]])
+        }
+        _ => {
+            data.push(0xF0);
+            data.extend_from_slice(&bytes);
+        }
+    }
+}
@@ -1,11 +1,31 @@
 use crate::{
-    Error, config::CONFIG, graphql::scalars::ScalarDuration, topology::access::device::DeviceAccess,
+    Error,
+    config::CONFIG,
+    graphql::scalars::ScalarDuration,
+    topology::access::{AccessTopology, device::DeviceAccess},
 };
 use async_graphql::{ComplexObject, Object, SimpleObject};
+use facts::DeviceFacts;
 use mikrotik_model::{MikrotikDevice, model::SystemRouterboardState};
-use std::{net::IpAddr, time::Duration};
-use surge_ping::{IcmpPacket, SurgeError, ping};
+use reachability::{PingError, PingMethod};
+use std::{collections::BTreeMap, net::IpAddr, sync::Arc, time::Duration};
+use tokio::{net::TcpStream, sync::OwnedSemaphorePermit, time::Instant};
 
+pub mod availability;
+pub mod backoff;
+pub mod bandwidth_test;
+pub mod cabling;
+pub mod certificate;
+pub mod command;
+pub mod dhcp;
+pub mod facts;
+pub mod import;
+pub mod log;
+pub mod mac;
+pub mod mirror;
+#[cfg(test)]
+pub mod mock;
+pub mod reachability;
 pub mod ros;
 #[derive(Debug, Clone, PartialEq, Hash, Eq)]
 pub enum Credentials {
@@ -15,18 +35,43 @@ pub enum Credentials {
         password: Option<Box<str>>,
     },
 }
+impl Credentials {
+    /// user and password, named credentials from the current settings
+    pub fn resolve(&self) -> Result<(Box<str>, Option<Box<str>>), Error> {
+        Ok(match self {
+            Credentials::Named(name) => {
+                let settings = CONFIG.load();
+                let c = settings
+                    .mikrotik_credentials
+                    .get(name.as_ref())
+                    .ok_or(Error::MissingCredentials)?;
+                (c.user().into(), c.password().map(Box::from))
+            }
+            Credentials::Adhoc { username, password } => (
+                username.clone().unwrap_or_else(|| "admin".into()),
+                password.clone(),
+            ),
+        })
+    }
+}
 pub struct AccessibleDevice {
     address: IpAddr,
     client: MikrotikDevice,
     device_config: DeviceAccess,
+    /// connection slot of the device in the graphql request, given back when the last connection
+    /// to the device is closed
+    permit: Option<Arc<OwnedSemaphorePermit>>,
+    /// cached routerboard facts of the application, none outside of requests
+    facts: Option<DeviceFacts>,
 }
 
-#[derive(SimpleObject)]
+#[derive(SimpleObject, Clone, Debug)]
 #[graphql(complex)]
 pub struct PingResult {
     ttl: Option<u8>,
     #[graphql(skip)]
     duration: Duration,
+    method: PingMethod,
 }
 #[ComplexObject]
 impl PingResult {
@@ -34,6 +79,29 @@ impl PingResult {
         self.duration.into()
     }
 }
+#[derive(SimpleObject)]
+pub struct TracerouteHop {
+    hop: u32,
+    address: Option<Box<str>>,
+    loss: Option<Box<str>>,
+    last: Option<Box<str>>,
+    avg: Option<Box<str>>,
+    status: Option<Box<str>>,
+}
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct TcpCheckResult {
+    open: bool,
+    error: Option<String>,
+    #[graphql(skip)]
+    duration: Duration,
+}
+#[ComplexObject]
+impl TcpCheckResult {
+    async fn duration(&self) -> ScalarDuration {
+        self.duration.into()
+    }
+}
 
 impl AccessibleDevice {
     pub async fn create_client(
@@ -42,46 +110,127 @@ impl AccessibleDevice {
         credentials: Credentials,
     ) -> Result<AccessibleDevice, Error> {
         Ok({
-            let (username, password) = match &credentials {
-                Credentials::Named(name) => {
-                    let c = CONFIG
-                        .mikrotik_credentials
-                        .get(name.as_ref())
-                        .ok_or(Error::MissingCredentials)?;
-                    (c.user(), c.password())
-                }
-                Credentials::Adhoc { username, password } => (
-                    username.as_ref().map(Box::as_ref).unwrap_or("admin"),
-                    password.as_ref().map(Box::as_ref),
-                ),
-            };
+            let (username, password) = credentials.resolve()?;
             let mikrotik_device = MikrotikDevice::connect(
                 (address, 8728),
                 username.as_bytes(),
-                password.map(|p| p.as_bytes()),
+                password.as_deref().map(str::as_bytes),
             )
             .await?;
-            AccessibleDevice {
-                address,
-                client: mikrotik_device,
-                device_config,
-            }
+            Self::from_client(device_config, address, mikrotik_device)
         })
     }
 
-    pub async fn simple_ping(&self, count: u8) -> Result<Box<[PingResult]>, SurgeError> {
+    /// wraps an already connected client
+    pub(crate) fn from_client(
+        device_config: DeviceAccess,
+        address: IpAddr,
+        client: MikrotikDevice,
+    ) -> AccessibleDevice {
+        AccessibleDevice {
+            address,
+            client,
+            device_config,
+            permit: None,
+            facts: None,
+        }
+    }
+    /// keeps the connection slot of a request until the connection is closed
+    pub fn with_permit(self, permit: Option<Arc<OwnedSemaphorePermit>>) -> Self {
+        Self { permit, ..self }
+    }
+    /// reads the routerboard facts from the cache of the application
+    pub fn with_facts(self, facts: Option<DeviceFacts>) -> Self {
+        Self { facts, ..self }
+    }
+
+    /// the address the device is connected on
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+    pub fn client(&self) -> &MikrotikDevice {
+        &self.client
+    }
+    pub fn device_config(&self) -> &DeviceAccess {
+        &self.device_config
+    }
+
+    pub async fn simple_ping(&self, count: u8) -> Result<Box<[PingResult]>, PingError> {
         let mut result = Vec::new();
         for _i in 0..count {
-            let (packet, duration) = ping(self.address, &[]).await?;
-            let ttl = match packet {
-                IcmpPacket::V4(v4) => v4.get_ttl(),
-                IcmpPacket::V6(v6) => Some(v6.get_max_hop_limit()),
-            };
-            result.push(PingResult { ttl, duration })
+            result.push(reachability::limited_ping(self.address).await?)
         }
         Ok(result.into_boxed_slice())
     }
+
+    pub async fn run_traceroute(&self, target: IpAddr) -> Result<Box<[TracerouteHop]>, Error> {
+        let replies = command::run_command(
+            &self.client,
+            "/tool/traceroute",
+            &[("address", target.to_string().as_str()), ("count", "3")],
+        )
+        .await?;
+        let mut hops = BTreeMap::new();
+        for mut reply in replies {
+            if let Some(hop) = reply.get(".section").and_then(|s| s.parse::<u32>().ok()) {
+                hops.insert(
+                    hop,
+                    TracerouteHop {
+                        hop: hop + 1,
+                        address: reply.remove("address").filter(|a| !a.is_empty()),
+                        loss: reply.remove("loss"),
+                        last: reply.remove("last"),
+                        avg: reply.remove("avg"),
+                        status: reply.remove("status").filter(|s| !s.is_empty()),
+                    },
+                );
+            }
+        }
+        Ok(hops.into_values().collect())
+    }
+
+    /// Only the device itself and addresses documented in netbox are checked, the provisioner
+    /// must not scan arbitrary hosts for its users
+    pub async fn check_tcp_port(
+        &self,
+        target: Option<IpAddr>,
+        port: u16,
+    ) -> Result<TcpCheckResult, Error> {
+        let target = target.unwrap_or(self.address);
+        if target != self.address
+            && self
+                .device_config
+                .topology()
+                .find_ip_address(target)
+                .is_none()
+        {
+            return Err(Error::UnknownAddress(target));
+        }
+        let start = Instant::now();
+        let result =
+            tokio::time::timeout(TCP_CHECK_TIMEOUT, TcpStream::connect((target, port))).await;
+        let duration = start.elapsed();
+        Ok(match result {
+            Ok(Ok(_)) => TcpCheckResult {
+                open: true,
+                error: None,
+                duration,
+            },
+            Ok(Err(e)) => TcpCheckResult {
+                open: false,
+                error: Some(e.to_string()),
+                duration,
+            },
+            Err(_) => TcpCheckResult {
+                open: false,
+                error: Some("timeout".to_string()),
+                duration,
+            },
+        })
+    }
 }
+
+const TCP_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
 #[derive(Clone, Debug)]
 struct GraphqlSystemRouterboard(SystemRouterboardState);
 
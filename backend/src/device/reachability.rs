@@ -0,0 +1,158 @@
+use crate::{
+    config::{CONFIG, PingMode},
+    device::{PingResult, availability::AVAILABILITY},
+};
+use async_graphql::{Enum, SimpleObject};
+use lazy_static::lazy_static;
+use log::debug;
+use std::{collections::HashMap, io::ErrorKind, net::IpAddr, time::Duration};
+use surge_ping::{IcmpPacket, SurgeError, ping};
+use thiserror::Error;
+use tokio::{
+    net::TcpStream,
+    sync::{Mutex, Semaphore},
+    time::Instant,
+};
+
+const MAX_CONCURRENT_PINGS: usize = 16;
+const CACHE_TTL: Duration = Duration::from_secs(10);
+const TCP_PROBE_PORTS: [u16; 2] = [8728, 22];
+const TCP_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+type CachedPing = (Instant, Result<PingResult, Box<str>>);
+
+lazy_static! {
+    static ref PING_PERMITS: Semaphore = Semaphore::new(MAX_CONCURRENT_PINGS);
+    static ref REACHABILITY_CACHE: Mutex<HashMap<IpAddr, CachedPing>> = Mutex::default();
+}
+
+/// How the reachability of a device was measured
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum PingMethod {
+    Icmp,
+    Tcp,
+}
+
+#[derive(Error, Debug)]
+pub enum PingError {
+    #[error("Icmp ping failed: {0}")]
+    Icmp(#[from] SurgeError),
+    #[error("Tcp probe failed: {0}")]
+    Tcp(std::io::Error),
+    #[error("Tcp probe timed out")]
+    Timeout,
+}
+
+#[derive(SimpleObject)]
+pub struct DeviceReachability {
+    device_id: u32,
+    address: Option<String>,
+    ping: Option<PingResult>,
+    error: Option<Box<str>>,
+}
+
+impl DeviceReachability {
+    pub async fn check(device_id: u32, address: Option<IpAddr>) -> Self {
+        let (ping, error) = match address {
+            None => (None, Some("No management address".into())),
+            Some(address) => match cached_ping(address).await {
+                Ok(ping) => (Some(ping), None),
+                Err(error) => (None, Some(error)),
+            },
+        };
+        DeviceReachability {
+            device_id,
+            address: address.map(|a| a.to_string()),
+            ping,
+            error,
+        }
+    }
+}
+
+/// Single ping, limited to a fixed number of pings in flight
+///
+/// Depending on the configured mode an icmp echo or a tcp connect is used. In `auto` mode the
+/// tcp probe is used if the icmp ping fails, e.g. if raw sockets are not permitted.
+pub async fn limited_ping(address: IpAddr) -> Result<PingResult, PingError> {
+    let _permit = PING_PERMITS
+        .acquire()
+        .await
+        .expect("ping semaphore is never closed");
+    match CONFIG.load().ping_mode() {
+        PingMode::Icmp => icmp_ping(address).await,
+        PingMode::Tcp => tcp_probe(address).await,
+        PingMode::Auto => match icmp_ping(address).await {
+            Ok(result) => Ok(result),
+            Err(error) => {
+                debug!("Icmp ping to {address} failed, falling back to tcp: {error}");
+                tcp_probe(address).await
+            }
+        },
+    }
+}
+
+async fn icmp_ping(address: IpAddr) -> Result<PingResult, PingError> {
+    let (packet, duration) = ping(address, &[]).await?;
+    let ttl = match packet {
+        IcmpPacket::V4(v4) => v4.get_ttl(),
+        IcmpPacket::V6(v6) => Some(v6.get_max_hop_limit()),
+    };
+    Ok(PingResult {
+        ttl,
+        duration,
+        method: PingMethod::Icmp,
+    })
+}
+
+async fn tcp_probe(address: IpAddr) -> Result<PingResult, PingError> {
+    let mut last_error = PingError::Timeout;
+    for port in TCP_PROBE_PORTS {
+        let start = Instant::now();
+        match tokio::time::timeout(TCP_PROBE_TIMEOUT, TcpStream::connect((address, port))).await {
+            // a refused connection is answered by the host, so it is reachable
+            Ok(Ok(_)) => {}
+            Ok(Err(error)) if error.kind() == ErrorKind::ConnectionRefused => {}
+            Ok(Err(error)) => {
+                last_error = PingError::Tcp(error);
+                continue;
+            }
+            Err(_) => {
+                last_error = PingError::Timeout;
+                continue;
+            }
+        }
+        return Ok(PingResult {
+            ttl: None,
+            duration: start.elapsed(),
+            method: PingMethod::Tcp,
+        });
+    }
+    Err(last_error)
+}
+
+/// Ping of the availability collector, recorded in the availability history
+///
+/// Only these pings are recorded, so the history has one sample per interval of the collector
+/// however often the reachability is requested meanwhile.
+pub async fn recorded_ping(address: IpAddr) -> Result<PingResult, Box<str>> {
+    let result = cached_ping(address).await;
+    AVAILABILITY.record(address, result.is_ok());
+    result
+}
+
+/// Ping result of an address, reused for a few seconds
+pub async fn cached_ping(address: IpAddr) -> Result<PingResult, Box<str>> {
+    if let Some((timestamp, result)) = REACHABILITY_CACHE.lock().await.get(&address) {
+        if timestamp.elapsed() < CACHE_TTL {
+            return result.clone();
+        }
+    }
+    let result = limited_ping(address)
+        .await
+        .map_err(|e| e.to_string().into_boxed_str());
+    REACHABILITY_CACHE
+        .lock()
+        .await
+        .insert(address, (Instant::now(), result.clone()));
+    result
+}
@@ -0,0 +1,174 @@
+use crate::{
+    config::RadiusConfig,
+    device::ros::{
+        CAPS_BRIDGE_NAME,
+        naming::clean_name,
+        warnings::{WarningKind, Warnings},
+    },
+    secrets::{SecretError, read_secret},
+    topology::{WlanAuth, WlanCipher, access::device::DeviceAccess},
+};
+use convert_case::{Case, Casing};
+use mikrotik_model::{
+    ascii::AsciiString,
+    mikrotik_model,
+    model::{
+        CapsManDatapathVlanMode, CapsManProvisioningAction, CapsManSecurityAuthenticationTypes,
+        CapsManSecurityEapMethods, CapsManSecurityEncryption, RadiusService,
+    },
+};
+use std::path::Path;
+
+mikrotik_model!(
+    name = CapsmanDeviceData,
+    detect = new,
+    fields(
+        manager(single = "caps-man/manager"),
+        datapath(by_key(path = "caps-man/datapath", key = name)),
+        security(by_key(path = "caps-man/security", key = name)),
+        configuration(by_key(path = "caps-man/configuration", key = name)),
+        provisioning(by_id(
+            path = "caps-man/provisioning",
+            keys(master_configuration)
+        )),
+        radius(by_id(path = "radius", keys(address))),
+    ),
+);
+
+impl CapsmanDeviceDataTarget {
+    pub(super) fn new(_model: &[u8]) -> Self {
+        Self {
+            manager: Default::default(),
+            datapath: Default::default(),
+            security: Default::default(),
+            configuration: Default::default(),
+            provisioning: Default::default(),
+            radius: Default::default(),
+        }
+    }
+
+    /// authenticates the manager by the managed certificates instead of generated ones
+    pub(super) fn use_certificates(&mut self, certificate: &str, ca_certificate: &str) {
+        if self.manager.enabled {
+            self.manager.certificate = Some(certificate.into());
+            self.manager.ca_certificate = Some(ca_certificate.into());
+        }
+    }
+
+    /// Controller side of a wlan group: one datapath, security and configuration per wlan,
+    /// all bound to the caps bridge which is connected to the aps by vxlan
+    pub(super) fn generate_from(
+        &mut self,
+        device: &DeviceAccess,
+        radius: Option<&RadiusConfig>,
+        secrets_dir: &Path,
+        warnings: &mut Warnings,
+    ) {
+        let Some(wlan_group) = device.wlan_controller_of() else {
+            return;
+        };
+        self.manager.enabled = true;
+        self.manager.certificate = Some(b"auto".into());
+        self.manager.ca_certificate = Some(b"auto".into());
+        self.manager.require_peer_certificate = false;
+
+        let mut configurations = Vec::new();
+        for wlan in wlan_group.wlan() {
+            let (Some(ssid), Some(vlan_id)) = (wlan.ssid(), wlan.vlan().and_then(|v| v.vlan_id()))
+            else {
+                continue;
+            };
+            let name = configuration_name(ssid);
+
+            let datapath = &mut self.datapath.entry(name.clone()).or_default().0;
+            datapath.bridge = Some(CAPS_BRIDGE_NAME.into());
+            datapath.local_forwarding = Some(true);
+            datapath.vlan_mode = Some(CapsManDatapathVlanMode::UseTag);
+            datapath.vlan_id = Some(vlan_id);
+            // guests must not reach each other
+            datapath.client_to_client_forwarding =
+                Some(!wlan.vlan().is_some_and(|vlan| vlan.is_guest()));
+
+            let security = match wlan.auth() {
+                Some(WlanAuth::Wpa(wpa)) => {
+                    let security = &mut self.security.entry(name.clone()).or_default().0;
+                    security.authentication_types = [CapsManSecurityAuthenticationTypes::Wpa2Psk]
+                        .into_iter()
+                        .collect();
+                    security.encryption = [CapsManSecurityEncryption::AesCcm].into_iter().collect();
+                    security.passphrase = Some(wpa.pre_shared_key().into());
+                    Some(name.clone())
+                }
+                Some(WlanAuth::WpaEnterprise(enterprise)) => {
+                    let Some(radius) = radius else {
+                        warnings.push(
+                            WarningKind::SkippedResource,
+                            format!("No radius server configured for wlan {ssid}"),
+                        );
+                        continue;
+                    };
+                    if let Err(error) = self.setup_radius(radius, secrets_dir) {
+                        warnings.push(
+                            WarningKind::SkippedResource,
+                            format!("No radius secret for wlan {ssid}: {error}"),
+                        );
+                        continue;
+                    }
+                    let security = &mut self.security.entry(name.clone()).or_default().0;
+                    security.authentication_types = [CapsManSecurityAuthenticationTypes::Wpa2Eap]
+                        .into_iter()
+                        .collect();
+                    security.encryption =
+                        encryption_of(enterprise.cipher()).iter().cloned().collect();
+                    security.eap_methods = [CapsManSecurityEapMethods::Passthrough]
+                        .into_iter()
+                        .collect();
+                    security.eap_radius_accounting = Some(radius.accounting);
+                    Some(name.clone())
+                }
+                Some(WlanAuth::Open(_)) | None => None,
+            };
+
+            let configuration = &mut self.configuration.entry(name.clone()).or_default().0;
+            configuration.ssid = Some(ssid.into());
+            configuration.datapath = Some(name.clone());
+            configuration.security = security;
+            configurations.push(name);
+        }
+        if let Some((master, slaves)) = configurations.split_first() {
+            let provisioning = self.provisioning.entry((master.clone(),)).or_default();
+            provisioning.action = CapsManProvisioningAction::CreateDynamicEnabled;
+            provisioning.slave_configurations = slaves.iter().cloned().collect();
+        }
+    }
+
+    /// the manager passes the authentication of the wlan clients to the radius server, the
+    /// shared secret is read from the secrets directory
+    fn setup_radius(
+        &mut self,
+        radius: &RadiusConfig,
+        secrets_dir: &Path,
+    ) -> Result<(), SecretError> {
+        let secret = read_secret(secrets_dir, radius.secret_name())?;
+        let client = self.radius.entry((radius.address,)).or_default();
+        client.service = [RadiusService::Wireless].into_iter().collect();
+        client.secret = secret.as_ref().into();
+        Ok(())
+    }
+}
+
+/// name of the datapath, security and configuration of a wlan
+pub(super) fn configuration_name(ssid: &str) -> AsciiString {
+    AsciiString::from(format!("wlan-{}", clean_name(ssid)).to_case(Case::Kebab))
+}
+
+fn encryption_of(cipher: WlanCipher) -> &'static [CapsManSecurityEncryption] {
+    match cipher {
+        WlanCipher::Auto => &[
+            CapsManSecurityEncryption::AesCcm,
+            CapsManSecurityEncryption::Tkip,
+        ],
+        WlanCipher::Aes => &[CapsManSecurityEncryption::AesCcm],
+        WlanCipher::Tkip => &[CapsManSecurityEncryption::Tkip],
+    }
+}
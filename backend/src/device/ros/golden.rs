@@ -0,0 +1,129 @@
+//! Golden-file tests of the generated configuration.
+//!
+//! Every `<scenario>.yaml` in the `golden` directory holds a fixture topology, the device to
+//! generate, its model and optionally the generation profile, the hotspot settings, the dhcp
+//! option sets, the apn profiles and the pppoe accounts. Their passwords are read from the
+//! `golden/secrets` directory. The script generated against a reset device is compared with
+//! `<scenario>.rsc`. Run the tests with `UPDATE_GOLDEN=1` to write the expected scripts
+//! of a new scenario or after an intended change, a missing script fails otherwise.
+use crate::{
+    config::{
+        ApnProfile, DhcpOptionSet, GenerationProfile, HotspotConfig, PppoeCredentials, Schedule,
+    },
+    device::ros::{BaseDeviceDataTarget, empty_current, render_script, warnings::Warnings},
+    topology::{DeviceId, Topology, TopologyHolder},
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Deserialize)]
+struct Scenario {
+    model: Box<str>,
+    device: u32,
+    #[serde(default)]
+    profile: GenerationProfile,
+    hotspot: Option<HotspotConfig>,
+    #[serde(default)]
+    dhcp_option_sets: HashMap<Box<str>, DhcpOptionSet>,
+    #[serde(default)]
+    apn_profiles: HashMap<Box<str>, ApnProfile>,
+    #[serde(default)]
+    pppoe_credentials: HashMap<Box<str>, PppoeCredentials>,
+    #[serde(default)]
+    schedules: HashMap<Box<str>, Schedule>,
+    topology: Topology,
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("src/device/ros/golden")
+}
+
+async fn render_scenario(path: &Path) -> Result<String, Box<dyn Error>> {
+    let scenario: Scenario = serde_yaml::from_slice(&fs::read(path)?)?;
+    let device = TopologyHolder::offline(scenario.topology)
+        .devices_by_id(DeviceId(scenario.device))
+        .await
+        .ok_or("device not found in topology")?;
+    let mut target = BaseDeviceDataTarget::new(scenario.model.as_bytes())?;
+    let current = empty_current(&target);
+    target.generate_from(
+        &device,
+        &scenario.profile,
+        scenario.hotspot.as_ref(),
+        &scenario.dhcp_option_sets,
+        &scenario.apn_profiles,
+        &scenario.pppoe_credentials,
+        &golden_dir().join("secrets"),
+        &scenario.schedules,
+        &mut Warnings::default(),
+    )?;
+    Ok(render_script(&target.generate_mutations(&current)?)?)
+}
+
+/// lines only in the expected script are prefixed with `-`, lines only in the generated with `+`
+fn diff(expected: &str, actual: &str) -> String {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+    let mut result = String::new();
+    for line in &expected {
+        if !actual.contains(line) {
+            writeln!(result, "-{line}").unwrap();
+        }
+    }
+    for line in &actual {
+        if !expected.contains(line) {
+            writeln!(result, "+{line}").unwrap();
+        }
+    }
+    if result.is_empty() {
+        result.push_str("(same lines in a different order)\n");
+    }
+    result
+}
+
+#[tokio::test]
+async fn test_golden_scenarios() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut scenarios = fs::read_dir(golden_dir())
+        .expect("golden directory not found")
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "yaml"))
+        .collect::<Vec<_>>();
+    scenarios.sort();
+    assert!(!scenarios.is_empty(), "no golden scenarios found");
+
+    let mut failures = String::new();
+    for scenario in scenarios {
+        let name = scenario.file_stem().unwrap_or_default().to_string_lossy();
+        let generated = match render_scenario(&scenario).await {
+            Ok(generated) => generated,
+            Err(error) => {
+                writeln!(failures, "{name}: {error}").unwrap();
+                continue;
+            }
+        };
+        let expected_path = scenario.with_extension("rsc");
+        match (fs::read_to_string(&expected_path), update) {
+            (Ok(expected), _) if expected == generated => {}
+            (_, true) => {
+                fs::write(&expected_path, &generated).expect("cannot write golden file");
+            }
+            (Ok(expected), false) => {
+                writeln!(failures, "{name} differs:\n{}", diff(&expected, &generated)).unwrap();
+            }
+            (Err(error), false) => {
+                writeln!(failures, "{name}: {}: {error}", expected_path.display()).unwrap();
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "golden scenarios failed, rerun with UPDATE_GOLDEN=1 if the changes are intended\n{failures}"
+    );
+}
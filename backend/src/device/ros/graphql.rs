@@ -1,25 +1,52 @@
 use crate::{
     Error,
+    config::CONFIG,
     device::{
-        AccessibleDevice, GraphqlSystemRouterboard, PingResult,
+        AccessibleDevice, GraphqlSystemRouterboard, PingResult, TcpCheckResult, TracerouteHop,
+        cabling::CablingCheck,
+        certificate::{CA_CERTIFICATE, DEVICE_CERTIFICATE, DeviceCertificate},
+        dhcp::DhcpLease,
+        import::DeviceImport,
+        log::LogEntry,
+        mac::MacCheck,
         ros::{
             BaseDeviceDataCurrent, BaseDeviceDataTarget, SetupError, WirelessDeviceDataCurrent,
             WirelessDeviceDataTarget,
+            capsman::{CapsmanDeviceDataCurrent, CapsmanDeviceDataTarget},
+            empty_current,
+            management::management_path_cuts,
+            naming::{GeneratedInterfaceName, InterfaceNames, NamingPolicy},
+            prune::{Orphan, find_orphans, managed_current},
+            render_script,
+            scripts::{ScriptDeviceDataCurrent, ScriptDeviceDataTarget},
+            version::RouterOsVersion,
+            warnings::{GenerationWarning, Warnings},
         },
     },
-    topology::access::device::DeviceAccess,
+    graphql::scalars::ScalarDuration,
+    provisioning::{
+        plan::{PlanOptions, ProvisionPlan},
+        scope::ApplyScope,
+    },
+    rate_limit::check_device_access,
+    secrets::mask_secrets,
+    topology::access::{AccessTopology, device::DeviceAccess},
+};
+use async_graphql::{
+    ComplexObject, Context, Object, SimpleObject,
+    futures_util::{
+        TryFutureExt,
+        future::{try_join, try_join5},
+    },
 };
-use async_graphql::{Object, SimpleObject};
-use log::info;
+use log::warn;
 use mikrotik_model::{
     MikrotikDevice,
-    generator::Generator,
     hwconfig::DeviceType,
-    model::{ReferenceType, SystemIdentityCfg, SystemPackageState, SystemRouterboardState},
-    resource::{ResourceMutation, SingleResource, collect_resource},
+    model::{SystemIdentityCfg, SystemPackageState},
+    resource::{ResourceMutation, collect_resource},
 };
-use std::collections::HashSet;
-use surge_ping::SurgeError;
+use std::{collections::HashSet, net::IpAddr, time::Duration};
 
 pub struct GraphqlDeviceType(DeviceType);
 impl From<DeviceType> for GraphqlDeviceType {
@@ -44,31 +71,151 @@ impl GraphqlSystemIdentity<'_> {
 #[derive(Clone, Debug)]
 pub struct DeviceCfg {
     base_current: BaseDeviceDataCurrent,
+    /// the current configuration without hand-made entries, set by the generation
+    base_managed_current: Option<BaseDeviceDataCurrent>,
     base_target: BaseDeviceDataTarget,
     wireless_current: Option<WirelessDeviceDataCurrent>,
     wireless_target: Option<WirelessDeviceDataTarget>,
+    capsman_current: Option<CapsmanDeviceDataCurrent>,
+    capsman_target: Option<CapsmanDeviceDataTarget>,
+    script_current: ScriptDeviceDataCurrent,
+    script_target: ScriptDeviceDataTarget,
+    /// certificates on the device, only fetched if certificates are managed. The managed
+    /// certificates are referenced only once they are issued, see `provisionCertificates`.
+    certificates: Option<Box<[DeviceCertificate]>>,
+    unknown_model: bool,
+    version: RouterOsVersion,
+    /// problems found by the last generation
+    warnings: Warnings,
 }
 
 impl DeviceCfg {
-    fn generate_from(&mut self, device: &DeviceAccess) -> Result<(), SetupError> {
-        self.base_target.generate_from(device)?;
+    pub fn generate_from(&mut self, device: &DeviceAccess) -> Result<(), SetupError> {
+        let settings = CONFIG.load();
+        let mut warnings = Warnings::default();
+        self.base_target.keep_current_services(&self.base_current);
+        generate_base(&mut self.base_target, device, &mut warnings)?;
         if let Some(wireless_target) = self.wireless_target.as_mut() {
             wireless_target.generate_from(device);
         }
+        if let Some(capsman_target) = self.capsman_target.as_mut() {
+            capsman_target.generate_from(
+                device,
+                settings.radius.as_ref(),
+                &settings.secrets_dir(),
+                &mut warnings,
+            );
+        }
+        self.script_target.generate_from(device, &settings.scripts);
+        self.script_target
+            .generate_schedules(device, &settings.schedules, &mut warnings);
+        self.warnings = warnings;
+        if self.certificate_valid(DEVICE_CERTIFICATE) {
+            self.base_target.use_certificate(DEVICE_CERTIFICATE);
+            if let Some(capsman_target) = self.capsman_target.as_mut() {
+                capsman_target.use_certificates(DEVICE_CERTIFICATE, CA_CERTIFICATE);
+            }
+        }
+        self.base_managed_current = Some(managed_current(&self.base_target, &self.base_current));
         Ok(())
     }
-    fn generate_mutations(&self) -> Result<Box<[ResourceMutation]>, Error> {
-        let mutations = self.base_target.generate_mutations(&self.base_current)?;
-        let mutations = if let (Some(wireless_target), Some(wireless_current)) =
+    fn certificate_valid(&self, name: &str) -> bool {
+        self.certificates
+            .iter()
+            .flatten()
+            .any(|certificate| certificate.name() == name && certificate.is_valid())
+    }
+    /// managed resources on the device missing in the generated configuration
+    pub fn orphans(&self) -> Vec<Orphan> {
+        find_orphans(&self.base_target, &self.base_current)
+    }
+    /// changes cutting the path to the device on the address, see [`management_path_cuts`]
+    pub fn management_path_cuts(
+        &self,
+        address: IpAddr,
+        prune: bool,
+    ) -> Vec<(&'static str, Box<str>)> {
+        management_path_cuts(&self.base_target, &self.base_current, address, prune)
+    }
+    /// no hardware facts are known for the model
+    pub fn unknown_model(&self) -> bool {
+        self.unknown_model
+    }
+    /// data problems the last generation worked around
+    pub fn warnings(&self) -> &[GenerationWarning] {
+        self.warnings.as_slice()
+    }
+    /// the mutations of all resource sets are collected once, a switch with hundreds of bridge
+    /// vlans would otherwise be copied for every set
+    pub fn generate_mutations(&self) -> Result<Box<[ResourceMutation]>, Error> {
+        let base_current = self
+            .base_managed_current
+            .as_ref()
+            .unwrap_or(&self.base_current);
+        let mut mutations = Vec::from_iter(self.base_target.generate_mutations(base_current)?);
+        if let (Some(wireless_target), Some(wireless_current)) =
             (&self.wireless_target, &self.wireless_current)
         {
-            let wireless_mutations = wireless_target.generate_mutations(wireless_current)?;
-            mutations.into_iter().chain(wireless_mutations).collect()
-        } else {
-            mutations
-        };
+            mutations.extend(wireless_target.generate_mutations(wireless_current)?);
+        }
+        if let (Some(capsman_target), Some(capsman_current)) =
+            (&self.capsman_target, &self.capsman_current)
+        {
+            mutations.extend(capsman_target.generate_mutations(capsman_current)?);
+        }
+        mutations.extend(
+            self.script_target
+                .generate_mutations(&self.script_current)?,
+        );
+        self.version.check(&mutations)?;
+        Ok(mutations.into_boxed_slice())
+    }
+}
 
-        Ok(mutations)
+fn generate_base(
+    target: &mut BaseDeviceDataTarget,
+    device: &DeviceAccess,
+    warnings: &mut Warnings,
+) -> Result<(), SetupError> {
+    let settings = CONFIG.load();
+    target.generate_from(
+        device,
+        &settings.generation_profile(device.role()),
+        settings.hotspot.as_ref(),
+        &settings.dhcp_option_sets,
+        &settings.apn_profiles,
+        &settings.pppoe_credentials,
+        &settings.secrets_dir(),
+        &settings.schedules,
+        warnings,
+    )
+}
+
+impl DeviceAccess {
+    /// Script configuring a reset device of the hardware model, generated from the topology
+    /// without connecting to the device. Only the base configuration is rendered, the wireless
+    /// and capsman configuration depend on the packages installed on the device.
+    pub fn render_config_for_model(&self, model: &str) -> Result<String, Error> {
+        let mut target = BaseDeviceDataTarget::new(model.as_bytes())?;
+        let current = empty_current(&target);
+        generate_base(&mut target, self, &mut Warnings::default())?;
+        render_script(&target.generate_mutations(&current)?)
+    }
+    /// names of the ports and tunnels in the generated configuration, ordered by interface
+    pub fn generated_interface_names(&self) -> Result<Box<[GeneratedInterfaceName]>, Error> {
+        let profile = CONFIG.load().generation_profile(self.role());
+        let policy = NamingPolicy::new(profile.interface_name_max_length)?;
+        let names = InterfaceNames::new(self, policy)?;
+        let mut interfaces = self
+            .interfaces()
+            .into_iter()
+            .filter_map(|interface| {
+                let name = names.get(interface.id())?.to_string().into();
+                Some(GeneratedInterfaceName { interface, name })
+            })
+            .collect::<Vec<_>>();
+        interfaces.sort_by_key(|generated| generated.interface.id());
+        Ok(interfaces.into_boxed_slice())
     }
 }
 
@@ -80,51 +227,148 @@ impl DeviceCfg {
     async fn target(&self) -> &BaseDeviceDataTarget {
         &self.base_target
     }
+    /// no hardware facts are known for the model, the ports are taken from the device
+    /// and cannot be validated
+    #[graphql(name = "unknownModel")]
+    async fn api_unknown_model(&self) -> bool {
+        self.unknown_model
+    }
+    /// major RouterOS version running on the device
+    async fn version(&self) -> RouterOsVersion {
+        self.version
+    }
+    /// data problems the generation worked around, empty before the target is generated
+    #[graphql(name = "warnings")]
+    async fn api_warnings(&self) -> &[GenerationWarning] {
+        self.warnings()
+    }
 }
 
 impl AccessibleDevice {
-    pub async fn fetch_config(&self, client: &MikrotikDevice) -> Result<DeviceCfg, SetupError> {
-        let installed_packages = collect_resource::<SystemPackageState>(client)
-            .await?
-            .into_iter()
-            .filter(|p| !p.disabled)
-            .map(|p| p.name.0)
-            .collect::<HashSet<_>>();
-
-        let current = BaseDeviceDataCurrent::fetch(client).await?;
-        let target = BaseDeviceDataTarget::detect_device(client).await?;
-        let (wireless_current, wireless_target) =
-            if installed_packages.contains(b"wireless".as_ref()) {
-                let current_wireless = WirelessDeviceDataCurrent::fetch(client).await?;
-                let target = WirelessDeviceDataTarget::detect_device(client).await?;
-                (Some(current_wireless), Some(target))
-            } else {
-                (None, None)
-            };
+    /// Reads the current configuration and the device type. The independent resource sets are
+    /// fetched concurrently over the same connection.
+    pub async fn fetch_config(&self, client: &MikrotikDevice) -> Result<DeviceCfg, Error> {
+        let (version, has_wireless) = try_join(
+            RouterOsVersion::detect(client),
+            has_wireless_package(client),
+        )
+        .await?;
+        if version == RouterOsVersion::V6 {
+            // the configuration is generated for RouterOS 7 only and reading the current state
+            // fails on the menus RouterOS 6 is missing, so nothing is fetched. The generated
+            // configuration is checked first to name a missing feature.
+            let mut target = BaseDeviceDataTarget::new(&self.routerboard().await?.value.model.0)?;
+            generate_base(&mut target, &self.device_config, &mut Warnings::default())?;
+            version.check(&target.generate_mutations(&empty_current(&target))?)?;
+            return Err(SetupError::UnsupportedVersion(version).into());
+        }
+        let has_capsman = has_wireless && self.device_config.wlan_controller_of().is_some();
+        let (
+            current,
+            detected,
+            (wireless_current, wireless_target),
+            (capsman_current, capsman_target),
+            (script_current, script_target),
+        ) = try_join5(
+            BaseDeviceDataCurrent::fetch(client).map_err(Error::from),
+            // an unknown model falls back to the ports of the current configuration
+            self.routerboard()
+                .map_ok(|routerboard| BaseDeviceDataTarget::new(&routerboard.value.model.0)),
+            async {
+                if !has_wireless {
+                    return Ok((None, None));
+                }
+                let (current, target) = try_join(
+                    WirelessDeviceDataCurrent::fetch(client).map_err(Error::from),
+                    WirelessDeviceDataTarget::detect_device(client).map_err(Error::from),
+                )
+                .await?;
+                Ok::<_, Error>((Some(current), Some(target)))
+            },
+            async {
+                if !has_capsman {
+                    return Ok((None, None));
+                }
+                let (current, target) = try_join(
+                    CapsmanDeviceDataCurrent::fetch(client).map_err(Error::from),
+                    CapsmanDeviceDataTarget::detect_device(client).map_err(Error::from),
+                )
+                .await?;
+                Ok::<_, Error>((Some(current), Some(target)))
+            },
+            try_join(
+                ScriptDeviceDataCurrent::fetch(client).map_err(Error::from),
+                ScriptDeviceDataTarget::detect_device(client).map_err(Error::from),
+            ),
+        )
+        .await?;
+        let (target, unknown_model) = match detected {
+            Ok(target) => (target, false),
+            Err(SetupError::NoPortsFound(model)) => {
+                warn!(
+                    "Unknown model {model} on {}, using the ports found on the device",
+                    self.device_config.name()
+                );
+                (BaseDeviceDataTarget::from_current_ports(&current), true)
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let certificates = match &CONFIG.load().certificates {
+            Some(config) => Some(self.fetch_certificates(config).await?),
+            None => None,
+        };
         Ok(DeviceCfg {
             base_current: current,
+            base_managed_current: None,
             base_target: target,
             wireless_current,
             wireless_target,
+            capsman_current,
+            capsman_target,
+            script_current,
+            script_target,
+            certificates,
+            unknown_model,
+            version,
+            warnings: Warnings::default(),
         })
     }
 }
 
+/// the wireless package is installed and enabled, the wireless and capsman resources exist
+pub(super) async fn has_wireless_package(client: &MikrotikDevice) -> Result<bool, Error> {
+    Ok(collect_resource::<SystemPackageState>(client)
+        .await?
+        .into_iter()
+        .filter(|p| !p.disabled)
+        .map(|p| p.name.0)
+        .collect::<HashSet<_>>()
+        .contains(b"wireless".as_ref()))
+}
+
 #[derive(Clone, Debug, SimpleObject)]
+#[graphql(complex)]
 pub struct DeviceStats {
     routerboard: GraphqlSystemRouterboard,
+    #[graphql(skip)]
+    routerboard_age: Duration,
 }
 impl DeviceStats {
-    pub async fn fetch(client: &MikrotikDevice) -> Result<DeviceStats, Error> {
+    pub async fn fetch(device: &AccessibleDevice) -> Result<DeviceStats, Error> {
+        let routerboard = device.routerboard().await?;
         Ok(Self {
-            routerboard: GraphqlSystemRouterboard(
-                SystemRouterboardState::fetch(&client)
-                    .await?
-                    .expect("system/routerboard not found"),
-            ),
+            routerboard: GraphqlSystemRouterboard(routerboard.value),
+            routerboard_age: routerboard.age,
         })
     }
 }
+#[ComplexObject]
+impl DeviceStats {
+    /// time since the routerboard facts were read from the device, they are cached
+    async fn routerboard_age(&self) -> ScalarDuration {
+        self.routerboard_age.into()
+    }
+}
 
 #[Object]
 impl BaseDeviceDataCurrent {
@@ -142,41 +386,108 @@ impl BaseDeviceDataTarget {
 
 #[Object]
 impl AccessibleDevice {
-    async fn ping(&self, count: Option<u8>) -> Result<Box<[PingResult]>, SurgeError> {
-        self.simple_ping(count.unwrap_or(1)).await
+    async fn ping(&self, ctx: &Context<'_>, count: Option<u8>) -> Result<Box<[PingResult]>, Error> {
+        check_device_access(ctx, &self.device_config)?;
+        Ok(self.simple_ping(count.unwrap_or(1)).await?)
     }
-
-    async fn device_stats(&self) -> Result<DeviceStats, Error> {
-        DeviceStats::fetch(&self.client).await
+    /// traceroute executed by the device itself
+    async fn traceroute(
+        &self,
+        ctx: &Context<'_>,
+        target: String,
+    ) -> Result<Box<[TracerouteHop]>, Error> {
+        check_device_access(ctx, &self.device_config)?;
+        self.run_traceroute(target.parse()?).await
+    }
+    /// tcp connect check executed from the provisioner, defaults to the device address. Other
+    /// targets have to be documented in netbox.
+    async fn tcp_check(
+        &self,
+        ctx: &Context<'_>,
+        target: Option<String>,
+        port: u16,
+    ) -> Result<TcpCheckResult, Error> {
+        check_device_access(ctx, &self.device_config)?;
+        let target = target.map(|t| t.parse::<IpAddr>()).transpose()?;
+        self.check_tcp_port(target, port).await
+    }
+    /// compares the lldp neighbors of the device against the cables in netbox
+    async fn verify_cabling(&self, ctx: &Context<'_>) -> Result<Box<[CablingCheck]>, Error> {
+        check_device_access(ctx, &self.device_config)?;
+        self.check_cabling().await
+    }
+    /// compares the mac addresses of the ethernet ports against the mac addresses in netbox
+    async fn verify_mac_addresses(&self, ctx: &Context<'_>) -> Result<Box<[MacCheck]>, Error> {
+        check_device_access(ctx, &self.device_config)?;
+        self.check_mac_addresses().await
+    }
+    /// netbox objects documenting the bridges, vlans and addresses found on a device set up by
+    /// hand, nothing is written to netbox
+    #[graphql(name = "importFromDevice")]
+    async fn api_import_from_device(&self, ctx: &Context<'_>) -> Result<DeviceImport, Error> {
+        check_device_access(ctx, &self.device_config)?;
+        self.import_from_device().await
+    }
+    /// current dhcp leases of the device, matched against the ip addresses in netbox
+    async fn dhcp_leases(&self, ctx: &Context<'_>) -> Result<Box<[DhcpLease]>, Error> {
+        check_device_access(ctx, &self.device_config)?;
+        self.fetch_dhcp_leases().await
+    }
+    /// newest entries of the device log, oldest first
+    async fn logs(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<u32>,
+        topics: Option<Vec<String>>,
+    ) -> Result<Box<[LogEntry]>, Error> {
+        check_device_access(ctx, &self.device_config)?;
+        let topics = topics.unwrap_or_default();
+        let topics = topics.iter().map(String::as_str).collect::<Vec<_>>();
+        self.fetch_logs(limit.unwrap_or(100) as usize, &topics)
+            .await
     }
 
-    async fn config(&self) -> Result<DeviceCfg, Error> {
-        Ok(self.fetch_config(&self.client).await?)
+    /// certificates installed on the device with their expiry
+    async fn certificates(&self, ctx: &Context<'_>) -> Result<Box<[DeviceCertificate]>, Error> {
+        check_device_access(ctx, &self.device_config)?;
+        let settings = CONFIG.load();
+        let config = settings
+            .certificates
+            .as_ref()
+            .ok_or(Error::CertificatesNotConfigured)?;
+        self.fetch_certificates(config).await
     }
-    async fn generate_cfg(&self) -> Result<Box<str>, Error> {
-        let mut device_cfg = self.fetch_config(&self.client).await?;
-        device_cfg.generate_from(&self.device_config)?;
 
-        let mutations = device_cfg.generate_mutations()?;
-        for m in &mutations {
-            info!("Mutation generated: {:?}", m);
-        }
+    async fn device_stats(&self, ctx: &Context<'_>) -> Result<DeviceStats, Error> {
+        check_device_access(ctx, &self.device_config)?;
+        DeviceStats::fetch(self).await
+    }
 
-        let mutations = ResourceMutation::sort_mutations_with_provided_dependencies(
-            mutations.as_ref(),
-            [
-                (ReferenceType::Interface, b"lo".into()),
-                (ReferenceType::RoutingTable, b"main".into()),
-                (ReferenceType::FirewallChain, b"input".into()),
-                (ReferenceType::FirewallChain, b"output".into()),
-                (ReferenceType::FirewallChain, b"forward".into()),
-            ],
-        )?;
-        let mut cfg = String::new();
-        let mut generator = Generator::new(&mut cfg);
-        for mutation in mutations {
-            generator.append_mutation(mutation)?;
-        }
-        Ok(cfg.into_boxed_str())
+    async fn config(&self, ctx: &Context<'_>) -> Result<DeviceCfg, Error> {
+        check_device_access(ctx, &self.device_config)?;
+        Ok(self.fetch_config(&self.client).await?)
+    }
+    async fn generate_cfg(
+        &self,
+        ctx: &Context<'_>,
+        scope: Option<ApplyScope>,
+        prune: Option<bool>,
+    ) -> Result<Box<str>, Error> {
+        check_device_access(ctx, &self.device_config)?;
+        let options = PlanOptions::for_device(&self.device_config, scope, prune.unwrap_or(false));
+        Ok(mask_secrets(&ProvisionPlan::create(self, &options).await?.script()).into_boxed_str())
+    }
+    /// changes to bring the device to the generated configuration, in the order to apply them,
+    /// restricted to the resource categories of the scope. With `prune` the removal of managed
+    /// resources missing in the generated configuration is proposed as well.
+    async fn plan(
+        &self,
+        ctx: &Context<'_>,
+        scope: Option<ApplyScope>,
+        prune: Option<bool>,
+    ) -> Result<ProvisionPlan, Error> {
+        check_device_access(ctx, &self.device_config)?;
+        let options = PlanOptions::for_device(&self.device_config, scope, prune.unwrap_or(false));
+        ProvisionPlan::create(self, &options).await
     }
 }
@@ -1,3 +1,4 @@
+use lazy_static::lazy_static;
 use mikrotik_model::{
     hwconfig::{
         ADVERTISE_1G, ADVERTISE_1G_FULL, ADVERTISE_1G_SFP, ADVERTISE_10G, ADVERTISE_10G_FULL,
@@ -7,7 +8,34 @@ use mikrotik_model::{
         InterfaceEthernetByDefaultName, InterfaceWifiByDefaultName, InterfaceWirelessByDefaultName,
     },
 };
-use std::iter::repeat_n;
+use std::{
+    collections::HashMap,
+    iter::repeat_n,
+    sync::{Arc, Mutex},
+};
+
+type PortTemplate = Arc<[InterfaceEthernetByDefaultName]>;
+
+lazy_static! {
+    static ref ETHERNET_PORT_TEMPLATES: Mutex<HashMap<Box<[u8]>, PortTemplate>> = Mutex::default();
+}
+
+/// Ethernet ports of the model, built from the hardware facts on the first use and shared by
+/// every target of the model afterward. Unknown models are not kept, their names come from
+/// the requests.
+pub fn ethernet_ports(model: &[u8]) -> PortTemplate {
+    let mut templates = ETHERNET_PORT_TEMPLATES
+        .lock()
+        .expect("port templates poisoned");
+    if let Some(ports) = templates.get(model) {
+        return ports.clone();
+    }
+    let ports = PortTemplate::from(build_ethernet_ports(model));
+    if !ports.is_empty() {
+        templates.insert(model.into(), ports.clone());
+    }
+    ports
+}
 
 pub fn build_ethernet_ports(model: &[u8]) -> Box<[InterfaceEthernetByDefaultName]> {
     match model {
@@ -1,7 +1,11 @@
-use crate::topology::{
-    InterfaceId, PhysicalPortId, VlanId,
-    access::{
-        cable::CablePortAccess, device::DeviceAccess, interface::InterfaceAccess, vlan::VlanAccess,
+use crate::{
+    device::ros::SetupError,
+    topology::{
+        InterfaceId, PhysicalPortId, VlanId,
+        access::{
+            AccessTopology, cable::CablePortAccess, device::DeviceAccess,
+            interface::InterfaceAccess, vlan::VlanAccess,
+        },
     },
 };
 use convert_case::{Case, Casing};
@@ -10,6 +14,7 @@ use std::{
     borrow::Cow,
     cmp::Ordering,
     collections::{BTreeMap, HashMap, HashSet},
+    ops::RangeInclusive,
 };
 
 #[cfg(test)]
@@ -25,12 +30,29 @@ pub enum L2Port {
         name: AsciiString,
         port: PhysicalPortId,
     },
+    TaggedEoip {
+        name: AsciiString,
+    },
+    UntaggedEoip {
+        name: AsciiString,
+    },
     VxLan {
         name: AsciiString,
     },
     Caps,
 }
 
+impl L2Port {
+    fn new(name: AsciiString, port: Option<PhysicalPortId>, tagged: bool) -> Self {
+        match (port, tagged) {
+            (Some(port), true) => L2Port::TaggedEthernet { name, port },
+            (Some(port), false) => L2Port::UntaggedEthernet { name, port },
+            (None, true) => L2Port::TaggedEoip { name },
+            (None, false) => L2Port::UntaggedEoip { name },
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct L2Plane {
     pub ports: Vec<L2Port>,
@@ -43,6 +65,32 @@ pub struct L2Setup {
     pub planes: Vec<L2Plane>,
 }
 
+impl L2Setup {
+    /// the setup without the bridge ports of the given tunnels, e.g. of tunnels not generated
+    pub fn without_tunnels(&self, tunnels: &HashSet<AsciiString>) -> L2Setup {
+        L2Setup {
+            planes: self
+                .planes
+                .iter()
+                .map(|plane| L2Plane {
+                    ports: plane
+                        .ports
+                        .iter()
+                        .filter(|port| match port {
+                            L2Port::TaggedEoip { name } | L2Port::UntaggedEoip { name } => {
+                                !tunnels.contains(name)
+                            }
+                            _ => true,
+                        })
+                        .cloned()
+                        .collect(),
+                    ..plane.clone()
+                })
+                .collect(),
+        }
+    }
+}
+
 pub trait NameGenerator {
     fn generate_interface_name<'s>(&mut self, interface: &'s InterfaceAccess) -> Cow<'s, str>;
 }
@@ -108,7 +156,13 @@ impl NameGenerator for EndpointNameGenerator {
 }
 
 impl L2Setup {
-    pub fn new<G: NameGenerator>(device: &DeviceAccess, name_generator: &mut G) -> Self {
+    /// Bridge planes without a netbox vlan get the lowest vlan id of `internal_vlans` no vlan on
+    /// the interfaces of the device uses
+    pub fn new<G: NameGenerator>(
+        device: &DeviceAccess,
+        name_generator: &mut G,
+        internal_vlans: RangeInclusive<u16>,
+    ) -> Result<Self, SetupError> {
         let mut planes =
             BTreeMap::<(InterfaceId, Option<VlanId>), (InterfaceAccess, Vec<L2Port>)>::new();
         let mut vlans = HashMap::new();
@@ -116,29 +170,29 @@ impl L2Setup {
             let root_device = interface.bridge().unwrap_or(interface.clone());
             let bridge_id = root_device.id();
             let mut vlan_added = false;
-            if let Some(port) = interface.external_port() {
-                let name = name_generator.generate_interface_name(&interface);
+            let port = interface.external_port();
+            // eoip tunnels are bridged like ethernet ports
+            if port.is_some() || interface.is_eoip_tunnel() {
+                let name: AsciiString = name_generator
+                    .generate_interface_name(&interface)
+                    .into_owned()
+                    .into();
+                // trunks towards other devices only carry the vlans needed behind them
+                let needed = port.and_then(|_| far_end_vlans(&interface));
 
                 for (vlan, port) in interface
                     .tagged_vlans()
-                    .map(|vlan| {
-                        (
-                            vlan,
-                            L2Port::TaggedEthernet {
-                                name: name.to_string().into(),
-                                port,
-                            },
-                        )
+                    .filter(|vlan| {
+                        needed
+                            .as_ref()
+                            .is_none_or(|needed| needed.contains(&vlan.id))
                     })
-                    .chain(interface.untagged_vlan().map(|vlan| {
-                        (
-                            vlan,
-                            L2Port::UntaggedEthernet {
-                                name: name.to_string().into(),
-                                port,
-                            },
-                        )
-                    }))
+                    .map(|vlan| (vlan, L2Port::new(name.clone(), port, true)))
+                    .chain(
+                        interface
+                            .untagged_vlan()
+                            .map(|vlan| (vlan, L2Port::new(name.clone(), port, false))),
+                    )
                 {
                     vlan_added = true;
                     planes
@@ -153,45 +207,83 @@ impl L2Setup {
                         .entry((bridge_id, None))
                         .or_insert_with(|| (root_device.clone(), Vec::new()))
                         .1
-                        .push(L2Port::UntaggedEthernet {
-                            name: name.to_string().into(),
-                            port,
-                        });
+                        .push(L2Port::new(name, port, false));
                 }
             }
         }
-        let mut used_vlans = planes
-            .keys()
-            .filter_map(|(_, v)| v.as_ref())
-            .filter_map(|id| vlans.get(id))
+        // also the vlans not bridged here, like the vlans of routed ports and of pruned trunks
+        let mut used_vlans = device
+            .interfaces()
+            .flat_map(|interface| {
+                interface
+                    .tagged_vlans()
+                    .chain(interface.untagged_vlan())
+                    .collect::<Vec<_>>()
+            })
             .filter_map(|vlan| vlan.vlan_id())
             .collect::<HashSet<_>>();
-        L2Setup {
-            planes: planes
-                .into_iter()
-                .map(|((_, vlan), (root_port, ports))| {
-                    let vlan = vlan.and_then(|id| vlans.get(&id).cloned());
-                    let vlan_id = if let Some(vlan_id) = vlan.as_ref().and_then(|vl| vl.vlan_id()) {
-                        vlan_id
-                    } else {
-                        let mut candidate = 60000;
-                        while !used_vlans.insert(candidate) {
-                            candidate += 1;
-                        }
-                        candidate
-                    };
-
-                    L2Plane {
-                        ports,
-                        vlan,
-                        vlan_id,
-                        root_port,
-                    }
+        let mut free_vlans = internal_vlans.clone();
+        let planes = planes
+            .into_iter()
+            .map(|((_, vlan), (root_port, ports))| {
+                let vlan = vlan.and_then(|id| vlans.get(&id).cloned());
+                let vlan_id = if let Some(vlan_id) = vlan.as_ref().and_then(|vl| vl.vlan_id()) {
+                    vlan_id
+                } else {
+                    free_vlans
+                        .find(|candidate| used_vlans.insert(*candidate))
+                        .ok_or(SetupError::InternalVlansExhausted {
+                            start: *internal_vlans.start(),
+                            end: *internal_vlans.end(),
+                        })?
+                };
+                Ok(L2Plane {
+                    ports,
+                    vlan,
+                    vlan_id,
+                    root_port,
                 })
-                .collect(),
+            })
+            .collect::<Result<_, SetupError>>()?;
+        Ok(L2Setup { planes })
+    }
+}
+/// VLANs the devices at the far end of the cable paths of a port terminate. A switch needs the
+/// vlans of its other interfaces, an end device the vlans of the port facing us. None if no
+/// far end device has vlans, the port carries all its tagged vlans then.
+fn far_end_vlans(interface: &InterfaceAccess) -> Option<HashSet<VlanId>> {
+    let own_device = interface.device().map(|device| device.id());
+    let mut needed = HashSet::new();
+    for far_port in interface.connected_interfaces() {
+        let Some(device) = far_port.device() else {
+            continue;
+        };
+        if Some(device.id()) == own_device {
+            continue;
         }
+        let port_vlans = |port: &InterfaceAccess| {
+            port.untagged_vlan()
+                .into_iter()
+                .chain(port.tagged_vlans())
+                .map(|vlan| vlan.id)
+                .collect::<Vec<_>>()
+        };
+        let behind = device
+            .interfaces()
+            .iter()
+            .filter(|other| **other != far_port)
+            .flat_map(port_vlans)
+            .collect::<Vec<_>>();
+        if behind.is_empty() {
+            needed.extend(port_vlans(&far_port));
+        } else {
+            needed.extend(behind);
+        }
+        needed.extend(device.vlans().map(|vlan| vlan.id));
     }
+    (!needed.is_empty()).then_some(needed)
 }
+
 impl PartialOrd for L2Plane {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
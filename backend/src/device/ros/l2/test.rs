@@ -1,55 +1,74 @@
 use crate::{
+    config::GenerationProfile,
     device::ros::{
-        BaseDeviceDataCurrent, BaseDeviceDataTarget, SwitchVlanConcept,
-        l2::{KeepNameGenerator, L2Setup},
+        BaseDeviceDataCurrent, BaseDeviceDataTarget, SetupError, SwitchVlanConcept, empty_current,
+        l2::{KeepNameGenerator, L2Port, L2Setup},
+        render_script,
+        warnings::Warnings,
     },
     topology::{
-        Device, Interface, IpAddressData, IpAddressId, PhysicalPortId, TopologyHolder, VlanData,
-        VlanGroupData, access::device::DeviceAccess, test::TopologyBuilder,
+        Device, DeviceId, Interface, IpAddressData, IpAddressId, PhysicalPortId, TopologyHolder,
+        VlanData, VlanGroupData,
+        access::device::DeviceAccess,
+        snapshot::{FixtureFormat, parse_topology},
+        test::TopologyBuilder,
     },
 };
 use ipnet::IpNet;
-use mikrotik_model::{
-    generator::Generator,
-    model::{InterfaceEthernetByDefaultName, InterfaceEthernetCfg, ReferenceType},
-    resource::ResourceMutation,
-};
+use mikrotik_model::ascii::AsciiString;
 use std::{
     collections::HashMap,
     error::Error,
     net::{IpAddr, Ipv4Addr},
+    ops::RangeInclusive,
+    path::Path,
 };
 
+const INTERNAL_VLANS: RangeInclusive<u16> = 3900..=3999;
+
 #[tokio::test]
 async fn test_l2_one_vlan() {
     let device = create_device_with_ports(1, 1, 3).await;
-    let setup = L2Setup::new(&device, &mut KeepNameGenerator);
+    let setup = L2Setup::new(&device, &mut KeepNameGenerator, INTERNAL_VLANS)
+        .expect("internal vlans exhausted");
     println!("Setup: {:#?}", setup);
 }
 #[tokio::test]
 async fn test_l2_no_vlan() -> Result<(), Box<dyn Error>> {
     let device = create_device_with_ports(1, 0, 3).await;
     let (mut target_data, empty_current) = setup_testdata(b"CRS326-24G-2S+")?;
-    target_data.generate_from(&device)?;
+    target_data.generate_from(
+        &device,
+        &GenerationProfile::default(),
+        None,
+        &HashMap::new(),
+        &HashMap::new(),
+        &HashMap::new(),
+        Path::new(""),
+        &HashMap::new(),
+        &mut Warnings::default(),
+    )?;
     dump_mutations(&target_data, &empty_current)?;
     Ok(())
 }
 #[tokio::test]
 async fn test_l2_multi_vlan() {
     let device = create_device_with_ports(1, 3, 3).await;
-    let setup = L2Setup::new(&device, &mut KeepNameGenerator);
+    let setup = L2Setup::new(&device, &mut KeepNameGenerator, INTERNAL_VLANS)
+        .expect("internal vlans exhausted");
     println!("Setup: {:#?}", setup);
 }
 #[tokio::test]
 async fn test_l2_untagged_switch() {
     let device = create_device_with_ports(1, 5, 24).await;
-    let setup = L2Setup::new(&device, &mut KeepNameGenerator);
+    let setup = L2Setup::new(&device, &mut KeepNameGenerator, INTERNAL_VLANS)
+        .expect("internal vlans exhausted");
     println!("Setup: {:#?}", setup);
 }
 #[tokio::test]
 async fn test_l2_multi_untagged_switch() -> Result<(), Box<dyn Error>> {
     let device = create_device_with_ports(5, 0, 24).await;
-    let setup = L2Setup::new(&device, &mut KeepNameGenerator);
+    let setup = L2Setup::new(&device, &mut KeepNameGenerator, INTERNAL_VLANS)?;
     println!("Setup: {:#?}", setup);
     let (mut target_data, empty_current) = setup_testdata(b"CRS326-24G-2S+")?;
     let mut mapped_planes = Vec::new();
@@ -58,28 +77,203 @@ async fn test_l2_multi_untagged_switch() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_l2_internal_vlans_from_range() {
+    let device = create_device_with_ports(5, 0, 24).await;
+    let setup = L2Setup::new(&device, &mut KeepNameGenerator, INTERNAL_VLANS)
+        .expect("internal vlans exhausted");
+    let mut vlan_ids = setup
+        .planes
+        .iter()
+        .map(|plane| plane.vlan_id)
+        .collect::<Vec<_>>();
+    assert!(vlan_ids.iter().all(|id| INTERNAL_VLANS.contains(id)));
+    vlan_ids.sort();
+    vlan_ids.dedup();
+    assert_eq!(setup.planes.len(), vlan_ids.len());
+}
+
+#[tokio::test]
+async fn test_l2_internal_vlans_skip_own_vlans() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: switch
+    ports:
+      - !Interface 1
+      - !Interface 2
+      - !Interface 3
+  2:
+    name: other
+    ports:
+      - !Interface 4
+interfaces:
+  1:
+    name: bridge
+    device: 1
+  2:
+    name: ether1
+    device: 1
+    external: !Ethernet 1
+    bridge: 1
+  3:
+    name: ether2
+    device: 1
+    external: !Ethernet 2
+    bridge: 1
+    vlan: 1
+  4:
+    name: ether1
+    device: 2
+    external: !Ethernet 1
+    vlan: 2
+vlan_groups:
+  1:
+    vlans: [1, 2]
+vlans:
+  1:
+    name: own
+    vlan_id: 3900
+    group: 1
+    terminations: [3]
+    wlans: []
+  2:
+    name: foreign
+    vlan_id: 3901
+    group: 1
+    terminations: [4]
+    wlans: []
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let setup = L2Setup::new(&device, &mut KeepNameGenerator, INTERNAL_VLANS)
+        .expect("internal vlans exhausted");
+    let mut vlan_ids = setup
+        .planes
+        .iter()
+        .map(|plane| plane.vlan_id)
+        .collect::<Vec<_>>();
+    vlan_ids.sort();
+    // the vlan of the other device does not reserve its id here
+    assert_eq!(vec![3900, 3901], vlan_ids);
+}
+
+#[tokio::test]
+async fn test_l2_internal_vlans_exhausted() {
+    let device = create_device_with_ports(5, 0, 24).await;
+    let result = L2Setup::new(&device, &mut KeepNameGenerator, 3900..=3901);
+    assert!(matches!(
+        result,
+        Err(SetupError::InternalVlansExhausted {
+            start: 3900,
+            end: 3901
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_l2_trunk_pruned_to_far_end_vlans() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: core
+    ports:
+      - !Interface 1
+      - !Interface 2
+      - !Interface 3
+  2:
+    name: access
+    ports:
+      - !Interface 4
+      - !Interface 5
+interfaces:
+  1:
+    name: bridge
+    device: 1
+  2:
+    name: ether1
+    device: 1
+    external: !Ethernet 1
+    bridge: 1
+    tagged_vlans: [1, 2]
+    cable: 1
+  3:
+    name: ether2
+    device: 1
+    external: !Ethernet 2
+    bridge: 1
+    vlan: 2
+  4:
+    name: ether1
+    device: 2
+    external: !Ethernet 1
+    tagged_vlans: [1, 2]
+    cable: 1
+  5:
+    name: ether2
+    device: 2
+    external: !Ethernet 2
+    vlan: 1
+vlan_groups:
+  1:
+    vlans: [1, 2]
+vlans:
+  1:
+    name: office
+    vlan_id: 10
+    group: 1
+    terminations: [5]
+    wlans: []
+  2:
+    name: servers
+    vlan_id: 20
+    group: 1
+    terminations: [3]
+    wlans: []
+cables:
+  1:
+    port_a:
+      - !Interface 2
+    port_b:
+      - !Interface 4
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let setup = L2Setup::new(&device, &mut KeepNameGenerator, INTERNAL_VLANS)
+        .expect("internal vlans exhausted");
+    let trunk_vlans = setup
+        .planes
+        .iter()
+        .filter(|plane| {
+            plane
+                .ports
+                .iter()
+                .any(|port| matches!(port, L2Port::TaggedEthernet { name, .. } if *name == AsciiString::from("ether1")))
+        })
+        .map(|plane| plane.vlan_id)
+        .collect::<Vec<_>>();
+    // the access switch only terminates vlan 10 behind the trunk
+    assert_eq!(vec![10], trunk_vlans);
+}
+
 fn dump_mutations(
     target_data: &BaseDeviceDataTarget,
     empty_current: &BaseDeviceDataCurrent,
 ) -> Result<(), Box<dyn Error>> {
     let mutations = target_data.generate_mutations(empty_current)?;
-    let mutations = ResourceMutation::sort_mutations_with_provided_dependencies(
-        mutations.as_ref(),
-        [
-            (ReferenceType::Interface, b"lo".into()),
-            (ReferenceType::RoutingTable, b"main".into()),
-            (ReferenceType::FirewallChain, b"input".into()),
-            (ReferenceType::FirewallChain, b"output".into()),
-            (ReferenceType::FirewallChain, b"forward".into()),
-        ],
-    )
-    .unwrap();
-    let mut cfg = String::new();
-    let mut generator = Generator::new(&mut cfg);
-    for mutation in mutations {
-        generator.append_mutation(mutation)?;
-    }
-    println!("{}", cfg.into_boxed_str());
+    println!("{}", render_script(&mutations)?);
     Ok(())
 }
 
@@ -87,39 +281,7 @@ fn setup_testdata(
     model: &[u8],
 ) -> Result<(BaseDeviceDataTarget, BaseDeviceDataCurrent), Box<dyn Error>> {
     let target_data = BaseDeviceDataTarget::new(model)?;
-    let empty_current = BaseDeviceDataCurrent {
-        ospf_interface: Box::new([]),
-        interface_list: Box::new([]),
-        identity: Default::default(),
-        bridge: Box::new([]),
-        bridge_port: Box::new([]),
-        ethernet: target_data
-            .ethernet
-            .iter()
-            .map(|(default_name, e)| InterfaceEthernetByDefaultName {
-                default_name: default_name.clone(),
-                data: InterfaceEthernetCfg {
-                    name: default_name.clone(),
-                    ..e.clone()
-                },
-            })
-            .collect(),
-        ipv_6_address: Box::new([]),
-        ospf_instance: Box::new([]),
-        vxlan: Box::new([]),
-        ospf_area: Box::new([]),
-        vxlan_vteps: Box::new([]),
-        interface_list_member: Box::new([]),
-        bridge_vlan: Box::new([]),
-        ipv_6_firewall_address_list: Box::new([]),
-        ipv_4_address: Box::new([]),
-        vlan: Box::new([]),
-        dhcp_v_4_client: Box::new([]),
-        ipv_6_firewall_filter: Box::new([]),
-        dhcp_v_4_server: Box::new([]),
-        dhcp_v_4_server_network: Box::new([]),
-        ipv_4_pool: Box::new([]),
-    };
+    let empty_current = empty_current(&target_data);
     Ok((target_data, empty_current))
 }
 
@@ -145,6 +307,7 @@ async fn create_device_with_ports(
                         .unwrap(),
                     interface: None,
                     prefix: None,
+                    vrf: None,
                 },
             );
             Box::new([ip_id])
@@ -174,6 +337,8 @@ async fn create_device_with_ports(
                     name: string.clone().into_boxed_str(),
                     vlan_id: vid + 1,
                     group: vlan_group,
+                    guest: false,
+                    captive_portal: false,
                     terminations: Box::new([]),
                     vxlan: None,
                     wlans: Box::new([]),
@@ -195,6 +360,7 @@ async fn create_device_with_ports(
                         .unwrap(),
                     interface: None,
                     prefix: None,
+                    vrf: None,
                 },
             );
             builder.interfaces.insert(
@@ -0,0 +1,99 @@
+use crate::device::ros::{BaseDeviceDataCurrent, BaseDeviceDataTarget, prune::find_orphans};
+use std::net::IpAddr;
+
+/// Changes of the generated configuration cutting the path the provisioner reaches the device
+/// on, each with the resource path of the change
+///
+/// The path is cut if the address the provisioner connects to moves to another interface or is
+/// disabled, if the interface carrying it becomes a bridge port, if its vlan changes or if it is
+/// removed in prune mode. An address not configured on the device, e.g. from a dhcp client, is
+/// not checked.
+pub fn management_path_cuts(
+    target: &BaseDeviceDataTarget,
+    current: &BaseDeviceDataCurrent,
+    address: IpAddr,
+    prune: bool,
+) -> Vec<(&'static str, Box<str>)> {
+    let mut cuts = Vec::new();
+    let (interface, target_interface) = match address {
+        IpAddr::V4(ip) => {
+            let Some(entry) = current
+                .ipv_4_address
+                .iter()
+                .find(|e| e.0.address.addr() == ip)
+            else {
+                return cuts;
+            };
+            let target_entry = target.ipv_4_address.get(&entry.0.address);
+            (
+                entry.0.interface.clone(),
+                target_entry.map(|t| (t.0.interface.clone(), "ip/address")),
+            )
+        }
+        IpAddr::V6(ip) => {
+            let Some(entry) = current
+                .ipv_6_address
+                .iter()
+                .find(|e| e.0.address.addr() == ip)
+            else {
+                return cuts;
+            };
+            let target_entry = target.ipv_6_address.get(&entry.0.address);
+            if target_entry.is_some_and(|t| t.0.disabled) {
+                cuts.push(("ipv6/address", format!("{address} is disabled").into()));
+            }
+            (
+                entry.0.interface.clone(),
+                target_entry.map(|t| (t.0.interface.clone(), "ipv6/address")),
+            )
+        }
+    };
+    if let Some((moved_to, path)) = target_interface.filter(|(moved_to, _)| *moved_to != interface)
+    {
+        cuts.push((
+            path,
+            format!("{address} moves from {interface} to {moved_to}").into(),
+        ));
+    }
+    if let Some((bridge, _)) = target
+        .bridge_port
+        .keys()
+        .find(|(_, port)| *port == interface)
+    {
+        cuts.push((
+            "interface/bridge/port",
+            format!("{interface} carrying {address} becomes a port of {bridge}").into(),
+        ));
+    }
+    if let Some(vlan) = current.vlan.iter().find(|v| v.0.name == interface) {
+        if let Some(target_vlan) = target.vlan.get(&interface) {
+            if target_vlan.0.interface != vlan.0.interface
+                || target_vlan.0.vlan_id != vlan.0.vlan_id
+            {
+                cuts.push((
+                    "interface/vlan",
+                    format!(
+                        "vlan {interface} carrying {address} changes from {} on {} to {} on {}",
+                        vlan.0.vlan_id,
+                        vlan.0.interface,
+                        target_vlan.0.vlan_id,
+                        target_vlan.0.interface
+                    )
+                    .into(),
+                ));
+            }
+        }
+    }
+    if prune {
+        if let Some(orphan) = find_orphans(target, current)
+            .into_iter()
+            .find(|orphan| orphan.name == interface)
+        {
+            cuts.push((
+                orphan.path,
+                format!("{interface} carrying {address} is removed").into(),
+            ));
+        }
+    }
+    cuts
+}
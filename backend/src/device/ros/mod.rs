@@ -1,44 +1,66 @@
 use crate::{
     Error,
+    config::{
+        ApnProfile, CONFIG, DhcpOptionSet, GenerationProfile, HotspotConfig, NetwatchTarget,
+        PppoeCredentials, Schedule,
+    },
     device::ros::{
-        hw_facts::build_ethernet_ports,
-        l2::{EndpointNameGenerator, L2Plane, L2Port, L2Setup},
+        hw_facts::ethernet_ports,
+        l2::{L2Plane, L2Port, L2Setup, NameGenerator},
+        naming::{InterfaceNames, NamingPolicy},
+        prune::MANAGED_MARKER,
+        version::RouterOsVersion,
+        warnings::{WarningKind, Warnings},
     },
+    provisioning::loopback::router_id,
+    secrets::read_secret,
     topology::{
-        IpPrefixId, PhysicalPortId,
+        FhrpProtocol, IpPrefixId, PhysicalPortId, PortType, TunnelEncapsulation,
         access::{
-            AccessTopology, device::DeviceAccess, interface::InterfaceAccess, vxlan::VxlanAccess,
+            AccessTopology, device::DeviceAccess, interface::InterfaceAccess,
+            ip_prefix::IpPrefixAccess, vxlan::VxlanAccess,
         },
     },
 };
-use convert_case::{Case, Casing};
-use ipnet::{IpAdd, IpNet, IpSub, Ipv4Net, Ipv6Net};
-use log::error;
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
 use mikrotik_model::{
     MikrotikDevice,
     ascii::{self, AsciiString},
+    generator::Generator,
     mikrotik_model,
     model::{
-        InterfaceBridgeProtocolMode, InterfaceEthernetCfg, InterfaceEthernetPoeOut,
-        InterfaceVlanByName, InterfaceVlanCfg, InterfaceVxlanByName, InterfaceVxlanCfg,
-        IpAddressByAddress, IpAddressCfg, IpDhcpClientCfg, Ipv6AddressByAddress, Ipv6AddressCfg,
-        RoutingOspfInstanceByName, RoutingOspfInstanceCfg, RoutingOspfInstanceVersion,
-        RoutingRedistribute, VlanFrameTypes, YesNo,
+        InterfaceBridgeProtocolMode, InterfaceEoipByName, InterfaceEoipCfg,
+        InterfaceEthernetByDefaultName, InterfaceEthernetCfg, InterfaceEthernetPoeOut,
+        InterfaceGreByName, InterfaceGreCfg, InterfaceLteByDefaultName, InterfaceLteCfg,
+        InterfaceVlanByName, InterfaceVlanCfg, InterfaceVrrpByName, InterfaceVrrpCfg,
+        InterfaceVxlanByName, InterfaceVxlanCfg, IpAddressByAddress, IpAddressCfg, IpDhcpClientCfg,
+        IpFirewallFilterAction, IpFirewallNatAction, IpHotspotProfileLoginBy,
+        IpHotspotWalledGardenAction, IpRouteCheckGateway, IpUpnpInterfacesType,
+        Ipv6AddressByAddress, Ipv6AddressCfg, ReferenceType, RoutingOspfInstanceByName,
+        RoutingOspfInstanceCfg, RoutingOspfInstanceVersion, RoutingRedistribute, VlanFrameTypes,
+        YesNo,
     },
+    resource::ResourceMutation,
     value,
 };
 use std::{
-    collections::{
-        BTreeMap, BTreeSet, HashMap, HashSet,
-        btree_map::{Entry, Iter},
-    },
-    net::{IpAddr, Ipv4Addr, Ipv6Addr},
-    ops::Range,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, btree_map::Entry},
+    net::{IpAddr, Ipv4Addr},
+    path::Path,
 };
 
+mod capsman;
 mod graphql;
 mod hw_facts;
+pub mod management;
+pub mod naming;
+pub mod prune;
+mod scripts;
+pub mod version;
+pub mod warnings;
 
+#[cfg(test)]
+mod golden;
 mod l2;
 #[cfg(test)]
 mod test;
@@ -64,6 +86,9 @@ mikrotik_model!(
         ipv6_address(by_key(path = "ipv6/address", key = address)),
         vlan(by_key(path = "interface/vlan", key = name)),
         vxlan(by_key(path = "interface/vxlan", key = name)),
+        vrrp(by_key(path = "interface/vrrp", key = name)),
+        gre(by_key(path = "interface/gre", key = name)),
+        eoip(by_key(path = "interface/eoip", key = name)),
         vxlan_vteps(by_id(
             path = "interface/vxlan/vteps",
             keys(interface, remote_ip)
@@ -77,12 +102,233 @@ mikrotik_model!(
             keys(address, list)
         )),
         ipv6_firewall_filter(by_id(path = "ipv6/firewall/filter", keys())),
+        ipv4_firewall_filter(by_id(path = "ip/firewall/filter", keys(comment))),
         dhcp_v4_server(by_key(path = "ip/dhcp-server", key = name)),
         dhcp_v4_server_network(by_key(path = "ip/dhcp-server/network", key = address)),
+        dhcp_v4_server_option(by_key(path = "ip/dhcp-server/option", key = name)),
+        dhcp_v4_server_lease(by_key(path = "ip/dhcp-server/lease", key = address)),
+        dhcp_v4_relay(by_key(path = "ip/dhcp-relay", key = name)),
+        lte(by_key(path = "interface/lte", key = defaultName)),
+        lte_apn(by_key(path = "interface/lte/apn", key = name)),
+        ipv4_route(by_id(path = "ip/route", keys(comment))),
+        pppoe_client(by_key(path = "interface/pppoe-client", key = name)),
+        ipv4_firewall_nat(by_id(path = "ip/firewall/nat", keys(comment))),
+        upnp(single = "ip/upnp"),
+        upnp_interface(by_id(path = "ip/upnp/interfaces", keys(interface))),
+        igmp_proxy_interface(by_key(
+            path = "routing/igmp-proxy/interface",
+            key = interface
+        )),
         ipv4_pool(by_key(path = "ip/pool", key = name)),
+        vrf(by_key(path = "ip/vrf", key = name)),
+        netwatch(by_key(path = "tool/netwatch", key = host)),
+        ip_service(by_key(path = "ip/service", key = name)),
+        snmp_community(by_key(path = "snmp/community", key = name)),
+        hotspot_profile(by_key(path = "ip/hotspot/profile", key = name)),
+        hotspot_user_profile(by_key(path = "ip/hotspot/user/profile", key = name)),
+        hotspot(by_key(path = "ip/hotspot", key = name)),
+        hotspot_walled_garden(by_id(path = "ip/hotspot/walled-garden", keys(dst_host))),
     ),
 );
 
+/// distance of the default route over the wan interfaces
+const WAN_ROUTE_DISTANCE: u8 = 1;
+/// distance of the default route over the lte interfaces, above the wan and dhcp client routes
+const LTE_ROUTE_DISTANCE: u8 = 20;
+
+/// services reachable only from the management networks if the device has a management interface
+const MANAGEMENT_SERVICES: [&str; 5] = ["api", "api-ssl", "ssh", "winbox", "www-ssl"];
+/// default snmp community of RouterOS
+const SNMP_COMMUNITY: &str = "public";
+
+/// networks guests must not reach, the router itself stays reachable as gateway and dns
+fn private_networks() -> [Ipv4Net; 3] {
+    [
+        Ipv4Net::new_assert(Ipv4Addr::new(10, 0, 0, 0), 8),
+        Ipv4Net::new_assert(Ipv4Addr::new(172, 16, 0, 0), 12),
+        Ipv4Net::new_assert(Ipv4Addr::new(192, 168, 0, 0), 16),
+    ]
+}
+
+/// the generated name of the tunnel on the port is already taken by a tunnel of the same kind
+fn tunnel_collision(kind: &'static str, name: AsciiString, port: &InterfaceAccess) -> SetupError {
+    SetupError::NameCollision {
+        kind,
+        first: format!("{kind} {name}").into(),
+        second: format!("{kind} on {}", port.name()).into(),
+        name,
+    }
+}
+
+/// network of the clients behind the device, neither the out-of-band management nor an ospf
+/// transit network nor a loopback
+fn is_client_network(if_access: &InterfaceAccess) -> bool {
+    !if_access.is_management()
+        && !if_access.use_ospf()
+        && if_access.ips().iter().any(|ip| {
+            matches!(ip.net(), Some(IpNet::V4(_)))
+                && !ip.prefix().is_some_and(|prefix| prefix.is_loopback())
+        })
+}
+
+/// `lte` followed by the number of the modem, the name RouterOS gives the lte interfaces
+fn is_lte_default_name(name: &str) -> bool {
+    name.strip_prefix("lte")
+        .is_some_and(|modem| !modem.is_empty() && modem.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// references every RouterOS device has without configuring them
+fn provided_dependencies() -> [(ReferenceType, AsciiString); 5] {
+    [
+        (ReferenceType::Interface, b"lo".into()),
+        (ReferenceType::RoutingTable, b"main".into()),
+        (ReferenceType::FirewallChain, b"input".into()),
+        (ReferenceType::FirewallChain, b"output".into()),
+        (ReferenceType::FirewallChain, b"forward".into()),
+    ]
+}
+
+/// Renders the mutations as RouterOS script, ordered by their dependencies. The script is the
+/// concatenation of the [`render_steps`], the same as a plan applies.
+pub fn render_script(mutations: &[ResourceMutation]) -> Result<String, Error> {
+    Ok(render_steps(mutations)?
+        .into_iter()
+        .map(|step| step.script)
+        .collect())
+}
+
+/// named resource a mutation needs or creates, like the interface a vlan is put on
+pub type Reference = (ReferenceType, AsciiString);
+
+/// single mutation rendered as RouterOS script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedMutation {
+    /// resource path of the mutation, e.g. `ip/address`
+    pub resource: &'static str,
+    pub script: String,
+    /// references the mutation needs, they are created by earlier mutations or the device
+    pub depends: Box<[Reference]>,
+    /// references the mutation creates
+    pub provides: Box<[Reference]>,
+}
+
+/// Renders every mutation as its own script together with its resource path and references,
+/// ordered by their dependencies
+pub fn render_steps(mutations: &[ResourceMutation]) -> Result<Vec<RenderedMutation>, Error> {
+    let mutations = ResourceMutation::sort_mutations_with_provided_dependencies(
+        mutations,
+        provided_dependencies(),
+    )?;
+    let mut steps = Vec::new();
+    for mutation in mutations {
+        let mut script = String::new();
+        Generator::new(&mut script).append_mutation(mutation)?;
+        steps.push(RenderedMutation {
+            resource: mutation.resource,
+            script,
+            depends: mutation.depends.iter().cloned().collect(),
+            provides: mutation.provides.iter().cloned().collect(),
+        });
+    }
+    Ok(steps)
+}
+
+/// state of a freshly reset device with the ethernet ports of the target
+pub fn empty_current(target: &BaseDeviceDataTarget) -> BaseDeviceDataCurrent {
+    BaseDeviceDataCurrent {
+        ospf_interface: Box::new([]),
+        interface_list: Box::new([]),
+        identity: Default::default(),
+        bridge: Box::new([]),
+        bridge_port: Box::new([]),
+        ethernet: target
+            .ethernet
+            .iter()
+            .map(|(default_name, e)| InterfaceEthernetByDefaultName {
+                default_name: default_name.clone(),
+                data: InterfaceEthernetCfg {
+                    name: default_name.clone(),
+                    ..e.clone()
+                },
+            })
+            .collect(),
+        ipv_6_address: Box::new([]),
+        ospf_instance: Box::new([]),
+        vxlan: Box::new([]),
+        vrrp: Box::new([]),
+        gre: Box::new([]),
+        eoip: Box::new([]),
+        ospf_area: Box::new([]),
+        vxlan_vteps: Box::new([]),
+        interface_list_member: Box::new([]),
+        bridge_vlan: Box::new([]),
+        ipv_6_firewall_address_list: Box::new([]),
+        ipv_4_address: Box::new([]),
+        vlan: Box::new([]),
+        dhcp_v_4_client: Box::new([]),
+        ipv_6_firewall_filter: Box::new([]),
+        ipv_4_firewall_filter: Box::new([]),
+        dhcp_v_4_server: Box::new([]),
+        dhcp_v_4_server_network: Box::new([]),
+        dhcp_v_4_server_option: Box::new([]),
+        dhcp_v_4_server_lease: Box::new([]),
+        dhcp_v_4_relay: Box::new([]),
+        // the modem of a reset device dials with the default apn
+        lte: target
+            .lte
+            .keys()
+            .map(|default_name| InterfaceLteByDefaultName {
+                default_name: default_name.clone(),
+                data: InterfaceLteCfg {
+                    name: default_name.clone(),
+                    ..Default::default()
+                },
+            })
+            .collect(),
+        lte_apn: Box::new([]),
+        ipv_4_route: Box::new([]),
+        pppoe_client: Box::new([]),
+        ipv_4_firewall_nat: Box::new([]),
+        upnp: Default::default(),
+        upnp_interface: Box::new([]),
+        igmp_proxy_interface: Box::new([]),
+        ipv_4_pool: Box::new([]),
+        vrf: Box::new([]),
+        netwatch: Box::new([]),
+        ip_service: Box::new([]),
+        snmp_community: Box::new([]),
+        hotspot_profile: Box::new([]),
+        hotspot_user_profile: Box::new([]),
+        hotspot: Box::new([]),
+        hotspot_walled_garden: Box::new([]),
+    }
+}
+
+/// Base configuration of a reset device of the hardware model, generated with the profile only.
+/// The hotspot, dhcp options, apn profiles and pppoe accounts of the configuration are left
+/// out, devices without netwatch profiles are generated without a configuration file, e.g. in
+/// the benchmarks.
+pub fn generate_for_model(
+    device: &DeviceAccess,
+    model: &[u8],
+    profile: &GenerationProfile,
+) -> Result<Vec<ResourceMutation>, Error> {
+    let mut target = BaseDeviceDataTarget::new(model)?;
+    let current = empty_current(&target);
+    target.generate_from(
+        device,
+        profile,
+        None,
+        &HashMap::new(),
+        &HashMap::new(),
+        &HashMap::new(),
+        Path::new(""),
+        &HashMap::new(),
+        &mut Warnings::default(),
+    )?;
+    Ok(Vec::from_iter(target.generate_mutations(&current)?))
+}
+
 const CAPS_BRIDGE_NAME: &[u8; 11] = b"bridge-caps";
 const DEFAULT_BRIDGE_NAME: &[u8; 6] = b"switch";
 
@@ -100,6 +346,39 @@ pub enum SetupError {
     MissingPrefixOnIpAddress { ip: IpNet },
     #[error("Address on Prefix {prefix} not found")]
     MissingAddressOnPrefix { prefix: IpPrefixId },
+    #[error(
+        "The configuration needs {feature} ({path}), which RouterOS {version} does not support"
+    )]
+    UnsupportedFeature {
+        feature: &'static str,
+        path: Box<str>,
+        version: RouterOsVersion,
+    },
+    #[error("RouterOS {0} is not supported, the configuration is generated for RouterOS 7")]
+    UnsupportedVersion(RouterOsVersion),
+    #[error("Generated {kind} name {name} of {first} collides with {second}")]
+    NameCollision {
+        kind: &'static str,
+        name: AsciiString,
+        first: Box<str>,
+        second: Box<str>,
+    },
+    #[error("Port {port} of {device} needs vlans, but the single bridge is set up without")]
+    TaggedPortWithoutVlan { device: Box<str>, port: Box<str> },
+    #[error("Port {port} of {device} cannot be bridged, the generation does not support it yet")]
+    UnsupportedBridgePort { device: Box<str>, port: Box<str> },
+    #[error("No free vlan id left for internal bridge planes in {start}-{end}")]
+    InternalVlansExhausted { start: u16, end: u16 },
+    #[error("Name {name} is longer than the maximum interface name length {max_length}")]
+    NameTooLong {
+        name: AsciiString,
+        max_length: usize,
+    },
+    #[error("No free name left for {name} within the maximum interface name length {max_length}")]
+    NoFreeName {
+        name: AsciiString,
+        max_length: usize,
+    },
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
@@ -118,15 +397,31 @@ impl BaseDeviceDataTarget {
         Self::new(&routerboard.model.0)
     }
     fn new(model: &[u8]) -> Result<Self, SetupError> {
-        let ethernet_ports = build_ethernet_ports(model);
+        let ethernet_ports = ethernet_ports(model);
         if ethernet_ports.is_empty() {
             return Err(SetupError::NoPortsFound(AsciiString::from(model)));
         }
-        let result = Ok(Self {
-            ethernet: ethernet_ports
-                .into_iter()
-                .map(|e| (e.default_name, e.data))
-                .collect(),
+        Ok(Self::with_ethernet_ports(
+            ethernet_ports
+                .iter()
+                .map(|e| (e.default_name.clone(), e.data.clone())),
+        ))
+    }
+    /// Fallback for models without hardware facts, the ports found on the device are taken
+    /// as they are
+    pub fn from_current_ports(current: &BaseDeviceDataCurrent) -> Self {
+        Self::with_ethernet_ports(
+            current
+                .ethernet
+                .iter()
+                .map(|e| (e.default_name.clone(), e.data.clone())),
+        )
+    }
+    fn with_ethernet_ports(
+        ethernet_ports: impl IntoIterator<Item = (AsciiString, InterfaceEthernetCfg)>,
+    ) -> Self {
+        Self {
+            ethernet: ethernet_ports.into_iter().collect(),
             identity: Default::default(),
             bridge: Default::default(),
             bridge_port: Default::default(),
@@ -136,6 +431,9 @@ impl BaseDeviceDataTarget {
             ipv_4_address: Default::default(),
             ipv_6_address: Default::default(),
             vxlan: Default::default(),
+            vrrp: Default::default(),
+            gre: Default::default(),
+            eoip: Default::default(),
             vxlan_vteps: Default::default(),
             ospf_instance: Default::default(),
             ospf_interface: Default::default(),
@@ -144,11 +442,49 @@ impl BaseDeviceDataTarget {
             dhcp_v_4_client: Default::default(),
             ipv_6_firewall_address_list: Default::default(),
             ipv_6_firewall_filter: Default::default(),
+            ipv_4_firewall_filter: Default::default(),
             dhcp_v_4_server: Default::default(),
             dhcp_v_4_server_network: Default::default(),
+            dhcp_v_4_server_option: Default::default(),
+            dhcp_v_4_server_lease: Default::default(),
+            dhcp_v_4_relay: Default::default(),
+            lte: Default::default(),
+            lte_apn: Default::default(),
+            ipv_4_route: Default::default(),
+            pppoe_client: Default::default(),
+            ipv_4_firewall_nat: Default::default(),
+            upnp: Default::default(),
+            upnp_interface: Default::default(),
+            igmp_proxy_interface: Default::default(),
             ipv_4_pool: Default::default(),
-        });
-        result
+            vrf: Default::default(),
+            netwatch: Default::default(),
+            ip_service: Default::default(),
+            snmp_community: Default::default(),
+            hotspot_profile: Default::default(),
+            hotspot_user_profile: Default::default(),
+            hotspot: Default::default(),
+            hotspot_walled_garden: Default::default(),
+        }
+    }
+    /// Takes the services and snmp communities as they are on the device. The generation only
+    /// restricts their addresses, a changed port or a disabled service is kept.
+    pub fn keep_current_services(&mut self, current: &BaseDeviceDataCurrent) {
+        for service in current.ip_service.iter() {
+            self.ip_service
+                .insert(service.0.name.clone(), service.clone());
+        }
+        for community in current.snmp_community.iter() {
+            self.snmp_community
+                .insert(community.0.name.clone(), community.clone());
+        }
+    }
+    /// serves api-ssl with the managed certificate of the device, the service is taken from the
+    /// device by [`Self::keep_current_services`] and left alone on devices without it
+    fn use_certificate(&mut self, name: &str) {
+        if let Some(service) = self.ip_service.get_mut(&AsciiString::from("api-ssl")) {
+            service.0.certificate = name.into();
+        }
     }
     fn set_identity(&mut self, name: impl Into<AsciiString>) {
         self.identity.name = name.into();
@@ -163,7 +499,10 @@ impl BaseDeviceDataTarget {
         for plane in &setup.planes {
             for port in &plane.ports {
                 match port {
-                    L2Port::TaggedEthernet { name, .. } | L2Port::UntaggedEthernet { name, .. } => {
+                    L2Port::TaggedEthernet { name, .. }
+                    | L2Port::UntaggedEthernet { name, .. }
+                    | L2Port::TaggedEoip { name }
+                    | L2Port::UntaggedEoip { name } => {
                         *plane_count_of_port.entry(name).or_default() += 1;
                     }
                     _ => {}
@@ -172,6 +511,14 @@ impl BaseDeviceDataTarget {
         }
         let mut switch_planes = Vec::new();
         for plane in &setup.planes {
+            let unsupported_port = |port: &str| SetupError::UnsupportedBridgePort {
+                device: plane
+                    .root_port
+                    .device()
+                    .map(|device| device.name().into())
+                    .unwrap_or_default(),
+                port: port.into(),
+            };
             let mut ports = Vec::new();
             let mut addresses = Vec::new();
             let mut enable_dhcp = false;
@@ -198,18 +545,19 @@ impl BaseDeviceDataTarget {
                         }
                         ports.push(port);
                     }
-                    L2Port::VxLan { .. } => {
+                    L2Port::TaggedEoip { .. } | L2Port::UntaggedEoip { .. } => {
                         ports.push(port);
-                        todo!("Define vxlan")
+                    }
+                    L2Port::VxLan { name } => {
+                        return Err(unsupported_port(&name.to_string()));
                     }
                     L2Port::Caps => {
-                        ports.push(port);
-                        todo!("define caps")
+                        return Err(unsupported_port("caps"));
                     }
                 }
             }
             match ports.as_slice() {
-                [L2Port::UntaggedEthernet { name, .. }]
+                [L2Port::UntaggedEthernet { name, .. } | L2Port::UntaggedEoip { name }]
                     if plane_count_of_port.get(name) == Some(&1) =>
                 {
                     mapped_planes
@@ -231,6 +579,8 @@ impl BaseDeviceDataTarget {
                 plane.ports.iter().all(|port| match port {
                     L2Port::TaggedEthernet { .. } => false,
                     L2Port::UntaggedEthernet { .. } => true,
+                    L2Port::TaggedEoip { .. } => false,
+                    L2Port::UntaggedEoip { .. } => true,
                     L2Port::VxLan { .. } => false,
                     L2Port::Caps => false,
                 })
@@ -239,7 +589,7 @@ impl BaseDeviceDataTarget {
             } else {
                 &[]
             } {
-                self.setup_single_switch_without_vlan(single_plane, mapped_planes);
+                self.setup_single_switch_without_vlan(single_plane, mapped_planes)?;
             } else {
                 match concept {
                     SwitchVlanConcept::OneBridge => {
@@ -271,11 +621,11 @@ impl BaseDeviceDataTarget {
             ));
             for port in &plane.ports {
                 match port {
-                    L2Port::TaggedEthernet { name, .. } => {
+                    L2Port::TaggedEthernet { name, .. } | L2Port::TaggedEoip { name } => {
                         tags_of_port.entry(name).or_default().1.push(plane.vlan_id);
                         ports_of_vlan.entry(plane.vlan_id).or_default().1.push(name);
                     }
-                    L2Port::UntaggedEthernet { name, .. } => {
+                    L2Port::UntaggedEthernet { name, .. } | L2Port::UntaggedEoip { name } => {
                         tags_of_port.entry(name).or_default().0 = Some(plane.vlan_id);
                         ports_of_vlan.entry(plane.vlan_id).or_default().0.push(name);
                     }
@@ -319,7 +669,15 @@ impl BaseDeviceDataTarget {
         &mut self,
         single_plane: &L2Plane,
         mapped_planes: &mut Vec<(InterfaceAccess, MappedPlane)>,
-    ) {
+    ) -> Result<(), SetupError> {
+        let tagged_port = |port: &str| SetupError::TaggedPortWithoutVlan {
+            device: single_plane
+                .root_port
+                .device()
+                .map(|device| device.name().into())
+                .unwrap_or_default(),
+            port: port.into(),
+        };
         let bridge = self.bridge.entry(DEFAULT_BRIDGE_NAME.into()).or_default();
         bridge.0.vlan_filtering = false;
         bridge.0.ingress_filtering = Some(false);
@@ -330,23 +688,24 @@ impl BaseDeviceDataTarget {
         ));
         for port in &single_plane.ports {
             match port {
-                L2Port::TaggedEthernet { .. } => {
-                    panic!("Cannot create tagged port on switch without vlan")
+                L2Port::TaggedEthernet { name, .. } | L2Port::TaggedEoip { name } => {
+                    return Err(tagged_port(&name.to_string()));
                 }
-                L2Port::UntaggedEthernet { name, .. } => {
+                L2Port::UntaggedEthernet { name, .. } | L2Port::UntaggedEoip { name } => {
                     self.bridge_port
                         .entry((DEFAULT_BRIDGE_NAME.into(), name.clone()))
                         .or_default()
                         .frame_types = VlanFrameTypes::AdmitOnlyUntaggedAndPriorityTagged;
                 }
-                L2Port::VxLan { .. } => {
-                    panic!("Cannot create tagged port on switch without vlan")
+                L2Port::VxLan { name } => {
+                    return Err(tagged_port(&name.to_string()));
                 }
                 L2Port::Caps => {
-                    panic!("Cannot create tagged port on switch without vlan")
+                    return Err(tagged_port("caps"));
                 }
             }
         }
+        Ok(())
     }
 
     fn get_ethernet_port(
@@ -396,34 +755,496 @@ impl BaseDeviceDataTarget {
             }
         }
     }
-    fn generate_from(&mut self, device: &DeviceAccess) -> Result<(), SetupError> {
+    #[allow(clippy::too_many_arguments)]
+    fn generate_from(
+        &mut self,
+        device: &DeviceAccess,
+        profile: &GenerationProfile,
+        hotspot: Option<&HotspotConfig>,
+        dhcp_option_sets: &HashMap<Box<str>, DhcpOptionSet>,
+        apn_profiles: &HashMap<Box<str>, ApnProfile>,
+        pppoe_credentials: &HashMap<Box<str>, PppoeCredentials>,
+        secrets_dir: &Path,
+        schedules: &HashMap<Box<str>, Schedule>,
+        warnings: &mut Warnings,
+    ) -> Result<(), SetupError> {
         self.set_identity(device.name());
         if let Some(loopback_ip) = device.loopback_ip().and_then(|ip| ip.addr()) {
             self.set_loopback_ip(loopback_ip);
         }
 
-        let l2 = L2Setup::new(device, &mut EndpointNameGenerator);
+        let mut names = InterfaceNames::new(
+            device,
+            NamingPolicy::new(profile.interface_name_max_length)?,
+        )?;
+        for (generated, name) in names.renamed() {
+            warnings.push(
+                WarningKind::FallbackName,
+                format!("Interface {generated} is named {name} on the device"),
+            );
+        }
+        let l2 = L2Setup::new(device, &mut names, profile.internal_vlans())?;
         let mut mapped_planes = Vec::new();
-        self.setup_l2(&l2, SwitchVlanConcept::OneBridge, &mut mapped_planes)?;
+        let skipped_tunnels =
+            self.setup_tunnels(device, &mut names, &mut mapped_planes, warnings)?;
+        self.setup_l2(
+            &l2.without_tunnels(&skipped_tunnels),
+            SwitchVlanConcept::OneBridge,
+            &mut mapped_planes,
+        )?;
         for port in device.interfaces() {
+            if port.port_type().is_none() {
+                warnings.push(
+                    WarningKind::UnknownInterfaceType,
+                    format!("Interface {} has an unknown type in netbox", port.name()),
+                );
+            }
             if let Some(port_id) = port.external_port() {
                 if let Some(ethernet_port) = self.get_ethernet_port(port_id)? {
-                    ethernet_port.poe_out = if port.enable_poe() {
-                        Some(InterfaceEthernetPoeOut::AutoOn)
-                    } else {
-                        Some(InterfaceEthernetPoeOut::Off)
+                    // ports without poe output have no poe setting in the hardware facts
+                    if ethernet_port.poe_out.is_none() {
+                        if port.enable_poe() {
+                            warnings.push(
+                                WarningKind::UnsupportedPoe,
+                                format!(
+                                    "Poe is enabled on {}, which has no poe output",
+                                    port.name()
+                                ),
+                            );
+                        }
+                        continue;
+                    }
+                    // a scheduler switches the poe of scheduled ports later on, until then
+                    // they are in the state of the schedule
+                    let schedule = port.schedule().and_then(|name| schedules.get(name));
+                    ethernet_port.poe_out = match (port.enable_poe(), schedule) {
+                        (true, Some(schedule)) if !schedule.is_on_now() => {
+                            Some(InterfaceEthernetPoeOut::Off)
+                        }
+                        (true, _) => Some(InterfaceEthernetPoeOut::AutoOn),
+                        (false, _) => Some(InterfaceEthernetPoeOut::Off),
                     };
                 }
             }
         }
-        self.setup_ip_addresses(&mapped_planes);
-        self.setup_ospf(device, &mapped_planes);
-        self.setup_wlan_ap(device);
+        let policy = names.policy();
+        self.setup_ip_addresses(&mapped_planes, policy, profile, dhcp_option_sets, warnings)?;
+        if let Some(hotspot) = hotspot {
+            self.setup_hotspots(&mapped_planes, policy, hotspot)?;
+        }
+        if profile.ospf {
+            self.setup_ospf(device, &mapped_planes, policy)?;
+        }
+        if profile.pppoe_client {
+            self.setup_pppoe(
+                device,
+                &mapped_planes,
+                policy,
+                pppoe_credentials,
+                secrets_dir,
+                warnings,
+            )?;
+        }
+        self.setup_upnp_igmp_proxy(device, profile, &mapped_planes, policy, warnings)?;
+        self.setup_lte(device, apn_profiles, secrets_dir, warnings);
+        self.setup_wlan_caps_bridge(device, policy)?;
+        if profile.netwatch {
+            self.setup_netwatch(device, warnings);
+        }
+        self.restrict_management_access(device, policy);
+        self.check_interface_names()?;
+        prune::mark_managed(self);
+        Ok(())
+    }
+
+    /// limits the management services and snmp to the networks of the out-of-band interfaces,
+    /// nothing is restricted on devices without management interface
+    fn restrict_management_access(&mut self, device: &DeviceAccess, policy: &NamingPolicy) {
+        let prefixes = device.management_prefixes();
+        if prefixes.is_empty() {
+            return;
+        }
+        let vrf = device
+            .management_interfaces()
+            .iter()
+            .find_map(|interface| interface.vrf())
+            .as_ref()
+            .and_then(|vrf| policy.vrf_name(vrf));
+        for service in MANAGEMENT_SERVICES {
+            let service = &mut self.ip_service.entry(service.into()).or_default().0;
+            service.address = prefixes.iter().copied().collect();
+            if let Some(vrf) = &vrf {
+                service.vrf = vrf.clone();
+            }
+        }
+        let community = &mut self
+            .snmp_community
+            .entry(SNMP_COMMUNITY.into())
+            .or_default()
+            .0;
+        community.addresses = prefixes.iter().copied().collect();
+    }
+
+    /// dials the lte interfaces into the apn of their profile. The apn adds a default route with
+    /// a higher distance than the wan interfaces, whose default route is given up while their
+    /// gateway does not answer pings, so the traffic fails over to lte and is masqueraded there.
+    /// RouterOS numbers the modems `lte1`, `lte2`, .. so a netbox name of another form is taken
+    /// as the name of the next modem. The password of the apn is read from the secrets directory.
+    fn setup_lte(
+        &mut self,
+        device: &DeviceAccess,
+        apn_profiles: &HashMap<Box<str>, ApnProfile>,
+        secrets_dir: &Path,
+        warnings: &mut Warnings,
+    ) {
+        let lte_ports = device
+            .interfaces()
+            .into_iter()
+            .filter(|port| port.port_type() == Some(PortType::Lte))
+            .collect::<Vec<_>>();
+        if lte_ports.is_empty() {
+            return;
+        }
+        let taken = lte_ports
+            .iter()
+            .map(|port| port.name())
+            .filter(|name| is_lte_default_name(name))
+            .collect::<HashSet<_>>();
+        let mut free_names = (1..)
+            .map(|modem| format!("lte{modem}"))
+            .filter(|name| !taken.contains(name.as_str()));
+        for port in &lte_ports {
+            let default_name = AsciiString::from(if is_lte_default_name(port.name()) {
+                port.name().to_string()
+            } else {
+                free_names.next().unwrap_or_default()
+            });
+            let Some(profile_name) = port.apn() else {
+                warnings.push(
+                    WarningKind::SkippedResource,
+                    format!(
+                        "Lte interface {} has no apn profile, tag it with apn-<profile>",
+                        port.name()
+                    ),
+                );
+                continue;
+            };
+            let Some(profile) = apn_profiles.get(profile_name) else {
+                warnings.push(
+                    WarningKind::SkippedResource,
+                    format!("Apn profile {profile_name} not configured"),
+                );
+                continue;
+            };
+            let password = match profile
+                .password_name()
+                .map(|name| read_secret(secrets_dir, name))
+                .transpose()
+            {
+                Ok(password) => password,
+                Err(error) => {
+                    warnings.push(
+                        WarningKind::SkippedResource,
+                        format!(
+                            "No password for apn profile {profile_name}, lte interface {} is not set up: {error}",
+                            port.name()
+                        ),
+                    );
+                    continue;
+                }
+            };
+            let apn_name = AsciiString::from(format!("apn-{profile_name}"));
+            let apn = &mut self.lte_apn.entry(apn_name.clone()).or_default().0;
+            apn.apn = profile.apn.as_ref().into();
+            apn.user = profile.user.as_deref().map(AsciiString::from);
+            apn.password = password.as_deref().map(AsciiString::from);
+            apn.add_default_route = true;
+            apn.default_route_distance = LTE_ROUTE_DISTANCE;
+            let name = AsciiString::from(port.name());
+            let lte = &mut self.lte.entry(default_name).or_default().0;
+            lte.name = name.clone();
+            lte.apn_profiles.insert(apn_name);
+            let comment = AsciiString::from(format!("masquerade {name}; {MANAGED_MARKER}"));
+            let rule = self.ipv_4_firewall_nat.entry((comment,)).or_default();
+            rule.chain = b"srcnat".into();
+            rule.action = IpFirewallNatAction::Masquerade;
+            rule.out_interface = Some(name);
+        }
+        for wan in device.interfaces().into_iter().filter(|port| port.is_wan()) {
+            let Some(gateway) = wan.wan_gateway() else {
+                // the default route of a dhcp client already has a lower distance
+                if !wan.is_enable_dhcp_client() {
+                    warnings.push(
+                        WarningKind::SkippedResource,
+                        format!(
+                            "No gateway on the prefix of wan interface {}, the lte failover is not set up",
+                            wan.name()
+                        ),
+                    );
+                }
+                continue;
+            };
+            let comment =
+                AsciiString::from(format!("wan {} to {gateway}; {MANAGED_MARKER}", wan.name()));
+            let route = self.ipv_4_route.entry((comment,)).or_default();
+            route.dst_address = Ipv4Net::default();
+            route.gateway = gateway.to_string().into();
+            route.distance = WAN_ROUTE_DISTANCE;
+            route.check_gateway = Some(IpRouteCheckGateway::Ping);
+        }
+    }
+
+    /// dials into the provider on the interfaces tagged `pppoe` with the account of the device,
+    /// the password is read from the secrets directory. The pppoe client adds the default route
+    /// and the traffic leaving through it is masqueraded.
+    fn setup_pppoe(
+        &mut self,
+        device: &DeviceAccess,
+        mapped_planes: &[(InterfaceAccess, MappedPlane)],
+        policy: &NamingPolicy,
+        pppoe_credentials: &HashMap<Box<str>, PppoeCredentials>,
+        secrets_dir: &Path,
+        warnings: &mut Warnings,
+    ) -> Result<(), SetupError> {
+        for (if_access, plane) in mapped_planes {
+            if !if_access.is_pppoe() {
+                continue;
+            }
+            let account = device.pppoe_credentials();
+            let Some(credentials) = pppoe_credentials.get(account) else {
+                warnings.push(
+                    WarningKind::SkippedResource,
+                    format!(
+                        "Pppoe account {account} not configured, no pppoe client on {}",
+                        if_access.name()
+                    ),
+                );
+                continue;
+            };
+            let password = match read_secret(secrets_dir, credentials.password_name()) {
+                Ok(password) => password,
+                Err(error) => {
+                    warnings.push(
+                        WarningKind::SkippedResource,
+                        format!(
+                            "No pppoe password for account {account}, no pppoe client on {}: {error}",
+                            if_access.name()
+                        ),
+                    );
+                    continue;
+                }
+            };
+            let if_name = self.if_of_mapped_plane(plane, policy)?;
+            let name = AsciiString::from(format!("pppoe-{if_name}"));
+            let client = &mut self.pppoe_client.entry(name.clone()).or_default().0;
+            client.interface = if_name;
+            client.user = credentials.user.as_ref().into();
+            client.password = password.as_ref().into();
+            client.add_default_route = true;
+            client.default_route_distance = WAN_ROUTE_DISTANCE;
+            client.use_peer_dns = true;
+            let comment = AsciiString::from(format!("masquerade {name}; {MANAGED_MARKER}"));
+            let rule = self.ipv_4_firewall_nat.entry((comment,)).or_default();
+            rule.chain = b"srcnat".into();
+            rule.action = IpFirewallNatAction::Masquerade;
+            rule.out_interface = Some(name);
+        }
         Ok(())
     }
 
-    fn setup_wlan_ap(&mut self, device: &DeviceAccess) {
-        if let Some(wlan_group) = device.wlan_ap_of() {
+    /// upnp and igmp proxy of the devices enabled in netbox. The wan interfaces, or their pppoe
+    /// clients, face the provider, the interfaces of the client networks the clients. Hosts on
+    /// the management and transit networks cannot open ports.
+    fn setup_upnp_igmp_proxy(
+        &mut self,
+        device: &DeviceAccess,
+        profile: &GenerationProfile,
+        mapped_planes: &[(InterfaceAccess, MappedPlane)],
+        policy: &NamingPolicy,
+        warnings: &mut Warnings,
+    ) -> Result<(), SetupError> {
+        let (upnp, igmp_proxy) = (device.is_upnp(), device.is_igmp_proxy());
+        if !upnp && !igmp_proxy {
+            return Ok(());
+        }
+        if profile.security_sensitive {
+            warnings.push(
+                WarningKind::SkippedResource,
+                format!(
+                    "Upnp and igmp proxy are not allowed on {}, its role {} is security sensitive",
+                    device.name(),
+                    device.role().unwrap_or_default()
+                ),
+            );
+            return Ok(());
+        }
+        let mut external = Vec::new();
+        let mut internal = Vec::new();
+        for (if_access, plane) in mapped_planes {
+            if if_access.is_wan() {
+                let if_name = self.if_of_mapped_plane(plane, policy)?;
+                let pppoe_name = AsciiString::from(format!("pppoe-{if_name}"));
+                external.push(if self.pppoe_client.contains_key(&pppoe_name) {
+                    pppoe_name
+                } else {
+                    if_name
+                });
+            } else if is_client_network(if_access) {
+                internal.push(self.if_of_mapped_plane(plane, policy)?);
+            }
+        }
+        if external.is_empty() {
+            warnings.push(
+                WarningKind::SkippedResource,
+                format!(
+                    "No wan interface on {}, upnp and igmp proxy are not set up",
+                    device.name()
+                ),
+            );
+            return Ok(());
+        }
+        if upnp {
+            self.upnp.enabled = true;
+            for interface in &external {
+                let entry = self.upnp_interface.entry((interface.clone(),)).or_default();
+                entry.r#type = IpUpnpInterfacesType::External;
+            }
+            for interface in &internal {
+                let entry = self.upnp_interface.entry((interface.clone(),)).or_default();
+                entry.r#type = IpUpnpInterfacesType::Internal;
+            }
+        }
+        if igmp_proxy {
+            for (interfaces, upstream) in [(&external, true), (&internal, false)] {
+                for interface in interfaces {
+                    let entry = &mut self
+                        .igmp_proxy_interface
+                        .entry(interface.clone())
+                        .or_default()
+                        .0;
+                    entry.interface = interface.clone();
+                    entry.upstream = upstream;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn setup_netwatch(&mut self, device: &DeviceAccess, warnings: &mut Warnings) {
+        let settings = CONFIG.load();
+        for profile_name in device.netwatch_profiles() {
+            let Some(profile) = settings.netwatch_profiles.get(profile_name) else {
+                warnings.push(
+                    WarningKind::SkippedResource,
+                    format!("Netwatch profile {profile_name} not configured"),
+                );
+                continue;
+            };
+            let target = match profile.target() {
+                NetwatchTarget::Gateway => device.upstream_gateway(),
+                NetwatchTarget::Controller => device.wlan_controller_address(),
+            };
+            let Some(target) = target else {
+                warnings.push(
+                    WarningKind::SkippedResource,
+                    format!(
+                        "No target found for netwatch profile {profile_name} on {}",
+                        device.name()
+                    ),
+                );
+                continue;
+            };
+            let netwatch = &mut self
+                .netwatch
+                .entry(target.to_string().into())
+                .or_default()
+                .0;
+            netwatch.interval = profile.interval();
+            netwatch.up_script = profile.up_script().map(AsciiString::from);
+            netwatch.down_script = profile.down_script().map(AsciiString::from);
+        }
+    }
+
+    /// gre and eoip interfaces between the outside addresses of the tunnel terminations,
+    /// gre interfaces are routed, eoip interfaces are bridged by the l2 setup. Returns the names
+    /// of the tunnels not generated, they must not be bridged either.
+    fn setup_tunnels(
+        &mut self,
+        device: &DeviceAccess,
+        names: &mut InterfaceNames,
+        mapped_planes: &mut Vec<(InterfaceAccess, MappedPlane)>,
+        warnings: &mut Warnings,
+    ) -> Result<HashSet<AsciiString>, SetupError> {
+        let mut skipped = HashSet::new();
+        for port in device.interfaces() {
+            let Some(tunnel) = port.tunnel() else {
+                continue;
+            };
+            let name: AsciiString = names.generate_interface_name(&port).to_string().into();
+            let (Some(IpAddr::V4(local)), Some(IpAddr::V4(remote))) = (
+                tunnel.local_address(port.id()),
+                tunnel.remote_address(port.id()),
+            ) else {
+                warnings.push(
+                    WarningKind::SkippedResource,
+                    format!(
+                        "Missing outside ipv4 addresses on tunnel {}",
+                        tunnel.name().unwrap_or(port.name())
+                    ),
+                );
+                skipped.insert(name);
+                continue;
+            };
+            match tunnel.encapsulation() {
+                Some(TunnelEncapsulation::Gre) => {
+                    let Entry::Vacant(gre) = self.gre.entry(name.clone()) else {
+                        return Err(tunnel_collision("gre tunnel", name, &port));
+                    };
+                    gre.insert(InterfaceGreByName(InterfaceGreCfg {
+                        local_address: local.into(),
+                        remote_address: remote.into(),
+                        ..Default::default()
+                    }));
+                    mapped_planes.push((port, MappedPlane::Untagged(name)));
+                }
+                Some(TunnelEncapsulation::Eoip) => {
+                    // both ends have to agree on the id, a default would connect unrelated tunnels
+                    let Some(tunnel_id) = tunnel.tunnel_id() else {
+                        warnings.push(
+                            WarningKind::SkippedResource,
+                            format!(
+                                "Missing tunnel id on eoip tunnel {}",
+                                tunnel.name().unwrap_or(port.name())
+                            ),
+                        );
+                        skipped.insert(name);
+                        continue;
+                    };
+                    let Entry::Vacant(eoip) = self.eoip.entry(name.clone()) else {
+                        return Err(tunnel_collision("eoip tunnel", name, &port));
+                    };
+                    eoip.insert(InterfaceEoipByName(InterfaceEoipCfg {
+                        local_address: local.into(),
+                        remote_address: remote.into(),
+                        tunnel_id: tunnel_id.into(),
+                        ..Default::default()
+                    }));
+                }
+                None => {
+                    skipped.insert(name);
+                }
+            }
+        }
+        Ok(skipped)
+    }
+
+    /// bridge connecting the wlan vlans by vxlan, the same on the aps and on the controller
+    fn setup_wlan_caps_bridge(
+        &mut self,
+        device: &DeviceAccess,
+        policy: &NamingPolicy,
+    ) -> Result<(), SetupError> {
+        if let Some(wlan_group) = device.wlan_ap_of().or_else(|| device.wlan_controller_of()) {
             let bridge_caps = self.bridge.entry(CAPS_BRIDGE_NAME.into()).or_default();
             bridge_caps.0.vlan_filtering = true;
             bridge_caps.0.protocol_mode = InterfaceBridgeProtocolMode::Mstp;
@@ -442,21 +1263,30 @@ impl BaseDeviceDataTarget {
                     .filter_map(|vlan| vlan.vxlan())
                     .collect::<HashSet<_>>();
                 for vxlan in vxlans {
-                    self.setup_vxlan(vxlan, &my_ip);
+                    self.setup_vxlan(vxlan, &my_ip, policy)?;
                 }
             }
         }
+        Ok(())
     }
 
-    fn setup_vxlan(&mut self, vxlan: VxlanAccess, my_ip: &IpAddr) {
-        if let (Some(name), Some(vni)) = (
-            vxlan
-                .name()
-                .map(cleanup_name)
-                .map(|name| format!("vxlan-{name}").to_case(Case::Kebab))
-                .map(AsciiString::from),
-            vxlan.vni(),
-        ) {
+    fn setup_vxlan(
+        &mut self,
+        vxlan: VxlanAccess,
+        my_ip: &IpAddr,
+        policy: &NamingPolicy,
+    ) -> Result<(), SetupError> {
+        if let (Some(name), Some(vni)) = (policy.vxlan_name(&vxlan), vxlan.vni()) {
+            if let Some(existing) = self.vxlan.get(&name) {
+                if existing.0.vni != vni {
+                    return Err(SetupError::NameCollision {
+                        kind: "vxlan",
+                        name,
+                        first: format!("vni {}", existing.0.vni).into(),
+                        second: format!("vni {vni}").into(),
+                    });
+                }
+            }
             self.bridge_port
                 .entry((CAPS_BRIDGE_NAME.into(), name.clone()))
                 .or_default();
@@ -483,6 +1313,69 @@ impl BaseDeviceDataTarget {
                     .insert((name.clone(), remote_vtep_addr), Default::default());
             }
         }
+        Ok(())
+    }
+
+    /// ports, bridges, vlans and tunnels share the interface names on RouterOS, a generated
+    /// name used twice would merge two interfaces. Within one kind the inserts check for
+    /// collisions, the bridges have fixed names reserved by the interface names.
+    fn check_interface_names(&self) -> Result<(), SetupError> {
+        let mut names = self
+            .ethernet
+            .iter()
+            .map(|(default_name, port)| (port.name.clone(), format!("ethernet {default_name}")))
+            .chain(
+                self.bridge
+                    .keys()
+                    .map(|name| (name.clone(), "bridge".to_string())),
+            )
+            .chain(self.vlan.iter().map(|(name, vlan)| {
+                (
+                    name.clone(),
+                    format!("vlan {} on {}", vlan.0.vlan_id, vlan.0.interface),
+                )
+            }))
+            .chain(
+                self.eoip
+                    .keys()
+                    .map(|name| (name.clone(), "eoip tunnel".to_string())),
+            )
+            .chain(
+                self.lte
+                    .iter()
+                    .map(|(default_name, lte)| (lte.name.clone(), format!("lte {default_name}"))),
+            )
+            .chain(
+                self.pppoe_client
+                    .keys()
+                    .map(|name| (name.clone(), "pppoe client".to_string())),
+            )
+            .chain(
+                self.gre
+                    .keys()
+                    .map(|name| (name.clone(), "gre tunnel".to_string())),
+            )
+            .chain(
+                self.vxlan
+                    .iter()
+                    .map(|(name, vxlan)| (name.clone(), format!("vxlan vni {}", vxlan.0.vni))),
+            )
+            .filter(|(name, _)| !name.0.is_empty())
+            .collect::<Vec<_>>();
+        names.sort();
+        for pair in names.windows(2) {
+            if let [(name, first), (other, second)] = pair {
+                if name == other {
+                    return Err(SetupError::NameCollision {
+                        kind: "interface",
+                        name: name.clone(),
+                        first: first.as_str().into(),
+                        second: second.as_str().into(),
+                    });
+                }
+            }
+        }
+        Ok(())
     }
 
     fn set_loopback_ip(&mut self, loopback_ip: IpAddr) {
@@ -508,72 +1401,118 @@ impl BaseDeviceDataTarget {
         }
     }
 
-    fn if_of_mapped_plane(&mut self, plane: &MappedPlane) -> AsciiString {
+    /// the interface of the plane, a tagged plane gets a vlan interface. Planes of the same vlan
+    /// on the same interface share it, a shortened name taken by another vlan is a collision.
+    fn if_of_mapped_plane(
+        &mut self,
+        plane: &MappedPlane,
+        policy: &NamingPolicy,
+    ) -> Result<AsciiString, SetupError> {
         match plane {
             MappedPlane::Tagged { if_name, vid } => {
-                let mut vlan_port_name = Vec::from(if_name.0.as_ref());
-                vlan_port_name.push(b'-');
-                vlan_port_name.extend(AsciiString::from(vid.to_string()).0);
-                let vlan_port_name = AsciiString::from(vlan_port_name.as_slice());
-                if let Entry::Vacant(vlan_port_entry) = self.vlan.entry(vlan_port_name.clone()) {
-                    vlan_port_entry.insert(InterfaceVlanByName(InterfaceVlanCfg {
-                        interface: if_name.clone(),
-                        vlan_id: *vid,
-                        ..Default::default()
-                    }));
+                let vlan_port_name = policy.vlan_name(if_name, *vid);
+                match self.vlan.entry(vlan_port_name.clone()) {
+                    Entry::Vacant(vlan_port_entry) => {
+                        vlan_port_entry.insert(InterfaceVlanByName(InterfaceVlanCfg {
+                            interface: if_name.clone(),
+                            vlan_id: *vid,
+                            ..Default::default()
+                        }));
+                    }
+                    Entry::Occupied(existing) => {
+                        let existing = &existing.get().0;
+                        if existing.interface != *if_name || existing.vlan_id != *vid {
+                            return Err(SetupError::NameCollision {
+                                kind: "vlan",
+                                name: vlan_port_name,
+                                first: format!(
+                                    "vlan {} on {}",
+                                    existing.vlan_id, existing.interface
+                                )
+                                .into(),
+                                second: format!("vlan {vid} on {if_name}").into(),
+                            });
+                        }
+                    }
                 }
-                vlan_port_name
+                Ok(vlan_port_name)
             }
-            MappedPlane::Untagged(if_name) => if_name.clone(),
+            MappedPlane::Untagged(if_name) => Ok(if_name.clone()),
         }
     }
 
-    fn setup_ospf(&mut self, device: &DeviceAccess, planes: &[(InterfaceAccess, MappedPlane)]) {
-        if let Some(router_id) = device.primary_ip_v4().and_then(|ip| ip.addr()) {
-            let ports = planes
-                .iter()
-                .filter(|(p, _)| p.use_ospf())
-                .map(|(_, map)| self.if_of_mapped_plane(map))
-                .collect::<BTreeSet<_>>();
-            if !ports.is_empty() {
-                let v2_instance = self
-                    .ospf_instance
-                    .entry(b"default-v2".into())
-                    .or_insert(RoutingOspfInstanceByName(RoutingOspfInstanceCfg::default()));
-                v2_instance.0.redistribute =
-                    [RoutingRedistribute::Connected, RoutingRedistribute::Static]
-                        .into_iter()
-                        .collect();
-                v2_instance.0.router_id = Some(router_id.to_string().into());
-                v2_instance.0.version = RoutingOspfInstanceVersion::_2;
-                let v3_instance = self
-                    .ospf_instance
-                    .entry(b"default-v3".into())
-                    .or_insert(RoutingOspfInstanceByName(RoutingOspfInstanceCfg::default()));
-                v3_instance.0.redistribute =
-                    [RoutingRedistribute::Connected, RoutingRedistribute::Static]
-                        .into_iter()
-                        .collect();
-                v3_instance.0.router_id = Some(router_id.to_string().into());
-                v3_instance.0.version = RoutingOspfInstanceVersion::_3;
-                let v2_area = self.ospf_area.entry(b"backbone-v2".into()).or_default();
-                v2_area.0.instance = b"default-v2".into();
-                let v3_area = self.ospf_area.entry(b"backbone-v3".into()).or_default();
-                v3_area.0.instance = b"default-v3".into();
-                let v2_backbone = self
-                    .ospf_interface
-                    .entry((b"backbone-v2".into(),))
-                    .or_default();
-                v2_backbone.interfaces = ports.clone();
-                v2_backbone.use_bfd = Some(false);
-                let v3_backbone = self
-                    .ospf_interface
-                    .entry((b"backbone-v3".into(),))
-                    .or_default();
-                v3_backbone.interfaces = ports;
-                v3_backbone.use_bfd = Some(false);
+    fn setup_ospf(
+        &mut self,
+        device: &DeviceAccess,
+        planes: &[(InterfaceAccess, MappedPlane)],
+        policy: &NamingPolicy,
+    ) -> Result<(), SetupError> {
+        if let Some(router_id) = router_id(device) {
+            // one set of instances per vrf, the main routing table uses the default instances
+            let mut ports_of_vrf = BTreeMap::<Option<AsciiString>, BTreeSet<_>>::new();
+            for (p, map) in planes.iter().filter(|(p, _)| p.use_ospf()) {
+                let vrf = p.vrf().as_ref().and_then(|vrf| policy.vrf_name(vrf));
+                let if_name = self.if_of_mapped_plane(map, policy)?;
+                ports_of_vrf.entry(vrf).or_default().insert(if_name);
+            }
+            for (vrf, ports) in ports_of_vrf {
+                let prefix = vrf
+                    .as_ref()
+                    .map(|name| format!("{name}-"))
+                    .unwrap_or_default();
+                for version in [
+                    RoutingOspfInstanceVersion::_2,
+                    RoutingOspfInstanceVersion::_3,
+                ] {
+                    let suffix = match version {
+                        RoutingOspfInstanceVersion::_2 => "v2",
+                        _ => "v3",
+                    };
+                    let instance_name: AsciiString = format!("{prefix}default-{suffix}").into();
+                    let area_name: AsciiString = format!("{prefix}backbone-{suffix}").into();
+                    let instance = self
+                        .ospf_instance
+                        .entry(instance_name.clone())
+                        .or_insert(RoutingOspfInstanceByName(RoutingOspfInstanceCfg::default()));
+                    instance.0.redistribute =
+                        [RoutingRedistribute::Connected, RoutingRedistribute::Static]
+                            .into_iter()
+                            .collect();
+                    instance.0.router_id = Some(router_id.to_string().into());
+                    instance.0.version = version;
+                    if let Some(vrf) = &vrf {
+                        instance.0.vrf = vrf.clone();
+                    }
+                    let area = self.ospf_area.entry(area_name.clone()).or_default();
+                    area.0.instance = instance_name;
+                    let backbone = self.ospf_interface.entry((area_name,)).or_default();
+                    backbone.interfaces = ports.clone();
+                    backbone.use_bfd = Some(false);
+                }
             }
         }
+        Ok(())
+    }
+
+    /// adds the interface to the vrf of its addresses
+    fn assign_vrf(
+        &mut self,
+        if_access: &InterfaceAccess,
+        if_name: &AsciiString,
+        policy: &NamingPolicy,
+    ) {
+        if let Some(vrf) = if_access
+            .vrf()
+            .as_ref()
+            .and_then(|vrf| policy.vrf_name(vrf))
+        {
+            self.vrf
+                .entry(vrf)
+                .or_default()
+                .0
+                .interfaces
+                .insert(if_name.clone());
+        }
     }
 
     fn enable_dhcp_client(&mut self, if_name: AsciiString) {
@@ -591,20 +1530,258 @@ impl BaseDeviceDataTarget {
             });
     }
 
+    /// vrrp interfaces for the fhrp groups assigned to the interface, returns the virtual ips
+    fn setup_vrrp(
+        &mut self,
+        if_access: &InterfaceAccess,
+        if_name: &AsciiString,
+        policy: &NamingPolicy,
+    ) -> Vec<IpNet> {
+        let mut virtual_ips = Vec::new();
+        for group in if_access.fhrp_groups().iter().filter(|g| g.is_vrrp()) {
+            let (Some(vrid), Some(priority)) =
+                (group.group_id(), group.priority_of(if_access.id()))
+            else {
+                continue;
+            };
+            let version = match group.protocol() {
+                Some(FhrpProtocol::Vrrp2) => 2,
+                _ => 3,
+            };
+            let vrrp_name: AsciiString = format!("{if_name}-vrrp{vrid}").into();
+            self.vrrp.insert(
+                vrrp_name.clone(),
+                InterfaceVrrpByName(InterfaceVrrpCfg {
+                    interface: if_name.clone(),
+                    vrid,
+                    priority,
+                    version,
+                    ..Default::default()
+                }),
+            );
+            self.assign_vrf(if_access, &vrrp_name, policy);
+            for virtual_ip in group.virtual_ips().iter().filter_map(|ip| ip.net()) {
+                self.set_ip_address(IpNet::from(virtual_ip.addr()), vrrp_name.clone());
+                virtual_ips.push(virtual_ip);
+            }
+        }
+        virtual_ips
+    }
+
+    /// drops the traffic of a guest network to the private networks, the traffic to the router
+    /// itself passes the input chain and is not affected
+    fn isolate_guest_network(&mut self, if_name: &AsciiString) {
+        for network in private_networks() {
+            let comment =
+                AsciiString::from(format!("guest {if_name} to {network}; {MANAGED_MARKER}"));
+            let rule = self.ipv_4_firewall_filter.entry((comment,)).or_default();
+            rule.chain = b"forward".into();
+            rule.action = IpFirewallFilterAction::Drop;
+            rule.in_interface = Some(if_name.clone());
+            rule.dst_address = Some(network.into());
+        }
+    }
+
+    /// captive portal on the interfaces tagged `captive-portal`, the first ipv4 address of the
+    /// interface serves the login page
+    fn setup_hotspots(
+        &mut self,
+        mapped_planes: &[(InterfaceAccess, MappedPlane)],
+        policy: &NamingPolicy,
+        config: &HotspotConfig,
+    ) -> Result<(), SetupError> {
+        for (if_access, plane) in mapped_planes {
+            if !if_access.is_captive_portal() {
+                continue;
+            }
+            let Some(address) = if_access.ips().iter().find_map(|ip| match ip.net() {
+                Some(IpNet::V4(net)) => Some(net.addr()),
+                _ => None,
+            }) else {
+                continue;
+            };
+            let if_name = self.if_of_mapped_plane(plane, policy)?;
+            let name = AsciiString::from(format!("hotspot-{if_name}"));
+            let profile = &mut self.hotspot_profile.entry(name.clone()).or_default().0;
+            profile.hotspot_address = Some(address);
+            profile.dns_name = config.dns_name.as_deref().map(AsciiString::from);
+            profile.html_directory = config.html_directory.as_deref().map(AsciiString::from);
+            profile.use_radius = config.use_radius;
+            profile.login_by = [
+                IpHotspotProfileLoginBy::HttpChap,
+                IpHotspotProfileLoginBy::Cookie,
+            ]
+            .into_iter()
+            .chain(config.trial.then_some(IpHotspotProfileLoginBy::Trial))
+            .collect();
+            let server = &mut self.hotspot.entry(name.clone()).or_default().0;
+            server.interface = if_name;
+            server.profile = name;
+        }
+        if self.hotspot.is_empty() {
+            return Ok(());
+        }
+        // users without an own profile get the default profile
+        let user_profile = &mut self
+            .hotspot_user_profile
+            .entry(b"default".into())
+            .or_default()
+            .0;
+        user_profile.session_timeout = config.session_timeout().map(Into::into);
+        user_profile.idle_timeout = config.idle_timeout().map(Into::into);
+        user_profile.rate_limit = config.rate_limit.as_deref().map(AsciiString::from);
+        for host in &config.walled_garden {
+            let entry = self
+                .hotspot_walled_garden
+                .entry((AsciiString::from(host.as_ref()),))
+                .or_default();
+            entry.action = IpHotspotWalledGardenAction::Allow;
+            entry.comment = Some(MANAGED_MARKER.into());
+        }
+        Ok(())
+    }
+
+    /// assigns the options of the set to the dhcp network, the raw options are created with the
+    /// name of the set as prefix to keep the options of different sets apart
+    fn setup_dhcp_options(&mut self, net: Ipv4Net, set_name: &str, set: &DhcpOptionSet) {
+        let mut option_names = Vec::new();
+        for (name, option) in &set.options {
+            let option_name = AsciiString::from(format!("{set_name}-{name}"));
+            let entry = &mut self
+                .dhcp_v_4_server_option
+                .entry(option_name.clone())
+                .or_default()
+                .0;
+            entry.code = option.code;
+            entry.value = option.value.as_ref().into();
+            option_names.push(option_name);
+        }
+        let network = &mut self.dhcp_v_4_server_network.entry(net).or_default().0;
+        network.domain = set.domain.as_deref().map(AsciiString::from);
+        network.next_server = set.tftp_server;
+        network.boot_file_name = set.boot_file.as_deref().map(AsciiString::from);
+        network.ntp_server.extend(set.ntp_servers.iter().copied());
+        network.dhcp_option.extend(option_names);
+    }
+
+    /// static leases for the addresses of the prefix on interfaces with a mac address in netbox,
+    /// so the documented clients always get their address. The addresses of the serving device
+    /// itself are skipped.
+    fn setup_static_leases(
+        &mut self,
+        prefix: &IpPrefixAccess,
+        serving: &InterfaceAccess,
+        server: &AsciiString,
+    ) {
+        let serving_device = serving.device().map(|device| device.id());
+        for address in prefix.ips() {
+            let (Some(IpAddr::V4(ip)), Some(interface)) = (address.addr(), address.interface())
+            else {
+                continue;
+            };
+            let Some(mac_address) = interface.mac_address() else {
+                continue;
+            };
+            let Some(device) = interface.device() else {
+                continue;
+            };
+            if Some(device.id()) == serving_device {
+                continue;
+            }
+            let lease = &mut self.dhcp_v_4_server_lease.entry(ip).or_default().0;
+            lease.mac_address = mac_address.into();
+            lease.server = server.clone();
+            lease.comment = Some(format!("{} {}", device.name(), interface.name()).into());
+        }
+    }
+
+    /// forwards the dhcp requests on the interface to the servers of its prefix or the central
+    /// servers of the profile, the first ipv4 address of the interface is the relay address
+    fn setup_dhcp_relay(
+        &mut self,
+        if_access: &InterfaceAccess,
+        if_name: &AsciiString,
+        profile: &GenerationProfile,
+        warnings: &mut Warnings,
+    ) {
+        let Some((ip, prefix)) = if_access
+            .ips()
+            .iter()
+            .find_map(|address| match address.net() {
+                Some(IpNet::V4(ip)) => Some((ip, address.prefix())),
+                _ => None,
+            })
+        else {
+            warnings.push(
+                WarningKind::SkippedResource,
+                format!("No ipv4 address for the dhcp relay on {if_name}"),
+            );
+            return;
+        };
+        let servers = prefix
+            .map(|prefix| prefix.dhcp_relay_servers())
+            .filter(|servers| !servers.is_empty())
+            .unwrap_or_else(|| profile.dhcp_relay_servers.clone());
+        if servers.is_empty() {
+            warnings.push(
+                WarningKind::SkippedResource,
+                format!(
+                    "No dhcp server to relay to on {if_name}, set dhcp_relay_servers in the generation profile or dhcp_relay on the prefix"
+                ),
+            );
+            return;
+        }
+        let name: AsciiString = format!("relay-{if_name}").into();
+        let relay = &mut self.dhcp_v_4_relay.entry(name).or_default().0;
+        relay.interface = if_name.clone();
+        relay.local_address = Some(ip.addr());
+        relay.dhcp_server.extend(servers.iter().copied());
+    }
+
     fn setup_ip_addresses(
         &mut self,
         mapped_planes: &[(InterfaceAccess, MappedPlane)],
+        policy: &NamingPolicy,
+        profile: &GenerationProfile,
+        dhcp_option_sets: &HashMap<Box<str>, DhcpOptionSet>,
+        warnings: &mut Warnings,
     ) -> Result<(), SetupError> {
         for (if_access, plane) in mapped_planes {
             let ips = if_access.ips();
             if ips.is_empty() {
-                if if_access.is_enable_dhcp_client() {
-                    let if_name = self.if_of_mapped_plane(plane);
+                if profile.dhcp_client && if_access.is_enable_dhcp_client() {
+                    let if_name = self.if_of_mapped_plane(plane, policy)?;
                     self.enable_dhcp_client(if_name);
                 }
             } else {
-                let if_name = self.if_of_mapped_plane(plane);
-                let dhcp_server = if_access.is_enable_dhcp_server();
+                let if_name = self.if_of_mapped_plane(plane, policy)?;
+                self.assign_vrf(if_access, &if_name, policy);
+                let virtual_ips = if profile.vrrp {
+                    self.setup_vrrp(if_access, &if_name, policy)
+                } else {
+                    Vec::new()
+                };
+                let dhcp_relay = if_access.is_enable_dhcp_relay();
+                if dhcp_relay && if_access.is_enable_dhcp_server() {
+                    warnings.push(
+                        WarningKind::SkippedResource,
+                        format!(
+                            "Interface {} is tagged for a dhcp server and a relay, only the relay is set up",
+                            if_access.name()
+                        ),
+                    );
+                }
+                let dhcp_server =
+                    profile.dhcp_server && if_access.is_enable_dhcp_server() && !dhcp_relay;
+                if dhcp_relay {
+                    self.setup_dhcp_relay(if_access, &if_name, profile, warnings);
+                }
+                let guest = if_access
+                    .untagged_vlan()
+                    .is_some_and(|vlan| vlan.is_guest());
+                if guest {
+                    self.isolate_guest_network(&if_name);
+                }
                 for (ip_idx, ip_address) in ips.iter().enumerate() {
                     if let Some(ip) = ip_address.net() {
                         self.set_ip_address(ip, if_name.clone());
@@ -618,31 +1795,19 @@ impl BaseDeviceDataTarget {
                                     prefix: prefix.id(),
                                 })?,
                             ) {
-                                let mut dhcp_ranges_explicit = Vec::new();
-                                let mut gap_finder = GapFinder::<Ipv4Addr>::new();
-                                for range in prefix.ranges() {
-                                    if let (Some(IpAddr::V4(start)), Some(IpAddr::V4(end))) =
-                                        (range.start(), range.end())
-                                    {
-                                        if range.is_dhcp() {
-                                            dhcp_ranges_explicit.push(start..end);
+                                let dhcp_ranges_explicit = prefix
+                                    .ranges()
+                                    .iter()
+                                    .filter(|range| range.is_dhcp())
+                                    .filter_map(|range| match (range.start(), range.end()) {
+                                        (Some(IpAddr::V4(start)), Some(IpAddr::V4(end))) => {
+                                            Some(start..end)
                                         }
-                                        gap_finder.reserve_ipv4_range(start..end);
-                                    }
-                                }
+                                        _ => None,
+                                    })
+                                    .collect::<Vec<_>>();
                                 let dhcp_ranges = if dhcp_ranges_explicit.is_empty() {
-                                    for child_prefix in prefix.children() {
-                                        if let Some(IpNet::V4(net)) = child_prefix.prefix() {
-                                            gap_finder.reserve_ipv4_net(net);
-                                        }
-                                    }
-                                    for ip in prefix.ips() {
-                                        if let Some(IpAddr::V4(ip)) = ip.addr() {
-                                            gap_finder.reserve_ipv4(ip)
-                                        }
-                                    }
-
-                                    gap_finder.find_gaps_ipv4(net).collect()
+                                    prefix.free_ipv4_ranges().into_vec()
                                 } else {
                                     dhcp_ranges_explicit
                                 };
@@ -653,6 +1818,20 @@ impl BaseDeviceDataTarget {
                                         format!("{if_name}-{ip_idx}")
                                     };
                                     let server_name: AsciiString = format!("dhcp-{suffix}").into();
+                                    if let Some(existing) = self.dhcp_v_4_server.get(&server_name) {
+                                        if existing.0.interface != if_name {
+                                            return Err(SetupError::NameCollision {
+                                                kind: "dhcp server",
+                                                name: server_name,
+                                                first: format!(
+                                                    "interface {}",
+                                                    existing.0.interface
+                                                )
+                                                .into(),
+                                                second: format!("interface {if_name}").into(),
+                                            });
+                                        }
+                                    }
                                     let server = &mut self
                                         .dhcp_v_4_server
                                         .entry(server_name.clone())
@@ -660,15 +1839,45 @@ impl BaseDeviceDataTarget {
                                         .0;
                                     server.interface = if_name.clone();
                                     server.address_pool = server_name.clone();
+                                    if guest {
+                                        server.lease_time = profile.guest_lease_time().into();
+                                    }
                                     let network =
                                         &mut self.dhcp_v_4_server_network.entry(net).or_default().0;
-                                    network.gateway.insert(ip.addr());
+                                    // clients use the virtual router if the prefix is redundant
+                                    let gateway = virtual_ips
+                                        .iter()
+                                        .find_map(|virtual_ip| match virtual_ip {
+                                            IpNet::V4(v) if net.contains(&v.addr()) => {
+                                                Some(v.addr())
+                                            }
+                                            _ => None,
+                                        })
+                                        .unwrap_or(ip.addr());
+                                    network.gateway.insert(gateway);
                                     network.dns_server.insert(ip.addr());
-                                    self.ipv_4_pool.entry(server_name).or_default().0.ranges =
-                                        dhcp_ranges
-                                            .iter()
-                                            .map(|r| format!("{}-{}", r.start, r.end).into())
-                                            .collect();
+                                    self.ipv_4_pool
+                                        .entry(server_name.clone())
+                                        .or_default()
+                                        .0
+                                        .ranges = dhcp_ranges
+                                        .iter()
+                                        .map(|r| format!("{}-{}", r.start, r.end).into())
+                                        .collect();
+                                    if let Some(set_name) = prefix.dhcp_options() {
+                                        match dhcp_option_sets.get(&set_name) {
+                                            Some(set) => {
+                                                self.setup_dhcp_options(net, &set_name, set)
+                                            }
+                                            None => warnings.push(
+                                                WarningKind::SkippedResource,
+                                                format!(
+                                                    "Unknown dhcp option set {set_name} on prefix {net}"
+                                                ),
+                                            ),
+                                        }
+                                    }
+                                    self.setup_static_leases(&prefix, if_access, &server_name);
                                 }
                             }
                         }
@@ -680,91 +1889,6 @@ impl BaseDeviceDataTarget {
     }
 }
 
-struct GapFinder<V: Ord + Copy> {
-    reserved_chunks: BTreeMap<V, i32>,
-}
-
-impl<V: Ord + Copy> GapFinder<V> {
-    pub fn new() -> Self {
-        GapFinder {
-            reserved_chunks: BTreeMap::new(),
-        }
-    }
-    pub fn reserve(&mut self, chunk: Range<V>) {
-        *self.reserved_chunks.entry(chunk.start).or_insert(0) += 1;
-        *self.reserved_chunks.entry(chunk.end).or_insert(0) -= 1;
-    }
-    pub fn gaps(&self, Range { start, end }: Range<V>) -> GapIterator<V> {
-        GapIterator {
-            current_value: 0,
-            last_key: start,
-            start,
-            end,
-            gap_iter: self.reserved_chunks.iter(),
-        }
-    }
-}
-
-impl GapFinder<Ipv4Addr> {
-    pub fn reserve_ipv4(&mut self, ip: Ipv4Addr) {
-        self.reserve(ip.saturating_sub(1)..ip.saturating_add(1))
-    }
-    pub fn reserve_ipv4_range(&mut self, ip: Range<Ipv4Addr>) {
-        self.reserve(ip.start.saturating_sub(1)..ip.end.saturating_add(1))
-    }
-    pub fn reserve_ipv4_net(&mut self, ip: Ipv4Net) {
-        self.reserve_ipv4_range(ip.network()..ip.broadcast());
-    }
-    pub fn find_gaps_ipv4(&self, net: Ipv4Net) -> GapIterator<Ipv4Addr> {
-        self.gaps(net.network().saturating_add(1)..net.broadcast().saturating_sub(1))
-    }
-}
-impl GapFinder<Ipv6Addr> {
-    pub fn reserve_ipv6(&mut self, ip: Ipv6Addr) {
-        self.reserve(ip.saturating_sub(1)..ip.saturating_add(1))
-    }
-}
-impl GapFinder<IpAddr> {
-    pub fn reserve_ip(&mut self, ip: IpAddr) {
-        self.reserve(match ip {
-            IpAddr::V4(ip) => IpAddr::V4(ip.saturating_sub(1))..IpAddr::V4(ip.saturating_add(1)),
-            IpAddr::V6(ip) => IpAddr::V6(ip.saturating_sub(1))..IpAddr::V6(ip.saturating_add(1)),
-        });
-    }
-}
-struct GapIterator<'a, V: Ord + Copy> {
-    current_value: i32,
-    last_key: V,
-    start: V,
-    end: V,
-    gap_iter: Iter<'a, V, i32>,
-}
-impl<'a, V: Ord + Copy> Iterator for GapIterator<'a, V> {
-    type Item = Range<V>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        for (current_key, increment) in self.gap_iter.by_ref() {
-            let current_key = (*current_key).clamp(self.start, self.end);
-            let found_gap = if self.current_value <= 0 && current_key > self.last_key {
-                Some(self.last_key..current_key)
-            } else {
-                None
-            };
-            self.current_value += increment;
-            self.last_key = current_key;
-            if let Some(found_gap) = found_gap {
-                return Some(found_gap);
-            }
-        }
-        if self.current_value <= 0 && self.end > self.last_key {
-            let last_key = self.last_key;
-            self.last_key = self.end;
-            Some(last_key..self.end)
-        } else {
-            None
-        }
-    }
-}
 mikrotik_model!(
     name = WirelessDeviceData,
     detect = new,
@@ -795,6 +1919,3 @@ impl WifiDeviceDataTarget {
         }
     }
 }
-fn cleanup_name(name: &str) -> String {
-    name.replace(['.', '/', '+', ':'], "_")
-}
@@ -0,0 +1,221 @@
+use crate::{
+    device::ros::{
+        CAPS_BRIDGE_NAME, DEFAULT_BRIDGE_NAME, SetupError,
+        l2::{EndpointNameGenerator, NameGenerator},
+    },
+    topology::{
+        InterfaceId,
+        access::{
+            device::DeviceAccess, interface::InterfaceAccess, vrf::VrfAccess, vxlan::VxlanAccess,
+        },
+    },
+};
+use async_graphql::SimpleObject;
+use convert_case::{Case, Casing};
+use mikrotik_model::ascii::AsciiString;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+#[cfg(test)]
+mod test;
+
+/// Writes umlauts and accented latin letters in ascii, other non ascii characters are dropped
+pub fn transliterate(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    for char in name.chars() {
+        if char.is_ascii() {
+            result.push(char);
+            continue;
+        }
+        result.push_str(match char {
+            'ä' => "ae",
+            'ö' => "oe",
+            'ü' => "ue",
+            'Ä' => "Ae",
+            'Ö' => "Oe",
+            'Ü' => "Ue",
+            'ß' => "ss",
+            'à' | 'á' | 'â' => "a",
+            'è' | 'é' | 'ê' | 'ë' => "e",
+            'ì' | 'í' | 'î' | 'ï' => "i",
+            'ò' | 'ó' | 'ô' => "o",
+            'ù' | 'ú' | 'û' => "u",
+            'ç' => "c",
+            'ñ' => "n",
+            _ => "",
+        });
+    }
+    result
+}
+
+/// Part of a generated name taken from netbox, e.g. the name of a vrf or an ssid, separators
+/// RouterOS does not accept in names are replaced
+pub fn clean_name(name: &str) -> String {
+    transliterate(name).replace(['.', '/', '+', ':'], "_")
+}
+
+/// cuts the name to at most `max_length` characters, a trailing dash is dropped
+pub fn shorten(name: &str, max_length: usize) -> String {
+    name.chars()
+        .take(max_length)
+        .collect::<String>()
+        .trim_end_matches('-')
+        .to_string()
+}
+
+/// name of a port or tunnel in the generated configuration
+#[derive(Debug, Clone, SimpleObject)]
+pub struct GeneratedInterfaceName {
+    pub interface: InterfaceAccess,
+    pub name: Box<str>,
+}
+
+/// Length and character rules for the names of the generated interfaces and vrfs
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct NamingPolicy {
+    max_length: usize,
+}
+
+impl NamingPolicy {
+    /// The bridges have fixed names, vlans on them are named after the bridge and the vlan id.
+    /// A maximum length too short for those names is rejected.
+    pub fn new(max_length: usize) -> Result<Self, SetupError> {
+        let longest_vlan = format!("{}-4094", AsciiString::from(DEFAULT_BRIDGE_NAME));
+        for fixed in [
+            AsciiString::from(DEFAULT_BRIDGE_NAME),
+            AsciiString::from(CAPS_BRIDGE_NAME),
+            AsciiString::from(longest_vlan),
+        ] {
+            if fixed.0.len() > max_length {
+                return Err(SetupError::NameTooLong {
+                    name: fixed,
+                    max_length,
+                });
+            }
+        }
+        Ok(Self { max_length })
+    }
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+    /// name of the vlan interface with the vlan id on the interface, the vlan id is kept if the
+    /// name is shortened
+    pub fn vlan_name(&self, interface: &AsciiString, vlan_id: u16) -> AsciiString {
+        let suffix = format!("-{vlan_id}");
+        let interface = shorten(
+            &interface.to_string(),
+            self.max_length.saturating_sub(suffix.len()),
+        );
+        format!("{interface}{suffix}").into()
+    }
+    pub fn vrf_name(&self, vrf: &VrfAccess) -> Option<AsciiString> {
+        vrf.name().map(|name| self.prefixed_name("vrf", name))
+    }
+    pub fn vxlan_name(&self, vxlan: &VxlanAccess) -> Option<AsciiString> {
+        vxlan.name().map(|name| self.prefixed_name("vxlan", name))
+    }
+    /// name from netbox in kebab case behind the prefix, shortened to the maximum length
+    fn prefixed_name(&self, prefix: &str, name: &str) -> AsciiString {
+        let name = format!("{prefix}-{}", clean_name(name)).to_case(Case::Kebab);
+        shorten(&name, self.max_length).into()
+    }
+}
+
+/// Names of the ports and tunnels of a device as the generated configuration names them. The
+/// names are shortened to the maximum length of the policy, colliding names get a numeric
+/// suffix in the order of the interface ids. The fixed names of the bridges are not given to a
+/// port.
+pub struct InterfaceNames {
+    policy: NamingPolicy,
+    names: HashMap<InterfaceId, AsciiString>,
+    /// interfaces not named as generated, with the generated name
+    renamed: Vec<(InterfaceId, Box<str>)>,
+}
+
+impl InterfaceNames {
+    pub fn new(device: &DeviceAccess, policy: NamingPolicy) -> Result<Self, SetupError> {
+        Self::with_generator(device, &mut EndpointNameGenerator, policy)
+    }
+    pub fn with_generator<G: NameGenerator>(
+        device: &DeviceAccess,
+        generator: &mut G,
+        policy: NamingPolicy,
+    ) -> Result<Self, SetupError> {
+        let mut interfaces = device
+            .interfaces()
+            .into_iter()
+            .filter(|interface| interface.external_port().is_some() || interface.tunnel().is_some())
+            .collect::<Vec<_>>();
+        interfaces.sort_by_key(|interface| interface.id());
+        let mut used = [DEFAULT_BRIDGE_NAME.as_slice(), CAPS_BRIDGE_NAME.as_slice()]
+            .into_iter()
+            .map(|name| AsciiString::from(name).to_string())
+            .collect::<HashSet<_>>();
+        let mut names = HashMap::new();
+        let mut renamed = Vec::new();
+        for interface in interfaces {
+            let generated = generator.generate_interface_name(&interface).into_owned();
+            let name = shorten(&transliterate(&generated), policy.max_length);
+            let name = unique_name(name, policy.max_length, &mut used)?;
+            if name != generated {
+                renamed.push((interface.id(), generated.into_boxed_str()));
+            }
+            names.insert(interface.id(), name.into());
+        }
+        Ok(Self {
+            policy,
+            names,
+            renamed,
+        })
+    }
+    pub fn policy(&self) -> &NamingPolicy {
+        &self.policy
+    }
+    pub fn get(&self, interface: InterfaceId) -> Option<&AsciiString> {
+        self.names.get(&interface)
+    }
+    /// names transliterated, shortened or numbered, with the name generated from netbox
+    pub fn renamed(&self) -> impl Iterator<Item = (&str, &AsciiString)> {
+        self.renamed
+            .iter()
+            .filter_map(|(id, generated)| Some((generated.as_ref(), self.names.get(id)?)))
+    }
+}
+
+impl NameGenerator for InterfaceNames {
+    fn generate_interface_name<'s>(&mut self, interface: &'s InterfaceAccess) -> Cow<'s, str> {
+        match self.names.get(&interface.id()) {
+            Some(name) => Cow::Owned(name.to_string()),
+            None => Cow::Borrowed(interface.name()),
+        }
+    }
+}
+
+/// the name itself if not used yet, otherwise the first free one with the suffix `-2`, `-3`, ..
+/// There are fewer names used than suffixes tried, the numbered names only run out if the
+/// maximum length leaves no room for the suffix.
+fn unique_name(
+    name: String,
+    max_length: usize,
+    used: &mut HashSet<String>,
+) -> Result<String, SetupError> {
+    if used.insert(name.clone()) {
+        return Ok(name);
+    }
+    for index in 2..=used.len() + 1 {
+        let suffix = format!("-{index}");
+        if suffix.len() > max_length {
+            break;
+        }
+        let candidate = format!("{}{suffix}", shorten(&name, max_length - suffix.len()));
+        if used.insert(candidate.clone()) {
+            return Ok(candidate);
+        }
+    }
+    Err(SetupError::NoFreeName {
+        name: name.into(),
+        max_length,
+    })
+}
@@ -0,0 +1,128 @@
+use crate::{
+    device::ros::{
+        SetupError,
+        l2::KeepNameGenerator,
+        naming::{InterfaceNames, NamingPolicy, clean_name, shorten, transliterate, unique_name},
+    },
+    topology::{
+        DeviceId, InterfaceId, TopologyHolder,
+        snapshot::{FixtureFormat, parse_topology},
+    },
+};
+use mikrotik_model::ascii::AsciiString;
+use std::collections::HashSet;
+
+#[test]
+fn test_transliterate() {
+    assert_eq!("Buero-Strasse", transliterate("Büro-Straße"));
+    assert_eq!("cafe", transliterate("café"));
+    assert_eq!("wlan", transliterate("wlan✓"));
+    assert_eq!("Gaeste_2.4GHz", transliterate("Gäste_2.4GHz"));
+    assert_eq!("Gaeste_2_4GHz", clean_name("Gäste_2.4GHz"));
+    assert_eq!("mgmt_net", clean_name("mgmt/net"));
+}
+
+#[test]
+fn test_shorten() {
+    assert_eq!("ether1", shorten("ether1", 10));
+    assert_eq!("uplink-to", shorten("uplink-to-core", 10));
+    assert_eq!("", shorten("ether1", 0));
+}
+
+#[tokio::test]
+async fn test_interface_names() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: switch
+    ports:
+      - !Interface 1
+      - !Interface 2
+      - !Interface 3
+      - !Interface 4
+interfaces:
+  1:
+    name: uplink-to-core-a
+    device: 1
+    external: !Ethernet 1
+  2:
+    name: uplink-to-core-b
+    device: 1
+    external: !Ethernet 2
+  3:
+    name: bridge
+    device: 1
+  4:
+    name: switch
+    device: 1
+    external: !Ethernet 3
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let policy = NamingPolicy::new(11).expect("invalid policy");
+    let names = InterfaceNames::with_generator(&device, &mut KeepNameGenerator, policy)
+        .expect("names not generated");
+    assert_eq!(
+        Some(&AsciiString::from("uplink-to-c")),
+        names.get(InterfaceId(1))
+    );
+    // the collision is numbered within the maximum length
+    assert_eq!(
+        Some(&AsciiString::from("uplink-to-2")),
+        names.get(InterfaceId(2))
+    );
+    // only ports and tunnels are named by the generation
+    assert_eq!(None, names.get(InterfaceId(3)));
+    // the name of the bridge is not given to a port
+    assert_eq!(
+        Some(&AsciiString::from("switch-2")),
+        names.get(InterfaceId(4))
+    );
+    assert_eq!(
+        vec![
+            ("uplink-to-core-a", &AsciiString::from("uplink-to-c")),
+            ("uplink-to-core-b", &AsciiString::from("uplink-to-2")),
+            ("switch", &AsciiString::from("switch-2")),
+        ],
+        names.renamed().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_naming_policy() {
+    // the vlans on the bridge would not fit
+    assert!(matches!(
+        NamingPolicy::new(10),
+        Err(SetupError::NameTooLong { max_length: 10, .. })
+    ));
+    let policy = NamingPolicy::new(11).expect("invalid policy");
+    assert_eq!(
+        AsciiString::from("switch-10"),
+        policy.vlan_name(&"switch".into(), 10)
+    );
+    // the vlan id is kept when the interface name is shortened
+    assert_eq!(
+        AsciiString::from("uplink-4094"),
+        policy.vlan_name(&"uplink-to-core".into(), 4094)
+    );
+}
+
+#[test]
+fn test_unique_name_exhausted() {
+    let mut used = HashSet::from(["a".to_string()]);
+    // no room for a suffix
+    assert!(matches!(
+        unique_name("a".into(), 1, &mut used),
+        Err(SetupError::NoFreeName { max_length: 1, .. })
+    ));
+    assert_eq!(
+        "a-2",
+        unique_name("a".into(), 3, &mut used).expect("no free name")
+    );
+}
@@ -0,0 +1,267 @@
+use crate::device::ros::{BaseDeviceDataCurrent, BaseDeviceDataTarget, DEFAULT_BRIDGE_NAME};
+use mikrotik_model::ascii::AsciiString;
+use std::collections::HashSet;
+
+/// Entry on the device of a managed resource type the generated configuration does not contain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Orphan {
+    /// resource path like `interface/vlan`
+    pub path: &'static str,
+    /// field identifying the entry, `name` or `comment` for resources without name
+    pub field: &'static str,
+    pub name: AsciiString,
+}
+
+impl Orphan {
+    /// RouterOS script removing the entry
+    pub fn script(&self) -> String {
+        format!(
+            "/{} remove [ find {}=\"{}\" ]\n",
+            self.path,
+            self.field,
+            escape(&self.name.to_string())
+        )
+    }
+}
+
+/// escapes a value for a quoted RouterOS string, `$` would start a variable
+fn escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '$' | '?') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// marker in the comment of every resource created by the provisioner
+pub const MANAGED_MARKER: &str = "managed-by: netbox-provisioner";
+
+/// the comment carries the marker of the provisioner, entries without it were created by hand
+pub fn is_managed(comment: Option<&AsciiString>) -> bool {
+    comment.is_some_and(|comment| comment.to_string().contains(MANAGED_MARKER))
+}
+
+/// appends the marker to the comment unless it is already there
+fn with_marker(comment: Option<AsciiString>) -> AsciiString {
+    match comment {
+        Some(comment) if is_managed(Some(&comment)) => comment,
+        Some(comment) if !comment.to_string().is_empty() => {
+            format!("{comment}; {MANAGED_MARKER}").into()
+        }
+        _ => MANAGED_MARKER.into(),
+    }
+}
+
+macro_rules! mark_entries {
+    ($target:ident, $($field:ident),* $(,)?) => {
+        $(
+            for entry in $target.$field.values_mut() {
+                entry.0.comment = Some(with_marker(entry.0.comment.take()));
+            }
+        )*
+    };
+}
+
+/// Marks the generated resources as managed by the provisioner
+pub fn mark_managed(target: &mut BaseDeviceDataTarget) {
+    mark_entries!(
+        target,
+        vlan,
+        vxlan,
+        vrrp,
+        gre,
+        eoip,
+        bridge,
+        dhcp_v_4_server,
+        dhcp_v_4_server_lease,
+        dhcp_v_4_relay,
+        pppoe_client,
+        ipv_4_pool,
+        ipv_4_address,
+        ipv_6_address,
+    );
+}
+
+macro_rules! retain_managed {
+    ($result:ident, $target:ident, $($field:ident => $key:ident),* $(,)?) => {
+        $(
+            $result.$field = $result
+                .$field
+                .iter()
+                .filter(|entry| {
+                    is_managed(entry.0.comment.as_ref()) || $target.$field.contains_key(&entry.0.$key)
+                })
+                .cloned()
+                .collect();
+        )*
+    };
+}
+
+/// The current configuration without the hand-made entries of the resources the provisioner
+/// marks. Mutations compared against it neither touch nor report those entries as drift, an
+/// entry the target generates with the same key is taken over.
+pub fn managed_current(
+    target: &BaseDeviceDataTarget,
+    current: &BaseDeviceDataCurrent,
+) -> BaseDeviceDataCurrent {
+    let mut result = current.clone();
+    retain_managed!(
+        result, target,
+        vlan => name,
+        vxlan => name,
+        vrrp => name,
+        gre => name,
+        eoip => name,
+        bridge => name,
+        dhcp_v_4_server => name,
+        dhcp_v_4_server_lease => address,
+        dhcp_v_4_relay => name,
+        pppoe_client => name,
+        ipv_4_pool => name,
+        ipv_4_address => address,
+        ipv_6_address => address,
+    );
+    result
+}
+
+/// entries of the current configuration keyed by a name missing in the target, only entries
+/// marked as managed are considered
+macro_rules! orphans_by_name {
+    ($result:ident, $target:ident, $current:ident, $referenced:ident, $($field:ident => $path:literal),* $(,)?) => {
+        $(
+            for entry in $current.$field.iter() {
+                if is_managed(entry.0.comment.as_ref())
+                    && !$target.$field.contains_key(&entry.0.name)
+                    && !$referenced.contains(&entry.0.name)
+                {
+                    $result.push(Orphan {
+                        path: $path,
+                        field: "name",
+                        name: entry.0.name.clone(),
+                    });
+                }
+            }
+        )*
+    };
+}
+
+/// entries of the current configuration keyed by their comment, the comment carries the marker
+macro_rules! orphans_by_comment {
+    ($result:ident, $target:ident, $current:ident, $($field:ident => $path:literal),* $(,)?) => {
+        $(
+            for entry in $current.$field.iter() {
+                if let Some(comment) = &entry.data.comment
+                    && is_managed(Some(comment))
+                    && !$target.$field.contains_key(&(comment.clone(),))
+                {
+                    $result.push(Orphan {
+                        path: $path,
+                        field: "comment",
+                        name: comment.clone(),
+                    });
+                }
+            }
+        )*
+    };
+}
+
+/// Interfaces the configuration on the device still uses after applying the target: by the
+/// generated entries and by the entries made by hand, which are never removed.
+fn referenced_interfaces(
+    target: &BaseDeviceDataTarget,
+    current: &BaseDeviceDataCurrent,
+) -> HashSet<AsciiString> {
+    let mut referenced = HashSet::new();
+    // the default bridge carries the ports of the device, even without generated ports
+    referenced.insert(AsciiString::from(DEFAULT_BRIDGE_NAME));
+    for (bridge, interface) in target.bridge_port.keys() {
+        referenced.insert(bridge.clone());
+        referenced.insert(interface.clone());
+    }
+    referenced.extend(target.vlan.values().map(|vlan| vlan.0.interface.clone()));
+    referenced.extend(
+        target
+            .ipv_4_address
+            .values()
+            .map(|address| address.0.interface.clone()),
+    );
+    referenced.extend(
+        target
+            .ipv_6_address
+            .values()
+            .map(|address| address.0.interface.clone()),
+    );
+    referenced.extend(
+        target
+            .pppoe_client
+            .values()
+            .map(|client| client.0.interface.clone()),
+    );
+    referenced.extend(
+        current
+            .vlan
+            .iter()
+            .filter(|vlan| !is_managed(vlan.0.comment.as_ref()))
+            .map(|vlan| vlan.0.interface.clone()),
+    );
+    referenced.extend(
+        current
+            .ipv_4_address
+            .iter()
+            .filter(|address| !is_managed(address.0.comment.as_ref()))
+            .map(|address| address.0.interface.clone()),
+    );
+    referenced
+}
+
+/// Resources on the device the provisioner created but did not generate anymore, like vlans of
+/// a removed network, stale dhcp servers or routes. Hand-made entries without the marker,
+/// ethernet ports, the default bridge, interfaces still used by other entries and single
+/// resources are never orphans.
+pub fn find_orphans(target: &BaseDeviceDataTarget, current: &BaseDeviceDataCurrent) -> Vec<Orphan> {
+    let referenced = referenced_interfaces(target, current);
+    // the names of dhcp servers are not interfaces
+    let no_reference = HashSet::new();
+    let mut result = Vec::new();
+    orphans_by_name!(
+        result, target, current, referenced,
+        vlan => "interface/vlan",
+        vxlan => "interface/vxlan",
+        vrrp => "interface/vrrp",
+        gre => "interface/gre",
+        eoip => "interface/eoip",
+        bridge => "interface/bridge",
+        pppoe_client => "interface/pppoe-client",
+    );
+    orphans_by_name!(
+        result, target, current, no_reference,
+        dhcp_v_4_server => "ip/dhcp-server",
+        dhcp_v_4_relay => "ip/dhcp-relay",
+    );
+    // a pool stays as long as a dhcp server on the device hands out its addresses
+    let used_pools = target
+        .dhcp_v_4_server
+        .values()
+        .map(|server| server.0.address_pool.clone())
+        .chain(
+            current
+                .dhcp_v_4_server
+                .iter()
+                .filter(|server| !result.iter().any(|orphan| orphan.name == server.0.name))
+                .map(|server| server.0.address_pool.clone()),
+        )
+        .collect::<HashSet<_>>();
+    orphans_by_name!(
+        result, target, current, used_pools,
+        ipv_4_pool => "ip/pool",
+    );
+    orphans_by_comment!(
+        result, target, current,
+        ipv_4_route => "ip/route",
+        ipv_4_firewall_nat => "ip/firewall/nat",
+    );
+    result
+}
@@ -0,0 +1,140 @@
+use crate::{
+    config::{Schedule, ScriptTemplate},
+    device::ros::{
+        capsman::configuration_name,
+        warnings::{WarningKind, Warnings},
+    },
+    topology::access::device::DeviceAccess,
+};
+use mikrotik_model::{ascii::AsciiString, mikrotik_model};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Commands switching the scheduled poe ports and wlans of a device on and off
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ScheduleCommands {
+    pub on: Vec<String>,
+    pub off: Vec<String>,
+}
+
+mikrotik_model!(
+    name = ScriptDeviceData,
+    detect = new,
+    fields(
+        script(by_key(path = "system/script", key = name)),
+        scheduler(by_key(path = "system/scheduler", key = name)),
+    ),
+);
+
+impl ScriptDeviceDataTarget {
+    fn new(_model: &[u8]) -> Self {
+        Self {
+            script: Default::default(),
+            scheduler: Default::default(),
+        }
+    }
+
+    /// Managed scripts from the config templates, a scheduler is added for templates with an interval
+    pub(super) fn generate_from(
+        &mut self,
+        device: &DeviceAccess,
+        templates: &HashMap<Box<str>, ScriptTemplate>,
+    ) {
+        for (name, template) in templates {
+            let name = AsciiString::from(name.as_ref());
+            let script = &mut self.script.entry(name.clone()).or_default().0;
+            script.source = render_source(template.source(), device).into();
+            if let Some(interval) = template.interval() {
+                let scheduler = &mut self.scheduler.entry(name.clone()).or_default().0;
+                scheduler.on_event = name.into();
+                scheduler.interval = interval.into();
+                scheduler.start_time = template.start_time().map(AsciiString::from);
+            }
+        }
+    }
+
+    /// Two scripts per schedule referenced by the device, run daily at the start and the end of
+    /// the time window. Ports of an unknown schedule stay switched on, wlans are left as they are.
+    pub(super) fn generate_schedules(
+        &mut self,
+        device: &DeviceAccess,
+        schedules: &HashMap<Box<str>, Schedule>,
+        warnings: &mut Warnings,
+    ) {
+        for (schedule_name, commands) in schedule_commands(device) {
+            let Some(schedule) = schedules.get(schedule_name.as_ref()) else {
+                warnings.push(
+                    WarningKind::SkippedResource,
+                    format!("Schedule {schedule_name} not configured"),
+                );
+                continue;
+            };
+            for (suffix, start_time, lines) in [
+                ("on", schedule.on(), commands.on),
+                ("off", schedule.off(), commands.off),
+            ] {
+                let name = AsciiString::from(format!("schedule-{schedule_name}-{suffix}"));
+                let script = &mut self.script.entry(name.clone()).or_default().0;
+                script.source = lines.join("\n").into();
+                let scheduler = &mut self.scheduler.entry(name.clone()).or_default().0;
+                scheduler.on_event = name.into();
+                scheduler.interval = DAY.into();
+                scheduler.start_time = Some(AsciiString::from(format!(
+                    "{:02}:{:02}:{:02}",
+                    start_time.hour(),
+                    start_time.minute(),
+                    start_time.second()
+                )));
+            }
+        }
+    }
+}
+
+/// commands by schedule name, only ports with poe enabled are switched
+pub(super) fn schedule_commands(device: &DeviceAccess) -> BTreeMap<Box<str>, ScheduleCommands> {
+    let mut result = BTreeMap::<Box<str>, ScheduleCommands>::new();
+    for port in device.interfaces() {
+        let (true, Some(schedule), Some(name)) = (
+            port.enable_poe(),
+            port.schedule(),
+            port.external_port().and_then(|port| port.default_name()),
+        ) else {
+            continue;
+        };
+        let commands = result.entry(schedule.into()).or_default();
+        for (lines, poe_out) in [(&mut commands.on, "auto-on"), (&mut commands.off, "off")] {
+            lines.push(format!(
+                "/interface ethernet set [find default-name=\"{name}\"] poe-out={poe_out}"
+            ));
+        }
+    }
+    if let Some(group) = device.wlan_controller_of() {
+        for wlan in group.wlan() {
+            let (Some(schedule), Some(ssid)) = (wlan.schedule(), wlan.ssid()) else {
+                continue;
+            };
+            let name = configuration_name(ssid);
+            let commands = result.entry(schedule.into()).or_default();
+            for (lines, action) in [(&mut commands.on, "enable"), (&mut commands.off, "disable")] {
+                lines.push(format!(
+                    "/caps-man interface {action} [find configuration=\"{name}\"]"
+                ));
+            }
+        }
+    }
+    result
+}
+
+fn render_source(source: &str, device: &DeviceAccess) -> String {
+    let address = device
+        .primary_ip()
+        .map(|ip| ip.to_string())
+        .unwrap_or_default();
+    source
+        .replace("{{device}}", device.name())
+        .replace("{{address}}", &address)
+}
@@ -1,91 +1,2146 @@
-use crate::device::ros::GapFinder;
+use crate::{
+    Error,
+    config::{
+        ApnProfile, DhcpOptionSet, GenerationProfile, HotspotConfig, PppoeCredentials,
+        RadiusConfig, Schedule,
+    },
+    device::{
+        AccessibleDevice,
+        bandwidth_test::{BandwidthTestProtocol, run_bandwidth_test},
+        command::{check_script_output, checked_script, run_command},
+        mock::MockDevice,
+        ros::{
+            BaseDeviceDataTarget, MappedPlane, SetupError, SwitchVlanConcept,
+            capsman::CapsmanDeviceDataTarget,
+            empty_current,
+            graphql::has_wireless_package,
+            hw_facts::{build_ethernet_ports, ethernet_ports},
+            l2::{L2Plane, L2Port, L2Setup},
+            management::management_path_cuts,
+            naming::NamingPolicy,
+            prune::{MANAGED_MARKER, Orphan, find_orphans, managed_current, mark_managed},
+            render_script, render_steps,
+            scripts::{ScheduleCommands, schedule_commands},
+            version::RouterOsVersion,
+            warnings::{GenerationWarning, WarningKind, Warnings},
+        },
+    },
+    secrets::{SECRET_MASK, mask_secrets},
+    topology::{
+        Device, DeviceId, IpAddressData, IpRangeData, PhysicalPortId, TopologyHolder,
+        access::{AccessTopology, ip_addresses::IpAddressAccess},
+        snapshot::{FixtureFormat, parse_topology},
+        test::TopologyBuilder,
+    },
+};
 use ipnet::Ipv4Net;
-use std::{net::Ipv4Addr, str::FromStr};
+use mikrotik_model::{
+    ascii::AsciiString,
+    model::{
+        CapsManSecurityAuthenticationTypes, CapsManSecurityEncryption,
+        InterfaceEthernetByDefaultName, InterfaceEthernetPoeOut, InterfaceVlanByName,
+        InterfaceVlanCfg, IpAddressByAddress, IpAddressCfg, IpFirewallFilterAction,
+        IpFirewallNatAction, IpHotspotProfileLoginBy, IpHotspotWalledGardenAction, IpServiceByName,
+        IpServiceCfg, IpUpnpInterfacesType, RadiusService,
+    },
+};
+use std::{collections::HashMap, net::Ipv4Addr, path::Path, sync::Arc, time::Duration};
 
 #[test]
-pub fn test_gap_empty() {
-    let finder = GapFinder::<Ipv4Addr>::new();
-    let gaps = finder
-        .find_gaps_ipv4("172.16.1.1/24".parse().unwrap())
+pub fn test_next_free_address() {
+    let mut builder = TopologyBuilder::default();
+    let mut ip_ids = Vec::new();
+    for ip in ["172.16.1.1/24", "172.16.1.5/24"] {
+        let id = builder.ip_addresses.next_id();
+        ip_ids.push(id);
+        builder.ip_addresses.insert(
+            id,
+            IpAddressData {
+                ip: ip.parse().unwrap(),
+                interface: None,
+                prefix: None,
+                vrf: None,
+            },
+        );
+    }
+    let range_id = builder.ip_ranges.next_id();
+    builder.ip_ranges.insert(
+        range_id,
+        IpRangeData {
+            is_dhcp: true,
+            net: "172.16.1.0/24".parse().unwrap(),
+            start: "172.16.1.8".parse().unwrap(),
+            end: "172.16.1.250".parse().unwrap(),
+            prefix: None,
+        },
+    );
+    let topology = Arc::new(builder.build());
+    let prefix = IpAddressAccess::create(topology, ip_ids[0])
+        .prefix()
+        .unwrap();
+    assert_eq!(
+        prefix.next_free_ipv4_addresses(7).as_ref(),
+        [
+            Ipv4Addr::new(172, 16, 1, 2),
+            Ipv4Addr::new(172, 16, 1, 3),
+            Ipv4Addr::new(172, 16, 1, 4),
+            Ipv4Addr::new(172, 16, 1, 6),
+            Ipv4Addr::new(172, 16, 1, 7),
+            Ipv4Addr::new(172, 16, 1, 251),
+            Ipv4Addr::new(172, 16, 1, 252),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_detect_device_on_mock() {
+    let mock = MockDevice::start().await.expect("cannot start mock");
+    mock.set_resource(
+        "system/routerboard",
+        [[
+            ("routerboard", "true"),
+            ("board-name", "hEX"),
+            ("model", "RB750Gr3"),
+            ("serial-number", "HCT0000001"),
+            ("firmware-type", "mt7621L"),
+            ("factory-firmware", "6.44.6"),
+            ("current-firmware", "7.16"),
+            ("upgrade-firmware", "7.16"),
+        ]
+        .as_slice()],
+    )
+    .await;
+    let client = mock.connect().await.expect("cannot connect to mock");
+    let target = BaseDeviceDataTarget::detect_device(&client)
+        .await
+        .expect("device not detected");
+    assert_eq!(target.ethernet.len(), 5);
+}
+
+#[tokio::test]
+async fn test_mock_applies_commands() {
+    let mock = MockDevice::start().await.expect("cannot start mock");
+    mock.set_resource("system/identity", [[("name", "MikroTik")].as_slice()])
+        .await;
+    let client = mock.connect().await.expect("cannot connect to mock");
+    run_command(&client, "/system/identity/set", &[("name", "router")])
+        .await
+        .expect("set failed");
+    run_command(&client, "/interface/bridge/add", &[("name", "bridge")])
+        .await
+        .expect("add failed");
+    let replies = run_command(&client, "/system/identity/print", &[])
+        .await
+        .expect("print failed");
+    assert_eq!(
+        replies.first().and_then(|r| r.get("name")).map(Box::as_ref),
+        Some("router")
+    );
+    assert_eq!(mock.resource("interface/bridge").await.len(), 1);
+    assert_eq!(mock.commands().await.len(), 2);
+    assert!(
+        run_command(&client, "/interface/bridge/remove", &[(".id", "*99")])
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_wireless_package_detection() {
+    let package = |name: &'static str, disabled: &'static str| {
+        [
+            ("name", name),
+            ("version", "7.16"),
+            ("build-time", "2024-09-20 13:00:27"),
+            ("scheduled", ""),
+            ("disabled", disabled),
+        ]
+    };
+    let mock = MockDevice::start().await.expect("cannot start mock");
+    let client = mock.connect().await.expect("cannot connect to mock");
+    let (routeros, wireless) = (package("routeros", "false"), package("wireless", "true"));
+    mock.set_resource("system/package", [routeros.as_slice(), wireless.as_slice()])
+        .await;
+    // a disabled package provides no menus
+    assert!(
+        !has_wireless_package(&client)
+            .await
+            .expect("packages not read")
+    );
+    let wireless = package("wireless", "false");
+    mock.set_resource("system/package", [routeros.as_slice(), wireless.as_slice()])
+        .await;
+    assert!(
+        has_wireless_package(&client)
+            .await
+            .expect("packages not read")
+    );
+}
+
+#[tokio::test]
+async fn test_tcp_check_only_documented_addresses() {
+    let mut builder = TopologyBuilder::default();
+    let device_id = builder.devices.next_id();
+    builder.devices.insert(
+        device_id,
+        Device {
+            name: "router".into(),
+            ..Default::default()
+        },
+    );
+    let ip_id = builder.ip_addresses.next_id();
+    builder.ip_addresses.insert(
+        ip_id,
+        IpAddressData {
+            ip: "127.0.0.2/8".parse().unwrap(),
+            interface: None,
+            prefix: None,
+            vrf: None,
+        },
+    );
+    let device = TopologyHolder::offline(builder.build())
+        .devices_by_id(device_id)
+        .await
+        .expect("device not found");
+    let mock = MockDevice::start().await.expect("cannot start mock");
+    let device = mock.accessible(device).await.expect("cannot connect");
+    let own = device
+        .check_tcp_port(None, mock.port())
+        .await
+        .expect("device address rejected");
+    assert!(own.open);
+    assert!(
+        device
+            .check_tcp_port(Some("127.0.0.2".parse().unwrap()), mock.port())
+            .await
+            .is_ok()
+    );
+    assert!(matches!(
+        device
+            .check_tcp_port(Some("10.99.0.1".parse().unwrap()), 22)
+            .await,
+        Err(Error::UnknownAddress(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_bandwidth_test_cleans_up_server() {
+    let mut builder = TopologyBuilder::default();
+    let device_id = builder.devices.next_id();
+    builder.devices.insert(
+        device_id,
+        Device {
+            name: "router".into(),
+            ..Default::default()
+        },
+    );
+    let device = TopologyHolder::offline(builder.build())
+        .devices_by_id(device_id)
+        .await
+        .expect("device not found");
+    let mock = MockDevice::start().await.expect("cannot start mock");
+    mock.set_resource("tool/bandwidth-server", [[("enabled", "no")].as_slice()])
+        .await;
+    mock.set_resource("user", [[("name", "admin"), ("group", "full")].as_slice()])
+        .await;
+    let server = mock
+        .accessible(device.clone())
+        .await
+        .expect("cannot connect");
+    let client = mock.accessible(device).await.expect("cannot connect");
+    // the mock does not know the test command, the server has to be cleaned up anyway
+    assert!(
+        run_bandwidth_test(
+            &server,
+            &client,
+            Duration::from_secs(1),
+            BandwidthTestProtocol::Tcp
+        )
+        .await
+        .is_err()
+    );
+    let users = mock.resource("user").await;
+    assert_eq!(1, users.len());
+    assert_eq!(Some("admin"), users[0].get("name").map(Box::as_ref));
+    assert!(mock.resource("user/group").await.is_empty());
+    assert_eq!(
+        Some("no"),
+        mock.resource("tool/bandwidth-server").await[0]
+            .get("enabled")
+            .map(Box::as_ref)
+    );
+    let test_user = mock
+        .commands()
+        .await
+        .into_iter()
+        .find(|c| c.command.as_ref() == "/user/add")
+        .expect("no test account created");
+    assert_eq!(
+        Some("test"),
+        mock.commands()
+            .await
+            .iter()
+            .find(|c| c.command.as_ref() == "/user/group/add")
+            .and_then(|c| c.attributes.get("policy"))
+            .map(Box::as_ref)
+    );
+    assert_ne!(
+        Some("admin"),
+        test_user.attributes.get("name").map(Box::as_ref)
+    );
+}
+
+#[tokio::test]
+async fn test_dhcp_relay() {
+    let topology = br#"
+devices:
+  1:
+    name: hex-branch
+    ports:
+      - !Interface 1
+      - !Interface 2
+interfaces:
+  1:
+    name: ether2
+    device: 1
+    external: !Ethernet 2
+    ips: [1]
+    enable_dhcp_relay: true
+    enable_dhcp_server: true
+  2:
+    name: ether3
+    device: 1
+    external: !Ethernet 3
+    ips: [2]
+    enable_dhcp_relay: true
+ip_addresses:
+  1:
+    ip: 10.0.60.1/24
+    interface: 1
+    prefix: 1
+  2:
+    ip: 10.0.61.1/24
+    interface: 2
+    prefix: 2
+ip_prefixes:
+  1:
+    prefix: 10.0.60.0/24
+    addresses: [1]
+    children: []
+    ranges: []
+  2:
+    prefix: 10.0.61.0/24
+    addresses: [2]
+    children: []
+    ranges: []
+    dhcp_relay: [10.0.1.5, 10.0.1.6]
+"#;
+    let profile = GenerationProfile {
+        dhcp_relay_servers: Box::new([Ipv4Addr::new(10, 0, 0, 10)]),
+        ..GenerationProfile::default()
+    };
+    let (target, _) = generate_device(topology, &profile).await;
+    let servers_of = |local_address: Ipv4Addr| {
+        target
+            .dhcp_v_4_relay
+            .values()
+            .find(|relay| relay.0.local_address == Some(local_address))
+            .map(|relay| relay.0.dhcp_server.iter().copied().collect::<Vec<_>>())
+    };
+    // the central servers of the profile, unless the prefix names its own
+    assert_eq!(
+        Some(vec![Ipv4Addr::new(10, 0, 0, 10)]),
+        servers_of(Ipv4Addr::new(10, 0, 60, 1))
+    );
+    assert_eq!(
+        Some(vec![Ipv4Addr::new(10, 0, 1, 5), Ipv4Addr::new(10, 0, 1, 6)]),
+        servers_of(Ipv4Addr::new(10, 0, 61, 1))
+    );
+    // a relayed network gets no own dhcp server
+    assert!(target.dhcp_v_4_server.is_empty());
+
+    // without any server to relay to no relay is generated
+    let without_servers =
+        String::from_utf8_lossy(topology).replace("    dhcp_relay: [10.0.1.5, 10.0.1.6]\n", "");
+    let (target, warnings) =
+        generate_device(without_servers.as_bytes(), &GenerationProfile::default()).await;
+    assert!(target.dhcp_v_4_relay.is_empty());
+    assert!(warnings.as_slice().iter().any(|warning| {
+        warning.kind() == WarningKind::SkippedResource
+            && warning.message().starts_with("No dhcp server to relay to")
+    }));
+}
+
+#[tokio::test]
+async fn test_lte_failover() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: hex-branch
+    ports:
+      - !Interface 1
+      - !Interface 2
+      - !Interface 3
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    external: !Ethernet 1
+    ips: [1]
+    wan: true
+  2:
+    name: lte-backup
+    device: 1
+    port_type: Lte
+    apn: internet
+  3:
+    name: lte1
+    device: 1
+    port_type: Lte
+    apn: internet
+ip_addresses:
+  1:
+    ip: 198.51.100.2/30
+    interface: 1
+    prefix: 1
+  2:
+    ip: 198.51.100.1/30
+    prefix: 1
+ip_prefixes:
+  1:
+    prefix: 198.51.100.0/30
+    addresses: [1, 2]
+    children: []
+    ranges: []
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let profile: ApnProfile =
+        serde_yaml::from_str("apn: internet.provider.example").expect("invalid apn profile");
+    let apn_profiles = HashMap::from([(Box::from("internet"), profile)]);
+    let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+    let mut warnings = Warnings::default();
+    target
+        .generate_from(
+            &device,
+            &GenerationProfile::default(),
+            None,
+            &HashMap::new(),
+            &apn_profiles,
+            &HashMap::new(),
+            Path::new(""),
+            &HashMap::new(),
+            &mut warnings,
+        )
+        .expect("generation failed");
+    // the modems are found by their default name, a renamed one takes the next free number
+    let names = target
+        .lte
+        .iter()
+        .map(|(default_name, lte)| (default_name.to_string(), lte.0.name.to_string()))
         .collect::<Vec<_>>();
     assert_eq!(
-        gaps,
-        vec!["172.16.1.1".parse().unwrap().."172.16.1.254".parse().unwrap()]
+        vec![
+            ("lte1".to_string(), "lte1".to_string()),
+            ("lte2".to_string(), "lte-backup".to_string()),
+        ],
+        names
+    );
+    // the traffic failing over to lte is masqueraded
+    for name in ["lte1", "lte-backup"] {
+        let comment = AsciiString::from(format!("masquerade {name}; {MANAGED_MARKER}"));
+        let rule = &target.ipv_4_firewall_nat[&(comment,)];
+        assert_eq!(IpFirewallNatAction::Masquerade, rule.action);
+        assert_eq!(Some(AsciiString::from(name)), rule.out_interface);
+    }
+    // the wan route is given up while the provider router does not answer
+    let route = target
+        .ipv_4_route
+        .values()
+        .next()
+        .expect("no route to the wan gateway");
+    assert_eq!(AsciiString::from("198.51.100.1"), route.gateway);
+}
+
+#[tokio::test]
+async fn test_pppoe_client() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: hex-cpe
+    ports:
+      - !Interface 1
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    external: !Ethernet 1
+    port_type: Ethernet
+    wan: true
+    pppoe: true
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let profile = GenerationProfile {
+        pppoe_client: true,
+        ..GenerationProfile::default()
+    };
+    let secrets_dir = std::env::temp_dir().join("provisioner-pppoe-secrets");
+    std::fs::create_dir_all(&secrets_dir).expect("cannot create secrets dir");
+    std::fs::write(secrets_dir.join("cpe-pppoe"), "p4ss-cpe-4711\n").expect("cannot write secret");
+    let generate = |password_name: &str| {
+        let credentials: PppoeCredentials = serde_yaml::from_str(&format!(
+            "user: customer-4711\npassword_name: {password_name}"
+        ))
+        .expect("invalid pppoe credentials");
+        let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+        let mut warnings = Warnings::default();
+        target
+            .generate_from(
+                &device,
+                &profile,
+                None,
+                &HashMap::new(),
+                &HashMap::new(),
+                &HashMap::from([(Box::from("hex-cpe"), credentials)]),
+                &secrets_dir,
+                &HashMap::new(),
+                &mut warnings,
+            )
+            .expect("generation failed");
+        (target, warnings)
+    };
+
+    let (target, warnings) = generate("cpe-pppoe");
+    assert!(warnings.as_slice().is_empty());
+    let client = &target.pppoe_client[&AsciiString::from("pppoe-ether1")].0;
+    assert_eq!(AsciiString::from("customer-4711"), client.user);
+    assert_eq!(AsciiString::from("p4ss-cpe-4711"), client.password);
+    // the script shown to the users does not contain the password
+    let current = empty_current(&target);
+    let script = render_script(
+        &target
+            .generate_mutations(&current)
+            .expect("mutations not generated"),
+    )
+    .expect("script not rendered");
+    assert!(script.contains("p4ss-cpe-4711"));
+    let shown = mask_secrets(&script);
+    assert!(!shown.contains("p4ss-cpe-4711"));
+    assert!(shown.contains(SECRET_MASK));
+
+    // without the password the pppoe client is skipped
+    let (target, warnings) = generate("missing");
+    assert!(target.pppoe_client.is_empty());
+    assert_eq!(
+        vec![WarningKind::SkippedResource],
+        warnings
+            .as_slice()
+            .iter()
+            .map(|warning| warning.kind())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn test_lte_apn_password() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: hex-branch
+    ports:
+      - !Interface 1
+interfaces:
+  1:
+    name: lte1
+    device: 1
+    port_type: Lte
+    apn: internet
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let secrets_dir = std::env::temp_dir().join("provisioner-apn-secrets");
+    std::fs::create_dir_all(&secrets_dir).expect("cannot create secrets dir");
+    std::fs::write(secrets_dir.join("lte-apn"), "4pn-s3cret\n").expect("cannot write secret");
+    let generate = |password_name: &str| {
+        let profile: ApnProfile = serde_yaml::from_str(&format!(
+            "apn: internet.provider.example\nuser: branch\npassword_name: {password_name}"
+        ))
+        .expect("invalid apn profile");
+        let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+        let mut warnings = Warnings::default();
+        target
+            .generate_from(
+                &device,
+                &GenerationProfile::default(),
+                None,
+                &HashMap::new(),
+                &HashMap::from([(Box::from("internet"), profile)]),
+                &HashMap::new(),
+                &secrets_dir,
+                &HashMap::new(),
+                &mut warnings,
+            )
+            .expect("generation failed");
+        (target, warnings)
+    };
+
+    let (target, warnings) = generate("lte-apn");
+    assert!(warnings.as_slice().is_empty());
+    let apn = &target.lte_apn[&AsciiString::from("apn-internet")].0;
+    assert_eq!(Some(AsciiString::from("4pn-s3cret")), apn.password);
+    // the script shown to the users does not contain the password
+    let current = empty_current(&target);
+    let script = render_script(
+        &target
+            .generate_mutations(&current)
+            .expect("mutations not generated"),
+    )
+    .expect("script not rendered");
+    assert!(script.contains("4pn-s3cret"));
+    let shown = mask_secrets(&script);
+    assert!(!shown.contains("4pn-s3cret"));
+    assert!(shown.contains(SECRET_MASK));
+
+    // without the password the lte interface is skipped
+    let (target, warnings) = generate("missing");
+    assert!(target.lte_apn.is_empty());
+    assert_eq!(
+        vec![WarningKind::SkippedResource],
+        warnings
+            .as_slice()
+            .iter()
+            .map(|warning| warning.kind())
+            .collect::<Vec<_>>()
+    );
+}
+
+/// configuration of device 1 of the topology for a hEX
+async fn generate_device(
+    topology: &[u8],
+    profile: &GenerationProfile,
+) -> (BaseDeviceDataTarget, Warnings) {
+    generate_device_with(topology, profile, None, &HashMap::new()).await
+}
+
+/// configuration of device 1 of the topology for a hEX with the captive portal settings and
+/// dhcp option sets
+async fn generate_device_with(
+    topology: &[u8],
+    profile: &GenerationProfile,
+    hotspot: Option<&HotspotConfig>,
+    dhcp_option_sets: &HashMap<Box<str>, DhcpOptionSet>,
+) -> (BaseDeviceDataTarget, Warnings) {
+    let topology = parse_topology(topology, FixtureFormat::Yaml).expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+    let mut warnings = Warnings::default();
+    target
+        .generate_from(
+            &device,
+            profile,
+            hotspot,
+            dhcp_option_sets,
+            &HashMap::new(),
+            &HashMap::new(),
+            Path::new(""),
+            &HashMap::new(),
+            &mut warnings,
+        )
+        .expect("generation failed");
+    (target, warnings)
+}
+
+async fn generate_eoip_tunnel(tunnel_id: Option<u16>) -> (BaseDeviceDataTarget, Warnings) {
+    let tunnel_id = tunnel_id.map(|id| id.to_string()).unwrap_or("~".into());
+    let topology = format!(
+        r#"
+devices:
+  1:
+    name: router
+    ports:
+      - !Interface 1
+      - !Interface 2
+      - !Interface 3
+      - !Interface 4
+  2:
+    name: remote
+    ports:
+      - !Interface 5
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    external: !Ethernet 1
+    ips: [1]
+  2:
+    name: ether2
+    device: 1
+    external: !Ethernet 2
+    bridge: 3
+  3:
+    name: bridge
+    device: 1
+  4:
+    name: tunnel-a
+    device: 1
+    bridge: 3
+    tunnel: 1
+  5:
+    name: tunnel-b
+    device: 2
+    tunnel: 1
+ip_addresses:
+  1:
+    ip: 192.0.2.1/24
+  2:
+    ip: 192.0.2.2/24
+tunnels:
+  1:
+    name: site-link
+    encapsulation: Eoip
+    tunnel_id: {tunnel_id}
+    terminations:
+      - interface: 4
+        outside_ip: 1
+      - interface: 5
+        outside_ip: 2
+"#
+    );
+    generate_device(topology.as_bytes(), &GenerationProfile::default()).await
+}
+
+#[tokio::test]
+async fn test_eoip_tunnel_requires_id() {
+    let tunnel = AsciiString::from("tunnel-a");
+    let skipped = |warnings: &Warnings| {
+        warnings
+            .as_slice()
+            .iter()
+            .filter(|warning| warning.kind() == WarningKind::SkippedResource)
+            .map(|warning| warning.message().to_string())
+            .collect::<Vec<_>>()
+    };
+    let (target, warnings) = generate_eoip_tunnel(Some(7)).await;
+    assert!(target.eoip.contains_key(&tunnel));
+    assert!(target.bridge_port.keys().any(|(_, port)| port == &tunnel));
+    assert!(skipped(&warnings).is_empty());
+
+    let (target, warnings) = generate_eoip_tunnel(None).await;
+    assert!(target.eoip.is_empty());
+    // the tunnel is not bridged either
+    assert!(!target.bridge_port.keys().any(|(_, port)| port == &tunnel));
+    assert_eq!(
+        vec!["Missing tunnel id on eoip tunnel site-link"],
+        skipped(&warnings)
+    );
+}
+
+#[tokio::test]
+async fn test_profile_without_dhcp_server() {
+    let topology = br#"
+devices:
+  1:
+    name: core
+    role: core-router
+    ports:
+      - !Interface 1
+      - !Interface 2
+interfaces:
+  1:
+    name: bridge
+    device: 1
+    ips: [1]
+    enable_dhcp_server: true
+  2:
+    name: ether2
+    device: 1
+    external: !Ethernet 2
+    bridge: 1
+ip_addresses:
+  1:
+    ip: 192.168.88.1/24
+    interface: 1
+    prefix: 1
+ip_prefixes:
+  1:
+    prefix: 192.168.88.0/24
+    addresses: [1]
+    children: []
+    ranges: []
+"#;
+    let (target, _) = generate_device(topology, &GenerationProfile::default()).await;
+    assert_eq!(1, target.dhcp_v_4_server.len());
+    let core = GenerationProfile {
+        dhcp_server: false,
+        ..GenerationProfile::default()
+    };
+    let (target, _) = generate_device(topology, &core).await;
+    assert!(target.dhcp_v_4_server.is_empty());
+    assert!(target.dhcp_v_4_server_network.is_empty());
+    assert!(target.ipv_4_pool.is_empty());
+}
+
+#[tokio::test]
+async fn test_guest_network_isolation() {
+    let topology = br#"
+devices:
+  1:
+    name: router
+    ports:
+      - !Interface 1
+      - !Interface 2
+      - !Interface 3
+interfaces:
+  1:
+    name: bridge
+    device: 1
+  2:
+    name: ether2
+    device: 1
+    external: !Ethernet 2
+    bridge: 1
+    vlan: 1
+  3:
+    name: guest
+    device: 1
+    bridge: 1
+    vlan: 1
+    ips: [1]
+    enable_dhcp_server: true
+vlan_groups:
+  1:
+    vlans: [1]
+vlans:
+  1:
+    name: guest
+    vlan_id: 20
+    group: 1
+    guest: true
+    terminations: [2, 3]
+    wlans: []
+ip_addresses:
+  1:
+    ip: 10.0.20.1/24
+    interface: 3
+    prefix: 1
+ip_prefixes:
+  1:
+    prefix: 10.0.20.0/24
+    addresses: [1]
+    children: []
+    ranges: []
+"#;
+    let profile = GenerationProfile {
+        guest_lease_time_secs: 600,
+        ..GenerationProfile::default()
+    };
+    let (target, _) = generate_device(topology, &profile).await;
+    let server = &target
+        .dhcp_v_4_server
+        .values()
+        .next()
+        .expect("no dhcp server")
+        .0;
+    let mut expected = server.clone();
+    expected.lease_time = Duration::from_secs(600).into();
+    assert_eq!(&expected, server);
+    let guest_lease_time = server.lease_time.clone();
+
+    // the private networks are dropped in the forward chain, the router stays reachable
+    assert_eq!(3, target.ipv_4_firewall_filter.len());
+    for network in ["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16"] {
+        let network = network.parse::<Ipv4Net>().expect("invalid network");
+        let comment = AsciiString::from(format!(
+            "guest {} to {network}; {MANAGED_MARKER}",
+            server.interface
+        ));
+        let rule = &target.ipv_4_firewall_filter[&(comment,)];
+        let mut expected = rule.clone();
+        expected.chain = b"forward".into();
+        expected.action = IpFirewallFilterAction::Drop;
+        expected.in_interface = Some(server.interface.clone());
+        expected.dst_address = Some(network.into());
+        assert_eq!(&expected, rule);
+    }
+
+    // other networks are not isolated and keep the default lease time
+    let private = String::from_utf8_lossy(topology).replace("guest: true", "guest: false");
+    let (target, _) = generate_device(private.as_bytes(), &profile).await;
+    assert!(target.ipv_4_firewall_filter.is_empty());
+    let server = &target
+        .dhcp_v_4_server
+        .values()
+        .next()
+        .expect("no dhcp server")
+        .0;
+    assert_ne!(guest_lease_time, server.lease_time);
+}
+
+#[tokio::test]
+async fn test_hotspot_on_captive_portal() {
+    let topology = br#"
+devices:
+  1:
+    name: router
+    ports:
+      - !Interface 1
+      - !Interface 2
+interfaces:
+  1:
+    name: ether2
+    device: 1
+    external: !Ethernet 2
+    ips: [1]
+    captive_portal: true
+  2:
+    name: ether3
+    device: 1
+    external: !Ethernet 3
+    ips: [2]
+ip_addresses:
+  1:
+    ip: 10.0.30.1/24
+    interface: 1
+  2:
+    ip: 10.0.40.1/24
+    interface: 2
+"#;
+    let hotspot: HotspotConfig = serde_yaml::from_str(
+        r#"
+dns_name: login.example.com
+trial: true
+rate_limit: 2M/10M
+walled_garden:
+  - "*.example.com"
+"#,
+    )
+    .expect("invalid hotspot config");
+    let profile = GenerationProfile::default();
+    let (target, _) =
+        generate_device_with(topology, &profile, Some(&hotspot), &HashMap::new()).await;
+
+    // only the tagged interface serves the portal
+    assert_eq!(1, target.hotspot.len());
+    let (name, server) = target.hotspot.iter().next().expect("no hotspot");
+    let server = &server.0;
+    assert_eq!(
+        &AsciiString::from(format!("hotspot-{}", server.interface)),
+        name
+    );
+    assert_eq!(name, &server.profile);
+    let hotspot_profile = &target.hotspot_profile[name].0;
+    assert_eq!(
+        Some(Ipv4Addr::new(10, 0, 30, 1)),
+        hotspot_profile.hotspot_address
+    );
+    assert_eq!(
+        Some(AsciiString::from("login.example.com")),
+        hotspot_profile.dns_name
+    );
+    let mut expected = hotspot_profile.clone();
+    expected.login_by = [
+        IpHotspotProfileLoginBy::HttpChap,
+        IpHotspotProfileLoginBy::Cookie,
+        IpHotspotProfileLoginBy::Trial,
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(&expected, hotspot_profile);
+    let user_profile = &target.hotspot_user_profile[&AsciiString::from("default")].0;
+    assert_eq!(Some(AsciiString::from("2M/10M")), user_profile.rate_limit);
+    let walled_garden = &target.hotspot_walled_garden[&(AsciiString::from("*.example.com"),)];
+    assert_eq!(IpHotspotWalledGardenAction::Allow, walled_garden.action);
+
+    // without settings no portal is generated
+    let (target, _) = generate_device(topology, &profile).await;
+    assert!(target.hotspot.is_empty());
+    assert!(target.hotspot_profile.is_empty());
+    assert!(target.hotspot_walled_garden.is_empty());
+}
+
+#[tokio::test]
+async fn test_dhcp_options_of_parent_prefix() {
+    let topology = br#"
+devices:
+  1:
+    name: router
+    ports:
+      - !Interface 1
+interfaces:
+  1:
+    name: ether2
+    device: 1
+    external: !Ethernet 2
+    ips: [1]
+    enable_dhcp_server: true
+ip_addresses:
+  1:
+    ip: 10.0.40.1/24
+    interface: 1
+    prefix: 2
+ip_prefixes:
+  1:
+    prefix: 10.0.0.0/16
+    addresses: []
+    children: [2]
+    ranges: []
+    dhcp_options: voip
+  2:
+    prefix: 10.0.40.0/24
+    addresses: [1]
+    children: []
+    parent: 1
+    ranges: []
+"#;
+    let option_sets: HashMap<Box<str>, DhcpOptionSet> = serde_yaml::from_str(
+        r#"
+voip:
+  domain: voip.example.com
+  tftp_server: 10.0.40.5
+  boot_file: provisioning.cfg
+  ntp_servers: [10.0.40.5]
+  options:
+    sip-server:
+      code: 120
+      value: "'sip.example.com'"
+"#,
+    )
+    .expect("invalid option sets");
+    let profile = GenerationProfile::default();
+    let (target, _) = generate_device_with(topology, &profile, None, &option_sets).await;
+
+    // the raw options are prefixed by the name of the set
+    let option = &target.dhcp_v_4_server_option[&AsciiString::from("voip-sip-server")].0;
+    assert_eq!(120, option.code);
+    assert_eq!(AsciiString::from("'sip.example.com'"), option.value);
+    let net = "10.0.40.0/24".parse::<Ipv4Net>().expect("invalid network");
+    let network = &target.dhcp_v_4_server_network[&net].0;
+    let tftp_server = Ipv4Addr::new(10, 0, 40, 5);
+    assert_eq!(Some(AsciiString::from("voip.example.com")), network.domain);
+    assert_eq!(Some(tftp_server), network.next_server);
+    assert_eq!(
+        Some(AsciiString::from("provisioning.cfg")),
+        network.boot_file_name
+    );
+    assert_eq!(
+        vec![tftp_server],
+        network.ntp_server.iter().copied().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![AsciiString::from("voip-sip-server")],
+        network.dhcp_option.iter().cloned().collect::<Vec<_>>()
+    );
+
+    // an unknown set leaves the network without options
+    let (target, _) = generate_device(topology, &profile).await;
+    assert!(target.dhcp_v_4_server_option.is_empty());
+    let network = &target.dhcp_v_4_server_network[&net].0;
+    assert_eq!(None, network.domain);
+    assert!(network.dhcp_option.is_empty());
+}
+
+#[tokio::test]
+async fn test_static_lease_for_documented_mac() {
+    let topology = br#"
+devices:
+  1:
+    name: router
+    ports:
+      - !Interface 1
+  2:
+    name: printer
+    ports:
+      - !Interface 2
+  3:
+    name: laptop
+    ports:
+      - !Interface 3
+interfaces:
+  1:
+    name: ether2
+    device: 1
+    external: !Ethernet 2
+    ips: [1]
+    enable_dhcp_server: true
+    mac_address: "48:A9:8A:00:00:01"
+  2:
+    name: eth0
+    device: 2
+    ips: [2]
+    mac_address: "48:A9:8A:01:02:03"
+  3:
+    name: wlan0
+    device: 3
+    ips: [3]
+ip_addresses:
+  1:
+    ip: 10.0.50.1/24
+    interface: 1
+    prefix: 1
+  2:
+    ip: 10.0.50.20/24
+    interface: 2
+    prefix: 1
+  3:
+    ip: 10.0.50.30/24
+    interface: 3
+    prefix: 1
+ip_prefixes:
+  1:
+    prefix: 10.0.50.0/24
+    addresses: [1, 2, 3]
+    children: []
+    ranges: []
+"#;
+    let (target, _) = generate_device(topology, &GenerationProfile::default()).await;
+    let server = target
+        .dhcp_v_4_server
+        .keys()
+        .next()
+        .expect("no dhcp server")
+        .clone();
+    // neither the router itself nor the client without a mac get a lease
+    assert_eq!(1, target.dhcp_v_4_server_lease.len());
+    let lease = &target.dhcp_v_4_server_lease[&Ipv4Addr::new(10, 0, 50, 20)].0;
+    let mut expected = lease.clone();
+    expected.mac_address = "48:A9:8A:01:02:03".into();
+    expected.server = server;
+    expected.comment = Some("printer eth0".into());
+    assert_eq!(&expected, lease);
+}
+
+#[tokio::test]
+async fn test_management_services_keep_current_settings() {
+    let topology = br#"
+devices:
+  1:
+    name: edge
+    ports:
+      - !Interface 1
+interfaces:
+  1:
+    name: ether5
+    device: 1
+    external: !Ethernet 5
+    management: true
+    ips: [1]
+ip_addresses:
+  1:
+    ip: 10.99.0.5/24
+    interface: 1
+"#;
+    let topology = parse_topology(topology, FixtureFormat::Yaml).expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+    let mut current = empty_current(&target);
+    current.ip_service = [IpServiceByName(IpServiceCfg {
+        name: "ssh".into(),
+        port: 2222,
+        address: ["192.168.88.0/24".parse().unwrap()].into_iter().collect(),
+        ..Default::default()
+    })]
+    .into();
+    target.keep_current_services(&current);
+    let mut warnings = Warnings::default();
+    target
+        .generate_from(
+            &device,
+            &GenerationProfile::default(),
+            None,
+            &HashMap::new(),
+            &HashMap::new(),
+            &HashMap::new(),
+            Path::new(""),
+            &HashMap::new(),
+            &mut warnings,
+        )
+        .expect("generation failed");
+    let ssh = &target.ip_service[&AsciiString::from("ssh")].0;
+    assert_eq!(2222, ssh.port);
+    // the management network replaces the addresses allowed before
+    assert_eq!(
+        ssh.address.iter().copied().collect::<Vec<_>>(),
+        vec!["10.99.0.0/24".parse().unwrap()]
     );
 }
+
+/// generateCfg shows the script of the plan, it has to be the same as the rendered script
+#[tokio::test]
+async fn test_render_script_matches_plan_steps() {
+    let topology = br#"
+devices:
+  1:
+    name: router
+    ports:
+      - !Interface 1
+      - !Interface 2
+interfaces:
+  1:
+    name: bridge
+    device: 1
+    ips: [1]
+  2:
+    name: ether2
+    device: 1
+    external: !Ethernet 2
+    bridge: 1
+ip_addresses:
+  1:
+    ip: 192.168.88.1/24
+    interface: 1
+"#;
+    let (target, _) = generate_device(topology, &GenerationProfile::default()).await;
+    let mutations = target
+        .generate_mutations(&empty_current(&target))
+        .expect("mutations failed");
+    let steps = render_steps(&mutations).expect("rendering steps failed");
+    assert!(!steps.is_empty());
+    assert_eq!(
+        render_script(&mutations).expect("rendering failed"),
+        steps
+            .into_iter()
+            .map(|step| step.script)
+            .collect::<String>()
+    );
+}
+
 #[test]
-pub fn test_gap_none() {
-    let mut finder = GapFinder::<Ipv4Addr>::new();
-    finder.reserve("172.16.1.0".parse().unwrap().."172.16.1.255".parse().unwrap());
-    let gaps = finder
-        .find_gaps_ipv4("172.16.1.1/24".parse().unwrap())
-        .collect::<Vec<_>>();
-    assert_eq!(gaps, vec![]);
+fn test_script_output_check() {
+    let script = checked_script("/interface/vlan add name=vlan10");
+    assert!(script.starts_with(":do {\n/interface/vlan add name=vlan10\n"));
+    assert!(script.contains("on-error="));
+    assert!(check_script_output("provisioner-script-done").is_ok());
+    // a failing command skips the done marker
+    assert!(check_script_output("provisioner-script-failed").is_err());
+    // no output at all, the script did not run through
+    assert!(check_script_output("").is_err());
 }
+
 #[test]
-pub fn test_gap_router_ip() {
-    let mut finder = GapFinder::<Ipv4Addr>::new();
-    finder.reserve_ipv4("172.16.1.1".parse().unwrap());
-    let gaps = finder
-        .find_gaps_ipv4("172.16.1.1/24".parse().unwrap())
-        .collect::<Vec<_>>();
+fn test_find_orphans() {
+    let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+    target.vlan.insert(
+        "ether1-10".into(),
+        InterfaceVlanByName(InterfaceVlanCfg {
+            name: "ether1-10".into(),
+            interface: "ether1".into(),
+            vlan_id: 10,
+            ..Default::default()
+        }),
+    );
+    let mut current = empty_current(&target);
+    // ether1-30 was created by hand and is left alone, ether1-40 still carries an address
+    current.vlan = [
+        ("ether1-10", Some(MANAGED_MARKER)),
+        ("ether1-20", Some(MANAGED_MARKER)),
+        ("ether1-30", None),
+        ("ether1-40", Some(MANAGED_MARKER)),
+    ]
+    .into_iter()
+    .map(|(name, comment)| {
+        InterfaceVlanByName(InterfaceVlanCfg {
+            name: name.into(),
+            interface: "ether1".into(),
+            comment: comment.map(Into::into),
+            ..Default::default()
+        })
+    })
+    .collect();
+    current.ipv_4_address = Box::new([IpAddressByAddress(IpAddressCfg {
+        address: "10.0.40.1/24".parse().unwrap(),
+        interface: "ether1-40".into(),
+        comment: None,
+    })]);
+    let orphans = find_orphans(&target, &current);
+    assert_eq!(
+        orphans,
+        vec![Orphan {
+            path: "interface/vlan",
+            field: "name",
+            name: "ether1-20".into(),
+        }]
+    );
+    assert_eq!(
+        orphans[0].script(),
+        "/interface/vlan remove [ find name=\"ether1-20\" ]\n"
+    );
+    let quoted = Orphan {
+        path: "interface/vlan",
+        field: "name",
+        name: "a\"b$c".into(),
+    };
     assert_eq!(
-        gaps,
-        vec!["172.16.1.2".parse().unwrap().."172.16.1.254".parse().unwrap()]
+        quoted.script(),
+        "/interface/vlan remove [ find name=\"a\\\"b\\$c\" ]\n"
     );
 }
+
 #[test]
-pub fn test_gap_router_and_host_ip() {
-    let mut finder = GapFinder::<Ipv4Addr>::new();
-    finder.reserve_ipv4("172.16.1.1".parse().unwrap());
-    finder.reserve_ipv4("172.16.1.10".parse().unwrap());
-    let gaps = finder
-        .find_gaps_ipv4("172.16.1.1/24".parse().unwrap())
-        .collect::<Vec<_>>();
+fn test_managed_current() {
+    let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+    target.vlan.insert(
+        "ether1-10".into(),
+        InterfaceVlanByName(InterfaceVlanCfg {
+            name: "ether1-10".into(),
+            ..Default::default()
+        }),
+    );
+    let mut current = empty_current(&target);
+    // ether1-10 is taken over by the target, ether1-30 was created by hand
+    current.vlan = [
+        ("ether1-10", None),
+        ("ether1-20", Some(MANAGED_MARKER)),
+        ("ether1-30", None),
+    ]
+    .into_iter()
+    .map(|(name, comment)| {
+        InterfaceVlanByName(InterfaceVlanCfg {
+            name: name.into(),
+            comment: comment.map(Into::into),
+            ..Default::default()
+        })
+    })
+    .collect();
+    let managed = managed_current(&target, &current);
     assert_eq!(
-        gaps,
-        vec![
-            "172.16.1.2".parse().unwrap().."172.16.1.9".parse().unwrap(),
-            "172.16.1.11".parse().unwrap().."172.16.1.254".parse().unwrap()
-        ]
+        managed
+            .vlan
+            .iter()
+            .map(|vlan| vlan.0.name.to_string())
+            .collect::<Vec<_>>(),
+        ["ether1-10", "ether1-20"]
+    );
+    // the hand-made entry is still known for the orphans
+    assert_eq!(current.vlan.len(), 3);
+}
+
+#[test]
+fn test_mark_managed() {
+    let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+    for (name, comment) in [("ether1-10", Some("uplink")), ("ether1-20", None)] {
+        target.vlan.insert(
+            name.into(),
+            InterfaceVlanByName(InterfaceVlanCfg {
+                name: name.into(),
+                comment: comment.map(Into::into),
+                ..Default::default()
+            }),
+        );
+    }
+    mark_managed(&mut target);
+    // marking twice does not repeat the marker
+    mark_managed(&mut target);
+    let comment_of = |name: &str| {
+        target.vlan[&AsciiString::from(name)]
+            .0
+            .comment
+            .as_ref()
+            .map(ToString::to_string)
+    };
+    assert_eq!(
+        comment_of("ether1-10"),
+        Some(format!("uplink; {MANAGED_MARKER}"))
+    );
+    assert_eq!(comment_of("ether1-20"), Some(MANAGED_MARKER.to_string()));
+}
+
+#[tokio::test]
+async fn test_schedule_commands() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: switch
+    ports:
+      - !Interface 1
+      - !Interface 2
+      - !Interface 3
+interfaces:
+  1:
+    name: ether2
+    device: 1
+    external: !Ethernet 2
+    enable_poe: true
+    schedule: office
+  2:
+    name: ether3
+    device: 1
+    external: !Ethernet 3
+    schedule: office
+  3:
+    name: ether4
+    device: 1
+    external: !Ethernet 4
+    enable_poe: true
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let commands = schedule_commands(&device);
+    assert_eq!(1, commands.len());
+    assert_eq!(
+        Some(&ScheduleCommands {
+            on: vec![
+                "/interface ethernet set [find default-name=\"ether2\"] poe-out=auto-on".into()
+            ],
+            off: vec!["/interface ethernet set [find default-name=\"ether2\"] poe-out=off".into()],
+        }),
+        commands.get("office")
+    );
+}
+
+#[tokio::test]
+async fn test_scheduled_poe_state() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: switch
+    ports:
+      - !Interface 1
+      - !Interface 2
+      - !Interface 3
+interfaces:
+  1:
+    name: ether2
+    device: 1
+    external: !Ethernet 2
+    enable_poe: true
+    schedule: office
+  2:
+    name: ether3
+    device: 1
+    external: !Ethernet 3
+    enable_poe: true
+    schedule: unknown
+  3:
+    name: ether4
+    device: 1
+    external: !Ethernet 4
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let office: Schedule =
+        serde_yaml::from_str("on: \"07:00\"\noff: \"19:00\"").expect("invalid schedule");
+    let expected_office = if office.is_on_now() {
+        InterfaceEthernetPoeOut::AutoOn
+    } else {
+        InterfaceEthernetPoeOut::Off
+    };
+    let mut target = BaseDeviceDataTarget::new(b"CRS318-16P-2S+").expect("model not found");
+    target
+        .generate_from(
+            &device,
+            &GenerationProfile::default(),
+            None,
+            &HashMap::new(),
+            &HashMap::new(),
+            &HashMap::new(),
+            Path::new(""),
+            &HashMap::from([("office".into(), office)]),
+            &mut Warnings::default(),
+        )
+        .expect("generation failed");
+    let poe_out = |name: &str| target.ethernet[&AsciiString::from(name)].poe_out.clone();
+    // the scheduled port starts in the state of the schedule, the poe stays managed
+    assert_eq!(Some(expected_office), poe_out("ether2"));
+    assert_eq!(Some(InterfaceEthernetPoeOut::AutoOn), poe_out("ether3"));
+    assert_eq!(Some(InterfaceEthernetPoeOut::Off), poe_out("ether4"));
+}
+
+#[tokio::test]
+async fn test_render_config_for_unknown_model() {
+    let topology = parse_topology(
+        include_bytes!("../../topology/fixtures/single-router.yaml"),
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid fixture");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    assert!(matches!(
+        device.render_config_for_model("RB0000"),
+        Err(Error::SetupError(SetupError::NoPortsFound(model))) if model == AsciiString::from("RB0000")
+    ));
+}
+
+#[test]
+fn test_ethernet_port_templates() {
+    let names = |ports: &[InterfaceEthernetByDefaultName]| {
+        let mut names = ports
+            .iter()
+            .map(|port| port.default_name.clone())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+    let ports = ethernet_ports(b"CRS326-24G-2S+");
+    // later targets of the model share the ports built for the first one
+    assert!(Arc::ptr_eq(&ports, &ethernet_ports(b"CRS326-24G-2S+")));
+    assert_eq!(
+        names(&build_ethernet_ports(b"CRS326-24G-2S+")),
+        names(&ports)
+    );
+    let target = BaseDeviceDataTarget::new(b"CRS326-24G-2S+").expect("model not found");
+    let mut target_names = target.ethernet.keys().cloned().collect::<Vec<_>>();
+    target_names.sort();
+    assert_eq!(names(&ports), target_names);
+    // unknown models are not kept
+    assert!(ethernet_ports(b"RB0000").is_empty());
+    assert!(!Arc::ptr_eq(
+        &ethernet_ports(b"RB0000"),
+        &ethernet_ports(b"RB0000")
+    ));
+}
+
+#[test]
+fn test_routeros_version() {
+    assert_eq!(
+        Some(RouterOsVersion::V6),
+        RouterOsVersion::parse("6.49.10 (long-term)")
+    );
+    assert_eq!(
+        Some(RouterOsVersion::V7),
+        RouterOsVersion::parse("7.15.3 (stable)")
+    );
+    assert_eq!(Some(RouterOsVersion::V7), RouterOsVersion::parse("8.0"));
+    assert_eq!(None, RouterOsVersion::parse("unknown"));
+
+    let v6 = RouterOsVersion::V6;
+    assert_eq!(
+        Some("vxlan"),
+        v6.unsupported_feature("interface/vxlan/vteps")
+    );
+    assert_eq!(
+        Some("ospf"),
+        v6.unsupported_feature("/routing/ospf/interface-template")
     );
+    assert_eq!(None, v6.unsupported_feature("interface/vlan"));
+    assert_eq!(None, v6.unsupported_feature("ip/vrfs"));
+    assert_eq!(
+        None,
+        RouterOsVersion::V7.unsupported_feature("interface/vxlan")
+    );
+}
+
+#[tokio::test]
+async fn test_routeros_6_rejected_before_fetch() {
+    let mock = MockDevice::start().await.expect("cannot start mock");
+    mock.set_resource(
+        "system/resource",
+        [[("version", "6.49.10 (long-term)")].as_slice()],
+    )
+    .await;
+    mock.set_resource(
+        "system/routerboard",
+        [[
+            ("routerboard", "true"),
+            ("board-name", "hEX"),
+            ("model", "RB750Gr3"),
+            ("serial-number", "HCT0000006"),
+            ("firmware-type", "mt7621L"),
+            ("factory-firmware", "6.44.6"),
+            ("current-firmware", "6.49.10"),
+            ("upgrade-firmware", "6.49.10"),
+        ]
+        .as_slice()],
+    )
+    .await;
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: router
+    ports:
+      - !Interface 1
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    external: !Ethernet 1
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let client = mock.connect().await.expect("cannot connect to mock");
+    let device = AccessibleDevice::from_client(device, "192.0.2.63".parse().unwrap(), client);
+    // no feature is missing, the device is rejected by its version
+    assert!(matches!(
+        device.fetch_config(device.client()).await,
+        Err(Error::SetupError(SetupError::UnsupportedVersion(
+            RouterOsVersion::V6
+        )))
+    ));
+}
+
+#[test]
+fn test_management_path_cuts() {
+    let net = "10.0.0.2/24".parse().unwrap();
+    let address = |interface: &str| {
+        IpAddressByAddress(IpAddressCfg {
+            address: net,
+            interface: interface.into(),
+            comment: None,
+        })
+    };
+    let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+    let mut current = empty_current(&target);
+    current.ipv_4_address = Box::new([address("ether1")]);
+    target.ipv_4_address.insert(net, address("ether1"));
+    let management_ip = "10.0.0.2".parse().unwrap();
+    assert!(management_path_cuts(&target, &current, management_ip, true).is_empty());
+    // an address unknown on the device is not checked
+    target.ipv_4_address.insert(net, address("switch"));
+    assert!(management_path_cuts(&target, &current, "10.0.0.3".parse().unwrap(), true).is_empty());
+
+    target
+        .bridge_port
+        .entry(("switch".into(), "ether1".into()))
+        .or_default();
+    let paths = management_path_cuts(&target, &current, management_ip, false)
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["ip/address", "interface/bridge/port"], paths);
+}
+
+#[test]
+fn test_interface_name_collision() {
+    let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+    assert!(target.check_interface_names().is_ok());
+    target
+        .ethernet
+        .get_mut(&AsciiString::from("ether2"))
+        .expect("port not found")
+        .name = "switch-10".into();
+    target
+        .if_of_mapped_plane(
+            &MappedPlane::Tagged {
+                if_name: "switch".into(),
+                vid: 10,
+            },
+            &NamingPolicy::new(64).expect("invalid policy"),
+        )
+        .expect("vlan not mapped");
+    match target.check_interface_names() {
+        Err(SetupError::NameCollision {
+            kind,
+            name,
+            first,
+            second,
+        }) => {
+            assert_eq!("interface", kind);
+            assert_eq!(AsciiString::from("switch-10"), name);
+            assert_eq!("ethernet ether2", first.as_ref());
+            assert_eq!("vlan 10 on switch", second.as_ref());
+        }
+        result => panic!("unexpected result {result:?}"),
+    }
 }
+
 #[test]
-pub fn test_gap_overlapping_ranges() {
-    let mut finder = GapFinder::<Ipv4Addr>::new();
-    finder.reserve_ipv4("172.16.1.1".parse().unwrap());
-    finder.reserve_ipv4("172.16.1.15".parse().unwrap());
-    finder.reserve_ipv4_range("172.16.1.10".parse().unwrap().."172.16.1.20".parse().unwrap());
-
-    let gaps = finder
-        .find_gaps_ipv4("172.16.1.1/24".parse().unwrap())
+fn test_vlan_name_collision() {
+    let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+    let policy = NamingPolicy::new(11).expect("invalid policy");
+    let plane = |if_name: &str| MappedPlane::Tagged {
+        if_name: if_name.into(),
+        vid: 10,
+    };
+    let first = target
+        .if_of_mapped_plane(&plane("uplink-to-core-a"), &policy)
+        .expect("vlan not mapped");
+    assert_eq!(AsciiString::from("uplink-t-10"), first);
+    // the same vlan on the same interface is shared
+    assert_eq!(
+        first,
+        target
+            .if_of_mapped_plane(&plane("uplink-to-core-a"), &policy)
+            .expect("vlan not mapped")
+    );
+    // the shortened name of another interface must not take over the vlan
+    match target.if_of_mapped_plane(&plane("uplink-to-core-b"), &policy) {
+        Err(SetupError::NameCollision {
+            kind,
+            name,
+            first,
+            second,
+        }) => {
+            assert_eq!("vlan", kind);
+            assert_eq!(AsciiString::from("uplink-t-10"), name);
+            assert_eq!("vlan 10 on uplink-to-core-a", first.as_ref());
+            assert_eq!("vlan 10 on uplink-to-core-b", second.as_ref());
+        }
+        result => panic!("unexpected result {result:?}"),
+    }
+    assert_eq!(1, target.vlan.len());
+}
+
+#[tokio::test]
+async fn test_tagged_port_on_single_switch() {
+    let topology = parse_topology(
+        include_bytes!("../../topology/fixtures/single-router.yaml"),
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid fixture");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let root_port = device
+        .interfaces()
+        .into_iter()
+        .next()
+        .expect("interface not found");
+    let plane = |port: L2Port| L2Plane {
+        ports: vec![
+            L2Port::UntaggedEthernet {
+                name: "ether2".into(),
+                port: PhysicalPortId::Ethernet(2),
+            },
+            port,
+        ],
+        vlan: None,
+        vlan_id: 1,
+        root_port: root_port.clone(),
+    };
+    let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+    let mut mapped_planes = Vec::new();
+    assert!(
+        target
+            .setup_single_switch_without_vlan(
+                &plane(L2Port::UntaggedEoip {
+                    name: "tunnel".into()
+                }),
+                &mut mapped_planes,
+            )
+            .is_ok()
+    );
+    for (port, name) in [
+        (
+            L2Port::TaggedEthernet {
+                name: "ether3".into(),
+                port: PhysicalPortId::Ethernet(3),
+            },
+            "ether3",
+        ),
+        (
+            L2Port::TaggedEoip {
+                name: "tunnel".into(),
+            },
+            "tunnel",
+        ),
+        (
+            L2Port::VxLan {
+                name: "vxlan-10".into(),
+            },
+            "vxlan-10",
+        ),
+        (L2Port::Caps, "caps"),
+    ] {
+        match target.setup_single_switch_without_vlan(&plane(port), &mut mapped_planes) {
+            Err(SetupError::TaggedPortWithoutVlan { device, port }) => {
+                assert_eq!("router", device.as_ref());
+                assert_eq!(name, port.as_ref());
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_unsupported_bridge_port() {
+    let topology = parse_topology(
+        include_bytes!("../../topology/fixtures/single-router.yaml"),
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid fixture");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let root_port = device
+        .interfaces()
+        .into_iter()
+        .next()
+        .expect("interface not found");
+    for (port, name) in [
+        (
+            L2Port::VxLan {
+                name: "vxlan-10".into(),
+            },
+            "vxlan-10",
+        ),
+        (L2Port::Caps, "caps"),
+    ] {
+        let setup = L2Setup {
+            planes: vec![L2Plane {
+                ports: vec![
+                    L2Port::TaggedEthernet {
+                        name: "ether2".into(),
+                        port: PhysicalPortId::Ethernet(2),
+                    },
+                    port,
+                ],
+                vlan: None,
+                vlan_id: 10,
+                root_port: root_port.clone(),
+            }],
+        };
+        let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+        let mut mapped_planes = Vec::new();
+        match target.setup_l2(&setup, SwitchVlanConcept::OneBridge, &mut mapped_planes) {
+            Err(SetupError::UnsupportedBridgePort { device, port }) => {
+                assert_eq!("router", device.as_ref());
+                assert_eq!(name, port.as_ref());
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_generation_warnings() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: router
+    ports:
+      - !Interface 1
+      - !Interface 2
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    external: !Ethernet 1
+    port_type: Ethernet
+  2:
+    name: uplink
+    device: 1
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+    let mut warnings = Warnings::default();
+    target
+        .generate_from(
+            &device,
+            &GenerationProfile::default(),
+            None,
+            &HashMap::new(),
+            &HashMap::new(),
+            &HashMap::new(),
+            Path::new(""),
+            &HashMap::new(),
+            &mut warnings,
+        )
+        .expect("generation failed");
+    let unknown_types = warnings
+        .as_slice()
+        .iter()
+        .filter(|warning| warning.kind() == WarningKind::UnknownInterfaceType)
+        .map(|warning| warning.message())
         .collect::<Vec<_>>();
     assert_eq!(
-        gaps,
+        vec!["Interface uplink has an unknown type in netbox"],
+        unknown_types
+    );
+}
+
+#[tokio::test]
+async fn test_upnp_client_networks() {
+    let (target, _) = generate_device(
+        br#"
+devices:
+  1:
+    name: hex-home
+    upnp: true
+    igmp_proxy: true
+    ports:
+      - !Interface 1
+      - !Interface 2
+      - !Interface 3
+      - !Interface 4
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    external: !Ethernet 1
+    port_type: Ethernet
+    ips: [1]
+    wan: true
+  2:
+    name: ether2
+    device: 1
+    external: !Ethernet 2
+    port_type: Ethernet
+    ips: [2]
+  3:
+    name: ether3
+    device: 1
+    external: !Ethernet 3
+    port_type: Ethernet
+    ips: [3]
+    management: true
+  4:
+    name: ether4
+    device: 1
+    external: !Ethernet 4
+    port_type: Ethernet
+    ips: [4]
+    use_ospf: true
+ip_addresses:
+  1:
+    ip: 198.51.100.2/30
+    interface: 1
+  2:
+    ip: 192.168.88.1/24
+    interface: 2
+  3:
+    ip: 10.0.0.1/24
+    interface: 3
+  4:
+    ip: 10.255.0.1/30
+    interface: 4
+"#,
+        &GenerationProfile::default(),
+    )
+    .await;
+    assert!(target.upnp.enabled);
+    // the management and ospf transit networks cannot open ports
+    assert_eq!(
         vec![
-            "172.16.1.2".parse().unwrap().."172.16.1.9".parse().unwrap(),
-            "172.16.1.21".parse().unwrap().."172.16.1.254".parse().unwrap()
-        ]
+            ("ether1".to_string(), IpUpnpInterfacesType::External),
+            ("ether2".to_string(), IpUpnpInterfacesType::Internal),
+        ],
+        target
+            .upnp_interface
+            .iter()
+            .map(|((name,), interface)| (name.to_string(), interface.r#type.clone()))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![("ether1".to_string(), true), ("ether2".to_string(), false)],
+        target
+            .igmp_proxy_interface
+            .iter()
+            .map(|(name, interface)| (name.to_string(), interface.0.upstream))
+            .collect::<Vec<_>>()
     );
 }
-#[test]
-pub fn test_gap_subnet() {
-    let mut finder = GapFinder::<Ipv4Addr>::new();
-    finder.reserve_ipv4("172.16.1.1".parse().unwrap());
-    finder.reserve_ipv4("172.16.1.15".parse().unwrap());
-    finder.reserve_ipv4_range("172.16.1.10".parse().unwrap().."172.16.1.20".parse().unwrap());
-    finder.reserve_ipv4_net(Ipv4Net::from_str("172.16.3.0/24").unwrap());
-
-    let gaps = finder
-        .find_gaps_ipv4("172.16.0.0/12".parse().unwrap())
+
+#[tokio::test]
+async fn test_upnp_on_security_sensitive_role() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: core
+    role: core-router
+    upnp: true
+    ports:
+      - !Interface 1
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    external: !Ethernet 1
+    port_type: Ethernet
+    wan: true
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let mut target = BaseDeviceDataTarget::new(b"RB750Gr3").expect("model not found");
+    let mut warnings = Warnings::default();
+    let profile = GenerationProfile {
+        security_sensitive: true,
+        ..GenerationProfile::default()
+    };
+    target
+        .generate_from(
+            &device,
+            &profile,
+            None,
+            &HashMap::new(),
+            &HashMap::new(),
+            &HashMap::new(),
+            Path::new(""),
+            &HashMap::new(),
+            &mut warnings,
+        )
+        .expect("generation failed");
+    assert!(!target.upnp.enabled);
+    assert!(target.upnp_interface.is_empty());
+    let skipped = warnings
+        .as_slice()
+        .iter()
+        .filter(|warning| warning.kind() == WarningKind::SkippedResource)
+        .map(|warning| warning.message())
         .collect::<Vec<_>>();
     assert_eq!(
-        gaps,
         vec![
-            "172.16.0.1".parse().unwrap().."172.16.1.0".parse().unwrap(),
-            "172.16.1.2".parse().unwrap().."172.16.1.9".parse().unwrap(),
-            "172.16.1.21".parse().unwrap().."172.16.2.255".parse().unwrap(),
-            "172.16.4.0".parse().unwrap().."172.31.255.254".parse().unwrap()
-        ]
+            "Upnp and igmp proxy are not allowed on core, its role core-router is security sensitive"
+        ],
+        skipped
+    );
+}
+
+#[tokio::test]
+async fn test_capsman_security() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: controller
+    wlan_controller_of: 1
+wlan_groups:
+  1:
+    controller: 1
+    aps: []
+    wlans: [1, 2]
+vlan_groups:
+  1:
+    vlans: [1, 2]
+vlans:
+  1:
+    name: office
+    vlan_id: 10
+    group: 1
+    terminations: []
+    wlans: [1]
+  2:
+    name: staff
+    vlan_id: 20
+    group: 1
+    terminations: []
+    wlans: [2]
+wlans:
+  1:
+    ssid: Office
+    vlan: 1
+    wlan_auth: !Wpa { key: office-psk }
+    wlan_group: 1
+  2:
+    ssid: Staff
+    vlan: 2
+    wlan_auth: !WpaEnterprise { cipher: aes }
+    wlan_group: 1
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let radius: RadiusConfig =
+        serde_yaml::from_str("address: 10.0.0.5\nsecret_name: radius\naccounting: true")
+            .expect("invalid radius config");
+    let secrets_dir = std::env::temp_dir().join("provisioner-capsman-secrets");
+    std::fs::create_dir_all(&secrets_dir).expect("cannot create secrets dir");
+    std::fs::write(secrets_dir.join("radius"), "radius-secret\n").expect("cannot write secret");
+
+    let mut target = CapsmanDeviceDataTarget::new(b"");
+    let mut warnings = Warnings::default();
+    target.generate_from(&device, Some(&radius), &secrets_dir, &mut warnings);
+    assert!(warnings.as_slice().is_empty());
+
+    let office = &target.security[&AsciiString::from("wlan-office")].0;
+    assert_eq!(Some(AsciiString::from("office-psk")), office.passphrase);
+    let staff = &target.security[&AsciiString::from("wlan-staff")].0;
+    assert_eq!(
+        vec![CapsManSecurityAuthenticationTypes::Wpa2Eap],
+        staff
+            .authentication_types
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![CapsManSecurityEncryption::AesCcm],
+        staff.encryption.iter().cloned().collect::<Vec<_>>()
+    );
+    assert_eq!(Some(true), staff.eap_radius_accounting);
+    let client = &target.radius[&(radius.address,)];
+    assert_eq!(AsciiString::from("radius-secret"), client.secret);
+    assert_eq!(
+        vec![RadiusService::Wireless],
+        client.service.iter().cloned().collect::<Vec<_>>()
+    );
+    assert_eq!(SECRET_MASK, mask_secrets("radius-secret"));
+
+    // without the secret the enterprise wlan is skipped, the others are still generated
+    let radius: RadiusConfig =
+        serde_yaml::from_str("address: 10.0.0.5\nsecret_name: missing").expect("invalid config");
+    let mut target = CapsmanDeviceDataTarget::new(b"");
+    let mut warnings = Warnings::default();
+    target.generate_from(&device, Some(&radius), &secrets_dir, &mut warnings);
+    assert_eq!(
+        vec![WarningKind::SkippedResource],
+        warnings
+            .as_slice()
+            .iter()
+            .map(GenerationWarning::kind)
+            .collect::<Vec<_>>()
+    );
+    assert!(target.radius.is_empty());
+    assert!(
+        target
+            .security
+            .contains_key(&AsciiString::from("wlan-office"))
+    );
+    assert!(
+        !target
+            .security
+            .contains_key(&AsciiString::from("wlan-staff"))
     );
 }
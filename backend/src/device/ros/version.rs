@@ -0,0 +1,82 @@
+use crate::{
+    Error,
+    device::{command::run_command, ros::SetupError},
+};
+use async_graphql::Enum;
+use mikrotik_model::{MikrotikDevice, resource::ResourceMutation};
+use std::fmt::{Display, Formatter};
+
+/// Resources only RouterOS 7 provides, with the feature named in the error
+const V7_ONLY: [(&str, &str); 4] = [
+    ("interface/vxlan", "vxlan"),
+    ("routing/ospf", "ospf"),
+    ("ip/vrf", "vrf"),
+    ("interface/wifi", "wifi"),
+];
+
+/// Major RouterOS release, the generation targets RouterOS 7 only. Devices running RouterOS 6
+/// are detected and rejected before their configuration is read.
+#[derive(Enum, Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RouterOsVersion {
+    /// long-term release still on older devices, ospf has a different syntax and vxlan, vrf
+    /// and wifi are missing
+    V6,
+    #[default]
+    V7,
+}
+
+impl RouterOsVersion {
+    /// major version of a version like `7.15.3 (stable)`, newer releases are handled as 7
+    pub fn parse(version: &str) -> Option<Self> {
+        match version.trim().split('.').next()?.parse::<u32>().ok()? {
+            6 => Some(RouterOsVersion::V6),
+            7.. => Some(RouterOsVersion::V7),
+            _ => None,
+        }
+    }
+    /// reads the version of the running RouterOS, unknown versions are taken as 7
+    pub async fn detect(client: &MikrotikDevice) -> Result<Self, Error> {
+        let replies = run_command(client, "/system/resource/print", &[]).await?;
+        Ok(replies
+            .iter()
+            .find_map(|reply| reply.get("version"))
+            .and_then(|version| Self::parse(version))
+            .unwrap_or_default())
+    }
+    /// feature of the resource path missing in this version
+    pub fn unsupported_feature(self, path: &str) -> Option<&'static str> {
+        if self == RouterOsVersion::V7 {
+            return None;
+        }
+        let path = path.trim_start_matches('/');
+        V7_ONLY
+            .iter()
+            .find(|(prefix, _)| {
+                path.strip_prefix(prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .map(|(_, feature)| *feature)
+    }
+    /// fails on the first mutation of a resource this version does not provide
+    pub fn check(self, mutations: &[ResourceMutation]) -> Result<(), SetupError> {
+        for mutation in mutations {
+            if let Some(feature) = self.unsupported_feature(mutation.resource) {
+                return Err(SetupError::UnsupportedFeature {
+                    feature,
+                    path: mutation.resource.into(),
+                    version: self,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for RouterOsVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RouterOsVersion::V6 => "6",
+            RouterOsVersion::V7 => "7",
+        })
+    }
+}
@@ -0,0 +1,49 @@
+use async_graphql::{Enum, SimpleObject};
+use log::warn;
+
+/// Kind of data problem the generation worked around
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WarningKind {
+    /// the type of the interface in netbox is not known, it is only generated by its name
+    UnknownInterfaceType,
+    /// poe is enabled in netbox on a port without poe output
+    UnsupportedPoe,
+    /// a resource is left out because of missing data or configuration
+    SkippedResource,
+    /// a generated name differs from the name taken from netbox
+    FallbackName,
+}
+
+/// Problem in the netbox data or the configuration found while generating the configuration of
+/// a device, the generation goes on without the affected part
+#[derive(Debug, Clone, PartialEq, Eq, SimpleObject)]
+pub struct GenerationWarning {
+    kind: WarningKind,
+    message: Box<str>,
+}
+
+impl GenerationWarning {
+    pub fn kind(&self) -> WarningKind {
+        self.kind
+    }
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Warnings collected during the generation of one device
+#[derive(Debug, Clone, Default)]
+pub struct Warnings(Vec<GenerationWarning>);
+
+impl Warnings {
+    /// records the warning, it is logged as well since not every generation has a caller
+    /// reading the warnings
+    pub fn push(&mut self, kind: WarningKind, message: impl Into<Box<str>>) {
+        let message = message.into();
+        warn!("{message}");
+        self.0.push(GenerationWarning { kind, message });
+    }
+    pub fn as_slice(&self) -> &[GenerationWarning] {
+        &self.0
+    }
+}
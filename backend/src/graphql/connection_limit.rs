@@ -0,0 +1,97 @@
+use crate::topology::DeviceId;
+use async_graphql::{
+    Context, Request, ServerResult,
+    async_trait::async_trait,
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, Weak},
+};
+use tokio::sync::{Mutex as AsyncMutex, OwnedSemaphorePermit, Semaphore};
+
+/// Device connections a single request may keep open at once
+///
+/// A list of devices with nested device fields, e.g. `allDevices { access { deviceStats } }`,
+/// resolves the devices concurrently. Each connection holds a permit until it is closed, the
+/// other devices wait for a free one instead of opening a socket each. The connections to the
+/// same device share one permit, a device queried twice in a request, e.g. by aliases, does not
+/// wait for a second one while holding the first.
+#[derive(Clone)]
+pub struct ConnectionLimit {
+    connections: Arc<Semaphore>,
+    devices: Arc<Mutex<HashMap<(Box<str>, DeviceId), DeviceSlot>>>,
+}
+
+/// permit of a device while any connection to it is open, locked while the first connection
+/// waits for it
+type DeviceSlot = Arc<AsyncMutex<Weak<OwnedSemaphorePermit>>>;
+
+impl ConnectionLimit {
+    pub fn new(max: usize) -> Self {
+        Self {
+            connections: Arc::new(Semaphore::new(max)),
+            devices: Arc::default(),
+        }
+    }
+    /// limit of the request, none for schemas without the extension
+    pub fn of(ctx: &Context<'_>) -> Option<Self> {
+        ctx.data_opt::<ConnectionLimit>().cloned()
+    }
+    /// connections still free
+    pub fn available(&self) -> usize {
+        self.connections.available_permits()
+    }
+    /// waits for a free connection, the permit is given back when dropped. Without a limit no
+    /// permit is needed.
+    pub async fn acquire(limit: Option<&Self>) -> Option<OwnedSemaphorePermit> {
+        limit?.connections.clone().acquire_owned().await.ok()
+    }
+    /// the permit of the device of the netbox instance, shared with the open connections to the
+    /// same device. It is given back when the last of them is dropped.
+    pub async fn acquire_device(
+        limit: Option<&Self>,
+        instance: &str,
+        device: DeviceId,
+    ) -> Option<Arc<OwnedSemaphorePermit>> {
+        let limit = limit?;
+        let slot = limit
+            .devices
+            .lock()
+            .expect("device slots poisoned")
+            .entry((instance.into(), device))
+            .or_default()
+            .clone();
+        let mut slot = slot.lock().await;
+        if let Some(permit) = slot.upgrade() {
+            return Some(permit);
+        }
+        let permit = Arc::new(Self::acquire(Some(limit)).await?);
+        *slot = Arc::downgrade(&permit);
+        Some(permit)
+    }
+}
+
+/// Schema extension giving each request its own [`ConnectionLimit`]
+pub struct DeviceConnections(pub usize);
+
+impl ExtensionFactory for DeviceConnections {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(DeviceConnectionsExtension(self.0))
+    }
+}
+
+struct DeviceConnectionsExtension(usize);
+
+#[async_trait]
+impl Extension for DeviceConnectionsExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        next.run(ctx, request.data(ConnectionLimit::new(self.0)))
+            .await
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{Error, device::reachability::PingError, device::ros::SetupError, netbox::NetboxError};
+use async_graphql::{
+    Response, ServerError,
+    async_trait::async_trait,
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextRequest},
+};
+use std::{net::AddrParseError, sync::Arc};
+
+/// Machine readable error classification, sent as `code` in the error extensions
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ErrorCode {
+    DeviceUnreachable,
+    DeviceError,
+    MissingCredentials,
+    MissingManagementAddress,
+    NetboxError,
+    ValidationFailed,
+    CommandFailed,
+    NotFound,
+    Forbidden,
+    PersistedQueryNotFound,
+    RateLimited,
+    DeviceBusy,
+    Timeout,
+    InternalError,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::DeviceUnreachable => "DEVICE_UNREACHABLE",
+            ErrorCode::DeviceError => "DEVICE_ERROR",
+            ErrorCode::MissingCredentials => "MISSING_CREDENTIALS",
+            ErrorCode::MissingManagementAddress => "MISSING_MANAGEMENT_ADDRESS",
+            ErrorCode::NetboxError => "NETBOX_ERROR",
+            ErrorCode::ValidationFailed => "VALIDATION_FAILED",
+            ErrorCode::CommandFailed => "COMMAND_FAILED",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::Forbidden => "FORBIDDEN",
+            ErrorCode::PersistedQueryNotFound => "PERSISTED_QUERY_NOT_FOUND",
+            ErrorCode::RateLimited => "RATE_LIMITED",
+            ErrorCode::DeviceBusy => "DEVICE_BUSY",
+            ErrorCode::Timeout => "TIMEOUT",
+            ErrorCode::InternalError => "INTERNAL_ERROR",
+        }
+    }
+    fn of_server_error(error: &ServerError) -> Option<ErrorCode> {
+        if let Some(error) = error.source::<Error>() {
+            Some(error.into())
+        } else if let Some(error) = error.source::<SetupError>() {
+            Some(error.into())
+        } else if error.source::<NetboxError>().is_some() {
+            Some(ErrorCode::NetboxError)
+        } else if error.source::<PingError>().is_some() {
+            Some(ErrorCode::DeviceUnreachable)
+        } else if error.source::<AddrParseError>().is_some() {
+            Some(ErrorCode::ValidationFailed)
+        } else {
+            None
+        }
+    }
+    /// attaches the code to a server error
+    pub fn apply(&self, error: &mut ServerError) {
+        error
+            .extensions
+            .get_or_insert_with(Default::default)
+            .set("code", self.as_str());
+    }
+}
+
+impl From<&Error> for ErrorCode {
+    fn from(value: &Error) -> Self {
+        match value {
+            Error::MikrotikApi(_) | Error::Ping(_) => ErrorCode::DeviceUnreachable,
+            Error::MikrotikModel(_) => ErrorCode::DeviceError,
+            Error::MissingCredentials => ErrorCode::MissingCredentials,
+            Error::MissingManagementAddress => ErrorCode::MissingManagementAddress,
+            Error::UnknownDevice(_)
+            | Error::UnknownPrefix(_)
+            | Error::UnknownInstance(_)
+            | Error::UnknownRollout(_) => ErrorCode::NotFound,
+            Error::RateLimited => ErrorCode::RateLimited,
+            Error::DeviceBusy(_) => ErrorCode::DeviceBusy,
+            Error::DeviceStatus(_) | Error::CertificatesNotConfigured => {
+                ErrorCode::ValidationFailed
+            }
+            Error::AddressParse(_)
+            | Error::UnknownAddress(_)
+            | Error::NotIpv4Prefix(_)
+            | Error::ResourceMutation(_)
+            | Error::MissingDependenciesError(_) => ErrorCode::ValidationFailed,
+            Error::SetupError(error) => error.into(),
+            Error::CommandFailed { .. } => ErrorCode::CommandFailed,
+            Error::Netbox(_) => ErrorCode::NetboxError,
+            Error::NoFreeLoopback(_) | Error::InvalidPortMirror(_) => ErrorCode::ValidationFailed,
+            Error::BackingOff { .. } => ErrorCode::DeviceUnreachable,
+            Error::ErrorGeneratingString(_)
+            | Error::CertificateFile { .. }
+            | Error::CertificateSigning(_) => ErrorCode::InternalError,
+        }
+    }
+}
+impl From<&SetupError> for ErrorCode {
+    fn from(value: &SetupError) -> Self {
+        match value {
+            SetupError::Access(_) => ErrorCode::DeviceError,
+            _ => ErrorCode::ValidationFailed,
+        }
+    }
+}
+
+/// Schema extension adding the error code to all errors with a known source
+pub struct ErrorCodes;
+
+impl ExtensionFactory for ErrorCodes {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(ErrorCodesExtension)
+    }
+}
+
+struct ErrorCodesExtension;
+
+#[async_trait]
+impl Extension for ErrorCodesExtension {
+    async fn request(&self, ctx: &ExtensionContext<'_>, next: NextRequest<'_>) -> Response {
+        let mut response = next.run(ctx).await;
+        for error in &mut response.errors {
+            if let Some(code) = ErrorCode::of_server_error(error) {
+                code.apply(error);
+            }
+        }
+        response
+    }
+}
@@ -1,53 +1,467 @@
-use crate::{config::CONFIG, topology::TopologyHolder};
-use async_graphql::{EmptyMutation, EmptySubscription, Object, Schema, SimpleObject};
+use crate::{
+    Error,
+    config::{CONFIG, QueryLimits},
+    context::UserInfo,
+    device::{
+        availability::AVAILABILITY,
+        backoff::BACKOFF,
+        bandwidth_test::{BandwidthTestProtocol, BandwidthTestResult, run_bandwidth_test},
+        certificate::CertificateProvisioning,
+        mirror::{PortMirror, disable_port_mirror, enable_port_mirror},
+    },
+    notify::NOTIFIER,
+    policy::TenantScope,
+    provisioning::{
+        decommission::{DecommissionMode, DecommissionResult, decommission_device},
+        history::{ProvisioningRun, RUN_HISTORY, RunFilter, RunStatus},
+        journal::write_journal,
+        lock::lock_device,
+        loopback::{LoopbackAllocation, allocate_loopback},
+        plan::{ApplyResult, PlanOptions, ProvisionPlan},
+        rollout::{ROLLOUTS, RolloutEvent, RolloutOptions},
+        scope::ApplyScope,
+    },
+    rate_limit::{RequestRateLimit, check_device_access},
+    topology::{
+        DeviceId, TopologyHolder, TopologyInstances,
+        access::{AdhocCredentials, device::DeviceAccess},
+    },
+};
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Object, Schema, SimpleObject, Subscription,
+    futures_util::Stream,
+};
+use connection_limit::DeviceConnections;
+use errors::ErrorCodes;
+use status::PublicStatus;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use timeout::RequestTimeout;
+pub mod connection_limit;
+pub mod errors;
 pub mod scalars;
-pub type AuthenticatedGraphqlSchema = Schema<QueryAuthenticated, EmptyMutation, EmptySubscription>;
+pub mod status;
+#[cfg(test)]
+mod test;
+pub mod timeout;
+pub type AuthenticatedGraphqlSchema =
+    Schema<QueryAuthenticated, MutationAuthenticated, SubscriptionAuthenticated>;
 pub type AnonymousGraphqlSchema = Schema<QueryAnonymous, EmptyMutation, EmptySubscription>;
 
 pub struct QueryAuthenticated {
-    topology: TopologyHolder,
+    topology: TopologyInstances,
+}
+pub struct MutationAuthenticated {
+    topology: TopologyInstances,
+}
+pub struct SubscriptionAuthenticated;
+/// Read-only queries without login, limited to data safe to publish. The answers carry cache
+/// hints, sent as `Cache-Control` header on `GET` requests.
+pub struct QueryAnonymous {
+    topology: TopologyInstances,
 }
-pub struct QueryAnonymous;
 
-pub fn create_schema(topology: TopologyHolder) -> AuthenticatedGraphqlSchema {
+pub fn create_schema(
+    topology: TopologyInstances,
+    limits: QueryLimits,
+) -> AuthenticatedGraphqlSchema {
+    let builder = Schema::build(
+        QueryAuthenticated {
+            topology: topology.clone(),
+        },
+        MutationAuthenticated { topology },
+        SubscriptionAuthenticated,
+    )
+    .extension(ErrorCodes)
+    .extension(DeviceConnections(limits.max_device_connections))
+    .extension(RequestRateLimit)
+    .extension(RequestTimeout(limits.request_timeout))
+    .limit_depth(limits.max_depth)
+    .limit_complexity(limits.max_complexity);
+    // the sdl stays available by `AuthenticatedGraphqlSchema::sdl`
+    if limits.introspection {
+        builder.finish()
+    } else {
+        builder.disable_introspection().finish()
+    }
+}
+pub fn create_anonymous_schema(topology: TopologyInstances) -> AnonymousGraphqlSchema {
     Schema::build(
-        QueryAuthenticated { topology },
+        QueryAnonymous { topology },
         EmptyMutation,
         EmptySubscription,
     )
+    .extension(ErrorCodes)
     .finish()
 }
-pub fn create_anonymous_schema() -> AnonymousGraphqlSchema {
-    Schema::build(QueryAnonymous, EmptyMutation, EmptySubscription).finish()
-}
 
 #[Object]
 impl QueryAuthenticated {
-    async fn topology(&self) -> TopologyHolder {
-        self.topology.clone()
+    /// topology of a netbox instance, the default instance if none is given
+    async fn topology(&self, instance: Option<String>) -> Result<TopologyHolder, Error> {
+        instance_topology(&self.topology, instance.as_deref()).cloned()
+    }
+    /// names of the configured netbox instances, the default instance first
+    async fn netbox_instances(&self) -> Box<[&str]> {
+        self.topology.names().collect()
+    }
+    /// latest provisioning runs, the newest first, of the default instance if none is given
+    async fn provisioning_runs(
+        &self,
+        ctx: &Context<'_>,
+        instance: Option<String>,
+        device_id: Option<u32>,
+        status: Option<RunStatus>,
+    ) -> Vec<ProvisioningRun> {
+        let mut runs = RUN_HISTORY.list(&RunFilter {
+            instance: Some(self.topology.resolve_name(instance.as_deref())),
+            device: device_id.map(DeviceId),
+            status,
+        });
+        runs.retain(|run| TenantScope::of(ctx).allows_tenant(run.tenant()));
+        runs
+    }
+    async fn provisioning_run(&self, ctx: &Context<'_>, id: u32) -> Option<ProvisioningRun> {
+        RUN_HISTORY
+            .get(id)
+            .filter(|run| TenantScope::of(ctx).allows_tenant(run.tenant()))
+    }
+    async fn list_credentials(&self) -> Box<[Box<str>]> {
+        CONFIG.load().mikrotik_credentials.keys().cloned().collect()
+    }
+}
+fn instance_topology<'a>(
+    topology: &'a TopologyInstances,
+    instance: Option<&str>,
+) -> Result<&'a TopologyHolder, Error> {
+    topology
+        .get(instance)
+        .ok_or_else(|| Error::UnknownInstance(instance.unwrap_or_default().into()))
+}
+impl MutationAuthenticated {
+    /// a device of the tenants visible to the user, others are reported as unknown
+    async fn device(
+        &self,
+        ctx: &Context<'_>,
+        instance: Option<&str>,
+        id: u32,
+    ) -> Result<DeviceAccess, Error> {
+        let device = instance_topology(&self.topology, instance)?
+            .devices_by_id(DeviceId(id))
+            .await
+            .filter(|device| TenantScope::of(ctx).allows(device))
+            .ok_or(Error::UnknownDevice(id))?;
+        check_device_access(ctx, &device)?;
+        Ok(device)
+    }
+}
+#[Object]
+impl MutationAuthenticated {
+    /// runs a bandwidth test between two devices, the server side is enabled on demand
+    async fn bandwidth_test(
+        &self,
+        ctx: &Context<'_>,
+        server_device_id: u32,
+        client_device_id: u32,
+        duration_secs: Option<u32>,
+        protocol: Option<BandwidthTestProtocol>,
+        instance: Option<String>,
+    ) -> Result<BandwidthTestResult, Error> {
+        let instance = instance.as_deref();
+        let server = self
+            .device(ctx, instance, server_device_id)
+            .await?
+            .connect(None, None)
+            .await?;
+        let client = self
+            .device(ctx, instance, client_device_id)
+            .await?
+            .connect(None, None)
+            .await?;
+        run_bandwidth_test(
+            &server,
+            &client,
+            Duration::from_secs(duration_secs.unwrap_or(10) as u64),
+            protocol.unwrap_or_default(),
+        )
+        .await
+    }
+    /// Creates the plan of a device and applies it right away. The device is locked meanwhile,
+    /// a concurrent apply on the same device fails with `DEVICE_BUSY`. A plan cutting the
+    /// management path to the device is refused unless `force` is set. A device not yet on its
+    /// management address, e.g. while it is onboarded, is reached through `target` and the given
+    /// credentials, the result is still verified on the management address.
+    #[allow(clippy::too_many_arguments)]
+    async fn apply_plan(
+        &self,
+        ctx: &Context<'_>,
+        device_id: u32,
+        scope: Option<ApplyScope>,
+        prune: Option<bool>,
+        force: Option<bool>,
+        target: Option<String>,
+        credential_name: Option<Box<str>>,
+        adhoc_credentials: Option<AdhocCredentials>,
+        instance: Option<String>,
+    ) -> Result<ApplyResult, Error> {
+        let instance = instance.as_deref();
+        let target = target.map(|ip| ip.parse()).transpose()?;
+        let credentials = AdhocCredentials::select(credential_name, adhoc_credentials);
+        let instance_name = self.topology.resolve_name(instance);
+        let device = self.device(ctx, instance, device_id).await?;
+        let _lock = lock_device(instance_name, device.id())?;
+        if !device.status().allows_apply() {
+            return Err(Error::DeviceStatus(device.status()));
+        }
+        let options = PlanOptions {
+            force: force.unwrap_or(false),
+            ..PlanOptions::for_device(&device, scope, prune.unwrap_or(false))
+        };
+        let started_at = SystemTime::now();
+        let result = async {
+            let client = device.connect(target, credentials).await?;
+            let mut result = ProvisionPlan::create(&client, &options)
+                .await?
+                .apply(&client)
+                .await;
+            result.verify(&device, &options).await;
+            Ok(result)
+        }
+        .await;
+        let user = ctx.data_opt::<UserInfo>().map(|user| user.name.as_str());
+        RUN_HISTORY.record(instance_name, &device, user, started_at, &result);
+        if let Ok(result) = &result {
+            NOTIFIER.plan_applied(instance_name, &device, result).await;
+            let netbox = CONFIG
+                .load()
+                .netbox_journal
+                .then(|| self.topology.get(instance).and_then(|t| t.instance()))
+                .flatten();
+            if let Some(netbox) = netbox {
+                write_journal(&netbox, &device, user, result).await;
+            }
+        }
+        result
+    }
+    /// Applies the plans of multiple devices in the background, ordered by their dependencies.
+    /// The first devices are applied as canaries and verified to be reachable with nothing left
+    /// to apply, before the rollout continues with the others. The devices are given by id, by
+    /// a netbox tag or both. Returns the id of the rollout to follow it by `rolloutProgress`.
+    async fn start_rollout(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] device_ids: Vec<u32>,
+        tag: Option<String>,
+        options: Option<RolloutOptions>,
+        instance: Option<String>,
+    ) -> Result<u32, Error> {
+        let instance = instance.as_deref();
+        let mut device_ids = device_ids;
+        if let Some(tag) = &tag {
+            let tagged = instance_topology(&self.topology, instance)?
+                .tagged_devices(tag, TenantScope::of(ctx))
+                .await;
+            device_ids.extend(tagged.iter().map(|device| device.id().0));
+        }
+        device_ids.sort();
+        device_ids.dedup();
+        let mut devices = Vec::with_capacity(device_ids.len());
+        for id in device_ids {
+            let device = self.device(ctx, instance, id).await?;
+            if !device.status().allows_apply() {
+                return Err(Error::DeviceStatus(device.status()));
+            }
+            devices.push(device);
+        }
+        let user = ctx.data_opt::<UserInfo>().map(|user| user.name.as_str());
+        Ok(ROLLOUTS.start(
+            self.topology.resolve_name(instance),
+            &devices,
+            options.unwrap_or_default(),
+            user,
+        ))
+    }
+    /// Issues the managed certificates missing on a device or due for renewal, all of them with
+    /// `force`. Services using a replaced certificate are switched to its successor, the next
+    /// apply serves api-ssl and CAPsMAN with new certificates. The device is locked meanwhile.
+    async fn provision_certificates(
+        &self,
+        ctx: &Context<'_>,
+        device_id: u32,
+        force: Option<bool>,
+        instance: Option<String>,
+    ) -> Result<CertificateProvisioning, Error> {
+        let instance = instance.as_deref();
+        let settings = CONFIG.load();
+        let config = settings
+            .certificates
+            .as_ref()
+            .ok_or(Error::CertificatesNotConfigured)?;
+        let device = self.device(ctx, instance, device_id).await?;
+        let _lock = lock_device(self.topology.resolve_name(instance), device.id())?;
+        if !device.status().allows_apply() {
+            return Err(Error::DeviceStatus(device.status()));
+        }
+        device
+            .connect(None, None)
+            .await?
+            .provision_certificates(config, force.unwrap_or(false))
+            .await
+    }
+    /// Reverses the provisioning of a device in status `decommissioning`: removes it from the
+    /// vteps of its peers, disables or resets it and sets the netbox device offline. By default
+    /// all interfaces except the ones the device is managed over are disabled. The device is
+    /// locked meanwhile.
+    async fn decommission_device(
+        &self,
+        ctx: &Context<'_>,
+        device_id: u32,
+        mode: Option<DecommissionMode>,
+        instance: Option<String>,
+    ) -> Result<DecommissionResult, Error> {
+        let instance = instance.as_deref();
+        let instance_name = self.topology.resolve_name(instance);
+        let device = self.device(ctx, instance, device_id).await?;
+        let _lock = lock_device(instance_name, device.id())?;
+        let netbox = self.topology.get(instance).and_then(|t| t.instance());
+        let user = ctx.data_opt::<UserInfo>().map(|user| user.name.as_str());
+        decommission_device(
+            instance_name,
+            netbox.as_ref(),
+            &device,
+            mode.unwrap_or_default(),
+            user,
+        )
+        .await
     }
-    async fn list_credentials(&self) -> Box<[&str]> {
-        CONFIG
-            .mikrotik_credentials
-            .keys()
-            .map(Box::as_ref)
-            .collect()
+    /// Assigns the next free addresses of the prefixes with role `loopback` to the loopback
+    /// interface of a device in netbox, one per address family the interface has no address of
+    /// yet. The interface is created if missing and the topology is fetched again afterward.
+    async fn allocate_loopback(
+        &self,
+        ctx: &Context<'_>,
+        device_id: u32,
+        instance: Option<String>,
+    ) -> Result<LoopbackAllocation, Error> {
+        let instance = instance.as_deref();
+        let instance_name = self.topology.resolve_name(instance);
+        let device = self.device(ctx, instance, device_id).await?;
+        let _lock = lock_device(instance_name, device.id())?;
+        let topology = instance_topology(&self.topology, instance)?;
+        let netbox = topology
+            .instance()
+            .ok_or_else(|| Error::UnknownInstance(instance_name.into()))?;
+        allocate_loopback(topology, &netbox, device.id()).await
+    }
+    /// Copies the traffic of `sourcePort` to `destPort` on the switch chip of a device for
+    /// troubleshooting, both named as in netbox. The previous switch settings are restored after
+    /// `durationSecs`, at most four hours. The drift check skips the device meanwhile. Fails
+    /// while a plan is applied to the device.
+    async fn enable_port_mirror(
+        &self,
+        ctx: &Context<'_>,
+        device_id: u32,
+        source_port: String,
+        dest_port: String,
+        duration_secs: u32,
+        instance: Option<String>,
+    ) -> Result<PortMirror, Error> {
+        let instance = instance.as_deref();
+        let instance_name = self.topology.resolve_name(instance);
+        let device = self.device(ctx, instance, device_id).await?;
+        let _lock = lock_device(instance_name, device.id())?;
+        if !device.status().allows_apply() {
+            return Err(Error::DeviceStatus(device.status()));
+        }
+        enable_port_mirror(
+            instance_name,
+            &device,
+            &source_port,
+            &dest_port,
+            Duration::from_secs(duration_secs as u64),
+        )
+        .await
+    }
+    /// Reverts the port mirror of a device before its time, returns false if there was none
+    async fn disable_port_mirror(
+        &self,
+        ctx: &Context<'_>,
+        device_id: u32,
+        instance: Option<String>,
+    ) -> Result<bool, Error> {
+        let instance = instance.as_deref();
+        let instance_name = self.topology.resolve_name(instance);
+        let device = self.device(ctx, instance, device_id).await?;
+        let _lock = lock_device(instance_name, device.id())?;
+        disable_port_mirror(instance_name, &device).await
+    }
+    /// Forgets the connection failures of a device, fleet operations connect to it again right
+    /// away. Returns false if there were none.
+    async fn reset_connection_backoff(
+        &self,
+        ctx: &Context<'_>,
+        device_id: u32,
+        instance: Option<String>,
+    ) -> Result<bool, Error> {
+        let device = self.device(ctx, instance.as_deref(), device_id).await?;
+        let address = device
+            .management_ip()
+            .ok_or(Error::MissingManagementAddress)?;
+        Ok(BACKOFF.reset(address))
+    }
+}
+#[Subscription]
+impl SubscriptionAuthenticated {
+    /// progress of a rollout, the events so far first, ends after the last event. Only the user
+    /// who started the rollout and the users of all its devices may follow it.
+    async fn rollout_progress(
+        &self,
+        ctx: &Context<'_>,
+        id: u32,
+    ) -> Result<impl Stream<Item = RolloutEvent>, Error> {
+        let user = ctx.data_opt::<UserInfo>().map(|user| user.name.as_str());
+        ROLLOUTS
+            .subscribe(id, user, TenantScope::of(ctx).clone())
+            .ok_or(Error::UnknownRollout(id))
     }
 }
 #[Object]
 impl QueryAnonymous {
     /// gives the coordinates for authentication
+    #[graphql(cache_control(max_age = 3600))]
     async fn authentication(&self) -> AuthenticationData {
+        let settings = CONFIG.load();
         AuthenticationData {
-            client_id: CONFIG.auth_client_id(),
-            auth_url: CONFIG.auth_url(),
-            token_url: CONFIG.auth_token_url(),
+            client_id: settings.auth_client_id().into(),
+            auth_url: settings.auth_url(),
+            token_url: settings.auth_token_url(),
         }
     }
+    /// version of the running server
+    #[graphql(cache_control(max_age = 3600))]
+    async fn server_version(&self) -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+    /// health for a public status page, from the state already known without accessing
+    /// netbox or the devices
+    #[graphql(cache_control(max_age = 30))]
+    async fn status(&self) -> PublicStatus {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        PublicStatus::collect(
+            &self.topology,
+            &NOTIFIER,
+            &AVAILABILITY,
+            &CONFIG.load().maintenance_windows,
+            now,
+        )
+        .await
+    }
 }
 #[derive(SimpleObject)]
 struct AuthenticationData {
-    client_id: &'static str,
+    client_id: String,
     token_url: String,
     auth_url: String,
 }
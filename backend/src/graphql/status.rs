@@ -0,0 +1,141 @@
+use crate::{
+    config::MaintenanceWindow, device::availability::AvailabilityHistory, notify::Notifier,
+    topology::TopologyInstances,
+};
+use async_graphql::{Enum, SimpleObject};
+use std::time::UNIX_EPOCH;
+
+/// Overall state of the provisioner on the public status page
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ServiceState {
+    Operational,
+    /// devices are unreachable or netbox cannot be fetched
+    Degraded,
+    /// an announced maintenance is ongoing, failures are expected
+    Maintenance,
+}
+
+/// Health of the provisioner summed over all netbox instances, without names or addresses of
+/// the topology, safe to be shown without login
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+pub struct PublicStatus {
+    state: ServiceState,
+    /// devices in operation whose last ping succeeded
+    devices_up: usize,
+    /// devices in operation whose last ping failed
+    devices_down: usize,
+    /// devices differing from netbox
+    drifted_devices: usize,
+    /// devices reported as unreachable and not accessed since
+    unreachable_devices: usize,
+    /// netbox instances whose last fetch failed
+    failing_instances: usize,
+    /// seconds since the unix epoch of the oldest topology served, none before the first fetch
+    last_sync: Option<u64>,
+    maintenance: Vec<PublicMaintenance>,
+}
+
+/// Ongoing maintenance window
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+pub struct PublicMaintenance {
+    title: Box<str>,
+    /// seconds since the unix epoch
+    starts_at: u64,
+    /// seconds since the unix epoch
+    ends_at: u64,
+}
+
+impl PublicStatus {
+    /// status of the topologies already fetched at the given time in seconds since the unix
+    /// epoch, neither netbox nor the devices are accessed
+    pub async fn collect(
+        topology: &TopologyInstances,
+        notifier: &Notifier,
+        availability: &AvailabilityHistory,
+        maintenance_windows: &[MaintenanceWindow],
+        now: u64,
+    ) -> Self {
+        let mut devices_up = 0;
+        let mut devices_down = 0;
+        let mut drifted_devices = 0;
+        let mut unreachable_devices = 0;
+        let mut failing_instances = 0;
+        let mut last_sync: Option<u64> = None;
+        for (instance, holder) in topology.iter() {
+            drifted_devices += notifier.drifted_devices(instance);
+            unreachable_devices += notifier.unreachable_devices(instance);
+            if holder.fetch_failures() > 0 {
+                failing_instances += 1;
+            }
+            let Some(current) = holder.current().await else {
+                continue;
+            };
+            let fetched_at = current
+                .fetched_at()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            last_sync = Some(last_sync.map_or(fetched_at, |sync| sync.min(fetched_at)));
+            for device in current.list_devices() {
+                if !device.status().in_fleet() {
+                    continue;
+                }
+                let Some(address) = device.management_ip() else {
+                    continue;
+                };
+                // devices not pinged yet are neither up nor down
+                match availability.availability(address) {
+                    Some(device) if device.is_up() => devices_up += 1,
+                    Some(_) => devices_down += 1,
+                    None => {}
+                }
+            }
+        }
+        let maintenance = maintenance_windows
+            .iter()
+            .filter(|window| window.is_ongoing(now))
+            .filter_map(|window| {
+                Some(PublicMaintenance {
+                    title: window.title.clone(),
+                    starts_at: window.start()?,
+                    ends_at: window.end()?,
+                })
+            })
+            .collect::<Vec<_>>();
+        let state = if !maintenance.is_empty() {
+            ServiceState::Maintenance
+        } else if unreachable_devices > 0 || failing_instances > 0 {
+            ServiceState::Degraded
+        } else {
+            ServiceState::Operational
+        };
+        Self {
+            state,
+            devices_up,
+            devices_down,
+            drifted_devices,
+            unreachable_devices,
+            failing_instances,
+            last_sync,
+            maintenance,
+        }
+    }
+    pub fn state(&self) -> ServiceState {
+        self.state
+    }
+    pub fn devices_up(&self) -> usize {
+        self.devices_up
+    }
+    pub fn devices_down(&self) -> usize {
+        self.devices_down
+    }
+    pub fn unreachable_devices(&self) -> usize {
+        self.unreachable_devices
+    }
+    pub fn last_sync(&self) -> Option<u64> {
+        self.last_sync
+    }
+    pub fn maintenance(&self) -> &[PublicMaintenance] {
+        &self.maintenance
+    }
+}
@@ -0,0 +1,245 @@
+use crate::{
+    config::{DEFAULT_NETBOX_INSTANCE, MaintenanceWindow, QueryLimits},
+    device::availability::AvailabilityHistory,
+    graphql::{
+        connection_limit::{ConnectionLimit, DeviceConnections},
+        create_anonymous_schema, create_schema,
+        status::{PublicStatus, ServiceState},
+        timeout::RequestTimeout,
+    },
+    notify::Notifier,
+    rate_limit::{RequestRateLimit, check_device_access},
+    topology::{
+        DeviceId, TopologyHolder, TopologyInstances,
+        access::device::DeviceAccess,
+        snapshot::{FixtureFormat, parse_topology},
+    },
+};
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Schema, value};
+use std::time::Duration;
+use tokio::time::sleep;
+
+struct Query;
+
+#[Object]
+impl Query {
+    async fn fast(&self) -> u32 {
+        1
+    }
+    async fn slow(&self) -> Option<u32> {
+        sleep(Duration::from_secs(10)).await;
+        Some(2)
+    }
+    /// free connections of the request after taking one
+    async fn free_connections(&self, ctx: &Context<'_>) -> Option<usize> {
+        let limit = ConnectionLimit::of(ctx)?;
+        let _permit = ConnectionLimit::acquire(Some(&limit)).await?;
+        Some(limit.available())
+    }
+    /// free connections of the request while holding the permit of the device for a while
+    async fn device_connections(&self, ctx: &Context<'_>, device: u32) -> Option<usize> {
+        let limit = ConnectionLimit::of(ctx)?;
+        let _permit = ConnectionLimit::acquire_device(
+            Some(&limit),
+            DEFAULT_NETBOX_INSTANCE,
+            DeviceId(device),
+        )
+        .await?;
+        sleep(Duration::from_millis(20)).await;
+        Some(limit.available())
+    }
+}
+
+#[tokio::test]
+async fn test_request_timeout() {
+    let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+        .extension(RequestTimeout(Duration::from_millis(50)))
+        .finish();
+    let response = schema.execute("{ fast slow }").await;
+    assert_eq!(value!({"fast": 1, "slow": null}), response.data);
+    assert_eq!(1, response.errors.len());
+    let code = response.errors[0]
+        .extensions
+        .as_ref()
+        .and_then(|extensions| extensions.get("code"))
+        .cloned();
+    assert_eq!(Some(value!("TIMEOUT")), code);
+}
+
+#[tokio::test]
+async fn test_connection_limit() {
+    let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+        .extension(DeviceConnections(3))
+        .finish();
+    let response = schema.execute("{ freeConnections }").await;
+    assert_eq!(value!({"freeConnections": 2}), response.data);
+    // without the extension the connections are not limited
+    let schema = Schema::build(Query, EmptyMutation, EmptySubscription).finish();
+    let response = schema.execute("{ freeConnections }").await;
+    assert_eq!(value!({"freeConnections": null}), response.data);
+}
+
+#[tokio::test]
+async fn test_connection_limit_per_device() {
+    let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+        .extension(DeviceConnections(1))
+        .finish();
+    // the aliases of a device share its permit instead of waiting for a second one
+    let response = tokio::time::timeout(
+        Duration::from_secs(5),
+        schema.execute(
+            "{ a: deviceConnections(device: 1) b: deviceConnections(device: 1) \
+             c: deviceConnections(device: 2) d: deviceConnections(device: 2) }",
+        ),
+    )
+    .await
+    .expect("aliases of a device wait for each other");
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(value!({"a": 0, "b": 0, "c": 0, "d": 0}), response.data);
+}
+
+#[tokio::test]
+async fn test_introspection_disabled() {
+    const QUERY: &str = "{ __schema { queryType { name } } }";
+    let schema = create_schema(TopologyInstances::default(), QueryLimits::default());
+    let response = schema.execute(QUERY).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        value!({"__schema": {"queryType": {"name": "QueryAuthenticated"}}}),
+        response.data
+    );
+    let schema = create_schema(
+        TopologyInstances::default(),
+        QueryLimits {
+            introspection: false,
+            ..QueryLimits::default()
+        },
+    );
+    let response = schema.execute(QUERY).await;
+    assert_ne!(
+        value!({"__schema": {"queryType": {"name": "QueryAuthenticated"}}}),
+        response.data
+    );
+    // the sdl for `--print-schema` and `/schema.graphql` is still complete
+    let sdl = schema.sdl();
+    assert!(sdl.contains("type QueryAuthenticated"), "{sdl}");
+    assert!(sdl.contains("type MutationAuthenticated"), "{sdl}");
+}
+
+#[tokio::test]
+async fn test_anonymous_cache_control() {
+    let schema = create_anonymous_schema(TopologyInstances::default());
+    let response = schema.execute("{ serverVersion }").await;
+    assert_eq!(
+        value!({"serverVersion": env!("CARGO_PKG_VERSION")}),
+        response.data
+    );
+    assert_eq!(
+        Some("max-age=3600".to_string()),
+        response.cache_control.value()
+    );
+}
+
+#[tokio::test]
+async fn test_public_status() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: router
+    primary_ip: 1
+  2:
+    name: switch
+    primary_ip: 2
+  3:
+    name: spare
+    status: inventory
+    primary_ip: 3
+ip_addresses:
+  1:
+    ip: 10.0.0.1/24
+  2:
+    ip: 10.0.0.2/24
+  3:
+    ip: 10.0.0.3/24
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let holder = TopologyHolder::offline(topology);
+    let router = holder
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let topology = TopologyInstances::offline(holder);
+    let notifier = Notifier::new(Box::default(), 1);
+    let availability = AvailabilityHistory::default();
+    availability.record("10.0.0.1".parse().unwrap(), true);
+    availability.record("10.0.0.2".parse().unwrap(), false);
+    availability.record("10.0.0.3".parse().unwrap(), false);
+    let windows = [MaintenanceWindow {
+        title: "Core switch replacement".into(),
+        start: "2026-10-20 22:00:00".into(),
+        end: "2026-10-21 02:00:00".into(),
+    }];
+    let before = 1_792_000_000;
+    let status = PublicStatus::collect(&topology, &notifier, &availability, &windows, before).await;
+    assert_eq!(ServiceState::Operational, status.state());
+    // the device in the inventory is not in operation
+    assert_eq!((1, 1), (status.devices_up(), status.devices_down()));
+    assert!(status.last_sync().is_some());
+    assert!(status.maintenance().is_empty());
+
+    notifier
+        .device_failed(DEFAULT_NETBOX_INSTANCE, &router, "timeout")
+        .await;
+    let status = PublicStatus::collect(&topology, &notifier, &availability, &windows, before).await;
+    assert_eq!(ServiceState::Degraded, status.state());
+    assert_eq!(1, status.unreachable_devices());
+
+    let during = windows[0].start().expect("invalid start") + 60;
+    let status = PublicStatus::collect(&topology, &notifier, &availability, &windows, during).await;
+    assert_eq!(ServiceState::Maintenance, status.state());
+    assert_eq!(1, status.maintenance().len());
+}
+
+struct DeviceQuery(DeviceAccess);
+
+#[Object]
+impl DeviceQuery {
+    /// accesses the device `count` times within the request
+    async fn access(&self, ctx: &Context<'_>, count: u32) -> bool {
+        (0..count).all(|_| check_device_access(ctx, &self.0).is_ok())
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limit_per_request() {
+    let topology = parse_topology(
+        br#"
+devices:
+  4711:
+    name: router
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(4711))
+        .await
+        .expect("device not found");
+    let schema = Schema::build(
+        DeviceQuery(device.clone()),
+        EmptyMutation,
+        EmptySubscription,
+    )
+    .extension(RequestRateLimit)
+    .finish();
+    // nested resolvers of a request share the token of the device
+    let response = schema.execute("{ access(count: 50) }").await;
+    assert_eq!(value!({"access": true}), response.data);
+    // without the extension every access takes a token
+    let schema = Schema::build(DeviceQuery(device), EmptyMutation, EmptySubscription).finish();
+    let response = schema.execute("{ access(count: 50) }").await;
+    assert_eq!(value!({"access": false}), response.data);
+}
@@ -0,0 +1,77 @@
+use crate::graphql::{MutationAuthenticated, errors::ErrorCode};
+use async_graphql::{
+    OutputType, Response, ServerError, ServerResult, Value,
+    async_trait::async_trait,
+    extensions::{
+        Extension, ExtensionContext, ExtensionFactory, NextExecute, NextResolve, ResolveInfo,
+    },
+};
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use tokio::time::{Instant, timeout_at};
+
+/// Time budget of a query or mutation
+///
+/// A field still resolving when the budget is used up, e.g. waiting for a hung device, is
+/// cancelled and reported as error. The other fields are returned, so a dashboard still shows
+/// the devices that answered. The mutations themselves are not cancelled to not stop an apply
+/// half way, subscriptions have no budget.
+pub struct RequestTimeout(pub Duration);
+
+impl ExtensionFactory for RequestTimeout {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(RequestTimeoutExtension {
+            timeout: self.0,
+            deadline: OnceLock::new(),
+        })
+    }
+}
+
+struct RequestTimeoutExtension {
+    timeout: Duration,
+    /// set when the operation is executed
+    deadline: OnceLock<Instant>,
+}
+
+#[async_trait]
+impl Extension for RequestTimeoutExtension {
+    async fn execute(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        operation_name: Option<&str>,
+        next: NextExecute<'_>,
+    ) -> Response {
+        self.deadline.get_or_init(|| Instant::now() + self.timeout);
+        next.run(ctx, operation_name).await
+    }
+
+    async fn resolve(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        info: ResolveInfo<'_>,
+        next: NextResolve<'_>,
+    ) -> ServerResult<Option<Value>> {
+        let Some(deadline) = self.deadline.get().copied() else {
+            return next.run(ctx, info).await;
+        };
+        if info.parent_type == MutationAuthenticated::type_name() {
+            return next.run(ctx, info).await;
+        }
+        match timeout_at(deadline, next.run(ctx, info)).await {
+            Ok(result) => result,
+            Err(_) => {
+                let mut error = ServerError::new(
+                    format!(
+                        "Request exceeded its time budget of {}s",
+                        self.timeout.as_secs()
+                    ),
+                    None,
+                );
+                ErrorCode::Timeout.apply(&mut error);
+                Err(error)
+            }
+        }
+    }
+}
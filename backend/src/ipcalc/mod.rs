@@ -0,0 +1,142 @@
+//! Address calculations shared by the configuration generation and the ip address management.
+use ipnet::{IpAdd, IpNet, IpSub, Ipv4Net, Ipv6Net};
+use std::{
+    collections::{BTreeMap, btree_map::Iter},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ops::Range,
+};
+
+#[cfg(test)]
+mod test;
+
+/// Finds the unreserved parts of an address range, reservations may overlap
+pub struct GapFinder<V: Ord + Copy> {
+    reserved_chunks: BTreeMap<V, i32>,
+}
+
+impl<V: Ord + Copy> Default for GapFinder<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Ord + Copy> GapFinder<V> {
+    pub fn new() -> Self {
+        GapFinder {
+            reserved_chunks: BTreeMap::new(),
+        }
+    }
+    pub fn reserve(&mut self, chunk: Range<V>) {
+        *self.reserved_chunks.entry(chunk.start).or_insert(0) += 1;
+        *self.reserved_chunks.entry(chunk.end).or_insert(0) -= 1;
+    }
+    pub fn gaps(&self, Range { start, end }: Range<V>) -> GapIterator<V> {
+        GapIterator {
+            current_value: 0,
+            last_key: start,
+            start,
+            end,
+            gap_iter: self.reserved_chunks.iter(),
+        }
+    }
+}
+
+impl GapFinder<Ipv4Addr> {
+    pub fn reserve_ipv4(&mut self, ip: Ipv4Addr) {
+        self.reserve(ip.saturating_sub(1)..ip.saturating_add(1))
+    }
+    pub fn reserve_ipv4_range(&mut self, ip: Range<Ipv4Addr>) {
+        self.reserve(ip.start.saturating_sub(1)..ip.end.saturating_add(1))
+    }
+    pub fn reserve_ipv4_net(&mut self, ip: Ipv4Net) {
+        self.reserve_ipv4_range(ip.network()..ip.broadcast());
+    }
+    /// gaps between the network and the broadcast address
+    pub fn find_gaps_ipv4(&self, net: Ipv4Net) -> GapIterator<Ipv4Addr> {
+        self.gaps(net.network().saturating_add(1)..net.broadcast().saturating_sub(1))
+    }
+}
+impl GapFinder<Ipv6Addr> {
+    pub fn reserve_ipv6(&mut self, ip: Ipv6Addr) {
+        self.reserve(ip.saturating_sub(1)..ip.saturating_add(1))
+    }
+    pub fn reserve_ipv6_range(&mut self, ip: Range<Ipv6Addr>) {
+        self.reserve(ip.start.saturating_sub(1)..ip.end.saturating_add(1))
+    }
+    pub fn reserve_ipv6_net(&mut self, ip: Ipv6Net) {
+        self.reserve_ipv6_range(ip.network()..ip.broadcast());
+    }
+    /// gaps after the subnet-router anycast address, ipv6 has no broadcast address
+    pub fn find_gaps_ipv6(&self, net: Ipv6Net) -> GapIterator<Ipv6Addr> {
+        self.gaps(net.network().saturating_add(1)..net.broadcast())
+    }
+}
+impl GapFinder<IpAddr> {
+    pub fn reserve_ip(&mut self, ip: IpAddr) {
+        self.reserve(match ip {
+            IpAddr::V4(ip) => IpAddr::V4(ip.saturating_sub(1))..IpAddr::V4(ip.saturating_add(1)),
+            IpAddr::V6(ip) => IpAddr::V6(ip.saturating_sub(1))..IpAddr::V6(ip.saturating_add(1)),
+        });
+    }
+    /// reserves the range if both ends are of the same address family
+    pub fn reserve_ip_range(&mut self, ip: Range<IpAddr>) {
+        match (ip.start, ip.end) {
+            (IpAddr::V4(start), IpAddr::V4(end)) => {
+                self.reserve(IpAddr::V4(start.saturating_sub(1))..IpAddr::V4(end.saturating_add(1)))
+            }
+            (IpAddr::V6(start), IpAddr::V6(end)) => {
+                self.reserve(IpAddr::V6(start.saturating_sub(1))..IpAddr::V6(end.saturating_add(1)))
+            }
+            _ => {}
+        }
+    }
+    pub fn reserve_ipnet(&mut self, ip: IpNet) {
+        self.reserve_ip_range(ip.network()..ip.broadcast());
+    }
+    /// gaps of an ipv4 or ipv6 network, like `find_gaps_ipv4` and `find_gaps_ipv6`
+    pub fn find_gaps_ipnet(&self, net: IpNet) -> GapIterator<IpAddr> {
+        match net {
+            IpNet::V4(net) => self.gaps(
+                IpAddr::V4(net.network().saturating_add(1))
+                    ..IpAddr::V4(net.broadcast().saturating_sub(1)),
+            ),
+            IpNet::V6(net) => {
+                self.gaps(IpAddr::V6(net.network().saturating_add(1))..IpAddr::V6(net.broadcast()))
+            }
+        }
+    }
+}
+/// Gaps in ascending order, the bounds of a gap are both part of it
+pub struct GapIterator<'a, V: Ord + Copy> {
+    current_value: i32,
+    last_key: V,
+    start: V,
+    end: V,
+    gap_iter: Iter<'a, V, i32>,
+}
+impl<'a, V: Ord + Copy> Iterator for GapIterator<'a, V> {
+    type Item = Range<V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (current_key, increment) in self.gap_iter.by_ref() {
+            let current_key = (*current_key).clamp(self.start, self.end);
+            let found_gap = if self.current_value <= 0 && current_key > self.last_key {
+                Some(self.last_key..current_key)
+            } else {
+                None
+            };
+            self.current_value += increment;
+            self.last_key = current_key;
+            if let Some(found_gap) = found_gap {
+                return Some(found_gap);
+            }
+        }
+        if self.current_value <= 0 && self.end > self.last_key {
+            let last_key = self.last_key;
+            self.last_key = self.end;
+            Some(last_key..self.end)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::ipcalc::GapFinder;
+use ipnet::{IpNet, Ipv4Net};
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+#[test]
+pub fn test_gap_empty() {
+    let finder = GapFinder::<Ipv4Addr>::new();
+    let gaps = finder
+        .find_gaps_ipv4("172.16.1.1/24".parse().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        gaps,
+        vec!["172.16.1.1".parse().unwrap().."172.16.1.254".parse().unwrap()]
+    );
+}
+#[test]
+pub fn test_gap_none() {
+    let mut finder = GapFinder::<Ipv4Addr>::new();
+    finder.reserve("172.16.1.0".parse().unwrap().."172.16.1.255".parse().unwrap());
+    let gaps = finder
+        .find_gaps_ipv4("172.16.1.1/24".parse().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(gaps, vec![]);
+}
+#[test]
+pub fn test_gap_router_ip() {
+    let mut finder = GapFinder::<Ipv4Addr>::new();
+    finder.reserve_ipv4("172.16.1.1".parse().unwrap());
+    let gaps = finder
+        .find_gaps_ipv4("172.16.1.1/24".parse().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        gaps,
+        vec!["172.16.1.2".parse().unwrap().."172.16.1.254".parse().unwrap()]
+    );
+}
+#[test]
+pub fn test_gap_router_and_host_ip() {
+    let mut finder = GapFinder::<Ipv4Addr>::new();
+    finder.reserve_ipv4("172.16.1.1".parse().unwrap());
+    finder.reserve_ipv4("172.16.1.10".parse().unwrap());
+    let gaps = finder
+        .find_gaps_ipv4("172.16.1.1/24".parse().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        gaps,
+        vec![
+            "172.16.1.2".parse().unwrap().."172.16.1.9".parse().unwrap(),
+            "172.16.1.11".parse().unwrap().."172.16.1.254".parse().unwrap()
+        ]
+    );
+}
+#[test]
+pub fn test_gap_overlapping_ranges() {
+    let mut finder = GapFinder::<Ipv4Addr>::new();
+    finder.reserve_ipv4("172.16.1.1".parse().unwrap());
+    finder.reserve_ipv4("172.16.1.15".parse().unwrap());
+    finder.reserve_ipv4_range("172.16.1.10".parse().unwrap().."172.16.1.20".parse().unwrap());
+
+    let gaps = finder
+        .find_gaps_ipv4("172.16.1.1/24".parse().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        gaps,
+        vec![
+            "172.16.1.2".parse().unwrap().."172.16.1.9".parse().unwrap(),
+            "172.16.1.21".parse().unwrap().."172.16.1.254".parse().unwrap()
+        ]
+    );
+}
+#[test]
+pub fn test_gap_subnet() {
+    let mut finder = GapFinder::<Ipv4Addr>::new();
+    finder.reserve_ipv4("172.16.1.1".parse().unwrap());
+    finder.reserve_ipv4("172.16.1.15".parse().unwrap());
+    finder.reserve_ipv4_range("172.16.1.10".parse().unwrap().."172.16.1.20".parse().unwrap());
+    finder.reserve_ipv4_net(Ipv4Net::from_str("172.16.3.0/24").unwrap());
+
+    let gaps = finder
+        .find_gaps_ipv4("172.16.0.0/12".parse().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        gaps,
+        vec![
+            "172.16.0.1".parse().unwrap().."172.16.1.0".parse().unwrap(),
+            "172.16.1.2".parse().unwrap().."172.16.1.9".parse().unwrap(),
+            "172.16.1.21".parse().unwrap().."172.16.2.255".parse().unwrap(),
+            "172.16.4.0".parse().unwrap().."172.31.255.254".parse().unwrap()
+        ]
+    );
+}
+#[test]
+pub fn test_gap_ipv6() {
+    let mut finder = GapFinder::<Ipv6Addr>::new();
+    finder.reserve_ipv6("2001:db8::1".parse().unwrap());
+    finder.reserve_ipv6_range("2001:db8::100".parse().unwrap().."2001:db8::1ff".parse().unwrap());
+    let gaps = finder
+        .find_gaps_ipv6("2001:db8::/64".parse().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        gaps,
+        vec![
+            "2001:db8::2".parse().unwrap().."2001:db8::ff".parse().unwrap(),
+            "2001:db8::200".parse().unwrap().."2001:db8::ffff:ffff:ffff:ffff".parse().unwrap()
+        ]
+    );
+}
+#[test]
+pub fn test_gap_ipnet() {
+    let mut finder = GapFinder::<IpAddr>::new();
+    finder.reserve_ip("172.16.1.1".parse().unwrap());
+    finder.reserve_ipnet(IpNet::from_str("172.16.1.128/25").unwrap());
+    finder.reserve_ip("2001:db8::1".parse().unwrap());
+    let gaps = finder
+        .find_gaps_ipnet("172.16.1.0/24".parse().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        gaps,
+        vec!["172.16.1.2".parse().unwrap().."172.16.1.127".parse().unwrap()]
+    );
+    let gaps = finder
+        .find_gaps_ipnet("2001:db8::/120".parse().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        gaps,
+        vec!["2001:db8::2".parse().unwrap().."2001:db8::ff".parse().unwrap()]
+    );
+}
@@ -1,6 +1,9 @@
 extern crate core;
 
-use crate::device::ros::SetupError;
+use crate::{
+    device::{reachability::PingError, ros::SetupError},
+    topology::DeviceStatus,
+};
 use mikrotik_model::resource::{MissingDependenciesError, ResourceMutationError};
 use thiserror::Error;
 
@@ -8,7 +11,15 @@ pub mod config;
 pub mod context;
 pub mod device;
 pub mod graphql;
+pub mod ipcalc;
+pub mod metrics;
+pub mod monitor;
 pub mod netbox;
+pub mod notify;
+pub mod policy;
+pub mod provisioning;
+pub mod rate_limit;
+pub mod secrets;
 pub mod topology;
 
 #[derive(Error, Debug)]
@@ -19,8 +30,30 @@ pub enum Error {
     MikrotikModel(#[from] mikrotik_model::resource::Error),
     #[error("Error credentials")]
     MissingCredentials,
+    #[error("No management address")]
+    MissingManagementAddress,
+    #[error("Device {0} not found")]
+    UnknownDevice(u32),
+    #[error("Netbox instance {0} not configured")]
+    UnknownInstance(Box<str>),
+    #[error("Too many device accesses, try again later")]
+    RateLimited,
+    #[error("Device {0} is being provisioned by someone else")]
+    DeviceBusy(u32),
+    #[error("Rollout {0} not found")]
+    UnknownRollout(u32),
+    #[error("Prefix {0} not found")]
+    UnknownPrefix(u32),
+    #[error("Prefix {0} is no ipv4 prefix")]
+    NotIpv4Prefix(u32),
+    #[error("Not possible on a device with status {0}")]
+    DeviceStatus(DeviceStatus),
+    #[error("Ping failed: {0}")]
+    Ping(#[from] PingError),
     #[error("Cannot parse ip address {0}")]
     AddressParse(#[from] std::net::AddrParseError),
+    #[error("Address {0} is not documented in netbox")]
+    UnknownAddress(std::net::IpAddr),
     #[error("Cannot generate mutations: {0}")]
     ResourceMutation(#[from] ResourceMutationError),
     #[error("{0}")]
@@ -29,6 +62,32 @@ pub enum Error {
     ErrorGeneratingString(#[from] std::fmt::Error),
     #[error("Cannot build setup {0}")]
     SetupError(#[from] SetupError),
+    #[error("Certificate management is not configured")]
+    CertificatesNotConfigured,
+    #[error("Cannot read certificate file {path}: {error}")]
+    CertificateFile {
+        path: Box<str>,
+        error: std::io::Error,
+    },
+    #[error("Cannot sign certificate: {0}")]
+    CertificateSigning(Box<str>),
+    #[error("Command {command} failed: {message}")]
+    CommandFailed {
+        command: Box<str>,
+        message: Box<str>,
+    },
+    #[error("Error accessing netbox: {0}")]
+    Netbox(#[from] crate::netbox::NetboxError),
+    #[error("No free loopback address for device {0}")]
+    NoFreeLoopback(u32),
+    #[error("Cannot mirror {0}")]
+    InvalidPortMirror(Box<str>),
+    #[error("Device {device} failed {failures} connections, retrying in {retry_in_secs}s")]
+    BackingOff {
+        device: Box<str>,
+        failures: u32,
+        retry_in_secs: u64,
+    },
 }
 
 impl From<MissingDependenciesError<'_, '_>> for Error {
@@ -0,0 +1,98 @@
+//! Gauges about the health of the provisioning, the base of the generated [`rules`].
+use crate::{notify::Notifier, topology::TopologyInstances};
+use prometheus::{
+    GaugeVec, Opts, Registry,
+    core::{Collector, Desc},
+    proto::MetricFamily,
+};
+
+pub mod rules;
+#[cfg(test)]
+mod test;
+
+pub const DRIFTED_DEVICES: &str = "provisioning_drifted_devices";
+pub const UNREACHABLE_DEVICES: &str = "provisioning_unreachable_devices";
+pub const TOPOLOGY_FETCH_FAILURES: &str = "topology_fetch_failures";
+/// label of the netbox instance, `instance` is the target label of prometheus
+pub const INSTANCE_LABEL: &str = "netbox_instance";
+
+/// Exports drifted and unreachable devices and failed netbox fetches per netbox instance.
+///
+/// The values are taken from the notifier and the topology on each scrape, so there is no
+/// collector to run. Drift is only known while the drift check is enabled.
+#[derive(Clone)]
+pub struct ProvisioningHealth {
+    topology: TopologyInstances,
+    notifier: &'static Notifier,
+    drifted: GaugeVec,
+    unreachable: GaugeVec,
+    fetch_failures: GaugeVec,
+}
+
+impl ProvisioningHealth {
+    pub fn new(
+        topology: TopologyInstances,
+        notifier: &'static Notifier,
+    ) -> Result<Self, prometheus::Error> {
+        let labels = [INSTANCE_LABEL];
+        Ok(Self {
+            topology,
+            notifier,
+            drifted: GaugeVec::new(
+                Opts::new(DRIFTED_DEVICES, "Devices differing from netbox"),
+                &labels,
+            )?,
+            unreachable: GaugeVec::new(
+                Opts::new(
+                    UNREACHABLE_DEVICES,
+                    "Devices reported as unreachable and not accessed since",
+                ),
+                &labels,
+            )?,
+            fetch_failures: GaugeVec::new(
+                Opts::new(
+                    TOPOLOGY_FETCH_FAILURES,
+                    "Failed netbox fetches since the last successful one",
+                ),
+                &labels,
+            )?,
+        })
+    }
+    pub fn register(&self, registry: &Registry) -> Result<(), prometheus::Error> {
+        registry.register(Box::new(self.clone()))
+    }
+
+    fn update(&self) {
+        for (instance, holder) in self.topology.iter() {
+            self.drifted
+                .with_label_values(&[instance])
+                .set(self.notifier.drifted_devices(instance) as f64);
+            self.unreachable
+                .with_label_values(&[instance])
+                .set(self.notifier.unreachable_devices(instance) as f64);
+            self.fetch_failures
+                .with_label_values(&[instance])
+                .set(holder.fetch_failures() as f64);
+        }
+    }
+    fn gauges(&self) -> [&GaugeVec; 3] {
+        [&self.drifted, &self.unreachable, &self.fetch_failures]
+    }
+}
+
+impl Collector for ProvisioningHealth {
+    fn desc(&self) -> Vec<&Desc> {
+        self.gauges()
+            .into_iter()
+            .flat_map(|gauge| gauge.desc())
+            .collect()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        self.update();
+        self.gauges()
+            .into_iter()
+            .flat_map(|gauge| gauge.collect())
+            .collect()
+    }
+}
@@ -0,0 +1,85 @@
+//! Prometheus alerting rules on the [`ProvisioningHealth`](super::ProvisioningHealth) gauges.
+//!
+//! The gauges carry the netbox instance as label [`INSTANCE_LABEL`], the `instance` label
+//! stays the scraped address of prometheus.
+use crate::metrics::{
+    DRIFTED_DEVICES, INSTANCE_LABEL, TOPOLOGY_FETCH_FAILURES, UNREACHABLE_DEVICES,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Name of the rule group in the generated file
+pub const GROUP_NAME: &str = "netbox-provisioner";
+
+#[derive(Serialize, Debug)]
+struct RuleFile {
+    groups: Vec<RuleGroup>,
+}
+
+#[derive(Serialize, Debug)]
+struct RuleGroup {
+    name: &'static str,
+    rules: Vec<AlertRule>,
+}
+
+#[derive(Serialize, Debug)]
+struct AlertRule {
+    alert: &'static str,
+    expr: String,
+    /// the condition has to hold this long before the alert fires
+    #[serde(rename = "for")]
+    duration: &'static str,
+    labels: BTreeMap<&'static str, &'static str>,
+    annotations: BTreeMap<&'static str, &'static str>,
+}
+
+impl AlertRule {
+    fn new(
+        alert: &'static str,
+        expr: String,
+        duration: &'static str,
+        severity: &'static str,
+        summary: &'static str,
+    ) -> Self {
+        Self {
+            alert,
+            expr,
+            duration,
+            labels: BTreeMap::from([("severity", severity)]),
+            annotations: BTreeMap::from([("summary", summary)]),
+        }
+    }
+}
+
+/// Rules file to be loaded by prometheus via `rule_files`
+pub fn alerting_rules() -> String {
+    serde_yaml::to_string(&RuleFile {
+        groups: vec![RuleGroup {
+            name: GROUP_NAME,
+            rules: vec![
+                AlertRule::new(
+                    "ProvisioningDrift",
+                    format!("{DRIFTED_DEVICES} > 0"),
+                    "1h",
+                    "warning",
+                    "{{ $value }} devices of netbox instance {{ $labels.netbox_instance }} differ from netbox",
+                ),
+                AlertRule::new(
+                    "ProvisioningDevicesUnreachable",
+                    format!("{UNREACHABLE_DEVICES} > 0"),
+                    "10m",
+                    "critical",
+                    "{{ $value }} devices of netbox instance {{ $labels.netbox_instance }} are unreachable",
+                ),
+                AlertRule::new(
+                    "ProvisioningTopologyFetchFailing",
+                    format!("{TOPOLOGY_FETCH_FAILURES} > 0"),
+                    "15m",
+                    "warning",
+                    "Netbox instance {{ $labels.netbox_instance }} cannot be fetched, the topology is outdated",
+                ),
+            ],
+        }],
+    })
+    .expect("alerting rules are serializable")
+}
@@ -0,0 +1,105 @@
+use crate::{
+    config::DEFAULT_NETBOX_INSTANCE,
+    metrics::{
+        DRIFTED_DEVICES, INSTANCE_LABEL, ProvisioningHealth, TOPOLOGY_FETCH_FAILURES,
+        UNREACHABLE_DEVICES,
+        rules::{GROUP_NAME, alerting_rules},
+    },
+    notify::Notifier,
+    topology::{
+        DeviceId, TopologyHolder, TopologyInstances,
+        snapshot::{FixtureFormat, parse_topology},
+    },
+};
+use prometheus::Registry;
+use std::collections::HashMap;
+
+fn gathered(registry: &Registry) -> HashMap<String, f64> {
+    registry
+        .gather()
+        .into_iter()
+        .flat_map(|family| {
+            let name = family.get_name().to_string();
+            family
+                .get_metric()
+                .iter()
+                .map(|metric| (name.clone(), metric.get_gauge().get_value()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_provisioning_health() {
+    let topology = parse_topology(
+        include_bytes!("../topology/fixtures/single-router.yaml"),
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid fixture");
+    let holder = TopologyHolder::offline(topology);
+    let device = holder
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let notifier: &'static Notifier = Box::leak(Box::new(Notifier::new(Box::default(), 2)));
+    let health = ProvisioningHealth::new(TopologyInstances::offline(holder), notifier)
+        .expect("invalid gauges");
+    let registry = Registry::new();
+    health.register(&registry).expect("cannot register");
+
+    // the target label of prometheus is not overwritten
+    for family in registry.gather() {
+        for metric in family.get_metric() {
+            let labels = metric
+                .get_label()
+                .iter()
+                .map(|label| (label.get_name(), label.get_value()))
+                .collect::<Vec<_>>();
+            assert_eq!(vec![(INSTANCE_LABEL, DEFAULT_NETBOX_INSTANCE)], labels);
+        }
+    }
+
+    let values = gathered(&registry);
+    assert_eq!(values.get(DRIFTED_DEVICES), Some(&0.0));
+    assert_eq!(values.get(UNREACHABLE_DEVICES), Some(&0.0));
+    assert_eq!(values.get(TOPOLOGY_FETCH_FAILURES), Some(&0.0));
+
+    // reported unreachable after the second failure only
+    notifier
+        .device_failed(DEFAULT_NETBOX_INSTANCE, &device, "timeout")
+        .await;
+    assert_eq!(gathered(&registry).get(UNREACHABLE_DEVICES), Some(&0.0));
+    notifier
+        .device_failed(DEFAULT_NETBOX_INSTANCE, &device, "timeout")
+        .await;
+    assert_eq!(gathered(&registry).get(UNREACHABLE_DEVICES), Some(&1.0));
+    notifier.device_reachable(DEFAULT_NETBOX_INSTANCE, &device);
+    assert_eq!(gathered(&registry).get(UNREACHABLE_DEVICES), Some(&0.0));
+}
+
+#[test]
+fn test_alerting_rules() {
+    let rules: serde_yaml::Value = serde_yaml::from_str(&alerting_rules()).expect("invalid yaml");
+    let group = &rules["groups"][0];
+    assert_eq!(group["name"].as_str(), Some(GROUP_NAME));
+    let rules = group["rules"].as_sequence().expect("no rules");
+    // every exported gauge is alerted on
+    for metric in [
+        DRIFTED_DEVICES,
+        UNREACHABLE_DEVICES,
+        TOPOLOGY_FETCH_FAILURES,
+    ] {
+        assert!(
+            rules.iter().any(|rule| rule["expr"]
+                .as_str()
+                .is_some_and(|expr| expr.contains(metric))),
+            "no rule on {metric}"
+        );
+    }
+    for rule in rules {
+        assert!(rule["for"].as_str().is_some());
+        let summary = rule["annotations"]["summary"].as_str().expect("no summary");
+        assert!(summary.contains(&format!("$labels.{INSTANCE_LABEL} ")));
+        assert!(rule["labels"]["severity"].as_str().is_some());
+    }
+}
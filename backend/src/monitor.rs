@@ -0,0 +1,342 @@
+use crate::{
+    Error,
+    config::CONFIG,
+    device::{
+        availability::AVAILABILITY,
+        backoff::{BACKOFF, BackoffState},
+        command::{CommandReply, run_command},
+        mirror::PORT_MIRRORS,
+        reachability::recorded_ping,
+    },
+    notify::NOTIFIER,
+    provisioning::{
+        lock::lock_device,
+        plan::{PlanOptions, ProvisionPlan},
+    },
+    topology::{TopologyInstances, access::device::DeviceAccess},
+};
+use async_graphql::futures_util::future::join_all;
+use log::{info, warn};
+use prometheus::{GaugeVec, IntCounterVec, Opts, Registry};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[cfg(test)]
+mod test;
+
+const COUNTERS: [(&str, &str, &str); 4] = [
+    ("rx-byte", "interface_rx_bytes_total", "Received bytes"),
+    ("tx-byte", "interface_tx_bytes_total", "Transmitted bytes"),
+    (
+        "rx-packet",
+        "interface_rx_packets_total",
+        "Received packets",
+    ),
+    (
+        "tx-packet",
+        "interface_tx_packets_total",
+        "Transmitted packets",
+    ),
+];
+
+/// counter attribute, instance, device and interface
+type CounterKey = (&'static str, Box<str>, Box<str>, Box<str>);
+
+/// Polls the interface counters of all devices in operation tagged with `monitor`
+#[derive(Clone)]
+pub struct InterfaceCollector {
+    counters: Box<[(&'static str, IntCounterVec)]>,
+    /// last value read from the devices, the exported counters grow by the difference
+    last_values: Arc<Mutex<HashMap<CounterKey, u64>>>,
+}
+
+impl InterfaceCollector {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let counters = COUNTERS
+            .iter()
+            .map(|(attribute, name, help)| {
+                IntCounterVec::new(
+                    Opts::new(*name, *help),
+                    &["instance", "device", "interface"],
+                )
+                .map(|counter| (*attribute, counter))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            counters,
+            last_values: Arc::default(),
+        })
+    }
+    pub fn register(&self, registry: &Registry) -> Result<(), prometheus::Error> {
+        for (_, counter) in &self.counters {
+            registry.register(Box::new(counter.clone()))?;
+        }
+        Ok(())
+    }
+
+    pub async fn run(self, topology: TopologyInstances, interval: Duration) {
+        info!("Start interface collector every {interval:?}");
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for (instance, holder) in topology.iter() {
+                for device in holder.devices().await {
+                    if !device.is_monitored()
+                        || !device.status().in_fleet()
+                        || device.check_backoff().is_err()
+                    {
+                        continue;
+                    }
+                    match self.poll(instance, &device).await {
+                        Ok(()) => NOTIFIER.device_reachable(instance, &device),
+                        Err(error) => {
+                            warn!("Cannot poll interfaces of {}: {error}", device.name());
+                            NOTIFIER
+                                .device_failed(instance, &device, &error.to_string())
+                                .await;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn poll(&self, instance: &str, device: &DeviceAccess) -> Result<(), Error> {
+        let client = device.connect(None, None).await?;
+        for reply in run_command(client.client(), "/interface/print", &[]).await? {
+            self.update(instance, device.name(), &reply);
+        }
+        Ok(())
+    }
+
+    fn update(&self, instance: &str, device: &str, reply: &CommandReply) {
+        let Some(interface) = reply.get("name") else {
+            return;
+        };
+        let mut last_values = self
+            .last_values
+            .lock()
+            .expect("interface counters poisoned");
+        for (attribute, counter) in &self.counters {
+            let Some(value) = reply.get(*attribute).and_then(|v| v.parse::<u64>().ok()) else {
+                continue;
+            };
+            let key = (*attribute, instance.into(), device.into(), interface.into());
+            counter
+                .with_label_values(&[instance, device, interface])
+                .inc_by(counter_increase(last_values.insert(key, value), value));
+        }
+    }
+}
+
+/// growth of a device counter since the last read, the counters of a device start at zero
+/// again after a reboot
+fn counter_increase(previous: Option<u64>, current: u64) -> u64 {
+    match previous {
+        Some(previous) if current >= previous => current - previous,
+        _ => current,
+    }
+}
+
+/// Pings all devices in operation with a management address and exports their availability and
+/// connection failures as gauges
+#[derive(Clone)]
+pub struct AvailabilityCollector {
+    up: GaugeVec,
+    uptime: GaugeVec,
+    failures: GaugeVec,
+    connection_failures: GaugeVec,
+    backing_off: GaugeVec,
+}
+
+impl AvailabilityCollector {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let labels = ["instance", "device"];
+        Ok(Self {
+            up: GaugeVec::new(
+                Opts::new("device_up", "Last ping of the device succeeded"),
+                &labels,
+            )?,
+            uptime: GaugeVec::new(
+                Opts::new(
+                    "device_uptime_percent",
+                    "Successful pings in the recorded history",
+                ),
+                &labels,
+            )?,
+            failures: GaugeVec::new(
+                Opts::new(
+                    "device_consecutive_failures",
+                    "Failed pings since the last successful one",
+                ),
+                &labels,
+            )?,
+            connection_failures: GaugeVec::new(
+                Opts::new(
+                    "device_connection_failures",
+                    "Failed connections since the last successful one",
+                ),
+                &labels,
+            )?,
+            backing_off: GaugeVec::new(
+                Opts::new(
+                    "device_backing_off",
+                    "Fleet operations skip the device after repeated connection failures",
+                ),
+                &labels,
+            )?,
+        })
+    }
+    pub fn register(&self, registry: &Registry) -> Result<(), prometheus::Error> {
+        registry.register(Box::new(self.up.clone()))?;
+        registry.register(Box::new(self.uptime.clone()))?;
+        registry.register(Box::new(self.failures.clone()))?;
+        registry.register(Box::new(self.connection_failures.clone()))?;
+        registry.register(Box::new(self.backing_off.clone()))?;
+        Ok(())
+    }
+
+    pub async fn run(self, topology: TopologyInstances, interval: Duration) {
+        info!("Start availability collector every {interval:?}");
+        let mut ticker = tokio::time::interval(interval);
+        let mut exported = HashSet::<(String, String)>::new();
+        loop {
+            ticker.tick().await;
+            let mut pings = Vec::new();
+            for (instance, holder) in topology.iter() {
+                for device in holder.devices().await {
+                    if !device.status().in_fleet() {
+                        continue;
+                    }
+                    if let Some(address) = device.management_ip() {
+                        pings.push(async move {
+                            // the result is recorded in the availability history
+                            let _ = recorded_ping(address).await;
+                            (instance, device, address)
+                        });
+                    }
+                }
+            }
+            let results = join_all(pings).await;
+            let current = results
+                .iter()
+                .map(|(instance, device, _)| (instance.to_string(), device.name().to_string()))
+                .collect::<HashSet<_>>();
+            // devices removed from netbox are not exported anymore, the others are updated in
+            // place so a scrape never sees them missing
+            for (instance, device) in exported.difference(&current) {
+                self.remove(&[instance, device]);
+            }
+            exported = current;
+            for (instance, device, address) in results {
+                let labels = [instance, device.name()];
+                let connection = BACKOFF.failures(address);
+                self.connection_failures.with_label_values(&labels).set(
+                    connection
+                        .as_ref()
+                        .map(|failures| failures.consecutive_failures() as f64)
+                        .unwrap_or_default(),
+                );
+                self.backing_off.with_label_values(&labels).set(
+                    if connection
+                        .is_some_and(|failures| failures.state() == BackoffState::BackingOff)
+                    {
+                        1.0
+                    } else {
+                        0.0
+                    },
+                );
+                let Some(availability) = AVAILABILITY.availability(address) else {
+                    self.remove_availability(&labels);
+                    continue;
+                };
+                self.up
+                    .with_label_values(&labels)
+                    .set(if availability.is_up() { 1.0 } else { 0.0 });
+                self.uptime
+                    .with_label_values(&labels)
+                    .set(availability.uptime_percent());
+                self.failures
+                    .with_label_values(&labels)
+                    .set(availability.consecutive_failures() as f64);
+            }
+        }
+    }
+    fn remove(&self, labels: &[&str]) {
+        self.remove_availability(labels);
+        let _ = self.connection_failures.remove_label_values(labels);
+        let _ = self.backing_off.remove_label_values(labels);
+    }
+    fn remove_availability(&self, labels: &[&str]) {
+        // labels never exported are not found, nothing to remove then
+        let _ = self.up.remove_label_values(labels);
+        let _ = self.uptime.remove_label_values(labels);
+        let _ = self.failures.remove_label_values(labels);
+    }
+}
+
+/// Compares all devices in operation tagged with `monitor` with the generated configuration and notifies
+/// about devices that differ from netbox
+pub async fn watch_drift(topology: TopologyInstances, interval: Duration) {
+    info!("Start drift check every {interval:?}");
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        for (instance, holder) in topology.iter() {
+            for device in holder.devices().await {
+                if !device.is_monitored()
+                    || !device.has_routeros()
+                    || !device.status().in_fleet()
+                    || device.check_backoff().is_err()
+                {
+                    continue;
+                }
+                if let Err(error) = check_drift(instance, &device).await {
+                    warn!("Cannot check drift of {}: {error}", device.name());
+                }
+            }
+        }
+    }
+}
+
+async fn check_drift(instance: &str, device: &DeviceAccess) -> Result<(), Error> {
+    // a device currently provisioned is checked on the next run
+    let Ok(_lock) = lock_device(instance, device.id()) else {
+        return Ok(());
+    };
+    // the device differs from the generated configuration while a port is mirrored
+    if PORT_MIRRORS.is_mirrored(instance, device.id()) {
+        return Ok(());
+    }
+    // the interface collector counts the failed accesses of the monitored devices already,
+    // counting them here as well would report a device unreachable after half the failures
+    let counts_failures = CONFIG.load().monitor_interval().is_none();
+    let client = match device.connect(None, None).await {
+        Ok(client) => {
+            NOTIFIER.device_reachable(instance, device);
+            client
+        }
+        Err(error) => {
+            if counts_failures {
+                NOTIFIER
+                    .device_failed(instance, device, &error.to_string())
+                    .await;
+            }
+            return Err(error);
+        }
+    };
+    let plan =
+        ProvisionPlan::create(&client, &PlanOptions::for_device(device, None, false)).await?;
+    // the drift is notified even if the mac addresses cannot be compared
+    let mac_checks = client.check_mac_addresses().await.unwrap_or_else(|error| {
+        warn!("Cannot check mac addresses of {}: {error}", device.name());
+        Box::default()
+    });
+    NOTIFIER
+        .drift_checked(instance, device, &plan, &mac_checks)
+        .await;
+    Ok(())
+}
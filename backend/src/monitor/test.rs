@@ -0,0 +1,11 @@
+use crate::monitor::counter_increase;
+
+#[test]
+fn test_counter_increase() {
+    // the first read counts everything the device counted so far
+    assert_eq!(1500, counter_increase(None, 1500));
+    assert_eq!(500, counter_increase(Some(1500), 2000));
+    assert_eq!(0, counter_increase(Some(2000), 2000));
+    // the device rebooted and counts from zero again
+    assert_eq!(300, counter_increase(Some(2000), 300));
+}
@@ -1,8 +1,10 @@
-use crate::config::CONFIG;
+use crate::{config::NetboxInstance, topology::DeviceStatus};
 use async_graphql::{ComplexObject, SimpleObject};
 use graphql_client::{GraphQLQuery, Response};
+use ipnet::IpNet;
 use reqwest::header::{AUTHORIZATION, HeaderMap};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::net::IpAddr;
 use thiserror::Error;
 
@@ -20,6 +22,12 @@ pub struct JSON {
     pub wlan_group: Option<u32>,
     pub wlan_mgmt: Option<u32>,
     pub controller: Option<u32>,
+    pub dhcp_options: Option<Box<str>>,
+    /// addresses of the dhcp servers separated by commas
+    pub dhcp_relay: Option<Box<str>>,
+    pub pppoe_credentials: Option<Box<str>>,
+    pub upnp: Option<bool>,
+    pub igmp_proxy: Option<bool>,
 }
 
 #[derive(Debug, SimpleObject)]
@@ -49,11 +57,13 @@ pub enum NetboxError {
     EmptyResult,
 }
 
-pub async fn fetch_topology() -> Result<fetch_topology::ResponseData, NetboxError> {
+pub async fn fetch_topology(
+    instance: &NetboxInstance,
+) -> Result<fetch_topology::ResponseData, NetboxError> {
     let request_body = FetchTopology::build_query(fetch_topology::Variables {});
-    let client = netbox_client()?;
+    let client = netbox_client(&instance.token)?;
     let response_body: Response<fetch_topology::ResponseData> = client
-        .post(netbox_url())
+        .post(instance.url.as_str())
         .json(&request_body)
         .send()
         .await?
@@ -68,13 +78,117 @@ pub async fn fetch_topology() -> Result<fetch_topology::ResponseData, NetboxErro
     }
 }
 
-fn netbox_url() -> &'static str {
-    CONFIG.netbox_url.as_str()
+/// Severity of a journal entry
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JournalKind {
+    Success,
+    Warning,
 }
 
-fn netbox_client() -> Result<reqwest::Client, NetboxError> {
+impl JournalKind {
+    fn slug(self) -> &'static str {
+        match self {
+            JournalKind::Success => "success",
+            JournalKind::Warning => "warning",
+        }
+    }
+}
+
+/// adds a journal entry to a device, the comment is rendered as markdown by netbox
+pub async fn create_journal_entry(
+    instance: &NetboxInstance,
+    device_id: u32,
+    kind: JournalKind,
+    comments: &str,
+) -> Result<(), NetboxError> {
+    netbox_client(&instance.token)?
+        .post(rest_url(instance, "extras/journal-entries/"))
+        .json(&json!({
+            "assigned_object_type": "dcim.device",
+            "assigned_object_id": device_id,
+            "kind": kind.slug(),
+            "comments": comments,
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// sets the status of a device, e.g. after it was decommissioned
+pub async fn update_device_status(
+    instance: &NetboxInstance,
+    device_id: u32,
+    status: DeviceStatus,
+) -> Result<(), NetboxError> {
+    netbox_client(&instance.token)?
+        .patch(rest_url(instance, &format!("dcim/devices/{device_id}/")))
+        .json(&json!({ "status": status.as_str() }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct CreatedObject {
+    id: u32,
+}
+
+/// creates a virtual interface on a device like the loopback interface, returns its id
+pub async fn create_virtual_interface(
+    instance: &NetboxInstance,
+    device_id: u32,
+    name: &str,
+) -> Result<u32, NetboxError> {
+    let created: CreatedObject = netbox_client(&instance.token)?
+        .post(rest_url(instance, "dcim/interfaces/"))
+        .json(&json!({
+            "device": device_id,
+            "name": name,
+            "type": "virtual",
+        }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(created.id)
+}
+
+/// creates an ip address assigned to an interface of a device
+pub async fn create_interface_address(
+    instance: &NetboxInstance,
+    interface_id: u32,
+    address: IpNet,
+    vrf: Option<u32>,
+    description: &str,
+) -> Result<(), NetboxError> {
+    netbox_client(&instance.token)?
+        .post(rest_url(instance, "ipam/ip-addresses/"))
+        .json(&json!({
+            "address": address.to_string(),
+            "assigned_object_type": "dcim.interface",
+            "assigned_object_id": interface_id,
+            "vrf": vrf,
+            "description": description,
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// the rest api is next to the graphql endpoint, `https://netbox/graphql/` becomes
+/// `https://netbox/api/<path>`
+fn rest_url(instance: &NetboxInstance, path: &str) -> String {
+    let url = instance.url.trim_end_matches('/');
+    let base = url.strip_suffix("graphql").unwrap_or(url);
+    format!("{}/api/{path}", base.trim_end_matches('/'))
+}
+
+fn netbox_client(access_token: &str) -> Result<reqwest::Client, NetboxError> {
     let mut headers = HeaderMap::new();
-    let access_token = CONFIG.netbox_token.as_str();
     headers.insert(AUTHORIZATION, format!("Token {access_token}").parse()?);
 
     Ok(reqwest::Client::builder()
@@ -0,0 +1,173 @@
+//! Periodic email summary of the provisioning status for teams not watching the dashboard.
+use crate::{
+    config::{DigestConfig, SmtpSecurity},
+    notify::{DeviceKey, DigestEvents, Notification, Notifier},
+    topology::TopologyInstances,
+};
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor, message::header::ContentType,
+    transport::smtp::authentication::Credentials,
+};
+use log::{info, warn};
+use std::{collections::HashSet, fmt::Write};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DigestError {
+    #[error("Invalid mail address: {0}")]
+    Address(#[from] lettre::address::AddressError),
+    #[error("Cannot build mail: {0}")]
+    Message(#[from] lettre::error::Error),
+    #[error("Cannot send mail: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+}
+
+/// Content of a single digest
+#[derive(Debug, Default)]
+pub struct Digest {
+    pub out_of_sync: Vec<Notification>,
+    pub failed_applies: Vec<Notification>,
+    /// instance and name of the RouterOS devices added since the last digest and not applied yet
+    pub new_devices: Vec<(Box<str>, Box<str>)>,
+}
+
+impl Digest {
+    pub fn subject(&self) -> String {
+        format!(
+            "Provisioning digest: {} out of sync, {} failed applies, {} new devices",
+            self.out_of_sync.len(),
+            self.failed_applies.len(),
+            self.new_devices.len()
+        )
+    }
+    pub fn render(&self) -> String {
+        if self.out_of_sync.is_empty()
+            && self.failed_applies.is_empty()
+            && self.new_devices.is_empty()
+        {
+            return "All devices in sync, no failed applies and no new devices.\n".into();
+        }
+        let mut text = String::new();
+        for (title, notifications) in [
+            ("Devices out of sync", &self.out_of_sync),
+            ("Failed applies", &self.failed_applies),
+        ] {
+            if notifications.is_empty() {
+                continue;
+            }
+            writeln!(text, "{title} ({}):", notifications.len()).unwrap();
+            for notification in notifications {
+                writeln!(
+                    text,
+                    "  {}/{}: {}",
+                    notification.instance, notification.device, notification.message
+                )
+                .unwrap();
+            }
+            writeln!(text).unwrap();
+        }
+        if !self.new_devices.is_empty() {
+            writeln!(text, "New devices ({}):", self.new_devices.len()).unwrap();
+            for (instance, device) in &self.new_devices {
+                writeln!(text, "  {instance}/{device}").unwrap();
+            }
+        }
+        text
+    }
+}
+
+/// Sends a digest of the events collected by the notifier in the configured interval
+pub struct DigestReporter {
+    config: DigestConfig,
+    /// RouterOS devices seen by the last digest, not set before the first run
+    known_devices: Option<HashSet<DeviceKey>>,
+}
+
+impl DigestReporter {
+    pub fn new(config: DigestConfig) -> Self {
+        Self {
+            config,
+            known_devices: None,
+        }
+    }
+
+    pub async fn run(mut self, topology: TopologyInstances, notifier: &Notifier) {
+        let interval = self.config.interval();
+        info!("Send provisioning digest every {interval:?}");
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let mut devices = Vec::new();
+            for (instance, holder) in topology.iter() {
+                for device in holder.devices().await {
+                    if device.has_routeros() && device.status().in_fleet() {
+                        devices.push(((instance.into(), device.id()), device.name().into()));
+                    }
+                }
+            }
+            let Some(digest) = self.collect(devices, notifier.take_digest_events()) else {
+                continue;
+            };
+            if let Err(error) = self.send(&digest).await {
+                warn!("Cannot send provisioning digest: {error}");
+            }
+        }
+    }
+
+    /// Builds the digest from the current devices and the events since the last digest.
+    ///
+    /// The first call only remembers the devices, so the devices present at startup are not
+    /// reported as new.
+    pub fn collect(
+        &mut self,
+        devices: Vec<(DeviceKey, Box<str>)>,
+        events: DigestEvents,
+    ) -> Option<Digest> {
+        let known = self
+            .known_devices
+            .replace(devices.iter().map(|(key, _)| key.clone()).collect())?;
+        let new_devices = devices
+            .into_iter()
+            .filter(|(key, _)| !known.contains(key) && !events.applied.contains(key))
+            .map(|((instance, _), name)| (instance, name))
+            .collect();
+        Some(Digest {
+            out_of_sync: events.drifted,
+            failed_applies: events.failed_applies,
+            new_devices,
+        })
+    }
+
+    async fn send(&self, digest: &Digest) -> Result<(), DigestError> {
+        let mut message = Message::builder()
+            .from(self.config.from.parse()?)
+            .subject(digest.subject());
+        for to in &self.config.to {
+            message = message.to(to.parse()?);
+        }
+        let message = message
+            .header(ContentType::TEXT_PLAIN)
+            .body(digest.render())?;
+        let host = self.config.smtp_host.as_ref();
+        let mut transport = match self.config.smtp_security {
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+            SmtpSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        };
+        if let Some(port) = self.config.smtp_port {
+            transport = transport.port(port);
+        }
+        if let Some(user) = &self.config.smtp_user {
+            transport = transport.credentials(Credentials::new(
+                user.to_string(),
+                self.config
+                    .smtp_password
+                    .as_deref()
+                    .unwrap_or_default()
+                    .to_string(),
+            ));
+        }
+        transport.build().send(message).await?;
+        Ok(())
+    }
+}
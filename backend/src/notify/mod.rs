@@ -0,0 +1,328 @@
+//! Notifications about provisioning events posted to Slack, Mattermost or a generic webhook.
+use crate::{
+    config::{CONFIG, NotificationEvent, NotifierConfig, NotifierKind},
+    device::mac::MacCheck,
+    provisioning::plan::{ApplyResult, ProvisionPlan},
+    topology::{DeviceId, access::device::DeviceAccess},
+};
+use lazy_static::lazy_static;
+use log::warn;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::json;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::Mutex,
+    time::Duration,
+};
+
+pub mod digest;
+
+/// Key of a device, the ids are only unique within a netbox instance
+pub type DeviceKey = (Box<str>, DeviceId);
+
+/// Single event about a device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub event: NotificationEvent,
+    pub instance: Box<str>,
+    pub device: Box<str>,
+    pub message: Box<str>,
+}
+
+#[derive(Default)]
+struct NotifierState {
+    /// consecutive failed accesses by device
+    failures: HashMap<DeviceKey, u32>,
+    /// devices already reported as drifted, reported again after they were in sync
+    drifted: HashMap<DeviceKey, Notification>,
+    /// failed applies since the last digest
+    failed_applies: Vec<Notification>,
+    /// devices with a successful apply since the last digest
+    applied: HashSet<DeviceKey>,
+}
+
+/// Failed applies kept for the digest, older ones are dropped
+const MAX_FAILED_APPLIES: usize = 100;
+
+/// Events collected since the last digest
+#[derive(Debug, Default)]
+pub struct DigestEvents {
+    /// drift notifications of all devices currently out of sync
+    pub drifted: Vec<Notification>,
+    pub failed_applies: Vec<Notification>,
+    pub applied: HashSet<DeviceKey>,
+}
+
+/// Posts notifications to the configured webhooks.
+///
+/// Unreachable and drifted devices are only reported once until they recover, so the
+/// periodic checks do not repeat the same notification on every run.
+pub struct Notifier {
+    targets: Box<[NotifierConfig]>,
+    unreachable_after: u32,
+    client: reqwest::Client,
+    state: Mutex<NotifierState>,
+}
+
+impl Notification {
+    fn default_template(&self) -> &'static str {
+        match self.event {
+            NotificationEvent::ApplySucceeded => "Applied plan on {{device}}: {{message}}",
+            NotificationEvent::ApplyFailed => "Applying plan on {{device}} failed: {{message}}",
+            NotificationEvent::ApplyNotConverged => {
+                "Applied plan on {{device}} did not converge: {{message}}"
+            }
+            NotificationEvent::DriftDetected => "{{device}} differs from netbox: {{message}}",
+            NotificationEvent::DeviceUnreachable => "{{device}} is unreachable: {{message}}",
+        }
+    }
+    /// the message of the notification by the template of the target or the default template
+    pub fn render(&self, target: &NotifierConfig) -> String {
+        target
+            .templates
+            .get(&self.event)
+            .map(Box::as_ref)
+            .unwrap_or_else(|| self.default_template())
+            .replace("{{event}}", self.event.name())
+            .replace("{{instance}}", &self.instance)
+            .replace("{{device}}", &self.device)
+            .replace("{{message}}", &self.message)
+    }
+    fn payload(&self, target: &NotifierConfig) -> serde_json::Value {
+        let text = self.render(target);
+        match target.kind {
+            NotifierKind::Slack | NotifierKind::Mattermost => json!({ "text": text }),
+            NotifierKind::Webhook => json!({
+                "event": self.event.name(),
+                "instance": self.instance,
+                "device": self.device,
+                "message": self.message,
+                "text": text,
+            }),
+        }
+    }
+}
+
+impl Notifier {
+    /// a device is reported unreachable after `unreachable_after` failed accesses in a row
+    pub fn new(targets: Box<[NotifierConfig]>, unreachable_after: u32) -> Self {
+        Self {
+            targets,
+            unreachable_after,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            state: Mutex::default(),
+        }
+    }
+
+    /// posts the notification to all targets enabled for the event, failures are only logged
+    pub async fn send(&self, notification: Notification) {
+        for target in self
+            .targets
+            .iter()
+            .filter(|target| target.is_enabled(notification.event))
+        {
+            let result = self
+                .client
+                .post(&target.url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(notification.payload(target).to_string())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            // the url of a webhook carries its secret, it is left out of the error as well
+            if let Err(error) = result {
+                warn!(
+                    "Cannot post notification to {}: {}",
+                    target.display_name(),
+                    error.without_url()
+                );
+            }
+        }
+    }
+
+    /// counts a failed access, notifies when the device failed the configured times in a row
+    pub async fn device_failed(&self, instance: &str, device: &DeviceAccess, error: &str) {
+        if let Some(notification) = self.record_failure(instance, device, error) {
+            self.send(notification).await;
+        }
+    }
+    pub fn device_reachable(&self, instance: &str, device: &DeviceAccess) {
+        self.lock().failures.remove(&(instance.into(), device.id()));
+    }
+    /// notifies when a device starts to differ from the generated configuration or its ports
+    /// from the mac addresses in netbox
+    pub async fn drift_checked(
+        &self,
+        instance: &str,
+        device: &DeviceAccess,
+        plan: &ProvisionPlan,
+        mac_checks: &[MacCheck],
+    ) {
+        if let Some(notification) = self.record_drift(instance, device, plan, mac_checks) {
+            self.send(notification).await;
+        }
+    }
+    /// notifies about an applied plan, plans without any steps are not reported
+    pub async fn plan_applied(&self, instance: &str, device: &DeviceAccess, result: &ApplyResult) {
+        if let Some(notification) = self.record_apply(instance, device, result) {
+            self.send(notification).await;
+        }
+    }
+    /// devices of the instance currently differing from netbox
+    pub fn drifted_devices(&self, instance: &str) -> usize {
+        self.lock()
+            .drifted
+            .keys()
+            .filter(|(name, _)| name.as_ref() == instance)
+            .count()
+    }
+    /// devices of the instance already reported as unreachable and not accessed successfully since
+    pub fn unreachable_devices(&self, instance: &str) -> usize {
+        self.lock()
+            .failures
+            .iter()
+            .filter(|((name, _), failures)| {
+                name.as_ref() == instance && **failures >= self.unreachable_after
+            })
+            .count()
+    }
+    /// drifted devices and the applies since the last call
+    pub fn take_digest_events(&self) -> DigestEvents {
+        let mut state = self.lock();
+        let mut drifted = state.drifted.values().cloned().collect::<Vec<_>>();
+        drifted.sort_by(|a, b| (&a.instance, &a.device).cmp(&(&b.instance, &b.device)));
+        DigestEvents {
+            drifted,
+            failed_applies: std::mem::take(&mut state.failed_applies),
+            applied: std::mem::take(&mut state.applied),
+        }
+    }
+
+    fn record_apply(
+        &self,
+        instance: &str,
+        device: &DeviceAccess,
+        result: &ApplyResult,
+    ) -> Option<Notification> {
+        let notification = match result.error() {
+            Some(error) => Notification {
+                event: NotificationEvent::ApplyFailed,
+                instance: instance.into(),
+                device: device.name().into(),
+                message: format!("{} steps applied, {error}", result.applied()).into(),
+            },
+            None if result.applied() > 0 => match result.convergence_problem() {
+                Some(problem) => Notification {
+                    event: NotificationEvent::ApplyNotConverged,
+                    instance: instance.into(),
+                    device: device.name().into(),
+                    message: format!("{} steps applied, {problem}", result.applied()).into(),
+                },
+                None => Notification {
+                    event: NotificationEvent::ApplySucceeded,
+                    instance: instance.into(),
+                    device: device.name().into(),
+                    message: format!("{} steps applied", result.applied()).into(),
+                },
+            },
+            None => return None,
+        };
+        let mut state = self.lock();
+        if notification.event != NotificationEvent::ApplyFailed {
+            state.applied.insert((instance.into(), device.id()));
+        }
+        // a plan that did not converge shows up with the failed ones in the digest
+        if notification.event != NotificationEvent::ApplySucceeded {
+            if state.failed_applies.len() >= MAX_FAILED_APPLIES {
+                state.failed_applies.remove(0);
+            }
+            state.failed_applies.push(notification.clone());
+        }
+        Some(notification)
+    }
+
+    fn record_failure(
+        &self,
+        instance: &str,
+        device: &DeviceAccess,
+        error: &str,
+    ) -> Option<Notification> {
+        let mut state = self.lock();
+        let failures = state
+            .failures
+            .entry((instance.into(), device.id()))
+            .or_default();
+        *failures += 1;
+        (*failures == self.unreachable_after).then(|| Notification {
+            event: NotificationEvent::DeviceUnreachable,
+            instance: instance.into(),
+            device: device.name().into(),
+            message: format!("{failures} failed accesses, last: {error}").into(),
+        })
+    }
+    fn record_drift(
+        &self,
+        instance: &str,
+        device: &DeviceAccess,
+        plan: &ProvisionPlan,
+        mac_checks: &[MacCheck],
+    ) -> Option<Notification> {
+        let key = (instance.into(), device.id());
+        let mut state = self.lock();
+        let mismatched_ports = mac_checks
+            .iter()
+            .filter(|check| check.is_mismatch())
+            .map(MacCheck::interface)
+            .collect::<Vec<_>>();
+        if plan.is_empty() && mismatched_ports.is_empty() {
+            state.drifted.remove(&key);
+            return None;
+        }
+        let mut parts = Vec::new();
+        if !plan.is_empty() {
+            let categories = plan
+                .all_steps()
+                .map(|step| step.category())
+                .collect::<BTreeSet<_>>();
+            parts.push(format!(
+                "{} pending changes on {}",
+                plan.all_steps().count(),
+                categories.into_iter().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        if !mismatched_ports.is_empty() {
+            parts.push(format!(
+                "mac addresses differ on {}",
+                mismatched_ports.join(", ")
+            ));
+        }
+        let notification = Notification {
+            event: NotificationEvent::DriftDetected,
+            instance: instance.into(),
+            device: device.name().into(),
+            message: parts.join(", ").into(),
+        };
+        // keep the current changes for the digest, but notify only once
+        state
+            .drifted
+            .insert(key, notification.clone())
+            .is_none()
+            .then_some(notification)
+    }
+    fn lock(&self) -> std::sync::MutexGuard<'_, NotifierState> {
+        self.state.lock().expect("notifier state poisoned")
+    }
+}
+
+lazy_static! {
+    pub static ref NOTIFIER: Notifier = Notifier::new(
+        CONFIG.load().notifiers.clone(),
+        CONFIG.load().notify_unreachable_after()
+    );
+}
+
+#[cfg(test)]
+mod test;
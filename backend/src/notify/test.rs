@@ -0,0 +1,182 @@
+use crate::{
+    config::{DigestConfig, NotificationEvent, NotifierConfig, NotifierKind, SmtpSecurity},
+    notify::{DigestEvents, Notification, Notifier, digest::DigestReporter},
+    provisioning::plan::{ApplyResult, PlanStep, ProvisionPlan},
+    topology::{
+        DeviceId, TopologyHolder,
+        access::device::DeviceAccess,
+        snapshot::{FixtureFormat, parse_topology},
+    },
+};
+use std::{collections::HashMap, time::Duration};
+
+async fn fixture_device() -> DeviceAccess {
+    let topology = parse_topology(
+        include_bytes!("../topology/fixtures/single-router.yaml"),
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid fixture");
+    TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found")
+}
+
+fn webhook(events: Option<Box<[NotificationEvent]>>) -> NotifierConfig {
+    NotifierConfig {
+        name: None,
+        kind: NotifierKind::Webhook,
+        url: "http://localhost/hook".into(),
+        events,
+        templates: HashMap::new(),
+    }
+}
+
+#[test]
+fn test_render_template() {
+    let notification = Notification {
+        event: NotificationEvent::ApplyFailed,
+        instance: "default".into(),
+        device: "router".into(),
+        message: "Step 2 on ip/address failed".into(),
+    };
+    let mut target = webhook(None);
+    assert_eq!(
+        notification.render(&target),
+        "Applying plan on router failed: Step 2 on ip/address failed"
+    );
+    target.templates.insert(
+        NotificationEvent::ApplyFailed,
+        "[{{instance}}] {{event}} {{device}}".into(),
+    );
+    assert_eq!(
+        notification.render(&target),
+        "[default] apply-failed router"
+    );
+}
+
+#[test]
+fn test_event_enablement() {
+    assert!(webhook(None).is_enabled(NotificationEvent::DriftDetected));
+    let target = webhook(Some(Box::new([NotificationEvent::ApplyFailed])));
+    assert!(target.is_enabled(NotificationEvent::ApplyFailed));
+    assert!(!target.is_enabled(NotificationEvent::ApplySucceeded));
+}
+
+#[tokio::test]
+async fn test_unreachable_after_failures() {
+    let device = fixture_device().await;
+    let notifier = Notifier::new(Box::new([]), 3);
+    assert_eq!(notifier.record_failure("default", &device, "timeout"), None);
+    assert_eq!(notifier.record_failure("default", &device, "timeout"), None);
+    let notification = notifier
+        .record_failure("default", &device, "timeout")
+        .expect("no notification after 3 failures");
+    assert_eq!(notification.event, NotificationEvent::DeviceUnreachable);
+    assert_eq!(notification.device.as_ref(), "router");
+    // reported once per outage
+    assert_eq!(notifier.record_failure("default", &device, "timeout"), None);
+    notifier.device_reachable("default", &device);
+    assert_eq!(notifier.record_failure("default", &device, "timeout"), None);
+    // the same device id in another instance is counted separately
+    assert_eq!(notifier.record_failure("other", &device, "timeout"), None);
+}
+
+#[tokio::test]
+async fn test_apply_not_converged() {
+    let device = fixture_device().await;
+    let notifier = Notifier::new(Box::new([]), 3);
+    let mut result = ApplyResult::new(ProvisionPlan::default(), 2, None);
+    result.record_verification(Ok(Box::new([PlanStep::new(
+        0,
+        "ip/address",
+        "/ip/address add interface=vlan10",
+    )])));
+    let notification = notifier
+        .record_apply("default", &device, &result)
+        .expect("no notification for applied steps");
+    assert_eq!(notification.event, NotificationEvent::ApplyNotConverged);
+    assert_eq!(
+        notification.message.as_ref(),
+        "2 steps applied, Not converged, 1 steps left after apply"
+    );
+
+    let mut result = ApplyResult::new(ProvisionPlan::default(), 2, None);
+    result.record_verification(Ok(Box::default()));
+    let notification = notifier
+        .record_apply("default", &device, &result)
+        .expect("no notification for applied steps");
+    assert_eq!(notification.event, NotificationEvent::ApplySucceeded);
+
+    // the device was applied, the plan that did not converge is listed with the failed ones
+    let events = notifier.take_digest_events();
+    assert!(
+        events
+            .applied
+            .contains(&(Box::<str>::from("default"), DeviceId(1)))
+    );
+    assert_eq!(
+        events
+            .failed_applies
+            .iter()
+            .map(|n| n.event)
+            .collect::<Vec<_>>(),
+        vec![NotificationEvent::ApplyNotConverged]
+    );
+}
+
+#[test]
+fn test_digest_new_devices() {
+    let config: DigestConfig = serde_yaml::from_str(
+        "smtp_host: localhost\nsmtp_security: none\nfrom: provisioner@example.com\nto: [noc@example.com]",
+    )
+    .expect("invalid digest config");
+    assert_eq!(config.smtp_security, SmtpSecurity::None);
+    assert_eq!(config.interval(), Duration::from_secs(86400));
+    let mut reporter = DigestReporter::new(config);
+    let router = (Box::<str>::from("default"), DeviceId(1));
+    let switch = (Box::<str>::from("default"), DeviceId(2));
+    let ap = (Box::<str>::from("default"), DeviceId(3));
+    // the devices present at startup are not new
+    assert!(
+        reporter
+            .collect(
+                vec![(router.clone(), "router".into())],
+                DigestEvents::default()
+            )
+            .is_none()
+    );
+    let digest = reporter
+        .collect(
+            vec![
+                (router.clone(), "router".into()),
+                (switch.clone(), "switch".into()),
+                (ap.clone(), "ap".into()),
+            ],
+            DigestEvents {
+                applied: [ap].into_iter().collect(),
+                ..DigestEvents::default()
+            },
+        )
+        .expect("no digest after the first run");
+    assert_eq!(
+        digest.new_devices,
+        vec![("default".into(), "switch".into())]
+    );
+    assert_eq!(digest.render(), "New devices (1):\n  default/switch\n");
+    let digest = reporter
+        .collect(vec![(switch, "switch".into())], DigestEvents::default())
+        .expect("no digest");
+    assert!(digest.new_devices.is_empty());
+}
+
+#[test]
+fn test_digest_config_hides_password() {
+    let config: DigestConfig = serde_yaml::from_str(
+        "smtp_host: localhost\nsmtp_user: provisioner\nsmtp_password: sm7p-s3cret\nfrom: provisioner@example.com\nto: [noc@example.com]",
+    )
+    .expect("invalid digest config");
+    let debug = format!("{config:?}");
+    assert!(debug.contains("provisioner"));
+    assert!(!debug.contains("sm7p-s3cret"));
+}
@@ -0,0 +1,179 @@
+//! Access of the authenticated users, by their subject or by the claims of their token.
+use crate::{
+    config::{AccessRule, Settings},
+    context::AccessLevel,
+    topology::access::{
+        cable::CablePortAccess, connections::CablePath, device::DeviceAccess,
+        interface::InterfaceAccess,
+    },
+};
+use async_graphql::Context;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// Decides the access level of a user.
+///
+/// Subjects listed in `auth_admins` or `auth_users` are granted directly, the rules grant
+/// access by claim values like the groups or roles managed in the issuer. The highest level
+/// of all matching entries is granted.
+pub struct AccessPolicy<'a> {
+    users: &'a [Box<str>],
+    admins: &'a [Box<str>],
+    rules: &'a [AccessRule],
+    tenant_claim: Option<&'a str>,
+    user_tenants: &'a HashMap<Box<str>, Box<[Box<str>]>>,
+}
+
+/// Netbox tenants whose devices a user sees
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum TenantScope {
+    /// admins, and every user if no tenants are mapped to the users
+    #[default]
+    All,
+    /// slugs of the visible tenants, devices without a tenant are hidden
+    Tenants(HashSet<Box<str>>),
+}
+
+impl<'a> AccessPolicy<'a> {
+    pub fn new(settings: &'a Settings) -> Self {
+        Self {
+            users: &settings.auth_users,
+            admins: &settings.auth_admins,
+            rules: &settings.auth_rules,
+            tenant_claim: settings.auth_tenant_claim.as_deref(),
+            user_tenants: &settings.auth_user_tenants,
+        }
+    }
+
+    /// `None` if the user is not allowed at all
+    pub fn access_level(&self, subject: Option<&str>, claims: &Value) -> Option<AccessLevel> {
+        let listed =
+            |list: &[Box<str>]| subject.is_some_and(|sub| list.iter().any(|id| **id == *sub));
+        let by_subject = if listed(self.admins) {
+            Some(AccessLevel::Admin)
+        } else if listed(self.users) {
+            Some(AccessLevel::User)
+        } else {
+            None
+        };
+        self.rules
+            .iter()
+            .filter(|rule| rule_matches(rule, claims))
+            .map(|rule| rule.level)
+            .chain(by_subject)
+            .max()
+    }
+}
+
+impl AccessPolicy<'_> {
+    /// tenants from the tenant claim of the token and from the tenants configured for the subject
+    pub fn tenant_scope(
+        &self,
+        subject: Option<&str>,
+        claims: &Value,
+        level: AccessLevel,
+    ) -> TenantScope {
+        if level == AccessLevel::Admin
+            || (self.tenant_claim.is_none() && self.user_tenants.is_empty())
+        {
+            return TenantScope::All;
+        }
+        let configured = subject
+            .and_then(|subject| self.user_tenants.get(subject))
+            .into_iter()
+            .flatten()
+            .cloned();
+        let claimed = self
+            .tenant_claim
+            .and_then(|claim| claim_value(claims, claim))
+            .into_iter()
+            .flat_map(|value| match value {
+                Value::Array(values) => values.iter().filter_map(Value::as_str).collect(),
+                value => value.as_str().into_iter().collect::<Vec<_>>(),
+            })
+            .map(Box::from);
+        TenantScope::Tenants(configured.chain(claimed).collect())
+    }
+}
+
+static ALL_TENANTS: TenantScope = TenantScope::All;
+
+impl TenantScope {
+    /// scope of the current request, requests without a scope see all devices
+    pub fn of<'a>(ctx: &Context<'a>) -> &'a TenantScope {
+        ctx.data_opt::<TenantScope>().unwrap_or(&ALL_TENANTS)
+    }
+    pub fn allows(&self, device: &DeviceAccess) -> bool {
+        self.allows_tenant(device.tenant())
+    }
+    pub fn allows_tenant(&self, tenant: Option<&str>) -> bool {
+        match self {
+            TenantScope::All => true,
+            TenantScope::Tenants(tenants) => tenant.is_some_and(|tenant| tenants.contains(tenant)),
+        }
+    }
+    /// the devices of the visible tenants
+    pub fn filter(&self, devices: impl IntoIterator<Item = DeviceAccess>) -> Box<[DeviceAccess]> {
+        devices
+            .into_iter()
+            .filter(|device| self.allows(device))
+            .collect()
+    }
+    /// interfaces of devices outside the scope are hidden, the passive ports of patch panels
+    /// are shared infrastructure and stay visible
+    pub fn allows_port(&self, port: &CablePortAccess) -> bool {
+        match port {
+            CablePortAccess::Interface(interface) => {
+                interface.device().is_none_or(|device| self.allows(&device))
+            }
+            CablePortAccess::FrontPort(_) | CablePortAccess::RearPort(_) => true,
+        }
+    }
+    /// the interfaces on devices of the visible tenants
+    pub fn filter_interfaces(
+        &self,
+        interfaces: impl IntoIterator<Item = InterfaceAccess>,
+    ) -> Box<[InterfaceAccess]> {
+        interfaces
+            .into_iter()
+            .filter(|interface| self.allows_port(&CablePortAccess::Interface(interface.clone())))
+            .collect()
+    }
+    /// the paths not reaching an interface outside the scope
+    pub fn filter_paths(&self, paths: impl IntoIterator<Item = CablePath>) -> Box<[CablePath]> {
+        paths
+            .into_iter()
+            .filter(|path| {
+                self.allows_port(&path.start_port)
+                    && path.end_port.iter().all(|port| self.allows_port(port))
+                    && path.cable_segments.iter().all(|segment| {
+                        self.allows_port(&segment.near) && self.allows_port(&segment.far)
+                    })
+            })
+            .collect()
+    }
+}
+
+/// value of a claim, nested claims are separated by dots
+fn claim_value<'v>(claims: &'v Value, claim: &str) -> Option<&'v Value> {
+    claim
+        .split('.')
+        .try_fold(claims, |value, key| value.get(key))
+}
+
+fn rule_matches(rule: &AccessRule, claims: &Value) -> bool {
+    let claim = claim_value(claims, &rule.claim);
+    let matches = |value: &Value| {
+        value
+            .as_str()
+            .is_some_and(|value| rule.values.iter().any(|v| **v == *value))
+    };
+    match claim {
+        Some(Value::Array(values)) => values.iter().any(matches),
+        Some(value) => matches(value),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test;
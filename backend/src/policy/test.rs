@@ -0,0 +1,128 @@
+use crate::{
+    config::AccessRule,
+    context::AccessLevel,
+    policy::{AccessPolicy, TenantScope},
+    topology::{
+        DeviceId, TopologyHolder,
+        snapshot::{FixtureFormat, parse_topology},
+    },
+};
+use serde_json::json;
+use std::collections::HashMap;
+
+#[test]
+fn test_access_policy() {
+    let users: Box<[Box<str>]> = Box::new(["alice".into()]);
+    let admins: Box<[Box<str>]> = Box::new(["root".into()]);
+    let rules: Box<[AccessRule]> = serde_yaml::from_str(
+        r#"
+- claim: groups
+  values: [network]
+- claim: realm_access.roles
+  values: [provisioning-admin]
+  level: admin
+"#,
+    )
+    .expect("invalid rules");
+    let user_tenants = HashMap::new();
+    let policy = AccessPolicy {
+        users: &users,
+        admins: &admins,
+        rules: &rules,
+        tenant_claim: None,
+        user_tenants: &user_tenants,
+    };
+    let no_claims = json!({});
+    assert_eq!(
+        policy.access_level(Some("alice"), &no_claims),
+        Some(AccessLevel::User)
+    );
+    assert_eq!(
+        policy.access_level(Some("root"), &no_claims),
+        Some(AccessLevel::Admin)
+    );
+    assert_eq!(policy.access_level(Some("bob"), &no_claims), None);
+    assert_eq!(policy.access_level(None, &no_claims), None);
+    assert_eq!(
+        policy.access_level(Some("bob"), &json!({"groups": ["office", "network"]})),
+        Some(AccessLevel::User)
+    );
+    assert_eq!(
+        policy.access_level(Some("bob"), &json!({"groups": "network"})),
+        Some(AccessLevel::User)
+    );
+    // the highest level of all matching entries is granted
+    assert_eq!(
+        policy.access_level(
+            Some("alice"),
+            &json!({"realm_access": {"roles": ["provisioning-admin"]}})
+        ),
+        Some(AccessLevel::Admin)
+    );
+    assert_eq!(
+        policy.access_level(Some("bob"), &json!({"realm_access": {"roles": "network"}})),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_tenant_scope() {
+    let users: Box<[Box<str>]> = Box::new(["alice".into(), "bob".into()]);
+    let user_tenants = HashMap::from([("alice".into(), Box::from(["branch".into()]))]);
+    let policy = AccessPolicy {
+        users: &users,
+        admins: &[],
+        rules: &[],
+        tenant_claim: Some("groups"),
+        user_tenants: &user_tenants,
+    };
+    let claims = json!({"groups": ["office", "unrelated"]});
+    let alice = policy.tenant_scope(Some("alice"), &claims, AccessLevel::User);
+    assert_eq!(
+        alice,
+        TenantScope::Tenants(["branch".into(), "office".into(), "unrelated".into()].into())
+    );
+    assert_eq!(
+        policy.tenant_scope(Some("alice"), &claims, AccessLevel::Admin),
+        TenantScope::All
+    );
+
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: office-router
+    tenant: office
+  2:
+    name: branch-router
+    tenant: branch
+  3:
+    name: core-router
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid fixture");
+    let holder = TopologyHolder::offline(topology);
+    let bob = policy.tenant_scope(Some("bob"), &json!({"groups": "branch"}), AccessLevel::User);
+    let mut visible = Vec::new();
+    for id in 1..=3 {
+        let device = holder
+            .devices_by_id(DeviceId(id))
+            .await
+            .expect("device not found");
+        visible.push((
+            device.name().to_string(),
+            alice.allows(&device),
+            bob.allows(&device),
+            TenantScope::All.allows(&device),
+        ));
+    }
+    assert_eq!(
+        visible,
+        vec![
+            ("office-router".to_string(), true, false, true),
+            ("branch-router".to_string(), true, true, true),
+            ("core-router".to_string(), false, false, true),
+        ]
+    );
+}
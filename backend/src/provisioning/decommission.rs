@@ -0,0 +1,308 @@
+use crate::{
+    Error,
+    config::NetboxInstance,
+    device::{
+        AccessibleDevice,
+        command::{run_command, run_script as run_checked_script},
+    },
+    netbox::{JournalKind, create_journal_entry, update_device_status},
+    provisioning::{
+        plan::{ApplyResult, PlanOptions},
+        rollout::apply_device,
+        scope::ApplyScope,
+    },
+    topology::{
+        DeviceStatus, InterfaceId,
+        access::{AccessTopology, device::DeviceAccess},
+    },
+};
+use async_graphql::{Enum, SimpleObject};
+use log::warn;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write,
+};
+
+/// Only the vxlans with their vteps and the CAPsMAN setup are updated on the peers of a
+/// decommissioned device
+const PEER_SCOPE: [&str; 2] = ["interface/vxlan", "caps-man"];
+
+/// How a device in status `decommissioning` is taken out of operation
+#[derive(Enum, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DecommissionMode {
+    /// disables all interfaces except the ones on the management path, the device stays
+    /// reachable
+    #[default]
+    Disable,
+    /// resets the configuration without defaults, the device is unreachable afterward
+    Wipe,
+    /// resets the device to the factory defaults
+    FactoryReset,
+    /// only the peers and netbox are updated, the device itself is not changed
+    Keep,
+}
+
+/// Update of a device sharing a vxlan or a wlan group with the decommissioned device
+#[derive(Debug, SimpleObject)]
+pub struct PeerUpdate {
+    device_id: u32,
+    device_name: Box<str>,
+    result: Option<ApplyResult>,
+    error: Option<Box<str>>,
+}
+
+/// Outcome of each phase of a decommission, later phases run even if earlier ones failed
+#[derive(Debug, SimpleObject)]
+pub struct DecommissionResult {
+    /// the peers with the device removed from their vtep lists and wlan groups
+    peers: Vec<PeerUpdate>,
+    /// error running the decommission script on the device
+    device_error: Option<Box<str>>,
+    /// the journal entry was written and the netbox device set offline, the status is kept if
+    /// the device was reachable but could not be changed
+    netbox_updated: bool,
+    netbox_error: Option<Box<str>>,
+}
+
+/// Script taking a device out of operation, only devices in status `decommissioning` qualify
+pub fn decommission_script(
+    device: &DeviceAccess,
+    mode: DecommissionMode,
+) -> Result<Box<str>, Error> {
+    let status = device.status();
+    if status != DeviceStatus::Decommissioning {
+        return Err(Error::DeviceStatus(status));
+    }
+    Ok(match mode {
+        DecommissionMode::Disable => {
+            let management_path = management_path(device);
+            let mut script = String::new();
+            for interface in device.interfaces() {
+                if !management_path.contains(&interface.id()) {
+                    writeln!(
+                        script,
+                        "/interface set [find name=\"{}\"] disabled=yes",
+                        interface.name()
+                    )?;
+                }
+            }
+            script.into_boxed_str()
+        }
+        DecommissionMode::Wipe => {
+            "/system reset-configuration no-defaults=yes skip-backup=yes".into()
+        }
+        DecommissionMode::FactoryReset => "/system reset-configuration skip-backup=yes".into(),
+        DecommissionMode::Keep => Box::default(),
+    })
+}
+
+/// Interfaces the device is managed over: the management interfaces and the interfaces of the
+/// management addresses, their bridges and the bridge ports carrying their vlans. All ports of
+/// the bridge are kept if the addresses are on untagged interfaces.
+fn management_path(device: &DeviceAccess) -> HashSet<InterfaceId> {
+    let addressed = device
+        .management_interfaces()
+        .into_iter()
+        .chain(
+            [
+                device.oob_ip(),
+                device.primary_ip_v4(),
+                device.primary_ip_v6(),
+            ]
+            .into_iter()
+            .flatten()
+            .filter_map(|ip| ip.interface()),
+        )
+        .collect::<Vec<_>>();
+    let vlans = addressed
+        .iter()
+        .filter_map(|interface| interface.untagged_vlan())
+        .map(|vlan| vlan.id())
+        .collect::<HashSet<_>>();
+    let bridges = addressed
+        .iter()
+        .map(|interface| interface.bridge().unwrap_or_else(|| interface.clone()).id())
+        .collect::<HashSet<_>>();
+    let mut path = addressed
+        .iter()
+        .map(|interface| interface.id())
+        .chain(bridges.iter().copied())
+        .collect::<HashSet<_>>();
+    for interface in device.interfaces() {
+        let Some(bridge) = interface.bridge() else {
+            continue;
+        };
+        let carries_vlan = vlans.is_empty()
+            || interface
+                .untagged_vlan()
+                .into_iter()
+                .chain(interface.tagged_vlans())
+                .any(|vlan| vlans.contains(&vlan.id()));
+        if bridges.contains(&bridge.id()) && carries_vlan {
+            path.insert(interface.id());
+        }
+    }
+    path
+}
+
+/// Reverses the provisioning of a device in status `decommissioning`
+///
+/// The device is removed from the vtep lists and wlan groups of its peers first, so they stop
+/// forwarding to it. Then the device is disabled or reset and the netbox device is set offline,
+/// if the netbox of the device is known. A device that is reachable but fails the script keeps
+/// its status, so it is not mistaken for a device taken out of operation. The caller has to hold
+/// the lock of the device.
+pub async fn decommission_device(
+    instance: &str,
+    netbox: Option<&NetboxInstance>,
+    device: &DeviceAccess,
+    mode: DecommissionMode,
+    user: Option<&str>,
+) -> Result<DecommissionResult, Error> {
+    let script = decommission_script(device, mode)?;
+    let mut peers = Vec::new();
+    for peer in peer_devices(device) {
+        peers.push(update_peer(instance, &peer, user).await);
+    }
+    let (device_error, unreachable) = if script.is_empty() {
+        (None, false)
+    } else {
+        match device.connect(None, None).await {
+            Ok(client) => (run_script(&client, mode, &script).await.err(), false),
+            Err(error) => {
+                let unreachable = is_unreachable(&error);
+                (Some(error), unreachable)
+            }
+        }
+    };
+    let device_error = device_error.map(|error| error.to_string().into_boxed_str());
+    let set_offline = device_error.is_none() || unreachable;
+    let netbox_error = match netbox {
+        Some(netbox) => flag_netbox(
+            netbox,
+            device,
+            &peers,
+            device_error.as_deref(),
+            set_offline,
+            user,
+        )
+        .await
+        .err(),
+        None => Some("Netbox instance unknown".into()),
+    };
+    Ok(DecommissionResult {
+        peers,
+        device_error,
+        netbox_updated: netbox_error.is_none(),
+        netbox_error,
+    })
+}
+
+/// devices sharing a vxlan with the device, its wlan controller and the aps it controls
+pub fn peer_devices(device: &DeviceAccess) -> Vec<DeviceAccess> {
+    let mut peers = BTreeMap::new();
+    let wlan_peers = device
+        .wlan_controller_of()
+        .map(|group| group.aps())
+        .unwrap_or_default()
+        .into_iter()
+        .chain(device.wlan_ap_of().and_then(|group| group.controller()));
+    for peer in device.vxlan_peers().into_iter().chain(wlan_peers) {
+        if peer.id() != device.id() {
+            peers.insert(peer.id(), peer);
+        }
+    }
+    peers.into_values().collect()
+}
+
+/// the connection failed because the device is not reachable, not because of its credentials
+/// or configuration
+fn is_unreachable(error: &Error) -> bool {
+    matches!(error, Error::MikrotikApi(_) | Error::Ping(_))
+}
+
+async fn update_peer(instance: &str, peer: &DeviceAccess, user: Option<&str>) -> PeerUpdate {
+    let (result, error) = match apply_to_peer(instance, peer, user).await {
+        Ok(result) => (Some(result), None),
+        Err(error) => {
+            warn!("Cannot update vteps of {}: {error}", peer.name());
+            (None, Some(error.to_string().into_boxed_str()))
+        }
+    };
+    PeerUpdate {
+        device_id: peer.id().0,
+        device_name: peer.name().into(),
+        result,
+        error,
+    }
+}
+
+async fn apply_to_peer(
+    instance: &str,
+    peer: &DeviceAccess,
+    user: Option<&str>,
+) -> Result<ApplyResult, Error> {
+    let scope = ApplyScope {
+        only: PEER_SCOPE.iter().copied().map(Box::from).collect(),
+        skip: vec![],
+    };
+    apply_device(
+        instance,
+        peer,
+        &PlanOptions::for_device(peer, Some(scope), true),
+        user,
+    )
+    .await
+}
+
+/// A reset is started as background job, the device drops the connection while resetting.
+/// Disabling waits for the script and fails if one of its commands fails.
+async fn run_script(
+    device: &AccessibleDevice,
+    mode: DecommissionMode,
+    script: &str,
+) -> Result<(), Error> {
+    if mode == DecommissionMode::Disable {
+        return run_checked_script(device.client(), script).await;
+    }
+    run_command(device.client(), "/execute", &[("script", script)]).await?;
+    Ok(())
+}
+
+async fn flag_netbox(
+    netbox: &NetboxInstance,
+    device: &DeviceAccess,
+    peers: &[PeerUpdate],
+    device_error: Option<&str>,
+    set_offline: bool,
+    user: Option<&str>,
+) -> Result<(), Box<str>> {
+    let updated = peers.iter().filter(|peer| peer.error.is_none()).count();
+    let mut comment = format!(
+        "provisioner decommissioned the device, updated {updated} of {} peers",
+        peers.len()
+    );
+    if let Some(user) = user {
+        comment.push_str(&format!("\n\nstarted by {user}"));
+    }
+    let kind = match device_error {
+        None => JournalKind::Success,
+        Some(error) => {
+            comment.push_str(&format!("\n\ndevice not changed: {error}"));
+            JournalKind::Warning
+        }
+    };
+    let id = device.id().0;
+    let result = async {
+        create_journal_entry(netbox, id, kind, &comment).await?;
+        if set_offline {
+            update_device_status(netbox, id, DeviceStatus::Offline).await?;
+        }
+        Ok(())
+    }
+    .await;
+    result.map_err(|error| {
+        warn!("Cannot flag netbox device {}: {error}", device.name());
+        error.to_string().into_boxed_str()
+    })
+}
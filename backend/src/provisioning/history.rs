@@ -0,0 +1,163 @@
+use crate::{
+    Error,
+    provisioning::plan::{ApplyResult, PlanStep},
+    topology::{DeviceId, access::device::DeviceAccess},
+};
+use async_graphql::{Enum, SimpleObject};
+use lazy_static::lazy_static;
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Runs kept in the history, older runs are dropped
+const HISTORY_SIZE: usize = 500;
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RunStatus {
+    Succeeded,
+    Failed,
+}
+
+/// A plan applied to a device, or the attempt to
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ProvisioningRun {
+    id: u32,
+    instance: Box<str>,
+    device_id: u32,
+    device_name: Box<str>,
+    /// user who started the run
+    user: Option<Box<str>>,
+    /// seconds since the unix epoch
+    started_at: u64,
+    finished_at: u64,
+    status: RunStatus,
+    /// steps of the plan applied successfully, in the order they were applied
+    applied_steps: Vec<PlanStep>,
+    /// steps not applied because of the error
+    pending_steps: Vec<PlanStep>,
+    error: Option<Box<str>>,
+    /// netbox tenant of the device, to show the run only to the users of the tenant
+    #[graphql(skip)]
+    tenant: Option<Box<str>>,
+}
+
+impl ProvisioningRun {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+    pub fn device(&self) -> DeviceId {
+        DeviceId(self.device_id)
+    }
+    pub fn status(&self) -> RunStatus {
+        self.status
+    }
+    pub fn tenant(&self) -> Option<&str> {
+        self.tenant.as_deref()
+    }
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Which runs are listed
+#[derive(Debug, Clone, Default)]
+pub struct RunFilter<'a> {
+    pub instance: Option<&'a str>,
+    pub device: Option<DeviceId>,
+    pub status: Option<RunStatus>,
+}
+
+#[derive(Default)]
+struct HistoryState {
+    last_id: u32,
+    runs: VecDeque<ProvisioningRun>,
+}
+
+/// Latest provisioning runs of the process, the history is lost on restart
+#[derive(Default)]
+pub struct RunHistory {
+    state: Mutex<HistoryState>,
+}
+
+impl RunHistory {
+    /// records the outcome of applying a plan or the error preventing it
+    pub fn record(
+        &self,
+        instance: &str,
+        device: &DeviceAccess,
+        user: Option<&str>,
+        started_at: SystemTime,
+        result: &Result<ApplyResult, Error>,
+    ) -> ProvisioningRun {
+        let (applied_steps, pending_steps, error) = match result {
+            Ok(result) => {
+                let steps = result.plan().all_steps().cloned().collect::<Vec<_>>();
+                let (applied, pending) = steps.split_at(result.applied() as usize);
+                (
+                    applied.to_vec(),
+                    pending.to_vec(),
+                    result.error().map(Box::from),
+                )
+            }
+            Err(error) => (Vec::new(), Vec::new(), Some(error.to_string().into())),
+        };
+        let mut state = self.lock();
+        state.last_id += 1;
+        let run = ProvisioningRun {
+            id: state.last_id,
+            instance: instance.into(),
+            device_id: device.id().0,
+            device_name: device.name().into(),
+            user: user.map(Box::from),
+            started_at: unix_seconds(started_at),
+            finished_at: unix_seconds(SystemTime::now()),
+            status: if error.is_some() {
+                RunStatus::Failed
+            } else {
+                RunStatus::Succeeded
+            },
+            applied_steps,
+            pending_steps,
+            error,
+            tenant: device.tenant().map(Box::from),
+        };
+        if state.runs.len() >= HISTORY_SIZE {
+            state.runs.pop_front();
+        }
+        state.runs.push_back(run.clone());
+        run
+    }
+    /// matching runs, the latest first
+    pub fn list(&self, filter: &RunFilter) -> Vec<ProvisioningRun> {
+        self.lock()
+            .runs
+            .iter()
+            .rev()
+            .filter(|run| filter.instance.is_none_or(|i| run.instance.as_ref() == i))
+            .filter(|run| filter.device.is_none_or(|d| run.device() == d))
+            .filter(|run| filter.status.is_none_or(|s| run.status == s))
+            .cloned()
+            .collect()
+    }
+    pub fn get(&self, id: u32) -> Option<ProvisioningRun> {
+        self.lock().runs.iter().find(|run| run.id == id).cloned()
+    }
+    fn lock(&self) -> MutexGuard<'_, HistoryState> {
+        self.state.lock().expect("run history poisoned")
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+lazy_static! {
+    pub static ref RUN_HISTORY: RunHistory = RunHistory::default();
+}
@@ -0,0 +1,61 @@
+use crate::{
+    config::NetboxInstance,
+    netbox::{JournalKind, create_journal_entry},
+    provisioning::plan::{ApplyResult, PlanStep},
+    topology::access::device::DeviceAccess,
+};
+use log::warn;
+use std::collections::BTreeMap;
+
+/// Comment of the journal entry of an apply, none if nothing was changed on the device
+///
+/// A partially applied plan is journaled as warning together with the error of the failed step.
+pub fn journal_comment<'a>(
+    applied: impl IntoIterator<Item = &'a PlanStep>,
+    error: Option<&str>,
+    user: Option<&str>,
+) -> Option<(JournalKind, String)> {
+    let mut categories = BTreeMap::<&str, u32>::new();
+    for step in applied {
+        *categories.entry(step.category()).or_default() += 1;
+    }
+    let count = categories.values().sum::<u32>();
+    if count == 0 {
+        return None;
+    }
+    let changes = categories
+        .iter()
+        .map(|(category, count)| format!("{category} ({count})"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut comment = format!("provisioner applied {count} changes: {changes}");
+    if let Some(user) = user {
+        comment.push_str(&format!("\n\nstarted by {user}"));
+    }
+    Some(match error {
+        None => (JournalKind::Success, comment),
+        Some(error) => {
+            comment.push_str(&format!("\n\nstopped by an error: {error}"));
+            (JournalKind::Warning, comment)
+        }
+    })
+}
+
+/// writes the journal entry of an apply to the netbox device, failures are only logged
+pub async fn write_journal(
+    instance: &NetboxInstance,
+    device: &DeviceAccess,
+    user: Option<&str>,
+    result: &ApplyResult,
+) {
+    let applied = result.plan().all_steps().take(result.applied() as usize);
+    let Some((kind, comment)) = journal_comment(applied, result.error(), user) else {
+        return;
+    };
+    if let Err(error) = create_journal_entry(instance, device.id().0, kind, &comment).await {
+        warn!(
+            "Cannot write netbox journal entry of {}: {error}",
+            device.name()
+        );
+    }
+}
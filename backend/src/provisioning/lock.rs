@@ -0,0 +1,48 @@
+use crate::{Error, topology::DeviceId};
+use lazy_static::lazy_static;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Key of a device, the ids are only unique within a netbox instance
+type LockKey = (Box<str>, DeviceId);
+
+/// One lock per device, held from fetching the configuration until the plan is applied.
+///
+/// The locks are local to the process, concurrent applies from several provisioner processes
+/// against the same device are not prevented.
+#[derive(Default)]
+pub struct DeviceLocks {
+    locks: Mutex<HashMap<LockKey, Arc<AsyncMutex<()>>>>,
+}
+
+/// Held while a device is provisioned, releases the lock when dropped
+pub struct DeviceLockGuard {
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl DeviceLocks {
+    /// takes the lock of the device, fails with [`Error::DeviceBusy`] instead of waiting
+    pub fn try_lock(&self, instance: &str, device: DeviceId) -> Result<DeviceLockGuard, Error> {
+        let lock = {
+            let mut locks = self.locks.lock().expect("device locks poisoned");
+            // forget locks nobody holds, so the map does not grow with every device ever locked
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            locks.entry((instance.into(), device)).or_default().clone()
+        };
+        lock.try_lock_owned()
+            .map(|guard| DeviceLockGuard { _guard: guard })
+            .map_err(|_| Error::DeviceBusy(device.0))
+    }
+}
+
+lazy_static! {
+    static ref DEVICE_LOCKS: DeviceLocks = DeviceLocks::default();
+}
+
+/// takes the process wide lock of the device
+pub fn lock_device(instance: &str, device: DeviceId) -> Result<DeviceLockGuard, Error> {
+    DEVICE_LOCKS.try_lock(instance, device)
+}
@@ -0,0 +1,131 @@
+use crate::{
+    Error,
+    config::NetboxInstance,
+    netbox::{create_interface_address, create_virtual_interface},
+    topology::{
+        DeviceId, TopologyHolder, VrfId,
+        access::{AccessTopology, device::DeviceAccess, interface::InterfaceAccess},
+    },
+};
+use async_graphql::SimpleObject;
+use ipnet::IpNet;
+use std::net::{IpAddr, Ipv4Addr};
+use tokio::sync::Mutex;
+
+/// Name of the loopback interface in netbox, the generation takes the router id from it
+pub const LOOPBACK_INTERFACE: &str = "lo";
+
+/// One allocation at a time, the next free addresses are only known once the topology is
+/// fetched again after the previous allocation
+static ALLOCATION: Mutex<()> = Mutex::const_new(());
+
+/// Loopback addresses written to netbox for a device
+#[derive(Debug, SimpleObject)]
+pub struct LoopbackAllocation {
+    /// the loopback interface was missing and created
+    interface_created: bool,
+    addresses: Box<[Box<str>]>,
+}
+
+/// The next free host address of each address family the loopback interface of the device has
+/// no address of yet, taken from the prefixes with role `loopback`, with the vrf of the prefix
+pub fn next_loopback_addresses(device: &DeviceAccess) -> Box<[(IpNet, Option<VrfId>)]> {
+    let assigned = loopback_interface(device)
+        .map(|interface| interface.ips())
+        .unwrap_or_default();
+    let has_family = |ipv4: bool| {
+        assigned
+            .iter()
+            .filter_map(|address| address.net())
+            .any(|net| matches!(net, IpNet::V4(_)) == ipv4)
+    };
+    let mut prefixes = device
+        .topology()
+        .list_ip_prefixes()
+        .filter(|prefix| prefix.is_loopback())
+        .collect::<Vec<_>>();
+    prefixes.sort_by_key(|prefix| prefix.prefix());
+    let mut result = Vec::new();
+    for ipv4 in [true, false] {
+        if has_family(ipv4) {
+            continue;
+        }
+        let next = prefixes
+            .iter()
+            .filter(|prefix| matches!(prefix.prefix(), Some(IpNet::V4(_))) == ipv4)
+            .find_map(|prefix| {
+                prefix
+                    .next_free_host()
+                    .map(|host| (host, prefix.vrf().map(|vrf| vrf.id())))
+            });
+        result.extend(next);
+    }
+    result.into_boxed_slice()
+}
+
+fn loopback_interface(device: &DeviceAccess) -> Option<InterfaceAccess> {
+    device
+        .interfaces()
+        .into_iter()
+        .find(|interface| interface.name() == LOOPBACK_INTERFACE)
+}
+
+/// the ipv4 address of the loopback interface, the primary ipv4 address of devices without one
+pub fn router_id(device: &DeviceAccess) -> Option<Ipv4Addr> {
+    let loopback = loopback_interface(device)
+        .into_iter()
+        .flat_map(|interface| interface.ips())
+        .find_map(|address| match address.addr() {
+            Some(IpAddr::V4(ip)) => Some(ip),
+            _ => None,
+        });
+    loopback.or_else(|| match device.primary_ip_v4()?.addr()? {
+        IpAddr::V4(ip) => Some(ip),
+        IpAddr::V6(_) => None,
+    })
+}
+
+/// Writes the next free loopback addresses of the device to netbox, the loopback interface is
+/// created if missing. The topology is fetched again before the next allocation starts, so two
+/// devices never get the same address.
+pub async fn allocate_loopback(
+    topology: &TopologyHolder,
+    netbox: &NetboxInstance,
+    device: DeviceId,
+) -> Result<LoopbackAllocation, Error> {
+    let _allocation = ALLOCATION.lock().await;
+    let device = topology
+        .devices_by_id(device)
+        .await
+        .ok_or(Error::UnknownDevice(device.0))?;
+    let addresses = next_loopback_addresses(&device);
+    if addresses.is_empty() {
+        return Err(Error::NoFreeLoopback(device.id().0));
+    }
+    let (interface_id, interface_created) = match loopback_interface(&device) {
+        Some(interface) => (interface.id().0, false),
+        None => (
+            create_virtual_interface(netbox, device.id().0, LOOPBACK_INTERFACE).await?,
+            true,
+        ),
+    };
+    let description = format!("loopback of {}", device.name());
+    for (address, vrf) in &addresses {
+        create_interface_address(
+            netbox,
+            interface_id,
+            *address,
+            vrf.map(|VrfId(id)| id),
+            &description,
+        )
+        .await?;
+    }
+    topology.fetch().await?;
+    Ok(LoopbackAllocation {
+        interface_created,
+        addresses: addresses
+            .iter()
+            .map(|(address, _)| address.to_string().into_boxed_str())
+            .collect(),
+    })
+}
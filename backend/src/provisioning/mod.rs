@@ -0,0 +1,15 @@
+//! Provisioning of devices as separate phases, the plan is created once and then shown,
+//! validated or applied.
+pub mod decommission;
+pub mod history;
+pub mod journal;
+pub mod lock;
+pub mod loopback;
+pub mod plan;
+pub mod protect;
+pub mod rollout;
+pub mod scope;
+pub mod site;
+
+#[cfg(test)]
+mod test;
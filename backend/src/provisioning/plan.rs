@@ -0,0 +1,534 @@
+use crate::{
+    Error,
+    config::CONFIG,
+    device::{
+        AccessibleDevice,
+        command::run_script,
+        reachability::limited_ping,
+        ros::{Reference, render_steps, warnings::GenerationWarning},
+    },
+    provisioning::{
+        protect::Protection,
+        scope::{ApplyScope, category},
+    },
+    secrets::mask_secrets,
+    topology::{DeviceId, access::device::DeviceAccess},
+};
+use async_graphql::{ComplexObject, Object, SimpleObject};
+use log::{debug, info};
+
+/// Changes needed to bring a device to the configuration generated from the topology.
+///
+/// Created by fetching the current configuration, generating the target from the topology and
+/// validating the resulting mutations. The steps are in the order they have to be applied,
+/// steps outside the [`ApplyScope`] are kept apart as skipped unless a step in scope depends on
+/// them, steps changing a protected resource as blocked. Removing orphaned resources is only
+/// proposed in prune mode and listed apart from the other steps. A plan cutting the management
+/// path of the provisioner to the device is refused on apply unless forced.
+#[derive(Debug, Clone, Default)]
+pub struct ProvisionPlan {
+    device: DeviceId,
+    steps: Box<[PlanStep]>,
+    deletions: Box<[PlanStep]>,
+    skipped: Box<[PlanStep]>,
+    blocked: Box<[PlanStep]>,
+    violations: Box<[Box<str>]>,
+    warnings: Box<[Box<str>]>,
+    generation_warnings: Box<[GenerationWarning]>,
+    management_cuts: Box<[Box<str>]>,
+}
+
+/// single mutation of the plan rendered as RouterOS script
+#[derive(Debug, Clone, PartialEq, Eq, SimpleObject)]
+#[graphql(complex)]
+pub struct PlanStep {
+    position: u32,
+    /// resource path of the mutation, e.g. `ip/address`
+    category: Box<str>,
+    /// applied as is, shown with the secrets masked
+    #[graphql(skip)]
+    script: Box<str>,
+    /// name of the entry the step changes, if known
+    key: Option<Box<str>>,
+    #[graphql(skip)]
+    depends: Box<[Reference]>,
+    #[graphql(skip)]
+    provides: Box<[Reference]>,
+}
+
+/// How a plan is created
+#[derive(Debug, Clone, Default)]
+pub struct PlanOptions {
+    pub scope: ApplyScope,
+    pub protection: Protection,
+    /// propose deletions of managed resources missing in the generated configuration
+    pub prune: bool,
+    /// apply even if the management path to the device is cut, the cuts are only warned about
+    pub force: bool,
+}
+
+impl PlanOptions {
+    /// options with the configured protected resources of the device
+    pub fn for_device(device: &DeviceAccess, scope: Option<ApplyScope>, prune: bool) -> Self {
+        Self {
+            scope: scope.unwrap_or_default(),
+            protection: Protection::new(CONFIG.load().protected_resources(device.name())),
+            prune,
+            force: false,
+        }
+    }
+}
+
+/// Outcome of applying a plan, the steps after a failed one are not applied
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ApplyResult {
+    plan: ProvisionPlan,
+    /// number of steps applied successfully
+    applied: u32,
+    /// error of the failed step
+    error: Option<Box<str>>,
+    /// the device answers pings after the apply and a new plan has no steps left
+    converged: bool,
+    /// steps planned again after the apply, the device did not take them as generated
+    residual: Box<[PlanStep]>,
+    /// why the convergence could not be verified
+    verify_error: Option<Box<str>>,
+}
+
+/// sorts out the steps outside the scope and the steps changing protected resources
+pub struct StepFilter<'a> {
+    options: &'a PlanOptions,
+    skipped: Vec<PlanStep>,
+    blocked: Vec<PlanStep>,
+    violations: Vec<Box<str>>,
+    dependencies: Vec<Box<str>>,
+}
+
+impl<'a> StepFilter<'a> {
+    pub fn new(options: &'a PlanOptions) -> Self {
+        Self {
+            options,
+            skipped: Vec::new(),
+            blocked: Vec::new(),
+            violations: Vec::new(),
+            dependencies: Vec::new(),
+        }
+    }
+    /// the steps to apply out of steps sorted by their dependencies
+    pub fn apply(&mut self, steps: Vec<PlanStep>) -> Vec<PlanStep> {
+        let in_scope = self.in_scope(&steps);
+        let mut result = Vec::new();
+        for (step, in_scope) in steps.into_iter().zip(in_scope) {
+            if !in_scope {
+                self.skipped.push(step);
+            } else if let Some(resource) = self.options.protection.violated_by(&step) {
+                self.violations.push(
+                    format!(
+                        "Step {} on {} would change protected {}",
+                        step.position,
+                        step.category,
+                        resource.describe()
+                    )
+                    .into_boxed_str(),
+                );
+                self.blocked.push(step);
+            } else if let Some(blocker) = self.blocked.iter().find(|blocked| {
+                step.depends
+                    .iter()
+                    .any(|dependency| blocked.provides.contains(dependency))
+            }) {
+                // the device would refuse the step without the blocked one
+                self.violations.push(
+                    format!(
+                        "Step {} on {} depends on blocked step {} on {}",
+                        step.position, step.category, blocker.position, blocker.category
+                    )
+                    .into_boxed_str(),
+                );
+                self.blocked.push(step);
+            } else {
+                result.push(step);
+            }
+        }
+        result
+    }
+    /// Marks the steps in the scope together with the steps outside providing what they
+    /// depend on, otherwise the device would refuse the steps in scope.
+    fn in_scope(&mut self, steps: &[PlanStep]) -> Vec<bool> {
+        let mut in_scope = steps
+            .iter()
+            .map(|step| self.options.scope.contains(&step.category))
+            .collect::<Vec<_>>();
+        // a provider comes before its dependents, so walking backwards includes the
+        // dependencies of included providers as well
+        for idx in (0..steps.len()).rev() {
+            if !in_scope[idx] {
+                continue;
+            }
+            for dependency in &steps[idx].depends {
+                let Some(provider) = steps[..idx]
+                    .iter()
+                    .rposition(|step| step.provides.contains(dependency))
+                else {
+                    continue;
+                };
+                if !in_scope[provider] {
+                    in_scope[provider] = true;
+                    self.dependencies.push(
+                        format!(
+                            "Step {} on {} is applied outside the scope, step {} on {} depends on it",
+                            steps[provider].position,
+                            steps[provider].category,
+                            steps[idx].position,
+                            steps[idx].category
+                        )
+                        .into_boxed_str(),
+                    );
+                }
+            }
+        }
+        in_scope
+    }
+    pub fn skipped(&self) -> &[PlanStep] {
+        &self.skipped
+    }
+    pub fn blocked(&self) -> &[PlanStep] {
+        &self.blocked
+    }
+    /// steps outside the scope applied because steps in the scope depend on them
+    pub fn dependencies(&self) -> &[Box<str>] {
+        &self.dependencies
+    }
+}
+
+impl PlanStep {
+    pub fn new(position: u32, category: &str, script: impl Into<Box<str>>) -> Self {
+        Self {
+            position,
+            category: category.into(),
+            script: script.into(),
+            key: None,
+            depends: Box::default(),
+            provides: Box::default(),
+        }
+    }
+    /// the references the step needs and the ones it creates
+    pub fn with_references(
+        mut self,
+        depends: impl Into<Box<[Reference]>>,
+        provides: impl Into<Box<[Reference]>>,
+    ) -> Self {
+        self.depends = depends.into();
+        self.provides = provides.into();
+        if self.key.is_none() {
+            self.key = self
+                .provides
+                .first()
+                .map(|(_, name)| name.to_string().into());
+        }
+        self
+    }
+    pub fn with_key(mut self, key: impl Into<Box<str>>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+    pub fn category(&self) -> &str {
+        &self.category
+    }
+    pub fn script(&self) -> &str {
+        &self.script
+    }
+}
+
+impl ApplyResult {
+    /// result of an apply that is not verified yet
+    pub(crate) fn new(plan: ProvisionPlan, applied: u32, error: Option<Box<str>>) -> Self {
+        ApplyResult {
+            plan,
+            applied,
+            error,
+            converged: false,
+            residual: Box::default(),
+            verify_error: None,
+        }
+    }
+    pub fn plan(&self) -> &ProvisionPlan {
+        &self.plan
+    }
+    pub fn applied(&self) -> u32 {
+        self.applied
+    }
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+    pub fn residual(&self) -> &[PlanStep] {
+        &self.residual
+    }
+    /// why a successfully applied plan did not converge, none if it did
+    pub fn convergence_problem(&self) -> Option<Box<str>> {
+        if self.converged {
+            None
+        } else if let Some(error) = &self.verify_error {
+            Some(error.clone())
+        } else {
+            Some(
+                format!(
+                    "Not converged, {} steps left after apply",
+                    self.residual.len()
+                )
+                .into(),
+            )
+        }
+    }
+    /// Verifies a successful apply: the management address of the device answers pings and a
+    /// new plan with the same options has no steps left. The device is connected again, the
+    /// apply may have changed the way to reach it.
+    pub async fn verify(&mut self, device: &DeviceAccess, options: &PlanOptions) {
+        if self.error.is_some() {
+            return;
+        }
+        self.record_verification(remaining_steps(device, options).await);
+    }
+    /// records the steps left after the apply, or why they could not be planned
+    pub(crate) fn record_verification(&mut self, remaining: Result<Box<[PlanStep]>, Box<str>>) {
+        match remaining {
+            Ok(residual) => {
+                self.converged = residual.is_empty();
+                self.residual = residual;
+            }
+            Err(error) => self.verify_error = Some(error),
+        }
+    }
+}
+
+async fn remaining_steps(
+    device: &DeviceAccess,
+    options: &PlanOptions,
+) -> Result<Box<[PlanStep]>, Box<str>> {
+    let address = device
+        .management_ip()
+        .ok_or_else(|| Box::from("No management address"))?;
+    limited_ping(address)
+        .await
+        .map_err(|error| format!("Not reachable after apply: {error}"))?;
+    let remaining = async {
+        let client = device.connect(None, None).await?;
+        ProvisionPlan::create(&client, options).await
+    }
+    .await
+    .map_err(|error| error.to_string())?;
+    Ok(remaining.all_steps().cloned().collect())
+}
+
+impl ProvisionPlan {
+    pub async fn create(device: &AccessibleDevice, options: &PlanOptions) -> Result<Self, Error> {
+        let config = device.device_config();
+        // fetch
+        let mut device_cfg = device.fetch_config(device.client()).await?;
+        // generate
+        device_cfg.generate_from(config)?;
+        let mutations = device_cfg.generate_mutations()?;
+        info!(
+            "{} mutations generated for {}",
+            mutations.len(),
+            config.name()
+        );
+        // formatting every mutation takes longer than generating them on big switches
+        for m in &mutations {
+            debug!("Mutation generated: {:?}", m);
+        }
+        // validate and sort, fails if a mutation references something that is not created.
+        // The scope is applied afterward, so the order stays the same as for the whole plan
+        let steps = render_steps(&mutations)?
+            .into_iter()
+            .enumerate()
+            .map(|(position, step)| {
+                PlanStep::new(position as u32, category(step.resource), step.script)
+                    .with_references(step.depends, step.provides)
+            })
+            .collect::<Vec<_>>();
+        // deletions are proposed after all other steps, nothing depends on an orphan
+        let deletions = if options.prune {
+            device_cfg
+                .orphans()
+                .into_iter()
+                .enumerate()
+                .map(|(idx, orphan)| {
+                    PlanStep::new((steps.len() + idx) as u32, orphan.path, orphan.script())
+                        .with_key(orphan.name.to_string())
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let mut filter = StepFilter::new(options);
+        let steps = filter.apply(steps);
+        let deletions = filter.apply(deletions);
+        let mut warnings = filter.dependencies;
+        if device_cfg.unknown_model() {
+            warnings.push(
+                format!(
+                    "No hardware facts known for the model of {}, ports cannot be validated",
+                    config.name()
+                )
+                .into_boxed_str(),
+            );
+        }
+        if !config.has_routeros() {
+            warnings.push(format!("{} is not marked as RouterOS device", config.name()).into());
+        }
+        // only changes within the scope are applied
+        let management_cuts = device_cfg
+            .management_path_cuts(device.address(), options.prune)
+            .into_iter()
+            .filter(|(path, _)| options.scope.contains(path))
+            .map(|(_, cut)| format!("Management path cut: {cut}").into_boxed_str());
+        let management_cuts = if options.force {
+            warnings.extend(management_cuts);
+            Vec::new()
+        } else {
+            management_cuts.collect()
+        };
+        Ok(Self {
+            device: config.id(),
+            steps: steps.into_boxed_slice(),
+            deletions: deletions.into_boxed_slice(),
+            skipped: filter.skipped.into_boxed_slice(),
+            blocked: filter.blocked.into_boxed_slice(),
+            violations: filter.violations.into_boxed_slice(),
+            warnings: warnings.into_boxed_slice(),
+            generation_warnings: device_cfg.warnings().into(),
+            management_cuts: management_cuts.into_boxed_slice(),
+        })
+    }
+    pub fn device(&self) -> DeviceId {
+        self.device
+    }
+    pub fn steps(&self) -> &[PlanStep] {
+        &self.steps
+    }
+    /// steps outside the scope of the plan
+    pub fn skipped(&self) -> &[PlanStep] {
+        &self.skipped
+    }
+    /// removals of orphaned resources, empty unless created in prune mode
+    pub fn deletions(&self) -> &[PlanStep] {
+        &self.deletions
+    }
+    /// steps not applied because they change a protected resource
+    pub fn blocked(&self) -> &[PlanStep] {
+        &self.blocked
+    }
+    pub fn violations(&self) -> &[Box<str>] {
+        &self.violations
+    }
+    pub fn warnings(&self) -> &[Box<str>] {
+        &self.warnings
+    }
+    /// data problems the generation of the target configuration worked around
+    pub fn generation_warnings(&self) -> &[GenerationWarning] {
+        &self.generation_warnings
+    }
+    /// changes cutting the path of the provisioner to the device, the plan is not applied
+    pub fn management_cuts(&self) -> &[Box<str>] {
+        &self.management_cuts
+    }
+    /// the steps in the order they are applied, deletions last
+    pub fn all_steps(&self) -> impl Iterator<Item = &PlanStep> {
+        self.steps.iter().chain(self.deletions.iter())
+    }
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty() && self.deletions.is_empty()
+    }
+    /// Runs the steps on the device in order, deletions last. Stops at the first failing step.
+    ///
+    /// The caller has to hold the lock of the device from creating the plan until it is applied.
+    pub async fn apply(self, device: &AccessibleDevice) -> ApplyResult {
+        if !self.management_cuts.is_empty() {
+            let error = format!(
+                "Refused, the provisioner would lose the device: {}",
+                self.management_cuts.join("; ")
+            );
+            return ApplyResult::new(self, 0, Some(error.into()));
+        }
+        let mut applied = 0;
+        let mut error = None;
+        for step in self.all_steps() {
+            if let Err(e) = run_script(device.client(), step.script()).await {
+                error = Some(format!("Step {} on {}: {e}", step.position, step.category).into());
+                break;
+            }
+            applied += 1;
+        }
+        ApplyResult::new(self, applied, error)
+    }
+    /// the whole plan as one script, deletions last
+    pub fn script(&self) -> String {
+        self.all_steps().map(|step| step.script.as_ref()).collect()
+    }
+}
+
+#[Object]
+impl ProvisionPlan {
+    #[graphql(name = "steps")]
+    async fn api_steps(&self) -> &[PlanStep] {
+        self.steps()
+    }
+    /// removals of resources not generated anymore, only in prune mode
+    #[graphql(name = "deletions")]
+    async fn api_deletions(&self) -> &[PlanStep] {
+        self.deletions()
+    }
+    #[graphql(name = "skipped")]
+    async fn api_skipped(&self) -> &[PlanStep] {
+        self.skipped()
+    }
+    #[graphql(name = "blocked")]
+    async fn api_blocked(&self) -> &[PlanStep] {
+        self.blocked()
+    }
+    /// protected resources the generated configuration would change
+    #[graphql(name = "violations")]
+    async fn api_violations(&self) -> &[Box<str>] {
+        self.violations()
+    }
+    /// changes cutting the management path of the provisioner, the plan is refused unless forced
+    #[graphql(name = "managementCuts")]
+    async fn api_management_cuts(&self) -> &[Box<str>] {
+        self.management_cuts()
+    }
+    /// problems that do not prevent applying the plan
+    #[graphql(name = "warnings")]
+    async fn api_warnings(&self) -> &[Box<str>] {
+        self.warnings()
+    }
+    /// problems in the netbox data or the configuration the generation worked around
+    #[graphql(name = "generationWarnings")]
+    async fn api_generation_warnings(&self) -> &[GenerationWarning] {
+        self.generation_warnings()
+    }
+    /// the device differs from the generated configuration
+    async fn drifted(&self) -> bool {
+        !self.is_empty()
+    }
+    /// the whole plan as one script, the secrets are masked
+    #[graphql(name = "script")]
+    async fn api_script(&self) -> String {
+        mask_secrets(&self.script())
+    }
+}
+
+#[ComplexObject]
+impl PlanStep {
+    /// the script of the step, the secrets are masked
+    #[graphql(name = "script")]
+    async fn api_script(&self) -> String {
+        mask_secrets(&self.script)
+    }
+}
@@ -0,0 +1,32 @@
+use crate::{
+    config::ProtectedResource,
+    provisioning::{plan::PlanStep, scope::is_below},
+};
+
+/// Resources the provisioner must not touch on a device, steps changing them are blocked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Protection(Box<[ProtectedResource]>);
+
+impl Protection {
+    pub fn new(resources: impl IntoIterator<Item = ProtectedResource>) -> Self {
+        Self(resources.into_iter().collect())
+    }
+    /// the protected resource changed by the step, if any
+    pub fn violated_by(&self, step: &PlanStep) -> Option<&ProtectedResource> {
+        self.0
+            .iter()
+            .find(|resource| protects(resource, step.category(), step.key()))
+    }
+}
+
+fn protects(resource: &ProtectedResource, category: &str, step_key: Option<&str>) -> bool {
+    if !is_below(category, &resource.path) {
+        return false;
+    }
+    match (&resource.key, step_key) {
+        (None, _) => true,
+        (Some(key), Some(step_key)) => key.as_ref() == step_key,
+        // the entry the step changes is not known, it could be the protected one
+        (Some(_), None) => true,
+    }
+}
@@ -0,0 +1,378 @@
+use crate::{
+    Error,
+    notify::NOTIFIER,
+    policy::TenantScope,
+    provisioning::{
+        history::RUN_HISTORY,
+        lock::lock_device,
+        plan::{ApplyResult, PlanOptions, ProvisionPlan},
+        scope::ApplyScope,
+        site::rollout_stages,
+    },
+    topology::access::device::DeviceAccess,
+};
+use async_graphql::{
+    Enum, InputObject, SimpleObject,
+    futures_util::{Stream, StreamExt, stream},
+};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use std::{
+    collections::BTreeMap,
+    future::ready,
+    sync::{Mutex, MutexGuard},
+    time::SystemTime,
+};
+use tokio::sync::broadcast;
+
+/// Rollouts kept for late subscribers, older rollouts are dropped
+const KEPT_ROLLOUTS: usize = 20;
+/// Events buffered per subscriber before it misses some
+const EVENT_BUFFER: usize = 64;
+
+/// How a rollout to multiple devices proceeds
+#[derive(InputObject, Debug, Clone)]
+pub struct RolloutOptions {
+    /// devices applied and verified first, the rollout is aborted if one of them fails
+    #[graphql(default = 1)]
+    pub canary_count: u32,
+    /// abort the rollout at the first failing device after the canaries as well
+    #[graphql(default = true)]
+    pub abort_on_failure: bool,
+    pub scope: Option<ApplyScope>,
+    #[graphql(default)]
+    pub prune: bool,
+    /// apply plans cutting the management path to a device as well
+    #[graphql(default)]
+    pub force: bool,
+    /// connect to devices fleet operations back off from after repeated connection failures
+    #[graphql(default)]
+    pub ignore_backoff: bool,
+}
+
+impl Default for RolloutOptions {
+    fn default() -> Self {
+        Self {
+            canary_count: 1,
+            abort_on_failure: true,
+            scope: None,
+            prune: false,
+            force: false,
+            ignore_backoff: false,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RolloutPhase {
+    Canary,
+    Fleet,
+}
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RolloutStep {
+    /// the plan of the device was applied
+    Applied,
+    /// the device is reachable and nothing is left to apply
+    Verified,
+    Failed,
+    /// not applied because the rollout was aborted
+    Skipped,
+    /// all devices are done, the last event of a rollout
+    Finished,
+    /// a device failed and the remaining devices were skipped, the last event of a rollout
+    Aborted,
+}
+
+/// Progress of a rollout
+#[derive(Debug, Clone, SimpleObject)]
+pub struct RolloutEvent {
+    rollout: u32,
+    phase: RolloutPhase,
+    step: RolloutStep,
+    device_id: Option<u32>,
+    device_name: Option<Box<str>>,
+    message: Option<Box<str>>,
+    /// netbox tenants of the devices of the event, all devices of the rollout for the last
+    /// event, to show the event only to the users of all of them
+    #[graphql(skip)]
+    tenants: Box<[Option<Box<str>>]>,
+}
+
+impl RolloutEvent {
+    pub fn step(&self) -> RolloutStep {
+        self.step
+    }
+    pub fn visible_to(&self, scope: &TenantScope) -> bool {
+        self.tenants
+            .iter()
+            .all(|tenant| scope.allows_tenant(tenant.as_deref()))
+    }
+    fn is_last(&self) -> bool {
+        matches!(self.step, RolloutStep::Finished | RolloutStep::Aborted)
+    }
+}
+
+struct RolloutState {
+    /// user who started the rollout
+    owner: Option<Box<str>>,
+    /// netbox tenants of the devices
+    tenants: Box<[Option<Box<str>>]>,
+    events: Vec<RolloutEvent>,
+    /// dropped after the last event, which ends the streams of the subscribers
+    sender: Option<broadcast::Sender<RolloutEvent>>,
+}
+
+#[derive(Default)]
+struct RolloutsState {
+    last_id: u32,
+    rollouts: BTreeMap<u32, RolloutState>,
+}
+
+/// Rollouts of this process, started in the background and observed by their events
+#[derive(Default)]
+pub struct Rollouts {
+    state: Mutex<RolloutsState>,
+}
+
+impl Rollouts {
+    /// Starts applying the plans to the devices in the order of their dependencies, the first
+    /// `canary_count` devices are applied and verified before all others
+    pub fn start(
+        &'static self,
+        instance: &str,
+        devices: &[DeviceAccess],
+        options: RolloutOptions,
+        user: Option<&str>,
+    ) -> u32 {
+        let devices = rollout_stages(devices)
+            .into_iter()
+            .map(|(device, _)| device)
+            .collect::<Vec<_>>();
+        let tenants = devices
+            .iter()
+            .map(|device| device.tenant().map(Box::from))
+            .collect::<Box<[_]>>();
+        let id = {
+            let mut state = self.lock();
+            state.last_id += 1;
+            let id = state.last_id;
+            if state.rollouts.len() >= KEPT_ROLLOUTS {
+                state.rollouts.pop_first();
+            }
+            state.rollouts.insert(
+                id,
+                RolloutState {
+                    owner: user.map(Box::from),
+                    tenants: tenants.clone(),
+                    events: Vec::new(),
+                    sender: Some(broadcast::channel(EVENT_BUFFER).0),
+                },
+            );
+            id
+        };
+        let rollout = Rollout {
+            id,
+            instance: instance.into(),
+            devices,
+            tenants,
+            options,
+            user: user.map(Box::from),
+        };
+        tokio::spawn(rollout.run(self));
+        id
+    }
+
+    /// The events of a rollout so far followed by the coming ones, none if the rollout is
+    /// unknown. Only the user who started the rollout and users of all tenants of its devices
+    /// may follow it, they only get the events of devices in their scope.
+    pub fn subscribe(
+        &self,
+        id: u32,
+        user: Option<&str>,
+        scope: TenantScope,
+    ) -> Option<impl Stream<Item = RolloutEvent>> {
+        let state = self.lock();
+        let rollout = state.rollouts.get(&id)?;
+        let owner = rollout.owner.is_some() && rollout.owner.as_deref() == user;
+        let covered = rollout
+            .tenants
+            .iter()
+            .all(|tenant| scope.allows_tenant(tenant.as_deref()));
+        if !owner && !covered {
+            return None;
+        }
+        let past = stream::iter(rollout.events.clone());
+        let receiver = rollout.sender.as_ref().map(broadcast::Sender::subscribe);
+        let coming = stream::unfold(receiver, |receiver| async move {
+            let mut receiver = receiver?;
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, Some(receiver))),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!("Rollout subscriber missed {missed} events");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+        Some(
+            past.chain(coming)
+                .filter(move |event| ready(event.is_last() || event.visible_to(&scope))),
+        )
+    }
+
+    fn emit(&self, event: RolloutEvent) {
+        let mut state = self.lock();
+        let Some(rollout) = state.rollouts.get_mut(&event.rollout) else {
+            return;
+        };
+        rollout.events.push(event.clone());
+        let last = event.is_last();
+        if let Some(sender) = &rollout.sender {
+            // no subscriber is not an error
+            let _ = sender.send(event);
+        }
+        if last {
+            rollout.sender = None;
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RolloutsState> {
+        self.state.lock().expect("rollouts poisoned")
+    }
+}
+
+lazy_static! {
+    pub static ref ROLLOUTS: Rollouts = Rollouts::default();
+}
+
+struct Rollout {
+    id: u32,
+    instance: Box<str>,
+    devices: Vec<DeviceAccess>,
+    tenants: Box<[Option<Box<str>>]>,
+    options: RolloutOptions,
+    user: Option<Box<str>>,
+}
+
+impl Rollout {
+    async fn run(self, rollouts: &Rollouts) {
+        info!(
+            "Start rollout {} to {} devices, {} canaries",
+            self.id,
+            self.devices.len(),
+            self.options.canary_count
+        );
+        let mut aborted = false;
+        for (idx, device) in self.devices.iter().enumerate() {
+            let phase = if idx < self.options.canary_count as usize {
+                RolloutPhase::Canary
+            } else {
+                RolloutPhase::Fleet
+            };
+            if aborted {
+                rollouts.emit(self.event(phase, RolloutStep::Skipped, Some(device), None));
+                continue;
+            }
+            let failure = match self.apply(device).await {
+                Ok(result) if result.error().is_some() => result.error().map(Box::from),
+                Ok(result) => {
+                    rollouts.emit(self.event(phase, RolloutStep::Applied, Some(device), None));
+                    result.convergence_problem()
+                }
+                Err(error) => Some(error.to_string().into_boxed_str()),
+            };
+            match failure {
+                None => rollouts.emit(self.event(phase, RolloutStep::Verified, Some(device), None)),
+                Some(error) => {
+                    warn!("Rollout {} failed on {}: {error}", self.id, device.name());
+                    rollouts.emit(self.event(
+                        phase,
+                        RolloutStep::Failed,
+                        Some(device),
+                        Some(error),
+                    ));
+                    aborted = phase == RolloutPhase::Canary || self.options.abort_on_failure;
+                }
+            }
+        }
+        let step = if aborted {
+            RolloutStep::Aborted
+        } else {
+            RolloutStep::Finished
+        };
+        rollouts.emit(self.event(RolloutPhase::Fleet, step, None, None));
+    }
+
+    async fn apply(&self, device: &DeviceAccess) -> Result<ApplyResult, Error> {
+        if !self.options.ignore_backoff {
+            device.check_backoff()?;
+        }
+        apply_device(
+            &self.instance,
+            device,
+            &self.plan_options(device),
+            self.user.as_deref(),
+        )
+        .await
+    }
+
+    fn plan_options(&self, device: &DeviceAccess) -> PlanOptions {
+        PlanOptions {
+            force: self.options.force,
+            ..PlanOptions::for_device(device, self.options.scope.clone(), self.options.prune)
+        }
+    }
+
+    fn event(
+        &self,
+        phase: RolloutPhase,
+        step: RolloutStep,
+        device: Option<&DeviceAccess>,
+        message: Option<Box<str>>,
+    ) -> RolloutEvent {
+        RolloutEvent {
+            rollout: self.id,
+            phase,
+            step,
+            device_id: device.map(|d| d.id().0),
+            device_name: device.map(|d| d.name().into()),
+            message,
+            tenants: match device {
+                Some(device) => Box::new([device.tenant().map(Box::from)]),
+                None => self.tenants.clone(),
+            },
+        }
+    }
+}
+
+/// Creates and applies the plan of a device while holding its lock and verifies the device
+/// converged, the run is recorded in the history and notified
+pub async fn apply_device(
+    instance: &str,
+    device: &DeviceAccess,
+    options: &PlanOptions,
+    user: Option<&str>,
+) -> Result<ApplyResult, Error> {
+    if !device.status().allows_apply() {
+        return Err(Error::DeviceStatus(device.status()));
+    }
+    let _lock = lock_device(instance, device.id())?;
+    let started_at = SystemTime::now();
+    let result = async {
+        let client = device.connect(None, None).await?;
+        let mut result = ProvisionPlan::create(&client, options)
+            .await?
+            .apply(&client)
+            .await;
+        result.verify(device, options).await;
+        Ok(result)
+    }
+    .await;
+    RUN_HISTORY.record(instance, device, user, started_at, &result);
+    if let Ok(result) = &result {
+        NOTIFIER.plan_applied(instance, device, result).await;
+    }
+    result
+}
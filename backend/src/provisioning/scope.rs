@@ -0,0 +1,37 @@
+use async_graphql::InputObject;
+
+/// Restricts a plan to some resource categories, so risky changes can be rolled out piecemeal.
+///
+/// A category is a resource path like `ip/address` and includes the paths below it, so
+/// `ip/firewall` covers `ip/firewall/filter` as well. Without `only` every category is in scope.
+#[derive(InputObject, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApplyScope {
+    /// only these categories are applied
+    #[graphql(default)]
+    pub only: Vec<Box<str>>,
+    /// these categories are never applied, even if listed in `only`
+    #[graphql(default)]
+    pub skip: Vec<Box<str>>,
+}
+
+impl ApplyScope {
+    pub fn all() -> Self {
+        Self::default()
+    }
+    pub fn contains(&self, resource_path: &str) -> bool {
+        let matches = |c: &Box<str>| is_below(resource_path, c);
+        (self.only.is_empty() || self.only.iter().any(matches)) && !self.skip.iter().any(matches)
+    }
+}
+
+/// resource path without leading or trailing slashes
+pub fn category(resource_path: &str) -> &str {
+    resource_path.trim_matches('/')
+}
+
+/// the resource path is the category or a path below it
+pub fn is_below(resource_path: &str, category_path: &str) -> bool {
+    category(resource_path)
+        .strip_prefix(category(category_path))
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
@@ -0,0 +1,170 @@
+use crate::{
+    Error,
+    graphql::connection_limit::ConnectionLimit,
+    provisioning::{
+        plan::{PlanOptions, ProvisionPlan},
+        scope::ApplyScope,
+    },
+    topology::{DeviceId, access::device::DeviceAccess},
+};
+use async_graphql::{SimpleObject, futures_util::future::join_all};
+use log::warn;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::IpAddr,
+};
+
+/// Plans of all devices of a site, in the order to roll them out
+#[derive(SimpleObject)]
+pub struct SitePlan {
+    devices: Vec<SitePlanEntry>,
+}
+
+/// Plan of one device of a site, or why it could not be created
+#[derive(SimpleObject)]
+pub struct SitePlanEntry {
+    device: DeviceAccess,
+    /// devices of a later stage depend on devices of an earlier stage, devices of the same
+    /// stage can be rolled out together
+    stage: u32,
+    plan: Option<ProvisionPlan>,
+    error: Option<Box<str>>,
+}
+
+impl SitePlanEntry {
+    pub fn device(&self) -> &DeviceAccess {
+        &self.device
+    }
+    pub fn stage(&self) -> u32 {
+        self.stage
+    }
+}
+
+/// Rollout stage of each device, the devices a device depends on are in earlier stages
+///
+/// A device depends on the router of its upstream gateway and an access point on the controller
+/// of its wlan group, as long as these are part of the given devices. Devices in a dependency
+/// cycle are put in the stage after their dependencies outside the cycle.
+pub fn rollout_stages(devices: &[DeviceAccess]) -> Vec<(DeviceAccess, u32)> {
+    let mut owner_of_address = HashMap::<IpAddr, DeviceId>::new();
+    for device in devices {
+        for address in device.interfaces().iter().flat_map(|i| i.ips()) {
+            if let Some(ip) = address.addr() {
+                owner_of_address.insert(ip, device.id());
+            }
+        }
+    }
+    let mut dependencies = BTreeMap::<DeviceId, Vec<DeviceId>>::new();
+    for device in devices {
+        let upstream = device
+            .upstream_gateway()
+            .and_then(|gateway| owner_of_address.get(&gateway).copied());
+        let controller = device
+            .wlan_ap_of()
+            .and_then(|group| group.controller())
+            .map(|controller| controller.id());
+        dependencies.insert(
+            device.id(),
+            upstream
+                .into_iter()
+                .chain(controller)
+                .filter(|id| *id != device.id() && owner_in(devices, *id))
+                .collect(),
+        );
+    }
+    let mut stages = HashMap::<DeviceId, u32>::new();
+    let mut stage = 0;
+    while stages.len() < devices.len() {
+        let ready = dependencies
+            .iter()
+            .filter(|(id, _)| !stages.contains_key(id))
+            .filter(|(_, depends_on)| depends_on.iter().all(|id| stages.contains_key(id)))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        let ready = if ready.is_empty() {
+            // cycle, release all remaining devices at once
+            dependencies
+                .keys()
+                .filter(|id| !stages.contains_key(id))
+                .copied()
+                .collect()
+        } else {
+            ready
+        };
+        for id in ready {
+            stages.insert(id, stage);
+        }
+        stage += 1;
+    }
+    let mut result = devices
+        .iter()
+        .map(|device| (device.clone(), stages[&device.id()]))
+        .collect::<Vec<_>>();
+    result.sort_by(|(a, stage_a), (b, stage_b)| (stage_a, a.name()).cmp(&(stage_b, b.name())));
+    result
+}
+
+fn owner_in(devices: &[DeviceAccess], id: DeviceId) -> bool {
+    devices.iter().any(|device| device.id() == id)
+}
+
+impl SitePlan {
+    pub fn devices(&self) -> &[SitePlanEntry] {
+        &self.devices
+    }
+    /// Creates the plans of the devices concurrently, a device that cannot be planned is
+    /// reported with its error and does not prevent the plans of the others. `check` is called
+    /// before accessing a device, e.g. to apply the rate limits. With a limit the devices wait
+    /// for a free connection.
+    pub async fn create(
+        devices: &[DeviceAccess],
+        scope: Option<ApplyScope>,
+        prune: bool,
+        check: impl Fn(&DeviceAccess) -> Result<(), Error>,
+        limit: Option<ConnectionLimit>,
+    ) -> Self {
+        let entries = rollout_stages(devices).into_iter().map(|(device, stage)| {
+            let scope = scope.clone();
+            let checked = check(&device);
+            let limit = limit.clone();
+            async move {
+                let planned = match checked {
+                    Ok(()) => {
+                        let _permit = ConnectionLimit::acquire(limit.as_ref()).await;
+                        plan_device(&device, scope, prune).await
+                    }
+                    Err(error) => Err(error),
+                };
+                let (plan, error) = match planned {
+                    Ok(plan) => (Some(plan), None),
+                    Err(error) => {
+                        warn!("Cannot plan {}: {error}", device.name());
+                        (None, Some(error.to_string().into_boxed_str()))
+                    }
+                };
+                SitePlanEntry {
+                    device,
+                    stage,
+                    plan,
+                    error,
+                }
+            }
+        });
+        SitePlan {
+            devices: join_all(entries).await,
+        }
+    }
+}
+
+async fn plan_device(
+    device: &DeviceAccess,
+    scope: Option<ApplyScope>,
+    prune: bool,
+) -> Result<ProvisionPlan, Error> {
+    if !device.status().allows_apply() {
+        return Err(Error::DeviceStatus(device.status()));
+    }
+    device.check_backoff()?;
+    let client = device.connect(None, None).await?;
+    ProvisionPlan::create(&client, &PlanOptions::for_device(device, scope, prune)).await
+}
@@ -0,0 +1,662 @@
+use crate::{
+    Error,
+    config::ProtectedResource,
+    netbox::JournalKind,
+    policy::TenantScope,
+    provisioning::{
+        decommission::{DecommissionMode, decommission_script, peer_devices},
+        history::{RunFilter, RunHistory, RunStatus},
+        journal::journal_comment,
+        lock::DeviceLocks,
+        loopback::{next_loopback_addresses, router_id},
+        plan::{ApplyResult, PlanOptions, PlanStep, ProvisionPlan, StepFilter},
+        protect::Protection,
+        rollout::{RolloutOptions, RolloutStep, Rollouts},
+        scope::ApplyScope,
+        site::rollout_stages,
+    },
+    topology::{
+        DeviceId, DeviceStatus, TopologyHolder, VrfId,
+        access::device::DeviceAccess,
+        snapshot::{FixtureFormat, parse_topology},
+    },
+};
+use async_graphql::futures_util::StreamExt;
+use ipnet::IpNet;
+use mikrotik_model::{ascii::AsciiString, model::ReferenceType};
+use std::{net::Ipv4Addr, time::SystemTime};
+
+#[test]
+fn test_scope_all() {
+    let scope = ApplyScope::all();
+    assert!(scope.contains("ip/address"));
+    assert!(scope.contains("/interface/vlan"));
+}
+
+#[test]
+fn test_scope_only() {
+    let scope = ApplyScope {
+        only: vec!["ip/address".into(), "interface/vlan".into()],
+        skip: vec![],
+    };
+    assert!(scope.contains("ip/address"));
+    assert!(scope.contains("interface/vlan"));
+    assert!(!scope.contains("ip/firewall/filter"));
+    assert!(!scope.contains("interface"));
+    // a prefix of a name is not a parent category
+    assert!(!scope.contains("ip/addresses"));
+}
+
+#[test]
+fn test_scope_skip() {
+    let scope = ApplyScope {
+        only: vec![],
+        skip: vec!["/ip/firewall".into()],
+    };
+    assert!(scope.contains("ip/address"));
+    assert!(!scope.contains("ip/firewall/filter"));
+    assert!(scope.contains("ipv6/firewall/filter"));
+}
+
+#[test]
+fn test_scope_includes_dependencies() {
+    let bridge = (ReferenceType::Interface, AsciiString::from("bridge"));
+    let vlan = (ReferenceType::Interface, AsciiString::from("vlan10"));
+    let steps = vec![
+        PlanStep::new(0, "interface/bridge", "/interface/bridge add name=bridge")
+            .with_references([], [bridge.clone()]),
+        PlanStep::new(1, "interface/vlan", "/interface/vlan add name=vlan10")
+            .with_references([bridge], [vlan.clone()]),
+        PlanStep::new(2, "ip/address", "/ip/address add interface=vlan10")
+            .with_references([vlan], []),
+        PlanStep::new(
+            3,
+            "ip/firewall/filter",
+            "/ip/firewall/filter add chain=input",
+        ),
+    ];
+    let options = PlanOptions {
+        scope: ApplyScope {
+            only: vec!["ip/address".into()],
+            skip: vec![],
+        },
+        ..PlanOptions::default()
+    };
+    let mut filter = StepFilter::new(&options);
+    let applied = filter.apply(steps);
+    assert_eq!(
+        applied.iter().map(PlanStep::category).collect::<Vec<_>>(),
+        ["interface/bridge", "interface/vlan", "ip/address"]
+    );
+    assert_eq!(filter.skipped().len(), 1);
+    assert_eq!(filter.dependencies().len(), 2);
+}
+
+#[test]
+fn test_protection() {
+    let protection = Protection::new([
+        ProtectedResource {
+            path: "ip/address".into(),
+            key: Some("10.99.0.5/24".into()),
+        },
+        ProtectedResource {
+            path: "/user".into(),
+            key: None,
+        },
+    ]);
+    let remove_mgmt = PlanStep::new(
+        0,
+        "ip/address",
+        "/ip/address remove [ find address=10.99.0.5/24 ]",
+    )
+    .with_key("10.99.0.5/24");
+    let add_lan = PlanStep::new(
+        1,
+        "ip/address",
+        "/ip/address add address=10.99.0.55/24 interface=lan comment=10.99.0.5/24",
+    )
+    .with_key("10.99.0.55/24");
+    let unknown_entry = PlanStep::new(2, "ip/address", "/ip/address set [ find ] disabled=no");
+    let add_user = PlanStep::new(3, "user/group", "/user/group add name=ops");
+    assert!(protection.violated_by(&remove_mgmt).is_some());
+    // the key is matched against the entry, not the script
+    assert!(protection.violated_by(&add_lan).is_none());
+    assert!(protection.violated_by(&unknown_entry).is_some());
+    assert!(protection.violated_by(&add_user).is_some());
+    assert!(Protection::default().violated_by(&remove_mgmt).is_none());
+}
+
+#[test]
+fn test_protection_blocks_dependents() {
+    let bridge = (ReferenceType::Interface, AsciiString::from("bridge"));
+    let steps = vec![
+        PlanStep::new(0, "interface/bridge", "/interface/bridge set bridge")
+            .with_references([], [bridge.clone()]),
+        PlanStep::new(1, "interface/vlan", "/interface/vlan add name=vlan10")
+            .with_references([bridge], []),
+        PlanStep::new(
+            2,
+            "ip/firewall/filter",
+            "/ip/firewall/filter add chain=input",
+        ),
+    ];
+    let options = PlanOptions {
+        protection: Protection::new([ProtectedResource {
+            path: "interface/bridge".into(),
+            key: Some("bridge".into()),
+        }]),
+        ..PlanOptions::default()
+    };
+    let mut filter = StepFilter::new(&options);
+    let applied = filter.apply(steps);
+    assert_eq!(
+        applied.iter().map(PlanStep::category).collect::<Vec<_>>(),
+        ["ip/firewall/filter"]
+    );
+    assert_eq!(filter.blocked().len(), 2);
+}
+
+#[test]
+fn test_device_lock() {
+    let locks = DeviceLocks::default();
+    let guard = locks.try_lock("default", DeviceId(1)).expect("lock failed");
+    assert!(matches!(
+        locks.try_lock("default", DeviceId(1)),
+        Err(Error::DeviceBusy(1))
+    ));
+    // other devices and the same id in another instance are independent
+    assert!(locks.try_lock("default", DeviceId(2)).is_ok());
+    assert!(locks.try_lock("other", DeviceId(1)).is_ok());
+    drop(guard);
+    assert!(locks.try_lock("default", DeviceId(1)).is_ok());
+}
+
+#[tokio::test]
+async fn test_run_history() {
+    let topology = parse_topology(
+        include_bytes!("../topology/fixtures/single-router.yaml"),
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid fixture");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let history = RunHistory::default();
+    let failed = history.record(
+        "default",
+        &device,
+        Some("admin"),
+        SystemTime::now(),
+        &Err(Error::MissingCredentials),
+    );
+    assert_eq!(failed.status(), RunStatus::Failed);
+    assert_eq!(failed.error(), Some("Error credentials"));
+    let other = history.record(
+        "other",
+        &device,
+        None,
+        SystemTime::now(),
+        &Err(Error::MissingManagementAddress),
+    );
+
+    let all = history.list(&RunFilter::default());
+    assert_eq!(
+        all.iter().map(|run| run.id()).collect::<Vec<_>>(),
+        vec![other.id(), failed.id()]
+    );
+    let default = history.list(&RunFilter {
+        instance: Some("default"),
+        ..RunFilter::default()
+    });
+    assert_eq!(default.len(), 1);
+    assert_eq!(default[0].instance(), "default");
+    assert!(
+        history
+            .list(&RunFilter {
+                status: Some(RunStatus::Succeeded),
+                ..RunFilter::default()
+            })
+            .is_empty()
+    );
+    assert_eq!(
+        history.get(failed.id()).map(|run| run.id()),
+        Some(failed.id())
+    );
+    assert!(history.get(42).is_none());
+}
+
+#[test]
+fn test_journal_comment() {
+    let steps = [
+        PlanStep::new(1, "ip/address", "/ip/address add address=10.0.0.1/24"),
+        PlanStep::new(2, "interface/bridge", "/interface/bridge add name=bridge"),
+        PlanStep::new(3, "ip/address", "/ip/address add address=10.0.1.1/24"),
+    ];
+    assert_eq!(None, journal_comment(&[], None, Some("alice")));
+    assert_eq!(
+        Some((
+            JournalKind::Success,
+            "provisioner applied 3 changes: interface/bridge (1), ip/address (2)\n\nstarted by alice"
+                .to_string()
+        )),
+        journal_comment(&steps, None, Some("alice"))
+    );
+    assert_eq!(
+        Some((
+            JournalKind::Warning,
+            "provisioner applied 1 changes: ip/address (1)\n\nstopped by an error: failure"
+                .to_string()
+        )),
+        journal_comment(&steps[..1], Some("failure"), None)
+    );
+}
+
+#[tokio::test]
+async fn test_decommission_script() {
+    let topology = parse_topology(
+        include_bytes!("../topology/fixtures/single-router.yaml"),
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid fixture");
+    let active = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    assert!(matches!(
+        decommission_script(&active, DecommissionMode::Wipe),
+        Err(Error::DeviceStatus(DeviceStatus::Active))
+    ));
+
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: router
+    status: decommissioning
+    ports:
+      - !Interface 10
+      - !Interface 11
+interfaces:
+  10:
+    name: ether1
+    device: 1
+    management: true
+  11:
+    name: ether2
+    device: 1
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    assert_eq!(
+        "/interface set [find name=\"ether2\"] disabled=yes\n",
+        decommission_script(&device, DecommissionMode::Disable)
+            .expect("no script")
+            .as_ref()
+    );
+    assert!(!device.status().allows_apply());
+    assert!(!device.status().in_fleet());
+
+    // managed through a vlan of the bridge: the uplink carrying the vlan stays up
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: switch
+    status: decommissioning
+    primary_ip_v4: 1
+    ports:
+      - !Interface 10
+      - !Interface 11
+      - !Interface 12
+      - !Interface 13
+      - !Interface 14
+interfaces:
+  10:
+    name: bridge
+    device: 1
+  11:
+    name: mgmt
+    device: 1
+    bridge: 10
+    vlan: 1
+    ips: [1]
+  12:
+    name: ether1
+    device: 1
+    bridge: 10
+    tagged_vlans: [1, 2]
+  13:
+    name: ether2
+    device: 1
+    bridge: 10
+    vlan: 2
+  14:
+    name: ether3
+    device: 1
+ip_addresses:
+  1:
+    ip: 10.0.0.2/24
+    interface: 11
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    assert_eq!(
+        "/interface set [find name=\"ether2\"] disabled=yes\n\
+         /interface set [find name=\"ether3\"] disabled=yes\n",
+        decommission_script(&device, DecommissionMode::Disable)
+            .expect("no script")
+            .as_ref()
+    );
+}
+
+#[tokio::test]
+async fn test_decommission_peers() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: controller
+    wlan_controller_of: 1
+  2:
+    name: ap-a
+    wlan_ap_of: 1
+    status: decommissioning
+  3:
+    name: ap-b
+    wlan_ap_of: 1
+  4:
+    name: switch
+wlan_groups:
+  1:
+    controller: 1
+    aps: [2, 3]
+    wlans: []
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let topology = TopologyHolder::offline(topology);
+    let names = |devices: Vec<DeviceAccess>| {
+        devices
+            .iter()
+            .map(|device| device.name().to_string())
+            .collect::<Vec<_>>()
+    };
+    let ap = topology
+        .devices_by_id(DeviceId(2))
+        .await
+        .expect("device not found");
+    assert_eq!(vec!["controller".to_string()], names(peer_devices(&ap)));
+    let controller = topology
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    assert_eq!(
+        vec!["ap-a".to_string(), "ap-b".to_string()],
+        names(peer_devices(&controller))
+    );
+    assert!(!DeviceStatus::Offline.allows_apply());
+}
+
+#[tokio::test]
+async fn test_next_loopback_addresses() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: core
+    ports:
+      - !Interface 10
+  2:
+    name: edge
+    primary_ip_v4: 2
+    ports:
+      - !Interface 20
+interfaces:
+  10:
+    name: lo
+    device: 1
+    ips: [1]
+  20:
+    name: ether1
+    device: 2
+    ips: [2]
+ip_addresses:
+  1:
+    ip: 10.255.0.1/32
+    interface: 10
+  2:
+    ip: 10.0.0.2/24
+    interface: 20
+vrfs:
+  1:
+    name: mgmt
+ip_prefixes:
+  1:
+    prefix: 10.255.0.0/24
+    addresses: []
+    children: []
+    ranges: []
+    loopback: true
+  2:
+    prefix: fd00:ff::/64
+    addresses: []
+    children: []
+    ranges: []
+    vrf: 1
+    loopback: true
+  3:
+    prefix: 10.0.0.0/24
+    addresses: []
+    children: []
+    ranges: []
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let holder = TopologyHolder::offline(topology);
+    let core = holder
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let edge = holder
+        .devices_by_id(DeviceId(2))
+        .await
+        .expect("device not found");
+    let parse = |ip: &str| ip.parse::<IpNet>().expect("invalid address");
+    assert_eq!(
+        vec![(parse("fd00:ff::1/128"), Some(VrfId(1)))],
+        next_loopback_addresses(&core).into_vec()
+    );
+    assert_eq!(
+        vec![
+            (parse("10.255.0.2/32"), None),
+            (parse("fd00:ff::1/128"), Some(VrfId(1)))
+        ],
+        next_loopback_addresses(&edge).into_vec()
+    );
+
+    // the router id is taken from the loopback interface before the primary address
+    assert_eq!(Some(Ipv4Addr::new(10, 255, 0, 1)), router_id(&core));
+    assert_eq!(Some(Ipv4Addr::new(10, 0, 0, 2)), router_id(&edge));
+}
+
+#[tokio::test]
+async fn test_rollout_stages() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: controller
+    wlan_controller_of: 1
+  2:
+    name: ap-b
+    wlan_ap_of: 1
+  3:
+    name: ap-a
+    wlan_ap_of: 1
+  4:
+    name: switch
+wlan_groups:
+  1:
+    controller: 1
+    aps: [2, 3]
+    wlans: []
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let devices = TopologyHolder::offline(topology).devices().await;
+    let stages = rollout_stages(&devices)
+        .iter()
+        .map(|(device, stage)| (device.name().to_string(), *stage))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            ("controller".to_string(), 0),
+            ("switch".to_string(), 0),
+            ("ap-a".to_string(), 1),
+            ("ap-b".to_string(), 1),
+        ],
+        stages
+    );
+}
+
+#[tokio::test]
+async fn test_rollout_aborts_after_canary() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: a
+    status: planned
+  2:
+    name: b
+    status: planned
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let devices = TopologyHolder::offline(topology).devices().await;
+    let rollouts: &'static Rollouts = Box::leak(Box::default());
+    let id = rollouts.start(
+        "default",
+        &devices,
+        RolloutOptions::default(),
+        Some("alice"),
+    );
+    let steps = rollouts
+        .subscribe(id, Some("alice"), TenantScope::All)
+        .expect("rollout not found")
+        .map(|event| event.step())
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(
+        vec![
+            RolloutStep::Failed,
+            RolloutStep::Skipped,
+            RolloutStep::Aborted
+        ],
+        steps
+    );
+    assert!(
+        rollouts
+            .subscribe(id + 1, Some("alice"), TenantScope::All)
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn test_rollout_ownership() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: a
+    status: planned
+    tenant: a
+  2:
+    name: b
+    status: planned
+    tenant: b
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let devices = TopologyHolder::offline(topology).devices().await;
+    let rollouts: &'static Rollouts = Box::leak(Box::default());
+    let id = rollouts.start(
+        "default",
+        &devices,
+        RolloutOptions::default(),
+        Some("alice"),
+    );
+    let tenants =
+        |tenants: &[&str]| TenantScope::Tenants(tenants.iter().copied().map(Box::from).collect());
+    assert!(
+        rollouts
+            .subscribe(id, Some("bob"), tenants(&["a"]))
+            .is_none()
+    );
+    assert!(rollouts.subscribe(id, None, tenants(&["a"])).is_none());
+    let steps = rollouts
+        .subscribe(id, Some("bob"), tenants(&["a", "b"]))
+        .expect("rollout not found")
+        .map(|event| event.step())
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(Some(&RolloutStep::Aborted), steps.last());
+    let steps = rollouts
+        .subscribe(id, Some("alice"), tenants(&["a"]))
+        .expect("rollout not found")
+        .collect::<Vec<_>>()
+        .await;
+    assert!(
+        steps
+            .iter()
+            .all(|event| event.step() == RolloutStep::Aborted || event.visible_to(&tenants(&["a"])))
+    );
+    assert_eq!(
+        Some(RolloutStep::Aborted),
+        steps.last().map(|event| event.step())
+    );
+}
+
+#[test]
+fn test_convergence_problem() {
+    let mut result = ApplyResult::new(ProvisionPlan::default(), 3, None);
+    result.record_verification(Ok(Box::default()));
+    assert!(result.converged());
+    assert_eq!(None, result.convergence_problem());
+
+    let mut result = ApplyResult::new(ProvisionPlan::default(), 3, None);
+    result.record_verification(Ok(Box::new([PlanStep::new(
+        0,
+        "ip/address",
+        "/ip/address add interface=vlan10",
+    )])));
+    assert!(!result.converged());
+    assert_eq!(
+        Some("Not converged, 1 steps left after apply"),
+        result.convergence_problem().as_deref()
+    );
+
+    let mut result = ApplyResult::new(ProvisionPlan::default(), 3, None);
+    result.record_verification(Err("10.0.0.1 does not answer pings".into()));
+    assert!(!result.converged());
+    assert_eq!(
+        Some("10.0.0.1 does not answer pings"),
+        result.convergence_problem().as_deref()
+    );
+}
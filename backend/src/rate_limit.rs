@@ -0,0 +1,141 @@
+use crate::{
+    Error,
+    config::CONFIG,
+    context::UserInfo,
+    topology::{
+        DeviceId,
+        access::{AccessTopology, device::DeviceAccess},
+    },
+};
+use async_graphql::{
+    Context, Request, ServerResult,
+    async_trait::async_trait,
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest},
+};
+use lazy_static::lazy_static;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum RateLimitKey {
+    User(Box<str>),
+    /// device ids are only unique within their netbox instance
+    Device(Box<str>, DeviceId),
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token bucket limiter, every key gets its own bucket
+pub struct RateLimiter {
+    capacity: f64,
+    refill_interval: Duration,
+    buckets: Mutex<HashMap<RateLimitKey, TokenBucket>>,
+}
+
+impl RateLimiter {
+    /// `capacity` tokens are available at once, one token is refilled every `refill_interval`
+    pub fn new(capacity: u32, refill_interval: Duration) -> Self {
+        Self {
+            capacity: capacity as f64,
+            refill_interval,
+            buckets: Mutex::default(),
+        }
+    }
+    fn try_acquire(&self, key: RateLimitKey) -> bool {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().expect("rate limiter poisoned");
+        let bucket = buckets.entry(key).or_insert(TokenBucket {
+            tokens: self.capacity,
+            updated: now,
+        });
+        let refilled = (now - bucket.updated).as_secs_f64() / self.refill_interval.as_secs_f64();
+        bucket.tokens = (bucket.tokens + refilled).min(self.capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+lazy_static! {
+    static ref USER_LIMITER: RateLimiter = RateLimiter::new(
+        CONFIG.load().rate_limit_burst(),
+        CONFIG.load().rate_limit_user_interval()
+    );
+    static ref DEVICE_LIMITER: RateLimiter = RateLimiter::new(
+        CONFIG.load().rate_limit_burst(),
+        CONFIG.load().rate_limit_device_interval()
+    );
+}
+
+/// Tokens already taken by the current request
+#[derive(Default)]
+struct RequestCharges {
+    user: bool,
+    devices: HashSet<RateLimitKey>,
+}
+
+/// Takes a token for the current user and for the device, fails if one of them is exhausted
+///
+/// A request takes a single user token and a single token per device, no matter how many of
+/// its resolvers access the device. Without the [`RequestRateLimit`] extension every call takes
+/// its tokens.
+pub fn check_device_access(ctx: &Context<'_>, device: &DeviceAccess) -> Result<(), Error> {
+    let key = RateLimitKey::Device(device.topology().instance_name().into(), device.id());
+    let charges = ctx.data_opt::<Arc<Mutex<RequestCharges>>>();
+    let mut charges = charges.map(|c| c.lock().expect("request charges poisoned"));
+    if charges
+        .as_ref()
+        .is_some_and(|charges| charges.devices.contains(&key))
+    {
+        return Ok(());
+    }
+    let charge_user = !charges.as_ref().is_some_and(|charges| charges.user);
+    if let Some(user) = ctx.data_opt::<UserInfo>().filter(|_| charge_user) {
+        if !USER_LIMITER.try_acquire(RateLimitKey::User(user.name.as_str().into())) {
+            return Err(Error::RateLimited);
+        }
+    }
+    if !DEVICE_LIMITER.try_acquire(key.clone()) {
+        return Err(Error::RateLimited);
+    }
+    if let Some(charges) = &mut charges {
+        charges.user = true;
+        charges.devices.insert(key);
+    }
+    Ok(())
+}
+
+/// Schema extension giving each request its own record of the tokens taken
+pub struct RequestRateLimit;
+
+impl ExtensionFactory for RequestRateLimit {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(RequestRateLimitExtension)
+    }
+}
+
+struct RequestRateLimitExtension;
+
+#[async_trait]
+impl Extension for RequestRateLimitExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        let charges = Arc::new(Mutex::new(RequestCharges::default()));
+        next.run(ctx, request.data(charges)).await
+    }
+}
@@ -0,0 +1,75 @@
+//! Secrets the generated configuration sends to the devices, like the RADIUS secret of the wlan
+//! controllers. They are kept out of the configuration file, every secret is a file named like
+//! the secret in the secrets directory, the way docker and kubernetes mount them.
+//!
+//! Every secret read is remembered and masked in the scripts shown to the users, only the
+//! scripts applied to the devices contain it.
+use lazy_static::lazy_static;
+use std::{collections::HashSet, path::Path, sync::RwLock};
+use thiserror::Error;
+
+#[cfg(test)]
+mod test;
+
+/// replaces a secret in the shown scripts
+pub const SECRET_MASK: &str = "********";
+
+#[derive(Error, Debug)]
+pub enum SecretError {
+    #[error("Invalid secret name {0}")]
+    InvalidName(Box<str>),
+    #[error("Cannot read secret {name}: {error}")]
+    Read {
+        name: Box<str>,
+        error: std::io::Error,
+    },
+    #[error("Secret {0} is empty")]
+    Empty(Box<str>),
+}
+
+lazy_static! {
+    static ref READ_SECRETS: RwLock<HashSet<Box<str>>> = RwLock::default();
+}
+
+/// Reads the secret from the directory, see `Settings::secrets_dir`. The name may only consist
+/// of letters, digits, `-`, `_` and `.`, it can come from netbox and must not point outside the
+/// directory.
+pub fn read_secret(dir: &Path, name: &str) -> Result<Box<str>, SecretError> {
+    let valid = !name.starts_with('.')
+        && !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(SecretError::InvalidName(name.into()));
+    }
+    let contents = std::fs::read_to_string(dir.join(name)).map_err(|error| SecretError::Read {
+        name: name.into(),
+        error,
+    })?;
+    // files written by an editor end with a newline
+    let secret = contents.trim_end_matches(['\r', '\n']);
+    if secret.is_empty() {
+        return Err(SecretError::Empty(name.into()));
+    }
+    READ_SECRETS
+        .write()
+        .expect("secrets poisoned")
+        .insert(secret.into());
+    Ok(secret.into())
+}
+
+/// the text with every secret read so far replaced by [`SECRET_MASK`]
+pub fn mask_secrets(text: &str) -> String {
+    let secrets = READ_SECRETS.read().expect("secrets poisoned");
+    let mut masked = text.to_string();
+    // longer secrets first, a secret containing another one is masked as a whole
+    let mut secrets = secrets.iter().collect::<Vec<_>>();
+    secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+    for secret in secrets {
+        if masked.contains(secret.as_ref()) {
+            masked = masked.replace(secret.as_ref(), SECRET_MASK);
+        }
+    }
+    masked
+}
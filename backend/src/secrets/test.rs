@@ -0,0 +1,47 @@
+use crate::secrets::{SECRET_MASK, SecretError, mask_secrets, read_secret};
+use std::path::PathBuf;
+
+fn secrets_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("provisioner-secrets-{name}"));
+    std::fs::create_dir_all(&dir).expect("cannot create secrets dir");
+    dir
+}
+
+#[test]
+fn test_read_secret() {
+    let dir = secrets_dir("read");
+    std::fs::write(dir.join("radius"), "s3cr3t-radius\n").expect("cannot write secret");
+    std::fs::write(dir.join("empty"), "\n").expect("cannot write secret");
+    assert_eq!(
+        "s3cr3t-radius",
+        read_secret(&dir, "radius")
+            .expect("secret not read")
+            .as_ref()
+    );
+    assert!(matches!(
+        read_secret(&dir, "missing"),
+        Err(SecretError::Read { .. })
+    ));
+    assert!(matches!(
+        read_secret(&dir, "empty"),
+        Err(SecretError::Empty(_))
+    ));
+    for name in ["../radius", "/etc/passwd", ".hidden", ""] {
+        assert!(matches!(
+            read_secret(&dir, name),
+            Err(SecretError::InvalidName(_))
+        ));
+    }
+}
+
+#[test]
+fn test_mask_secrets() {
+    let dir = secrets_dir("mask");
+    std::fs::write(dir.join("pppoe"), "p4ss-pppoe").expect("cannot write secret");
+    read_secret(&dir, "pppoe").expect("secret not read");
+    assert_eq!(
+        format!("/interface pppoe-client add password=\"{SECRET_MASK}\" user=\"cpe\"\n"),
+        mask_secrets("/interface pppoe-client add password=\"p4ss-pppoe\" user=\"cpe\"\n")
+    );
+    assert_eq!("nothing secret", mask_secrets("nothing secret"));
+}
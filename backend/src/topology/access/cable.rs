@@ -1,3 +1,4 @@
+use crate::policy::TenantScope;
 use crate::topology::{
     Cable, CableId, CablePort, Topology,
     access::{
@@ -5,6 +6,7 @@ use crate::topology::{
         front_port::FrontPortAccess, interface::InterfaceAccess, rear_port::RearPortAccess,
     },
 };
+use async_graphql::{Context, Object, Union};
 use std::sync::Arc;
 
 #[derive(Clone, PartialEq, Eq)]
@@ -13,7 +15,7 @@ pub struct CableAccess {
     id: CableId,
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Union)]
 pub enum CablePortAccess {
     Interface(InterfaceAccess),
     FrontPort(FrontPortAccess),
@@ -106,6 +108,28 @@ impl CableAccess {
     }
 }
 
+#[Object]
+impl CableAccess {
+    #[graphql(name = "id")]
+    async fn api_id(&self) -> u32 {
+        self.id.0
+    }
+    #[graphql(name = "portA")]
+    async fn api_port_a(&self, ctx: &Context<'_>) -> Box<[CablePortAccess]> {
+        let scope = TenantScope::of(ctx);
+        self.port_a()
+            .filter(|port| scope.allows_port(port))
+            .collect()
+    }
+    #[graphql(name = "portB")]
+    async fn api_port_b(&self, ctx: &Context<'_>) -> Box<[CablePortAccess]> {
+        let scope = TenantScope::of(ctx);
+        self.port_b()
+            .filter(|port| scope.allows_port(port))
+            .collect()
+    }
+}
+
 impl CablePortAccess {
     pub fn name(&self) -> Option<&str> {
         match self {
@@ -4,6 +4,7 @@ use crate::topology::access::{
     front_port::FrontPortAccess,
     rear_port::RearPortAccess,
 };
+use async_graphql::Object;
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct CableConnection {
@@ -32,6 +33,42 @@ pub struct CablePath {
     pub end_port: Option<CablePortAccess>,
 }
 
+/// one cable of a path, `near` is the end towards the start of the path
+#[Object]
+impl CableConnection {
+    async fn near(&self) -> &CablePortAccess {
+        &self.near
+    }
+    async fn far(&self) -> &CablePortAccess {
+        &self.far
+    }
+    async fn cable(&self) -> &CableAccess {
+        &self.cable
+    }
+}
+
+/// path through cables and patch panels, `endPort` is missing if the path ends at an unconnected port
+#[Object]
+impl CablePath {
+    #[graphql(name = "startPort")]
+    async fn api_start_port(&self) -> &CablePortAccess {
+        &self.start_port
+    }
+    #[graphql(name = "segments")]
+    async fn api_segments(&self) -> &[CableConnection] {
+        &self.cable_segments
+    }
+    #[graphql(name = "endPort")]
+    async fn api_end_port(&self) -> Option<&CablePortAccess> {
+        self.end_port.as_ref()
+    }
+    /// last port reached by the path
+    #[graphql(name = "farPort")]
+    async fn api_far_port(&self) -> &CablePortAccess {
+        self.far_port()
+    }
+}
+
 impl CablePath {
     pub fn far_port(&self) -> &CablePortAccess {
         if let Some(end_port) = self.end_port.as_ref() {
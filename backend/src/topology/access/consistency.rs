@@ -0,0 +1,184 @@
+use crate::{
+    ipcalc::GapFinder,
+    policy::TenantScope,
+    topology::{
+        IpAddressId, IpPrefixId, Topology, VrfId,
+        access::{AccessTopology, ip_addresses::IpAddressAccess, ip_prefix::IpPrefixAccess},
+    },
+};
+use async_graphql::{Enum, SimpleObject};
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, Ipv4Addr},
+    sync::Arc,
+};
+
+/// Kind of inconsistency in the ip address management of netbox
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FindingKind {
+    /// the same address in the same vrf is assigned to more than one interface
+    DuplicateAddress,
+    /// prefixes of the same vrf overlap without being parent and child
+    OverlappingPrefix,
+    /// a dhcp range of a prefix contains statically assigned addresses
+    DhcpRangeOverlap,
+}
+
+/// Inconsistency found in the topology, referencing the involved netbox objects
+#[derive(SimpleObject)]
+pub struct ConsistencyFinding {
+    kind: FindingKind,
+    message: Box<str>,
+    addresses: Box<[IpAddressAccess]>,
+    prefix_ids: Box<[u32]>,
+    range_ids: Box<[u32]>,
+}
+
+impl ConsistencyFinding {
+    pub fn kind(&self) -> FindingKind {
+        self.kind
+    }
+    pub fn addresses(&self) -> &[IpAddressAccess] {
+        &self.addresses
+    }
+    pub fn prefix_ids(&self) -> &[u32] {
+        &self.prefix_ids
+    }
+    pub fn range_ids(&self) -> &[u32] {
+        &self.range_ids
+    }
+}
+
+impl Topology {
+    /// Checks the addresses, prefixes and ip ranges for conflicts netbox does not prevent. Only
+    /// findings whose addresses and prefixes are all visible in the scope are returned.
+    pub fn check_consistency(self: &Arc<Self>, scope: &TenantScope) -> Box<[ConsistencyFinding]> {
+        let mut findings = Vec::new();
+        self.find_duplicate_addresses(&mut findings);
+        self.find_overlapping_prefixes(&mut findings);
+        self.find_dhcp_range_overlaps(&mut findings);
+        findings.retain(|finding| {
+            finding
+                .addresses
+                .iter()
+                .all(|address| self.address_in_scope(address.id(), scope))
+                && finding
+                    .prefix_ids
+                    .iter()
+                    .all(|id| self.prefix_in_scope(IpPrefixId(*id), scope))
+        });
+        findings.into_boxed_slice()
+    }
+
+    fn find_duplicate_addresses(self: &Arc<Self>, findings: &mut Vec<ConsistencyFinding>) {
+        let mut assigned = BTreeMap::<(Option<VrfId>, IpAddr), Vec<IpAddressId>>::new();
+        for (id, address) in &self.ip_addresses {
+            if address.interface.is_some() {
+                assigned
+                    .entry((address.vrf, address.ip.addr()))
+                    .or_default()
+                    .push(*id);
+            }
+        }
+        for ((_, ip), mut ids) in assigned {
+            if ids.len() < 2 {
+                continue;
+            }
+            ids.sort();
+            findings.push(ConsistencyFinding {
+                kind: FindingKind::DuplicateAddress,
+                message: format!("{ip} is assigned to {} interfaces", ids.len()).into(),
+                addresses: ids
+                    .into_iter()
+                    .map(|id| IpAddressAccess::create(self.clone(), id))
+                    .collect(),
+                prefix_ids: Box::new([]),
+                range_ids: Box::new([]),
+            });
+        }
+    }
+
+    fn find_overlapping_prefixes(self: &Arc<Self>, findings: &mut Vec<ConsistencyFinding>) {
+        let mut prefixes = self
+            .ip_prefixes
+            .iter()
+            .map(|(id, prefix)| (prefix.vrf, prefix.prefix, *id))
+            .collect::<Vec<_>>();
+        prefixes.sort();
+        for (idx, (vrf, net, id)) in prefixes.iter().enumerate() {
+            // sorted by network, all prefixes overlapping a prefix follow it directly
+            for (other_vrf, other_net, other_id) in &prefixes[idx + 1..] {
+                if other_vrf != vrf || !net.contains(&other_net.network()) {
+                    break;
+                }
+                if self.is_ancestor(*id, *other_id) || self.is_ancestor(*other_id, *id) {
+                    continue;
+                }
+                findings.push(ConsistencyFinding {
+                    kind: FindingKind::OverlappingPrefix,
+                    message: format!("{net} overlaps {other_net}").into(),
+                    addresses: Box::new([]),
+                    prefix_ids: Box::new([id.0, other_id.0]),
+                    range_ids: Box::new([]),
+                });
+            }
+        }
+    }
+
+    fn is_ancestor(&self, ancestor: IpPrefixId, prefix: IpPrefixId) -> bool {
+        let mut current = self.ip_prefixes.get(&prefix).and_then(|p| p.parent);
+        while let Some(parent) = current {
+            if parent == ancestor {
+                return true;
+            }
+            current = self.ip_prefixes.get(&parent).and_then(|p| p.parent);
+        }
+        false
+    }
+
+    fn find_dhcp_range_overlaps(self: &Arc<Self>, findings: &mut Vec<ConsistencyFinding>) {
+        let mut prefix_ids = self.ip_prefixes.keys().copied().collect::<Vec<_>>();
+        prefix_ids.sort();
+        for prefix_id in prefix_ids {
+            let prefix = IpPrefixAccess::create(self.clone(), prefix_id);
+            let statics = prefix
+                .ips()
+                .into_iter()
+                .filter_map(|address| match address.addr() {
+                    Some(IpAddr::V4(ip)) => Some((ip, address)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            let mut gap_finder = GapFinder::<Ipv4Addr>::new();
+            for (ip, _) in &statics {
+                gap_finder.reserve_ipv4(*ip);
+            }
+            for range in prefix.ranges().iter().filter(|range| range.is_dhcp()) {
+                let (Some(IpAddr::V4(start)), Some(IpAddr::V4(end))) = (range.start(), range.end())
+                else {
+                    continue;
+                };
+                // a range without static addresses is a single gap
+                if gap_finder.gaps(start..end).eq([start..end]) {
+                    continue;
+                }
+                let inside = statics
+                    .iter()
+                    .filter(|(ip, _)| (start..=end).contains(ip))
+                    .map(|(_, address)| address.clone())
+                    .collect::<Box<[_]>>();
+                findings.push(ConsistencyFinding {
+                    kind: FindingKind::DhcpRangeOverlap,
+                    message: format!(
+                        "dhcp range {start}-{end} contains {} assigned addresses",
+                        inside.len()
+                    )
+                    .into(),
+                    addresses: inside,
+                    prefix_ids: Box::new([prefix_id.0]),
+                    range_ids: Box::new([range.id().0]),
+                });
+            }
+        }
+    }
+}
@@ -1,17 +1,31 @@
 use crate::{
-    device::{AccessibleDevice, Credentials},
+    Error,
+    device::{
+        AccessibleDevice, Credentials,
+        availability::{AVAILABILITY, DeviceAvailability},
+        backoff::{BACKOFF, ConnectionFailures},
+        facts::DeviceFacts,
+    },
+    graphql::connection_limit::ConnectionLimit,
+    rate_limit::check_device_access,
     topology::{
-        CablePort, Device, DeviceId, Topology,
+        CablePort, Device, DeviceId, DeviceStatus, Topology,
         access::{
-            AccessTopology, AdhocCredentials, interface::InterfaceAccess,
-            ip_addresses::IpAddressAccess, vlan::VlanAccess, vxlan::VxlanAccess,
+            AccessTopology, AdhocCredentials, front_port::FrontPortAccess,
+            interface::InterfaceAccess, ip_addresses::IpAddressAccess, location::LocationAccess,
+            rear_port::RearPortAccess, site::SiteAccess, vlan::VlanAccess, vxlan::VxlanAccess,
             wlan_group::WlanGroupAccess,
         },
     },
 };
-use async_graphql::Object;
+use async_graphql::{Context, Object};
+use ipnet::IpNet;
 use log::error;
-use std::{collections::HashSet, net::IpAddr, sync::Arc};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    net::IpAddr,
+    sync::Arc,
+};
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct DeviceAccess {
@@ -67,6 +81,43 @@ impl DeviceAccess {
             .map(self.create_access())
     }
 
+    pub fn management_interfaces(&self) -> Box<[InterfaceAccess]> {
+        self.interfaces()
+            .into_iter()
+            .filter(InterfaceAccess::is_management)
+            .collect()
+    }
+    /// out-of-band address of the device, falls back to the first address of a management
+    /// interface
+    pub fn oob_ip(&self) -> Option<IpAddressAccess> {
+        self.data()
+            .and_then(|d| d.oob_ip)
+            .map(self.create_access())
+            .or_else(|| {
+                self.management_interfaces()
+                    .iter()
+                    .flat_map(|interface| interface.ips())
+                    .next()
+            })
+    }
+    /// address the provisioner connects to, the out-of-band address is preferred
+    pub fn management_ip(&self) -> Option<IpAddr> {
+        self.oob_ip()
+            .and_then(|ip| ip.addr())
+            .or_else(|| self.primary_ip())
+    }
+    /// networks of the management interfaces, service access is restricted to them
+    pub fn management_prefixes(&self) -> Box<[IpNet]> {
+        self.management_interfaces()
+            .iter()
+            .flat_map(|interface| interface.ips())
+            .filter_map(|ip| ip.net())
+            .map(|net| net.trunc())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
     pub fn loopback_ip(&self) -> Option<IpAddressAccess> {
         self.data()
             .and_then(|d| d.loopback_ip)
@@ -76,9 +127,59 @@ impl DeviceAccess {
     pub fn has_routeros(&self) -> bool {
         self.data().map(|d| d.has_routeros).unwrap_or(false)
     }
+    /// device is tagged with `monitor` in netbox
+    pub fn is_monitored(&self) -> bool {
+        self.data().map(|d| d.monitor).unwrap_or(false)
+    }
+    /// upnp is enabled by the netbox custom field `upnp`
+    pub fn is_upnp(&self) -> bool {
+        self.data().map(|d| d.upnp).unwrap_or(false)
+    }
+    /// igmp proxy is enabled by the netbox custom field `igmp_proxy`
+    pub fn is_igmp_proxy(&self) -> bool {
+        self.data().map(|d| d.igmp_proxy).unwrap_or(false)
+    }
+    /// slug of the netbox device role
+    pub fn role(&self) -> Option<&str> {
+        self.data().and_then(|d| d.role.as_deref())
+    }
+    pub fn status(&self) -> DeviceStatus {
+        self.data().map(|d| d.status).unwrap_or_default()
+    }
+    /// slug of the netbox tenant
+    pub fn tenant(&self) -> Option<&str> {
+        self.data().and_then(|d| d.tenant.as_deref())
+    }
+    pub fn site(&self) -> Option<SiteAccess> {
+        self.data().and_then(|d| d.site).map(self.create_access())
+    }
+    pub fn location(&self) -> Option<LocationAccess> {
+        self.data()
+            .and_then(|d| d.location)
+            .map(self.create_access())
+    }
+    /// slugs of the netbox tags of the device
+    pub fn tags(&self) -> &[Box<str>] {
+        self.data().map(|d| d.tags.as_ref()).unwrap_or_default()
+    }
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().iter().any(|t| t.as_ref() == tag)
+    }
+    /// names of the netwatch profiles enabled by tags
+    pub fn netwatch_profiles(&self) -> &[Box<str>] {
+        self.data()
+            .map(|d| d.netwatch_profiles.as_ref())
+            .unwrap_or_default()
+    }
     pub fn credentials(&self) -> Option<&str> {
         self.data().and_then(|d| Device::credentials(d))
     }
+    /// pppoe account of the device in the configuration, named like the device by default
+    pub fn pppoe_credentials(&self) -> &str {
+        self.data()
+            .and_then(|d| d.pppoe_credentials.as_deref())
+            .unwrap_or(self.name())
+    }
     pub fn interfaces<'a>(&'a self) -> Box<[InterfaceAccess]> {
         self.topology
             .devices
@@ -99,6 +200,34 @@ impl DeviceAccess {
             })
             .unwrap_or_default()
     }
+    pub fn front_ports(&self) -> Box<[FrontPortAccess]> {
+        self.data()
+            .map(|data| {
+                data.ports
+                    .iter()
+                    .filter_map(|p| match p {
+                        CablePort::FrontPort(id) => Some(*id),
+                        _ => None,
+                    })
+                    .map(self.create_access())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    pub fn rear_ports(&self) -> Box<[RearPortAccess]> {
+        self.data()
+            .map(|data| {
+                data.ports
+                    .iter()
+                    .filter_map(|p| match p {
+                        CablePort::RearPort(id) => Some(*id),
+                        _ => None,
+                    })
+                    .map(self.create_access())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
     pub fn wlan_controller_of(&self) -> Option<WlanGroupAccess> {
         self.data()
             .and_then(|d| d.wlan_controller_of)
@@ -120,6 +249,102 @@ impl DeviceAccess {
     pub fn vxlan(&self) -> HashSet<VxlanAccess> {
         self.vlans().filter_map(|vl| vl.vxlan()).collect()
     }
+    /// other members of the vxlans this device is a member of, their vtep lists contain it
+    pub fn vxlan_peers(&self) -> Box<[DeviceAccess]> {
+        let mut peers = BTreeMap::new();
+        for vxlan in self
+            .topology
+            .vxlans
+            .keys()
+            .copied()
+            .map(self.create_access::<_, VxlanAccess>())
+        {
+            let members = vxlan.members();
+            if members.iter().any(|member| member.id == self.id) {
+                for member in members {
+                    if member.id != self.id {
+                        peers.insert(member.id(), member);
+                    }
+                }
+            }
+        }
+        peers.into_values().collect()
+    }
+
+    /// the router on the prefix of the primary ipv4 address, virtual router addresses are preferred
+    pub fn upstream_gateway(&self) -> Option<IpAddr> {
+        let primary_ip = self.primary_ip_v4()?;
+        let mut candidates = primary_ip
+            .prefix()?
+            .ips()
+            .into_iter()
+            .filter(|ip| ip != &primary_ip)
+            .filter(|ip| {
+                ip.is_virtual()
+                    || ip
+                        .interface()
+                        .and_then(|i| i.device())
+                        .map(|d| d.id != self.id && d.has_routeros())
+                        .unwrap_or(false)
+            })
+            .filter_map(|ip| ip.addr().map(|addr| (!ip.is_virtual(), addr)))
+            .collect::<Vec<_>>();
+        candidates.sort();
+        candidates.into_iter().next().map(|(_, addr)| addr)
+    }
+
+    /// management address of the wlan controller if the device is an access point
+    pub fn wlan_controller_address(&self) -> Option<IpAddr> {
+        self.wlan_ap_of()
+            .and_then(|group| group.controller())
+            .and_then(|controller| controller.primary_ip())
+    }
+
+    /// connects to the device, falling back to the management address and the configured
+    /// credentials
+    pub async fn connect(
+        &self,
+        target: Option<IpAddr>,
+        credentials: Option<Credentials>,
+    ) -> Result<AccessibleDevice, Error> {
+        let address = target
+            .or_else(|| self.management_ip())
+            .ok_or(Error::MissingManagementAddress)?;
+        let credentials = credentials
+            .or_else(|| {
+                self.credentials()
+                    .map(|cred| Credentials::Named(cred.to_string().into()))
+            })
+            .ok_or(Error::MissingCredentials)?;
+        let result = AccessibleDevice::create_client(self.clone(), address, credentials).await;
+        match &result {
+            Ok(_) => BACKOFF.record_success(address),
+            Err(error @ Error::MikrotikApi(_)) => {
+                BACKOFF.record_failure(address, &error.to_string())
+            }
+            Err(_) => {}
+        }
+        result
+    }
+    /// Fails while fleet operations back off from the device after repeated connection
+    /// failures. Operations started for the single device connect anyway, a success ends the
+    /// backoff.
+    pub fn check_backoff(&self) -> Result<(), Error> {
+        let Some(address) = self.management_ip() else {
+            return Ok(());
+        };
+        match BACKOFF.remaining(address) {
+            None => Ok(()),
+            Some(remaining) => Err(Error::BackingOff {
+                device: self.name().into(),
+                failures: BACKOFF
+                    .failures(address)
+                    .map(|failures| failures.consecutive_failures())
+                    .unwrap_or_default(),
+                retry_in_secs: remaining.as_secs(),
+            }),
+        }
+    }
 }
 
 #[Object]
@@ -133,41 +358,70 @@ impl DeviceAccess {
         self.name()
     }
     async fn management_address(&self) -> Option<IpAddressAccess> {
-        self.data()
-            .and_then(|a| a.primary_ip)
-            .map(self.create_access())
+        self.oob_ip().or_else(|| {
+            self.data()
+                .and_then(|a| a.primary_ip)
+                .map(self.create_access())
+        })
+    }
+    #[graphql(name = "status")]
+    async fn api_status(&self) -> DeviceStatus {
+        self.status()
+    }
+    #[graphql(name = "role")]
+    async fn api_role(&self) -> Option<&str> {
+        self.role()
+    }
+    #[graphql(name = "site")]
+    async fn api_site(&self) -> Option<SiteAccess> {
+        self.site()
+    }
+    #[graphql(name = "location")]
+    async fn api_location(&self) -> Option<LocationAccess> {
+        self.location()
     }
     #[graphql(name = "serial")]
     async fn api_serial(&self) -> Option<String> {
         self.serial().map(ToString::to_string)
     }
+    #[graphql(name = "tags")]
+    async fn api_tags(&self) -> &[Box<str>] {
+        self.tags()
+    }
+    /// reachability history of the management address, from the pings of the provisioner
+    async fn availability(&self) -> Option<DeviceAvailability> {
+        self.management_ip()
+            .and_then(|address| AVAILABILITY.availability(address))
+    }
+    /// failed connections to the management address, fleet operations back off after some
+    async fn connection_failures(&self) -> Option<ConnectionFailures> {
+        self.management_ip()
+            .and_then(|address| BACKOFF.failures(address))
+    }
     async fn access(
         &self,
+        ctx: &Context<'_>,
         target: Option<String>,
         credential_name: Option<Box<str>>,
         adhoc_credentials: Option<AdhocCredentials>,
-    ) -> Option<AccessibleDevice> {
-        let addr = target.and_then(|ip| ip.parse().ok()).or(self.primary_ip());
-        let credentials = if let Some(credential_name) = credential_name {
-            Some(Credentials::Named(credential_name))
-        } else if let Some(AdhocCredentials { username, password }) = adhoc_credentials {
-            Some(Credentials::Adhoc { username, password })
-        } else {
-            self.credentials()
-                .map(|cred| Credentials::Named(cred.to_string().into()))
-        };
-        if let (Some(address), Some(credentials)) = (addr, credentials) {
-            let client = AccessibleDevice::create_client(self.clone(), address, credentials).await;
-            match client {
-                Ok(c) => Some(c),
-                Err(error) => {
-                    error!("Cannot access device {}: {}", self.name(), error);
-                    None
-                }
+    ) -> Result<Option<AccessibleDevice>, Error> {
+        check_device_access(ctx, self)?;
+        let addr = target.and_then(|ip| ip.parse().ok());
+        let credentials = AdhocCredentials::select(credential_name, adhoc_credentials);
+        let permit = ConnectionLimit::acquire_device(
+            ConnectionLimit::of(ctx).as_ref(),
+            self.topology().instance_name(),
+            self.id(),
+        )
+        .await;
+        Ok(match self.connect(addr, credentials).await {
+            Ok(c) => Some(c.with_permit(permit).with_facts(DeviceFacts::of(ctx))),
+            Err(Error::MissingManagementAddress | Error::MissingCredentials) => None,
+            Err(error) => {
+                error!("Cannot access device {}: {}", self.name(), error);
+                None
             }
-        } else {
-            None
-        }
+        })
     }
     #[graphql(name = "wlanControllerOf")]
     async fn api_is_wlan_controller_of(&self) -> Option<WlanGroupAccess> {
@@ -177,4 +431,16 @@ impl DeviceAccess {
     async fn api_is_wlan_ap_of(&self) -> Option<WlanGroupAccess> {
         self.wlan_ap_of()
     }
+    #[graphql(name = "interfaces")]
+    async fn api_interfaces(&self) -> Box<[InterfaceAccess]> {
+        self.interfaces()
+    }
+    #[graphql(name = "frontPorts")]
+    async fn api_front_ports(&self) -> Box<[FrontPortAccess]> {
+        self.front_ports()
+    }
+    #[graphql(name = "rearPorts")]
+    async fn api_rear_ports(&self) -> Box<[RearPortAccess]> {
+        self.rear_ports()
+    }
 }
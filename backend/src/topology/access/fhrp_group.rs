@@ -0,0 +1,100 @@
+use crate::policy::TenantScope;
+use crate::topology::{
+    FhrpGroupData, FhrpGroupId, FhrpProtocol, InterfaceId, Topology,
+    access::{AccessTopology, interface::InterfaceAccess, ip_addresses::IpAddressAccess},
+};
+use async_graphql::{Context, Object};
+use std::sync::Arc;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct FhrpGroupAccess {
+    topology: Arc<Topology>,
+    id: FhrpGroupId,
+}
+
+impl AccessTopology for FhrpGroupAccess {
+    type Id = FhrpGroupId;
+    type Data = FhrpGroupData;
+
+    fn topology(&self) -> Arc<Topology> {
+        self.topology.clone()
+    }
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn data(&self) -> Option<&Self::Data> {
+        self.topology.fhrp_groups.get(&self.id)
+    }
+
+    fn create(topology: Arc<Topology>, id: Self::Id) -> Self {
+        FhrpGroupAccess { topology, id }
+    }
+}
+
+impl FhrpGroupAccess {
+    /// the VRID of the group
+    pub fn group_id(&self) -> Option<u8> {
+        self.data().map(|d| d.group_id)
+    }
+    pub fn protocol(&self) -> Option<FhrpProtocol> {
+        self.data().map(|d| d.protocol)
+    }
+    pub fn is_vrrp(&self) -> bool {
+        matches!(
+            self.protocol(),
+            Some(FhrpProtocol::Vrrp2 | FhrpProtocol::Vrrp3)
+        )
+    }
+    pub fn virtual_ips(&self) -> Box<[IpAddressAccess]> {
+        self.data()
+            .map(|d| {
+                d.virtual_ips
+                    .iter()
+                    .copied()
+                    .map(self.create_access())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    pub fn priority_of(&self, interface: InterfaceId) -> Option<u8> {
+        self.data().and_then(|d| {
+            d.assignments
+                .iter()
+                .find(|a| a.interface == interface)
+                .map(|a| a.priority)
+        })
+    }
+    pub fn members(&self) -> Box<[InterfaceAccess]> {
+        self.data()
+            .map(|d| {
+                d.assignments
+                    .iter()
+                    .map(|a| a.interface)
+                    .map(self.create_access())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[Object]
+impl FhrpGroupAccess {
+    #[graphql(name = "id")]
+    async fn api_id(&self) -> u32 {
+        self.id.0
+    }
+    #[graphql(name = "groupId")]
+    async fn api_group_id(&self) -> Option<u8> {
+        self.group_id()
+    }
+    #[graphql(name = "virtualIps")]
+    async fn api_virtual_ips(&self) -> Box<[IpAddressAccess]> {
+        self.virtual_ips()
+    }
+    #[graphql(name = "members")]
+    async fn api_members(&self, ctx: &Context<'_>) -> Box<[InterfaceAccess]> {
+        TenantScope::of(ctx).filter_interfaces(self.members())
+    }
+}
@@ -1,7 +1,15 @@
+use crate::policy::TenantScope;
 use crate::topology::{
     FrontPort, FrontPortId, Topology,
-    access::{AccessTopology, cable::CableAccess, device::DeviceAccess, rear_port::RearPortAccess},
+    access::{
+        AccessTopology,
+        cable::{CableAccess, CablePortAccess},
+        connections::CablePath,
+        device::DeviceAccess,
+        rear_port::RearPortAccess,
+    },
 };
+use async_graphql::{Context, Object};
 use std::sync::Arc;
 
 #[derive(Clone, PartialEq, Eq)]
@@ -47,3 +55,33 @@ impl FrontPortAccess {
             .map(self.create_access())
     }
 }
+
+#[Object]
+impl FrontPortAccess {
+    #[graphql(name = "id")]
+    async fn api_id(&self) -> u32 {
+        self.id.0
+    }
+    #[graphql(name = "name")]
+    async fn api_name(&self) -> Option<&str> {
+        self.name()
+    }
+    #[graphql(name = "device")]
+    async fn api_device(&self, ctx: &Context<'_>) -> Option<DeviceAccess> {
+        self.device()
+            .filter(|device| TenantScope::of(ctx).allows(device))
+    }
+    #[graphql(name = "cable")]
+    async fn api_cable(&self) -> Option<CableAccess> {
+        self.cable()
+    }
+    #[graphql(name = "rearPort")]
+    async fn api_rear_port(&self) -> Option<RearPortAccess> {
+        self.rear_port()
+    }
+    /// paths through the cable of this port up to the ports at the far end
+    #[graphql(name = "cablePaths")]
+    async fn api_cable_paths(&self, ctx: &Context<'_>) -> Box<[CablePath]> {
+        TenantScope::of(ctx).filter_paths(CablePortAccess::FrontPort(self.clone()).collect_cables())
+    }
+}
@@ -1,5 +1,6 @@
 use async_graphql::Object;
 use ipnet::IpNet;
+use std::{net::IpAddr, ops::Range};
 
 pub struct IpNetGraphql(IpNet);
 impl From<IpNet> for IpNetGraphql {
@@ -28,3 +29,24 @@ impl IpNetGraphql {
         self.0.to_string()
     }
 }
+
+/// Range of addresses, both ends included
+pub struct IpRangeGraphql(Range<IpAddr>);
+impl From<Range<IpAddr>> for IpRangeGraphql {
+    fn from(range: Range<IpAddr>) -> Self {
+        Self(range)
+    }
+}
+
+#[Object]
+impl IpRangeGraphql {
+    async fn start(&self) -> String {
+        self.0.start.to_string()
+    }
+    async fn end(&self) -> String {
+        self.0.end.to_string()
+    }
+    async fn display(&self) -> String {
+        format!("{}-{}", self.0.start, self.0.end)
+    }
+}
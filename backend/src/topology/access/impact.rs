@@ -0,0 +1,99 @@
+use crate::{
+    policy::TenantScope,
+    topology::{
+        DeviceId,
+        access::{device::DeviceAccess, interface::InterfaceAccess},
+    },
+};
+use async_graphql::{Enum, SimpleObject};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Why the generated configuration of another device depends on the analyzed device
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ImpactReason {
+    /// connected by a cable path, e.g. trunk ports carrying the same vlans
+    Cable,
+    /// member of a shared vxlan, its vtep list contains the analyzed device
+    Vxlan,
+    /// controller or access point of the same wlan group
+    WlanGroup,
+}
+
+/// Cable path between a port of the analyzed device and a port of the impacted device
+#[derive(SimpleObject)]
+pub struct ImpactLink {
+    local: InterfaceAccess,
+    remote: InterfaceAccess,
+}
+
+/// Device whose generated configuration may change together with the analyzed device
+#[derive(SimpleObject)]
+pub struct ImpactedDevice {
+    device: DeviceAccess,
+    reasons: Box<[ImpactReason]>,
+    /// the cable paths, empty if the device is not connected by cable
+    links: Box<[ImpactLink]>,
+}
+
+impl ImpactedDevice {
+    pub fn device(&self) -> &DeviceAccess {
+        &self.device
+    }
+    pub fn reasons(&self) -> &[ImpactReason] {
+        &self.reasons
+    }
+}
+
+#[derive(Default)]
+struct ImpactEntry {
+    reasons: BTreeSet<ImpactReason>,
+    links: Vec<ImpactLink>,
+}
+
+impl DeviceAccess {
+    /// devices whose generated configuration may change with a change of this device, to roll
+    /// out the changes together. Only devices visible in the scope are reported.
+    pub fn impact_analysis(&self, scope: &TenantScope) -> Box<[ImpactedDevice]> {
+        let mut impacted = BTreeMap::<DeviceId, (DeviceAccess, ImpactEntry)>::new();
+        let mut add = |device: DeviceAccess, reason: ImpactReason, link: Option<ImpactLink>| {
+            if device.id() == self.id() || !scope.allows(&device) {
+                return;
+            }
+            let (_, entry) = impacted
+                .entry(device.id())
+                .or_insert_with(|| (device, ImpactEntry::default()));
+            entry.reasons.insert(reason);
+            entry.links.extend(link);
+        };
+        for local in self.interfaces() {
+            for remote in local.connected_interfaces() {
+                if let Some(device) = remote.device() {
+                    let link = ImpactLink {
+                        local: local.clone(),
+                        remote,
+                    };
+                    add(device, ImpactReason::Cable, Some(link));
+                }
+            }
+        }
+        for peer in self.vxlan_peers() {
+            add(peer, ImpactReason::Vxlan, None);
+        }
+        if let Some(group) = self.wlan_controller_of() {
+            for ap in group.aps() {
+                add(ap, ImpactReason::WlanGroup, None);
+            }
+        }
+        if let Some(controller) = self.wlan_ap_of().and_then(|group| group.controller()) {
+            add(controller, ImpactReason::WlanGroup, None);
+        }
+        impacted
+            .into_values()
+            .map(|(device, entry)| ImpactedDevice {
+                device,
+                reasons: entry.reasons.into_iter().collect(),
+                links: entry.links.into_boxed_slice(),
+            })
+            .collect()
+    }
+}
@@ -1,17 +1,25 @@
-use crate::topology::{
-    Interface, InterfaceId, PhysicalPortId, Topology,
-    access::{
-        AccessTopology,
-        cable::{CableAccess, CablePortAccess},
-        device::DeviceAccess,
-        ip_addresses::IpAddressAccess,
-        vlan::VlanAccess,
+use crate::{
+    policy::TenantScope,
+    topology::{
+        Interface, InterfaceId, PhysicalPortId, PortType, Topology, TunnelEncapsulation,
+        access::{
+            AccessTopology,
+            cable::{CableAccess, CablePortAccess},
+            connections::CablePath,
+            device::DeviceAccess,
+            fhrp_group::FhrpGroupAccess,
+            ip_addresses::IpAddressAccess,
+            tunnel::TunnelAccess,
+            vlan::VlanAccess,
+            vrf::VrfAccess,
+        },
     },
 };
-use async_graphql::Object;
-use mikrotik_model::ascii::AsciiString;
+use async_graphql::{Context, Object};
+use ipnet::IpNet;
 use std::{
     fmt::{Debug, Formatter},
+    net::{IpAddr, Ipv4Addr},
     sync::Arc,
 };
 
@@ -67,9 +75,24 @@ impl InterfaceAccess {
     pub fn is_enable_dhcp_server(&self) -> bool {
         self.data().map(|d| d.enable_dhcp_server).unwrap_or(false)
     }
+    /// tagged `dhcp-relay`, the requests are forwarded instead of served by the device
+    pub fn is_enable_dhcp_relay(&self) -> bool {
+        self.data().is_some_and(|d| d.enable_dhcp_relay)
+    }
+    /// the interface or its untagged vlan is tagged `captive-portal`
+    pub fn is_captive_portal(&self) -> bool {
+        self.data().is_some_and(|d| d.captive_portal)
+            || self
+                .untagged_vlan()
+                .is_some_and(|vlan| vlan.is_captive_portal())
+    }
     pub fn external_port(&self) -> Option<PhysicalPortId> {
         self.data().and_then(|d| d.external)
     }
+    /// kind of the interface by its type in netbox, none if the type is not known
+    pub fn port_type(&self) -> Option<PortType> {
+        self.data().and_then(|d| d.port_type)
+    }
     pub fn is_ethernet_port(&self) -> bool {
         self.external_port()
             .map(|p| p.is_ethernet())
@@ -101,30 +124,6 @@ impl InterfaceAccess {
             .unwrap_or_default()
     }
 
-    pub fn interface_name(&self) -> Option<AsciiString> {
-        self.external_port().map(|port| {
-            if let Some(label) = self.label() {
-                let mut name = port.short_name().0.to_vec();
-                name.push(b'-');
-                for char in label.chars() {
-                    if char.is_ascii_alphanumeric() {
-                        name.push(char as u8);
-                    } else {
-                        match char {
-                            '-' | '.' => name.push(b'-'),
-                            'ä' => name.extend_from_slice(b"ae"),
-                            'ö' => name.extend_from_slice(b"oe"),
-                            'ü' => name.extend_from_slice(b"ue"),
-                            _ => {}
-                        }
-                    }
-                }
-                name.into_boxed_slice().into()
-            } else {
-                port.short_name()
-            }
-        })
-    }
     pub fn untagged_vlan(&self) -> Option<VlanAccess> {
         self.data().and_then(|d| d.vlan).map(self.create_access())
     }
@@ -136,9 +135,81 @@ impl InterfaceAccess {
     pub fn bridge(&self) -> Option<InterfaceAccess> {
         self.data().and_then(|d| d.bridge).map(self.create_access())
     }
+    /// out-of-band management interface
+    pub fn is_management(&self) -> bool {
+        self.data().map(|d| d.management).unwrap_or(false)
+    }
     pub fn enable_poe(&self) -> bool {
         self.data().map(|d| d.enable_poe).unwrap_or(false)
     }
+    /// uplink to the internet provider, tagged `wan`
+    pub fn is_wan(&self) -> bool {
+        self.data().is_some_and(|d| d.wan)
+    }
+    /// dials into the provider by pppoe, tagged `pppoe`
+    pub fn is_pppoe(&self) -> bool {
+        self.data().is_some_and(|d| d.pppoe)
+    }
+    /// apn profile of an lte interface, tagged `apn-<name>`
+    pub fn apn(&self) -> Option<&str> {
+        self.data().and_then(|d| d.apn.as_deref())
+    }
+    /// the router of the provider on the prefix of the first ipv4 address, an address of the
+    /// prefix not assigned to this device. Addresses without interface are preferred, they are
+    /// usually documented for the provider side.
+    pub fn wan_gateway(&self) -> Option<Ipv4Addr> {
+        let device = self.data()?.device;
+        self.ips().iter().find_map(|ip| {
+            let IpNet::V4(own) = ip.net()? else {
+                return None;
+            };
+            ip.prefix()?
+                .ips()
+                .iter()
+                .filter(|other| {
+                    other
+                        .interface()
+                        .and_then(|interface| interface.device())
+                        .is_none_or(|other_device| other_device.id() != device)
+                })
+                .filter_map(|other| match other.addr()? {
+                    IpAddr::V4(addr) if addr != own.addr() => {
+                        Some((other.interface().is_some(), addr))
+                    }
+                    _ => None,
+                })
+                .min()
+                .map(|(_, addr)| addr)
+        })
+    }
+    /// schedule switching the poe of the port
+    pub fn schedule(&self) -> Option<&str> {
+        self.data().and_then(|d| d.schedule.as_deref())
+    }
+    pub fn mac_address(&self) -> Option<&str> {
+        self.data().and_then(|d| d.mac_address.as_deref())
+    }
+    /// vrf of the addresses on the interface, the main routing table if none is set
+    pub fn vrf(&self) -> Option<VrfAccess> {
+        self.ips().iter().find_map(|ip| ip.vrf())
+    }
+    pub fn tunnel(&self) -> Option<TunnelAccess> {
+        self.data().and_then(|d| d.tunnel).map(self.create_access())
+    }
+    pub fn is_eoip_tunnel(&self) -> bool {
+        self.tunnel().and_then(|t| t.encapsulation()) == Some(TunnelEncapsulation::Eoip)
+    }
+    pub fn fhrp_groups(&self) -> Box<[FhrpGroupAccess]> {
+        self.data()
+            .map(|d| {
+                d.fhrp_groups
+                    .iter()
+                    .copied()
+                    .map(self.create_access())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
 }
 
 impl Debug for InterfaceAccess {
@@ -164,4 +235,33 @@ impl InterfaceAccess {
     async fn api_ips(&self) -> Box<[IpAddressAccess]> {
         self.ips()
     }
+    #[graphql(name = "fhrpGroups")]
+    async fn api_fhrp_groups(&self) -> Box<[FhrpGroupAccess]> {
+        self.fhrp_groups()
+    }
+    #[graphql(name = "untaggedVlan")]
+    async fn api_untagged_vlan(&self) -> Option<VlanAccess> {
+        self.untagged_vlan()
+    }
+    #[graphql(name = "taggedVlans")]
+    async fn api_tagged_vlans(&self) -> Box<[VlanAccess]> {
+        self.tagged_vlans().collect()
+    }
+    #[graphql(name = "management")]
+    async fn api_management(&self) -> bool {
+        self.is_management()
+    }
+    #[graphql(name = "macAddress")]
+    async fn api_mac_address(&self) -> Option<&str> {
+        self.mac_address()
+    }
+    #[graphql(name = "cable")]
+    async fn api_cable(&self) -> Option<CableAccess> {
+        self.cable()
+    }
+    /// paths through the cable of this interface, including patch panels on the way
+    #[graphql(name = "cablePaths")]
+    async fn api_cable_paths(&self, ctx: &Context<'_>) -> Box<[CablePath]> {
+        TenantScope::of(ctx).filter_paths(self.cable_port().collect_cables())
+    }
 }
@@ -2,7 +2,7 @@ use crate::topology::{
     IpAddressData, IpAddressId, Topology,
     access::{
         AccessTopology, graphql::IpNetGraphql, interface::InterfaceAccess,
-        ip_prefix::IpPrefixAccess,
+        ip_prefix::IpPrefixAccess, vrf::VrfAccess,
     },
 };
 use async_graphql::Object;
@@ -50,6 +50,16 @@ impl IpAddressAccess {
             .and_then(|a| a.interface)
             .map(self.create_access())
     }
+    /// the address is the virtual ip of a fhrp group
+    pub fn is_virtual(&self) -> bool {
+        self.topology
+            .fhrp_groups
+            .values()
+            .any(|g| g.virtual_ips.contains(&self.id))
+    }
+    pub fn vrf(&self) -> Option<VrfAccess> {
+        self.data().and_then(|a| a.vrf).map(self.create_access())
+    }
 }
 
 #[Object]
@@ -57,4 +67,8 @@ impl IpAddressAccess {
     async fn address(&self) -> Option<IpNetGraphql> {
         self.data().map(|d| d.ip.into())
     }
+    #[graphql(name = "vrf")]
+    async fn api_vrf(&self) -> Option<VrfAccess> {
+        self.vrf()
+    }
 }
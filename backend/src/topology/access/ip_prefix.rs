@@ -1,9 +1,18 @@
-use crate::topology::{
-    IpPrefixData, IpPrefixId, Topology,
-    access::{AccessTopology, ip_addresses::IpAddressAccess, ip_ranges::IpRangeAccess},
+use crate::{
+    ipcalc::GapFinder,
+    topology::{
+        IpPrefixData, IpPrefixId, Topology,
+        access::{
+            AccessTopology, ip_addresses::IpAddressAccess, ip_ranges::IpRangeAccess, vrf::VrfAccess,
+        },
+    },
 };
 use ipnet::IpNet;
-use std::sync::Arc;
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    ops::Range,
+    sync::Arc,
+};
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct IpPrefixAccess {
@@ -65,4 +74,110 @@ impl IpPrefixAccess {
     pub fn prefix(&self) -> Option<IpNet> {
         self.data().map(|d| d.prefix)
     }
+    pub fn id(&self) -> IpPrefixId {
+        self.id
+    }
+    pub fn vrf(&self) -> Option<VrfAccess> {
+        self.data().and_then(|d| d.vrf).map(self.create_access())
+    }
+    /// dhcp option set of the prefix, inherited from the nearest parent naming one
+    pub fn dhcp_options(&self) -> Option<Box<str>> {
+        let own = self.data().and_then(|d| d.dhcp_options.clone());
+        own.or_else(|| self.parent().and_then(|parent| parent.dhcp_options()))
+    }
+    /// dhcp servers the relays of the prefix forward to, inherited from the nearest parent
+    /// naming some
+    pub fn dhcp_relay_servers(&self) -> Box<[Ipv4Addr]> {
+        match self.data() {
+            Some(d) if !d.dhcp_relay.is_empty() => d.dhcp_relay.clone(),
+            _ => self
+                .parent()
+                .map(|parent| parent.dhcp_relay_servers())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// ranges of the prefix not covered by ip ranges, child prefixes or assigned addresses
+    pub fn free_ipv4_ranges(&self) -> Box<[Range<Ipv4Addr>]> {
+        let Some(IpNet::V4(net)) = self.prefix() else {
+            return Box::default();
+        };
+        let mut gap_finder = GapFinder::<Ipv4Addr>::new();
+        for range in self.ranges() {
+            if let (Some(IpAddr::V4(start)), Some(IpAddr::V4(end))) = (range.start(), range.end()) {
+                gap_finder.reserve_ipv4_range(start..end);
+            }
+        }
+        for child_prefix in self.children() {
+            if let Some(IpNet::V4(net)) = child_prefix.prefix() {
+                gap_finder.reserve_ipv4_net(net);
+            }
+        }
+        for ip in self.ips() {
+            if let Some(IpAddr::V4(ip)) = ip.addr() {
+                gap_finder.reserve_ipv4(ip)
+            }
+        }
+        gap_finder.find_gaps_ipv4(net).collect()
+    }
+    /// designated for the loopback addresses of the devices
+    pub fn is_loopback(&self) -> bool {
+        self.data().map(|d| d.loopback).unwrap_or(false)
+    }
+    /// first host address of the prefix neither in an ip range, a child prefix nor used by any
+    /// address of the same vrf, as /32 or /128
+    pub fn next_free_host(&self) -> Option<IpNet> {
+        let net = self.prefix()?;
+        let vrf = self.data().and_then(|d| d.vrf);
+        let mut gap_finder = GapFinder::<IpAddr>::new();
+        for range in self.ranges() {
+            if let (Some(start), Some(end)) = (range.start(), range.end()) {
+                gap_finder.reserve_ip_range(start..end);
+            }
+        }
+        for child_prefix in self.children() {
+            if let Some(net) = child_prefix.prefix() {
+                gap_finder.reserve_ipnet(net);
+            }
+        }
+        // host addresses like /32 are not linked to the prefix, all addresses are checked
+        for address in self.topology.ip_addresses.values() {
+            if address.vrf == vrf && net.contains(&address.ip.addr()) {
+                gap_finder.reserve_ip(address.ip.addr());
+            }
+        }
+        let host = gap_finder.find_gaps_ipnet(net).next()?.start;
+        IpNet::new(host, net.max_prefix_len()).ok()
+    }
+    /// ranges of the prefix not covered by ip ranges, child prefixes or assigned addresses, of
+    /// ipv4 and ipv6 prefixes
+    pub fn free_ranges(&self) -> Box<[Range<IpAddr>]> {
+        let Some(net) = self.prefix() else {
+            return Box::default();
+        };
+        let mut gap_finder = GapFinder::<IpAddr>::new();
+        for range in self.ranges() {
+            if let (Some(start), Some(end)) = (range.start(), range.end()) {
+                gap_finder.reserve_ip_range(start..end);
+            }
+        }
+        for child_prefix in self.children() {
+            if let Some(net) = child_prefix.prefix() {
+                gap_finder.reserve_ipnet(net);
+            }
+        }
+        for ip in self.ips() {
+            if let Some(ip) = ip.addr() {
+                gap_finder.reserve_ip(ip)
+            }
+        }
+        gap_finder.find_gaps_ipnet(net).collect()
+    }
+    pub fn next_free_ipv4_addresses(&self, count: usize) -> Box<[Ipv4Addr]> {
+        self.free_ipv4_ranges()
+            .iter()
+            .flat_map(|range| range.start..=range.end)
+            .take(count)
+            .collect()
+    }
 }
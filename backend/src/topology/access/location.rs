@@ -0,0 +1,66 @@
+use crate::topology::{
+    LocationData, LocationId, Topology,
+    access::{AccessTopology, site::SiteAccess},
+};
+use async_graphql::Object;
+use std::sync::Arc;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct LocationAccess {
+    topology: Arc<Topology>,
+    id: LocationId,
+}
+
+impl AccessTopology for LocationAccess {
+    type Id = LocationId;
+    type Data = LocationData;
+
+    fn topology(&self) -> Arc<Topology> {
+        self.topology.clone()
+    }
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn data(&self) -> Option<&Self::Data> {
+        self.topology.locations.get(&self.id)
+    }
+
+    fn create(topology: Arc<Topology>, id: Self::Id) -> Self {
+        LocationAccess { topology, id }
+    }
+}
+
+impl LocationAccess {
+    pub fn name(&self) -> Option<&str> {
+        self.data().map(|d| d.name.as_ref())
+    }
+    pub fn site(&self) -> Option<SiteAccess> {
+        self.data().map(|d| d.site).map(self.create_access())
+    }
+    /// the enclosing location, `None` for top level locations of a site
+    pub fn parent(&self) -> Option<LocationAccess> {
+        self.data().and_then(|d| d.parent).map(self.create_access())
+    }
+}
+
+#[Object]
+impl LocationAccess {
+    #[graphql(name = "id")]
+    async fn api_id(&self) -> u32 {
+        self.id.0
+    }
+    #[graphql(name = "name")]
+    async fn api_name(&self) -> Option<&str> {
+        self.name()
+    }
+    #[graphql(name = "site")]
+    async fn api_site(&self) -> Option<SiteAccess> {
+        self.site()
+    }
+    #[graphql(name = "parent")]
+    async fn api_parent(&self) -> Option<LocationAccess> {
+        self.parent()
+    }
+}
@@ -1,19 +1,35 @@
-use crate::topology::{DeviceId, Topology};
+use crate::{
+    device::Credentials,
+    topology::{DeviceId, IpPrefixId, SiteId, Topology},
+};
 use async_graphql::InputObject;
 use device::DeviceAccess;
-use std::sync::Arc;
+use ip_addresses::IpAddressAccess;
+use ip_prefix::IpPrefixAccess;
+use site::SiteAccess;
+use std::{net::IpAddr, sync::Arc};
+use vlan::VlanAccess;
+use wlan_group::WlanGroupAccess;
 
 pub mod cable;
 pub mod connections;
+pub mod consistency;
 pub mod device;
+pub mod fhrp_group;
 pub mod front_port;
 pub mod graphql;
+pub mod impact;
 pub mod interface;
 pub mod ip_addresses;
 pub mod ip_prefix;
 pub mod ip_ranges;
+pub mod location;
 pub mod rear_port;
+pub mod site;
+pub mod tunnel;
 pub mod vlan;
+pub mod vlan_matrix;
+pub mod vrf;
 pub mod vxlan;
 pub mod wlan;
 pub mod wlan_group;
@@ -49,11 +65,75 @@ impl Topology {
             None
         }
     }
+    pub fn list_wlan_groups(self: &Arc<Self>) -> impl Iterator<Item = WlanGroupAccess> {
+        let topo = self;
+        self.wlan_groups
+            .keys()
+            .copied()
+            .map(move |id| WlanGroupAccess::create(topo.clone(), id))
+    }
+    pub fn list_sites(self: &Arc<Self>) -> impl Iterator<Item = SiteAccess> {
+        let topo = self;
+        self.sites
+            .keys()
+            .copied()
+            .map(move |id| SiteAccess::create(topo.clone(), id))
+    }
+    pub fn get_site_by_id(self: &Arc<Self>, id: &SiteId) -> Option<SiteAccess> {
+        if self.sites.contains_key(id) {
+            Some(SiteAccess::create(self.clone(), *id))
+        } else {
+            None
+        }
+    }
+    pub fn get_ip_prefix_by_id(self: &Arc<Self>, id: &IpPrefixId) -> Option<IpPrefixAccess> {
+        if self.ip_prefixes.contains_key(id) {
+            Some(IpPrefixAccess::create(self.clone(), *id))
+        } else {
+            None
+        }
+    }
+    pub fn list_vlans(self: &Arc<Self>) -> impl Iterator<Item = VlanAccess> {
+        let topo = self;
+        self.vlans
+            .keys()
+            .copied()
+            .map(move |id| VlanAccess::create(topo.clone(), id))
+    }
+    pub fn list_ip_prefixes(self: &Arc<Self>) -> impl Iterator<Item = IpPrefixAccess> {
+        let topo = self;
+        self.ip_prefixes
+            .keys()
+            .copied()
+            .map(move |id| IpPrefixAccess::create(topo.clone(), id))
+    }
+    pub fn find_ip_address(self: &Arc<Self>, addr: IpAddr) -> Option<IpAddressAccess> {
+        self.ip_addresses
+            .iter()
+            .find(|(_, data)| data.ip.addr() == addr)
+            .map(|(id, _)| IpAddressAccess::create(self.clone(), *id))
+    }
 }
 
 #[derive(InputObject)]
-struct AdhocCredentials {
+pub struct AdhocCredentials {
     username: Option<Box<str>>,
     #[graphql(secret)]
     password: Option<Box<str>>,
 }
+
+impl AdhocCredentials {
+    /// credentials chosen by name or given adhoc, none connects with the configured ones
+    pub fn select(
+        credential_name: Option<Box<str>>,
+        adhoc_credentials: Option<AdhocCredentials>,
+    ) -> Option<Credentials> {
+        if let Some(credential_name) = credential_name {
+            Some(Credentials::Named(credential_name))
+        } else if let Some(AdhocCredentials { username, password }) = adhoc_credentials {
+            Some(Credentials::Adhoc { username, password })
+        } else {
+            None
+        }
+    }
+}
@@ -1,9 +1,15 @@
+use crate::policy::TenantScope;
 use crate::topology::{
     RearPort, RearPortId, Topology,
     access::{
-        AccessTopology, cable::CableAccess, device::DeviceAccess, front_port::FrontPortAccess,
+        AccessTopology,
+        cable::{CableAccess, CablePortAccess},
+        connections::CablePath,
+        device::DeviceAccess,
+        front_port::FrontPortAccess,
     },
 };
+use async_graphql::{Context, Object};
 use std::sync::Arc;
 
 #[derive(Clone, PartialEq, Eq)]
@@ -49,3 +55,33 @@ impl RearPortAccess {
         self.data().and_then(|d| d.cable).map(self.create_access())
     }
 }
+
+#[Object]
+impl RearPortAccess {
+    #[graphql(name = "id")]
+    async fn api_id(&self) -> u32 {
+        self.id.0
+    }
+    #[graphql(name = "name")]
+    async fn api_name(&self) -> Option<&str> {
+        self.name()
+    }
+    #[graphql(name = "device")]
+    async fn api_device(&self, ctx: &Context<'_>) -> Option<DeviceAccess> {
+        self.device()
+            .filter(|device| TenantScope::of(ctx).allows(device))
+    }
+    #[graphql(name = "cable")]
+    async fn api_cable(&self) -> Option<CableAccess> {
+        self.cable()
+    }
+    #[graphql(name = "frontPort")]
+    async fn api_front_port(&self) -> Option<FrontPortAccess> {
+        self.front_port()
+    }
+    /// paths through the cable of this port up to the ports at the far end
+    #[graphql(name = "cablePaths")]
+    async fn api_cable_paths(&self, ctx: &Context<'_>) -> Box<[CablePath]> {
+        TenantScope::of(ctx).filter_paths(CablePortAccess::RearPort(self.clone()).collect_cables())
+    }
+}
@@ -0,0 +1,85 @@
+use crate::policy::TenantScope;
+use crate::topology::{
+    SiteData, SiteId, Topology,
+    access::{AccessTopology, device::DeviceAccess, location::LocationAccess},
+};
+use async_graphql::{Context, Object};
+use std::sync::Arc;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct SiteAccess {
+    topology: Arc<Topology>,
+    id: SiteId,
+}
+
+impl AccessTopology for SiteAccess {
+    type Id = SiteId;
+    type Data = SiteData;
+
+    fn topology(&self) -> Arc<Topology> {
+        self.topology.clone()
+    }
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn data(&self) -> Option<&Self::Data> {
+        self.topology.sites.get(&self.id)
+    }
+
+    fn create(topology: Arc<Topology>, id: Self::Id) -> Self {
+        SiteAccess { topology, id }
+    }
+}
+
+impl SiteAccess {
+    pub fn name(&self) -> Option<&str> {
+        self.data().map(|d| d.name.as_ref())
+    }
+    pub fn slug(&self) -> Option<&str> {
+        self.data().map(|d| d.slug.as_ref())
+    }
+    pub fn devices(&self) -> Box<[DeviceAccess]> {
+        self.topology
+            .devices
+            .iter()
+            .filter(|(_, d)| d.site == Some(self.id))
+            .map(|(id, _)| *id)
+            .map(self.create_access())
+            .collect()
+    }
+    pub fn locations(&self) -> Box<[LocationAccess]> {
+        self.topology
+            .locations
+            .iter()
+            .filter(|(_, l)| l.site == self.id)
+            .map(|(id, _)| *id)
+            .map(self.create_access())
+            .collect()
+    }
+}
+
+#[Object]
+impl SiteAccess {
+    #[graphql(name = "id")]
+    async fn api_id(&self) -> u32 {
+        self.id.0
+    }
+    #[graphql(name = "name")]
+    async fn api_name(&self) -> Option<&str> {
+        self.name()
+    }
+    #[graphql(name = "slug")]
+    async fn api_slug(&self) -> Option<&str> {
+        self.slug()
+    }
+    #[graphql(name = "devices")]
+    async fn api_devices(&self, ctx: &Context<'_>) -> Box<[DeviceAccess]> {
+        TenantScope::of(ctx).filter(self.devices())
+    }
+    #[graphql(name = "locations")]
+    async fn api_locations(&self) -> Box<[LocationAccess]> {
+        self.locations()
+    }
+}
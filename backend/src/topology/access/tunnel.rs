@@ -0,0 +1,87 @@
+use crate::topology::{
+    InterfaceId, Topology, TunnelData, TunnelEncapsulation, TunnelId,
+    access::{AccessTopology, interface::InterfaceAccess, ip_addresses::IpAddressAccess},
+};
+use async_graphql::Object;
+use std::{net::IpAddr, sync::Arc};
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct TunnelAccess {
+    topology: Arc<Topology>,
+    id: TunnelId,
+}
+
+impl AccessTopology for TunnelAccess {
+    type Id = TunnelId;
+    type Data = TunnelData;
+
+    fn topology(&self) -> Arc<Topology> {
+        self.topology.clone()
+    }
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn data(&self) -> Option<&Self::Data> {
+        self.topology.tunnels.get(&self.id)
+    }
+
+    fn create(topology: Arc<Topology>, id: Self::Id) -> Self {
+        TunnelAccess { topology, id }
+    }
+}
+
+impl TunnelAccess {
+    pub fn name(&self) -> Option<&str> {
+        self.data().map(|d| d.name.as_ref())
+    }
+    pub fn encapsulation(&self) -> Option<TunnelEncapsulation> {
+        self.data().map(|d| d.encapsulation)
+    }
+    pub fn tunnel_id(&self) -> Option<u16> {
+        self.data().and_then(|d| d.tunnel_id)
+    }
+    pub fn endpoints(&self) -> Box<[InterfaceAccess]> {
+        self.data()
+            .map(|d| {
+                d.terminations
+                    .iter()
+                    .map(|t| t.interface)
+                    .map(self.create_access())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    /// outside address of the termination on the given interface
+    pub fn local_address(&self, interface: InterfaceId) -> Option<IpAddr> {
+        self.outside_address(|t| t == interface)
+    }
+    /// outside address of the termination on the other end of the tunnel
+    pub fn remote_address(&self, interface: InterfaceId) -> Option<IpAddr> {
+        self.outside_address(|t| t != interface)
+    }
+    fn outside_address(&self, filter: impl Fn(InterfaceId) -> bool) -> Option<IpAddr> {
+        self.data()
+            .and_then(|d| d.terminations.iter().find(|t| filter(t.interface)))
+            .and_then(|t| t.outside_ip)
+            .map(self.create_access())
+            .and_then(|ip: IpAddressAccess| ip.addr())
+    }
+}
+
+#[Object]
+impl TunnelAccess {
+    #[graphql(name = "id")]
+    async fn api_id(&self) -> u32 {
+        self.id.0
+    }
+    #[graphql(name = "name")]
+    async fn api_name(&self) -> Option<&str> {
+        self.name()
+    }
+    #[graphql(name = "endpoints")]
+    async fn api_endpoints(&self) -> Box<[InterfaceAccess]> {
+        self.endpoints()
+    }
+}
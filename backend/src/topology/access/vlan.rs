@@ -43,6 +43,12 @@ impl VlanAccess {
     pub fn vlan_id(&self) -> Option<u16> {
         self.data().map(|v| v.vlan_id)
     }
+    pub fn is_guest(&self) -> bool {
+        self.data().is_some_and(|v| v.guest)
+    }
+    pub fn is_captive_portal(&self) -> bool {
+        self.data().is_some_and(|v| v.captive_portal)
+    }
     pub fn vxlan(&self) -> Option<VxlanAccess> {
         self.data().and_then(|v| v.vxlan).map(self.create_access())
     }
@@ -82,7 +88,18 @@ impl VlanAccess {
     async fn api_name(&self) -> &str {
         self.name().unwrap_or_default()
     }
+    #[graphql(name = "vlanId")]
     async fn api_vlan_id(&self) -> u16 {
         self.vlan_id().expect("vlan_id not set")
     }
+    /// guest network, isolated from the private networks
+    #[graphql(name = "guest")]
+    async fn api_guest(&self) -> bool {
+        self.is_guest()
+    }
+    /// the clients have to log in to the hotspot
+    #[graphql(name = "captivePortal")]
+    async fn api_captive_portal(&self) -> bool {
+        self.is_captive_portal()
+    }
 }
@@ -0,0 +1,110 @@
+use crate::{
+    policy::TenantScope,
+    topology::{
+        SiteId, Topology,
+        access::{
+            AccessTopology, device::DeviceAccess, interface::InterfaceAccess, vlan::VlanAccess,
+        },
+    },
+};
+use async_graphql::{Enum, SimpleObject};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// How a port carries a vlan
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VlanMembership {
+    Untagged,
+    Tagged,
+}
+
+/// Ports against the vlans they carry, to check the layer 2 intent before provisioning
+#[derive(SimpleObject)]
+pub struct VlanMatrix {
+    /// columns of the matrix, ordered by vlan id
+    vlans: Box<[VlanAccess]>,
+    rows: Box<[VlanMatrixRow]>,
+}
+
+/// Port carrying at least one vlan
+#[derive(SimpleObject)]
+pub struct VlanMatrixRow {
+    device: DeviceAccess,
+    interface: InterfaceAccess,
+    /// membership in the vlan of the same column, null if the port does not carry it
+    memberships: Box<[Option<VlanMembership>]>,
+}
+
+impl VlanMatrix {
+    pub fn vlans(&self) -> &[VlanAccess] {
+        &self.vlans
+    }
+    pub fn rows(&self) -> &[VlanMatrixRow] {
+        &self.rows
+    }
+}
+
+impl VlanMatrixRow {
+    pub fn device(&self) -> &DeviceAccess {
+        &self.device
+    }
+    pub fn interface(&self) -> &InterfaceAccess {
+        &self.interface
+    }
+    pub fn memberships(&self) -> &[Option<VlanMembership>] {
+        &self.memberships
+    }
+}
+
+impl Topology {
+    /// vlan membership of the ports of all visible devices, or of the devices of a site
+    pub fn vlan_matrix(self: &Arc<Self>, site: Option<SiteId>, scope: &TenantScope) -> VlanMatrix {
+        let mut devices = self
+            .list_devices()
+            .filter(|device| scope.allows(device))
+            .filter(|device| site.is_none_or(|site| device.site().map(|s| s.id()) == Some(site)))
+            .collect::<Vec<_>>();
+        devices.sort_by(|a, b| a.name().cmp(b.name()));
+        let mut ports = Vec::new();
+        let mut vlans = BTreeMap::new();
+        for device in devices {
+            let mut interfaces = device.interfaces().into_vec();
+            interfaces.sort_by(|a, b| a.name().cmp(b.name()));
+            for interface in interfaces {
+                let mut memberships = Vec::new();
+                if let Some(vlan) = interface.untagged_vlan() {
+                    memberships.push((vlan.id, VlanMembership::Untagged));
+                    vlans.insert(vlan.id, vlan);
+                }
+                for vlan in interface.tagged_vlans() {
+                    memberships.push((vlan.id, VlanMembership::Tagged));
+                    vlans.insert(vlan.id, vlan);
+                }
+                if !memberships.is_empty() {
+                    ports.push((device.clone(), interface, memberships));
+                }
+            }
+        }
+        let mut vlans = vlans.into_values().collect::<Vec<_>>();
+        vlans.sort_by_key(|vlan| (vlan.vlan_id(), vlan.name().map(str::to_string)));
+        let rows = ports
+            .into_iter()
+            .map(|(device, interface, memberships)| VlanMatrixRow {
+                device,
+                interface,
+                memberships: vlans
+                    .iter()
+                    .map(|vlan| {
+                        memberships
+                            .iter()
+                            .find(|(id, _)| *id == vlan.id)
+                            .map(|(_, membership)| *membership)
+                    })
+                    .collect(),
+            })
+            .collect();
+        VlanMatrix {
+            vlans: vlans.into_boxed_slice(),
+            rows,
+        }
+    }
+}
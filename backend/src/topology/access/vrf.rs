@@ -0,0 +1,48 @@
+use crate::topology::{Topology, VrfData, VrfId, access::AccessTopology};
+use async_graphql::Object;
+use std::sync::Arc;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct VrfAccess {
+    topology: Arc<Topology>,
+    id: VrfId,
+}
+
+impl AccessTopology for VrfAccess {
+    type Id = VrfId;
+    type Data = VrfData;
+
+    fn topology(&self) -> Arc<Topology> {
+        self.topology.clone()
+    }
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn data(&self) -> Option<&Self::Data> {
+        self.topology.vrfs.get(&self.id)
+    }
+
+    fn create(topology: Arc<Topology>, id: Self::Id) -> Self {
+        VrfAccess { topology, id }
+    }
+}
+
+impl VrfAccess {
+    pub fn name(&self) -> Option<&str> {
+        self.data().map(|d| d.name.as_ref())
+    }
+}
+
+#[Object]
+impl VrfAccess {
+    #[graphql(name = "id")]
+    async fn api_id(&self) -> u32 {
+        self.id.0
+    }
+    #[graphql(name = "name")]
+    async fn api_name(&self) -> Option<&str> {
+        self.name()
+    }
+}
@@ -1,8 +1,9 @@
+use crate::policy::TenantScope;
 use crate::topology::{
-    Topology, VxlanData, VxlanId,
-    access::{AccessTopology, interface::InterfaceAccess, vlan::VlanAccess},
+    DeviceStatus, Topology, VxlanData, VxlanId,
+    access::{AccessTopology, device::DeviceAccess, interface::InterfaceAccess, vlan::VlanAccess},
 };
-use async_graphql::Object;
+use async_graphql::{Context, Object};
 use std::{
     collections::BTreeSet,
     fmt::{Debug, Formatter},
@@ -71,18 +72,27 @@ impl VxlanAccess {
             .unwrap_or_default()
     }
 
+    /// devices terminating the vxlan, directly or as member of a wlan group
+    pub fn members(&self) -> Vec<DeviceAccess> {
+        self.interface_terminations()
+            .iter()
+            .filter_map(InterfaceAccess::device)
+            .chain(
+                self.vlan_terminations()
+                    .iter()
+                    .flat_map(|vl| vl.wlan())
+                    .filter_map(|wlan| wlan.wlan_group())
+                    .flat_map(|group| group.aps().into_iter().chain(group.controller())),
+            )
+            .collect()
+    }
+
+    /// addresses of the members, devices being decommissioned are left out
     pub fn vteps(&self) -> Box<[IpAddr]> {
         Box::from_iter(
-            self.interface_terminations()
+            self.members()
                 .iter()
-                .filter_map(InterfaceAccess::device)
-                .chain(
-                    self.vlan_terminations()
-                        .iter()
-                        .flat_map(|vl| vl.wlan())
-                        .filter_map(|wlan| wlan.wlan_group())
-                        .flat_map(|group| group.aps().into_iter().chain(group.controller())),
-                )
+                .filter(|dev| dev.status() != DeviceStatus::Decommissioning)
                 .filter_map(|dev| dev.primary_ip_v4().and_then(|ip| ip.addr()))
                 .collect::<BTreeSet<IpAddr>>(),
         )
@@ -117,8 +127,8 @@ impl VxlanAccess {
         self.vni()
     }
     #[graphql(name = "interfaceTerminations")]
-    async fn api_interface_terminations(&self) -> Box<[InterfaceAccess]> {
-        self.interface_terminations()
+    async fn api_interface_terminations(&self, ctx: &Context<'_>) -> Box<[InterfaceAccess]> {
+        TenantScope::of(ctx).filter_interfaces(self.interface_terminations())
     }
     #[graphql(name = "vlanTerminations")]
     async fn api_vlan_terminations(&self) -> Box<[VlanAccess]> {
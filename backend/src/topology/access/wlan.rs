@@ -1,5 +1,5 @@
 use crate::topology::{
-    Topology, WlanData, WlanId,
+    Topology, WlanAuth, WlanData, WlanId,
     access::{AccessTopology, vlan::VlanAccess, wlan_group::WlanGroupAccess},
 };
 use async_graphql::Object;
@@ -33,9 +33,19 @@ impl AccessTopology for WlanAccess {
 }
 
 impl WlanAccess {
+    pub fn ssid(&self) -> Option<&str> {
+        self.data().map(|d| d.ssid.as_ref())
+    }
+    pub fn auth(&self) -> Option<&WlanAuth> {
+        self.data().map(|d| &d.wlan_auth)
+    }
     pub fn vlan(&self) -> Option<VlanAccess> {
         self.data().and_then(|d| d.vlan).map(self.create_access())
     }
+    /// schedule switching the wlan on and off
+    pub fn schedule(&self) -> Option<&str> {
+        self.data().and_then(|d| d.schedule.as_deref())
+    }
     pub fn wlan_group(&self) -> Option<WlanGroupAccess> {
         self.data().map(|d| d.wlan_group).map(self.create_access())
     }
@@ -47,4 +57,20 @@ impl WlanAccess {
     async fn api_id(&self) -> u32 {
         self.id().0
     }
+    #[graphql(name = "ssid")]
+    async fn api_ssid(&self) -> &str {
+        self.ssid().unwrap_or_default()
+    }
+    #[graphql(name = "auth")]
+    async fn api_auth(&self) -> Option<&WlanAuth> {
+        self.auth()
+    }
+    #[graphql(name = "vlan")]
+    async fn api_vlan(&self) -> Option<VlanAccess> {
+        self.vlan()
+    }
+    #[graphql(name = "schedule")]
+    async fn api_schedule(&self) -> Option<&str> {
+        self.schedule()
+    }
 }
@@ -1,8 +1,9 @@
+use crate::policy::TenantScope;
 use crate::topology::{
     Topology, WlanGroupData, WlanGroupId,
     access::{AccessTopology, device::DeviceAccess, vlan::VlanAccess, wlan::WlanAccess},
 };
-use async_graphql::Object;
+use async_graphql::{Context, Object};
 use std::sync::Arc;
 
 #[derive(Clone, PartialEq, Eq)]
@@ -67,11 +68,16 @@ impl WlanGroupAccess {
         self.wlan().collect()
     }
     #[graphql(name = "controller")]
-    async fn api_controller(&self) -> Option<DeviceAccess> {
+    async fn api_controller(&self, ctx: &Context<'_>) -> Option<DeviceAccess> {
         self.controller()
+            .filter(|device| TenantScope::of(ctx).allows(device))
     }
     #[graphql(name = "aps")]
-    async fn api_aps(&self) -> Box<[DeviceAccess]> {
-        self.aps()
+    async fn api_aps(&self, ctx: &Context<'_>) -> Box<[DeviceAccess]> {
+        TenantScope::of(ctx).filter(self.aps())
+    }
+    #[graphql(name = "mgmtVlan")]
+    async fn api_mgmt_vlan(&self) -> Option<VlanAccess> {
+        self.mgmt_vlan()
     }
 }
@@ -0,0 +1,204 @@
+use crate::{
+    policy::TenantScope,
+    topology::{DeviceId, InterfaceId, IpPrefixId, Topology, VlanId},
+};
+use async_graphql::{Enum, SimpleObject};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeSet, HashMap},
+    hash::Hash,
+};
+
+/// Kind of netbox object compared between two fetches of the topology
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangedObject {
+    Device,
+    Interface,
+    Vlan,
+    Prefix,
+}
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// Object added, removed or modified in netbox between two fetches of the topology
+#[derive(Debug, Clone, PartialEq, Eq, SimpleObject)]
+pub struct TopologyChange {
+    object: ChangedObject,
+    kind: ChangeKind,
+    /// netbox id of the object
+    id: u32,
+    /// name of the object, a removed object is named as in the previous topology
+    name: Box<str>,
+    /// fields of a modified object with a different value, empty if added or removed
+    fields: Box<[Box<str>]>,
+}
+
+impl TopologyChange {
+    pub fn object(&self) -> ChangedObject {
+        self.object
+    }
+    pub fn kind(&self) -> ChangeKind {
+        self.kind
+    }
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn fields(&self) -> &[Box<str>] {
+        &self.fields
+    }
+}
+
+impl Topology {
+    /// Devices, interfaces, vlans and prefixes that differ from the previous topology, ordered by
+    /// kind and id. Devices and interfaces of tenants outside the scope are left out, as are
+    /// vlans and prefixes not used by a device in the scope, see [`Topology::vlan_in_scope`] and
+    /// [`Topology::prefix_in_scope`].
+    pub fn changes_since(&self, previous: &Topology, scope: &TenantScope) -> Box<[TopologyChange]> {
+        let mut changes = Vec::new();
+        let device_visible = |topology: &Topology, id: DeviceId| {
+            scope.allows_tenant(
+                topology
+                    .devices
+                    .get(&id)
+                    .and_then(|device| device.tenant.as_deref()),
+            )
+        };
+        self.compare(
+            previous,
+            ChangedObject::Device,
+            |topology| &topology.devices,
+            |DeviceId(id)| id,
+            |_, _, device| {
+                scope
+                    .allows_tenant(device.tenant.as_deref())
+                    .then(|| device.name.clone())
+            },
+            &mut changes,
+        );
+        self.compare(
+            previous,
+            ChangedObject::Interface,
+            |topology| &topology.interfaces,
+            |InterfaceId(id)| id,
+            |topology, _, interface| {
+                if !device_visible(topology, interface.device) {
+                    return None;
+                }
+                let device = topology
+                    .devices
+                    .get(&interface.device)
+                    .map(|device| device.name.as_ref())
+                    .unwrap_or_default();
+                Some(format!("{device} {}", interface.name).into())
+            },
+            &mut changes,
+        );
+        self.compare(
+            previous,
+            ChangedObject::Vlan,
+            |topology| &topology.vlans,
+            |VlanId(id)| id,
+            |topology, id, vlan| {
+                topology
+                    .vlan_in_scope(id, scope)
+                    .then(|| format!("{} ({})", vlan.name, vlan.vlan_id).into())
+            },
+            &mut changes,
+        );
+        self.compare(
+            previous,
+            ChangedObject::Prefix,
+            |topology| &topology.ip_prefixes,
+            |IpPrefixId(id)| id,
+            |topology, id, prefix| {
+                topology
+                    .prefix_in_scope(id, scope)
+                    .then(|| prefix.prefix.to_string().into())
+            },
+            &mut changes,
+        );
+        changes.into_boxed_slice()
+    }
+
+    /// `describe` names the object in the topology it is taken from, none hides the object
+    fn compare<ID: Copy + Ord + Hash, Data: Serialize + PartialEq>(
+        &self,
+        previous: &Topology,
+        object: ChangedObject,
+        select: fn(&Topology) -> &HashMap<ID, Data>,
+        raw_id: fn(ID) -> u32,
+        describe: impl Fn(&Topology, ID, &Data) -> Option<Box<str>>,
+        changes: &mut Vec<TopologyChange>,
+    ) {
+        let (before, after) = (select(previous), select(self));
+        let ids = before
+            .keys()
+            .chain(after.keys())
+            .copied()
+            .collect::<BTreeSet<_>>();
+        for id in ids {
+            let (kind, topology, data, fields) = match (before.get(&id), after.get(&id)) {
+                (None, Some(data)) => (ChangeKind::Added, self, data, Box::default()),
+                (Some(data), None) => (ChangeKind::Removed, previous, data, Box::default()),
+                (Some(old), Some(new)) if old != new => {
+                    let fields = changed_fields(old, new);
+                    // only the order of a list changed
+                    if fields.is_empty() {
+                        continue;
+                    }
+                    (ChangeKind::Modified, self, new, fields)
+                }
+                _ => continue,
+            };
+            if let Some(name) = describe(topology, id, data) {
+                changes.push(TopologyChange {
+                    object,
+                    kind,
+                    id: raw_id(id),
+                    name,
+                    fields,
+                });
+            }
+        }
+    }
+}
+
+/// names of the serialized fields with different values, lists are compared as multisets
+/// ignoring the order
+fn changed_fields(old: &impl Serialize, new: &impl Serialize) -> Box<[Box<str>]> {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return Box::default();
+    };
+    old.keys()
+        .chain(new.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|field| !same_value(&old, &new, field))
+        .map(|field| field.as_str().into())
+        .collect()
+}
+
+fn same_value(old: &Map<String, Value>, new: &Map<String, Value>, field: &str) -> bool {
+    match (old.get(field), new.get(field)) {
+        (Some(Value::Array(old)), Some(Value::Array(new))) => {
+            let sorted = |values: &[Value]| {
+                let mut values = values.iter().map(Value::to_string).collect::<Vec<_>>();
+                values.sort();
+                values
+            };
+            sorted(old) == sorted(new)
+        }
+        (old, new) => old == new,
+    }
+}
@@ -1,25 +1,32 @@
 use crate::{
+    config::NetboxInstance,
     netbox::{
         NetboxError, fetch_topology,
         fetch_topology::{
             CableConnectionTermination, CableConnectionTerminationOnFrontPortType,
             CableConnectionTerminationOnInterfaceType, CableConnectionTerminationOnRearPortType,
+            FetchTopologyFhrpGroupListFhrpgroupassignmentSetInterface,
             FetchTopologyL2vpnListTerminationsAssignedObject,
         },
     },
     topology::{
-        Cable, CableId, CablePort, Device, DeviceId, FrontPort, FrontPortId, Interface,
-        InterfaceId, IpAddressData, IpAddressId, IpPrefixData, IpPrefixId, IpRangeData, IpRangeId,
-        PhysicalPortId, PortType, RearPort, RearPortId, Topology, VlanData, VlanGroupData,
-        VlanGroupId, VlanId, VxlanData, VxlanId, WlanAuth, WlanData, WlanGroupData, WlanGroupId,
-        WlanId, WlanOpenSettings, WlanWpaSettings,
+        Cable, CableId, CablePort, Device, DeviceId, DeviceStatus, FhrpAssignment, FhrpGroupData,
+        FhrpGroupId, FhrpProtocol, FrontPort, FrontPortId, Interface, InterfaceId, IpAddressData,
+        IpAddressId, IpPrefixData, IpPrefixId, IpRangeData, IpRangeId, LocationData, LocationId,
+        PhysicalPortId, PortType, RearPort, RearPortId, SiteData, SiteId, Topology, TunnelData,
+        TunnelEncapsulation, TunnelId, TunnelTermination, VlanData, VlanGroupData, VlanGroupId,
+        VlanId, VrfData, VrfId, VxlanData, VxlanId, WlanAuth, WlanCipher, WlanData,
+        WlanEnterpriseSettings, WlanGroupData, WlanGroupId, WlanId, WlanOpenSettings,
+        WlanWpaSettings,
     },
 };
 use ipnet::IpNet;
 use log::warn;
 use std::{
     collections::{BTreeSet, HashMap, HashSet},
+    net::Ipv4Addr,
     str::FromStr,
+    time::SystemTime,
 };
 use tokio::time::Instant;
 
@@ -159,9 +166,10 @@ impl InternalDeviceConnection {
     }
 }
 
-pub async fn build_topology() -> Result<Topology, NetboxError> {
+pub async fn build_topology(instance: &NetboxInstance) -> Result<Topology, NetboxError> {
     let fetch_time = Instant::now();
-    let data = fetch_topology().await?;
+    let fetched_at = SystemTime::now();
+    let data = fetch_topology(instance).await?;
     //let mut internal_connections = HashMap::<_, HashSet<_>>::new();
     let mut cable_chains = Vec::<CableChain>::new();
     /*    for (cable, internal_connections_of_cable) in data.cable_list.into_iter().filter_map(|cable| {
@@ -299,6 +307,11 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
                         Some("wpa-personal") => Some(WlanAuth::Wpa(WlanWpaSettings {
                             key: wlan.auth_psk.into_boxed_str(),
                         })),
+                        Some("wpa-enterprise") => {
+                            Some(WlanAuth::WpaEnterprise(WlanEnterpriseSettings::new(
+                                WlanCipher::from_netbox(wlan.auth_cipher.as_deref()),
+                            )))
+                        }
                         Some("open") => Some(WlanAuth::Open(WlanOpenSettings { use_owe: true })),
                         _ => None,
                     },
@@ -311,6 +324,7 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
                             vlan,
                             wlan_auth,
                             wlan_group: wlan_group_id,
+                            schedule: schedule_of(wlan.tags.iter().map(|t| t.slug.as_str())),
                         },
                     );
                     if let Some(vlan) = vlan {
@@ -330,26 +344,76 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
         }
     }
 
+    let mut sites = HashMap::new();
+    for site in data.site_list {
+        if let Ok(id) = site.id.parse().map(SiteId) {
+            sites.insert(
+                id,
+                SiteData {
+                    name: site.name.into_boxed_str(),
+                    slug: site.slug.into_boxed_str(),
+                },
+            );
+        }
+    }
+    let mut locations = HashMap::new();
+    for location in data.location_list {
+        if let (Ok(id), Ok(site)) = (
+            location.id.parse().map(LocationId),
+            location.site.id.parse().map(SiteId),
+        ) {
+            locations.insert(
+                id,
+                LocationData {
+                    name: location.name.into_boxed_str(),
+                    site,
+                    parent: location
+                        .parent
+                        .and_then(|parent| parent.id.parse().ok().map(LocationId)),
+                },
+            );
+        }
+    }
+
     let mut interface_of_address = HashMap::new();
     for device in data.device_list {
         if let Some(device_id) = device.id.parse().ok().map(DeviceId) {
+            let site = device.site.id.parse().ok().map(SiteId);
+            let location = device
+                .location
+                .as_ref()
+                .and_then(|location| location.id.parse().ok().map(LocationId));
             let credentials = device
                 .tenant
+                .as_ref()
                 .and_then(|tenant| credentials_by_tenants.get(tenant.id.as_str()))
                 .or_else(|| {
                     device
                         .location
-                        .and_then(|location| location.tenant)
+                        .as_ref()
+                        .and_then(|location| location.tenant.as_ref())
                         .and_then(|tenant| credentials_by_tenants.get(tenant.id.as_str()))
                 })
                 .or_else(|| {
                     device
                         .site
                         .tenant
+                        .as_ref()
                         .and_then(|tenant| credentials_by_tenants.get(tenant.id.as_str()))
                 })
                 .copied()
                 .map(Box::<str>::from);
+            let tenant = device
+                .tenant
+                .map(|tenant| tenant.slug)
+                .or_else(|| {
+                    device
+                        .location
+                        .and_then(|location| location.tenant)
+                        .map(|tenant| tenant.slug)
+                })
+                .or_else(|| device.site.tenant.map(|tenant| tenant.slug))
+                .map(String::into_boxed_str);
             let primary_ip_v6 = device
                 .primary_ip6
                 .and_then(|primary_ip| primary_ip.id.parse().map(IpAddressId).ok());
@@ -357,6 +421,9 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
                 .primary_ip4
                 .and_then(|primary_ip| primary_ip.id.parse().map(IpAddressId).ok());
             let primary_ip = Option::or(primary_ip_v6, primary_ip_v4);
+            let oob_ip = device
+                .oob_ip
+                .and_then(|oob_ip| oob_ip.id.parse().map(IpAddressId).ok());
             let mut ports = HashSet::new();
             let mut loopback_ip = None;
             for interface in device.interfaces {
@@ -383,6 +450,14 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
                     let use_ospf = interface.tags.iter().any(|t| t.slug == "ospf");
                     let enable_dhcp_client = interface.tags.iter().any(|t| t.slug == "dhcp-client");
                     let enable_dhcp_server = interface.tags.iter().any(|t| t.slug == "dhcp");
+                    let enable_dhcp_relay = interface.tags.iter().any(|t| t.slug == "dhcp-relay");
+                    let captive_portal = interface.tags.iter().any(|t| t.slug == "captive-portal");
+                    let schedule = schedule_of(interface.tags.iter().map(|t| t.slug.as_str()));
+                    let wan = interface.tags.iter().any(|t| t.slug == "wan");
+                    let pppoe = interface.tags.iter().any(|t| t.slug == "pppoe");
+                    let apn = apn_of(interface.tags.iter().map(|t| t.slug.as_str()));
+                    let management =
+                        interface.mgmt_only || interface.tags.iter().any(|t| t.slug == "mgmt");
                     let external = PhysicalPortId::from_str(&interface.name).ok();
                     let port_type = match interface.type_.as_str() {
                         "10gbase-x-sfpp" | "1000base-x-sfp" | "1000base-t" | "100base-tx"
@@ -390,6 +465,7 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
                         "ieee802.11n" | "ieee802.11ac" | "ieee802.11ad" => Some(PortType::Wireless),
                         "bridge" => Some(PortType::Bridge),
                         "virtual" => Some(PortType::Loopback),
+                        "lte" | "4g" | "5g" | "gsm" | "cdma" => Some(PortType::Lte),
                         &_ => {
                             warn!("Unknown interface type: {}", interface.type_);
                             None
@@ -408,6 +484,9 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
                         .and_then(|b| b.id.parse().ok())
                         .map(InterfaceId);
                     let enable_poe = interface.poe_mode.map(|v| v == "pse").unwrap_or(false);
+                    let mac_address = interface
+                        .primary_mac_address
+                        .map(|mac| mac.mac_address.to_uppercase().into_boxed_str());
                     interfaces.insert(
                         id,
                         Interface {
@@ -422,9 +501,19 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
                             use_ospf,
                             enable_dhcp_client,
                             enable_dhcp_server,
+                            enable_dhcp_relay,
+                            captive_portal,
+                            schedule,
                             bridge,
                             cable: None,
                             enable_poe,
+                            fhrp_groups: Box::new([]),
+                            tunnel: None,
+                            management,
+                            mac_address,
+                            wan,
+                            pppoe,
+                            apn,
                         },
                     );
                     if let Some(vlan_id) = vlan {
@@ -468,6 +557,14 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
             }
             let platform = device.platform.map(|p| p.name).unwrap_or_default();
             let serial = Some(device.serial.into_boxed_str()).filter(|s| !s.is_empty());
+            let monitor = device.tags.iter().any(|t| t.slug == "monitor");
+            let netwatch_profiles = device
+                .tags
+                .iter()
+                .filter_map(|t| t.slug.strip_prefix("netwatch-"))
+                .map(Box::from)
+                .collect();
+            let tags = device.tags.iter().map(|t| t.slug.as_str().into()).collect();
             let wlan_controller_of = controllers.get(&device_id).cloned();
             let wlan_ap_of = device.custom_field_data.wlan_group.map(WlanGroupId);
             let mut vlans = HashSet::new();
@@ -488,13 +585,25 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
                     primary_ip,
                     primary_ip_v4,
                     primary_ip_v6,
+                    oob_ip,
                     loopback_ip,
                     credentials,
+                    pppoe_credentials: device.custom_field_data.pppoe_credentials.clone(),
+                    upnp: device.custom_field_data.upnp.unwrap_or(false),
+                    igmp_proxy: device.custom_field_data.igmp_proxy.unwrap_or(false),
                     has_routeros: platform == "routeros",
                     serial,
                     wlan_controller_of,
                     wlan_ap_of,
                     vlans: vlans.into_iter().collect(),
+                    monitor,
+                    netwatch_profiles,
+                    role: Some(device.role.slug.into_boxed_str()),
+                    site,
+                    location,
+                    tenant,
+                    status: DeviceStatus::from_netbox(&device.status),
+                    tags,
                 },
             );
         }
@@ -557,6 +666,8 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
                             name: vlan.name.into_boxed_str(),
                             vlan_id: vlan.vid as u16,
                             group: vlan_group_id,
+                            guest: vlan.tags.iter().any(|t| t.slug == "guest"),
+                            captive_portal: vlan.tags.iter().any(|t| t.slug == "captive-portal"),
                             terminations: Box::from_iter(
                                 interfaces_of_vlan.remove(&vlan_id).unwrap_or_default(),
                             ),
@@ -610,7 +721,20 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
             cables.insert(cable_id, Cable { port_a, port_b });
         }
     }
+    let mut vrfs = HashMap::new();
+    for vrf in data.vrf_list {
+        if let Ok(id) = vrf.id.parse().map(VrfId) {
+            vrfs.insert(
+                id,
+                VrfData {
+                    name: vrf.name.into_boxed_str(),
+                },
+            );
+        }
+    }
+
     let mut ip_prefixes = HashMap::new();
+    // the same prefix can appear in multiple vrfs
     let mut prefix_idx = HashMap::new();
 
     for prefix_data in data.prefix_list {
@@ -618,7 +742,10 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
             prefix_data.id.parse().map(IpPrefixId),
             prefix_data.prefix.parse::<IpNet>(),
         ) {
-            prefix_idx.insert(prefix, id);
+            let vrf = prefix_data
+                .vrf
+                .and_then(|vrf| vrf.id.parse().ok().map(VrfId));
+            prefix_idx.insert((vrf, prefix), id);
             ip_prefixes.insert(
                 id,
                 IpPrefixData {
@@ -627,6 +754,10 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
                     children: Box::new([]),
                     parent: None,
                     ranges: Box::new([]),
+                    vrf,
+                    dhcp_relay: parse_dhcp_relay(&prefix, prefix_data.custom_fields.dhcp_relay),
+                    dhcp_options: prefix_data.custom_fields.dhcp_options,
+                    loopback: prefix_data.role.is_some_and(|role| role.slug == "loopback"),
                 },
             );
         }
@@ -635,7 +766,7 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
     for (id, prefix_data) in &mut ip_prefixes {
         let mut prefix = prefix_data.prefix;
         while let Some(net_address) = prefix.supernet() {
-            if let Some(parent_idx) = prefix_idx.get(&net_address) {
+            if let Some(parent_idx) = prefix_idx.get(&(prefix_data.vrf, net_address)) {
                 prefix_data.parent = Some(*parent_idx);
                 children_prefix
                     .entry(*parent_idx)
@@ -663,7 +794,8 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
             let start_net = start_addr.trunc();
             let end_net = end_addr.trunc();
             if start_net == end_net {
-                let prefix = prefix_idx.get(&start_addr).copied();
+                let vrf = ip_range.vrf.and_then(|vrf| vrf.id.parse().ok().map(VrfId));
+                let prefix = prefix_idx.get(&(vrf, start_net)).copied();
                 if let Some(prefix_id) = prefix {
                     ranges_of_prefix
                         .entry(prefix_id)
@@ -701,7 +833,10 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
             ip_addr_data.id.parse().map(IpAddressId),
             ip_addr_data.address.parse::<IpNet>(),
         ) {
-            let prefix = prefix_idx.get(&ip.trunc()).copied();
+            let vrf = ip_addr_data
+                .vrf
+                .and_then(|vrf| vrf.id.parse().ok().map(VrfId));
+            let prefix = prefix_idx.get(&(vrf, ip.trunc())).copied();
             if let Some(prefix_id) = prefix {
                 ip_addresses_of_prefix
                     .entry(prefix_id)
@@ -715,6 +850,7 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
                     ip,
                     interface: interface_of_address.get(&id).copied(),
                     prefix,
+                    vrf,
                 },
             );
         }
@@ -733,9 +869,104 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
             .map(Vec::into_boxed_slice)
             .unwrap_or_default();
     }
+    let mut tunnels = HashMap::new();
+    for tunnel in data.tunnel_list {
+        let encapsulation = match tunnel.encapsulation.as_str() {
+            "gre" if tunnel.tags.iter().any(|t| t.slug == "eoip") => TunnelEncapsulation::Eoip,
+            "gre" => TunnelEncapsulation::Gre,
+            _ => continue,
+        };
+        if let Ok(id) = tunnel.id.parse().map(TunnelId) {
+            let mut terminations = Vec::new();
+            for termination in tunnel.terminations {
+                if termination.termination_type.map(|t| t.model).as_deref() != Some("interface") {
+                    continue;
+                }
+                if let Some((if_id, interface)) = termination
+                    .termination_id
+                    .and_then(|id| u32::try_from(id).ok())
+                    .map(InterfaceId)
+                    .and_then(|if_id| interfaces.get_mut(&if_id).map(|i| (if_id, i)))
+                {
+                    interface.tunnel = Some(id);
+                    interface.port_type = Some(PortType::Tunnel);
+                    terminations.push(TunnelTermination {
+                        interface: if_id,
+                        outside_ip: termination
+                            .outside_ip
+                            .and_then(|ip| ip.id.parse().map(IpAddressId).ok()),
+                    });
+                }
+            }
+            tunnels.insert(
+                id,
+                TunnelData {
+                    name: tunnel.name.into_boxed_str(),
+                    encapsulation,
+                    tunnel_id: tunnel.tunnel_id.and_then(|id| u16::try_from(id).ok()),
+                    terminations: terminations.into_boxed_slice(),
+                },
+            );
+        }
+    }
+
+    let mut fhrp_groups = HashMap::new();
+    for fhrp_group in data.fhrp_group_list {
+        if let (Ok(id), Ok(group_id)) = (
+            fhrp_group.id.parse().map(FhrpGroupId),
+            u8::try_from(fhrp_group.group_id),
+        ) {
+            let protocol = match fhrp_group.protocol.as_str() {
+                "vrrp2" => FhrpProtocol::Vrrp2,
+                "vrrp3" => FhrpProtocol::Vrrp3,
+                _ => FhrpProtocol::Other,
+            };
+            let mut assignments = Vec::new();
+            for assignment in fhrp_group.fhrpgroupassignment_set {
+                if let FetchTopologyFhrpGroupListFhrpgroupassignmentSetInterface::InterfaceType(
+                    if_type,
+                ) = assignment.interface
+                {
+                    if let Some((if_id, interface)) = if_type
+                        .id
+                        .parse()
+                        .ok()
+                        .map(InterfaceId)
+                        .and_then(|if_id| interfaces.get_mut(&if_id).map(|i| (if_id, i)))
+                    {
+                        interface.fhrp_groups = interface
+                            .fhrp_groups
+                            .iter()
+                            .copied()
+                            .chain(Some(id))
+                            .collect();
+                        assignments.push(FhrpAssignment {
+                            interface: if_id,
+                            priority: assignment.priority.clamp(1, 254) as u8,
+                        });
+                    }
+                }
+            }
+            fhrp_groups.insert(
+                id,
+                FhrpGroupData {
+                    group_id,
+                    protocol,
+                    virtual_ips: fhrp_group
+                        .ip_addresses
+                        .into_iter()
+                        .filter_map(|address| address.id.parse().map(IpAddressId).ok())
+                        .collect(),
+                    assignments: assignments.into_boxed_slice(),
+                },
+            );
+        }
+    }
 
     Ok(Topology {
         fetch_time,
+        fetched_at,
+        instance: Default::default(),
         devices,
         interfaces,
         front_ports,
@@ -753,6 +984,11 @@ pub async fn build_topology() -> Result<Topology, NetboxError> {
             .into_iter()
             .map(|(id, ranges)| (id, ranges.into_boxed_slice()))
             .collect(),
+        fhrp_groups,
+        vrfs,
+        tunnels,
+        sites,
+        locations,
     })
 }
 
@@ -824,3 +1060,33 @@ fn termination_2_cable_port(termination: CableConnectionTermination) -> Option<C
     }
 }
 */
+
+/// name of the apn profile given by a tag `apn-<name>`
+fn apn_of<'a>(mut tags: impl Iterator<Item = &'a str>) -> Option<Box<str>> {
+    tags.find_map(|tag| tag.strip_prefix("apn-")).map(Box::from)
+}
+
+/// name of the schedule given by a tag `schedule-<name>`
+fn schedule_of<'a>(mut tags: impl Iterator<Item = &'a str>) -> Option<Box<str>> {
+    tags.find_map(|tag| tag.strip_prefix("schedule-"))
+        .map(Box::from)
+}
+
+/// dhcp servers of the custom field `dhcp_relay` separated by commas, invalid addresses are
+/// skipped
+fn parse_dhcp_relay(prefix: &IpNet, value: Option<Box<str>>) -> Box<[Ipv4Addr]> {
+    value
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|server| !server.is_empty())
+        .filter_map(|server| match server.parse() {
+            Ok(server) => Some(server),
+            Err(_) => {
+                warn!("Invalid dhcp relay server {server} on prefix {prefix}");
+                None
+            }
+        })
+        .collect()
+}
@@ -1,19 +1,56 @@
-use crate::topology::{DeviceId, TopologyHolder, access::device::DeviceAccess};
-use async_graphql::{InputObject, Object};
+use crate::{
+    Error,
+    device::{reachability::DeviceReachability, ros::naming::GeneratedInterfaceName},
+    graphql::connection_limit::ConnectionLimit,
+    policy::TenantScope,
+    provisioning::{scope::ApplyScope, site::SitePlan},
+    rate_limit::check_device_access,
+    secrets::mask_secrets,
+    topology::{
+        DeviceId, IpPrefixId, SiteId, TopologyHolder,
+        access::{
+            AccessTopology,
+            consistency::ConsistencyFinding,
+            device::DeviceAccess,
+            graphql::{IpNetGraphql, IpRangeGraphql},
+            impact::ImpactedDevice,
+            site::SiteAccess,
+            vlan_matrix::VlanMatrix,
+            wlan_group::WlanGroupAccess,
+        },
+        diff::TopologyChange,
+        search::SearchHit,
+    },
+};
+use async_graphql::{Context, InputObject, Object, futures_util::future::join_all};
+use ipnet::{IpNet, Ipv4Net};
+
+/// most hits returned by the search
+const SEARCH_LIMIT: u32 = 50;
+/// most addresses returned by `nextFreeAddress`
+const FREE_ADDRESS_LIMIT: u32 = 256;
 
 #[derive(InputObject)]
 struct DeviceListFilter {
     has_routeros: Option<bool>,
+    /// only devices of the site with this id
+    site: Option<u32>,
 }
 
 #[Object]
 impl TopologyHolder {
-    async fn all_devices(&self, filter: Option<DeviceListFilter>) -> Box<[DeviceAccess]> {
+    async fn all_devices(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<DeviceListFilter>,
+    ) -> Box<[DeviceAccess]> {
+        let scope = TenantScope::of(ctx);
         self.topo_lock()
             .await
             .as_ref()
             .map(|topo| {
                 topo.list_devices()
+                    .filter(|d| scope.allows(d))
                     .filter(|d| {
                         filter
                             .as_ref()
@@ -23,6 +60,11 @@ impl TopologyHolder {
                                         return false;
                                     }
                                 }
+                                if let Some(site) = filter.site {
+                                    if d.site().map(|s| s.id()) != Some(SiteId(site)) {
+                                        return false;
+                                    }
+                                }
                                 true
                             })
                             .unwrap_or(true)
@@ -31,11 +73,236 @@ impl TopologyHolder {
             })
             .unwrap_or_default()
     }
-    async fn device_by_id(&self, id: u32) -> Option<DeviceAccess> {
+    /// wlan groups with a controller or access point visible to the user
+    async fn all_wlan_groups(&self, ctx: &Context<'_>) -> Box<[WlanGroupAccess]> {
+        let scope = TenantScope::of(ctx);
+        self.topo_lock()
+            .await
+            .as_ref()
+            .map(|topo| {
+                topo.list_wlan_groups()
+                    .filter(|group| {
+                        group
+                            .controller()
+                            .into_iter()
+                            .chain(group.aps())
+                            .any(|device| scope.allows(&device))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    /// sites with a device visible to the user
+    async fn all_sites(&self, ctx: &Context<'_>) -> Box<[SiteAccess]> {
+        let scope = TenantScope::of(ctx);
+        self.topo_lock()
+            .await
+            .as_ref()
+            .map(|topo| {
+                topo.list_sites()
+                    .filter(|site| site.devices().iter().any(|device| scope.allows(device)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    /// ports against the vlans they carry, of all devices or of the devices of a site
+    async fn vlan_matrix(&self, ctx: &Context<'_>, site: Option<u32>) -> Option<VlanMatrix> {
+        self.topo_lock()
+            .await
+            .as_ref()
+            .map(|topo| topo.vlan_matrix(site.map(SiteId), TenantScope::of(ctx)))
+    }
+    /// devices carrying the netbox tag, ordered by name
+    async fn devices_by_tag(&self, ctx: &Context<'_>, tag: String) -> Box<[DeviceAccess]> {
+        self.tagged_devices(&tag, TenantScope::of(ctx)).await
+    }
+    async fn device_by_id(&self, ctx: &Context<'_>, id: u32) -> Option<DeviceAccess> {
         if let Some(topo) = self.topo_lock().await.as_ref() {
             topo.get_device_by_id(&DeviceId(id))
+                .filter(|device| TenantScope::of(ctx).allows(device))
         } else {
             None
         }
     }
+    /// configuration script of the device for a reset device of the hardware model, e.g. to
+    /// preview the configuration of hardware not delivered yet. No device is contacted.
+    async fn render_config_for_model(
+        &self,
+        ctx: &Context<'_>,
+        model: String,
+        device_id: u32,
+    ) -> Result<String, Error> {
+        let device = self
+            .device_by_id(ctx, device_id)
+            .await
+            .ok_or(Error::UnknownDevice(device_id))?;
+        Ok(mask_secrets(&device.render_config_for_model(&model)?))
+    }
+    /// names of the ports and tunnels of the device in the generated configuration, shortened
+    /// and numbered by the naming policy of its generation profile
+    async fn generated_interface_names(
+        &self,
+        ctx: &Context<'_>,
+        device_id: u32,
+    ) -> Result<Box<[GeneratedInterfaceName]>, Error> {
+        self.device_by_id(ctx, device_id)
+            .await
+            .ok_or(Error::UnknownDevice(device_id))?
+            .generated_interface_names()
+    }
+    /// devices whose generated configuration may change together with the device, walking the
+    /// cable paths, the vxlans and the wlan group of the device
+    async fn impact_analysis(
+        &self,
+        ctx: &Context<'_>,
+        device_id: u32,
+    ) -> Option<Box<[ImpactedDevice]>> {
+        let scope = TenantScope::of(ctx);
+        self.device_by_id(ctx, device_id)
+            .await
+            .map(|device| device.impact_analysis(scope))
+    }
+    /// plans of all routeros devices of a site visible to the user, ordered by their
+    /// dependencies: the router of an upstream gateway first, the wlan controller before its
+    /// access points
+    async fn plan_site(
+        &self,
+        ctx: &Context<'_>,
+        site_id: u32,
+        scope: Option<ApplyScope>,
+        prune: Option<bool>,
+    ) -> Option<SitePlan> {
+        let tenants = TenantScope::of(ctx);
+        let devices = self
+            .topo_lock()
+            .await
+            .as_ref()
+            .and_then(|topo| topo.get_site_by_id(&SiteId(site_id)))?
+            .devices()
+            .into_iter()
+            .filter(|device| device.has_routeros() && tenants.allows(device))
+            .collect::<Vec<_>>();
+        Some(
+            SitePlan::create(
+                &devices,
+                scope,
+                prune.unwrap_or(false),
+                |device| check_device_access(ctx, device),
+                ConnectionLimit::of(ctx),
+            )
+            .await,
+        )
+    }
+    /// plans of all routeros devices carrying the netbox tag and visible to the user, ordered by
+    /// their dependencies like the plans of a site. Devices with a non-empty plan have drifted.
+    async fn plan_tag(
+        &self,
+        ctx: &Context<'_>,
+        tag: String,
+        scope: Option<ApplyScope>,
+        prune: Option<bool>,
+    ) -> SitePlan {
+        let devices = self
+            .tagged_devices(&tag, TenantScope::of(ctx))
+            .await
+            .into_iter()
+            .filter(|device| device.has_routeros())
+            .collect::<Vec<_>>();
+        SitePlan::create(
+            &devices,
+            scope,
+            prune.unwrap_or(false),
+            |device| check_device_access(ctx, device),
+            ConnectionLimit::of(ctx),
+        )
+        .await
+    }
+    /// pings the management address of multiple devices from the provisioner, devices over
+    /// their rate limit are reported without address
+    async fn ping_devices(&self, ctx: &Context<'_>, ids: Vec<u32>) -> Box<[DeviceReachability]> {
+        let scope = TenantScope::of(ctx);
+        let devices = ids
+            .into_iter()
+            .map(|id| {
+                let topology = self.clone();
+                async move {
+                    let address = topology
+                        .devices_by_id(DeviceId(id))
+                        .await
+                        .filter(|device| scope.allows(device))
+                        .filter(|device| check_device_access(ctx, device).is_ok())
+                        .and_then(|d| d.management_ip());
+                    DeviceReachability::check(id, address).await
+                }
+            })
+            .collect::<Vec<_>>();
+        join_all(devices).await.into_boxed_slice()
+    }
+    /// conflicts in the ip address management of netbox: duplicate addresses, overlapping
+    /// prefixes and dhcp ranges containing assigned addresses
+    async fn consistency_findings(&self, ctx: &Context<'_>) -> Box<[ConsistencyFinding]> {
+        self.topo_lock()
+            .await
+            .as_ref()
+            .map(|topo| topo.check_consistency(TenantScope::of(ctx)))
+            .unwrap_or_default()
+    }
+    /// devices, interfaces, vlans and prefixes changed in netbox between the last two differing
+    /// fetches, null if netbox did not change since the provisioner started
+    async fn topology_changes(&self, ctx: &Context<'_>) -> Option<Box<[TopologyChange]>> {
+        self.changes(TenantScope::of(ctx)).await
+    }
+    /// devices, vlans and prefixes matching the query by name, serial, vlan id or address, the
+    /// best matches first
+    async fn search(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        limit: Option<u32>,
+    ) -> Box<[SearchHit]> {
+        let limit = limit.unwrap_or(SEARCH_LIMIT).min(SEARCH_LIMIT) as usize;
+        self.topo_lock()
+            .await
+            .as_ref()
+            .map(|topo| topo.search(&query, TenantScope::of(ctx), limit))
+            .unwrap_or_default()
+    }
+    /// unused parts of a prefix, skipping ranges, child prefixes and assigned ips
+    async fn free_ranges(&self, ctx: &Context<'_>, prefix_id: u32) -> Box<[IpRangeGraphql]> {
+        let id = IpPrefixId(prefix_id);
+        self.topo_lock()
+            .await
+            .as_ref()
+            .filter(|topo| topo.prefix_in_scope(id, TenantScope::of(ctx)))
+            .and_then(|topo| topo.get_ip_prefix_by_id(&id))
+            .map(|prefix| prefix.free_ranges().into_iter().map(Into::into).collect())
+            .unwrap_or_default()
+    }
+    /// candidates for unused addresses in an ipv4 prefix, skipping ranges, child prefixes and
+    /// assigned ips, at most 256
+    async fn next_free_address(
+        &self,
+        ctx: &Context<'_>,
+        prefix_id: u32,
+        count: Option<u32>,
+    ) -> Result<Box<[IpNetGraphql]>, Error> {
+        let id = IpPrefixId(prefix_id);
+        let prefix = self
+            .topo_lock()
+            .await
+            .as_ref()
+            .filter(|topo| topo.prefix_in_scope(id, TenantScope::of(ctx)))
+            .and_then(|topo| topo.get_ip_prefix_by_id(&id))
+            .ok_or(Error::UnknownPrefix(prefix_id))?;
+        let Some(IpNet::V4(net)) = prefix.prefix() else {
+            return Err(Error::NotIpv4Prefix(prefix_id));
+        };
+        let count = count.unwrap_or(1).min(FREE_ADDRESS_LIMIT) as usize;
+        Ok(prefix
+            .next_free_ipv4_addresses(count)
+            .iter()
+            .filter_map(|ip| Ipv4Net::new(*ip, net.prefix_len()).ok())
+            .map(|net| IpNet::V4(net).into())
+            .collect())
+    }
 }
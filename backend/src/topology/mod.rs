@@ -1,19 +1,34 @@
-use crate::{netbox::NetboxError, topology::fetch::build_topology};
+use crate::{
+    config::{DEFAULT_NETBOX_INSTANCE, NetboxInstance},
+    context::AccessLevel,
+    netbox::NetboxError,
+    policy::TenantScope,
+    topology::{
+        diff::TopologyChange,
+        fetch::build_topology,
+        snapshot::{SnapshotError, read_fixture, read_snapshot, write_snapshot},
+    },
+};
 use access::device::DeviceAccess;
-use async_graphql::{Interface, SimpleObject, Union};
+use async_graphql::{ComplexObject, Context, Enum, Interface, SimpleObject, Union};
 use ipnet::IpNet;
 use lazy_static::lazy_static;
-use log::{error, info};
+use log::{error, info, warn};
 use mikrotik_model::ascii::AsciiString;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::{Display, Formatter},
     hash::Hash,
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::{Path, PathBuf},
     str::FromStr,
-    sync::Arc,
-    time::Duration,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::{Duration, SystemTime},
 };
 use tokio::{
     sync::{Mutex, MutexGuard},
@@ -21,29 +36,134 @@ use tokio::{
 };
 
 pub mod access;
+pub mod diff;
 pub mod fetch;
 mod graphql;
+pub mod search;
+pub mod snapshot;
 #[cfg(test)]
 pub mod test;
 
 #[derive(Debug, Default, Clone)]
 pub struct TopologyHolder {
+    /// name of the netbox instance, the topologies are stamped with it
+    name: Box<str>,
     data: Arc<Mutex<Option<Arc<Topology>>>>,
+    /// the last topology differing from the current one, to show what changed in netbox
+    previous: Arc<Mutex<Option<Arc<Topology>>>>,
+    /// netbox the topology is fetched from, an offline topology is never refreshed
+    instance: Option<Arc<RwLock<NetboxInstance>>>,
+    snapshot: Option<Arc<Path>>,
+    /// failed fetches since the last successful one
+    fetch_failures: Arc<AtomicU32>,
 }
 
 impl TopologyHolder {
+    pub fn new(name: &str, instance: NetboxInstance, snapshot: Option<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            data: Default::default(),
+            previous: Default::default(),
+            instance: Some(Arc::new(RwLock::new(instance))),
+            snapshot: snapshot.map(Arc::from),
+            fetch_failures: Default::default(),
+        }
+    }
+    /// fixed topology without netbox access
+    pub fn offline(mut topology: Topology) -> Self {
+        topology.instance = DEFAULT_NETBOX_INSTANCE.into();
+        Self {
+            name: DEFAULT_NETBOX_INSTANCE.into(),
+            data: Arc::new(Mutex::new(Some(Arc::new(topology)))),
+            previous: Default::default(),
+            instance: None,
+            snapshot: None,
+            fetch_failures: Default::default(),
+        }
+    }
+    /// offline topology from a json or yaml fixture file
+    pub async fn from_fixture(path: &Path) -> Result<Self, SnapshotError> {
+        Ok(Self::offline(read_fixture(path).await?))
+    }
     pub async fn fetch(&self) -> Result<(), NetboxError> {
+        let Some(instance) = &self.instance else {
+            return Ok(());
+        };
+        let instance = instance.read().expect("netbox instance poisoned").clone();
         let data_ref = self.data.clone();
-        match build_topology().await {
-            Ok(value) => {
-                data_ref.lock().await.replace(Arc::new(value));
+        match build_topology(&instance).await {
+            Ok(mut value) => {
+                value.instance = self.name.clone();
+                self.fetch_failures.store(0, Ordering::Relaxed);
+                if let Some(path) = &self.snapshot {
+                    let unchanged = data_ref
+                        .lock()
+                        .await
+                        .as_ref()
+                        .is_some_and(|current| value.same_data(current));
+                    if !unchanged {
+                        if let Err(e) = write_snapshot(path, &value).await {
+                            warn!("Cannot write topology snapshot {}: {e}", path.display());
+                        }
+                    }
+                }
+                let value = Arc::new(value);
+                let replaced = data_ref.lock().await.replace(value.clone());
+                // a fetch without changes keeps the older topology to compare with
+                if let Some(replaced) = replaced {
+                    if !value.changes_since(&replaced, &TenantScope::All).is_empty() {
+                        self.previous.lock().await.replace(replaced);
+                    }
+                }
                 Ok(())
             }
-            Err(err) => Err(err),
+            Err(err) => {
+                self.fetch_failures.fetch_add(1, Ordering::Relaxed);
+                Err(err)
+            }
+        }
+    }
+    /// failed fetches from netbox since the last successful one, the topology served meanwhile
+    /// is outdated
+    pub fn fetch_failures(&self) -> u32 {
+        self.fetch_failures.load(Ordering::Relaxed)
+    }
+    /// the topology fetched last, an outdated one is not fetched again
+    pub async fn current(&self) -> Option<Arc<Topology>> {
+        self.data.lock().await.clone()
+    }
+    /// the netbox the topology is fetched from, none for an offline topology
+    pub fn instance(&self) -> Option<NetboxInstance> {
+        self.instance
+            .as_ref()
+            .map(|instance| instance.read().expect("netbox instance poisoned").clone())
+    }
+    /// replaces url and token of the netbox instance, used by the next fetch
+    pub fn set_instance(&self, instance: NetboxInstance) {
+        if let Some(current) = &self.instance {
+            *current.write().expect("netbox instance poisoned") = instance;
+        }
+    }
+    /// loads the last persisted topology, returns true if there was one
+    pub async fn load_snapshot(&self) -> bool {
+        let Some(path) = &self.snapshot else {
+            return false;
+        };
+        match read_snapshot(path).await {
+            Ok(Some(mut topology)) => {
+                topology.instance = self.name.clone();
+                self.data.lock().await.replace(Arc::new(topology));
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                warn!("Cannot read topology snapshot {}: {e}", path.display());
+                false
+            }
         }
     }
     pub async fn topo_lock(&self) -> MutexGuard<Option<Arc<Topology>>> {
-        let outdated = {
+        let outdated = self.instance.is_some() && {
             let guard = self.data.lock().await;
             if let Some(data) = &*guard {
                 let duration = data.fetch_time.elapsed();
@@ -62,6 +182,12 @@ impl TopologyHolder {
         self.data.lock().await
     }
 
+    /// changes since the previous topology, none before netbox changed for the first time
+    pub async fn changes(&self, scope: &TenantScope) -> Option<Box<[TopologyChange]>> {
+        let current = self.topo_lock().await.clone()?;
+        let previous = self.previous.lock().await.clone()?;
+        Some(current.changes_since(&previous, scope))
+    }
     pub async fn devices(&self) -> Box<[DeviceAccess]> {
         if let Some(device) = self.topo_lock().await.as_ref().cloned() {
             device.list_devices().collect()
@@ -69,6 +195,17 @@ impl TopologyHolder {
             Box::default()
         }
     }
+    /// the devices of the scope carrying the netbox tag, ordered by name
+    pub async fn tagged_devices(&self, tag: &str, scope: &TenantScope) -> Box<[DeviceAccess]> {
+        let mut devices = self
+            .devices()
+            .await
+            .into_iter()
+            .filter(|device| device.has_tag(tag) && scope.allows(device))
+            .collect::<Vec<_>>();
+        devices.sort_by(|a, b| a.name().cmp(b.name()));
+        devices.into_boxed_slice()
+    }
     pub async fn devices_by_id(&self, id: DeviceId) -> Option<DeviceAccess> {
         if let Some(topo) = self.topo_lock().await.as_ref().cloned() {
             topo.get_device_by_id(&id)
@@ -78,9 +215,115 @@ impl TopologyHolder {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Topologies of all configured netbox instances, keyed by instance name
+#[derive(Debug, Default, Clone)]
+pub struct TopologyInstances {
+    default: Box<str>,
+    instances: Arc<BTreeMap<Box<str>, TopologyHolder>>,
+}
+
+impl TopologyInstances {
+    /// the first instance is taken as default, snapshots are kept as `<instance>.json` in the snapshot directory
+    pub fn new<'a>(
+        instances: impl IntoIterator<Item = (&'a str, NetboxInstance)>,
+        snapshot_dir: Option<&Path>,
+    ) -> Self {
+        let mut default = None;
+        let instances = instances
+            .into_iter()
+            .map(|(name, instance)| {
+                default.get_or_insert_with(|| Box::from(name));
+                let snapshot = snapshot_dir.map(|dir| dir.join(format!("{name}.json")));
+                (
+                    Box::from(name),
+                    TopologyHolder::new(name, instance, snapshot),
+                )
+            })
+            .collect();
+        Self {
+            default: default.unwrap_or_default(),
+            instances: Arc::new(instances),
+        }
+    }
+    /// topology of the named instance, the default instance if no name is given
+    pub fn get(&self, name: Option<&str>) -> Option<&TopologyHolder> {
+        self.instances.get(name.unwrap_or(&self.default))
+    }
+    /// name of the given instance or of the default instance
+    pub fn resolve_name<'a>(&'a self, name: Option<&'a str>) -> &'a str {
+        name.unwrap_or(&self.default)
+    }
+    /// names of all instances, the default instance first
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.instances
+            .keys()
+            .filter(|name| name.as_ref() == self.default.as_ref())
+            .chain(
+                self.instances
+                    .keys()
+                    .filter(|name| name.as_ref() != self.default.as_ref()),
+            )
+            .map(Box::as_ref)
+    }
+    /// updates url and token of the instances after a configuration reload, instances
+    /// are only added or removed by a restart
+    pub fn reconfigure<'a>(&self, instances: impl IntoIterator<Item = (&'a str, NetboxInstance)>) {
+        let mut configured = HashSet::new();
+        for (name, instance) in instances {
+            configured.insert(name);
+            match self.instances.get(name) {
+                Some(holder) => holder.set_instance(instance),
+                None => warn!("Netbox instance {name} is only added on restart"),
+            }
+        }
+        for name in self.instances.keys() {
+            if !configured.contains(name.as_ref()) {
+                warn!("Netbox instance {name} is only removed on restart");
+            }
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TopologyHolder)> {
+        self.instances
+            .iter()
+            .map(|(name, holder)| (name.as_ref(), holder))
+    }
+    /// a single offline instance
+    pub fn offline(holder: TopologyHolder) -> Self {
+        Self {
+            default: Box::from(DEFAULT_NETBOX_INSTANCE),
+            instances: Arc::new(BTreeMap::from([(
+                Box::from(DEFAULT_NETBOX_INSTANCE),
+                holder,
+            )])),
+        }
+    }
+    /// loads the snapshots and fetches all instances, an instance not reachable is only
+    /// an error if there is no snapshot of it
+    pub async fn initialize(&self) -> Result<(), NetboxError> {
+        for (name, holder) in self.instances.iter() {
+            let has_snapshot = holder.load_snapshot().await;
+            match holder.fetch().await {
+                Ok(()) => {}
+                Err(e) if has_snapshot => {
+                    warn!("Cannot fetch netbox instance {name}, serving snapshot: {e}")
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Topology {
+    #[serde(skip, default = "Instant::now")]
     fetch_time: Instant,
+    /// wall clock time of the fetch, kept in the snapshot
+    fetched_at: SystemTime,
+    /// name of the netbox instance, set by the [`TopologyHolder`]
+    #[serde(skip)]
+    instance: Box<str>,
     devices: HashMap<DeviceId, Device>,
     interfaces: HashMap<InterfaceId, Interface>,
     front_ports: HashMap<FrontPortId, FrontPort>,
@@ -96,59 +339,257 @@ pub struct Topology {
     ip_prefixes: HashMap<IpPrefixId, IpPrefixData>,
     ip_ranges: HashMap<IpRangeId, IpRangeData>,
     ip_range_idx: HashMap<IpNet, Box<[IpRangeId]>>,
+    fhrp_groups: HashMap<FhrpGroupId, FhrpGroupData>,
+    vrfs: HashMap<VrfId, VrfData>,
+    tunnels: HashMap<TunnelId, TunnelData>,
+    sites: HashMap<SiteId, SiteData>,
+    locations: HashMap<LocationId, LocationData>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+impl Topology {
+    /// name of the netbox instance the topology is fetched from
+    pub fn instance_name(&self) -> &str {
+        &self.instance
+    }
+    /// time the topology was fetched from netbox, for a topology loaded from a snapshot the
+    /// time its data was fetched
+    pub fn fetched_at(&self) -> SystemTime {
+        self.fetched_at
+    }
+    /// the same data fetched from netbox, whenever it was fetched
+    pub fn same_data(&mut self, other: &Topology) -> bool {
+        let fetch_time = std::mem::replace(&mut self.fetch_time, other.fetch_time);
+        let fetched_at = std::mem::replace(&mut self.fetched_at, other.fetched_at);
+        let same = *self == *other;
+        self.fetch_time = fetch_time;
+        self.fetched_at = fetched_at;
+        same
+    }
+    fn interface_in_scope(&self, id: InterfaceId, scope: &TenantScope) -> bool {
+        self.interfaces
+            .get(&id)
+            .and_then(|interface| self.devices.get(&interface.device))
+            .is_some_and(|device| scope.allows_tenant(device.tenant.as_deref()))
+    }
+    /// addresses are visible on the interfaces of devices in the scope, unassigned addresses
+    /// only to users seeing all tenants
+    pub fn address_in_scope(&self, id: IpAddressId, scope: &TenantScope) -> bool {
+        match scope {
+            TenantScope::All => true,
+            TenantScope::Tenants(_) => self
+                .ip_addresses
+                .get(&id)
+                .and_then(|address| address.interface)
+                .is_some_and(|interface| self.interface_in_scope(interface, scope)),
+        }
+    }
+    /// vlans have no tenant in the topology either, a vlan is visible if it terminates on an
+    /// interface of a device in the scope
+    pub fn vlan_in_scope(&self, id: VlanId, scope: &TenantScope) -> bool {
+        match scope {
+            TenantScope::All => true,
+            TenantScope::Tenants(_) => self.vlans.get(&id).is_some_and(|vlan| {
+                vlan.terminations
+                    .iter()
+                    .any(|interface| self.interface_in_scope(*interface, scope))
+            }),
+        }
+    }
+    /// netbox has no tenant on the prefixes of the topology, a prefix is visible if it or one
+    /// of its child prefixes holds a visible address
+    pub fn prefix_in_scope(&self, id: IpPrefixId, scope: &TenantScope) -> bool {
+        match scope {
+            TenantScope::All => true,
+            TenantScope::Tenants(_) => self.ip_prefixes.get(&id).is_some_and(|prefix| {
+                prefix
+                    .addresses
+                    .iter()
+                    .any(|address| self.address_in_scope(*address, scope))
+                    || prefix
+                        .children
+                        .iter()
+                        .any(|child| self.prefix_in_scope(*child, scope))
+            }),
+        }
+    }
+}
+
+impl Default for Topology {
+    fn default() -> Self {
+        Self {
+            fetch_time: Instant::now(),
+            fetched_at: SystemTime::now(),
+            instance: Default::default(),
+            devices: Default::default(),
+            interfaces: Default::default(),
+            front_ports: Default::default(),
+            rear_ports: Default::default(),
+            cables: Default::default(),
+            vxlans: Default::default(),
+            wlan_groups: Default::default(),
+            wlans: Default::default(),
+            vlan_groups: Default::default(),
+            vlans: Default::default(),
+            ip_addresses: Default::default(),
+            ip_prefixes: Default::default(),
+            ip_ranges: Default::default(),
+            ip_range_idx: Default::default(),
+            fhrp_groups: Default::default(),
+            vrfs: Default::default(),
+            tunnels: Default::default(),
+            sites: Default::default(),
+            locations: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Device {
     pub name: Box<str>,
     pub ports: HashSet<CablePort>,
     pub primary_ip: Option<IpAddressId>,
     pub primary_ip_v4: Option<IpAddressId>,
     pub primary_ip_v6: Option<IpAddressId>,
+    /// out-of-band management address
+    pub oob_ip: Option<IpAddressId>,
     pub loopback_ip: Option<IpAddressId>,
     pub credentials: Option<Box<str>>,
+    /// pppoe account in the configuration, netbox custom field `pppoe_credentials`
+    pub pppoe_credentials: Option<Box<str>>,
+    /// clients may open ports on the wan interfaces, netbox custom field `upnp`
+    pub upnp: bool,
+    /// multicast of the wan interfaces is forwarded to the clients, netbox custom field
+    /// `igmp_proxy`
+    pub igmp_proxy: bool,
     pub has_routeros: bool,
     pub serial: Option<Box<str>>,
     pub wlan_controller_of: Option<WlanGroupId>,
     pub wlan_ap_of: Option<WlanGroupId>,
     pub vlans: Box<[VlanId]>,
+    pub monitor: bool,
+    pub netwatch_profiles: Box<[Box<str>]>,
+    /// slug of the netbox device role
+    pub role: Option<Box<str>>,
+    pub site: Option<SiteId>,
+    pub location: Option<LocationId>,
+    /// slug of the netbox tenant, of the location or of the site if the device has none
+    pub tenant: Option<Box<str>>,
+    pub status: DeviceStatus,
+    /// slugs of all netbox tags, to select groups of devices
+    pub tags: Box<[Box<str>]>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Lifecycle status of a device in netbox
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceStatus {
+    Offline,
+    #[default]
+    Active,
+    Planned,
+    Staged,
+    Failed,
+    Inventory,
+    Decommissioning,
+}
+
+impl DeviceStatus {
+    /// status of a netbox status value, custom values are treated as active
+    pub fn from_netbox(value: &str) -> Self {
+        match value {
+            "offline" => DeviceStatus::Offline,
+            "active" => DeviceStatus::Active,
+            "planned" => DeviceStatus::Planned,
+            "staged" => DeviceStatus::Staged,
+            "failed" => DeviceStatus::Failed,
+            "inventory" => DeviceStatus::Inventory,
+            "decommissioning" => DeviceStatus::Decommissioning,
+            other => {
+                warn!("Unknown device status {other}, treated as active");
+                DeviceStatus::Active
+            }
+        }
+    }
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeviceStatus::Offline => "offline",
+            DeviceStatus::Active => "active",
+            DeviceStatus::Planned => "planned",
+            DeviceStatus::Staged => "staged",
+            DeviceStatus::Failed => "failed",
+            DeviceStatus::Inventory => "inventory",
+            DeviceStatus::Decommissioning => "decommissioning",
+        }
+    }
+    /// plans may be applied, planned devices are only bootstrapped with the generated script,
+    /// decommissioning devices only get disabled or wiped and offline devices are out of
+    /// operation
+    pub fn allows_apply(self) -> bool {
+        matches!(
+            self,
+            DeviceStatus::Active | DeviceStatus::Staged | DeviceStatus::Failed
+        )
+    }
+    /// the device takes part in the periodic monitoring, drift checks and digests
+    pub fn in_fleet(self) -> bool {
+        matches!(
+            self,
+            DeviceStatus::Active | DeviceStatus::Staged | DeviceStatus::Failed
+        )
+    }
+}
+
+impl Display for DeviceStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct VxlanData {
     pub name: Box<str>,
     pub vni: u32,
     pub interface_terminations: Box<[InterfaceId]>,
     pub vlan_terminations: Box<[VlanId]>,
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct VlanData {
     pub name: Box<str>,
     pub vlan_id: u16,
     pub group: VlanGroupId,
+    /// tagged `guest` in netbox, the clients are isolated from the private networks
+    #[serde(default)]
+    pub guest: bool,
+    /// tagged `captive-portal` in netbox, the clients have to log in to the hotspot
+    #[serde(default)]
+    pub captive_portal: bool,
     pub terminations: Box<[InterfaceId]>,
     pub vxlan: Option<VxlanId>,
     pub wlans: Box<[WlanId]>,
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WlanGroupData {
     pub mgmt_vlan: Option<VlanId>,
     pub controller: DeviceId,
     pub aps: Box<[DeviceId]>,
     pub wlans: Box<[WlanId]>,
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct VlanGroupData {
     pub vlans: Box<[VlanId]>,
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WlanData {
     pub ssid: Box<str>,
     pub vlan: Option<VlanId>,
     pub wlan_auth: WlanAuth,
     pub wlan_group: WlanGroupId,
+    /// switched on and off by the configured schedule, tagged `schedule-<name>` in netbox
+    #[serde(default)]
+    pub schedule: Option<Box<str>>,
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct IpRangeData {
     pub is_dhcp: bool,
     pub net: IpNet,
@@ -156,30 +597,142 @@ pub struct IpRangeData {
     pub end: IpAddr,
     pub prefix: Option<IpPrefixId>,
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct IpAddressData {
     pub ip: IpNet,
     pub interface: Option<InterfaceId>,
     pub prefix: Option<IpPrefixId>,
+    pub vrf: Option<VrfId>,
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct IpPrefixData {
     pub prefix: IpNet,
     pub addresses: Box<[IpAddressId]>,
     pub children: Box<[IpPrefixId]>,
     pub parent: Option<IpPrefixId>,
     pub ranges: Box<[IpRangeId]>,
+    pub vrf: Option<VrfId>,
+    /// option set of the dhcp server in the configuration, netbox custom field `dhcp_options`
+    #[serde(default)]
+    pub dhcp_options: Option<Box<str>>,
+    /// dhcp servers the relays of the prefix forward to, netbox custom field `dhcp_relay`
+    #[serde(default)]
+    pub dhcp_relay: Box<[Ipv4Addr]>,
+    /// role `loopback` in netbox, the loopback addresses of new devices are taken from it
+    #[serde(default)]
+    pub loopback: bool,
 }
-#[derive(Debug, Clone, PartialEq, Eq, Union)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TunnelData {
+    pub name: Box<str>,
+    pub encapsulation: TunnelEncapsulation,
+    pub tunnel_id: Option<u16>,
+    pub terminations: Box<[TunnelTermination]>,
+}
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TunnelTermination {
+    pub interface: InterfaceId,
+    pub outside_ip: Option<IpAddressId>,
+}
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TunnelEncapsulation {
+    Gre,
+    /// netbox has no eoip encapsulation, gre tunnels tagged with `eoip` are used
+    Eoip,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VrfData {
+    pub name: Box<str>,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiteData {
+    pub name: Box<str>,
+    pub slug: Box<str>,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocationData {
+    pub name: Box<str>,
+    pub site: SiteId,
+    pub parent: Option<LocationId>,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FhrpGroupData {
+    pub group_id: u8,
+    pub protocol: FhrpProtocol,
+    pub virtual_ips: Box<[IpAddressId]>,
+    pub assignments: Box<[FhrpAssignment]>,
+}
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FhrpAssignment {
+    pub interface: InterfaceId,
+    pub priority: u8,
+}
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FhrpProtocol {
+    Vrrp2,
+    Vrrp3,
+    Other,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Union, Serialize, Deserialize)]
 pub enum WlanAuth {
     Wpa(WlanWpaSettings),
+    WpaEnterprise(WlanEnterpriseSettings),
     Open(WlanOpenSettings),
 }
-#[derive(Debug, Clone, PartialEq, Eq, SimpleObject)]
+#[derive(Debug, Clone, PartialEq, Eq, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
 pub struct WlanWpaSettings {
+    #[graphql(skip)]
     key: Box<str>,
 }
-#[derive(Debug, Clone, PartialEq, Eq, SimpleObject)]
+impl WlanWpaSettings {
+    pub fn pre_shared_key(&self) -> &str {
+        &self.key
+    }
+}
+#[ComplexObject]
+impl WlanWpaSettings {
+    /// the pre-shared key, only visible to admins
+    async fn key(&self, ctx: &Context<'_>) -> Option<&str> {
+        if ctx.data_opt::<AccessLevel>() == Some(&AccessLevel::Admin) {
+            Some(&self.key)
+        } else {
+            None
+        }
+    }
+}
+/// WPA with 802.1X, the clients are authenticated by the configured RADIUS server
+#[derive(Debug, Clone, PartialEq, Eq, SimpleObject, Serialize, Deserialize)]
+pub struct WlanEnterpriseSettings {
+    cipher: WlanCipher,
+}
+impl WlanEnterpriseSettings {
+    pub fn new(cipher: WlanCipher) -> Self {
+        Self { cipher }
+    }
+    pub fn cipher(&self) -> WlanCipher {
+        self.cipher
+    }
+}
+/// Encryption of a wlan, `auto` allows all ciphers
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "lowercase")]
+pub enum WlanCipher {
+    #[default]
+    Auto,
+    Tkip,
+    Aes,
+}
+impl WlanCipher {
+    pub fn from_netbox(value: Option<&str>) -> Self {
+        match value {
+            Some("tkip") => WlanCipher::Tkip,
+            Some("aes") => WlanCipher::Aes,
+            _ => WlanCipher::Auto,
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Eq, SimpleObject, Serialize, Deserialize)]
 pub struct WlanOpenSettings {
     use_owe: bool,
 }
@@ -193,7 +746,8 @@ impl Device {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Default)]
+#[derive(Debug, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Interface {
     pub name: Box<str>,
     pub label: Box<str>,
@@ -206,20 +760,41 @@ pub struct Interface {
     pub use_ospf: bool,
     pub enable_dhcp_client: bool,
     pub enable_dhcp_server: bool,
+    /// tagged `dhcp-relay` in netbox, the dhcp requests are forwarded to a central server
+    pub enable_dhcp_relay: bool,
+    /// tagged `captive-portal` in netbox
+    pub captive_portal: bool,
+    /// poe switched on and off by the configured schedule, tagged `schedule-<name>` in netbox
+    pub schedule: Option<Box<str>>,
     pub bridge: Option<InterfaceId>,
     pub cable: Option<CableId>,
     pub enable_poe: bool,
+    pub fhrp_groups: Box<[FhrpGroupId]>,
+    pub tunnel: Option<TunnelId>,
+    /// out-of-band management interface, `mgmt_only` in netbox or tagged with `mgmt`
+    pub management: bool,
+    /// primary mac address in netbox, upper case with colons like `48:A9:8A:01:02:03`
+    pub mac_address: Option<Box<str>>,
+    /// uplink to the internet provider, tagged `wan` in netbox
+    pub wan: bool,
+    /// dials into the provider by pppoe, tagged `pppoe` in netbox
+    pub pppoe: bool,
+    /// apn profile of an lte interface, tagged `apn-<name>` in netbox
+    pub apn: Option<Box<str>>,
 }
 
-#[derive(Debug, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Copy)]
+#[derive(Debug, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Copy, Serialize, Deserialize)]
 pub enum PortType {
     Ethernet,
     Wireless,
     Loopback,
     Bridge,
+    Tunnel,
+    /// cellular modem, a backup of the wan interfaces
+    Lte,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub enum PhysicalPortId {
     Ethernet(u16),
     SfpSfpPlus(u16),
@@ -227,21 +802,21 @@ pub enum PhysicalPortId {
     Wlan(u16),
     Loopback,
 }
-#[derive(Debug, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Default)]
+#[derive(Debug, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Default, Serialize, Deserialize)]
 pub struct FrontPort {
     pub name: Box<str>,
     pub device: DeviceId,
     pub rear_port: Option<RearPortId>,
     pub cable: Option<CableId>,
 }
-#[derive(Debug, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Default)]
+#[derive(Debug, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Default, Serialize, Deserialize)]
 pub struct RearPort {
     pub name: Box<str>,
     pub device: DeviceId,
     pub front_port: Option<FrontPortId>,
     pub cable: Option<CableId>,
 }
-#[derive(Debug, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Default)]
+#[derive(Debug, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Default, Serialize, Deserialize)]
 pub struct Cable {
     pub port_a: Box<[CablePort]>,
     pub port_b: Box<[CablePort]>,
@@ -342,34 +917,46 @@ impl Display for PhysicalPortId {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub struct InterfaceId(pub u32);
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Default)]
+#[derive(
+    Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Default, Serialize, Deserialize,
+)]
 pub struct DeviceId(pub u32);
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub struct VxlanId(pub u32);
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub struct VlanId(pub u32);
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub struct WlanId(pub u32);
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub struct VlanGroupId(pub u32);
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub struct WlanGroupId(pub u32);
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub struct FrontPortId(pub u32);
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub struct RearPortId(pub u32);
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub struct CableId(pub u32);
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub struct IpRangeId(pub u32);
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub struct IpAddressId(pub u32);
-#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub struct IpPrefixId(pub u32);
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
+pub struct FhrpGroupId(pub u32);
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
+pub struct VrfId(pub u32);
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
+pub struct TunnelId(pub u32);
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
+pub struct SiteId(pub u32);
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash, Serialize, Deserialize)]
+pub struct LocationId(pub u32);
 
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum CablePort {
     Interface(InterfaceId),
     FrontPort(FrontPortId),
@@ -446,6 +1033,31 @@ impl Display for IpPrefixId {
         self.0.fmt(f)
     }
 }
+impl From<u32> for FhrpGroupId {
+    fn from(value: u32) -> Self {
+        FhrpGroupId(value)
+    }
+}
+impl From<u32> for VrfId {
+    fn from(value: u32) -> Self {
+        VrfId(value)
+    }
+}
+impl From<u32> for TunnelId {
+    fn from(value: u32) -> Self {
+        TunnelId(value)
+    }
+}
+impl From<u32> for SiteId {
+    fn from(value: u32) -> Self {
+        SiteId(value)
+    }
+}
+impl From<u32> for LocationId {
+    fn from(value: u32) -> Self {
+        LocationId(value)
+    }
+}
 
 impl From<u32> for InterfaceId {
     fn from(value: u32) -> Self {
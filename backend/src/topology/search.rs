@@ -0,0 +1,150 @@
+use crate::{
+    policy::TenantScope,
+    topology::{DeviceId, IpPrefixId, Topology, VlanId},
+};
+use async_graphql::{Enum, SimpleObject};
+use std::net::IpAddr;
+
+/// Kind of netbox object found by the search
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SearchHitKind {
+    Device,
+    Vlan,
+    Prefix,
+}
+
+/// How well a value matches the query, better matches are ordered first
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Rank {
+    Exact,
+    Prefix,
+    Contains,
+}
+
+/// Object matching a search query
+#[derive(Debug, Clone, PartialEq, Eq, SimpleObject)]
+pub struct SearchHit {
+    kind: SearchHitKind,
+    /// netbox id of the object
+    id: u32,
+    name: Box<str>,
+    /// the matching value if it is not the name, e.g. the serial or an address
+    detail: Option<Box<str>>,
+    #[graphql(skip)]
+    rank: Rank,
+}
+
+impl SearchHit {
+    pub fn kind(&self) -> SearchHitKind {
+        self.kind
+    }
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn detail(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+}
+
+impl Topology {
+    /// Devices, vlans and prefixes matching the query, ignoring the case. Devices are found by
+    /// name, serial and address, vlans by name and vlan id and prefixes by their network. An
+    /// address finds the prefixes containing it as well. Exact matches come first, devices of
+    /// tenants outside the scope are left out.
+    pub fn search(&self, query: &str, scope: &TenantScope, limit: usize) -> Box<[SearchHit]> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Box::default();
+        }
+        let address = query.parse::<IpAddr>().ok();
+        let mut hits = Vec::new();
+        for (DeviceId(id), device) in &self.devices {
+            if !scope.allows_tenant(device.tenant.as_deref()) {
+                continue;
+            }
+            let addresses = self
+                .ip_addresses
+                .values()
+                .filter(|address| {
+                    address
+                        .interface
+                        .and_then(|interface| self.interfaces.get(&interface))
+                        .is_some_and(|interface| interface.device == DeviceId(*id))
+                })
+                .map(|address| address.ip.addr().to_string());
+            let matched = rank(&device.name, &query)
+                .map(|rank| (rank, None))
+                .or_else(|| {
+                    let serial = device.serial.as_deref()?;
+                    rank(serial, &query).map(|rank| (rank, Some(serial.into())))
+                })
+                .or_else(|| {
+                    addresses
+                        .filter_map(|address| rank(&address, &query).map(|rank| (rank, address)))
+                        .min()
+                        .map(|(rank, address)| (rank, Some(address.into())))
+                });
+            if let Some((rank, detail)) = matched {
+                hits.push(SearchHit {
+                    kind: SearchHitKind::Device,
+                    id: *id,
+                    name: device.name.clone(),
+                    detail,
+                    rank,
+                });
+            }
+        }
+        for (VlanId(id), vlan) in &self.vlans {
+            let vlan_id = vlan.vlan_id.to_string();
+            let matched =
+                rank(&vlan.name, &query).or_else(|| (vlan_id == query).then_some(Rank::Exact));
+            if let Some(rank) = matched {
+                hits.push(SearchHit {
+                    kind: SearchHitKind::Vlan,
+                    id: *id,
+                    name: vlan.name.clone(),
+                    detail: Some(format!("vlan {vlan_id}").into()),
+                    rank,
+                });
+            }
+        }
+        for (IpPrefixId(id), prefix) in &self.ip_prefixes {
+            let network = prefix.prefix.to_string();
+            // the containing prefix is a weaker match than the device with the address
+            let matched = rank(&network, &query).or_else(|| {
+                address
+                    .filter(|address| prefix.prefix.contains(address))
+                    .map(|_| Rank::Prefix)
+            });
+            if let Some(rank) = matched {
+                hits.push(SearchHit {
+                    kind: SearchHitKind::Prefix,
+                    id: *id,
+                    name: network.into(),
+                    detail: None,
+                    rank,
+                });
+            }
+        }
+        hits.sort_by(|a, b| (a.rank, a.kind, &a.name, a.id).cmp(&(b.rank, b.kind, &b.name, b.id)));
+        hits.truncate(limit);
+        hits.into_boxed_slice()
+    }
+}
+
+/// the query is expected in lower case
+fn rank(value: &str, query: &str) -> Option<Rank> {
+    let value = value.to_lowercase();
+    if value == query {
+        Some(Rank::Exact)
+    } else if value.starts_with(query) {
+        Some(Rank::Prefix)
+    } else if value.contains(query) {
+        Some(Rank::Contains)
+    } else {
+        None
+    }
+}
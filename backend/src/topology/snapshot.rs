@@ -0,0 +1,81 @@
+use crate::topology::Topology;
+use log::info;
+use std::{io::ErrorKind, path::Path};
+use thiserror::Error;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("accessing topology snapshot {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid topology snapshot {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid topology fixture {0}")]
+    Yaml(#[from] serde_yaml::Error),
+}
+
+/// File format of a topology fixture
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FixtureFormat {
+    Json,
+    Yaml,
+}
+
+impl FixtureFormat {
+    /// yaml for `.yaml` and `.yml` files, json otherwise
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => FixtureFormat::Yaml,
+            _ => FixtureFormat::Json,
+        }
+    }
+}
+
+pub fn parse_topology(data: &[u8], format: FixtureFormat) -> Result<Topology, SnapshotError> {
+    Ok(match format {
+        FixtureFormat::Json => serde_json::from_slice(data)?,
+        FixtureFormat::Yaml => serde_yaml::from_slice(data)?,
+    })
+}
+
+/// Reads a topology fixture as json or yaml, depending on the file extension
+pub async fn read_fixture(path: &Path) -> Result<Topology, SnapshotError> {
+    let data = tokio::fs::read(path).await?;
+    parse_topology(&data, FixtureFormat::of(path))
+}
+
+/// Writes the topology to a temporary file first, so a crash never leaves a partial snapshot.
+/// The snapshot holds the wlan keys, only the owner can read it.
+pub async fn write_snapshot(path: &Path, topology: &Topology) -> Result<(), SnapshotError> {
+    let data = serde_json::to_vec(topology)?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let temp_path = path.with_extension("tmp");
+    // the mode applies only to a new file
+    match tokio::fs::remove_file(&temp_path).await {
+        Err(error) if error.kind() != ErrorKind::NotFound => return Err(error.into()),
+        _ => {}
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(&temp_path).await?;
+    file.write_all(&data).await?;
+    file.sync_all().await?;
+    tokio::fs::rename(&temp_path, path).await?;
+    Ok(())
+}
+
+/// Reads a snapshot written by [write_snapshot], a missing file is not an error
+pub async fn read_snapshot(path: &Path) -> Result<Option<Topology>, SnapshotError> {
+    let data = match tokio::fs::read(path).await {
+        Ok(data) => data,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    let topology = parse_topology(&data, FixtureFormat::Json)?;
+    info!("Loaded topology snapshot from {}", path.display());
+    Ok(Some(topology))
+}
@@ -1,16 +1,33 @@
+use crate::graphql::errors::ErrorCodes;
+use crate::policy::TenantScope;
 use crate::topology::{
-    Cable, CableId, CablePort, Device, DeviceId, FrontPort, FrontPortId, Interface, InterfaceId,
-    IpAddressData, IpAddressId, IpPrefixData, IpPrefixId, IpRangeData, IpRangeId, RearPort,
-    RearPortId, Topology, TopologyHolder, VlanData, VlanGroupData, VlanGroupId, VlanId, VxlanData,
-    VxlanId, WlanData, WlanGroupData, WlanGroupId, WlanId,
+    Cable, CableId, CablePort, Device, DeviceId, DeviceStatus, FhrpGroupData, FhrpGroupId,
+    FrontPort, FrontPortId, Interface, InterfaceId, IpAddressData, IpAddressId, IpPrefixData,
+    IpPrefixId, IpRangeData, IpRangeId, LocationData, LocationId, RearPort, RearPortId, SiteData,
+    SiteId, Topology, TopologyHolder, TunnelData, TunnelId, VlanData, VlanGroupData, VlanGroupId,
+    VlanId, VrfData, VrfId, VxlanData, VxlanId, WlanCipher, WlanData, WlanGroupData, WlanGroupId,
+    WlanId,
+    access::{
+        AccessTopology,
+        consistency::{ConsistencyFinding, FindingKind},
+        device::DeviceAccess,
+        impact::ImpactReason,
+        vlan_matrix::VlanMembership,
+        vxlan::VxlanAccess,
+    },
+    diff::{ChangeKind, ChangedObject},
+    search::SearchHitKind,
+    snapshot::{FixtureFormat, parse_topology, read_snapshot, write_snapshot},
 };
+use async_graphql::{EmptyMutation, EmptySubscription, Schema, value};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     hash::Hash,
     ops::{Deref, DerefMut},
     sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use tokio::{sync::Mutex, time::Instant};
+use tokio::time::Instant;
 
 #[derive(Default, Clone)]
 pub struct TopologyBuilder {
@@ -27,6 +44,11 @@ pub struct TopologyBuilder {
     pub ip_addresses: MapBuilder<IpAddressId, IpAddressData>,
     pub ip_prefixes: MapBuilder<IpPrefixId, IpPrefixData>,
     pub ip_ranges: MapBuilder<IpRangeId, IpRangeData>,
+    pub fhrp_groups: MapBuilder<FhrpGroupId, FhrpGroupData>,
+    pub vrfs: MapBuilder<VrfId, VrfData>,
+    pub tunnels: MapBuilder<TunnelId, TunnelData>,
+    pub sites: MapBuilder<SiteId, SiteData>,
+    pub locations: MapBuilder<LocationId, LocationData>,
 }
 
 #[derive(Clone)]
@@ -100,7 +122,7 @@ impl TopologyBuilder {
             }
         }
         for (id, cable) in self.cables.deref() {
-            for cable_port in cable.port_b.iter() {
+            for cable_port in cable.port_a.iter().chain(cable.port_b.iter()) {
                 match cable_port {
                     CablePort::Interface(if_id) => {
                         self.interfaces
@@ -123,6 +145,30 @@ impl TopologyBuilder {
                 }
             }
         }
+        for (id, group) in self.fhrp_groups.deref() {
+            for assignment in group.assignments.iter() {
+                let interface = self
+                    .interfaces
+                    .get_mut(&assignment.interface)
+                    .expect("interface not found");
+                if !interface.fhrp_groups.contains(id) {
+                    interface.fhrp_groups = interface
+                        .fhrp_groups
+                        .iter()
+                        .copied()
+                        .chain(Some(*id))
+                        .collect();
+                }
+            }
+        }
+        for (id, tunnel) in self.tunnels.deref() {
+            for termination in tunnel.terminations.iter() {
+                self.interfaces
+                    .get_mut(&termination.interface)
+                    .expect("interface not found")
+                    .tunnel = Some(*id);
+            }
+        }
         for (id, front_port) in self.front_ports.deref() {
             if let Some(rp_id) = front_port.rear_port {
                 self.rear_ports
@@ -142,18 +188,18 @@ impl TopologyBuilder {
 
         let mut prefix_idx = HashMap::new();
         for (id, prefix_data) in self.ip_prefixes.deref() {
-            prefix_idx.insert(prefix_data.prefix, *id);
+            prefix_idx.insert((prefix_data.vrf, prefix_data.prefix), *id);
         }
 
         let mut ranges_of_prefix = HashMap::new();
 
         let mut ip_ranges_idx = HashMap::new();
         for (id, range) in self.ip_ranges.deref_mut() {
-            let prefix_id = if let Some(prefix_id) = prefix_idx.get(&range.net) {
+            let prefix_id = if let Some(prefix_id) = prefix_idx.get(&(None, range.net)) {
                 *prefix_id
             } else {
                 let prefix_id = self.ip_prefixes.next_id();
-                prefix_idx.insert(range.net, prefix_id);
+                prefix_idx.insert((None, range.net), prefix_id);
                 self.ip_prefixes.insert(
                     prefix_id,
                     IpPrefixData {
@@ -162,6 +208,10 @@ impl TopologyBuilder {
                         children: Box::new([]),
                         parent: None,
                         ranges: Box::new([]),
+                        vrf: None,
+                        dhcp_options: None,
+                        dhcp_relay: Box::new([]),
+                        loopback: false,
                     },
                 );
                 prefix_id
@@ -178,11 +228,12 @@ impl TopologyBuilder {
         let mut ips_of_prefixes = HashMap::new();
         for (id, address_data) in self.ip_addresses.deref_mut() {
             let net = address_data.ip.trunc();
-            let prefix_id = if let Some(prefix_id) = prefix_idx.get(&net) {
+            let vrf = address_data.vrf;
+            let prefix_id = if let Some(prefix_id) = prefix_idx.get(&(vrf, net)) {
                 *prefix_id
             } else {
                 let prefix_id = self.ip_prefixes.next_id();
-                prefix_idx.insert(net, prefix_id);
+                prefix_idx.insert((vrf, net), prefix_id);
                 self.ip_prefixes.insert(
                     prefix_id,
                     IpPrefixData {
@@ -191,6 +242,10 @@ impl TopologyBuilder {
                         children: Box::new([]),
                         parent: None,
                         ranges: Box::new([]),
+                        vrf,
+                        dhcp_options: None,
+                        dhcp_relay: Box::new([]),
+                        loopback: false,
                     },
                 );
                 prefix_id
@@ -206,7 +261,7 @@ impl TopologyBuilder {
             prefix_data.parent = None;
             let mut prefix = prefix_data.prefix;
             while let Some(net_address) = prefix.supernet() {
-                if let Some(parent_idx) = prefix_idx.get(&net_address) {
+                if let Some(parent_idx) = prefix_idx.get(&(prefix_data.vrf, net_address)) {
                     prefix_data.parent = Some(*parent_idx);
                     children_prefix
                         .entry(*parent_idx)
@@ -234,6 +289,8 @@ impl TopologyBuilder {
 
         Topology {
             fetch_time: Instant::now(),
+            fetched_at: SystemTime::now(),
+            instance: Default::default(),
             devices: self.devices.into(),
             interfaces: self.interfaces.into(),
             front_ports: self.front_ports.into(),
@@ -251,13 +308,1167 @@ impl TopologyBuilder {
                 .into_iter()
                 .map(|(id, ranges)| (id, ranges.into_boxed_slice()))
                 .collect(),
+            fhrp_groups: self.fhrp_groups.into(),
+            vrfs: self.vrfs.into(),
+            tunnels: self.tunnels.into(),
+            sites: self.sites.into(),
+            locations: self.locations.into(),
         }
     }
 }
 impl From<TopologyBuilder> for TopologyHolder {
     fn from(value: TopologyBuilder) -> Self {
-        TopologyHolder {
-            data: Arc::new(Mutex::new(Some(Arc::new(value.build())))),
+        TopologyHolder::offline(value.build())
+    }
+}
+
+#[tokio::test]
+async fn test_load_yaml_fixture() {
+    let topology = parse_topology(
+        include_bytes!("fixtures/single-router.yaml"),
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid fixture");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    assert_eq!(device.name(), "router");
+    assert!(device.has_routeros());
+    let mut interfaces = device
+        .interfaces()
+        .iter()
+        .map(|i| i.name().to_string())
+        .collect::<Vec<_>>();
+    interfaces.sort();
+    assert_eq!(interfaces, vec!["ether1", "ether2"]);
+}
+
+#[test]
+fn test_json_roundtrip() {
+    let mut builder = TopologyBuilder::default();
+    let device = builder.devices.next_id();
+    let interface = builder.interfaces.next_id();
+    builder.devices.insert(
+        device,
+        Device {
+            name: "switch".into(),
+            ports: [CablePort::Interface(interface)].into_iter().collect(),
+            has_routeros: true,
+            ..Default::default()
+        },
+    );
+    builder.interfaces.insert(
+        interface,
+        Interface {
+            name: "ether1".into(),
+            device,
+            ..Default::default()
+        },
+    );
+    let topology = builder.build();
+    let json = serde_json::to_vec(&topology).expect("cannot serialize");
+    let loaded = parse_topology(&json, FixtureFormat::Json).expect("cannot deserialize");
+    assert_eq!(loaded.devices, topology.devices);
+    assert_eq!(loaded.interfaces, topology.interfaces);
+}
+
+#[tokio::test]
+async fn test_cable_path_through_patch_panel() {
+    let mut builder = TopologyBuilder::default();
+    let switch = builder.devices.next_id();
+    let router = builder.devices.next_id();
+    let panel = builder.devices.next_id();
+    let switch_port = builder.interfaces.next_id();
+    let router_port = builder.interfaces.next_id();
+    let front = builder.front_ports.next_id();
+    let rear = builder.rear_ports.next_id();
+    for (device, name, tenant, ports) in [
+        (
+            switch,
+            "switch",
+            Some("a"),
+            vec![CablePort::Interface(switch_port)],
+        ),
+        (
+            router,
+            "router",
+            None,
+            vec![CablePort::Interface(router_port)],
+        ),
+        (
+            panel,
+            "panel",
+            None,
+            vec![CablePort::FrontPort(front), CablePort::RearPort(rear)],
+        ),
+    ] {
+        builder.devices.insert(
+            device,
+            Device {
+                name: name.into(),
+                tenant: tenant.map(Box::from),
+                ports: ports.into_iter().collect(),
+                ..Default::default()
+            },
+        );
+    }
+    for (id, device) in [(switch_port, switch), (router_port, router)] {
+        builder.interfaces.insert(
+            id,
+            Interface {
+                name: "ether1".into(),
+                device,
+                ..Default::default()
+            },
+        );
+    }
+    builder.front_ports.insert(
+        front,
+        FrontPort {
+            name: "1".into(),
+            device: panel,
+            rear_port: Some(rear),
+            cable: None,
+        },
+    );
+    builder.rear_ports.insert(
+        rear,
+        RearPort {
+            name: "1".into(),
+            device: panel,
+            front_port: None,
+            cable: None,
+        },
+    );
+    for (a, b) in [
+        (
+            CablePort::Interface(switch_port),
+            CablePort::FrontPort(front),
+        ),
+        (CablePort::RearPort(rear), CablePort::Interface(router_port)),
+    ] {
+        let cable = builder.cables.next_id();
+        builder.cables.insert(
+            cable,
+            Cable {
+                port_a: Box::new([a]),
+                port_b: Box::new([b]),
+            },
+        );
+    }
+    let schema = Schema::build(
+        TopologyHolder::from(builder),
+        EmptyMutation,
+        EmptySubscription,
+    )
+    .finish();
+    let response = schema
+        .execute(format!(
+            "{{ deviceById(id: {}) {{ interfaces {{ cablePaths {{ \
+                segments {{ far {{ __typename ... on FrontPortAccess {{ name }} ... on RearPortAccess {{ name }} }} }} \
+                farPort {{ ... on InterfaceAccess {{ id }} }} }} }} }} }}",
+            switch.0
+        ))
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    let data = response.data.into_json().expect("invalid response");
+    let path = &data["deviceById"]["interfaces"][0]["cablePaths"][0];
+    assert_eq!(path["segments"][0]["far"]["__typename"], "FrontPortAccess");
+    assert_eq!(path["segments"][1]["far"]["__typename"], "InterfaceAccess");
+    assert_eq!(path["farPort"]["id"], router_port.0);
+
+    // the router belongs to no tenant, the path to it is hidden from tenant users
+    let scope = TenantScope::Tenants(HashSet::from([Box::from("a")]));
+    let response = schema
+        .execute(
+            async_graphql::Request::new(format!(
+                "{{ deviceById(id: {}) {{ interfaces {{ cablePaths {{ farPort {{ __typename }} }} }} }} }}",
+                switch.0
+            ))
+            .data(scope),
+        )
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    let data = response.data.into_json().expect("invalid response");
+    assert_eq!(
+        data["deviceById"]["interfaces"][0]["cablePaths"],
+        serde_json::json!([])
+    );
+}
+
+#[tokio::test]
+async fn test_management_address_preferred() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: router
+    primary_ip: 1
+    ports:
+      - !Interface 1
+      - !Interface 2
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    ips: [1]
+  2:
+    name: ether5
+    device: 1
+    management: true
+    ips: [2]
+ip_addresses:
+  1:
+    ip: 10.0.0.1/24
+    interface: 1
+  2:
+    ip: 10.99.0.5/24
+    interface: 2
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid fixture");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    assert_eq!(device.primary_ip(), Some("10.0.0.1".parse().unwrap()));
+    assert_eq!(device.management_ip(), Some("10.99.0.5".parse().unwrap()));
+    assert_eq!(
+        device.management_prefixes().as_ref(),
+        &["10.99.0.0/24".parse().unwrap()]
+    );
+}
+
+#[tokio::test]
+async fn test_wan_gateway() {
+    let fixture = r#"
+devices:
+  1:
+    name: router
+    ports:
+      - !Interface 1
+      - !Interface 2
+      - !Interface 3
+  2:
+    name: modem
+    ports:
+      - !Interface 4
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    ips: [1]
+  2:
+    name: ether2
+    device: 1
+    ips: [2]
+  3:
+    name: ether3
+    device: 1
+  4:
+    name: ether1
+    device: 2
+    ips: [3]
+ip_addresses:
+  1:
+    ip: 198.51.100.2/29
+    interface: 1
+    prefix: 1
+  2:
+    ip: 198.51.100.3/29
+    interface: 2
+    prefix: 1
+  3:
+    ip: 198.51.100.5/29
+    interface: 4
+    prefix: 1
+  4:
+    ip: 198.51.100.6/29
+    prefix: 1
+ip_prefixes:
+  1:
+    prefix: 198.51.100.0/29
+    addresses: [1, 2, 3, 4]
+    children: []
+    ranges: []
+"#;
+    let wan_gateway = |fixture: &str| {
+        let topology =
+            parse_topology(fixture.as_bytes(), FixtureFormat::Yaml).expect("invalid fixture");
+        async move {
+            let device = TopologyHolder::offline(topology)
+                .devices_by_id(DeviceId(1))
+                .await
+                .expect("device not found");
+            device
+                .interfaces()
+                .iter()
+                .find(|interface| interface.name() == "ether1")
+                .expect("wan interface not found")
+                .wan_gateway()
         }
+    };
+    // the address documented without interface is the provider side
+    assert_eq!(
+        Some("198.51.100.6".parse().unwrap()),
+        wan_gateway(fixture).await
+    );
+    // otherwise the address of another device, never one of the router itself
+    let without_provider = fixture.replace("addresses: [1, 2, 3, 4]", "addresses: [1, 2, 3]");
+    assert_eq!(
+        Some("198.51.100.5".parse().unwrap()),
+        wan_gateway(&without_provider).await
+    );
+    let alone = fixture.replace("addresses: [1, 2, 3, 4]", "addresses: [1, 2]");
+    assert_eq!(None, wan_gateway(&alone).await);
+    // no gateway on the ports without address
+    let topology =
+        parse_topology(fixture.as_bytes(), FixtureFormat::Yaml).expect("invalid fixture");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    assert!(
+        device
+            .interfaces()
+            .iter()
+            .find(|interface| interface.name() == "ether3")
+            .expect("port not found")
+            .wan_gateway()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn test_vlan_matrix() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: switch
+    ports:
+      - !Interface 1
+      - !Interface 2
+      - !Interface 3
+  2:
+    name: ap
+    ports:
+      - !Interface 4
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    vlan: 10
+  2:
+    name: ether2
+    device: 1
+    tagged_vlans: [20, 10]
+  3:
+    name: ether3
+    device: 1
+  4:
+    name: wlan1
+    device: 2
+    vlan: 20
+vlans:
+  10:
+    name: users
+    vlan_id: 100
+    group: 1
+    terminations: []
+    vxlan: null
+    wlans: []
+  20:
+    name: guests
+    vlan_id: 50
+    group: 1
+    terminations: []
+    vxlan: null
+    wlans: []
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid fixture");
+    let matrix = Arc::new(topology).vlan_matrix(None, &TenantScope::All);
+    assert_eq!(
+        matrix
+            .vlans()
+            .iter()
+            .map(|vlan| vlan.vlan_id())
+            .collect::<Vec<_>>(),
+        vec![Some(50), Some(100)]
+    );
+    let rows = matrix
+        .rows()
+        .iter()
+        .map(|row| {
+            (
+                row.device().name().to_string(),
+                row.interface().name().to_string(),
+                row.memberships().to_vec(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![
+            (
+                "ap".to_string(),
+                "wlan1".to_string(),
+                vec![Some(VlanMembership::Untagged), None]
+            ),
+            (
+                "switch".to_string(),
+                "ether1".to_string(),
+                vec![None, Some(VlanMembership::Untagged)]
+            ),
+            (
+                "switch".to_string(),
+                "ether2".to_string(),
+                vec![Some(VlanMembership::Tagged), Some(VlanMembership::Tagged)]
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_snapshot() {
+    let mut topology = parse_topology(
+        br#"
+wlan_groups:
+  1:
+    controller: 1
+    aps: []
+    wlans: [1]
+wlans:
+  1:
+    ssid: Office
+    wlan_auth: !Wpa { key: office-psk }
+    wlan_group: 1
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    topology.fetched_at = UNIX_EPOCH + Duration::from_secs(1_792_000_000);
+    let path = std::env::temp_dir()
+        .join("provisioner-snapshot-test")
+        .join("default.json");
+    write_snapshot(&path, &topology)
+        .await
+        .expect("cannot write snapshot");
+    // the snapshot holds the wlan keys
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path)
+            .expect("snapshot not written")
+            .permissions()
+            .mode();
+        assert_eq!(0o600, mode & 0o777);
     }
+    let mut loaded = read_snapshot(&path)
+        .await
+        .expect("cannot read snapshot")
+        .expect("snapshot not found");
+    // the status page shows the time of the fetch, not of the load
+    assert_eq!(topology.fetched_at(), loaded.fetched_at());
+    // a topology fetched later with the same data is not written again
+    loaded.fetched_at = SystemTime::now();
+    assert!(loaded.same_data(&topology));
+}
+
+#[tokio::test]
+async fn test_site_hierarchy() {
+    let mut builder = TopologyBuilder::default();
+    let site = builder.sites.next_id();
+    builder.sites.insert(
+        site,
+        SiteData {
+            name: "Headquarters".into(),
+            slug: "hq".into(),
+        },
+    );
+    let other_site = builder.sites.next_id();
+    builder.sites.insert(
+        other_site,
+        SiteData {
+            name: "Branch".into(),
+            slug: "branch".into(),
+        },
+    );
+    let location = builder.locations.next_id();
+    builder.locations.insert(
+        location,
+        LocationData {
+            name: "Server room".into(),
+            site,
+            parent: None,
+        },
+    );
+    for (name, site, location) in [
+        ("core", Some(site), Some(location)),
+        ("branch-router", Some(other_site), None),
+        ("spare", None, None),
+    ] {
+        let id = builder.devices.next_id();
+        builder.devices.insert(
+            id,
+            Device {
+                name: name.into(),
+                site,
+                location,
+                ..Default::default()
+            },
+        );
+    }
+    let schema = Schema::build(
+        TopologyHolder::from(builder),
+        EmptyMutation,
+        EmptySubscription,
+    )
+    .finish();
+    let response = schema
+        .execute(format!(
+            "{{ allDevices(filter: {{site: {}}}) {{ name site {{ slug }} location {{ name site {{ name }} }} }} \
+                allSites {{ slug devices {{ name }} locations {{ name }} }} }}",
+            site.0
+        ))
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    let data = response.data.into_json().expect("invalid response");
+    let devices = data["allDevices"].as_array().expect("no devices");
+    assert_eq!(1, devices.len());
+    assert_eq!(devices[0]["name"], "core");
+    assert_eq!(devices[0]["site"]["slug"], "hq");
+    assert_eq!(devices[0]["location"]["name"], "Server room");
+    assert_eq!(devices[0]["location"]["site"]["name"], "Headquarters");
+    let hq = data["allSites"]
+        .as_array()
+        .expect("no sites")
+        .iter()
+        .find(|site| site["slug"] == "hq")
+        .expect("site not found");
+    assert_eq!(hq["devices"], serde_json::json!([{"name": "core"}]));
+    assert_eq!(
+        hq["locations"],
+        serde_json::json!([{"name": "Server room"}])
+    );
+}
+
+#[tokio::test]
+async fn test_wlan_groups_and_sites_scope() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: controller-a
+    tenant: a
+    site: 1
+  2:
+    name: controller-b
+    tenant: b
+    site: 2
+wlan_groups:
+  1:
+    controller: 1
+    aps: []
+    wlans: []
+  2:
+    controller: 2
+    aps: []
+    wlans: []
+sites:
+  1:
+    name: Site A
+    slug: site-a
+  2:
+    name: Site B
+    slug: site-b
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let schema = Schema::build(
+        TopologyHolder::offline(topology),
+        EmptyMutation,
+        EmptySubscription,
+    )
+    .data(TenantScope::Tenants(HashSet::from([Box::from("a")])))
+    .finish();
+    let response = schema
+        .execute("{ allWlanGroups { id } allSites { slug } }")
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    let data = response.data.into_json().expect("invalid response");
+    assert_eq!(data["allWlanGroups"], serde_json::json!([{"id": 1}]));
+    assert_eq!(data["allSites"], serde_json::json!([{"slug": "site-a"}]));
+}
+
+#[tokio::test]
+async fn test_next_free_address_scope() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: router
+    tenant: a
+    ports:
+      - !Interface 1
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    ips: [1, 2]
+ip_addresses:
+  1:
+    ip: 10.1.0.1/16
+    interface: 1
+    prefix: 1
+  2:
+    ip: 2001:db8::1/64
+    interface: 1
+    prefix: 2
+ip_prefixes:
+  1:
+    prefix: 10.1.0.0/16
+    addresses: [1]
+    children: []
+    ranges: []
+  2:
+    prefix: 2001:db8::/64
+    addresses: [2]
+    children: []
+    ranges: []
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let holder = TopologyHolder::offline(topology);
+    let execute = |tenant: &str, query: &str| {
+        let schema = Schema::build(holder.clone(), EmptyMutation, EmptySubscription)
+            .data(TenantScope::Tenants(HashSet::from([Box::from(tenant)])))
+            .extension(ErrorCodes)
+            .finish();
+        let query = query.to_string();
+        async move { schema.execute(query).await }
+    };
+    let code = |response: &async_graphql::Response| {
+        response.errors[0]
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.get("code"))
+            .cloned()
+    };
+
+    // the count is capped
+    let response = execute(
+        "a",
+        "{ nextFreeAddress(prefixId: 1, count: 100000) { display } }",
+    )
+    .await;
+    assert!(response.errors.is_empty());
+    let addresses = response.data.into_json().expect("invalid response");
+    assert_eq!(
+        Some(256),
+        addresses["nextFreeAddress"].as_array().map(Vec::len)
+    );
+    // the prefix of another tenant is not found
+    let response = execute("b", "{ nextFreeAddress(prefixId: 1) { display } }").await;
+    assert_eq!(Some(value!("NOT_FOUND")), code(&response));
+    let response = execute("b", "{ freeRanges(prefixId: 1) { display } }").await;
+    assert_eq!(value!({"freeRanges": []}), response.data);
+    // ipv6 prefixes have no free address candidates
+    let response = execute("a", "{ nextFreeAddress(prefixId: 2) { display } }").await;
+    assert_eq!(Some(value!("VALIDATION_FAILED")), code(&response));
+}
+
+#[tokio::test]
+async fn test_vxlan_peers() {
+    let mut builder = TopologyBuilder::default();
+    let vxlan = builder.vxlans.next_id();
+    let mut terminations = Vec::new();
+    for (index, status) in [
+        DeviceStatus::Active,
+        DeviceStatus::Active,
+        DeviceStatus::Decommissioning,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let device = builder.devices.next_id();
+        let interface = builder.interfaces.next_id();
+        let address = builder.ip_addresses.next_id();
+        builder.devices.insert(
+            device,
+            Device {
+                name: format!("router{index}").into(),
+                ports: [CablePort::Interface(interface)].into_iter().collect(),
+                primary_ip_v4: Some(address),
+                status,
+                ..Default::default()
+            },
+        );
+        builder.interfaces.insert(
+            interface,
+            Interface {
+                name: "lo".into(),
+                device,
+                ips: Box::new([address]),
+                ..Default::default()
+            },
+        );
+        builder.ip_addresses.insert(
+            address,
+            IpAddressData {
+                ip: format!("10.0.0.{}/32", index + 1)
+                    .parse()
+                    .expect("invalid ip"),
+                interface: Some(interface),
+                prefix: None,
+                vrf: None,
+            },
+        );
+        terminations.push(interface);
+    }
+    builder.vxlans.insert(
+        vxlan,
+        VxlanData {
+            name: "caps".into(),
+            vni: 100,
+            interface_terminations: terminations.into_boxed_slice(),
+            vlan_terminations: Box::default(),
+        },
+    );
+    let topology = Arc::new(builder.build());
+    let peer_ids = |device: u32| {
+        DeviceAccess::create(topology.clone(), DeviceId(device))
+            .vxlan_peers()
+            .iter()
+            .map(|peer| peer.id().0)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec![0, 1], peer_ids(2));
+    assert_eq!(vec![1, 2], peer_ids(0));
+    // the decommissioned device is not a vtep anymore
+    let vxlan = VxlanAccess::create(topology.clone(), vxlan);
+    assert_eq!(3, vxlan.members().len());
+    assert_eq!(2, vxlan.vteps().len());
+}
+
+#[tokio::test]
+async fn test_impact_analysis() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: switch
+    ports:
+      - !Interface 1
+  2:
+    name: router
+    ports:
+      - !Interface 2
+  3:
+    name: other
+    ports:
+      - !Interface 3
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    cable: 1
+  2:
+    name: ether1
+    device: 2
+    cable: 1
+  3:
+    name: ether1
+    device: 3
+cables:
+  1:
+    port_a:
+      - !Interface 1
+    port_b:
+      - !Interface 2
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let device = TopologyHolder::offline(topology)
+        .devices_by_id(DeviceId(1))
+        .await
+        .expect("device not found");
+    let impacted = device.impact_analysis(&TenantScope::All);
+    assert_eq!(1, impacted.len());
+    assert_eq!("router", impacted[0].device().name());
+    assert_eq!(&[ImpactReason::Cable], impacted[0].reasons());
+}
+
+#[test]
+fn test_wlan_cipher() {
+    assert_eq!(WlanCipher::Aes, WlanCipher::from_netbox(Some("aes")));
+    assert_eq!(WlanCipher::Tkip, WlanCipher::from_netbox(Some("tkip")));
+    assert_eq!(WlanCipher::Auto, WlanCipher::from_netbox(Some("auto")));
+    assert_eq!(WlanCipher::Auto, WlanCipher::from_netbox(None));
+}
+
+#[test]
+fn test_consistency_findings() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: router
+    tenant: a
+    ports:
+      - !Interface 1
+      - !Interface 2
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    ips: [1]
+  2:
+    name: ether2
+    device: 1
+    ips: [2, 3]
+ip_addresses:
+  1:
+    ip: 10.0.1.1/24
+    interface: 1
+    prefix: 1
+  2:
+    ip: 10.0.1.1/24
+    interface: 2
+    prefix: 1
+  3:
+    ip: 10.0.1.50/24
+    interface: 2
+    prefix: 1
+ip_prefixes:
+  1:
+    prefix: 10.0.1.0/24
+    addresses: [1, 2, 3]
+    children: []
+    ranges: [1, 2]
+  2:
+    prefix: 10.0.1.0/25
+    addresses: []
+    children: []
+    ranges: []
+ip_ranges:
+  1:
+    is_dhcp: true
+    net: 10.0.1.0/24
+    start: 10.0.1.10
+    end: 10.0.1.100
+    prefix: 1
+  2:
+    is_dhcp: true
+    net: 10.0.1.0/24
+    start: 10.0.1.150
+    end: 10.0.1.200
+    prefix: 1
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let topology = Arc::new(topology);
+    let findings = topology.check_consistency(&TenantScope::All);
+    let kinds = |findings: &[ConsistencyFinding]| {
+        findings
+            .iter()
+            .map(|finding| finding.kind())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        vec![
+            FindingKind::DuplicateAddress,
+            FindingKind::OverlappingPrefix,
+            FindingKind::DhcpRangeOverlap
+        ],
+        kinds(&findings)
+    );
+    assert_eq!(2, findings[0].addresses().len());
+    assert_eq!(&[1, 2], findings[1].prefix_ids());
+    assert_eq!(&[1], findings[2].range_ids());
+    assert_eq!(
+        Some("10.0.1.50".parse().unwrap()),
+        findings[2].addresses()[0].addr()
+    );
+
+    // the prefix without addresses is not visible to a tenant
+    let tenant = |name: &str| TenantScope::Tenants(HashSet::from([Box::from(name)]));
+    assert_eq!(
+        vec![FindingKind::DuplicateAddress, FindingKind::DhcpRangeOverlap],
+        kinds(&topology.check_consistency(&tenant("a")))
+    );
+    assert!(topology.check_consistency(&tenant("b")).is_empty());
+}
+
+#[test]
+fn test_topology_changes() {
+    let previous = parse_topology(
+        br#"
+devices:
+  1:
+    name: router
+    tenant: a
+    ports:
+      - !Interface 10
+  2:
+    name: switch
+    tenant: b
+    ports:
+      - !Interface 20
+interfaces:
+  10:
+    name: ether1
+    device: 1
+  20:
+    name: ether1
+    device: 2
+vlans:
+  1:
+    name: office
+    vlan_id: 10
+    group: 1
+    terminations: [20]
+    wlans: []
+ip_prefixes:
+  1:
+    prefix: 10.0.1.0/24
+    addresses: []
+    children: [2, 2, 3]
+    ranges: []
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let current = parse_topology(
+        br#"
+devices:
+  1:
+    name: router
+    tenant: a
+    serial: HEX0001
+    ports:
+      - !Interface 11
+  2:
+    name: switch
+    tenant: b
+    ports:
+      - !Interface 20
+interfaces:
+  11:
+    name: ether2
+    device: 1
+  20:
+    name: ether1
+    device: 2
+    enable_poe: true
+vlans:
+  1:
+    name: office
+    vlan_id: 20
+    group: 1
+    terminations: [20]
+    wlans: []
+  2:
+    name: voice
+    vlan_id: 30
+    group: 1
+    terminations: [11]
+    wlans: []
+ip_prefixes:
+  1:
+    prefix: 10.0.1.0/24
+    addresses: []
+    children: [2, 3, 3]
+    ranges: []
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let changes = current.changes_since(&previous, &TenantScope::All);
+    let summary = changes
+        .iter()
+        .map(|change| (change.object(), change.kind(), change.name()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            (ChangedObject::Device, ChangeKind::Modified, "router"),
+            (
+                ChangedObject::Interface,
+                ChangeKind::Removed,
+                "router ether1"
+            ),
+            (ChangedObject::Interface, ChangeKind::Added, "router ether2"),
+            (
+                ChangedObject::Interface,
+                ChangeKind::Modified,
+                "switch ether1"
+            ),
+            (ChangedObject::Vlan, ChangeKind::Modified, "office (20)"),
+            (ChangedObject::Vlan, ChangeKind::Added, "voice (30)"),
+            // lists with the same entries repeated differently are changed
+            (ChangedObject::Prefix, ChangeKind::Modified, "10.0.1.0/24"),
+        ],
+        summary
+    );
+    assert_eq!(
+        vec!["ports", "serial"],
+        changes[0]
+            .fields()
+            .iter()
+            .map(AsRef::as_ref)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(&[Box::<str>::from("enable_poe")], changes[3].fields());
+    assert!(
+        previous
+            .changes_since(&previous, &TenantScope::All)
+            .is_empty()
+    );
+
+    let scope = TenantScope::Tenants(HashSet::from([Box::from("a")]));
+    let visible = current
+        .changes_since(&previous, &scope)
+        .iter()
+        .map(|change| change.id())
+        .collect::<Vec<_>>();
+    // the vlan of the other tenant and the prefix without addresses are hidden
+    assert_eq!(vec![1, 10, 11, 2], visible);
+}
+
+#[tokio::test]
+async fn test_tagged_devices() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: core-b
+    tenant: a
+    tags: [core, monitor]
+  2:
+    name: core-a
+    tenant: b
+    tags: [core]
+  3:
+    name: edge
+    tenant: a
+    tags: [edge]
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let holder = TopologyHolder::offline(topology);
+    let names = |devices: Box<[DeviceAccess]>| {
+        devices
+            .iter()
+            .map(|device| device.name().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        vec!["core-a", "core-b"],
+        names(holder.tagged_devices("core", &TenantScope::All).await)
+    );
+    let scope = TenantScope::Tenants(HashSet::from([Box::from("a")]));
+    assert_eq!(
+        vec!["core-b"],
+        names(holder.tagged_devices("core", &scope).await)
+    );
+    assert!(holder.tagged_devices("unknown", &scope).await.is_empty());
+}
+
+#[test]
+fn test_search() {
+    let topology = parse_topology(
+        br#"
+devices:
+  1:
+    name: core-router
+    serial: HEX123
+    tenant: a
+    ports:
+      - !Interface 1
+  2:
+    name: router-lab
+    tenant: b
+    ports:
+      - !Interface 2
+interfaces:
+  1:
+    name: ether1
+    device: 1
+    ips: [1]
+  2:
+    name: ether1
+    device: 2
+ip_addresses:
+  1:
+    ip: 10.0.1.1/24
+    interface: 1
+    prefix: 1
+ip_prefixes:
+  1:
+    prefix: 10.0.1.0/24
+    addresses: [1]
+    children: []
+    ranges: []
+vlans:
+  10:
+    name: Routers
+    vlan_id: 100
+    group: 1
+    terminations: []
+    vxlan: null
+    wlans: []
+"#,
+        FixtureFormat::Yaml,
+    )
+    .expect("invalid topology");
+    let hits = |query: &str, scope: &TenantScope| {
+        topology
+            .search(query, scope, 10)
+            .iter()
+            .map(|hit| (hit.kind(), hit.id(), hit.detail().map(str::to_string)))
+            .collect::<Vec<_>>()
+    };
+    // the name starting with the query comes first
+    assert_eq!(
+        vec![
+            (SearchHitKind::Device, 2, None),
+            (SearchHitKind::Vlan, 10, Some("vlan 100".into())),
+            (SearchHitKind::Device, 1, None),
+        ],
+        hits("ROUTER", &TenantScope::All)
+    );
+    assert_eq!(
+        vec![(SearchHitKind::Device, 1, Some("HEX123".into()))],
+        hits("hex123", &TenantScope::All)
+    );
+    // the device with the address before the prefix containing it
+    assert_eq!(
+        vec![
+            (SearchHitKind::Device, 1, Some("10.0.1.1".into())),
+            (SearchHitKind::Prefix, 1, None),
+        ],
+        hits("10.0.1.1", &TenantScope::All)
+    );
+    assert_eq!(
+        vec![(SearchHitKind::Vlan, 10, Some("vlan 100".into()))],
+        hits("100", &TenantScope::All)
+    );
+    let scope = TenantScope::Tenants(HashSet::from([Box::from("b")]));
+    assert_eq!(
+        vec![
+            (SearchHitKind::Device, 2, None),
+            (SearchHitKind::Vlan, 10, Some("vlan 100".into())),
+        ],
+        hits("router", &scope)
+    );
+    assert!(hits("  ", &TenantScope::All).is_empty());
 }
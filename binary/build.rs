@@ -1,4 +1,4 @@
-use std::{env, fs, io::Write, time::SystemTime};
+use std::{env, fs, io::Write, path::Path, time::SystemTime};
 fn main() -> std::io::Result<()> {
     std::process::Command::new("trunk")
         .current_dir("../frontend/")
@@ -13,5 +13,49 @@ fn main() -> std::io::Result<()> {
 
     let mut fh = fs::File::create(&outfile).unwrap();
     write!(fh, "{}", epoch_time)?;
+
+    write_persisted_queries(&outdir)?;
+    Ok(())
+}
+
+/// collects all operations of the frontend as allow-list, identified by operation name
+fn write_persisted_queries(outdir: &str) -> std::io::Result<()> {
+    let query_dir = Path::new("../frontend/src/graphql/authenticated").canonicalize()?;
+    let mut entries = Vec::new();
+    for file in fs::read_dir(&query_dir)? {
+        let path = file?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("graphql")
+            || path.file_name().and_then(|n| n.to_str()) == Some("schema.graphql")
+        {
+            continue;
+        }
+        let content = fs::read_to_string(&path)?;
+        for line in content.lines() {
+            let Some(operation) = line
+                .strip_prefix("query ")
+                .or_else(|| line.strip_prefix("mutation "))
+            else {
+                continue;
+            };
+            let name: String = operation
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect();
+            if !name.is_empty() {
+                entries.push((name, path.clone()));
+            }
+        }
+    }
+    entries.sort();
+    let mut fh = fs::File::create(format!("{outdir}/persisted_queries.rs"))?;
+    writeln!(fh, "pub static PERSISTED_QUERIES: &[(&str, &str)] = &[")?;
+    for (name, path) in entries {
+        writeln!(
+            fh,
+            "    ({name:?}, include_str!({:?})),",
+            path.display().to_string()
+        )?;
+    }
+    writeln!(fh, "];")?;
     Ok(())
 }
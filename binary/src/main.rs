@@ -3,32 +3,42 @@ use actix_4_jwt_auth::{
     biscuit::{Validation, ValidationOptions},
 };
 use actix_web::{
-    App, HttpServer, get,
-    guard::Post,
+    App, HttpRequest, HttpResponse, HttpServer, get,
+    guard::{Any, Get, Post},
+    http::header::AUTHORIZATION,
     middleware::Logger,
-    web::{Data, resource},
+    post,
+    web::{Data, Payload, resource},
 };
 use actix_web_prometheus::PrometheusMetricsBuilder;
 use actix_web_static_files::{ResourceFiles, deps::static_files::Resource};
 use async_graphql::{Response, ServerError, futures_util::future::join_all};
-use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
 use env_logger::Env;
 use include_dir::{Dir, include_dir};
-use log::{error, info, trace};
+use log::{error, info, trace, warn};
 use mime_db::lookup;
 use prometheus::{HistogramVec, histogram_opts};
-use std::{collections::HashMap, ffi::OsStr};
+use std::{collections::HashMap, ffi::OsStr, path::PathBuf};
 use thiserror::Error;
 use tracing_actix_web::TracingLogger;
 
+mod persisted_queries;
+
 use backend::{
-    config::CONFIG,
-    context::UserInfo,
+    config::{CONFIG, ConfigError},
+    context::{AccessLevel, UserInfo},
+    device::{backoff::BACKOFF, facts::DeviceFacts, mirror::revert_persisted_mirrors},
     graphql::{
         AnonymousGraphqlSchema, AuthenticatedGraphqlSchema, create_anonymous_schema, create_schema,
+        errors::ErrorCode,
     },
+    metrics::{ProvisioningHealth, rules::alerting_rules},
+    monitor::{AvailabilityCollector, InterfaceCollector, watch_drift},
     netbox::NetboxError,
-    topology::TopologyHolder,
+    notify::{NOTIFIER, digest::DigestReporter},
+    policy::{AccessPolicy, TenantScope},
+    topology::{TopologyHolder, TopologyInstances, snapshot::SnapshotError},
 };
 
 const BUILD_TIME: u64 = include!(concat!(env!("OUT_DIR"), "/timestamp.txt"));
@@ -73,31 +83,16 @@ async fn graphql(
     trace!("Execute Authenticated: {user:#?}");
     let schema = &context.schema;
     let histogram = context.graphql_request_histogram.clone();
-    let request = request.into_inner();
-    let subject = user.as_ref().and_then(|user| {
-        user.payload
-            .registered
-            .subject
-            .as_ref()
-            .map(|subj| subj.as_str())
-    });
-    if !subject
-        .map(|sub| CONFIG.auth_users.iter().any(|id| *sub == **id))
-        .unwrap_or(false)
-    {
-        return Response::from_errors(vec![ServerError::new("User not allowed", None)]).into();
-    }
-    let found_user = if let Some(DecodedInfo { jwt: _jwt, payload }) = user {
-        match serde_json::from_value::<UserInfo>(payload.private.clone()) {
-            Ok(user) => Some(user),
-            Err(error) => {
-                error!("Cannot decode user info: {:#?}: {error}", payload.private);
-                None
-            }
-        }
-    } else {
-        None
+    let mut request = request.into_inner();
+    let Some((access_level, tenant_scope)) = user_access(user.as_ref()) else {
+        let mut error = ServerError::new("User not allowed", None);
+        ErrorCode::Forbidden.apply(&mut error);
+        return Response::from_errors(vec![error]).into();
     };
+    if let Err(error) = persisted_queries::resolve_persisted_query(&mut request) {
+        return Response::from_errors(vec![error]).into();
+    }
+    let found_user = user.and_then(user_info);
     let hd = found_user
         .as_ref()
         .and_then(|user| user.hd.as_ref().map(|hd| hd.as_str()));
@@ -110,6 +105,10 @@ async fn graphql(
                 .unwrap_or_default(),
         ])
         .start_timer();
+    let request = request
+        .data(access_level)
+        .data(tenant_scope)
+        .data(context.device_facts.clone());
     let request = if let Some(user) = found_user {
         request.data(user)
     } else {
@@ -120,6 +119,55 @@ async fn graphql(
     timer.stop_and_record();
     response.into()
 }
+/// subscriptions over websocket, authorized and restricted to the persisted queries the same way
+/// as the queries
+async fn graphql_ws(
+    context: Data<ApplicationContext>,
+    user: Option<DecodedInfo>,
+    request: HttpRequest,
+    payload: Payload,
+) -> actix_web::Result<HttpResponse> {
+    trace!("Subscribe Authenticated: {user:#?}");
+    let Some((access_level, tenant_scope)) = user_access(user.as_ref()) else {
+        return Ok(HttpResponse::Forbidden().body("User not allowed"));
+    };
+    let mut data = async_graphql::Data::default();
+    data.insert(access_level);
+    data.insert(tenant_scope);
+    data.insert(context.device_facts.clone());
+    if let Some(user) = user.and_then(user_info) {
+        data.insert(user);
+    }
+    GraphQLSubscription::new(persisted_queries::PersistedQueryExecutor(
+        context.schema.clone(),
+    ))
+    .with_data(data)
+    .start(&request, payload)
+}
+fn user_access(user: Option<&DecodedInfo>) -> Option<(AccessLevel, TenantScope)> {
+    let user = user?;
+    let subject = user
+        .payload
+        .registered
+        .subject
+        .as_ref()
+        .map(|subj| subj.as_str());
+    let settings = CONFIG.load();
+    let policy = AccessPolicy::new(&settings);
+    let claims = &user.payload.private;
+    policy
+        .access_level(subject, claims)
+        .map(|level| (level, policy.tenant_scope(subject, claims, level)))
+}
+fn user_info(DecodedInfo { jwt: _jwt, payload }: DecodedInfo) -> Option<UserInfo> {
+    match serde_json::from_value::<UserInfo>(payload.private.clone()) {
+        Ok(user) => Some(user),
+        Err(error) => {
+            error!("Cannot decode user info: {:#?}: {error}", payload.private);
+            None
+        }
+    }
+}
 async fn graphql_anonymous(
     context: Data<ApplicationContext>,
     request: GraphQLRequest,
@@ -141,11 +189,70 @@ async fn health() -> &'static str {
     "Ok"
 }
 
+/// sdl of the authenticated schema, available even if introspection is disabled
+#[get("/schema.graphql")]
+async fn schema_sdl(context: Data<ApplicationContext>) -> String {
+    context.schema.sdl()
+}
+
+/// prometheus alerting rules on the provisioning health, to be loaded via `rule_files`
+#[get("/alerting-rules.yaml")]
+async fn alerting_rules_file() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/yaml")
+        .body(alerting_rules())
+}
+
+/// reloads `config.yaml`, triggered by `SIGHUP` as well. The request needs the
+/// `server_reload_token` as bearer token.
+#[post("/reload")]
+async fn reload_config(context: Data<ApplicationContext>, request: HttpRequest) -> HttpResponse {
+    let authorization = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    if !CONFIG.load().reload_authorized(authorization) {
+        return HttpResponse::Forbidden().body("Reload not allowed");
+    }
+    match reload(&context.topology) {
+        Ok(()) => HttpResponse::Ok().body("Ok"),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// replaces the settings and updates the netbox instances and the connection backoff, the
+/// current settings stay active on error
+fn reload(topology: &TopologyInstances) -> Result<(), ConfigError> {
+    if let Err(e) = CONFIG.reload() {
+        warn!("Cannot reload config.yaml, keeping current settings: {e}");
+        return Err(e);
+    }
+    let settings = CONFIG.load();
+    topology.reconfigure(settings.netbox_instances());
+    BACKOFF.reconfigure(&settings);
+    info!("Reloaded config.yaml");
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn reload_on_hangup(topology: TopologyInstances) -> Result<(), std::io::Error> {
+    use actix_web::rt::signal::unix::{SignalKind, signal};
+    let mut hangup = signal(SignalKind::hangup())?;
+    while hangup.recv().await.is_some() {
+        // failures are already logged
+        let _ = reload(&topology);
+    }
+    Ok(())
+}
+
 #[derive(Clone)]
 struct ApplicationContext {
     graphql_request_histogram: HistogramVec,
     schema: AuthenticatedGraphqlSchema,
     anonymous_schema: AnonymousGraphqlSchema,
+    topology: TopologyInstances,
+    /// routerboard facts of the devices, cached across requests
+    device_facts: DeviceFacts,
 }
 
 #[derive(Error, Debug)]
@@ -160,15 +267,55 @@ enum BackendError {
     OidcValidationError(#[from] OIDCValidationError),
     #[error("Error from netbox {0}")]
     Netbox(#[from] NetboxError),
+    #[error("Error loading topology {0}")]
+    Snapshot(#[from] SnapshotError),
+}
+
+/// `--print-schema` prints the sdl of the authenticated schema and exits
+fn print_schema_requested() -> bool {
+    std::env::args_os()
+        .skip(1)
+        .any(|arg| arg == "--print-schema")
+}
+
+/// `--print-alerting-rules` prints the prometheus alerting rules and exits
+fn print_alerting_rules_requested() -> bool {
+    std::env::args_os()
+        .skip(1)
+        .any(|arg| arg == "--print-alerting-rules")
+}
+
+/// fixture file given by `--offline <file>`, the topology is not fetched from netbox then
+fn offline_fixture() -> Option<PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--offline" {
+            return args.next().map(PathBuf::from);
+        }
+    }
+    None
 }
 
 #[actix_web::main]
 async fn main() -> Result<(), BackendError> {
+    if print_schema_requested() {
+        print!(
+            "{}",
+            create_schema(Default::default(), Default::default()).sdl()
+        );
+        return Ok(());
+    }
+    if print_alerting_rules_requested() {
+        print!("{}", alerting_rules());
+        return Ok(());
+    }
     env_logger::init_from_env(Env::default().filter_or("LOG_LEVEL", "debug"));
 
-    let bind_addr = CONFIG.server_bind_address();
-    let api_port = CONFIG.server_port();
-    let mgmt_port = CONFIG.server_mgmt_port();
+    // changes of these settings need a restart
+    let settings = CONFIG.load();
+    let bind_addr = settings.server_bind_address();
+    let api_port = settings.server_port();
+    let mgmt_port = settings.server_mgmt_port();
 
     let mut labels = HashMap::new();
     labels.insert("server".to_string(), "api".to_string());
@@ -183,14 +330,52 @@ async fn main() -> Result<(), BackendError> {
 
     let registry = prometheus.registry.clone();
     registry.register(Box::new(graphql_request_histogram.clone()))?;
-    let topology = TopologyHolder::default();
-    // ensure initial fetch
-    topology.fetch().await?;
+    let topology = if let Some(fixture) = offline_fixture() {
+        info!("Offline mode, topology from {}", fixture.display());
+        TopologyInstances::offline(TopologyHolder::from_fixture(&fixture).await?)
+    } else {
+        let topology = TopologyInstances::new(
+            settings.netbox_instances(),
+            settings.topology_snapshot_dir(),
+        );
+        // ensure initial fetch, a persisted snapshot is used if netbox is not reachable
+        topology.initialize().await?;
+        topology
+    };
+    ProvisioningHealth::new(topology.clone(), &NOTIFIER)?.register(&registry)?;
+    // mirrors left on the switches by the previous run
+    revert_persisted_mirrors(&topology).await;
+    if let Some(interval) = settings.monitor_interval() {
+        let collector = InterfaceCollector::new()?;
+        collector.register(&registry)?;
+        actix_web::rt::spawn(collector.run(topology.clone(), interval));
+    }
+    if let Some(interval) = settings.availability_interval() {
+        let collector = AvailabilityCollector::new()?;
+        collector.register(&registry)?;
+        actix_web::rt::spawn(collector.run(topology.clone(), interval));
+    }
+    if let Some(interval) = settings.drift_check_interval() {
+        actix_web::rt::spawn(watch_drift(topology.clone(), interval));
+    }
+    if let Some(digest) = &settings.digest {
+        actix_web::rt::spawn(DigestReporter::new(digest.clone()).run(topology.clone(), &NOTIFIER));
+    }
+
+    #[cfg(unix)]
+    {
+        let topology = topology.clone();
+        actix_web::rt::spawn(async move {
+            if let Err(e) = reload_on_hangup(topology).await {
+                error!("Cannot listen for SIGHUP: {e}");
+            }
+        });
+    }
 
-    let schema = create_schema(topology.clone());
-    let anonymous_schema = create_anonymous_schema();
+    let schema = create_schema(topology.clone(), settings.graphql_limits());
+    let anonymous_schema = create_anonymous_schema(topology.clone());
 
-    let issuer = CONFIG.auth_issuer().to_string();
+    let issuer = settings.auth_issuer().to_string();
     //info!("Issuer: {issuer}");
     let oidc = Oidc::new(OidcConfig::Issuer(issuer.clone().into())).await?;
 
@@ -205,7 +390,10 @@ async fn main() -> Result<(), BackendError> {
         graphql_request_histogram,
         schema,
         anonymous_schema,
+        topology,
+        device_facts: DeviceFacts::new(settings.device_facts_ttl()),
     });
+    let mgmt_data = data.clone();
     let main_server = HttpServer::new(move || {
         let resources = generate();
 
@@ -221,9 +409,16 @@ async fn main() -> Result<(), BackendError> {
                     .wrap(biscuit_validator.clone())
                     .to(graphql),
             )
+            .service(
+                resource("/graphql_ws")
+                    .guard(Get())
+                    .wrap(biscuit_validator.clone())
+                    .to(graphql_ws),
+            )
+            // queries by get can be cached by proxies and browsers
             .service(
                 resource("/graphql_anonymous")
-                    .guard(Post())
+                    .guard(Any(Get()).or(Post()))
                     .to(graphql_anonymous),
             )
             // workaround for proxy troubles
@@ -235,7 +430,7 @@ async fn main() -> Result<(), BackendError> {
             )
             .service(
                 resource("/graphql_anonymous/")
-                    .guard(Post())
+                    .guard(Any(Get()).or(Post()))
                     .to(graphql_anonymous),
             )
             .service(ResourceFiles::new("/", resources).resolve_not_found_to_root())
@@ -251,10 +446,18 @@ async fn main() -> Result<(), BackendError> {
         .endpoint("/metrics")
         .build()
         .unwrap();
-    let mgmt_server = HttpServer::new(move || App::new().wrap(prometheus.clone()).service(health))
-        .bind((bind_addr, mgmt_port))?
-        .workers(2)
-        .run();
+    let mgmt_server = HttpServer::new(move || {
+        App::new()
+            .wrap(prometheus.clone())
+            .app_data(mgmt_data.clone())
+            .service(health)
+            .service(schema_sdl)
+            .service(alerting_rules_file)
+            .service(reload_config)
+    })
+    .bind((bind_addr, mgmt_port))?
+    .workers(2)
+    .run();
     if let Some(e) = join_all(vec![main_server, mgmt_server])
         .await
         .into_iter()
@@ -0,0 +1,64 @@
+use async_graphql::{
+    Data, Executor, Request, Response, ServerError, Value,
+    futures_util::stream::{self, BoxStream, StreamExt},
+};
+use backend::{config::CONFIG, graphql::errors::ErrorCode};
+use std::sync::Arc;
+
+include!(concat!(env!("OUT_DIR"), "/persisted_queries.rs"));
+
+/// Replaces the query of the request by the allow-listed document referenced in
+/// `extensions.persistedQuery.id`.
+///
+/// Requests without id are rejected if only persisted queries are allowed.
+pub fn resolve_persisted_query(request: &mut Request) -> Result<(), ServerError> {
+    let id = match request.extensions.get("persistedQuery") {
+        Some(Value::Object(persisted_query)) => match persisted_query.get("id") {
+            Some(Value::String(id)) => Some(id.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(id) = id {
+        let Some((_, query)) = PERSISTED_QUERIES.iter().find(|(name, _)| *name == id) else {
+            let mut error = ServerError::new(format!("Persisted query {id} not found"), None);
+            ErrorCode::PersistedQueryNotFound.apply(&mut error);
+            return Err(error);
+        };
+        request.query = query.to_string();
+        if request.operation_name.is_none() {
+            request.operation_name = Some(id);
+        }
+        Ok(())
+    } else if CONFIG.load().graphql_persisted_queries_only() {
+        let mut error = ServerError::new("Only persisted queries are allowed", None);
+        ErrorCode::Forbidden.apply(&mut error);
+        Err(error)
+    } else {
+        Ok(())
+    }
+}
+
+/// Executes the operations of a websocket against the persisted queries like the requests
+/// over http, the graphql-ws protocol runs queries and mutations as well.
+#[derive(Clone)]
+pub struct PersistedQueryExecutor<E>(pub E);
+
+impl<E: Executor> Executor for PersistedQueryExecutor<E> {
+    async fn execute(&self, mut request: Request) -> Response {
+        match resolve_persisted_query(&mut request) {
+            Ok(()) => self.0.execute(request).await,
+            Err(error) => Response::from_errors(vec![error]),
+        }
+    }
+    fn execute_stream(
+        &self,
+        mut request: Request,
+        session_data: Option<Arc<Data>>,
+    ) -> BoxStream<'static, Response> {
+        match resolve_persisted_query(&mut request) {
+            Ok(()) => self.0.execute_stream(request, session_data),
+            Err(error) => stream::once(async move { Response::from_errors(vec![error]) }).boxed(),
+        }
+    }
+}
@@ -10,12 +10,12 @@ fn main() -> Result<()> {
 }
 
 fn write_graphql_schema() -> Result<()> {
-    let schema = create_schema(Default::default());
+    let schema = create_schema(Default::default(), Default::default());
     fs::write("src/graphql/authenticated/schema.graphql", schema.sdl())?;
     Ok(())
 }
 fn write_anonymous_graphql_schema() -> Result<()> {
-    let schema = create_anonymous_schema();
+    let schema = create_anonymous_schema(Default::default());
     fs::write("target/anonymous_schema.graphql", schema.sdl())?;
     Ok(())
 }
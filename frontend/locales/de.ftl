@@ -0,0 +1,180 @@
+# German texts of the frontend
+app-login = Anmelden
+app-logging-in = Anmeldung läuft
+app-not-found = Nicht gefunden
+
+nav-devices = Geräte
+nav-onboarding = Inbetriebnahme
+nav-wireless = WLAN
+nav-vlans = VLANs
+nav-runs = Durchläufe
+
+settings-dark-mode = Dunkles Design
+settings-high-contrast = Hoher Kontrast
+
+status-operational = In Betrieb
+status-degraded = Beeinträchtigt
+status-maintenance = Wartung
+status-unknown = Unbekannt
+status-maintenance-period = , seit { $since }, endet { $ends }
+status-devices-up = Geräte erreichbar
+status-devices-down = Geräte ausgefallen
+status-unreachable = Unerreichbar
+status-drifted = Abweichend
+status-ongoing-maintenance = Laufende Wartung
+status-last-sync = Letzter Abgleich der Topologie: { $age }
+status-never = nie
+status-failing-instances =
+    { $count ->
+        [one] , eine Netbox-Instanz fehlerhaft
+       *[other] , { $count } Netbox-Instanzen fehlerhaft
+    }
+
+time-now = jetzt
+time-in-minutes = in { $count } min
+time-in-hours = in { $count } h
+time-in-days =
+    { $count ->
+        [one] in einem Tag
+       *[other] in { $count } Tagen
+    }
+time-just-now = gerade eben
+time-minutes-ago = vor { $count } min
+time-hours-ago = vor { $count } h
+time-days-ago =
+    { $count ->
+        [one] vor einem Tag
+       *[other] vor { $count } Tagen
+    }
+
+runs-all = Alle Durchläufe
+runs-succeeded = Erfolgreich
+runs-failed = Fehlgeschlagen
+runs-none = Keine Provisionierungsläufe
+
+run-device = Gerät
+run-status = Status
+run-started = Gestartet
+run-user = Benutzer
+run-applied-steps = Angewendete Schritte
+run-error = Fehler
+run-not-found = Der Durchlauf ist nicht mehr im Verlauf
+run-show-pending = Nicht angewendete Schritte anzeigen
+run-hide-pending = Nicht angewendete Schritte ausblenden
+run-title = Durchlauf #{ $id }
+run-instance = Instanz
+run-duration = Dauer
+run-seconds = { $seconds } s
+run-no-steps = keine
+
+devices-all-sites = Alle Standorte
+devices-ping-failed = Geräte können nicht angepingt werden
+devices-list = Geräte
+devices-sort-name = Nach Name sortieren
+devices-sort-address = Nach Adresse sortieren
+
+device-generate-failed = Konfiguration kann nicht erzeugt werden
+device-generate-failed-of = Konfiguration von { $device } kann nicht erzeugt werden
+device-generated = Konfiguration erzeugt
+device-update-ready = Aktualisierung für { $device } ist bereit
+device-apply-failed-after =
+    { $steps ->
+        [one] Anwenden nach einem Schritt fehlgeschlagen
+       *[other] Anwenden nach { $steps } Schritten fehlgeschlagen
+    }
+device-plan-applied = Plan angewendet
+device-steps-applied =
+    { $steps ->
+        [one] Ein Schritt angewendet
+       *[other] { $steps } Schritte angewendet
+    }
+device-apply-failed = Plan kann nicht angewendet werden
+device-name = Name
+device-current-name = Aktueller Name
+device-update = Aktualisierung
+device-runs = Provisionierungsläufe
+device-generated-config = Erzeugte Konfiguration
+device-change-target = Ziel ändern
+device-hide-target = Zielauswahl ausblenden
+device-apply = Anwenden
+device-update-of = Konfigurationsänderung von { $device }
+
+config-regenerate = Neu erzeugen
+config-device-not-found = Gerät nicht gefunden
+config-device-unreachable = Auf { $device } kann nicht zugegriffen werden, es wurde keine Konfiguration erzeugt
+config-up-to-date = Das Gerät entspricht bereits der erzeugten Konfiguration
+config-title = Konfiguration von { $device }
+config-copy = Kopieren
+config-download = Herunterladen
+config-back = Zurück zum Gerät
+config-copied = Kopiert
+config-copied-of = Konfiguration von { $device } kopiert
+config-copy-failed = Kopieren in die Zwischenablage nicht möglich
+
+wlan-auth-wpa = WPA
+wlan-auth-open = Offen
+wlan-controller = Controller
+wlan-management-vlan = Management-VLAN
+wlan-ssids = SSIDs
+wlan-access-points = Access Points
+
+vlans-none = Kein Port führt ein VLAN
+vlans-device = Gerät
+vlans-port = Port
+vlans-caption = Von den Ports geführte VLANs
+vlans-untagged = ungetaggt
+vlans-tagged = getaggt
+
+onboarding-title = Gerät in Betrieb nehmen
+onboarding-step-device = Gerät
+onboarding-step-target = Ziel
+onboarding-step-hardware = Hardware
+onboarding-step-preview = Vorschau
+onboarding-step-apply = Anwenden
+onboarding-back = Zurück
+onboarding-next = Weiter
+onboarding-no-address = keine
+onboarding-management-address = Management-Adresse
+onboarding-serial = Seriennummer
+onboarding-target-help = Ein Gerät, das noch nicht über seine Management-Adresse erreichbar ist, z. B. nach einem Reset, wird über eine andere Adresse und mit anderen Zugangsdaten erreicht. Leer lassen, um die Management-Adresse und die konfigurierten Zugangsdaten zu verwenden.
+onboarding-unreachable = Das Gerät ist mit dem gewählten Ziel und den Zugangsdaten nicht erreichbar
+onboarding-serial-mismatch = Die Seriennummer in netbox ist { $serial }
+onboarding-unknown-model = Für das Modell sind keine Hardwaredaten bekannt, die Ports können nicht geprüft werden
+onboarding-model = Modell
+onboarding-firmware = Firmware
+onboarding-routeros = RouterOS
+onboarding-management-cuts = Der Management-Pfad wird unterbrochen
+onboarding-warnings = Warnungen
+onboarding-device = Gerät
+onboarding-bootstrap-help = Das Skript in das Terminal des zurückgesetzten Geräts einfügen oder als Datei importieren. Den Status von { $device } in netbox ändern, um weitere Änderungen anzuwenden.
+onboarding-failed = Fehlgeschlagen: { $error }
+onboarding-converged = Konvergiert
+onboarding-not-converged = Nicht konvergiert: { $error }
+onboarding-state = Zustand
+onboarding-apply-cut = Anwenden und Management-Pfad unterbrechen
+onboarding-confirm-cut = Ich akzeptiere, dass der Management-Pfad unterbrochen wird
+onboarding-planned-device = { $device } (geplant)
+onboarding-steps = Schritte der Inbetriebnahme
+
+search-placeholder = Geräte, VLANs und Präfixe suchen
+search-nothing-found = Nichts gefunden
+search-kind-device = Gerät
+search-kind-vlan = VLAN
+search-kind-prefix = Präfix
+search-title = Suche
+
+target-address = IP-Adresse
+target-address-placeholder = Alternative IP-Adresse
+target-credentials = Hinterlegte Zugangsdaten
+target-username = Ad-hoc-Benutzername
+target-password = Ad-hoc-Passwort
+
+error-javascript = Javascript-Fehler
+error-serialization = Serialisierungsfehler
+error-server = Fehler vom Server
+error-at = bei
+error-call = Server nicht erreichbar
+error-header = Header-Fehler
+error-missing-data = Fehlende Daten
+error-no-data = Keine Daten vom Server erhalten
+error-login = Anmeldung fehlgeschlagen
@@ -0,0 +1,180 @@
+# English texts of the frontend
+app-login = Login
+app-logging-in = Logging in
+app-not-found = Not Found
+
+nav-devices = Devices
+nav-onboarding = Onboarding
+nav-wireless = Wireless
+nav-vlans = VLANs
+nav-runs = Runs
+
+settings-dark-mode = Dark mode
+settings-high-contrast = High contrast
+
+status-operational = Operational
+status-degraded = Degraded
+status-maintenance = Maintenance
+status-unknown = Unknown
+status-maintenance-period = , since { $since }, ends { $ends }
+status-devices-up = Devices up
+status-devices-down = Devices down
+status-unreachable = Unreachable
+status-drifted = Drifted
+status-ongoing-maintenance = Ongoing maintenance
+status-last-sync = Last topology sync: { $age }
+status-never = never
+status-failing-instances =
+    { $count ->
+        [one] , one netbox instance failing
+       *[other] , { $count } netbox instances failing
+    }
+
+time-now = now
+time-in-minutes = in { $count } min
+time-in-hours = in { $count } h
+time-in-days =
+    { $count ->
+        [one] in one day
+       *[other] in { $count } days
+    }
+time-just-now = just now
+time-minutes-ago = { $count } min ago
+time-hours-ago = { $count } h ago
+time-days-ago =
+    { $count ->
+        [one] one day ago
+       *[other] { $count } days ago
+    }
+
+runs-all = All runs
+runs-succeeded = Succeeded
+runs-failed = Failed
+runs-none = No provisioning runs
+
+run-device = Device
+run-status = Status
+run-started = Started
+run-user = User
+run-applied-steps = Applied steps
+run-error = Error
+run-not-found = The run is not in the history anymore
+run-show-pending = Show steps not applied
+run-hide-pending = Hide steps not applied
+run-title = Run #{ $id }
+run-instance = Instance
+run-duration = Duration
+run-seconds = { $seconds }s
+run-no-steps = none
+
+devices-all-sites = All sites
+devices-ping-failed = Cannot ping devices
+devices-list = Devices
+devices-sort-name = Sort by name
+devices-sort-address = Sort by address
+
+device-generate-failed = Cannot generate configuration
+device-generate-failed-of = Cannot generate configuration of { $device }
+device-generated = Configuration generated
+device-update-ready = Update for { $device } is ready
+device-apply-failed-after =
+    { $steps ->
+        [one] Apply failed after one step
+       *[other] Apply failed after { $steps } steps
+    }
+device-plan-applied = Plan applied
+device-steps-applied =
+    { $steps ->
+        [one] One step applied
+       *[other] { $steps } steps applied
+    }
+device-apply-failed = Cannot apply plan
+device-name = Name
+device-current-name = Current Name
+device-update = Update
+device-runs = Provisioning runs
+device-generated-config = Generated configuration
+device-change-target = Change Target
+device-hide-target = Hide Target Selection
+device-apply = Apply
+device-update-of = Configuration update of { $device }
+
+config-regenerate = Regenerate
+config-device-not-found = Device not found
+config-device-unreachable = { $device } cannot be accessed, no configuration was generated
+config-up-to-date = The device already matches the generated configuration
+config-title = Configuration of { $device }
+config-copy = Copy
+config-download = Download
+config-back = Back to device
+config-copied = Copied
+config-copied-of = Configuration of { $device } copied
+config-copy-failed = Cannot copy to clipboard
+
+wlan-auth-wpa = WPA
+wlan-auth-open = Open
+wlan-controller = Controller
+wlan-management-vlan = Management VLAN
+wlan-ssids = SSIDs
+wlan-access-points = Access Points
+
+vlans-none = No ports carry a vlan
+vlans-device = Device
+vlans-port = Port
+vlans-caption = VLANs carried by the ports
+vlans-untagged = untagged
+vlans-tagged = tagged
+
+onboarding-title = Onboard device
+onboarding-step-device = Device
+onboarding-step-target = Target
+onboarding-step-hardware = Hardware
+onboarding-step-preview = Preview
+onboarding-step-apply = Apply
+onboarding-back = Back
+onboarding-next = Next
+onboarding-no-address = none
+onboarding-management-address = Management address
+onboarding-serial = Serial
+onboarding-target-help = A device not yet reachable on its management address, e.g. after a reset, is reached on another address and with other credentials. Leave empty to use the management address and the configured credentials.
+onboarding-unreachable = The device cannot be reached with the selected target and credentials
+onboarding-serial-mismatch = The serial in netbox is { $serial }
+onboarding-unknown-model = No hardware facts are known for the model, the ports cannot be validated
+onboarding-model = Model
+onboarding-firmware = Firmware
+onboarding-routeros = RouterOS
+onboarding-management-cuts = Management path is cut
+onboarding-warnings = Warnings
+onboarding-device = Device
+onboarding-bootstrap-help = Paste the script into the terminal of the reset device or import it as file. Change the status of { $device } in netbox to apply further changes.
+onboarding-failed = Failed: { $error }
+onboarding-converged = Converged
+onboarding-not-converged = Not converged: { $error }
+onboarding-state = State
+onboarding-apply-cut = Apply and cut management path
+onboarding-confirm-cut = I accept that the management path is cut
+onboarding-planned-device = { $device } (planned)
+onboarding-steps = Steps of the onboarding
+
+search-placeholder = Search devices, VLANs and prefixes
+search-nothing-found = Nothing found
+search-kind-device = Device
+search-kind-vlan = VLAN
+search-kind-prefix = Prefix
+search-title = Search
+
+target-address = IP Address
+target-address-placeholder = Alternate IP Address
+target-credentials = Defined Credentials
+target-username = Adhoc Username
+target-password = Adhoc Password
+
+error-javascript = Javascript Error
+error-serialization = Serialization Error
+error-server = Error from Server
+error-at = at
+error-call = Cannot call Server
+error-header = Header Error
+error-missing-data = Missing Data
+error-no-data = No data received from server
+error-login = Login Failed
@@ -0,0 +1,279 @@
+//! OpenID Connect login by the authorization code flow with PKCE.
+//!
+//! The browser is redirected to the authorization endpoint of the issuer configured in the
+//! backend. After the login the issuer redirects back with a code, which is exchanged against
+//! the tokens at the token endpoint. The id token is sent to the backend as bearer token.
+//!
+//! The tokens are kept in the session storage of the tab, a reload continues the session,
+//! renewed by the refresh token if the id token expired meanwhile. They are neither shared with
+//! other tabs nor kept after the browser is closed.
+use crate::error::FrontendError;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use gloo::storage::{SessionStorage, Storage};
+use log::warn;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{cell::RefCell, time::Duration};
+use wasm_bindgen::JsValue;
+use yew::platform::time::sleep;
+
+/// key of the pending login in the session storage
+const LOGIN_KEY: &str = "oidc-login";
+/// key of the tokens in the session storage, to keep the session on a reload
+const TOKEN_KEY: &str = "oidc-token";
+const SCOPE: &str = "openid profile email";
+
+/// Endpoints of the issuer, as given by the anonymous settings query
+#[derive(Debug, Clone, PartialEq)]
+pub struct OidcSettings {
+    pub client_id: String,
+    pub auth_url: String,
+    pub token_url: String,
+}
+
+/// Login started by the redirect to the issuer
+#[derive(Serialize, Deserialize)]
+struct PendingLogin {
+    state: String,
+    verifier: String,
+    /// page shown before the login with its query and fragment, restored afterwards
+    path: String,
+}
+
+/// Tokens of the session, the id token authenticates the graphql calls
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tokens {
+    pub id_token: String,
+    /// renews the id token without an interactive login, if the issuer grants one
+    pub refresh_token: Option<String>,
+}
+
+/// Refresh of the tokens in this tab
+#[derive(Default)]
+struct RefreshState {
+    running: bool,
+    /// outcome of the last refresh, taken by the refreshes waiting for it
+    outcome: Option<Result<Tokens, Box<str>>>,
+}
+
+thread_local! {
+    static REFRESH: RefCell<RefreshState> = RefCell::default();
+}
+
+/// a waiting refresh checks in this interval whether the running one has finished
+const REFRESH_WAIT: Duration = Duration::from_millis(50);
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+    refresh_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// redirects the browser to the login page of the issuer
+pub fn start_login(settings: &OidcSettings) -> Result<(), FrontendError> {
+    let login = PendingLogin {
+        state: random_string()?,
+        verifier: random_string()?,
+        path: current_path()?,
+    };
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(login.verifier.as_bytes()));
+    let url = Url::parse_with_params(
+        &settings.auth_url,
+        [
+            ("response_type", "code"),
+            ("client_id", settings.client_id.as_str()),
+            ("redirect_uri", redirect_uri().as_str()),
+            ("scope", SCOPE),
+            ("state", login.state.as_str()),
+            ("code_challenge", challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|e| FrontendError::Login(format!("Invalid authorization url: {e}").into()))?;
+    SessionStorage::set(LOGIN_KEY, &login)
+        .map_err(|e| FrontendError::Login(format!("Cannot store login state: {e}").into()))?;
+    // the tokens of the ended session cannot be renewed anymore
+    clear_tokens();
+    window()?.location().set_href(url.as_str())?;
+    Ok(())
+}
+
+/// finishes a login when the issuer redirected back to the frontend, `None` if the current
+/// location is not the answer of the issuer
+pub async fn complete_login(settings: &OidcSettings) -> Result<Option<Tokens>, FrontendError> {
+    let location = Url::parse(&window()?.location().href()?)
+        .map_err(|e| FrontendError::Login(format!("Invalid location: {e}").into()))?;
+    let parameter = |name: &str| {
+        location
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    let error = parameter("error");
+    let (code, state) = (parameter("code"), parameter("state"));
+    if error.is_none() && code.is_none() {
+        return Ok(None);
+    }
+    let login = SessionStorage::get::<PendingLogin>(LOGIN_KEY).ok();
+    SessionStorage::delete(LOGIN_KEY);
+    restore_location(login.as_ref().map_or("/", |login| login.path.as_str()))?;
+    if let Some(error) = error {
+        let description = parameter("error_description").unwrap_or_default();
+        return Err(FrontendError::Login(
+            format!("{error} {description}").into(),
+        ));
+    }
+    let (Some(code), Some(login)) = (code, login) else {
+        return Err(FrontendError::Login("No login was started".into()));
+    };
+    if state.as_ref() != Some(&login.state) {
+        return Err(FrontendError::Login(
+            "State of the login does not match".into(),
+        ));
+    }
+    token_request(
+        settings,
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", redirect_uri().as_str()),
+            ("client_id", settings.client_id.as_str()),
+            ("code_verifier", login.verifier.as_str()),
+        ],
+        None,
+    )
+    .await
+    .map(Some)
+}
+
+/// new tokens by the refresh token, without leaving the page
+pub async fn refresh(
+    settings: &OidcSettings,
+    refresh_token: &str,
+) -> Result<Tokens, FrontendError> {
+    // the issuer may accept a refresh token only once, a refresh requested while another one
+    // is running takes its outcome
+    if REFRESH.with_borrow(|state| state.running) {
+        while REFRESH.with_borrow(|state| state.running) {
+            sleep(REFRESH_WAIT).await;
+        }
+        return REFRESH
+            .with_borrow(|state| state.outcome.clone())
+            .unwrap_or_else(|| Err("Refresh aborted".into()))
+            .map_err(FrontendError::Login);
+    }
+    REFRESH.with_borrow_mut(|state| state.running = true);
+    let result = token_request(
+        settings,
+        &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", settings.client_id.as_str()),
+        ],
+        Some(refresh_token),
+    )
+    .await;
+    REFRESH.with_borrow_mut(|state| {
+        state.running = false;
+        state.outcome = Some(match &result {
+            Ok(tokens) => Ok(tokens.clone()),
+            Err(FrontendError::Login(message)) => Err(message.clone()),
+            Err(error) => Err(error.to_string().into()),
+        });
+    });
+    result
+}
+
+/// calls the token endpoint and stores the received tokens, the issuer may keep the refresh
+/// token and only send a new id token
+async fn token_request(
+    settings: &OidcSettings,
+    form: &[(&str, &str)],
+    refresh_token: Option<&str>,
+) -> Result<Tokens, FrontendError> {
+    let response = reqwest::Client::new()
+        .post(&settings.token_url)
+        .form(form)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        let message = match response.json::<TokenErrorResponse>().await {
+            Ok(TokenErrorResponse {
+                error,
+                error_description,
+            }) => format!("{error} {}", error_description.unwrap_or_default()),
+            Err(e) => e.to_string(),
+        };
+        return Err(FrontendError::Login(message.into()));
+    }
+    let response = response.json::<TokenResponse>().await?;
+    let tokens = Tokens {
+        id_token: response
+            .id_token
+            .ok_or_else(|| FrontendError::Login("No id token received".into()))?,
+        refresh_token: response
+            .refresh_token
+            .or_else(|| refresh_token.map(String::from)),
+    };
+    store_tokens(&tokens);
+    Ok(tokens)
+}
+
+/// tokens of an earlier login in this browser tab
+pub fn stored_tokens() -> Option<Tokens> {
+    SessionStorage::get(TOKEN_KEY).ok()
+}
+
+fn store_tokens(tokens: &Tokens) {
+    if let Err(e) = SessionStorage::set(TOKEN_KEY, tokens) {
+        warn!("Cannot store tokens: {e}");
+    }
+}
+
+fn clear_tokens() {
+    SessionStorage::delete(TOKEN_KEY);
+}
+
+/// the frontend root, has to be registered as redirect uri of the client at the issuer
+fn redirect_uri() -> String {
+    format!("{}/", crate::graphql::host())
+}
+
+/// path of the shown page with query and fragment, a deep link into the app
+fn current_path() -> Result<String, FrontendError> {
+    let location = window()?.location();
+    Ok(format!(
+        "{}{}{}",
+        location.pathname()?,
+        location.search()?,
+        location.hash()?
+    ))
+}
+
+/// replaces the answer of the issuer in the address bar by the page shown before the login
+fn restore_location(path: &str) -> Result<(), FrontendError> {
+    window()?
+        .history()?
+        .replace_state_with_url(&JsValue::NULL, "", Some(path))?;
+    Ok(())
+}
+
+/// 32 random bytes, url safe encoded
+fn random_string() -> Result<String, FrontendError> {
+    let mut bytes = [0u8; 32];
+    window()?
+        .crypto()?
+        .get_random_values_with_u8_array(&mut bytes)?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn window() -> Result<web_sys::Window, FrontendError> {
+    web_sys::window().ok_or_else(|| FrontendError::Login("No browser window".into()))
+}
@@ -1,6 +1,7 @@
 use crate::error::FrontendError;
 use crate::graphql::authenticated::{AdjustTargetListCredentials, adjust_target_list_credentials};
 use crate::graphql::query_authenticated_response;
+use crate::i18n::tr;
 use log::{error, info};
 use patternfly_yew::prelude::{
     Form, FormGroup, FormGroupValidated, InputState, SimpleSelect, TextInput, ValidationContext,
@@ -233,22 +234,22 @@ impl Component for AdjustTarget {
         html! {
             <Form>
                 <FormGroupValidated<TextInput>
-                    label="IP Address"
+                    label={tr("target-address")}
                     required=true
                     validator={ip_validator}>
                     <TextInput
                         value={ip_address}
                         onchange={ip_change}
-                        placeholder="Alternate IP Address"
+                        placeholder={tr("target-address-placeholder")}
                     />
                 </FormGroupValidated<TextInput>>
-                <FormGroup label="Defined Credentials">
+                <FormGroup label={tr("target-credentials")}>
                     <SimpleSelect<Box<str>> selected={selected_credential} entries={defined_credentials} onselect={on_change_credential}/>
                 </FormGroup>
-                <FormGroup label="Adhoc Username">
+                <FormGroup label={tr("target-username")}>
                     <TextInput disabled={!enable_adhoc} onchange={on_change_username} value={username} />
                 </FormGroup>
-                <FormGroup label="Adhoc Password">
+                <FormGroup label={tr("target-password")}>
                     <TextInput disabled={!enable_adhoc} onchange={on_change_password} value={password} />
                 </FormGroup>
             </Form>
@@ -0,0 +1,19 @@
+use patternfly_yew::prelude::{CodeBlock, CodeBlockCode};
+use yew::{AttrValue, Html, Properties, function_component, html};
+
+#[derive(Debug, Clone, PartialEq, Properties)]
+pub struct CodeRegionProps {
+    /// name of the region read by screen readers, e.g. `Configuration of router-1`
+    pub label: AttrValue,
+    pub code: AttrValue,
+}
+
+/// Script or configuration update in a scrollable region, focusable to be scrolled by keyboard
+#[function_component(CodeRegion)]
+pub fn code_region(props: &CodeRegionProps) -> Html {
+    html! {
+        <div class="code-region" role="region" aria-label={props.label.clone()} tabindex="0">
+            <CodeBlock><CodeBlockCode>{props.code.clone()}</CodeBlockCode></CodeBlock>
+        </div>
+    }
+}
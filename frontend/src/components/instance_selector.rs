@@ -0,0 +1,76 @@
+use crate::{
+    data::SelectedInstance,
+    graphql::{
+        authenticated::{ListNetboxInstances, list_netbox_instances},
+        query_authenticated,
+    },
+};
+use log::error;
+use patternfly_yew::prelude::SimpleSelect;
+use yew::{Callback, Component, Context, Html, html, platform::spawn_local};
+
+/// Selects the netbox instance the pages are showing, hidden if only one instance is configured
+#[derive(Debug, Default)]
+pub struct InstanceSelector {
+    instances: Box<[Box<str>]>,
+}
+
+pub enum InstanceSelectorMsg {
+    Instances(Box<[Box<str>]>),
+}
+
+impl Component for InstanceSelector {
+    type Message = InstanceSelectorMsg;
+    type Properties = ();
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self::default()
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            InstanceSelectorMsg::Instances(instances) => {
+                self.instances = instances;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        if self.instances.len() < 2 {
+            return html! {};
+        }
+        let Some((selected_instance, _)) = ctx.link().context::<SelectedInstance>(Callback::noop())
+        else {
+            return html! {};
+        };
+        let selected = selected_instance
+            .name
+            .or_else(|| self.instances.first().cloned())
+            .unwrap_or_default();
+        let entries = self.instances.to_vec();
+        let onselect = selected_instance.select;
+        html! {
+            <SimpleSelect<Box<str>> {selected} {entries} {onselect}/>
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            let scope = ctx.link().clone();
+            spawn_local(async move {
+                match query_authenticated::<ListNetboxInstances, _>(
+                    scope.clone(),
+                    list_netbox_instances::Variables {},
+                )
+                .await
+                {
+                    Ok(data) => scope.send_message(InstanceSelectorMsg::Instances(
+                        data.netbox_instances.into_iter().map(Box::from).collect(),
+                    )),
+                    Err(e) => error!("Failed to fetch netbox instances. {}", e),
+                }
+            })
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement, KeyboardEvent, Node};
+use yew::{Callback, NodeRef};
+
+/// Attribute marking the entries of a list navigated by keyboard, e.g. the cards of the devices
+/// or the rows of a table. The entries need a `tabindex` to be focused.
+const NAV_ENTRY: &str = "data-nav-entry";
+
+/// Moves the focus between the entries of the list with the arrow keys, `Home` and `End`.
+/// `Enter` and space on a focused entry follow its first link, like a click on its title.
+pub fn list_keydown(list: NodeRef) -> Callback<KeyboardEvent> {
+    Callback::from(move |event: KeyboardEvent| {
+        let (Some(list), Some(target)) = (
+            list.cast::<Element>(),
+            event
+                .target()
+                .and_then(|target| target.dyn_into::<Element>().ok()),
+        ) else {
+            return;
+        };
+        let entries = entries(&list);
+        let target_node: &Node = &target;
+        let Some(current) = entries
+            .iter()
+            .position(|entry| entry.contains(Some(target_node)))
+        else {
+            return;
+        };
+        let next = match event.key().as_str() {
+            "ArrowDown" | "ArrowRight" => (current + 1).min(entries.len() - 1),
+            "ArrowUp" | "ArrowLeft" => current.saturating_sub(1),
+            "Home" => 0,
+            "End" => entries.len() - 1,
+            // keys on a link inside the entry are left to the link
+            "Enter" | " " if target.dyn_ref::<HtmlElement>() == Some(&entries[current]) => {
+                if let Some(link) = entries[current]
+                    .query_selector("a")
+                    .ok()
+                    .flatten()
+                    .and_then(|link| link.dyn_into::<HtmlElement>().ok())
+                {
+                    event.prevent_default();
+                    link.click();
+                }
+                return;
+            }
+            _ => return,
+        };
+        // the arrows would scroll the page otherwise
+        event.prevent_default();
+        let _ = entries[next].focus();
+    })
+}
+
+/// focuses the element, e.g. a heading made focusable with `tabindex="-1"`
+pub fn focus(node: &NodeRef) {
+    if let Some(element) = node.cast::<HtmlElement>() {
+        let _ = element.focus();
+    }
+}
+
+/// Moves the focus to the first heading of the page, or to the page itself without a heading,
+/// so screen readers announce the page that was opened.
+pub fn focus_page(page: &NodeRef) {
+    let Some(page) = page.cast::<HtmlElement>() else {
+        return;
+    };
+    let target = page
+        .query_selector("h1")
+        .ok()
+        .flatten()
+        .and_then(|heading| heading.dyn_into::<HtmlElement>().ok())
+        .unwrap_or(page);
+    if !target.has_attribute("tabindex") {
+        let _ = target.set_attribute("tabindex", "-1");
+    }
+    let _ = target.focus();
+}
+
+fn entries(list: &Element) -> Vec<HtmlElement> {
+    let Ok(nodes) = list.query_selector_all(&format!("[{NAV_ENTRY}]")) else {
+        return Vec::new();
+    };
+    (0..nodes.length())
+        .filter_map(|index| nodes.item(index)?.dyn_into::<HtmlElement>().ok())
+        .collect()
+}
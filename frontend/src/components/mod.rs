@@ -1 +1,6 @@
 pub mod adjust_target;
+pub mod code_region;
+pub mod instance_selector;
+pub mod keyboard;
+pub mod notification;
+pub mod search;
@@ -0,0 +1,49 @@
+use crate::error::FrontendError;
+use patternfly_yew::prelude::{AlertType, Toast, Toaster};
+use std::time::Duration;
+use yew::{Callback, Component, ToHtml, html::Scope};
+
+/// successful operations disappear after this time, failures stay until closed
+const SUCCESS_TIMEOUT: Duration = Duration::from_secs(8);
+
+/// Toasts about finished operations, shown by the `ToastViewer` of the main page
+#[derive(Clone, Default)]
+pub struct Notifications {
+    toaster: Option<Toaster>,
+}
+
+impl Notifications {
+    pub fn of<S: Component>(scope: &Scope<S>) -> Self {
+        Self {
+            toaster: scope
+                .context::<Toaster>(Callback::noop())
+                .map(|(toaster, _)| toaster),
+        }
+    }
+    pub fn success(&self, title: impl Into<String>, body: impl ToHtml) {
+        self.show(Toast {
+            title: title.into(),
+            r#type: AlertType::Success,
+            timeout: Some(SUCCESS_TIMEOUT),
+            body: body.to_html(),
+            ..Default::default()
+        });
+    }
+    pub fn error(&self, title: impl Into<String>, error: &FrontendError) {
+        self.failure(title, error.message());
+    }
+    pub fn failure(&self, title: impl Into<String>, body: impl ToHtml) {
+        self.show(Toast {
+            title: title.into(),
+            r#type: AlertType::Danger,
+            timeout: None,
+            body: body.to_html(),
+            ..Default::default()
+        });
+    }
+    fn show(&self, toast: Toast) {
+        if let Some(toaster) = &self.toaster {
+            toaster.toast(toast);
+        }
+    }
+}
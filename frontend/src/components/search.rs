@@ -0,0 +1,300 @@
+use crate::{
+    data::SelectedInstance,
+    error::FrontendError,
+    graphql::{
+        authenticated::{GlobalSearch, global_search},
+        query_authenticated,
+    },
+    i18n::tr,
+    pages::routes::{AppRoute, DeviceView, RouteDevices},
+};
+use gloo::{events::EventListener, timers::callback::Timeout};
+use patternfly_yew::prelude::Spinner;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlElement, HtmlInputElement, KeyboardEvent};
+use yew::{
+    Callback, Component, Context, Html, NodeRef,
+    events::{InputEvent, MouseEvent, TargetCast},
+    html,
+    platform::spawn_local,
+};
+use yew_nested_router::prelude::RouterContext;
+
+/// the search starts when no key was typed for this time
+const SEARCH_DELAY_MS: u32 = 250;
+
+/// Search over devices, vlans and prefixes opened by Ctrl+K, the chosen hit is shown right away
+pub struct SearchPalette {
+    open: bool,
+    query: String,
+    hits: Box<[SearchEntry]>,
+    selected: usize,
+    searching: bool,
+    error: Option<FrontendError>,
+    /// starts the search of the typed query, replaced by each key
+    delay: Option<Timeout>,
+    input: NodeRef,
+    /// the input is focused when the search was opened
+    focus: bool,
+    /// focused before the search was opened, focused again when it is closed without a choice
+    previous_focus: Option<HtmlElement>,
+    /// opens the search from every page, removed when dropped
+    _shortcut: EventListener,
+}
+#[derive(Debug)]
+pub struct SearchEntry {
+    kind: global_search::SearchHitKind,
+    id: u32,
+    name: Box<str>,
+    detail: Option<Box<str>>,
+}
+impl SearchEntry {
+    /// vlans and prefixes have no page of their own, they are shown in the vlan matrix
+    fn route(&self) -> AppRoute {
+        match self.kind {
+            global_search::SearchHitKind::DEVICE => AppRoute::Devices(RouteDevices::Device {
+                id: self.id,
+                view: DeviceView::Show,
+            }),
+            _ => AppRoute::VlanMatrix,
+        }
+    }
+    fn kind_label(&self) -> String {
+        match self.kind {
+            global_search::SearchHitKind::DEVICE => tr("search-kind-device"),
+            global_search::SearchHitKind::VLAN => tr("search-kind-vlan"),
+            global_search::SearchHitKind::PREFIX => tr("search-kind-prefix"),
+            global_search::SearchHitKind::Other(_) => String::new(),
+        }
+    }
+}
+#[derive(Debug)]
+pub enum SearchPaletteMsg {
+    Open,
+    Close,
+    Input(String),
+    Search,
+    Hits {
+        query: String,
+        hits: Box<[SearchEntry]>,
+    },
+    Failed(FrontendError),
+    Move(isize),
+    Choose(usize),
+}
+
+impl Component for SearchPalette {
+    type Message = SearchPaletteMsg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let scope = ctx.link().clone();
+        let shortcut = EventListener::new(&gloo::utils::document(), "keydown", move |event| {
+            let Some(event) = event.dyn_ref::<KeyboardEvent>() else {
+                return;
+            };
+            if (event.ctrl_key() || event.meta_key()) && event.key().eq_ignore_ascii_case("k") {
+                // the browser would focus its own search bar
+                event.prevent_default();
+                scope.send_message(SearchPaletteMsg::Open);
+            }
+        });
+        Self {
+            open: false,
+            query: String::new(),
+            hits: Box::default(),
+            selected: 0,
+            searching: false,
+            error: None,
+            delay: None,
+            input: NodeRef::default(),
+            focus: false,
+            previous_focus: None,
+            _shortcut: shortcut,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            SearchPaletteMsg::Open => {
+                if self.open {
+                    return false;
+                }
+                self.open = true;
+                self.focus = true;
+                self.previous_focus = gloo::utils::document()
+                    .active_element()
+                    .and_then(|element| element.dyn_into::<HtmlElement>().ok());
+            }
+            SearchPaletteMsg::Close => {
+                self.open = false;
+                self.delay = None;
+                if let Some(element) = self.previous_focus.take() {
+                    let _ = element.focus();
+                }
+            }
+            SearchPaletteMsg::Input(query) => {
+                self.query = query;
+                let scope = ctx.link().clone();
+                self.delay = Some(Timeout::new(SEARCH_DELAY_MS, move || {
+                    scope.send_message(SearchPaletteMsg::Search)
+                }));
+                return false;
+            }
+            SearchPaletteMsg::Search => {
+                self.delay = None;
+                if self.query.trim().is_empty() {
+                    self.hits = Box::default();
+                    self.error = None;
+                    return true;
+                }
+                self.searching = true;
+                search(ctx, self.query.clone());
+            }
+            SearchPaletteMsg::Hits { query, hits } => {
+                // the answer of a query typed over meanwhile
+                if query != self.query {
+                    return false;
+                }
+                self.searching = false;
+                self.error = None;
+                self.hits = hits;
+                self.selected = 0;
+            }
+            SearchPaletteMsg::Failed(error) => {
+                self.searching = false;
+                self.error = Some(error);
+            }
+            SearchPaletteMsg::Move(offset) => {
+                if self.hits.is_empty() {
+                    return false;
+                }
+                self.selected = self
+                    .selected
+                    .saturating_add_signed(offset)
+                    .min(self.hits.len() - 1);
+            }
+            SearchPaletteMsg::Choose(index) => {
+                let Some(hit) = self.hits.get(index) else {
+                    return false;
+                };
+                if let Some((router, _)) = ctx
+                    .link()
+                    .context::<RouterContext<AppRoute>>(Callback::noop())
+                {
+                    router.push(hit.route());
+                }
+                self.open = false;
+                // the focus moves to the opened page
+                self.previous_focus = None;
+                self.query.clear();
+                self.hits = Box::default();
+                self.selected = 0;
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        if !self.open {
+            return Html::default();
+        }
+        let oninput = ctx.link().callback(|event: InputEvent| {
+            SearchPaletteMsg::Input(event.target_unchecked_into::<HtmlInputElement>().value())
+        });
+        let selected = self.selected;
+        let onkeydown = ctx.link().batch_callback(move |event: KeyboardEvent| {
+            let msg = match event.key().as_str() {
+                // the input is the only control of the dialog, the focus stays inside
+                "Tab" => {
+                    event.prevent_default();
+                    return None;
+                }
+                "ArrowDown" => SearchPaletteMsg::Move(1),
+                "ArrowUp" => SearchPaletteMsg::Move(-1),
+                "Enter" => SearchPaletteMsg::Choose(selected),
+                "Escape" => SearchPaletteMsg::Close,
+                _ => return None,
+            };
+            event.prevent_default();
+            Some(msg)
+        });
+        let hits = self.hits.iter().enumerate().map(|(index, hit)| {
+            let selected = index == self.selected;
+            let class = selected.then_some("search-hit-selected");
+            let onclick = ctx.link().callback(move |_| SearchPaletteMsg::Choose(index));
+            html! {
+                <li id={format!("search-hit-{index}")} role="option" aria-selected={selected.to_string()} {class} {onclick}>
+                    <span class="search-hit-kind">{hit.kind_label()}</span>
+                    <span class="search-hit-name">{hit.name.as_ref()}</span>
+                    <span class="search-hit-detail">{hit.detail.as_deref().unwrap_or_default()}</span>
+                </li>
+            }
+        });
+        let active = (!self.hits.is_empty()).then(|| format!("search-hit-{}", self.selected));
+        let status = if let Some(error) = &self.error {
+            error.to_html()
+        } else if self.searching {
+            html! {<Spinner/>}
+        } else if self.hits.is_empty() && !self.query.trim().is_empty() && self.delay.is_none() {
+            html! {<p role="status">{tr("search-nothing-found")}</p>}
+        } else {
+            Html::default()
+        };
+        html! {
+            <div class="search-backdrop" onclick={ctx.link().callback(|_| SearchPaletteMsg::Close)}>
+                <div class="search-palette" role="dialog" aria-modal="true" aria-label={tr("search-title")}
+                    onclick={Callback::from(|event: MouseEvent| event.stop_propagation())}>
+                    <input ref={self.input.clone()} class="pf-v5-c-form-control" type="search"
+                        role="combobox" aria-autocomplete="list" aria-controls="search-hits"
+                        aria-expanded={(!self.hits.is_empty()).to_string()} aria-activedescendant={active}
+                        aria-label={tr("search-placeholder")} placeholder={tr("search-placeholder")}
+                        value={self.query.clone()} {oninput} {onkeydown}/>
+                    <ul id="search-hits" class="search-hits" role="listbox">{for hits}</ul>
+                    {status}
+                </div>
+            </div>
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
+        if !self.focus {
+            return;
+        }
+        if let Some(input) = self.input.cast::<HtmlInputElement>() {
+            self.focus = false;
+            let _ = input.focus();
+        }
+    }
+}
+
+fn search(ctx: &Context<SearchPalette>, query: String) {
+    let scope = ctx.link().clone();
+    spawn_local(async move {
+        match query_authenticated::<GlobalSearch, _>(
+            scope.clone(),
+            global_search::Variables {
+                instance: SelectedInstance::of(&scope),
+                query: query.clone(),
+            },
+        )
+        .await
+        {
+            Ok(data) => scope.send_message(SearchPaletteMsg::Hits {
+                query,
+                hits: data
+                    .topology
+                    .search
+                    .into_iter()
+                    .map(|hit| SearchEntry {
+                        kind: hit.kind,
+                        id: hit.id as u32,
+                        name: hit.name.into_boxed_str(),
+                        detail: hit.detail.map(String::into_boxed_str),
+                    })
+                    .collect(),
+            }),
+            Err(error) => scope.send_message(SearchPaletteMsg::Failed(error)),
+        }
+    });
+}
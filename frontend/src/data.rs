@@ -1,11 +1,34 @@
-use google_signin_client::prompt;
+use crate::{
+    auth::{self, OidcSettings, Tokens},
+    error::FrontendError,
+    i18n::Language,
+};
+use gloo::storage::{LocalStorage, Storage};
 use jwt::{Claims, Header, Token, Unverified, claims::SecondsSinceEpoch};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use yew::{Callback, Component, html::Scope};
+
+/// key of the preferences in the local storage
+const PREFERENCES_KEY: &str = "ui-preferences";
+
+/// the id token is renewed this long before it expires
+pub const REFRESH_MARGIN_SECS: u64 = 60;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct UserSessionData {
     jwt: Option<String>,
     valid_until: Option<SecondsSinceEpoch>,
     auto_select_user: bool,
+    refresh_token: Option<String>,
+    refresh: Option<SessionRefresh>,
+}
+
+/// Renews the tokens of the session, `renewed` passes new tokens to the app
+#[derive(Debug, PartialEq, Clone)]
+pub struct SessionRefresh {
+    pub settings: OidcSettings,
+    pub renewed: Callback<Tokens>,
 }
 
 impl Default for UserSessionData {
@@ -14,12 +37,18 @@ impl Default for UserSessionData {
             jwt: None,
             valid_until: None,
             auto_select_user: true,
+            refresh_token: None,
+            refresh: None,
         }
     }
 }
 
 impl UserSessionData {
-    pub fn from_token(token: String) -> Self {
+    pub fn from_tokens(tokens: Tokens) -> Self {
+        let Tokens {
+            id_token: token,
+            refresh_token,
+        } = tokens;
         let expiration =
             if let Ok(token) = Token::<Header, Claims, Unverified<'_>>::parse_unverified(&token) {
                 token.claims().registered.expiration
@@ -31,38 +60,185 @@ impl UserSessionData {
                 jwt: Some(token),
                 valid_until: Some(exp),
                 auto_select_user: false,
+                refresh_token,
+                refresh: None,
             }
         } else {
-            Self {
-                jwt: None,
-                valid_until: None,
-                auto_select_user: true,
-            }
+            Self::default()
+        }
+    }
+    /// enables the renewal of the tokens by the refresh token
+    pub fn with_refresh(self, refresh: SessionRefresh) -> Self {
+        Self {
+            refresh: Some(refresh),
+            ..self
         }
     }
     pub fn is_token_valid(&self) -> bool {
+        self.is_valid_for(0)
+    }
+    fn is_valid_for(&self, seconds: u64) -> bool {
         if let (Some(expire), Ok(now)) = (
             &self.valid_until,
             wasm_timer::SystemTime::now().duration_since(wasm_timer::SystemTime::UNIX_EPOCH),
         ) {
-            now.as_secs_f64() < *expire as f64
+            now.as_secs() + seconds < *expire
         } else {
             false
         }
     }
+    pub fn can_refresh(&self) -> bool {
+        self.refresh_token.is_some()
+    }
+    pub fn refresh_token(&self) -> Option<&str> {
+        self.refresh_token.as_deref()
+    }
     #[allow(dead_code)]
     pub fn logout(&mut self) {
         self.auto_select_user = false;
         self.jwt = None;
         self.valid_until = None;
-        //prompt(None);
+        self.refresh_token = None;
     }
 
-    pub fn jwt(&self) -> &Option<String> {
-        &self.jwt
+    /// token for a request, renewed first if it is about to expire
+    pub async fn current_jwt(&self) -> Result<String, FrontendError> {
+        if self.is_valid_for(REFRESH_MARGIN_SECS) {
+            if let Some(jwt) = &self.jwt {
+                return Ok(jwt.clone());
+            }
+        }
+        let (Some(refresh_token), Some(refresh)) = (&self.refresh_token, &self.refresh) else {
+            return self.jwt_if_valid();
+        };
+        // another request may have renewed the tokens since this session was handed out
+        let stored = auth::stored_tokens()
+            .filter(|tokens| Some(&tokens.id_token) != self.jwt.as_ref())
+            .filter(|tokens| {
+                UserSessionData::from_tokens(tokens.clone()).is_valid_for(REFRESH_MARGIN_SECS)
+            });
+        let tokens = match stored {
+            Some(tokens) => tokens,
+            None => match auth::refresh(&refresh.settings, refresh_token).await {
+                Ok(tokens) => tokens,
+                Err(error) => {
+                    warn!("Cannot refresh token: {error}");
+                    return self.jwt_if_valid();
+                }
+            },
+        };
+        refresh.renewed.emit(tokens.clone());
+        Ok(tokens.id_token)
+    }
+    fn jwt_if_valid(&self) -> Result<String, FrontendError> {
+        self.jwt
+            .clone()
+            .filter(|_| self.is_token_valid())
+            .ok_or_else(|| FrontendError::Login("Session expired".into()))
     }
     #[allow(dead_code)]
     pub fn valid_until(&self) -> Option<&SecondsSinceEpoch> {
         self.valid_until.as_ref()
     }
 }
+
+/// Netbox instance chosen in the navigation, `None` selects the default instance
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SelectedInstance {
+    pub name: Option<Box<str>>,
+    pub select: Callback<Box<str>>,
+}
+
+impl SelectedInstance {
+    /// instance argument for the topology queries of a component
+    pub fn of<S: Component>(scope: &Scope<S>) -> Option<String> {
+        scope
+            .context::<SelectedInstance>(Callback::noop())
+            .and_then(|(selected, _)| selected.name.map(String::from))
+    }
+}
+
+/// Settings of the user interface, kept in the local storage of the browser
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiPreferences {
+    pub dark_mode: bool,
+    /// stronger borders, outlines and text colors, the system setting applies without it
+    pub high_contrast: bool,
+    /// selected netbox instance, the default instance if not set
+    pub instance: Option<Box<str>>,
+    /// site filter of the device list and the vlan matrix
+    pub site: Option<u32>,
+    /// sort order of the device list
+    pub device_sort: DeviceSort,
+    /// status filter of the provisioning runs
+    pub run_status: Option<Box<str>>,
+    /// language of the user interface, the language of the browser if not chosen
+    pub language: Option<Language>,
+}
+
+impl UiPreferences {
+    /// the stored preferences, the defaults if there are none or they cannot be read
+    pub fn load() -> Self {
+        LocalStorage::get(PREFERENCES_KEY).unwrap_or_default()
+    }
+    pub fn language(&self) -> Language {
+        self.language.unwrap_or_else(Language::of_browser)
+    }
+    pub fn store(&self) {
+        if let Err(e) = LocalStorage::set(PREFERENCES_KEY, self) {
+            warn!("Cannot store preferences: {e}");
+        }
+    }
+    /// switches the patternfly theme and the contrast of the whole document
+    pub fn apply_theme(&self) {
+        if let Some(root) = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.document_element())
+        {
+            // other classes on the document are left as they are
+            let classes = root.class_list();
+            for (class, enabled) in [
+                ("pf-v5-theme-dark", self.dark_mode),
+                ("high-contrast", self.high_contrast),
+            ] {
+                if let Err(e) = classes.toggle_with_force(class, enabled) {
+                    warn!("Cannot switch class {class}: {e:?}");
+                }
+            }
+        }
+    }
+}
+
+/// Order of the device list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DeviceSort {
+    #[default]
+    Name,
+    /// by management address, devices without address last
+    Address,
+}
+
+/// Preferences of the user, `update` stores and applies changed preferences
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Preferences {
+    pub values: UiPreferences,
+    pub update: Callback<UiPreferences>,
+}
+
+impl Preferences {
+    pub fn of<S: Component>(scope: &Scope<S>) -> Self {
+        scope
+            .context::<Preferences>(Callback::noop())
+            .map(|(preferences, _)| preferences)
+            .unwrap_or_default()
+    }
+    /// stores the preferences modified by `change`
+    pub fn change(&self, change: impl FnOnce(&mut UiPreferences)) {
+        let mut values = self.values.clone();
+        change(&mut values);
+        if values != self.values {
+            self.update.emit(values);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
 
+use crate::i18n::tr;
 use log::error;
 use patternfly_yew::prelude::{Alert, AlertGroup, AlertType};
 use reqwest::header::InvalidHeaderValue;
@@ -57,6 +58,50 @@ pub enum FrontendError {
     InvalidHeader(#[from] InvalidHeaderValue),
     #[error("No data received")]
     MissingData,
+    #[error("Login failed: {0}")]
+    Login(Box<str>),
+}
+
+impl From<JsValue> for FrontendError {
+    fn from(value: JsValue) -> Self {
+        FrontendError::JS(value.into())
+    }
+}
+
+impl FrontendError {
+    /// error codes sent by the server in the `code` extension
+    pub fn error_codes(&self) -> impl Iterator<Item = &str> {
+        let errors = if let FrontendError::Graphql(errors) = self {
+            errors.as_slice()
+        } else {
+            &[]
+        };
+        errors.iter().filter_map(|error| {
+            error
+                .extensions
+                .as_ref()
+                .and_then(|ext| ext.get("code"))
+                .and_then(|code| code.as_str())
+        })
+    }
+    pub fn has_error_code(&self, code: &str) -> bool {
+        self.error_codes().any(|c| c == code)
+    }
+    /// single line description, the messages of the server errors or the cause of the error
+    pub fn message(&self) -> String {
+        match self {
+            FrontendError::Graphql(errors) => errors
+                .iter()
+                .map(|error| error.message.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            FrontendError::MissingData | FrontendError::Login(_) => self.to_string(),
+            other => match other.source() {
+                Some(source) => format!("{other}: {source}"),
+                None => other.to_string(),
+            },
+        }
+    }
 }
 
 impl ToHtml for FrontendError {
@@ -65,14 +110,14 @@ impl ToHtml for FrontendError {
             FrontendError::JS(js_error) => {
                 html! {
                     <AlertGroup>
-                        <Alert inline=true title="Javascript Error" r#type={AlertType::Danger}>{js_error.to_string()}</Alert>
+                        <Alert inline=true title={tr("error-javascript")} r#type={AlertType::Danger}>{js_error.to_string()}</Alert>
                     </AlertGroup>
                 }
             }
             FrontendError::Serde(serde_error) => {
                 html! {
                     <AlertGroup>
-                        <Alert inline=true title="Serialization Error" r#type={AlertType::Danger}>{serde_error.to_string()}</Alert>
+                        <Alert inline=true title={tr("error-serialization")} r#type={AlertType::Danger}>{serde_error.to_string()}</Alert>
                     </AlertGroup>
                 }
             }
@@ -80,7 +125,7 @@ impl ToHtml for FrontendError {
                 let graphql_error = graphql_error.clone();
                 html! {
                     <AlertGroup>
-                        <Alert inline=true title="Error from Server" r#type={AlertType::Danger}>
+                        <Alert inline=true title={tr("error-server")} r#type={AlertType::Danger}>
                             <ul>
                         {
                           graphql_error.iter().map(|error| {
@@ -94,7 +139,7 @@ impl ToHtml for FrontendError {
                                             .join("/")
                                     )
                                 {
-                                    html!{<li>{message}{" at "}{path}</li>}
+                                    html!{<li>{message}{" "}{tr("error-at")}{" "}{path}</li>}
                                 }else{
                                     html!{<li>{message}</li>}
                                 }
@@ -108,21 +153,28 @@ impl ToHtml for FrontendError {
             FrontendError::Reqwest(reqwest_error) => {
                 html! {
                     <AlertGroup>
-                        <Alert inline=true title="Cannot call Server" r#type={AlertType::Danger}>{reqwest_error.to_string()}</Alert>
+                        <Alert inline=true title={tr("error-call")} r#type={AlertType::Danger}>{reqwest_error.to_string()}</Alert>
                     </AlertGroup>
                 }
             }
             FrontendError::InvalidHeader(header_error) => {
                 html! {
                     <AlertGroup>
-                        <Alert inline=true title="Header Error" r#type={AlertType::Danger}>{header_error.to_string()}</Alert>
+                        <Alert inline=true title={tr("error-header")} r#type={AlertType::Danger}>{header_error.to_string()}</Alert>
                     </AlertGroup>
                 }
             }
             FrontendError::MissingData => {
                 html! {
                     <AlertGroup>
-                        <Alert inline=true title="Missing Data" r#type={AlertType::Danger}>{"No data received from server"}</Alert>
+                        <Alert inline=true title={tr("error-missing-data")} r#type={AlertType::Danger}>{tr("error-no-data")}</Alert>
+                    </AlertGroup>
+                }
+            }
+            FrontendError::Login(message) => {
+                html! {
+                    <AlertGroup>
+                        <Alert inline=true title={tr("error-login")} r#type={AlertType::Danger}>{message.as_ref()}</Alert>
                     </AlertGroup>
                 }
             }
@@ -7,3 +7,11 @@ use graphql_client::GraphQLQuery;
     response_derives = "Debug"
 )]
 pub struct Settings;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "target/anonymous_schema.graphql",
+    query_path = "src/graphql/anonymous/status.graphql",
+    response_derives = "Debug"
+)]
+pub struct PublicStatus;
@@ -16,7 +16,7 @@ pub struct ListDevices;
     query_path = "src/graphql/authenticated/list-devices.graphql",
     response_derives = "Debug"
 )]
-pub struct PingDevice;
+pub struct PingDevices;
 
 #[derive(GraphQLQuery)]
 #[graphql(
@@ -34,6 +34,22 @@ pub struct DetectDeviceType;
 )]
 pub struct DeviceOverview;
 
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/graphql/authenticated/schema.graphql",
+    query_path = "src/graphql/authenticated/show-device.graphql",
+    response_derives = "Debug"
+)]
+pub struct ApplyDevicePlan;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/graphql/authenticated/schema.graphql",
+    query_path = "src/graphql/authenticated/show-device.graphql",
+    response_derives = "Debug"
+)]
+pub struct DeviceConfig;
+
 #[derive(GraphQLQuery)]
 #[graphql(
     schema_path = "src/graphql/authenticated/schema.graphql",
@@ -41,3 +57,91 @@ pub struct DeviceOverview;
     response_derives = "Debug"
 )]
 pub struct AdjustTargetListCredentials;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/graphql/authenticated/schema.graphql",
+    query_path = "src/graphql/authenticated/list-wlan-groups.graphql",
+    response_derives = "Debug"
+)]
+pub struct ListWlanGroups;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/graphql/authenticated/schema.graphql",
+    query_path = "src/graphql/authenticated/list-netbox-instances.graphql",
+    response_derives = "Debug"
+)]
+pub struct ListNetboxInstances;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/graphql/authenticated/schema.graphql",
+    query_path = "src/graphql/authenticated/list-runs.graphql",
+    response_derives = "Debug"
+)]
+pub struct ListRuns;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/graphql/authenticated/schema.graphql",
+    query_path = "src/graphql/authenticated/list-runs.graphql",
+    response_derives = "Debug"
+)]
+pub struct RunDetails;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/graphql/authenticated/schema.graphql",
+    query_path = "src/graphql/authenticated/vlan-matrix.graphql",
+    response_derives = "Debug"
+)]
+pub struct ListVlanMatrix;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/graphql/authenticated/schema.graphql",
+    query_path = "src/graphql/authenticated/onboarding.graphql",
+    response_derives = "Debug"
+)]
+pub struct OnboardingDevices;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/graphql/authenticated/schema.graphql",
+    query_path = "src/graphql/authenticated/onboarding.graphql",
+    response_derives = "Debug"
+)]
+pub struct OnboardingDetect;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/graphql/authenticated/schema.graphql",
+    query_path = "src/graphql/authenticated/onboarding.graphql",
+    response_derives = "Debug"
+)]
+pub struct OnboardingPreview;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/graphql/authenticated/schema.graphql",
+    query_path = "src/graphql/authenticated/onboarding.graphql",
+    response_derives = "Debug"
+)]
+pub struct OnboardingBootstrapScript;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/graphql/authenticated/schema.graphql",
+    query_path = "src/graphql/authenticated/onboarding.graphql",
+    response_derives = "Debug"
+)]
+pub struct OnboardingApply;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/graphql/authenticated/schema.graphql",
+    query_path = "src/graphql/authenticated/search.graphql",
+    response_derives = "Debug"
+)]
+pub struct GlobalSearch;
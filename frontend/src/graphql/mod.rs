@@ -1,10 +1,10 @@
 use crate::data::UserSessionData;
 use crate::error::FrontendError;
-use google_signin_client::prompt_async;
 use graphql_client::reqwest::post_graphql;
 use graphql_client::{GraphQLQuery, Response};
 use lazy_static::lazy_static;
 use reqwest::header::{AUTHORIZATION, HeaderMap};
+use serde::Serialize;
 use yew::Component;
 use yew::html::Scope;
 
@@ -42,18 +42,71 @@ pub async fn query_authenticated_response<Q: GraphQLQuery, S: Component>(
 ) -> Result<Response<<Q as GraphQLQuery>::ResponseData>, FrontendError> {
     let mut headers = HeaderMap::new();
     if let Some((session_data, _)) = scope.context::<UserSessionData>(Default::default()) {
-        if !session_data.is_token_valid() {
-            //info!("Invalid session token");
-            prompt_async().await;
-        }
-        if let Some(access_token) = session_data.jwt() {
-            headers.insert(AUTHORIZATION, format!("Bearer {access_token}").parse()?);
-        }
+        let access_token = session_data.current_jwt().await?;
+        headers.insert(AUTHORIZATION, format!("Bearer {access_token}").parse()?);
     }
     let client = reqwest::Client::builder()
         .default_headers(headers)
         .build()?;
-    Ok(post_graphql::<Q, _>(&client, GRAPHQL_AUTHENTICATED_URL.as_str(), request).await?)
+    let query = Q::build_query(request);
+    let body = PersistedQueryBody {
+        variables: query.variables,
+        operation_name: query.operation_name,
+        extensions: PersistedQueryExtensions {
+            persisted_query: PersistedQueryId {
+                id: query.operation_name,
+            },
+        },
+    };
+    Ok(client
+        .post(GRAPHQL_AUTHENTICATED_URL.as_str())
+        .json(&body)
+        .send()
+        .await?
+        .json()
+        .await?)
+}
+
+/// Request referencing a query of the allow-list on the server instead of sending the document
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PersistedQueryBody<V> {
+    variables: V,
+    operation_name: &'static str,
+    extensions: PersistedQueryExtensions,
+}
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PersistedQueryExtensions {
+    persisted_query: PersistedQueryId,
+}
+#[derive(Serialize)]
+struct PersistedQueryId {
+    id: &'static str,
+}
+
+/// Send an anonymous query by get, the answer may come from the cache of the browser or a proxy
+pub async fn query_anonymous_cached<Q: GraphQLQuery>(
+    request: Q::Variables,
+) -> Result<Q::ResponseData, FrontendError> {
+    let client = reqwest::Client::builder().build()?;
+    let query = Q::build_query(request);
+    let response: Response<Q::ResponseData> = client
+        .get(GRAPHQL_ANONYMOUS_URL.as_str())
+        .query(&[
+            ("query", query.query.to_string()),
+            ("operationName", query.operation_name.to_string()),
+            ("variables", serde_json::to_string(&query.variables)?),
+        ])
+        .send()
+        .await?
+        .json()
+        .await?;
+    if let Some(data) = response.data {
+        Ok(data)
+    } else {
+        Err(FrontendError::Graphql(response.errors.unwrap_or_default()))
+    }
 }
 
 pub async fn query_anonymous<Q: GraphQLQuery>(
@@ -0,0 +1,130 @@
+//! Translations of the user interface.
+//!
+//! The texts are Fluent messages of the catalogs in `locales`, compiled into the frontend. The
+//! language is chosen in the sidebar and kept in the preferences, without a choice the
+//! language of the browser is used if there is a catalog for it. Pages are recreated when the
+//! language changes, so the texts are looked up while rendering.
+use crate::data::UiPreferences;
+use fluent::{FluentArgs, FluentBundle, FluentResource, FluentValue};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::{Cell, OnceCell},
+    fmt::{Display, Formatter},
+    rc::Rc,
+};
+use unic_langid::{LanguageIdentifier, langid};
+
+/// Language of the user interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::German];
+
+    fn id(self) -> LanguageIdentifier {
+        match self {
+            Language::English => langid!("en"),
+            Language::German => langid!("de"),
+        }
+    }
+    fn catalog(self) -> &'static str {
+        match self {
+            Language::English => include_str!("../locales/en.ftl"),
+            Language::German => include_str!("../locales/de.ftl"),
+        }
+    }
+    /// the preferred language of the browser if there is a catalog for it, English otherwise
+    pub fn of_browser() -> Self {
+        let preferred = web_sys::window()
+            .and_then(|window| window.navigator().language())
+            .unwrap_or_default();
+        Self::ALL
+            .into_iter()
+            .find(|language| preferred.starts_with(language.id().language.as_str()))
+            .unwrap_or_default()
+    }
+    fn bundle(self) -> Rc<FluentBundle<FluentResource>> {
+        BUNDLES.with(|bundles| {
+            let index = Self::ALL
+                .iter()
+                .position(|language| *language == self)
+                .unwrap_or_default();
+            bundles[index]
+                .get_or_init(|| {
+                    let resource = FluentResource::try_new(self.catalog().to_string())
+                        .unwrap_or_else(|(resource, errors)| {
+                            warn!("Invalid catalog {}: {errors:?}", self.id());
+                            resource
+                        });
+                    let mut bundle = FluentBundle::new(vec![self.id()]);
+                    // the unicode isolation marks around arguments would show up in inputs
+                    bundle.set_use_isolating(false);
+                    if let Err(errors) = bundle.add_resource(resource) {
+                        warn!("Duplicate messages in catalog {}: {errors:?}", self.id());
+                    }
+                    Rc::new(bundle)
+                })
+                .clone()
+        })
+    }
+}
+
+impl Display for Language {
+    /// name of the language in itself, as shown in the selection
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Language::English => "English",
+            Language::German => "Deutsch",
+        })
+    }
+}
+
+thread_local! {
+    static BUNDLES: [OnceCell<Rc<FluentBundle<FluentResource>>>; 2] = Default::default();
+    static CURRENT: Cell<Language> = Cell::new(UiPreferences::load().language());
+}
+
+/// language the texts are shown in
+pub fn language() -> Language {
+    CURRENT.with(Cell::get)
+}
+
+/// switches the texts rendered from now on and the language of the document
+pub fn set_language(language: Language) {
+    CURRENT.with(|current| current.set(language));
+    if let Some(root) = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.document_element())
+    {
+        let _ = root.set_attribute("lang", &language.id().to_string());
+    }
+}
+
+/// text of a message in the current language, the id itself if the message is missing
+pub fn tr(id: &str) -> String {
+    tr_args(id, &[])
+}
+
+/// text of a message with arguments like `{ $name }` in the current language
+pub fn tr_args(id: &str, args: &[(&str, FluentValue)]) -> String {
+    let bundle = language().bundle();
+    let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) else {
+        warn!("Missing message {id} in catalog {}", language().id());
+        return id.to_string();
+    };
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, value.clone());
+    }
+    let mut errors = Vec::new();
+    let text = bundle.format_pattern(pattern, Some(&fluent_args), &mut errors);
+    if !errors.is_empty() {
+        warn!("Cannot format message {id}: {errors:?}");
+    }
+    text.into_owned()
+}
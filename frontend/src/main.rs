@@ -1,9 +1,11 @@
 use crate::pages::App;
 use wasm_bindgen::{JsValue, prelude::wasm_bindgen};
+mod auth;
 pub mod components;
 mod data;
 mod error;
 mod graphql;
+mod i18n;
 pub mod pages;
 
 #[wasm_bindgen]
@@ -16,6 +18,8 @@ const LOG_LEVEL: log::Level = log::Level::Info;
 const LOG_LEVEL: log::Level = log::Level::Trace;
 pub fn main() -> Result<(), JsValue> {
     wasm_logger::init(wasm_logger::Config::new(LOG_LEVEL));
+    // marks the document with the stored language before the first render
+    i18n::set_language(i18n::language());
     yew::Renderer::<App>::new().render();
     Ok(())
 }
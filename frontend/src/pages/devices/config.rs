@@ -0,0 +1,191 @@
+use crate::{
+    components::{code_region::CodeRegion, notification::Notifications},
+    data::SelectedInstance,
+    error::FrontendError,
+    graphql::{
+        authenticated::{DeviceConfig, device_config},
+        query_authenticated,
+    },
+    i18n::{tr, tr_args},
+    pages::routes::{AppRoute, DeviceView, RouteDevices},
+};
+use gloo::file::{Blob, ObjectUrl};
+use patternfly_yew::prelude::{Alert, AlertType, Button, ButtonVariant, Spinner};
+use wasm_bindgen_futures::JsFuture;
+use yew::{Component, Context, Html, Properties, html, html::Scope, platform::spawn_local};
+use yew_nested_router::components::Link;
+
+/// Generated `.rsc` script of a device, to be reviewed before it is applied
+pub struct DeviceConfigView {
+    state: ConfigState,
+}
+enum ConfigState {
+    Loading,
+    NotFound,
+    /// the device has no management address or cannot be accessed
+    Unreachable(Box<str>),
+    Data {
+        name: Box<str>,
+        script: Box<str>,
+        /// download link of the script, revoked when replaced
+        download: ObjectUrl,
+    },
+    Error(FrontendError),
+}
+#[derive(Debug, Properties, Clone, PartialEq)]
+pub struct DeviceConfigProps {
+    pub id: u32,
+}
+#[derive(Debug)]
+pub enum DeviceConfigMessage {
+    Data(Option<(Box<str>, Box<str>)>),
+    Unreachable(Box<str>),
+    Error(FrontendError),
+    Reload,
+    Copy,
+}
+
+impl Component for DeviceConfigView {
+    type Message = DeviceConfigMessage;
+    type Properties = DeviceConfigProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            state: ConfigState::Loading,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            DeviceConfigMessage::Data(None) => self.state = ConfigState::NotFound,
+            DeviceConfigMessage::Data(Some((name, script))) => {
+                let download =
+                    ObjectUrl::from(Blob::new_with_options(script.as_ref(), Some("text/plain")));
+                self.state = ConfigState::Data {
+                    name,
+                    script,
+                    download,
+                };
+            }
+            DeviceConfigMessage::Unreachable(name) => self.state = ConfigState::Unreachable(name),
+            DeviceConfigMessage::Error(error) => self.state = ConfigState::Error(error),
+            DeviceConfigMessage::Reload => {
+                self.state = ConfigState::Loading;
+                fetch_config(ctx);
+            }
+            DeviceConfigMessage::Copy => {
+                if let ConfigState::Data { name, script, .. } = &self.state {
+                    copy_to_clipboard(ctx.link(), name, script);
+                }
+                return false;
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let device = AppRoute::Devices(RouteDevices::Device {
+            id: ctx.props().id,
+            view: DeviceView::Show,
+        });
+        let reload = html! {
+            <Button variant={ButtonVariant::Secondary} onclick={ctx.link().callback(|_| DeviceConfigMessage::Reload)}>{tr("config-regenerate")}</Button>
+        };
+        let content = match &self.state {
+            ConfigState::Loading => html! {<Spinner/>},
+            ConfigState::NotFound => html! {<p>{tr("config-device-not-found")}</p>},
+            ConfigState::Unreachable(name) => html! {<>
+                <Alert inline=true title={tr_args("config-device-unreachable", &[("device", name.as_ref().into())])} r#type={AlertType::Danger}/>
+                {reload}
+            </>},
+            ConfigState::Error(error) => html! {<>{error.to_html()}{reload}</>},
+            ConfigState::Data {
+                name,
+                script,
+                download,
+            } => {
+                let filename = format!("{name}.rsc");
+                let title = tr_args("config-title", &[("device", name.as_ref().into())]);
+                let body = if script.is_empty() {
+                    html! {<p>{tr("config-up-to-date")}</p>}
+                } else {
+                    html! {<CodeRegion label={title.clone()} code={script.to_string()}/>}
+                };
+                html! {
+                    <>
+                    <h1>{title}</h1>
+                    <div class="config-actions">
+                        <Button variant={ButtonVariant::Primary} disabled={script.is_empty()} onclick={ctx.link().callback(|_| DeviceConfigMessage::Copy)}>{tr("config-copy")}</Button>
+                        <a class="pf-v5-c-button pf-m-secondary" href={download.to_string()} download={filename}>{tr("config-download")}</a>
+                        {reload}
+                    </div>
+                    {body}
+                    </>
+                }
+            }
+        };
+        html! {
+            <>
+            <Link<AppRoute> to={device}>{tr("config-back")}</Link<AppRoute>>
+            {content}
+            </>
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            fetch_config(ctx);
+        }
+    }
+}
+
+fn fetch_config(ctx: &Context<DeviceConfigView>) {
+    let scope = ctx.link().clone();
+    let id = ctx.props().id as i64;
+    spawn_local(async move {
+        match query_authenticated::<DeviceConfig, _>(
+            scope.clone(),
+            device_config::Variables {
+                instance: SelectedInstance::of(&scope),
+                id,
+            },
+        )
+        .await
+        {
+            Ok(data) => scope.send_message(match data.topology.device_by_id {
+                None => DeviceConfigMessage::Data(None),
+                // an empty script would look like a device already matching netbox
+                Some(device) => match device.access {
+                    Some(access) => DeviceConfigMessage::Data(Some((
+                        device.name.into_boxed_str(),
+                        access.generate_cfg.into_boxed_str(),
+                    ))),
+                    None => DeviceConfigMessage::Unreachable(device.name.into_boxed_str()),
+                },
+            }),
+            Err(error) => scope.send_message(DeviceConfigMessage::Error(error)),
+        }
+    });
+}
+
+/// copies the configuration script of the named device, the outcome is notified
+pub fn copy_to_clipboard<C: Component>(scope: &Scope<C>, name: &str, script: &str) {
+    let notifications = Notifications::of(scope);
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let promise = window.navigator().clipboard().write_text(script);
+    let name = name.to_string();
+    spawn_local(async move {
+        match JsFuture::from(promise).await {
+            Ok(_) => notifications.success(
+                tr("config-copied"),
+                tr_args("config-copied-of", &[("device", name.into())]),
+            ),
+            Err(error) => notifications.failure(
+                tr("config-copy-failed"),
+                error.as_string().unwrap_or_default(),
+            ),
+        }
+    });
+}
@@ -1,19 +1,107 @@
 use crate::{
+    components::{keyboard::list_keydown, notification::Notifications},
+    data::{DeviceSort, Preferences, SelectedInstance},
     error::FrontendError,
     graphql::{
-        authenticated::{ListDevices, PingDevice, list_devices, ping_device},
+        authenticated::{
+            DetectDeviceType, ListDevices, PingDevices, detect_device_type, list_devices,
+            ping_devices,
+        },
         query_authenticated, query_authenticated_response,
     },
+    i18n::tr,
     pages::routes::{AppRoute, DeviceView, RouteDevices},
 };
-use patternfly_yew::prelude::{Card, CardBody, CardHeader, CardTitle, Spinner, SpinnerSize};
-use std::{net::IpAddr, str::FromStr};
-use yew::{Component, Context, Html, Properties, ToHtml, html, platform::spawn_local};
+use gloo::timers::callback::Interval;
+use patternfly_yew::prelude::{
+    Card, CardBody, CardHeader, CardTitle, SimpleSelect, Spinner, SpinnerSize,
+};
+use std::{collections::HashMap, fmt::Display, net::IpAddr, str::FromStr};
+use yew::{
+    Component, Context, Html, NodeRef, Properties, ToHtml, html, html::Scope, platform::spawn_local,
+};
 use yew_nested_router::components::Link;
+pub mod config;
 pub mod show;
+
+/// the reachability of the listed devices is refreshed in this interval, the backend caches
+/// the ping results for a few seconds so polling stays cheap
+const REACHABILITY_POLL_INTERVAL_MS: u32 = 15_000;
+
 pub struct Devices {
     state: DeviceState,
+    sites: Box<[SiteEntry]>,
+    selected_site: SiteEntry,
+    sort: DeviceSort,
+    reachability: HashMap<u32, Reachability>,
+    /// refreshes the reachability while devices are shown, stopped when dropped
+    reachability_poll: Option<Interval>,
+    pinging: bool,
+    ping_failed: bool,
     error_state: Option<FrontendError>,
+    /// the cards are navigated by the arrow keys
+    list: NodeRef,
+}
+/// entry of the site filter, entries with the same id are equal
+#[derive(Debug, Clone, Default)]
+pub enum SiteEntry {
+    #[default]
+    All,
+    Site {
+        id: u32,
+        name: Box<str>,
+    },
+}
+impl SiteEntry {
+    /// entry of the stored site filter, the name is resolved when the sites are loaded
+    pub fn stored<S: Component>(scope: &Scope<S>) -> Self {
+        match Preferences::of(scope).values.site {
+            Some(id) => SiteEntry::Site {
+                id,
+                name: Box::default(),
+            },
+            None => SiteEntry::All,
+        }
+    }
+    pub fn id(&self) -> Option<u32> {
+        match self {
+            SiteEntry::All => None,
+            SiteEntry::Site { id, .. } => Some(*id),
+        }
+    }
+    /// the matching loaded entry, all sites if the site does not exist anymore
+    pub fn resolve(&self, sites: &[SiteEntry]) -> Self {
+        sites
+            .iter()
+            .find(|site| *site == self)
+            .cloned()
+            .unwrap_or_default()
+    }
+    /// stores the site as filter of the next visit
+    pub fn store<S: Component>(&self, scope: &Scope<S>) {
+        Preferences::of(scope).change(|values| values.site = self.id());
+    }
+}
+impl PartialEq for SiteEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
+    }
+}
+impl Display for SiteEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SiteEntry::All => f.write_str(&tr("devices-all-sites")),
+            SiteEntry::Site { name, .. } => f.write_str(name),
+        }
+    }
+}
+impl Display for DeviceSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&tr(match self {
+            DeviceSort::Name => "devices-sort-name",
+            DeviceSort::Address => "devices-sort-address",
+        }))
+    }
 }
 enum DeviceState {
     Loading,
@@ -26,9 +114,26 @@ struct DeviceRow {
     address: Option<IpAddr>,
     serial: Option<Box<str>>,
 }
+#[derive(Debug, Clone, PartialEq, Default)]
+enum Reachability {
+    #[default]
+    Pending,
+    Reachable {
+        duration: u64,
+    },
+    Unreachable(Box<str>),
+}
 #[derive(Debug)]
 pub enum DevicesMsg {
-    Data(Box<[DeviceRow]>),
+    Data {
+        sites: Box<[SiteEntry]>,
+        devices: Box<[DeviceRow]>,
+    },
+    SelectSite(SiteEntry),
+    SelectSort(DeviceSort),
+    PollReachability,
+    Reachability(HashMap<u32, Reachability>),
+    PingFailed(FrontendError),
     Error(FrontendError),
 }
 impl Component for Devices {
@@ -38,17 +143,90 @@ impl Component for Devices {
     fn create(ctx: &Context<Self>) -> Self {
         Self {
             state: DeviceState::Loading,
+            sites: Box::default(),
+            selected_site: SiteEntry::stored(ctx.link()),
+            sort: Preferences::of(ctx.link()).values.device_sort,
+            reachability: HashMap::new(),
+            reachability_poll: None,
+            pinging: false,
+            ping_failed: false,
             error_state: None,
+            list: NodeRef::default(),
         }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
-            DevicesMsg::Data(data) => {
-                self.state = DeviceState::Data(data);
+            DevicesMsg::Data { sites, devices } => {
+                self.selected_site = self.selected_site.resolve(&sites);
+                self.sites = sites;
+                self.state = DeviceState::Data(devices);
                 self.error_state = None;
+                self.ping(ctx);
+                let scope = ctx.link().clone();
+                self.reachability_poll =
+                    Some(Interval::new(REACHABILITY_POLL_INTERVAL_MS, move || {
+                        scope.send_message(DevicesMsg::PollReachability)
+                    }));
                 true
             }
+            DevicesMsg::PollReachability => {
+                self.ping(ctx);
+                false
+            }
+            DevicesMsg::SelectSite(site) => {
+                if site == self.selected_site {
+                    return false;
+                }
+                site.store(ctx.link());
+                self.selected_site = site;
+                self.state = DeviceState::Loading;
+                self.reachability.clear();
+                self.reachability_poll = None;
+                self.load(ctx);
+                true
+            }
+            DevicesMsg::SelectSort(sort) => {
+                if sort == self.sort {
+                    return false;
+                }
+                Preferences::of(ctx.link()).change(|values| values.device_sort = sort);
+                self.sort = sort;
+                true
+            }
+            DevicesMsg::Reachability(reachability) => {
+                self.pinging = false;
+                self.ping_failed = false;
+                // the answer of a ping started before the site was changed
+                if let DeviceState::Data(rows) = &self.state {
+                    if rows.iter().any(|row| !reachability.contains_key(&row.id)) {
+                        self.ping(ctx);
+                        return false;
+                    }
+                }
+                if reachability == self.reachability {
+                    return false;
+                }
+                self.reachability = reachability;
+                true
+            }
+            DevicesMsg::PingFailed(error) => {
+                self.pinging = false;
+                // keep the last known state and only report the first of repeated failures
+                if !self.ping_failed {
+                    Notifications::of(ctx.link()).error(tr("devices-ping-failed"), &error);
+                }
+                self.ping_failed = true;
+                let message: Box<str> = error.to_string().into();
+                let mut changed = false;
+                for reachability in self.reachability.values_mut() {
+                    if *reachability == Reachability::Pending {
+                        *reachability = Reachability::Unreachable(message.clone());
+                        changed = true;
+                    }
+                }
+                changed
+            }
             DevicesMsg::Error(error) => {
                 self.error_state = Some(error);
                 true
@@ -63,9 +241,18 @@ impl Component for Devices {
                 html! {<Spinner/>}
             }
             DeviceState::Data(rows) => {
-                let cards = rows.iter().map(|row| {
+                let mut rows = rows.iter().collect::<Vec<_>>();
+                match self.sort {
+                    DeviceSort::Name => rows.sort_by(|a, b| a.name.cmp(&b.name)),
+                    // devices without address last
+                    DeviceSort::Address => {
+                        rows.sort_by_key(|row| (row.address.is_none(), row.address))
+                    }
+                }
+                let cards = rows.into_iter().map(|row| {
+                    let reachability = self.reachability.get(&row.id).cloned().unwrap_or_default();
                     html! {
-                        <DeviceEntryCard device={row.clone()}/>
+                        <DeviceEntryCard device={row.clone()} {reachability}/>
                     }
                 });
                 html! {
@@ -73,79 +260,212 @@ impl Component for Devices {
                 }
             }
         };
+        // the filter is only useful with more than one site
+        let site_filter = (self.sites.len() > 1).then(|| {
+            let entries = std::iter::once(SiteEntry::All)
+                .chain(self.sites.iter().cloned())
+                .collect::<Vec<_>>();
+            let selected = self.selected_site.clone();
+            let onselect = ctx.link().callback(DevicesMsg::SelectSite);
+            html! {
+                <div class="device-site-filter">
+                    <SimpleSelect<SiteEntry> {selected} {entries} {onselect}/>
+                </div>
+            }
+        });
+        let sort_order = {
+            let entries = vec![DeviceSort::Name, DeviceSort::Address];
+            let selected = self.sort;
+            let onselect = ctx.link().callback(DevicesMsg::SelectSort);
+            html! {
+                <div class="device-site-filter">
+                    <SimpleSelect<DeviceSort> {selected} {entries} {onselect}/>
+                </div>
+            }
+        };
         html! {
-            <div class="device-list">
+            <>
+                {site_filter}
+                {sort_order}
                 {error_msg}
-                {data}
-            </div>
+                <div class="device-list" role="list" aria-label={tr("devices-list")} ref={self.list.clone()} onkeydown={list_keydown(self.list.clone())}>
+                    {data}
+                </div>
+            </>
         }
     }
 
     fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
         if first_render {
-            let scope = ctx.link().clone();
-            spawn_local(async move {
-                match query_authenticated::<ListDevices, _>(
-                    scope.clone(),
-                    list_devices::Variables {},
-                )
-                .await
-                {
-                    Ok(data) => {
-                        scope.send_message(DevicesMsg::Data(
-                            data.topology
-                                .all_devices
-                                .into_iter()
-                                .map(|device| DeviceRow {
-                                    id: device.id as u32,
-                                    name: device.name.into_boxed_str(),
-                                    address: device
-                                        .management_address
-                                        .and_then(|ip| ip.address)
-                                        .and_then(|a| IpAddr::from_str(a.ip.as_str()).ok()),
-                                    serial: device.serial.map(|s| s.into_boxed_str()),
-                                })
-                                .collect(),
-                        ));
-                    }
-                    Err(e) => {
-                        scope.send_message(DevicesMsg::Error(e));
-                    }
-                }
-            })
+            self.load(ctx);
         }
     }
 }
 
+impl Devices {
+    /// pings the listed devices, skipped while the previous ping is still running
+    fn ping(&mut self, ctx: &Context<Self>) {
+        let DeviceState::Data(rows) = &self.state else {
+            return;
+        };
+        if self.pinging {
+            return;
+        }
+        self.pinging = true;
+        let ids = rows.iter().map(|row| row.id as i64).collect::<Vec<_>>();
+        for id in &ids {
+            self.reachability.entry(*id as u32).or_default();
+        }
+        let scope = ctx.link().clone();
+        spawn_local(async move {
+            match query_authenticated::<PingDevices, _>(
+                scope.clone(),
+                ping_devices::Variables {
+                    instance: SelectedInstance::of(&scope),
+                    ids,
+                },
+            )
+            .await
+            {
+                Ok(data) => scope.send_message(DevicesMsg::Reachability(
+                    data.topology
+                        .ping_devices
+                        .into_iter()
+                        .map(|entry| {
+                            let reachability = match (entry.ping, entry.error) {
+                                (Some(ping), _) => Reachability::Reachable {
+                                    duration: ping.duration,
+                                },
+                                (None, error) => {
+                                    Reachability::Unreachable(error.unwrap_or_default().into())
+                                }
+                            };
+                            (entry.device_id as u32, reachability)
+                        })
+                        .collect(),
+                )),
+                Err(e) => scope.send_message(DevicesMsg::PingFailed(e)),
+            }
+        });
+    }
+    fn load(&self, ctx: &Context<Self>) {
+        let scope = ctx.link().clone();
+        let site = self.selected_site.id().map(i64::from);
+        spawn_local(async move {
+            match query_authenticated::<ListDevices, _>(
+                scope.clone(),
+                list_devices::Variables {
+                    instance: SelectedInstance::of(&scope),
+                    site,
+                },
+            )
+            .await
+            {
+                Ok(data) => {
+                    let mut sites = data
+                        .topology
+                        .all_sites
+                        .into_iter()
+                        .map(|site| SiteEntry::Site {
+                            id: site.id as u32,
+                            name: site.name.unwrap_or_default().into_boxed_str(),
+                        })
+                        .collect::<Box<[_]>>();
+                    sites.sort_by_key(|site| site.to_string());
+                    scope.send_message(DevicesMsg::Data {
+                        sites,
+                        devices: data
+                            .topology
+                            .all_devices
+                            .into_iter()
+                            .map(|device| DeviceRow {
+                                id: device.id as u32,
+                                name: device.name.into_boxed_str(),
+                                address: device
+                                    .management_address
+                                    .and_then(|ip| ip.address)
+                                    .and_then(|a| IpAddr::from_str(a.ip.as_str()).ok()),
+                                serial: device.serial.map(|s| s.into_boxed_str()),
+                            })
+                            .collect(),
+                    });
+                }
+                Err(e) => {
+                    scope.send_message(DevicesMsg::Error(e));
+                }
+            }
+        })
+    }
+}
+
 #[derive(Debug)]
 struct DeviceEntryCard {
-    device: DeviceRow,
-    ping_result: PingResult,
+    detection: Detection,
 }
 #[derive(Debug)]
-enum PingResult {
+enum Detection {
+    NotStarted,
     Pending,
-    None,
     Success {
-        ping_result: ping_device::PingDeviceTopologyDeviceByIdAccessPing,
         device_type: Box<str>,
         serial: Box<str>,
     },
+    None,
     Failed(FrontendError),
 }
 #[derive(Debug, Clone, Properties, PartialEq)]
 struct DeviceEntryCardProps {
     device: DeviceRow,
+    reachability: Reachability,
 }
 enum DeviceEntryCardMsg {
-    Data,
-    PingResult {
-        ping_result: ping_device::PingDeviceTopologyDeviceByIdAccessPing,
+    Detected {
         device_type: Box<str>,
         serial: Box<str>,
     },
-    NoPing,
-    PingError(FrontendError),
+    NotDetected,
+    DetectionError(FrontendError),
+}
+
+impl DeviceEntryCard {
+    fn start_detection(&mut self, ctx: &Context<Self>) {
+        if !matches!(self.detection, Detection::NotStarted)
+            || !matches!(ctx.props().reachability, Reachability::Reachable { .. })
+        {
+            return;
+        }
+        self.detection = Detection::Pending;
+        let scope = ctx.link().clone();
+        let id = ctx.props().device.id as i64;
+        spawn_local(async move {
+            match query_authenticated_response::<DetectDeviceType, _>(
+                scope.clone(),
+                detect_device_type::Variables {
+                    instance: SelectedInstance::of(&scope),
+                    id,
+                },
+            )
+            .await
+            {
+                Ok(result) => {
+                    let msg = result
+                        .data
+                        .and_then(|data| data.topology.device_by_id.and_then(|d| d.access))
+                        .map(|d| DeviceEntryCardMsg::Detected {
+                            device_type: d.device_stats.routerboard.device_type.into_boxed_str(),
+                            serial: d.device_stats.routerboard.serial_number.into_boxed_str(),
+                        })
+                        .or(result
+                            .errors
+                            .filter(|e| !e.is_empty())
+                            .map(|e| DeviceEntryCardMsg::DetectionError(FrontendError::Graphql(e))))
+                        .unwrap_or(DeviceEntryCardMsg::NotDetected);
+                    scope.send_message(msg);
+                }
+                Err(e) => scope.send_message(DeviceEntryCardMsg::DetectionError(e)),
+            }
+        });
+    }
 }
 
 impl Component for DeviceEntryCard {
@@ -153,64 +473,59 @@ impl Component for DeviceEntryCard {
     type Properties = DeviceEntryCardProps;
 
     fn create(ctx: &Context<Self>) -> Self {
-        Self {
-            device: ctx.props().device.clone(),
-            ping_result: PingResult::Pending,
-        }
+        let mut card = Self {
+            detection: Detection::NotStarted,
+        };
+        card.start_detection(ctx);
+        card
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
-        match msg {
-            DeviceEntryCardMsg::Data => false,
-            DeviceEntryCardMsg::PingResult {
-                ping_result,
+        self.detection = match msg {
+            DeviceEntryCardMsg::Detected {
                 device_type,
                 serial,
-            } => {
-                self.ping_result = PingResult::Success {
-                    ping_result,
-                    device_type,
-                    serial,
-                };
-                true
-            }
-            DeviceEntryCardMsg::NoPing => {
-                self.ping_result = PingResult::None;
-                true
-            }
-            DeviceEntryCardMsg::PingError(e) => {
-                self.ping_result = PingResult::Failed(e);
-                true
-            }
-        }
+            } => Detection::Success {
+                device_type,
+                serial,
+            },
+            DeviceEntryCardMsg::NotDetected => Detection::None,
+            DeviceEntryCardMsg::DetectionError(e) => Detection::Failed(e),
+        };
+        true
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.start_detection(ctx);
+        true
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
-        let device = &self.device;
+        let device = &ctx.props().device;
         let title = device.name.as_ref();
         let address = device
             .address
             .as_ref()
             .map(|a| a.to_string())
             .unwrap_or_default();
-        let (ping_result_data, type_description, detected_serial) = match &self.ping_result {
-            PingResult::Pending => (
-                html! {<Spinner size={SpinnerSize::Sm}/>},
-                Html::default(),
-                None,
-            ),
-            PingResult::Success {
-                ping_result,
+        let ping_result_data = match &ctx.props().reachability {
+            Reachability::Pending => html! {<Spinner size={SpinnerSize::Sm}/>},
+            Reachability::Reachable { duration } => {
+                format!("{:.2}ms", *duration as f32 / 1000.0 / 1000.0).into_html()
+            }
+            Reachability::Unreachable(error) => error.into_html(),
+        };
+        let (type_description, detected_serial) = match &self.detection {
+            Detection::Pending => (html! {<Spinner size={SpinnerSize::Sm}/>}, None),
+            Detection::Success {
                 device_type,
                 serial,
             } => (
-                format!("{:.2}ms", ping_result.duration as f32 / 1000.0 / 1000.0).into_html(),
                 device_type.into_html(),
                 Some(html!(<div class="device-detected-serial">{serial.as_ref()}</div>)),
             ),
-
-            PingResult::Failed(e) => (e.into_html(), Html::default(), None),
-            PingResult::None => Default::default(),
+            Detection::Failed(e) => (e.into_html(), None),
+            Detection::NotStarted | Detection::None => Default::default(),
         };
         let to = AppRoute::Devices(RouteDevices::Device {
             id: device.id,
@@ -222,6 +537,7 @@ impl Component for DeviceEntryCard {
             .map(|serial| html!(<div class="device-serial">{serial}</div>));
 
         html! {
+            <div class="device-entry" role="listitem" tabindex="0" data-nav-entry="">
             <Card>
                 <CardHeader><CardTitle><Link<AppRoute> {to}>{title}</Link<AppRoute>></CardTitle></CardHeader>
                 <CardBody>
@@ -231,54 +547,7 @@ impl Component for DeviceEntryCard {
                     {serial} {detected_serial}
                 </CardBody>
             </Card>
-        }
-    }
-
-    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
-        if first_render {
-            let scope = ctx.link().clone();
-            let id = self.device.id as i64;
-            spawn_local(async move {
-                match query_authenticated_response::<PingDevice, _>(
-                    scope.clone(),
-                    ping_device::Variables { id },
-                )
-                .await
-                {
-                    Ok(result) => {
-                        let msg =
-                            result
-                                .data
-                                .and_then(|data| {
-                                    data.topology.device_by_id.and_then(|d| d.access).and_then(
-                                        |d| {
-                                            d.ping.into_iter().next().map(|pr| {
-                                                DeviceEntryCardMsg::PingResult {
-                                                    ping_result: pr,
-                                                    device_type: d
-                                                        .device_stats
-                                                        .routerboard
-                                                        .device_type
-                                                        .into_boxed_str(),
-                                                    serial: d
-                                                        .device_stats
-                                                        .routerboard
-                                                        .serial_number
-                                                        .into_boxed_str(),
-                                                }
-                                            })
-                                        },
-                                    )
-                                })
-                                .or(result.errors.filter(|e| !e.is_empty()).map(|e| {
-                                    DeviceEntryCardMsg::PingError(FrontendError::Graphql(e))
-                                }))
-                                .unwrap_or(DeviceEntryCardMsg::NoPing);
-                        scope.send_message(msg);
-                    }
-                    Err(e) => scope.send_message(DeviceEntryCardMsg::PingError(e)),
-                }
-            });
+            </div>
         }
     }
 }
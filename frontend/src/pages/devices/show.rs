@@ -1,19 +1,29 @@
 use crate::{
-    components::adjust_target::{AdjustTarget, SelectedCredentials, SelectedTarget},
+    components::{
+        adjust_target::{AdjustTarget, SelectedCredentials, SelectedTarget},
+        code_region::CodeRegion,
+        notification::Notifications,
+    },
+    data::SelectedInstance,
     error::FrontendError,
     graphql::{
-        authenticated::{DeviceOverview, device_overview},
-        query_authenticated_response,
+        authenticated::{ApplyDevicePlan, DeviceOverview, apply_device_plan, device_overview},
+        query_authenticated, query_authenticated_response,
     },
+    i18n::{tr, tr_args},
+    pages::routes::{AppRoute, DeviceView, RouteDevices, RouteRuns},
 };
-use patternfly_yew::prelude::{CodeBlock, CodeBlockCode, ExpandableSection};
-use yew::{Component, Context, Html, Properties, ToHtml, html, html::Scope, platform::spawn_local};
+use patternfly_yew::prelude::{Button, ButtonVariant, ExpandableSection};
+use yew::{Component, Context, Html, Properties, html, html::Scope, platform::spawn_local};
+use yew_nested_router::components::Link;
 
 pub struct ShowDevice {
     id: u32,
-    error: Option<FrontendError>,
     data: Option<ShowDeviceData>,
     alternate_target: SelectedTarget,
+    applying: bool,
+    /// the configuration is generated on request of the user, not by loading the page
+    announce_generated: bool,
 }
 #[derive(Debug, PartialEq)]
 pub struct ShowDeviceData {
@@ -34,6 +44,12 @@ pub enum ShowDeviceMessage {
         error: Option<FrontendError>,
     },
     AdjustTarget(SelectedTarget),
+    Apply,
+    Applied {
+        applied: u32,
+        error: Option<String>,
+    },
+    ApplyFailed(FrontendError),
 }
 
 impl Component for ShowDevice {
@@ -43,51 +59,110 @@ impl Component for ShowDevice {
     fn create(ctx: &Context<Self>) -> Self {
         Self {
             id: ctx.props().id,
-            error: None,
             data: None,
             alternate_target: Default::default(),
+            applying: false,
+            announce_generated: false,
         }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             ShowDeviceMessage::Error(e) => {
-                self.error = Some(e);
-                true
+                Notifications::of(ctx.link()).error(tr("device-generate-failed"), &e);
+                false
             }
             ShowDeviceMessage::Data { data, error } => {
+                let notifications = Notifications::of(ctx.link());
+                match error {
+                    Some(error) => notifications.error(
+                        tr_args(
+                            "device-generate-failed-of",
+                            &[("device", data.configured_name.as_ref().into())],
+                        ),
+                        &error,
+                    ),
+                    None if self.announce_generated => notifications.success(
+                        tr("device-generated"),
+                        tr_args(
+                            "device-update-ready",
+                            &[("device", data.configured_name.as_ref().into())],
+                        ),
+                    ),
+                    None => {}
+                }
+                self.announce_generated = false;
                 self.data = Some(data);
-                self.error = error;
                 true
             }
             ShowDeviceMessage::AdjustTarget(t) => {
                 if self.alternate_target != t {
                     self.alternate_target = t;
+                    self.announce_generated = true;
                     fetch_overview(ctx.link().clone(), self.id, self.alternate_target.clone());
                     true
                 } else {
                     false
                 }
             }
+            ShowDeviceMessage::Apply => {
+                if self.applying {
+                    return false;
+                }
+                self.applying = true;
+                apply_plan(ctx.link().clone(), self.id);
+                true
+            }
+            ShowDeviceMessage::Applied { applied, error } => {
+                self.applying = false;
+                let notifications = Notifications::of(ctx.link());
+                match error {
+                    Some(error) => notifications.failure(
+                        tr_args("device-apply-failed-after", &[("steps", applied.into())]),
+                        error,
+                    ),
+                    None => notifications.success(
+                        tr("device-plan-applied"),
+                        tr_args("device-steps-applied", &[("steps", applied.into())]),
+                    ),
+                }
+                fetch_overview(ctx.link().clone(), self.id, self.alternate_target.clone());
+                true
+            }
+            ShowDeviceMessage::ApplyFailed(error) => {
+                self.applying = false;
+                Notifications::of(ctx.link()).error(tr("device-apply-failed"), &error);
+                true
+            }
         }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
-        let error = self.error.as_ref().map(|e| e.to_html());
         let data = self.data.as_ref().map(|data| {
             html! {<dl>
-            <dt>{"Name"}</dt><dd>{ data.configured_name.as_ref() }</dd>
-            <dt>{"Current Name"}</dt><dd>{ data.current_name.as_ref() }</dd>
-            <dt>{"Update"}</dt><dd><CodeBlock><CodeBlockCode>{ data.cfg_update.as_ref() }</CodeBlockCode></CodeBlock></dd>
+            <dt>{tr("device-name")}</dt><dd>{ data.configured_name.as_ref() }</dd>
+            <dt>{tr("device-current-name")}</dt><dd>{ data.current_name.as_ref() }</dd>
+            <dt>{tr("device-update")}</dt><dd><CodeRegion label={tr_args("device-update-of", &[("device", data.configured_name.as_ref().into())])} code={data.cfg_update.to_string()}/></dd>
             </dl>}
         });
+        let runs = AppRoute::Runs(RouteRuns::Device { id: self.id });
+        let config = AppRoute::Devices(RouteDevices::Device {
+            id: self.id,
+            view: DeviceView::Config,
+        });
+        // the plan is applied through the management address with the configured credentials
+        let disabled = self.applying
+            || self.data.is_none()
+            || self.alternate_target != SelectedTarget::default();
         html! {
             <>
-            <ExpandableSection toggle_text_hidden="Change Target" toggle_text_expanded="Hide Target Selection">
+            <Link<AppRoute> to={runs}>{tr("device-runs")}</Link<AppRoute>>{" "}
+            <Link<AppRoute> to={config}>{tr("device-generated-config")}</Link<AppRoute>>
+            <ExpandableSection toggle_text_hidden={tr("device-change-target")} toggle_text_expanded={tr("device-hide-target")}>
                 <AdjustTarget onchange={ctx.link().callback(ShowDeviceMessage::AdjustTarget)} value={self.alternate_target.clone()} />
             </ExpandableSection>
-            {error}
             {data}
+            <Button variant={ButtonVariant::Primary} {disabled} onclick={ctx.link().callback(|_| ShowDeviceMessage::Apply)}>{tr("device-apply")}</Button>
             </>
         }
     }
@@ -115,6 +190,7 @@ fn fetch_overview(scope: Scope<ShowDevice>, id: u32, target: SelectedTarget) {
         match query_authenticated_response::<DeviceOverview, _>(
             scope.clone(),
             device_overview::Variables {
+                instance: SelectedInstance::of(&scope),
                 id: id as i64,
                 target: target.address.map(|a| a.to_string()),
                 credential_name,
@@ -166,3 +242,23 @@ fn fetch_overview(scope: Scope<ShowDevice>, id: u32, target: SelectedTarget) {
         }
     });
 }
+
+fn apply_plan(scope: Scope<ShowDevice>, id: u32) {
+    spawn_local(async move {
+        match query_authenticated::<ApplyDevicePlan, _>(
+            scope.clone(),
+            apply_device_plan::Variables {
+                instance: SelectedInstance::of(&scope),
+                id: id as i64,
+            },
+        )
+        .await
+        {
+            Ok(data) => scope.send_message(ShowDeviceMessage::Applied {
+                applied: data.apply_plan.applied as u32,
+                error: data.apply_plan.error,
+            }),
+            Err(e) => scope.send_message(ShowDeviceMessage::ApplyFailed(e)),
+        }
+    });
+}
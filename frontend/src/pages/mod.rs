@@ -1,117 +1,165 @@
+use crate::components::{keyboard::focus_page, search::SearchPalette};
 use crate::{
-    data::UserSessionData,
+    auth::{self, OidcSettings, Tokens},
+    data::{
+        Preferences, REFRESH_MARGIN_SECS, SelectedInstance, SessionRefresh, UiPreferences,
+        UserSessionData,
+    },
     error::FrontendError,
     graphql::{
         anonymous::{Settings, settings},
         query_anonymous,
     },
+    i18n::{self, tr},
 };
 use gloo::timers::callback::Timeout;
-use google_signin_client::{
-    ButtonType, DismissedReason, GsiButtonConfiguration, IdConfiguration, NotDisplayedReason,
-    PromptResult, initialize, prompt_async, render_button,
-};
-use log::{info, warn};
+use log::warn;
 use patternfly_yew::prelude::{
-    Alert, AlertGroup, AlertType, BackdropViewer, Nav, NavItem, NavRouterItem, Page, PageSidebar,
-    ToastViewer,
+    BackdropViewer, Button, ButtonVariant, Page, PageSidebar, ToastViewer,
 };
 use routes::{AppRoute, Sidebar};
+use status::{StatusPage, is_status_page};
 use std::time::Duration;
-use web_sys::HtmlElement;
 use yew::{
-    Context, ContextProvider, Html, NodeRef, Properties, ToHtml, function_component, html,
-    html_nested, platform::spawn_local,
+    Callback, Context, ContextProvider, Html, ToHtml, function_component, html, html_nested,
+    platform::spawn_local, use_effect_with, use_mut_ref, use_node_ref, use_state,
+};
+use yew_nested_router::{
+    Router,
+    prelude::{Switch as RouterSwitch, use_router},
 };
-use yew_nested_router::{Router, prelude::Switch as RouterSwitch};
 
 pub mod devices;
+pub mod onboarding;
 pub mod routes;
+pub mod runs;
+pub mod status;
+pub mod vlans;
+pub mod wlan;
 
 #[derive(Debug)]
 pub struct App {
-    client_id: Option<String>,
+    settings: Option<OidcSettings>,
     user_session: UserSessionData,
-    error_state: Option<ErrorState>,
-    login_button_ref: NodeRef,
+    error_state: Option<FrontendError>,
     running_timeout: Option<Timeout>,
+    /// handed to the pages to report renewed tokens, created once to keep the context stable
+    renewed: Callback<Tokens>,
+    /// the refresh token was rejected, the session ends when the token expires
+    refresh_failed: bool,
 }
-#[derive(Debug)]
-enum ErrorState {
-    FrontendError(FrontendError),
-    PromptResult(PromptResult),
-}
-
-#[derive(Properties, PartialEq)]
-pub struct Props {}
 
 #[derive(Debug)]
 pub enum AppMessage {
-    ClientIdReceived(String),
-    TokenReceived(String),
+    SettingsReceived(OidcSettings),
+    TokenReceived(Tokens),
     ClientError(FrontendError),
-    LoginFailed(PromptResult),
+    Login,
+    Refresh,
+    RefreshFailed,
     CheckSession,
 }
 impl yew::Component for App {
     type Message = AppMessage;
     type Properties = ();
-    fn create(_ctx: &Context<Self>) -> Self {
-        Self {
-            client_id: None,
-            user_session: Default::default(),
+    fn create(ctx: &Context<Self>) -> Self {
+        let mut app = Self {
+            settings: None,
+            user_session: auth::stored_tokens()
+                .map(UserSessionData::from_tokens)
+                .unwrap_or_default(),
             error_state: None,
-            login_button_ref: NodeRef::default(),
             running_timeout: None,
-        }
+            renewed: ctx.link().callback(AppMessage::TokenReceived),
+            refresh_failed: false,
+        };
+        app.schedule_renewal(ctx);
+        app
     }
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
-            AppMessage::TokenReceived(token) => {
-                self.user_session = UserSessionData::from_token(token);
+            AppMessage::TokenReceived(tokens) => {
+                let session = UserSessionData::from_tokens(tokens);
+                self.refresh_failed = false;
                 self.error_state = None;
-                self.user_session.is_token_valid()
+                let changed = session != self.user_session;
+                self.user_session = session;
+                self.schedule_renewal(ctx);
+                changed
+            }
+            AppMessage::SettingsReceived(settings) => {
+                let scope = ctx.link().clone();
+                self.settings = Some(settings.clone());
+                self.schedule_renewal(ctx);
+                spawn_local(async move {
+                    match auth::complete_login(&settings).await {
+                        Ok(Some(token)) => scope.send_message(AppMessage::TokenReceived(token)),
+                        Ok(None) => scope.send_message(AppMessage::CheckSession),
+                        Err(error) => scope.send_message(AppMessage::ClientError(error)),
+                    }
+                });
+                false
+            }
+            AppMessage::ClientError(error) => {
+                self.error_state = Some(error);
+                true
             }
-            AppMessage::ClientIdReceived(client_id) => {
-                self.client_id = Some(client_id);
+            AppMessage::Login => {
                 self.error_state = None;
+                if let Some(settings) = &self.settings {
+                    if let Err(error) = auth::start_login(settings) {
+                        self.error_state = Some(error);
+                    }
+                }
                 true
             }
-            AppMessage::ClientError(error) => {
-                self.error_state = Some(ErrorState::FrontendError(error));
+            AppMessage::Refresh => {
+                let (Some(settings), Some(refresh_token)) = (
+                    self.settings.clone(),
+                    self.user_session.refresh_token().map(String::from),
+                ) else {
+                    self.refresh_failed = true;
+                    return true;
+                };
+                let scope = ctx.link().clone();
+                spawn_local(async move {
+                    match auth::refresh(&settings, &refresh_token).await {
+                        Ok(tokens) => scope.send_message(AppMessage::TokenReceived(tokens)),
+                        Err(error) => {
+                            warn!("Cannot refresh token: {error}");
+                            scope.send_message(AppMessage::RefreshFailed);
+                        }
+                    }
+                });
+                false
+            }
+            AppMessage::RefreshFailed => {
+                self.refresh_failed = true;
+                self.schedule_renewal(ctx);
+                ctx.link().send_message(AppMessage::CheckSession);
                 true
             }
-            AppMessage::LoginFailed(prompt) => {
-                self.error_state = Some(ErrorState::PromptResult(prompt));
+            AppMessage::CheckSession => {
+                // an expired session is renewed by the refresh token, e.g. one stored before a
+                // reload, and only by a new login at the issuer if that fails
+                if !self.user_session.is_token_valid() && self.error_state.is_none() {
+                    if self.user_session.can_refresh() && !self.refresh_failed {
+                        ctx.link().send_message(AppMessage::Refresh);
+                    } else {
+                        ctx.link().send_message(AppMessage::Login);
+                    }
+                }
                 true
             }
-            AppMessage::CheckSession => self.user_session.is_token_valid(),
         }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
-        let user_session = &self.user_session;
-        if let (Some(client_id), false) = (&self.client_id, user_session.is_token_valid()) {
-            if self.error_state.is_none() {
-                let mut configuration = IdConfiguration::new(client_id.clone());
-                //configuration.set_auto_select(true);
-                let link = ctx.link().clone();
-                configuration.set_callback(Box::new(move |response| {
-                    info!("Got token response: {}", response.credential());
-                    link.send_message(AppMessage::TokenReceived(response.credential().to_string()));
-                }));
-                let link = ctx.link().clone();
-                initialize(configuration);
-                spawn_local(async move {
-                    let result = prompt_async().await;
-                    if result != PromptResult::Dismissed(DismissedReason::CredentialReturned) {
-                        link.send_message(AppMessage::LoginFailed(result))
-                    }
-                });
-                //prompt(Some(Box::new(|not| info!("Notification: {not:?}"))))
-            }
+        // the status page for wall displays needs no login
+        if is_status_page() {
+            return html! {<StatusPage/>};
         }
-        let context = user_session.clone();
+        let context = self.session_context();
         if context.is_token_valid() {
             html! {
             <ContextProvider<UserSessionData> {context}>
@@ -121,88 +169,43 @@ impl yew::Component for App {
             </ContextProvider<UserSessionData>>
             }
         } else if let Some(error) = &self.error_state {
-            let error_message = match error {
-                ErrorState::FrontendError(e) => e.to_html(),
-                ErrorState::PromptResult(PromptResult::NotDisplayed) => {
-                    html! {
-                        <AlertGroup>
-                            <Alert inline=true title="Not Displayed" r#type={AlertType::Danger}>{"Prompt was not displayed to the user"}</Alert>
-                        </AlertGroup>
-                    }
-                }
-                ErrorState::PromptResult(PromptResult::Skipped) => {
-                    html! {
-                        <AlertGroup>
-                            <Alert inline=true title="Skipped" r#type={AlertType::Danger}>{"Prompt skipped by the user"}</Alert>
-                        </AlertGroup>
-                    }
+            let login = self.settings.is_some().then(|| {
+                html! {
+                    <Button variant={ButtonVariant::Primary} onclick={ctx.link().callback(|_| AppMessage::Login)}>{tr("app-login")}</Button>
                 }
-                ErrorState::PromptResult(PromptResult::Dismissed(reason)) => {
-                    html! {
-                        <AlertGroup>
-                            <Alert inline=true title="Dismissed" r#type={AlertType::Danger}>{format!("{reason:?}")}</Alert>
-                        </AlertGroup>
-                    }
-                }
-            };
+            });
             html! {
                 <>
-                    {error_message}
-                    <div ref={self.login_button_ref.clone()}></div>
+                    {error.to_html()}
+                    {login}
                 </>
             }
         } else {
             html! {
-                <h1>{"Logging in"}</h1>
+                <h1>{tr("app-logging-in")}</h1>
             }
         }
     }
     fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
-        if let Some(login_button_ref) = self.login_button_ref.cast::<HtmlElement>() {
-            render_button(
-                login_button_ref,
-                GsiButtonConfiguration::new(ButtonType::Standard),
-            );
-        }
-        if self.user_session.is_token_valid() {
-            if let Some(valid_until) = self.user_session.valid_until() {
-                if let Some(timer) = self.running_timeout.take() {
-                    timer.cancel();
-                };
-                self.running_timeout = {
-                    let link = ctx.link().clone();
-                    let valid_until =
-                        wasm_timer::SystemTime::UNIX_EPOCH + Duration::new(*valid_until, 0);
-
-                    if let Ok(valid_time) =
-                        valid_until.duration_since(wasm_timer::SystemTime::now())
-                    {
-                        let duration = valid_time.as_millis();
-                        Some(Timeout::new((duration as u32).max(2000), move || {
-                            link.send_message(AppMessage::CheckSession)
-                        }))
-                    } else {
-                        None
-                    }
-                };
-                /*
-                self.timeout = Some(handle);
-
-                self.messages.clear();
-
-                self.messages.push("Timer started!");
-                self.console_timer = Some(Timer::new("Timer"));*/
-            }
-        }
-        if first_render {
+        // without the settings no login is started
+        if first_render && !is_status_page() {
             let scope = ctx.link().clone();
             spawn_local(async move {
                 let result = query_anonymous::<Settings>(settings::Variables {}).await;
                 match result {
                     Ok(settings::ResponseData {
-                        authentication: settings::SettingsAuthentication { client_id },
+                        authentication:
+                            settings::SettingsAuthentication {
+                                client_id,
+                                auth_url,
+                                token_url,
+                            },
                     }) => {
-                        scope.send_message(AppMessage::ClientIdReceived(client_id));
+                        scope.send_message(AppMessage::SettingsReceived(OidcSettings {
+                            client_id,
+                            auth_url,
+                            token_url,
+                        }));
                     }
                     Err(err) => {
                         warn!("Error on server {err:?}");
@@ -213,17 +216,118 @@ impl yew::Component for App {
         }
     }
 }
+impl App {
+    /// session handed to the pages, renewing its tokens through the app
+    fn session_context(&self) -> UserSessionData {
+        match &self.settings {
+            Some(settings) => self.user_session.clone().with_refresh(SessionRefresh {
+                settings: settings.clone(),
+                renewed: self.renewed.clone(),
+            }),
+            None => self.user_session.clone(),
+        }
+    }
+    /// replaces the timer by one at the expiry of the current token: it is renewed shortly
+    /// before, or the session ends when it cannot be renewed
+    fn schedule_renewal(&mut self, ctx: &Context<Self>) {
+        if let Some(timer) = self.running_timeout.take() {
+            timer.cancel();
+        }
+        if !self.user_session.is_token_valid() {
+            return;
+        }
+        let Some(valid_until) = self.user_session.valid_until() else {
+            return;
+        };
+        let (message, check_at) = if self.user_session.can_refresh() && !self.refresh_failed {
+            (
+                AppMessage::Refresh,
+                valid_until.saturating_sub(REFRESH_MARGIN_SECS),
+            )
+        } else {
+            (AppMessage::CheckSession, *valid_until)
+        };
+        let link = ctx.link().clone();
+        let check_at = wasm_timer::SystemTime::UNIX_EPOCH + Duration::new(check_at, 0);
+        let duration = check_at
+            .duration_since(wasm_timer::SystemTime::now())
+            .unwrap_or_default()
+            .as_millis();
+        self.running_timeout = Some(Timeout::new((duration as u32).max(2000), move || {
+            link.send_message(message)
+        }));
+    }
+}
+
 #[function_component(MainPage)]
 fn main_page() -> Html {
+    let preferences = use_state(UiPreferences::load);
+    use_effect_with((preferences.dark_mode, preferences.high_contrast), {
+        let preferences = preferences.clone();
+        move |_| preferences.apply_theme()
+    });
+    // the focus moves to the new page, it would stay on the link of the navigation otherwise
+    let content = use_node_ref();
+    let route = use_router::<AppRoute>().and_then(|router| router.active_target);
+    let first_route = use_mut_ref(|| true);
+    use_effect_with(route, {
+        let content = content.clone();
+        move |_| {
+            if !first_route.replace(false) {
+                focus_page(&content);
+            }
+        }
+    });
+    let update = {
+        let preferences = preferences.clone();
+        Callback::from(move |values: UiPreferences| {
+            values.store();
+            i18n::set_language(values.language());
+            preferences.set(values);
+        })
+    };
+    let context = SelectedInstance {
+        name: preferences.instance.clone(),
+        select: {
+            let preferences = preferences.clone();
+            let update = update.clone();
+            Callback::from(move |name| {
+                update.emit(UiPreferences {
+                    instance: Some(name),
+                    // sites are not shared between the instances
+                    site: None,
+                    ..(*preferences).clone()
+                })
+            })
+        },
+    };
+    let preferences = Preferences {
+        values: (*preferences).clone(),
+        update,
+    };
+    // the pages are recreated to reload their data from the selected instance and to render
+    // their texts in the selected language
+    let key = format!(
+        "{}-{:?}",
+        preferences.values.instance.as_deref().unwrap_or_default(),
+        preferences.values.language()
+    );
     html! {
-        <BackdropViewer>
-            <ToastViewer>
-                    <Page sidebar={html_nested! {<PageSidebar><Sidebar/></PageSidebar>}}>
-                        <RouterSwitch<AppRoute>
-                            render = { AppRoute::content}
-                        />
-                    </Page>
-            </ToastViewer>
-        </BackdropViewer>
+        <ContextProvider<Preferences> context={preferences}>
+        <ContextProvider<SelectedInstance> {context}>
+            <BackdropViewer>
+                <ToastViewer>
+                        <SearchPalette/>
+                        <Page sidebar={html_nested! {<PageSidebar><Sidebar/></PageSidebar>}}>
+                            <div {key} ref={content} class="page-content" tabindex="-1">
+                                <RouterSwitch<AppRoute>
+                                    render = { AppRoute::content}
+                                />
+                            </div>
+                        </Page>
+                </ToastViewer>
+            </BackdropViewer>
+        </ContextProvider<SelectedInstance>>
+        </ContextProvider<Preferences>>
     }
 }
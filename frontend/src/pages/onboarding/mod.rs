@@ -0,0 +1,821 @@
+use crate::{
+    components::{
+        adjust_target::{AdjustTarget, SelectedCredentials, SelectedTarget},
+        code_region::CodeRegion,
+        keyboard::focus,
+        notification::Notifications,
+    },
+    data::SelectedInstance,
+    error::FrontendError,
+    graphql::{
+        authenticated::{
+            OnboardingApply, OnboardingBootstrapScript, OnboardingDetect, OnboardingDevices,
+            OnboardingPreview, onboarding_apply, onboarding_bootstrap_script, onboarding_detect,
+            onboarding_devices, onboarding_preview,
+        },
+        query_authenticated, query_authenticated_response,
+    },
+    i18n::{tr, tr_args},
+    pages::{
+        devices::config::copy_to_clipboard,
+        routes::{AppRoute, DeviceView, RouteDevices, RouteRuns},
+    },
+};
+use gloo::file::{Blob, ObjectUrl};
+use patternfly_yew::prelude::{
+    Button, ButtonVariant, Card, CardBody, CardHeader, CardTitle, SimpleSelect, Spinner, Switch,
+};
+use std::{fmt::Display, net::IpAddr, str::FromStr};
+use yew::{Component, Context, Html, NodeRef, html, html::Scope, platform::spawn_local};
+use yew_nested_router::components::Link;
+
+/// Onboarding of a device from netbox step by step: the device is reached on the given target,
+/// its hardware is detected and the generated configuration is previewed before it is applied.
+/// Planned devices are not applied, they get the bootstrap script for the detected model.
+pub struct Onboarding {
+    step: Step,
+    devices: Fetch<Box<[OnboardingDevice]>>,
+    device: Option<OnboardingDevice>,
+    target: SelectedTarget,
+    detection: Fetch<Option<Detection>>,
+    preview: Fetch<Option<PreviewData>>,
+    /// download link of the previewed script, revoked when replaced
+    download: Option<ObjectUrl>,
+    outcome: Fetch<ApplyOutcome>,
+    /// the user accepted that the apply cuts the management path shown in the preview
+    confirm_cut: bool,
+    /// counts the requests, an answer to an older request belongs to another device or target
+    /// and is dropped
+    generation: u32,
+    /// title of the current step, focused when the step changes so screen readers announce it
+    step_title: NodeRef,
+    focus_step: bool,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Step {
+    SelectDevice,
+    Target,
+    Detect,
+    Preview,
+    Apply,
+}
+const STEPS: [Step; 5] = [
+    Step::SelectDevice,
+    Step::Target,
+    Step::Detect,
+    Step::Preview,
+    Step::Apply,
+];
+impl Step {
+    fn next(self) -> Option<Self> {
+        STEPS.into_iter().find(|step| *step > self)
+    }
+    fn previous(self) -> Option<Self> {
+        STEPS.into_iter().rev().find(|step| *step < self)
+    }
+}
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&tr(match self {
+            Step::SelectDevice => "onboarding-step-device",
+            Step::Target => "onboarding-step-target",
+            Step::Detect => "onboarding-step-hardware",
+            Step::Preview => "onboarding-step-preview",
+            Step::Apply => "onboarding-step-apply",
+        }))
+    }
+}
+enum Fetch<T> {
+    Idle,
+    Loading,
+    Done(T),
+    Failed(FrontendError),
+}
+/// device of netbox to onboard, entries with the same id are equal
+#[derive(Debug, Clone)]
+pub struct OnboardingDevice {
+    id: u32,
+    name: Box<str>,
+    planned: bool,
+    serial: Option<Box<str>>,
+    address: Option<IpAddr>,
+}
+impl PartialEq for OnboardingDevice {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+impl Display for OnboardingDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.planned {
+            f.write_str(&tr_args(
+                "onboarding-planned-device",
+                &[("device", self.name.as_ref().into())],
+            ))
+        } else {
+            f.write_str(&self.name)
+        }
+    }
+}
+#[derive(Debug)]
+pub struct Detection {
+    device_type: Box<str>,
+    firmware_type: Box<str>,
+    serial: Box<str>,
+    version: Box<str>,
+    unknown_model: bool,
+}
+#[derive(Debug)]
+pub struct PreviewData {
+    script: Box<str>,
+    management_cuts: Box<[Box<str>]>,
+    warnings: Box<[Box<str>]>,
+}
+#[derive(Debug)]
+pub struct ApplyOutcome {
+    applied: u32,
+    error: Option<Box<str>>,
+    converged: bool,
+    verify_error: Option<Box<str>>,
+}
+#[derive(Debug)]
+pub enum OnboardingMessage {
+    Devices(Box<[OnboardingDevice]>),
+    DevicesFailed(FrontendError),
+    SelectDevice(OnboardingDevice),
+    AdjustTarget(SelectedTarget),
+    Goto(Step),
+    Detected(u32, Option<Detection>),
+    DetectionFailed(u32, FrontendError),
+    Previewed(u32, Option<PreviewData>),
+    PreviewFailed(u32, FrontendError),
+    ConfirmCut(bool),
+    Apply,
+    Applied(u32, ApplyOutcome),
+    ApplyFailed(u32, FrontendError),
+    Copy,
+}
+
+impl Component for Onboarding {
+    type Message = OnboardingMessage;
+    type Properties = ();
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            step: Step::SelectDevice,
+            devices: Fetch::Loading,
+            device: None,
+            target: SelectedTarget::default(),
+            detection: Fetch::Idle,
+            preview: Fetch::Idle,
+            download: None,
+            outcome: Fetch::Idle,
+            confirm_cut: false,
+            generation: 0,
+            step_title: NodeRef::default(),
+            focus_step: false,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match &msg {
+            OnboardingMessage::Detected(generation, _)
+            | OnboardingMessage::DetectionFailed(generation, _)
+            | OnboardingMessage::Previewed(generation, _)
+            | OnboardingMessage::PreviewFailed(generation, _)
+            | OnboardingMessage::Applied(generation, _)
+            | OnboardingMessage::ApplyFailed(generation, _)
+                if *generation != self.generation =>
+            {
+                return false;
+            }
+            _ => {}
+        }
+        match msg {
+            OnboardingMessage::Devices(devices) => self.devices = Fetch::Done(devices),
+            OnboardingMessage::DevicesFailed(error) => self.devices = Fetch::Failed(error),
+            OnboardingMessage::SelectDevice(device) => {
+                if self.device.as_ref() == Some(&device) {
+                    return false;
+                }
+                self.device = Some(device);
+                self.reset_results();
+            }
+            OnboardingMessage::AdjustTarget(target) => {
+                if self.target == target {
+                    return false;
+                }
+                self.target = target;
+                self.reset_results();
+            }
+            OnboardingMessage::Goto(step) => {
+                // the data of a step is fetched again when it is entered
+                self.step = step;
+                self.focus_step = true;
+                match step {
+                    Step::Detect => self.detect(ctx),
+                    Step::Preview => self.preview(ctx),
+                    Step::Apply => self.outcome = Fetch::Idle,
+                    Step::SelectDevice | Step::Target => {}
+                }
+            }
+            OnboardingMessage::Detected(_, detection) => self.detection = Fetch::Done(detection),
+            OnboardingMessage::DetectionFailed(_, error) => self.detection = Fetch::Failed(error),
+            OnboardingMessage::Previewed(_, preview) => {
+                self.download = preview.as_ref().map(|preview| {
+                    ObjectUrl::from(Blob::new_with_options(
+                        preview.script.as_ref(),
+                        Some("text/plain"),
+                    ))
+                });
+                self.preview = Fetch::Done(preview);
+            }
+            OnboardingMessage::PreviewFailed(_, error) => self.preview = Fetch::Failed(error),
+            OnboardingMessage::ConfirmCut(confirm_cut) => self.confirm_cut = confirm_cut,
+            OnboardingMessage::Apply => {
+                if matches!(self.outcome, Fetch::Loading) {
+                    return false;
+                }
+                self.apply(ctx);
+            }
+            OnboardingMessage::Applied(_, outcome) => {
+                let notifications = Notifications::of(ctx.link());
+                match &outcome.error {
+                    Some(error) => notifications.failure(
+                        tr_args(
+                            "device-apply-failed-after",
+                            &[("steps", outcome.applied.into())],
+                        ),
+                        error.to_string(),
+                    ),
+                    None => notifications.success(
+                        tr("device-plan-applied"),
+                        tr_args("device-steps-applied", &[("steps", outcome.applied.into())]),
+                    ),
+                }
+                self.outcome = Fetch::Done(outcome);
+            }
+            OnboardingMessage::ApplyFailed(_, error) => {
+                Notifications::of(ctx.link()).error(tr("device-apply-failed"), &error);
+                self.outcome = Fetch::Failed(error);
+            }
+            OnboardingMessage::Copy => {
+                if let (Some(device), Fetch::Done(Some(preview))) = (&self.device, &self.preview) {
+                    copy_to_clipboard(ctx.link(), &device.name, &preview.script);
+                }
+                return false;
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let steps = STEPS.into_iter().map(|step| {
+            let class = if step == self.step {
+                "onboarding-step-current"
+            } else if step < self.step {
+                "onboarding-step-done"
+            } else {
+                ""
+            };
+            let current = (step == self.step).then_some("step");
+            html! {<li {class} aria-current={current}>{step.to_string()}</li>}
+        });
+        let content = match self.step {
+            Step::SelectDevice => self.view_select_device(ctx),
+            Step::Target => self.view_target(ctx),
+            Step::Detect => self.view_detection(),
+            Step::Preview => self.view_preview(ctx),
+            Step::Apply => self.view_apply(ctx),
+        };
+        let back = self.step.previous().map(|step| {
+            let disabled = matches!(self.outcome, Fetch::Loading);
+            html! {
+                <Button variant={ButtonVariant::Secondary} {disabled} onclick={ctx.link().callback(move |_| OnboardingMessage::Goto(step))}>{tr("onboarding-back")}</Button>
+            }
+        });
+        let next = self.step.next().map(|step| {
+            let disabled = !self.completed();
+            html! {
+                <Button variant={ButtonVariant::Primary} {disabled} onclick={ctx.link().callback(move |_| OnboardingMessage::Goto(step))}>{tr("onboarding-next")}</Button>
+            }
+        });
+        html! {
+            <>
+            <h1>{tr("onboarding-title")}</h1>
+            <nav aria-label={tr("onboarding-steps")}>
+                <ol class="onboarding-steps">{for steps}</ol>
+            </nav>
+            <Card>
+                <CardHeader><CardTitle>
+                    <h2 class="onboarding-step-title" ref={self.step_title.clone()} tabindex="-1">{self.step.to_string()}</h2>
+                </CardTitle></CardHeader>
+                <CardBody>{content}</CardBody>
+            </Card>
+            <div class="config-actions">{back}{next}</div>
+            </>
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            fetch_devices(ctx.link().clone());
+        }
+        if self.focus_step {
+            self.focus_step = false;
+            focus(&self.step_title);
+        }
+    }
+}
+
+impl Onboarding {
+    /// the results depend on the device and the target
+    fn reset_results(&mut self) {
+        self.detection = Fetch::Idle;
+        self.preview = Fetch::Idle;
+        self.download = None;
+        self.outcome = Fetch::Idle;
+        self.confirm_cut = false;
+        self.generation += 1;
+    }
+    /// the current step is completed and the next one can be entered
+    fn completed(&self) -> bool {
+        match self.step {
+            Step::SelectDevice => self.device.is_some(),
+            Step::Target => true,
+            Step::Detect => matches!(self.detection, Fetch::Done(Some(_))),
+            Step::Preview => matches!(self.preview, Fetch::Done(Some(_))),
+            Step::Apply => false,
+        }
+    }
+    fn detect(&mut self, ctx: &Context<Self>) {
+        let Some(device) = &self.device else {
+            return;
+        };
+        self.detection = Fetch::Loading;
+        self.generation += 1;
+        let generation = self.generation;
+        let scope = ctx.link().clone();
+        let id = device.id as i64;
+        let target = self.target.clone();
+        spawn_local(async move {
+            let (credential_name, adhoc_credentials) = credential_variables(target.credentials);
+            match query_authenticated_response::<OnboardingDetect, _>(
+                scope.clone(),
+                onboarding_detect::Variables {
+                    instance: SelectedInstance::of(&scope),
+                    id,
+                    target: target.address.map(|a| a.to_string()),
+                    credential_name,
+                    adhoc_credentials: adhoc_credentials.map(|(username, password)| {
+                        onboarding_detect::AdhocCredentials { username, password }
+                    }),
+                },
+            )
+            .await
+            {
+                Ok(response) => {
+                    let access = response
+                        .data
+                        .and_then(|data| data.topology.device_by_id)
+                        .and_then(|device| device.access);
+                    let msg = match (access, response.errors.filter(|e| !e.is_empty())) {
+                        (Some(access), _) => {
+                            let routerboard = access.device_stats.routerboard;
+                            OnboardingMessage::Detected(
+                                generation,
+                                Some(Detection {
+                                    device_type: routerboard.device_type.into_boxed_str(),
+                                    firmware_type: routerboard.firmware_type.into_boxed_str(),
+                                    serial: routerboard.serial_number.into_boxed_str(),
+                                    version: match access.config.version {
+                                        onboarding_detect::RouterOsVersion::V6 => "6".into(),
+                                        onboarding_detect::RouterOsVersion::V7 => "7".into(),
+                                        onboarding_detect::RouterOsVersion::Other(version) => {
+                                            version.into_boxed_str()
+                                        }
+                                    },
+                                    unknown_model: access.config.unknown_model,
+                                }),
+                            )
+                        }
+                        (None, Some(errors)) => OnboardingMessage::DetectionFailed(
+                            generation,
+                            FrontendError::Graphql(errors),
+                        ),
+                        (None, None) => OnboardingMessage::Detected(generation, None),
+                    };
+                    scope.send_message(msg);
+                }
+                Err(error) => {
+                    scope.send_message(OnboardingMessage::DetectionFailed(generation, error))
+                }
+            }
+        });
+    }
+    fn preview(&mut self, ctx: &Context<Self>) {
+        let (Some(device), Fetch::Done(Some(detection))) = (&self.device, &self.detection) else {
+            return;
+        };
+        self.preview = Fetch::Loading;
+        self.download = None;
+        self.confirm_cut = false;
+        self.generation += 1;
+        let scope = ctx.link().clone();
+        let id = device.id as i64;
+        if device.planned {
+            fetch_bootstrap_script(
+                scope,
+                self.generation,
+                id,
+                detection.device_type.to_string(),
+            );
+        } else {
+            fetch_plan(scope, self.generation, id, self.target.clone());
+        }
+    }
+    fn apply(&mut self, ctx: &Context<Self>) {
+        let (Some(device), Fetch::Done(Some(preview))) = (&self.device, &self.preview) else {
+            return;
+        };
+        // the cuts of the management path shown in the preview must be accepted explicitly
+        if !preview.management_cuts.is_empty() && !self.confirm_cut {
+            return;
+        }
+        self.outcome = Fetch::Loading;
+        self.generation += 1;
+        let generation = self.generation;
+        let scope = ctx.link().clone();
+        let id = device.id as i64;
+        let target = self.target.clone();
+        let force = Some(self.confirm_cut);
+        spawn_local(async move {
+            let (credential_name, adhoc_credentials) = credential_variables(target.credentials);
+            match query_authenticated::<OnboardingApply, _>(
+                scope.clone(),
+                onboarding_apply::Variables {
+                    instance: SelectedInstance::of(&scope),
+                    id,
+                    force,
+                    target: target.address.map(|a| a.to_string()),
+                    credential_name,
+                    adhoc_credentials: adhoc_credentials.map(|(username, password)| {
+                        onboarding_apply::AdhocCredentials { username, password }
+                    }),
+                },
+            )
+            .await
+            {
+                Ok(data) => scope.send_message(OnboardingMessage::Applied(
+                    generation,
+                    ApplyOutcome {
+                        applied: data.apply_plan.applied as u32,
+                        error: data.apply_plan.error.map(String::into_boxed_str),
+                        converged: data.apply_plan.converged,
+                        verify_error: data.apply_plan.verify_error.map(String::into_boxed_str),
+                    },
+                )),
+                Err(error) => scope.send_message(OnboardingMessage::ApplyFailed(generation, error)),
+            }
+        });
+    }
+
+    fn view_select_device(&self, ctx: &Context<Self>) -> Html {
+        match &self.devices {
+            Fetch::Idle | Fetch::Loading => html! {<Spinner/>},
+            Fetch::Failed(error) => error.to_html(),
+            Fetch::Done(devices) => {
+                let entries = devices.to_vec();
+                let selected = self.device.clone();
+                let onselect = ctx.link().callback(OnboardingMessage::SelectDevice);
+                let details = self.device.as_ref().map(|device| {
+                    let address = device
+                        .address
+                        .map(|address| address.to_string())
+                        .unwrap_or_else(|| tr("onboarding-no-address"));
+                    html! {<dl>
+                    <dt>{tr("onboarding-management-address")}</dt><dd>{address}</dd>
+                    <dt>{tr("onboarding-serial")}</dt><dd>{device.serial.as_deref().unwrap_or_default()}</dd>
+                    </dl>}
+                });
+                html! {
+                    <>
+                    <SimpleSelect<OnboardingDevice> {selected} {entries} {onselect}/>
+                    {details}
+                    </>
+                }
+            }
+        }
+    }
+    fn view_target(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <>
+            <p>{tr("onboarding-target-help")}</p>
+            <AdjustTarget onchange={ctx.link().callback(OnboardingMessage::AdjustTarget)} value={self.target.clone()} />
+            </>
+        }
+    }
+    fn view_detection(&self) -> Html {
+        match &self.detection {
+            Fetch::Idle | Fetch::Loading => html! {<Spinner/>},
+            Fetch::Failed(error) => error.to_html(),
+            Fetch::Done(None) => {
+                html! {<p>{tr("onboarding-unreachable")}</p>}
+            }
+            Fetch::Done(Some(detection)) => {
+                // the serial of netbox is compared to catch a device plugged in by mistake
+                let serial_mismatch = self
+                    .device
+                    .as_ref()
+                    .and_then(|device| device.serial.as_deref())
+                    .filter(|serial| !serial.is_empty() && *serial != detection.serial.as_ref())
+                    .map(|serial| {
+                        html! {<p class="run-failed">{tr_args("onboarding-serial-mismatch", &[("serial", serial.into())])}</p>}
+                    });
+                let unknown_model = detection.unknown_model.then(|| {
+                    html! {<p>{tr("onboarding-unknown-model")}</p>}
+                });
+                html! {
+                    <>
+                    <dl>
+                    <dt>{tr("onboarding-model")}</dt><dd>{detection.device_type.as_ref()}</dd>
+                    <dt>{tr("onboarding-serial")}</dt><dd>{detection.serial.as_ref()}</dd>
+                    <dt>{tr("onboarding-firmware")}</dt><dd>{detection.firmware_type.as_ref()}</dd>
+                    <dt>{tr("onboarding-routeros")}</dt><dd>{detection.version.as_ref()}</dd>
+                    </dl>
+                    {serial_mismatch}
+                    {unknown_model}
+                    </>
+                }
+            }
+        }
+    }
+    fn view_preview(&self, ctx: &Context<Self>) -> Html {
+        match &self.preview {
+            Fetch::Idle | Fetch::Loading => html! {<Spinner/>},
+            Fetch::Failed(error) => error.to_html(),
+            Fetch::Done(None) => {
+                html! {<p>{tr("onboarding-unreachable")}</p>}
+            }
+            Fetch::Done(Some(preview)) => {
+                let list = |title: &str, entries: &[Box<str>]| {
+                    (!entries.is_empty()).then(|| {
+                        let entries = entries
+                            .iter()
+                            .map(|entry| html! {<li>{entry.as_ref()}</li>});
+                        html! {<><h3>{title}</h3><ul>{for entries}</ul></>}
+                    })
+                };
+                let body = if preview.script.is_empty() {
+                    html! {<p>{tr("config-up-to-date")}</p>}
+                } else {
+                    let label = self
+                        .device
+                        .as_ref()
+                        .map(|device| {
+                            tr_args("config-title", &[("device", device.name.as_ref().into())])
+                        })
+                        .unwrap_or_default();
+                    html! {<CodeRegion {label} code={preview.script.to_string()}/>}
+                };
+                html! {
+                    <>
+                    {list(&tr("onboarding-management-cuts"), &preview.management_cuts)}
+                    {list(&tr("onboarding-warnings"), &preview.warnings)}
+                    {self.script_actions(ctx)}
+                    {body}
+                    </>
+                }
+            }
+        }
+    }
+    fn view_apply(&self, ctx: &Context<Self>) -> Html {
+        let (Some(device), Fetch::Done(Some(preview))) = (&self.device, &self.preview) else {
+            return Html::default();
+        };
+        let links = {
+            let show = AppRoute::Devices(RouteDevices::Device {
+                id: device.id,
+                view: DeviceView::Show,
+            });
+            let runs = AppRoute::Runs(RouteRuns::Device { id: device.id });
+            html! {
+                <p>
+                <Link<AppRoute> to={show}>{tr("onboarding-device")}</Link<AppRoute>>{" "}
+                <Link<AppRoute> to={runs}>{tr("device-runs")}</Link<AppRoute>>
+                </p>
+            }
+        };
+        // planned devices are only bootstrapped with the generated script
+        if device.planned {
+            return html! {
+                <>
+                <p>{tr_args("onboarding-bootstrap-help", &[("device", device.name.as_ref().into())])}</p>
+                {self.script_actions(ctx)}
+                {links}
+                </>
+            };
+        }
+        let outcome = match &self.outcome {
+            Fetch::Idle => None,
+            Fetch::Loading => Some(html! {<Spinner/>}),
+            Fetch::Failed(error) => Some(error.to_html()),
+            Fetch::Done(outcome) => {
+                let (class, state) = match (&outcome.error, outcome.converged) {
+                    (Some(error), _) => (
+                        "run-failed",
+                        tr_args("onboarding-failed", &[("error", error.as_ref().into())]),
+                    ),
+                    (None, true) => ("run-succeeded", tr("onboarding-converged")),
+                    (None, false) => (
+                        "run-failed",
+                        tr_args(
+                            "onboarding-not-converged",
+                            &[(
+                                "error",
+                                outcome.verify_error.as_deref().unwrap_or_default().into(),
+                            )],
+                        ),
+                    ),
+                };
+                Some(html! {
+                    <dl>
+                    <dt>{tr("run-applied-steps")}</dt><dd>{outcome.applied}</dd>
+                    <dt>{tr("onboarding-state")}</dt><dd {class}>{state}</dd>
+                    </dl>
+                })
+            }
+        };
+        let cut = !preview.management_cuts.is_empty();
+        let disabled = matches!(self.outcome, Fetch::Loading)
+            || preview.script.is_empty()
+            || (cut && !self.confirm_cut);
+        let label = tr(if cut {
+            "onboarding-apply-cut"
+        } else {
+            "device-apply"
+        });
+        let confirm = cut.then(|| {
+            let onchange = ctx.link().callback(OnboardingMessage::ConfirmCut);
+            html! {
+                <Switch checked={self.confirm_cut} {onchange} label={tr("onboarding-confirm-cut")}/>
+            }
+        });
+        html! {
+            <>
+            {confirm}
+            <Button variant={ButtonVariant::Primary} {disabled} onclick={ctx.link().callback(|_| OnboardingMessage::Apply)}>{label}</Button>
+            <div aria-live="polite">{outcome}</div>
+            {links}
+            </>
+        }
+    }
+    /// copy and download of the previewed script
+    fn script_actions(&self, ctx: &Context<Self>) -> Option<Html> {
+        let (Some(device), Fetch::Done(Some(preview)), Some(download)) =
+            (&self.device, &self.preview, &self.download)
+        else {
+            return None;
+        };
+        let filename = format!("{}.rsc", device.name);
+        Some(html! {
+            <div class="config-actions">
+                <Button variant={ButtonVariant::Secondary} disabled={preview.script.is_empty()} onclick={ctx.link().callback(|_| OnboardingMessage::Copy)}>{tr("config-copy")}</Button>
+                <a class="pf-v5-c-button pf-m-secondary" href={download.to_string()} download={filename}>{tr("config-download")}</a>
+            </div>
+        })
+    }
+}
+
+/// credential variables of the queries, adhoc credentials as username and password
+fn credential_variables(
+    credentials: SelectedCredentials,
+) -> (Option<String>, Option<(Option<String>, Option<String>)>) {
+    match credentials {
+        SelectedCredentials::Default => (None, None),
+        SelectedCredentials::Named(name) => (Some(name.into_string()), None),
+        SelectedCredentials::Adhoc { username, password } => (
+            None,
+            Some((Some(username.into_string()), Some(password.into_string()))),
+        ),
+    }
+}
+
+fn fetch_devices(scope: Scope<Onboarding>) {
+    spawn_local(async move {
+        match query_authenticated::<OnboardingDevices, _>(
+            scope.clone(),
+            onboarding_devices::Variables {
+                instance: SelectedInstance::of(&scope),
+            },
+        )
+        .await
+        {
+            Ok(data) => {
+                let mut devices = data
+                    .topology
+                    .all_devices
+                    .into_iter()
+                    .map(|device| OnboardingDevice {
+                        id: device.id as u32,
+                        name: device.name.into_boxed_str(),
+                        planned: matches!(device.status, onboarding_devices::DeviceStatus::PLANNED),
+                        serial: device.serial.map(String::into_boxed_str),
+                        address: device
+                            .management_address
+                            .and_then(|ip| ip.address)
+                            .and_then(|a| IpAddr::from_str(a.ip.as_str()).ok()),
+                    })
+                    .collect::<Box<[_]>>();
+                devices.sort_by(|a, b| a.name.cmp(&b.name));
+                scope.send_message(OnboardingMessage::Devices(devices));
+            }
+            Err(error) => scope.send_message(OnboardingMessage::DevicesFailed(error)),
+        }
+    });
+}
+
+fn fetch_plan(scope: Scope<Onboarding>, generation: u32, id: i64, target: SelectedTarget) {
+    spawn_local(async move {
+        let (credential_name, adhoc_credentials) = credential_variables(target.credentials);
+        match query_authenticated_response::<OnboardingPreview, _>(
+            scope.clone(),
+            onboarding_preview::Variables {
+                instance: SelectedInstance::of(&scope),
+                id,
+                target: target.address.map(|a| a.to_string()),
+                credential_name,
+                adhoc_credentials: adhoc_credentials.map(|(username, password)| {
+                    onboarding_preview::AdhocCredentials { username, password }
+                }),
+            },
+        )
+        .await
+        {
+            Ok(response) => {
+                let access = response
+                    .data
+                    .and_then(|data| data.topology.device_by_id)
+                    .and_then(|device| device.access);
+                let msg = match (access, response.errors.filter(|e| !e.is_empty())) {
+                    (Some(access), _) => {
+                        let plan = access.plan;
+                        OnboardingMessage::Previewed(
+                            generation,
+                            Some(PreviewData {
+                                script: plan.script.into_boxed_str(),
+                                management_cuts: plan
+                                    .management_cuts
+                                    .into_iter()
+                                    .map(String::into_boxed_str)
+                                    .collect(),
+                                warnings: plan
+                                    .warnings
+                                    .into_iter()
+                                    .chain(
+                                        plan.generation_warnings
+                                            .into_iter()
+                                            .map(|warning| warning.message),
+                                    )
+                                    .map(String::into_boxed_str)
+                                    .collect(),
+                            }),
+                        )
+                    }
+                    (None, Some(errors)) => {
+                        OnboardingMessage::PreviewFailed(generation, FrontendError::Graphql(errors))
+                    }
+                    (None, None) => OnboardingMessage::Previewed(generation, None),
+                };
+                scope.send_message(msg);
+            }
+            Err(error) => scope.send_message(OnboardingMessage::PreviewFailed(generation, error)),
+        }
+    });
+}
+
+/// script for the reset device of the model, the device is not contacted
+fn fetch_bootstrap_script(scope: Scope<Onboarding>, generation: u32, id: i64, model: String) {
+    spawn_local(async move {
+        match query_authenticated::<OnboardingBootstrapScript, _>(
+            scope.clone(),
+            onboarding_bootstrap_script::Variables {
+                instance: SelectedInstance::of(&scope),
+                id,
+                model,
+            },
+        )
+        .await
+        {
+            Ok(data) => scope.send_message(OnboardingMessage::Previewed(
+                generation,
+                Some(PreviewData {
+                    script: data.topology.render_config_for_model.into_boxed_str(),
+                    management_cuts: Box::default(),
+                    warnings: Box::default(),
+                }),
+            )),
+            Err(error) => scope.send_message(OnboardingMessage::PreviewFailed(generation, error)),
+        }
+    });
+}
@@ -1,13 +1,27 @@
+use crate::components::instance_selector::InstanceSelector;
+use crate::data::Preferences;
+use crate::i18n::{Language, tr};
 use crate::pages::devices::Devices;
+use crate::pages::devices::config::DeviceConfigView;
 use crate::pages::devices::show::ShowDevice;
-use patternfly_yew::prelude::{Nav, NavRouterItem};
-use yew::{Html, function_component, html};
+use crate::pages::onboarding::Onboarding;
+use crate::pages::runs::Runs;
+use crate::pages::runs::show::ShowRun;
+use crate::pages::vlans::VlanMatrix;
+use crate::pages::wlan::WlanGroups;
+use patternfly_yew::prelude::{Nav, NavRouterItem, SimpleSelect, Switch};
+use yew::{Callback, Html, function_component, html, use_context};
 use yew_nested_router::Target;
 
 #[derive(Debug, Clone, PartialEq, Eq, Target)]
 pub enum AppRoute {
     NotFound,
     Devices(RouteDevices),
+    /// guided onboarding of a new device
+    Onboarding,
+    WlanGroups,
+    VlanMatrix,
+    Runs(RouteRuns),
 }
 
 impl Default for AppRoute {
@@ -28,13 +42,31 @@ pub enum RouteDevices {
 #[derive(Clone, Debug, PartialEq, Eq, Target)]
 pub enum DeviceView {
     Show,
+    /// generated configuration to review before applying it
+    Config,
+}
+#[derive(Clone, Debug, PartialEq, Eq, Target)]
+pub enum RouteRuns {
+    #[target(index)]
+    List,
+    /// runs of a single device
+    Device {
+        id: u32,
+    },
+    Run {
+        id: u32,
+    },
 }
 
 impl AppRoute {
     pub fn content(self) -> Html {
         match self {
             AppRoute::Devices(d) => d.content(),
-            AppRoute::NotFound => html! {<h1>{"Not Found"}</h1>},
+            AppRoute::Onboarding => html! {<Onboarding/>},
+            AppRoute::WlanGroups => html! {<WlanGroups/>},
+            AppRoute::VlanMatrix => html! {<VlanMatrix/>},
+            AppRoute::Runs(r) => r.content(),
+            AppRoute::NotFound => html! {<h1>{tr("app-not-found")}</h1>},
         }
     }
 }
@@ -47,19 +79,57 @@ impl RouteDevices {
         }
     }
 }
+impl RouteRuns {
+    pub fn content(self) -> Html {
+        match self {
+            RouteRuns::List => html! {<Runs/>},
+            RouteRuns::Device { id } => html! {<Runs device={Some(id)}/>},
+            RouteRuns::Run { id } => html! {<ShowRun {id}/>},
+        }
+    }
+}
 impl DeviceView {
     pub fn content(self, id: u32) -> Html {
         match self {
             Self::Show => html! {<ShowDevice {id}/>},
+            Self::Config => html! {<DeviceConfigView {id}/>},
         }
     }
 }
 
 #[function_component(Sidebar)]
 pub fn sidebar() -> Html {
+    let preferences = use_context::<Preferences>().unwrap_or_default();
+    let checked = preferences.values.dark_mode;
+    let selected = preferences.values.language();
+    let onselect = {
+        let preferences = preferences.clone();
+        Callback::from(move |language| {
+            preferences.change(|values| values.language = Some(language))
+        })
+    };
+    let high_contrast = preferences.values.high_contrast;
+    let on_high_contrast = {
+        let preferences = preferences.clone();
+        Callback::from(move |high_contrast| {
+            preferences.change(|values| values.high_contrast = high_contrast)
+        })
+    };
+    let onchange =
+        Callback::from(move |dark_mode| preferences.change(|values| values.dark_mode = dark_mode));
     html! {
-        <Nav>
-            <NavRouterItem<AppRoute> to={AppRoute::Devices(RouteDevices::List)}>{"Devices"}</NavRouterItem<AppRoute>>
-        </Nav>
+        <>
+            <InstanceSelector/>
+            <Nav>
+                <NavRouterItem<AppRoute> to={AppRoute::Devices(RouteDevices::List)}>{tr("nav-devices")}</NavRouterItem<AppRoute>>
+                <NavRouterItem<AppRoute> to={AppRoute::Onboarding}>{tr("nav-onboarding")}</NavRouterItem<AppRoute>>
+                <NavRouterItem<AppRoute> to={AppRoute::WlanGroups}>{tr("nav-wireless")}</NavRouterItem<AppRoute>>
+                <NavRouterItem<AppRoute> to={AppRoute::VlanMatrix}>{tr("nav-vlans")}</NavRouterItem<AppRoute>>
+                <NavRouterItem<AppRoute> to={AppRoute::Runs(RouteRuns::List)}>{tr("nav-runs")}</NavRouterItem<AppRoute>>
+            </Nav>
+            <Switch {checked} {onchange} label={tr("settings-dark-mode")}/>
+            <Switch checked={high_contrast} onchange={on_high_contrast} label={tr("settings-high-contrast")}/>
+            <SimpleSelect<Language> {selected} entries={Language::ALL.to_vec()} {onselect}/>
+        </>
     }
 }
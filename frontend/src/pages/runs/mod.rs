@@ -0,0 +1,253 @@
+use crate::{
+    data::{Preferences, SelectedInstance},
+    error::FrontendError,
+    graphql::{
+        authenticated::{ListRuns, list_runs},
+        query_authenticated,
+    },
+    i18n::{tr, tr_args},
+    pages::routes::{AppRoute, DeviceView, RouteDevices, RouteRuns},
+};
+use patternfly_yew::prelude::{Card, CardBody, CardHeader, CardTitle, SimpleSelect, Spinner};
+use std::fmt::Display;
+use yew::{Component, Context, Html, Properties, html, platform::spawn_local};
+use yew_nested_router::components::Link;
+
+pub mod show;
+
+pub struct Runs {
+    state: RunState,
+    selected_status: StatusEntry,
+    error_state: Option<FrontendError>,
+}
+enum RunState {
+    Loading,
+    Data(Box<[RunRow]>),
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunRow {
+    id: u32,
+    device_id: u32,
+    device_name: Box<str>,
+    user: Option<Box<str>>,
+    started_at: u64,
+    failed: bool,
+    applied_steps: usize,
+    error: Option<Box<str>>,
+}
+/// entry of the status filter
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StatusEntry {
+    #[default]
+    All,
+    Succeeded,
+    Failed,
+}
+impl StatusEntry {
+    /// name of the entry in the stored preferences
+    fn key(self) -> Option<&'static str> {
+        match self {
+            StatusEntry::All => None,
+            StatusEntry::Succeeded => Some("succeeded"),
+            StatusEntry::Failed => Some("failed"),
+        }
+    }
+    fn from_key(key: Option<&str>) -> Self {
+        [StatusEntry::Succeeded, StatusEntry::Failed]
+            .into_iter()
+            .find(|entry| entry.key() == key)
+            .unwrap_or_default()
+    }
+    fn status(self) -> Option<list_runs::RunStatus> {
+        match self {
+            StatusEntry::All => None,
+            StatusEntry::Succeeded => Some(list_runs::RunStatus::SUCCEEDED),
+            StatusEntry::Failed => Some(list_runs::RunStatus::FAILED),
+        }
+    }
+}
+impl Display for StatusEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&tr(match self {
+            StatusEntry::All => "runs-all",
+            StatusEntry::Succeeded => "runs-succeeded",
+            StatusEntry::Failed => "runs-failed",
+        }))
+    }
+}
+#[derive(Debug, Properties, Clone, PartialEq)]
+pub struct RunsProps {
+    /// only the runs of this device
+    #[prop_or_default]
+    pub device: Option<u32>,
+}
+#[derive(Debug)]
+pub enum RunsMsg {
+    Data(Box<[RunRow]>),
+    SelectStatus(StatusEntry),
+    Error(FrontendError),
+}
+
+impl Component for Runs {
+    type Message = RunsMsg;
+    type Properties = RunsProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let stored = Preferences::of(ctx.link()).values.run_status;
+        Self {
+            state: RunState::Loading,
+            selected_status: StatusEntry::from_key(stored.as_deref()),
+            error_state: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            RunsMsg::Data(runs) => {
+                self.state = RunState::Data(runs);
+                self.error_state = None;
+                true
+            }
+            RunsMsg::SelectStatus(status) => {
+                if status == self.selected_status {
+                    return false;
+                }
+                Preferences::of(ctx.link())
+                    .change(|values| values.run_status = status.key().map(Box::from));
+                self.selected_status = status;
+                self.state = RunState::Loading;
+                self.load(ctx);
+                true
+            }
+            RunsMsg::Error(error) => {
+                self.error_state = Some(error);
+                true
+            }
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.state = RunState::Loading;
+        self.load(ctx);
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let error_msg = self.error_state.as_ref().map(|e| FrontendError::to_html(e));
+        let data = match &self.state {
+            RunState::Loading => html! {<Spinner/>},
+            RunState::Data(rows) if rows.is_empty() => html! {<p>{tr("runs-none")}</p>},
+            RunState::Data(rows) => rows.iter().map(run_card).collect::<Html>(),
+        };
+        let entries = vec![
+            StatusEntry::All,
+            StatusEntry::Succeeded,
+            StatusEntry::Failed,
+        ];
+        let selected = self.selected_status;
+        let onselect = ctx.link().callback(RunsMsg::SelectStatus);
+        html! {
+            <>
+                <div class="run-status-filter">
+                    <SimpleSelect<StatusEntry> {selected} {entries} {onselect}/>
+                </div>
+                <div class="run-list">
+                    {error_msg}
+                    {data}
+                </div>
+            </>
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            self.load(ctx);
+        }
+    }
+}
+
+impl Runs {
+    fn load(&self, ctx: &Context<Self>) {
+        let scope = ctx.link().clone();
+        let device_id = ctx.props().device.map(i64::from);
+        let status = self.selected_status.status();
+        spawn_local(async move {
+            match query_authenticated::<ListRuns, _>(
+                scope.clone(),
+                list_runs::Variables {
+                    instance: SelectedInstance::of(&scope),
+                    device_id,
+                    status,
+                },
+            )
+            .await
+            {
+                Ok(data) => scope.send_message(RunsMsg::Data(
+                    data.provisioning_runs
+                        .into_iter()
+                        .map(|run| RunRow {
+                            id: run.id as u32,
+                            device_id: run.device_id as u32,
+                            device_name: run.device_name.into_boxed_str(),
+                            user: run.user.map(String::into_boxed_str),
+                            started_at: run.started_at as u64,
+                            failed: run.status == list_runs::RunStatus::FAILED,
+                            applied_steps: run.applied_steps.len(),
+                            error: run.error.map(String::into_boxed_str),
+                        })
+                        .collect(),
+                )),
+                Err(e) => scope.send_message(RunsMsg::Error(e)),
+            }
+        });
+    }
+}
+
+/// time since the given unix timestamp in seconds, like `5 min ago`
+pub fn format_age(timestamp: u64) -> String {
+    let now = wasm_timer::SystemTime::now()
+        .duration_since(wasm_timer::SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let age = now.saturating_sub(timestamp);
+    match age {
+        0..60 => tr("time-just-now"),
+        60..3600 => tr_args("time-minutes-ago", &[("count", (age / 60).into())]),
+        3600..86400 => tr_args("time-hours-ago", &[("count", (age / 3600).into())]),
+        _ => tr_args("time-days-ago", &[("count", (age / 86400).into())]),
+    }
+}
+
+pub fn device_link(id: u32, name: &str) -> Html {
+    let to = AppRoute::Devices(RouteDevices::Device {
+        id,
+        view: DeviceView::Show,
+    });
+    html! {<Link<AppRoute> {to}>{name}</Link<AppRoute>>}
+}
+
+fn run_card(run: &RunRow) -> Html {
+    let to = AppRoute::Runs(RouteRuns::Run { id: run.id });
+    let status = tr(if run.failed {
+        "runs-failed"
+    } else {
+        "runs-succeeded"
+    });
+    html! {
+        <Card key={run.id}>
+            <CardHeader><CardTitle>
+                <Link<AppRoute> {to}>{format!("#{} {}", run.id, run.device_name)}</Link<AppRoute>>
+            </CardTitle></CardHeader>
+            <CardBody>
+                <dl>
+                    <dt>{tr("run-device")}</dt><dd>{device_link(run.device_id, &run.device_name)}</dd>
+                    <dt>{tr("run-status")}</dt><dd class={if run.failed {"run-failed"} else {"run-succeeded"}}>{status}</dd>
+                    <dt>{tr("run-started")}</dt><dd>{format_age(run.started_at)}</dd>
+                    <dt>{tr("run-user")}</dt><dd>{run.user.as_deref().unwrap_or_default()}</dd>
+                    <dt>{tr("run-applied-steps")}</dt><dd>{run.applied_steps}</dd>
+                    {run.error.as_deref().map(|error| html!{<><dt>{tr("run-error")}</dt><dd>{error}</dd></>})}
+                </dl>
+            </CardBody>
+        </Card>
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    components::code_region::CodeRegion,
+    error::FrontendError,
+    graphql::{
+        authenticated::{RunDetails, run_details},
+        query_authenticated,
+    },
+    i18n::{tr, tr_args},
+    pages::runs::{device_link, format_age},
+};
+use patternfly_yew::prelude::{ExpandableSection, Spinner};
+use yew::{Component, Context, Html, Properties, html, platform::spawn_local};
+
+pub struct ShowRun {
+    state: RunDetailState,
+    error: Option<FrontendError>,
+}
+enum RunDetailState {
+    Loading,
+    NotFound,
+    Data(RunDetailData),
+}
+#[derive(Debug, PartialEq)]
+pub struct RunDetailData {
+    id: u32,
+    instance: Box<str>,
+    device_id: u32,
+    device_name: Box<str>,
+    user: Option<Box<str>>,
+    started_at: u64,
+    finished_at: u64,
+    failed: bool,
+    applied_steps: Box<[StepRow]>,
+    pending_steps: Box<[StepRow]>,
+    error: Option<Box<str>>,
+}
+#[derive(Debug, PartialEq)]
+struct StepRow {
+    position: u32,
+    category: Box<str>,
+    script: Box<str>,
+}
+#[derive(Debug, Properties, Clone, PartialEq)]
+pub struct ShowRunProps {
+    pub id: u32,
+}
+#[derive(Debug)]
+pub enum ShowRunMessage {
+    Data(Option<RunDetailData>),
+    Error(FrontendError),
+}
+
+impl Component for ShowRun {
+    type Message = ShowRunMessage;
+    type Properties = ShowRunProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            state: RunDetailState::Loading,
+            error: None,
+        }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            ShowRunMessage::Data(data) => {
+                self.state = data
+                    .map(RunDetailState::Data)
+                    .unwrap_or(RunDetailState::NotFound);
+                self.error = None;
+            }
+            ShowRunMessage::Error(error) => self.error = Some(error),
+        }
+        true
+    }
+
+    fn view(&self, _ctx: &Context<Self>) -> Html {
+        let error = self.error.as_ref().map(|e| e.to_html());
+        let data = match &self.state {
+            RunDetailState::Loading => html! {<Spinner/>},
+            RunDetailState::NotFound => {
+                html! {<p>{tr("run-not-found")}</p>}
+            }
+            RunDetailState::Data(run) => {
+                let pending = (!run.pending_steps.is_empty()).then(|| {
+                    html! {
+                        <ExpandableSection toggle_text_hidden={tr("run-show-pending")} toggle_text_expanded={tr("run-hide-pending")}>
+                            {steps(&run.pending_steps)}
+                        </ExpandableSection>
+                    }
+                });
+                html! {
+                    <>
+                    <h1>{tr_args("run-title", &[("id", run.id.into())])}</h1>
+                    <dl>
+                        <dt>{tr("run-device")}</dt><dd>{device_link(run.device_id, &run.device_name)}</dd>
+                        <dt>{tr("run-instance")}</dt><dd>{run.instance.as_ref()}</dd>
+                        <dt>{tr("run-status")}</dt><dd class={if run.failed {"run-failed"} else {"run-succeeded"}}>{tr(if run.failed {"runs-failed"} else {"runs-succeeded"})}</dd>
+                        <dt>{tr("run-user")}</dt><dd>{run.user.as_deref().unwrap_or_default()}</dd>
+                        <dt>{tr("run-started")}</dt><dd>{format_age(run.started_at)}</dd>
+                        <dt>{tr("run-duration")}</dt><dd>{tr_args("run-seconds", &[("seconds", run.finished_at.saturating_sub(run.started_at).into())])}</dd>
+                        {run.error.as_deref().map(|error| html!{<><dt>{tr("run-error")}</dt><dd>{error}</dd></>})}
+                        <dt>{tr("run-applied-steps")}</dt><dd>{steps(&run.applied_steps)}</dd>
+                    </dl>
+                    {pending}
+                    </>
+                }
+            }
+        };
+        html! {
+            <>
+            {error}
+            {data}
+            </>
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            let scope = ctx.link().clone();
+            let id = ctx.props().id as i64;
+            spawn_local(async move {
+                match query_authenticated::<RunDetails, _>(
+                    scope.clone(),
+                    run_details::Variables { id },
+                )
+                .await
+                {
+                    Ok(data) => {
+                        scope.send_message(ShowRunMessage::Data(data.provisioning_run.map(|run| {
+                            RunDetailData {
+                                id: run.id as u32,
+                                instance: run.instance.into_boxed_str(),
+                                device_id: run.device_id as u32,
+                                device_name: run.device_name.into_boxed_str(),
+                                user: run.user.map(String::into_boxed_str),
+                                started_at: run.started_at as u64,
+                                finished_at: run.finished_at as u64,
+                                failed: run.status == run_details::RunStatus::FAILED,
+                                applied_steps: run
+                                    .applied_steps
+                                    .into_iter()
+                                    .map(|step| StepRow {
+                                        position: step.position as u32,
+                                        category: step.category.into_boxed_str(),
+                                        script: step.script.into_boxed_str(),
+                                    })
+                                    .collect(),
+                                pending_steps: run
+                                    .pending_steps
+                                    .into_iter()
+                                    .map(|step| StepRow {
+                                        position: step.position as u32,
+                                        category: step.category.into_boxed_str(),
+                                        script: step.script.into_boxed_str(),
+                                    })
+                                    .collect(),
+                                error: run.error.map(String::into_boxed_str),
+                            }
+                        })))
+                    }
+                    Err(e) => scope.send_message(ShowRunMessage::Error(e)),
+                }
+            });
+        }
+    }
+}
+
+fn steps(steps: &[StepRow]) -> Html {
+    if steps.is_empty() {
+        return html! {{tr("run-no-steps")}};
+    }
+    steps
+        .iter()
+        .map(|step| {
+            let title = format!("{}. {}", step.position + 1, step.category);
+            html! {
+                <div key={step.position} class="run-step">
+                    <div class="run-step-category">{title.clone()}</div>
+                    <CodeRegion label={title} code={step.script.to_string()}/>
+                </div>
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,158 @@
+use crate::{
+    error::FrontendError,
+    graphql::{
+        anonymous::{PublicStatus, public_status},
+        query_anonymous_cached,
+    },
+    i18n::{tr, tr_args},
+    pages::runs::format_age,
+};
+use gloo::timers::callback::Interval;
+use patternfly_yew::prelude::{Spinner, SpinnerSize};
+use yew::{Component, Context, Html, html, platform::spawn_local};
+
+/// path of the status page, shown without login
+pub const STATUS_PATH: &str = "/status";
+/// the status is reloaded in this interval, the server lets it be cached as long
+const STATUS_POLL_INTERVAL_MS: u32 = 30_000;
+
+/// the browser shows the public status page
+pub fn is_status_page() -> bool {
+    gloo::utils::window()
+        .location()
+        .pathname()
+        .is_ok_and(|path| path.trim_end_matches('/') == STATUS_PATH)
+}
+
+/// Aggregate health of the provisioner for wall displays, without login and without any names
+/// of the topology
+pub struct StatusPage {
+    data: Option<public_status::ResponseData>,
+    error: Option<FrontendError>,
+    /// reloads the status, stopped when dropped
+    _poll: Interval,
+}
+
+#[derive(Debug)]
+pub enum StatusPageMsg {
+    Load,
+    Data(public_status::ResponseData),
+    Error(FrontendError),
+}
+
+impl Component for StatusPage {
+    type Message = StatusPageMsg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(StatusPageMsg::Load);
+        let scope = ctx.link().clone();
+        Self {
+            data: None,
+            error: None,
+            _poll: Interval::new(STATUS_POLL_INTERVAL_MS, move || {
+                scope.send_message(StatusPageMsg::Load)
+            }),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            StatusPageMsg::Load => {
+                let scope = ctx.link().clone();
+                spawn_local(async move {
+                    match query_anonymous_cached::<PublicStatus>(public_status::Variables {}).await
+                    {
+                        Ok(data) => scope.send_message(StatusPageMsg::Data(data)),
+                        Err(error) => scope.send_message(StatusPageMsg::Error(error)),
+                    }
+                });
+                false
+            }
+            StatusPageMsg::Data(data) => {
+                self.data = Some(data);
+                self.error = None;
+                true
+            }
+            // the last known status stays visible
+            StatusPageMsg::Error(error) => {
+                self.error = Some(error);
+                true
+            }
+        }
+    }
+
+    fn view(&self, _ctx: &Context<Self>) -> Html {
+        let error = self.error.as_ref().map(|error| error.to_html());
+        let Some(data) = &self.data else {
+            return html! {
+                <div class="status-page">
+                    {error.unwrap_or_else(|| html! {<Spinner size={SpinnerSize::Xl}/>})}
+                </div>
+            };
+        };
+        let status = &data.status;
+        let (state, class) = match status.state {
+            public_status::ServiceState::OPERATIONAL => {
+                ("status-operational", "status-operational")
+            }
+            public_status::ServiceState::DEGRADED => ("status-degraded", "status-degraded"),
+            public_status::ServiceState::MAINTENANCE => {
+                ("status-maintenance", "status-maintenance")
+            }
+            public_status::ServiceState::Other(_) => ("status-unknown", "status-degraded"),
+        };
+        let maintenance = status.maintenance.iter().map(|window| {
+            html! {
+                <li>
+                    <strong>{&window.title}</strong>
+                    {tr_args("status-maintenance-period", &[
+                        ("since", format_age(window.starts_at as u64).into()),
+                        ("ends", format_until(window.ends_at as u64).into()),
+                    ])}
+                </li>
+            }
+        });
+        html! {
+            <div class="status-page">
+                <h1 class={class}>{tr(state)}</h1>
+                <dl class="status-counts">
+                    <div><dt>{tr("status-devices-up")}</dt><dd>{status.devices_up}</dd></div>
+                    <div><dt>{tr("status-devices-down")}</dt><dd>{status.devices_down}</dd></div>
+                    <div><dt>{tr("status-unreachable")}</dt><dd>{status.unreachable_devices}</dd></div>
+                    <div><dt>{tr("status-drifted")}</dt><dd>{status.drifted_devices}</dd></div>
+                </dl>
+                if !status.maintenance.is_empty() {
+                    <h2>{tr("status-ongoing-maintenance")}</h2>
+                    <ul>{for maintenance}</ul>
+                }
+                <p class="status-sync">
+                    {tr_args("status-last-sync", &[(
+                        "age",
+                        status.last_sync.map(|sync| format_age(sync as u64)).unwrap_or_else(|| tr("status-never")).into(),
+                    )])}
+                    if status.failing_instances > 0 {
+                        {tr_args("status-failing-instances", &[("count", status.failing_instances.into())])}
+                    }
+                </p>
+                {error}
+                <p class="status-version">{format!("netbox-provisioner {}", data.server_version)}</p>
+            </div>
+        }
+    }
+}
+
+/// time until the given unix timestamp in seconds, like `in 5 min`
+fn format_until(timestamp: u64) -> String {
+    let now = wasm_timer::SystemTime::now()
+        .duration_since(wasm_timer::SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let remaining = timestamp.saturating_sub(now);
+    match remaining {
+        0..60 => tr("time-now"),
+        60..3600 => tr_args("time-in-minutes", &[("count", (remaining / 60).into())]),
+        3600..86400 => tr_args("time-in-hours", &[("count", (remaining / 3600).into())]),
+        _ => tr_args("time-in-days", &[("count", (remaining / 86400).into())]),
+    }
+}
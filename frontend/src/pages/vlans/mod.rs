@@ -0,0 +1,251 @@
+use crate::{
+    components::keyboard::list_keydown,
+    data::SelectedInstance,
+    error::FrontendError,
+    graphql::{
+        authenticated::{
+            ListVlanMatrix,
+            list_vlan_matrix::{self, VlanMembership},
+        },
+        query_authenticated,
+    },
+    i18n::tr,
+    pages::{
+        devices::SiteEntry,
+        routes::{AppRoute, DeviceView, RouteDevices},
+    },
+};
+use patternfly_yew::prelude::{SimpleSelect, Spinner};
+use yew::{Component, Context, Html, NodeRef, html, platform::spawn_local};
+use yew_nested_router::components::Link;
+
+/// Matrix of the ports against the vlans they carry
+pub struct VlanMatrix {
+    state: MatrixState,
+    sites: Box<[SiteEntry]>,
+    selected_site: SiteEntry,
+    error_state: Option<FrontendError>,
+    /// the rows are navigated by the arrow keys
+    table: NodeRef,
+}
+enum MatrixState {
+    Loading,
+    Data(MatrixData),
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatrixData {
+    /// column titles
+    vlans: Box<[Box<str>]>,
+    rows: Box<[MatrixRow]>,
+}
+#[derive(Debug, Clone, PartialEq)]
+struct MatrixRow {
+    device_id: u32,
+    device_name: Box<str>,
+    interface: Box<str>,
+    cells: Box<[Membership]>,
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Membership {
+    None,
+    Untagged,
+    Tagged,
+}
+#[derive(Debug)]
+pub enum VlanMatrixMsg {
+    Data {
+        sites: Box<[SiteEntry]>,
+        matrix: MatrixData,
+    },
+    SelectSite(SiteEntry),
+    Error(FrontendError),
+}
+
+impl Component for VlanMatrix {
+    type Message = VlanMatrixMsg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            state: MatrixState::Loading,
+            sites: Box::default(),
+            selected_site: SiteEntry::stored(ctx.link()),
+            error_state: None,
+            table: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            VlanMatrixMsg::Data { sites, matrix } => {
+                self.selected_site = self.selected_site.resolve(&sites);
+                self.sites = sites;
+                self.state = MatrixState::Data(matrix);
+                self.error_state = None;
+                true
+            }
+            VlanMatrixMsg::SelectSite(site) => {
+                if site == self.selected_site {
+                    return false;
+                }
+                site.store(ctx.link());
+                self.selected_site = site;
+                self.state = MatrixState::Loading;
+                self.load(ctx);
+                true
+            }
+            VlanMatrixMsg::Error(error) => {
+                self.error_state = Some(error);
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let error_msg = self.error_state.as_ref().map(|e| FrontendError::to_html(e));
+        let data = match &self.state {
+            MatrixState::Loading => html! {<Spinner/>},
+            MatrixState::Data(matrix) if matrix.rows.is_empty() => {
+                html! {<p>{tr("vlans-none")}</p>}
+            }
+            MatrixState::Data(matrix) => matrix_table(matrix, self.table.clone()),
+        };
+        let site_filter = (self.sites.len() > 1).then(|| {
+            let entries = std::iter::once(SiteEntry::All)
+                .chain(self.sites.iter().cloned())
+                .collect::<Vec<_>>();
+            let selected = self.selected_site.clone();
+            let onselect = ctx.link().callback(VlanMatrixMsg::SelectSite);
+            html! {
+                <div class="vlan-site-filter">
+                    <SimpleSelect<SiteEntry> {selected} {entries} {onselect}/>
+                </div>
+            }
+        });
+        html! {
+            <>
+                {site_filter}
+                {error_msg}
+                {data}
+            </>
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            self.load(ctx);
+        }
+    }
+}
+
+impl VlanMatrix {
+    fn load(&self, ctx: &Context<Self>) {
+        let scope = ctx.link().clone();
+        let site = self.selected_site.id().map(i64::from);
+        spawn_local(async move {
+            match query_authenticated::<ListVlanMatrix, _>(
+                scope.clone(),
+                list_vlan_matrix::Variables {
+                    instance: SelectedInstance::of(&scope),
+                    site,
+                },
+            )
+            .await
+            {
+                Ok(data) => {
+                    let mut sites = data
+                        .topology
+                        .all_sites
+                        .into_iter()
+                        .map(|site| SiteEntry::Site {
+                            id: site.id as u32,
+                            name: site.name.unwrap_or_default().into_boxed_str(),
+                        })
+                        .collect::<Box<[_]>>();
+                    sites.sort_by_key(|site| site.to_string());
+                    let matrix = data
+                        .topology
+                        .vlan_matrix
+                        .map(|matrix| MatrixData {
+                            vlans: matrix
+                                .vlans
+                                .into_iter()
+                                .map(|vlan| format!("{} ({})", vlan.name, vlan.vlan_id).into())
+                                .collect(),
+                            rows: matrix
+                                .rows
+                                .into_iter()
+                                .map(|row| MatrixRow {
+                                    device_id: row.device.id as u32,
+                                    device_name: row.device.name.into_boxed_str(),
+                                    interface: row.interface.name.into_boxed_str(),
+                                    cells: row
+                                        .memberships
+                                        .into_iter()
+                                        .map(|membership| match membership {
+                                            Some(VlanMembership::UNTAGGED) => Membership::Untagged,
+                                            Some(VlanMembership::TAGGED) => Membership::Tagged,
+                                            _ => Membership::None,
+                                        })
+                                        .collect(),
+                                })
+                                .collect(),
+                        })
+                        .unwrap_or(MatrixData {
+                            vlans: Box::default(),
+                            rows: Box::default(),
+                        });
+                    scope.send_message(VlanMatrixMsg::Data { sites, matrix });
+                }
+                Err(e) => scope.send_message(VlanMatrixMsg::Error(e)),
+            }
+        });
+    }
+}
+
+fn matrix_table(matrix: &MatrixData, table: NodeRef) -> Html {
+    let header = matrix
+        .vlans
+        .iter()
+        .map(|vlan| html! {<th scope="col">{vlan.as_ref()}</th>})
+        .collect::<Html>();
+    let rows = matrix
+        .rows
+        .iter()
+        .map(|row| {
+            let to = AppRoute::Devices(RouteDevices::Device {
+                id: row.device_id,
+                view: DeviceView::Show,
+            });
+            let cells = row
+                .cells
+                .iter()
+                .map(|cell| match cell {
+                    Membership::Untagged => {
+                        html! {<td class="vlan-untagged"><abbr title={tr("vlans-untagged")}>{"U"}</abbr></td>}
+                    }
+                    Membership::Tagged => {
+                        html! {<td class="vlan-tagged"><abbr title={tr("vlans-tagged")}>{"T"}</abbr></td>}
+                    }
+                    Membership::None => html! {<td/>},
+                })
+                .collect::<Html>();
+            html! {
+                <tr tabindex="0" data-nav-entry="">
+                    <th scope="row"><Link<AppRoute> {to}>{row.device_name.as_ref()}</Link<AppRoute>></th>
+                    <td>{row.interface.as_ref()}</td>
+                    {cells}
+                </tr>
+            }
+        })
+        .collect::<Html>();
+    html! {
+        <table class="vlan-matrix" ref={table.clone()} onkeydown={list_keydown(table)}>
+            <caption class="pf-v5-screen-reader">{tr("vlans-caption")}</caption>
+            <thead>
+                <tr><th scope="col">{tr("vlans-device")}</th><th scope="col">{tr("vlans-port")}</th>{header}</tr>
+            </thead>
+            <tbody>{rows}</tbody>
+        </table>
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{
+    data::SelectedInstance,
+    error::FrontendError,
+    graphql::{
+        authenticated::{
+            ListWlanGroups,
+            list_wlan_groups::{self, ListWlanGroupsTopologyAllWlanGroupsWlanListAuth},
+        },
+        query_authenticated,
+    },
+    i18n::tr,
+    pages::routes::{AppRoute, DeviceView, RouteDevices},
+};
+use patternfly_yew::prelude::{Card, CardBody, CardHeader, CardTitle, Spinner};
+use yew::{Component, Context, Html, ToHtml, html, platform::spawn_local};
+use yew_nested_router::components::Link;
+
+pub struct WlanGroups {
+    state: WlanGroupState,
+    error_state: Option<FrontendError>,
+}
+enum WlanGroupState {
+    Loading,
+    Data(Box<[WlanGroupRow]>),
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct WlanGroupRow {
+    id: u32,
+    mgmt_vlan: Option<Box<str>>,
+    controller: Option<DeviceLink>,
+    aps: Box<[DeviceLink]>,
+    wlans: Box<[WlanRow]>,
+}
+#[derive(Debug, Clone, PartialEq)]
+struct DeviceLink {
+    id: u32,
+    name: Box<str>,
+}
+#[derive(Debug, Clone, PartialEq)]
+struct WlanRow {
+    ssid: Box<str>,
+    /// message id of the authentication
+    auth: Option<&'static str>,
+    vlan: Option<Box<str>>,
+}
+#[derive(Debug)]
+pub enum WlanGroupsMsg {
+    Data(Box<[WlanGroupRow]>),
+    Error(FrontendError),
+}
+
+impl Component for WlanGroups {
+    type Message = WlanGroupsMsg;
+    type Properties = ();
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            state: WlanGroupState::Loading,
+            error_state: None,
+        }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            WlanGroupsMsg::Data(data) => {
+                self.state = WlanGroupState::Data(data);
+                self.error_state = None;
+                true
+            }
+            WlanGroupsMsg::Error(error) => {
+                self.error_state = Some(error);
+                true
+            }
+        }
+    }
+
+    fn view(&self, _ctx: &Context<Self>) -> Html {
+        let error_msg = self.error_state.as_ref().map(|e| FrontendError::to_html(e));
+        let data = match &self.state {
+            WlanGroupState::Loading => {
+                html! {<Spinner/>}
+            }
+            WlanGroupState::Data(rows) => rows.iter().map(wlan_group_card).collect::<Html>(),
+        };
+        html! {
+            <div class="wlan-group-list">
+                {error_msg}
+                {data}
+            </div>
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            let scope = ctx.link().clone();
+            spawn_local(async move {
+                match query_authenticated::<ListWlanGroups, _>(
+                    scope.clone(),
+                    list_wlan_groups::Variables {
+                        instance: SelectedInstance::of(&scope),
+                    },
+                )
+                .await
+                {
+                    Ok(data) => {
+                        scope.send_message(WlanGroupsMsg::Data(
+                            data.topology
+                                .all_wlan_groups
+                                .into_iter()
+                                .map(|group| WlanGroupRow {
+                                    id: group.id as u32,
+                                    mgmt_vlan: group.mgmt_vlan.map(|v| v.name.into_boxed_str()),
+                                    controller: group.controller.map(|d| DeviceLink {
+                                        id: d.id as u32,
+                                        name: d.name.into_boxed_str(),
+                                    }),
+                                    aps: group
+                                        .aps
+                                        .into_iter()
+                                        .map(|d| DeviceLink {
+                                            id: d.id as u32,
+                                            name: d.name.into_boxed_str(),
+                                        })
+                                        .collect(),
+                                    wlans: group
+                                        .wlan_list
+                                        .into_iter()
+                                        .map(|wlan| WlanRow {
+                                            ssid: wlan.ssid.into_boxed_str(),
+                                            auth: auth_message(wlan.auth),
+                                            vlan: wlan.vlan.map(|v| v.name.into_boxed_str()),
+                                        })
+                                        .collect(),
+                                })
+                                .collect(),
+                        ));
+                    }
+                    Err(e) => {
+                        scope.send_message(WlanGroupsMsg::Error(e));
+                    }
+                }
+            })
+        }
+    }
+}
+
+fn auth_message(
+    auth: Option<ListWlanGroupsTopologyAllWlanGroupsWlanListAuth>,
+) -> Option<&'static str> {
+    match auth? {
+        ListWlanGroupsTopologyAllWlanGroupsWlanListAuth::WlanWpaSettings => Some("wlan-auth-wpa"),
+        ListWlanGroupsTopologyAllWlanGroupsWlanListAuth::WlanOpenSettings => Some("wlan-auth-open"),
+    }
+}
+
+fn device_link(device: &DeviceLink) -> Html {
+    let to = AppRoute::Devices(RouteDevices::Device {
+        id: device.id,
+        view: DeviceView::Show,
+    });
+    html! {<Link<AppRoute> {to}>{device.name.as_ref()}</Link<AppRoute>>}
+}
+
+fn wlan_group_card(group: &WlanGroupRow) -> Html {
+    let title = group
+        .wlans
+        .iter()
+        .map(|w| w.ssid.as_ref())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let controller = group.controller.as_ref().map(device_link);
+    let aps = group
+        .aps
+        .iter()
+        .map(|ap| html! {<li>{device_link(ap)}</li>})
+        .collect::<Html>();
+    let wlans = group
+        .wlans
+        .iter()
+        .map(|wlan| {
+            html! {
+                <li>
+                    {wlan.ssid.as_ref()}{" ("}{wlan.auth.map(tr).unwrap_or_default()}{")"}
+                    {wlan.vlan.as_deref().map(|vlan| html!{<>{" VLAN: "}{vlan}</>})}
+                </li>
+            }
+        })
+        .collect::<Html>();
+    html! {
+        <Card key={group.id}>
+            <CardHeader><CardTitle>{title}</CardTitle></CardHeader>
+            <CardBody>
+                <dl>
+                    <dt>{tr("wlan-controller")}</dt><dd>{controller}</dd>
+                    <dt>{tr("wlan-management-vlan")}</dt><dd>{group.mgmt_vlan.as_deref().unwrap_or_default()}</dd>
+                    <dt>{tr("wlan-ssids")}</dt><dd><ul>{wlans}</ul></dd>
+                    <dt>{tr("wlan-access-points")}</dt><dd><ul>{aps}</ul></dd>
+                </dl>
+            </CardBody>
+        </Card>
+    }
+}